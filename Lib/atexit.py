@@ -0,0 +1,47 @@
+"""Register functions to be called when the interpreter exits.
+
+The registered functions are called in the reverse order of their
+registration, by the interpreter once the main module finished.
+"""
+
+_exithandlers = []
+
+
+def register(func, *args, **kwargs):
+    """Register a function to be executed upon normal program termination.
+
+    func is returned to facilitate usage as a decorator.
+    """
+    _exithandlers.append((func, args, kwargs))
+    return func
+
+
+def unregister(func):
+    """Unregister an exit function which was previously registered using
+    atexit.register.
+    """
+    _exithandlers[:] = [h for h in _exithandlers if h[0] != func]
+
+
+def _clear():
+    """Clear the list of previously registered exit functions."""
+    del _exithandlers[:]
+
+
+def _ncallbacks():
+    """Return the number of registered exit functions."""
+    return len(_exithandlers)
+
+
+def _run_exitfuncs():
+    """Run all registered exit functions."""
+    import sys
+    while _exithandlers:
+        func, args, kwargs = _exithandlers.pop()
+        try:
+            func(*args, **kwargs)
+        except SystemExit:
+            pass
+        except BaseException as e:
+            print("Error in atexit._run_exitfuncs:", file=sys.stderr)
+            print("%s: %s" % (type(e).__name__, e), file=sys.stderr)
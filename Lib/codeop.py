@@ -16,23 +16,12 @@ First, check if the source consists entirely of blank lines and
 comments; if so, replace it with 'pass', because the built-in
 parser doesn't always do the right thing for these.
 
-Compile three times: as is, with \n, and with \n\n appended.  If it
-compiles as is, it's complete.  If it compiles with one \n appended,
-we expect more.  If it doesn't compile either way, we compare the
-error we get when compiling with \n or \n\n appended.  If the errors
-are the same, the code is broken.  But if the errors are different, we
-expect more.  Not intuitive; not even guaranteed to hold in future
-releases; but this matches the compiler's behavior from Python 1.4
-through 2.2, at least.
-
-Caveat:
-
-It is possible (but not likely) that the parser stops parsing with a
-successful outcome before reaching the end of the source; in this
-case, trailing symbols may be ignored instead of causing an error.
-For example, a backslash followed by two newlines may be followed by
-arbitrary garbage.  This will be fixed once the API for the parser is
-better.
+Then compile it with the PyCF_DONT_IMPLY_DEDENT flag.  Under that flag
+the compiler asks the parser whether the input is complete, and raises
+_IncompleteInputError (a SyntaxError subclass) when more lines are
+needed, for example inside brackets, inside a triple-quoted string or
+in a compound statement that was not yet ended by a blank line.  Any
+other SyntaxError means the code is broken.
 
 The two interfaces are:
 
@@ -75,28 +64,10 @@ def _maybe_compile(compiler, source, filename, symbol):
         if symbol != "eval":
             source = "pass"     # Replace it with a 'pass' statement
 
-    err = err1 = err2 = None
-    code = code1 = code2 = None
-
-    try:
-        code = compiler(source, filename, symbol)
-    except SyntaxError as err:
-        pass
-
-    try:
-        code1 = compiler(source + "\n", filename, symbol)
-    except SyntaxError as e:
-        err1 = e
-
     try:
-        code2 = compiler(source + "\n\n", filename, symbol)
-    except SyntaxError as e:
-        err2 = e
-
-    if code:
-        return code
-    if not code1 and repr(err1) == repr(err2):
-        raise err1
+        return compiler(source, filename, symbol)
+    except _IncompleteInputError:
+        return None
 
 def _compile(source, filename, symbol):
     return compile(source, filename, symbol, PyCF_DONT_IMPLY_DEDENT)
@@ -0,0 +1,1139 @@
+//! Assemble the textual listing that code objects display as back into a
+//! code object, for writing bytecode by hand.
+//!
+//! A listing looks like this:
+//!
+//! ```text
+//! code "<module>"
+//!     .source_path "example.py"
+//!     .first_line 1
+//!     .flags 0x0
+//!     .max_stackdepth 2
+//!        1:1     0 LoadConst 1
+//!        1:1     1 JumpIfFalse L0
+//!        2:5     2 LoadName "print" Local
+//!        2:5     3 LoadConst "yes"
+//!        2:5     4 CallFunction Positional 1
+//!        2:5     5 Pop
+//!     L0:
+//!        2:5     6 LoadConst None
+//!        2:5     7 ReturnValue
+//! end
+//! ```
+//!
+//! Whitespace only separates tokens and `#` starts a comment. The source
+//! location and the offset in front of an instruction may be left out; a
+//! given offset must match the position of the instruction.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use num_bigint::BigInt;
+use num_complex::Complex64;
+
+use crate::bytecode::{
+    BinaryOperator, CallType, CodeFlags, CodeObject, ComparisonOperator, Constant, ConversionFlag,
+    FunctionOpArg, Instruction, Label, Location, NameScope, UnaryOperator, Varargs,
+};
+
+#[derive(Debug, PartialEq)]
+pub struct AsmError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for AsmError {}
+
+type AsmResult<T> = Result<T, AsmError>;
+
+/// Parse a listing into a code object.
+pub fn assemble(source: &str) -> AsmResult<CodeObject> {
+    let mut parser = Parser {
+        tokens: tokenize(source)?,
+        position: 0,
+        last_line: source.lines().count().max(1),
+    };
+    let code = parser.code()?;
+    if let Some((token, line)) = parser.tokens.get(parser.position) {
+        return Err(AsmError {
+            line: *line,
+            message: format!("unexpected {} after the code object", token),
+        });
+    }
+    Ok(code)
+}
+
+impl FromStr for CodeObject {
+    type Err = AsmError;
+
+    fn from_str(source: &str) -> AsmResult<Self> {
+        assemble(source)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(String),
+    Str(String),
+    Bytes(Vec<u8>),
+    Punct(char),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Ident(ident) => write!(f, "'{}'", ident),
+            Token::Number(number) => write!(f, "number {}", number),
+            Token::Str(_) => write!(f, "string"),
+            Token::Bytes(_) => write!(f, "bytes"),
+            Token::Punct(c) => write!(f, "'{}'", c),
+        }
+    }
+}
+
+fn tokenize(source: &str) -> AsmResult<Vec<(Token, usize)>> {
+    let mut tokens = vec![];
+    let mut line = 1;
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        let token = match c {
+            '\n' => {
+                line += 1;
+                continue;
+            }
+            '#' => {
+                while matches!(chars.peek(), Some(c) if *c != '\n') {
+                    chars.next();
+                }
+                continue;
+            }
+            c if c.is_whitespace() => continue,
+            '"' => Token::Str(string_literal(&mut chars, false, line)?),
+            'b' if chars.peek() == Some(&'"') => {
+                chars.next();
+                let value = string_literal(&mut chars, true, line)?;
+                Token::Bytes(value.chars().map(|c| c as u8).collect())
+            }
+            c if c.is_ascii_digit()
+                || (c == '-' && matches!(chars.peek(), Some(c) if c.is_ascii_alphanumeric())) =>
+            {
+                let mut number = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    let exponent_sign = (c == '-' || c == '+')
+                        && !number.starts_with("0x")
+                        && number.ends_with(&['e', 'E'][..]);
+                    if !(c.is_ascii_alphanumeric() || c == '.' || c == '_' || exponent_sign) {
+                        break;
+                    }
+                    number.push(c);
+                    chars.next();
+                }
+                Token::Number(number)
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut ident = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if !(c.is_alphanumeric() || c == '_') {
+                        break;
+                    }
+                    ident.push(c);
+                    chars.next();
+                }
+                Token::Ident(ident)
+            }
+            ':' | ',' | '(' | ')' | '[' | ']' | '.' | '*' => Token::Punct(c),
+            c => {
+                return Err(AsmError {
+                    line,
+                    message: format!("unexpected character {:?}", c),
+                })
+            }
+        };
+        tokens.push((token, line));
+    }
+    Ok(tokens)
+}
+
+/// Read the rest of a string literal with the escapes of Rust's `Debug` for
+/// strings. In bytes, which are otherwise ASCII, `\xNN` escapes stand for
+/// the byte itself and are kept as the character with that code.
+fn string_literal(
+    chars: &mut std::iter::Peekable<std::str::Chars>,
+    bytes: bool,
+    line: usize,
+) -> AsmResult<String> {
+    let error = |message: &str| AsmError {
+        line,
+        message: message.to_string(),
+    };
+    let mut value = String::new();
+    loop {
+        match chars.next() {
+            None | Some('\n') => return Err(error("unterminated string")),
+            Some('"') => return Ok(value),
+            Some('\\') => {
+                let c = match chars.next() {
+                    Some('n') => '\n',
+                    Some('r') => '\r',
+                    Some('t') => '\t',
+                    Some('0') => '\0',
+                    Some(c @ '\\') | Some(c @ '"') | Some(c @ '\'') => c,
+                    Some('x') => {
+                        let digits: String = chars.take(2).collect();
+                        u8::from_str_radix(&digits, 16)
+                            .map(char::from)
+                            .map_err(|_| error("invalid \\x escape"))?
+                    }
+                    Some('u') if !bytes => {
+                        if chars.next() != Some('{') {
+                            return Err(error("invalid \\u escape"));
+                        }
+                        let digits: String = chars.take_while(|c| *c != '}').collect();
+                        u32::from_str_radix(&digits, 16)
+                            .ok()
+                            .and_then(std::char::from_u32)
+                            .ok_or_else(|| error("invalid \\u escape"))?
+                    }
+                    _ => return Err(error("unknown escape")),
+                };
+                value.push(c);
+            }
+            Some(c) if bytes && !c.is_ascii() => {
+                return Err(error("bytes can only contain ASCII characters"))
+            }
+            Some(c) => value.push(c),
+        }
+    }
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    /// Line reported for errors at the end of the input.
+    last_line: usize,
+}
+
+/// The labels of the code object being parsed.
+#[derive(Default)]
+struct Labels {
+    defined: HashMap<Label, usize>,
+    /// The first line each label is used on, to report undefined ones.
+    used: HashMap<Label, usize>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn peek_at(&self, distance: usize) -> Option<&Token> {
+        self.tokens
+            .get(self.position + distance)
+            .map(|(token, _)| token)
+    }
+
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.position)
+            .map_or(self.last_line, |(_, line)| *line)
+    }
+
+    fn error<T>(&self, message: String) -> AsmResult<T> {
+        Err(AsmError {
+            line: self.line(),
+            message,
+        })
+    }
+
+    fn unexpected<T>(&self, expected: &str) -> AsmResult<T> {
+        match self.peek() {
+            Some(token) => self.error(format!("expected {}, found {}", expected, token)),
+            None => self.error(format!("expected {}, found the end of input", expected)),
+        }
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek().cloned();
+        self.position += 1;
+        token
+    }
+
+    fn is_ident(&self, ident: &str) -> bool {
+        match self.peek() {
+            Some(Token::Ident(i)) => i == ident,
+            _ => false,
+        }
+    }
+
+    fn is_punct(&self, c: char) -> bool {
+        self.peek() == Some(&Token::Punct(c))
+    }
+
+    fn keyword(&mut self, keyword: &str) -> AsmResult<()> {
+        if !self.is_ident(keyword) {
+            return self.unexpected(&format!("'{}'", keyword));
+        }
+        self.next();
+        Ok(())
+    }
+
+    fn punct(&mut self, c: char) -> AsmResult<()> {
+        if !self.is_punct(c) {
+            return self.unexpected(&format!("'{}'", c));
+        }
+        self.next();
+        Ok(())
+    }
+
+    fn ident(&mut self) -> AsmResult<String> {
+        match self.peek() {
+            Some(Token::Ident(ident)) => {
+                let ident = ident.clone();
+                self.next();
+                Ok(ident)
+            }
+            _ => self.unexpected("a name"),
+        }
+    }
+
+    fn string(&mut self) -> AsmResult<String> {
+        match self.peek() {
+            Some(Token::Str(value)) => {
+                let value = value.clone();
+                self.next();
+                Ok(value)
+            }
+            _ => self.unexpected("a string"),
+        }
+    }
+
+    fn optional_string(&mut self) -> AsmResult<Option<String>> {
+        if self.is_ident("None") {
+            self.next();
+            Ok(None)
+        } else {
+            self.string().map(Some)
+        }
+    }
+
+    fn number<T: FromStr>(&mut self) -> AsmResult<T> {
+        if let Some(Token::Number(number)) = self.peek() {
+            if let Ok(value) = number.parse() {
+                self.next();
+                return Ok(value);
+            }
+        }
+        self.unexpected("a number")
+    }
+
+    fn boolean(&mut self) -> AsmResult<bool> {
+        self.choice("true or false", &[("true", true), ("false", false)])
+    }
+
+    /// One of the names in `options`, for the enums in operands.
+    fn choice<T: Clone>(&mut self, expected: &str, options: &[(&str, T)]) -> AsmResult<T> {
+        if let Some(Token::Ident(ident)) = self.peek() {
+            if let Some((_, value)) = options.iter().find(|(name, _)| name == ident) {
+                self.next();
+                return Ok(value.clone());
+            }
+        }
+        self.unexpected(expected)
+    }
+
+    /// A label name like `L3`, with its number.
+    fn label_name(&self) -> Option<Label> {
+        match self.peek() {
+            Some(Token::Ident(ident)) if ident.starts_with('L') => ident[1..].parse().ok(),
+            _ => None,
+        }
+    }
+
+    fn label(&mut self, labels: &mut Labels) -> AsmResult<Label> {
+        match self.label_name() {
+            Some(label) => {
+                labels.used.entry(label).or_insert_with(|| self.line());
+                self.next();
+                Ok(label)
+            }
+            None => self.unexpected("a label"),
+        }
+    }
+
+    fn names(&mut self) -> Vec<String> {
+        let mut names = vec![];
+        while let Ok(name) = self.string() {
+            names.push(name);
+        }
+        names
+    }
+
+    fn varargs(&mut self) -> AsmResult<Varargs> {
+        if self.is_punct('*') {
+            self.next();
+            Ok(Varargs::Unnamed)
+        } else {
+            self.string().map(Varargs::Named)
+        }
+    }
+
+    fn code(&mut self) -> AsmResult<CodeObject> {
+        self.keyword("code")?;
+        let mut code = CodeObject::new(
+            vec![],
+            Varargs::None,
+            vec![],
+            Varargs::None,
+            String::new(),
+            1,
+            self.string()?,
+        );
+
+        while self.is_punct('.') {
+            self.next();
+            match self.ident()?.as_str() {
+                "source_path" => code.source_path = self.string()?,
+                "first_line" => code.first_line_number = self.number()?,
+                "flags" => code.flags = self.flags()?,
+                "max_stackdepth" => code.max_stackdepth = self.number()?,
+                "args" => code.arg_names = self.names(),
+                "varargs" => code.varargs = self.varargs()?,
+                "kwonlyargs" => code.kwonlyarg_names = self.names(),
+                "varkeywords" => code.varkeywords = self.varargs()?,
+                "cellvars" => code.cellvars = self.names(),
+                "freevars" => code.freevars = self.names(),
+                "generator" => code.is_generator = true,
+                "doc" => code.doc = Some(self.string()?),
+                "doc_stand_ins" => code.doc_stand_ins = Some(self.number()?),
+                attribute => {
+                    self.position -= 1;
+                    return self.error(format!("unknown attribute .{}", attribute));
+                }
+            }
+        }
+
+        let mut labels = Labels::default();
+        while !self.is_ident("end") {
+            if let Some(label) = self.label_name() {
+                if self.peek_at(1) == Some(&Token::Punct(':')) {
+                    let offset = code.instructions.len();
+                    if labels.defined.insert(label, offset).is_some() {
+                        return self.error(format!("label L{} is defined twice", label));
+                    }
+                    self.position += 2;
+                    continue;
+                }
+            }
+            self.instruction(&mut code, &mut labels)?;
+        }
+        self.next();
+
+        let mut undefined: Vec<(usize, Label)> = labels
+            .used
+            .iter()
+            .filter(|(label, _)| !labels.defined.contains_key(label))
+            .map(|(label, line)| (*line, *label))
+            .collect();
+        undefined.sort();
+        if let Some((line, label)) = undefined.first() {
+            return Err(AsmError {
+                line: *line,
+                message: format!("label L{} is not defined", label),
+            });
+        }
+        code.label_map = labels.defined;
+        Ok(code)
+    }
+
+    fn flags(&mut self) -> AsmResult<CodeFlags> {
+        let bits = match self.peek() {
+            Some(Token::Number(number)) if number.starts_with("0x") => {
+                u32::from_str_radix(&number[2..], 16).ok()
+            }
+            Some(Token::Number(number)) => number.parse().ok(),
+            _ => None,
+        };
+        match bits {
+            Some(bits) => match CodeFlags::from_bits(bits) {
+                Some(flags) => {
+                    self.next();
+                    Ok(flags)
+                }
+                None => self.error(format!("unknown code flags {:#x}", bits)),
+            },
+            None => self.unexpected("flags"),
+        }
+    }
+
+    fn instruction(&mut self, code: &mut CodeObject, labels: &mut Labels) -> AsmResult<()> {
+        let offset = code.instructions.len();
+        if let (Some(Token::Number(_)), Some(Token::Punct(':'))) = (self.peek(), self.peek_at(1)) {
+            if code.locations.len() != offset {
+                return self.error("an earlier instruction has no location".to_string());
+            }
+            let row = self.number()?;
+            self.punct(':')?;
+            let column = self.number()?;
+            code.locations.push(Location::new(row, column));
+        }
+        if let Some(Token::Number(_)) = self.peek() {
+            let given: usize = self.number()?;
+            if given != offset {
+                self.position -= 1;
+                return self.error(format!(
+                    "offset {} does not match the instruction's position {}",
+                    given, offset
+                ));
+            }
+        }
+
+        let name = self.ident()?;
+        let instruction = match name.as_str() {
+            "Import" => Instruction::Import {
+                name: self.optional_string()?,
+                symbols: self.symbols()?,
+                level: self.number()?,
+            },
+            "ImportStar" => Instruction::ImportStar {
+                name: self.optional_string()?,
+                level: self.number()?,
+            },
+            "ImportFrom" => Instruction::ImportFrom {
+                name: self.string()?,
+            },
+            "LoadName" => Instruction::LoadName {
+                name: self.string()?,
+                scope: self.name_scope()?,
+            },
+            "StoreName" => Instruction::StoreName {
+                name: self.string()?,
+                scope: self.name_scope()?,
+            },
+            "DeleteName" => Instruction::DeleteName {
+                name: self.string()?,
+            },
+            "StoreSubscript" => Instruction::StoreSubscript,
+            "DeleteSubscript" => Instruction::DeleteSubscript,
+            "StoreAttr" => Instruction::StoreAttr {
+                name: self.string()?,
+            },
+            "DeleteAttr" => Instruction::DeleteAttr {
+                name: self.string()?,
+            },
+            "LoadConst" => Instruction::LoadConst {
+                value: self.constant()?,
+            },
+            "UnaryOperation" => Instruction::UnaryOperation {
+                op: self.unary_operator()?,
+            },
+            "BinaryOperation" => Instruction::BinaryOperation {
+                op: self.binary_operator()?,
+                inplace: self.boolean()?,
+            },
+            "LoadAttr" => Instruction::LoadAttr {
+                name: self.string()?,
+            },
+            "CompareOperation" => Instruction::CompareOperation {
+                op: self.comparison_operator()?,
+            },
+            "Pop" => Instruction::Pop,
+            "Rotate" => Instruction::Rotate {
+                amount: self.number()?,
+            },
+            "Duplicate" => Instruction::Duplicate,
+            "GetIter" => Instruction::GetIter,
+            "Pass" => Instruction::Pass,
+            "Continue" => Instruction::Continue,
+            "Break" => Instruction::Break,
+            "Jump" => Instruction::Jump {
+                target: self.label(labels)?,
+            },
+            "JumpIf" => Instruction::JumpIf {
+                target: self.label(labels)?,
+            },
+            "JumpIfFalse" => Instruction::JumpIfFalse {
+                target: self.label(labels)?,
+            },
+            "MakeFunction" => {
+                let bits = self.number()?;
+                match FunctionOpArg::from_bits(bits) {
+                    Some(flags) => Instruction::MakeFunction { flags },
+                    None => {
+                        self.position -= 1;
+                        return self.error(format!("unknown function flags {}", bits));
+                    }
+                }
+            }
+            "CallFunction" => Instruction::CallFunction {
+                typ: self.call_type()?,
+            },
+            "ForIter" => Instruction::ForIter {
+                target: self.label(labels)?,
+            },
+            "ReturnValue" => Instruction::ReturnValue,
+            "YieldValue" => Instruction::YieldValue,
+            "YieldFrom" => Instruction::YieldFrom,
+            "SetupLoop" => Instruction::SetupLoop {
+                start: self.label(labels)?,
+                end: self.label(labels)?,
+            },
+            "SetupExcept" => Instruction::SetupExcept {
+                handler: self.label(labels)?,
+            },
+            "SetupWith" => Instruction::SetupWith {
+                end: self.label(labels)?,
+            },
+            "CleanupWith" => Instruction::CleanupWith {
+                end: self.label(labels)?,
+            },
+            "PopBlock" => Instruction::PopBlock,
+            "Raise" => Instruction::Raise {
+                argc: self.number()?,
+            },
+            "BuildString" => Instruction::BuildString {
+                size: self.number()?,
+            },
+            "BuildTuple" => Instruction::BuildTuple {
+                size: self.number()?,
+                unpack: self.boolean()?,
+            },
+            "BuildList" => Instruction::BuildList {
+                size: self.number()?,
+                unpack: self.boolean()?,
+            },
+            "BuildSet" => Instruction::BuildSet {
+                size: self.number()?,
+                unpack: self.boolean()?,
+            },
+            "BuildMap" => Instruction::BuildMap {
+                size: self.number()?,
+                unpack: self.boolean()?,
+            },
+            "BuildSlice" => Instruction::BuildSlice {
+                size: self.number()?,
+            },
+            "ListAppend" => Instruction::ListAppend { i: self.number()? },
+            "SetAdd" => Instruction::SetAdd { i: self.number()? },
+            "MapAdd" => Instruction::MapAdd { i: self.number()? },
+            "PrintExpr" => Instruction::PrintExpr,
+            "LoadBuildClass" => Instruction::LoadBuildClass,
+            "SetupAnnotation" => Instruction::SetupAnnotation,
+            "UnpackSequence" => Instruction::UnpackSequence {
+                size: self.number()?,
+            },
+            "UnpackEx" => Instruction::UnpackEx {
+                before: self.number()?,
+                after: self.number()?,
+            },
+            "Unpack" => Instruction::Unpack,
+            "FormatValue" => Instruction::FormatValue {
+                conversion: self.choice(
+                    "a conversion",
+                    &[
+                        ("None", None),
+                        ("Str", Some(ConversionFlag::Str)),
+                        ("Ascii", Some(ConversionFlag::Ascii)),
+                        ("Repr", Some(ConversionFlag::Repr)),
+                    ],
+                )?,
+                spec: self.string()?,
+            },
+            "PopException" => Instruction::PopException,
+            "LoadMethod" => Instruction::LoadMethod {
+                name: self.string()?,
+            },
+            "CallMethod" => Instruction::CallMethod {
+                typ: self.call_type()?,
+            },
+            _ => {
+                self.position -= 1;
+                return self.error(format!("unknown instruction {}", name));
+            }
+        };
+        code.instructions.push(instruction);
+        Ok(())
+    }
+
+    fn symbols(&mut self) -> AsmResult<Vec<String>> {
+        let mut symbols = vec![];
+        self.punct('[')?;
+        while !self.is_punct(']') {
+            symbols.push(self.string()?);
+            if !self.is_punct(']') {
+                self.punct(',')?;
+            }
+        }
+        self.next();
+        Ok(symbols)
+    }
+
+    fn name_scope(&mut self) -> AsmResult<NameScope> {
+        self.choice(
+            "a name scope",
+            &[
+                ("Local", NameScope::Local),
+                ("NonLocal", NameScope::NonLocal),
+                ("Global", NameScope::Global),
+            ],
+        )
+    }
+
+    fn unary_operator(&mut self) -> AsmResult<UnaryOperator> {
+        use UnaryOperator::*;
+        self.choice(
+            "a unary operator",
+            &[
+                ("Not", Not),
+                ("Invert", Invert),
+                ("Minus", Minus),
+                ("Plus", Plus),
+            ],
+        )
+    }
+
+    fn binary_operator(&mut self) -> AsmResult<BinaryOperator> {
+        use BinaryOperator::*;
+        self.choice(
+            "a binary operator",
+            &[
+                ("Power", Power),
+                ("Multiply", Multiply),
+                ("MatrixMultiply", MatrixMultiply),
+                ("Divide", Divide),
+                ("FloorDivide", FloorDivide),
+                ("Modulo", Modulo),
+                ("Add", Add),
+                ("Subtract", Subtract),
+                ("Subscript", Subscript),
+                ("Lshift", Lshift),
+                ("Rshift", Rshift),
+                ("And", And),
+                ("Xor", Xor),
+                ("Or", Or),
+            ],
+        )
+    }
+
+    fn comparison_operator(&mut self) -> AsmResult<ComparisonOperator> {
+        use ComparisonOperator::*;
+        self.choice(
+            "a comparison operator",
+            &[
+                ("Greater", Greater),
+                ("GreaterOrEqual", GreaterOrEqual),
+                ("Less", Less),
+                ("LessOrEqual", LessOrEqual),
+                ("Equal", Equal),
+                ("NotEqual", NotEqual),
+                ("In", In),
+                ("NotIn", NotIn),
+                ("Is", Is),
+                ("IsNot", IsNot),
+            ],
+        )
+    }
+
+    fn call_type(&mut self) -> AsmResult<CallType> {
+        match self.ident()?.as_str() {
+            "Positional" => Ok(CallType::Positional(self.number()?)),
+            "Keyword" => Ok(CallType::Keyword(self.number()?)),
+            "Ex" => Ok(CallType::Ex(self.boolean()?)),
+            _ => {
+                self.position -= 1;
+                self.unexpected("a call type")
+            }
+        }
+    }
+
+    fn float(&mut self) -> AsmResult<f64> {
+        let value = match self.peek() {
+            Some(Token::Ident(ident)) if ident == "inf" || ident == "NaN" => ident.parse().ok(),
+            Some(Token::Number(number)) => number.parse().ok(),
+            _ => None,
+        };
+        match value {
+            Some(value) => {
+                self.next();
+                Ok(value)
+            }
+            None => self.unexpected("a float"),
+        }
+    }
+
+    fn constant(&mut self) -> AsmResult<Constant> {
+        let constant = match self.peek() {
+            Some(Token::Number(number)) => {
+                let is_float = number.contains(&['.', 'e', 'E'][..])
+                    || number.ends_with("inf")
+                    || number.ends_with("NaN");
+                if is_float {
+                    Constant::Float {
+                        value: self.float()?,
+                    }
+                } else {
+                    Constant::Integer {
+                        value: self.number::<BigInt>()?,
+                    }
+                }
+            }
+            Some(Token::Str(_)) => Constant::String {
+                value: self.string()?,
+                stand_ins: None,
+            },
+            Some(Token::Bytes(value)) => {
+                let value = value.clone();
+                self.next();
+                Constant::Bytes { value }
+            }
+            Some(Token::Punct('(')) => {
+                self.next();
+                let mut elements = vec![];
+                while !self.is_punct(')') {
+                    elements.push(self.constant()?);
+                    if !self.is_punct(')') {
+                        self.punct(',')?;
+                    }
+                }
+                self.next();
+                Constant::Tuple { elements }
+            }
+            Some(Token::Ident(ident)) => match ident.as_str() {
+                "None" => {
+                    self.next();
+                    Constant::None
+                }
+                "Ellipsis" => {
+                    self.next();
+                    Constant::Ellipsis
+                }
+                "True" | "False" => Constant::Boolean {
+                    value: self.choice("a boolean", &[("True", true), ("False", false)])?,
+                },
+                "inf" | "NaN" => Constant::Float {
+                    value: self.float()?,
+                },
+                "complex" => {
+                    self.next();
+                    self.punct('(')?;
+                    let re = self.float()?;
+                    self.punct(',')?;
+                    let im = self.float()?;
+                    self.punct(')')?;
+                    Constant::Complex {
+                        value: Complex64::new(re, im),
+                    }
+                }
+                "str" => {
+                    self.next();
+                    self.punct('(')?;
+                    let value = self.string()?;
+                    self.punct(',')?;
+                    let stand_ins = self.number()?;
+                    self.punct(')')?;
+                    Constant::String {
+                        value,
+                        stand_ins: Some(stand_ins),
+                    }
+                }
+                "code" => Constant::Code {
+                    code: Box::new(self.code()?),
+                },
+                _ => return self.unexpected("a constant"),
+            },
+            _ => return self.unexpected("a constant"),
+        };
+        Ok(constant)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(code: &CodeObject) {
+        let listing = code.to_string();
+        match assemble(&listing) {
+            Ok(assembled) => assert!(
+                assembled == *code,
+                "{} assembled into\n{}",
+                listing,
+                assembled
+            ),
+            Err(err) => panic!("{}\n{}", err, listing),
+        }
+    }
+
+    fn code_object(name: &str, instructions: Vec<Instruction>) -> CodeObject {
+        let mut code = CodeObject::new(
+            vec![],
+            Varargs::None,
+            vec![],
+            Varargs::None,
+            "test.py".to_string(),
+            1,
+            name.to_string(),
+        );
+        code.locations = (0..instructions.len())
+            .map(|i| Location::new(i + 1, 1))
+            .collect();
+        code.instructions = instructions;
+        code
+    }
+
+    #[test]
+    fn test_roundtrip_constants() {
+        let constants = vec![
+            Constant::Integer {
+                value: BigInt::from(-12_345_678_901_234_567i64) * 1000,
+            },
+            Constant::Float { value: 0.1 },
+            Constant::Float { value: -1e300 },
+            Constant::Float { value: 1e-7 },
+            Constant::Float { value: -0.0 },
+            Constant::Float {
+                value: f64::INFINITY,
+            },
+            Constant::Float {
+                value: f64::NEG_INFINITY,
+            },
+            Constant::Complex {
+                value: Complex64::new(1.5, -2.0),
+            },
+            Constant::Boolean { value: true },
+            Constant::String {
+                value: "tab\t \"quote\" \\ \u{0} é \u{200b}".to_string(),
+                stand_ins: None,
+            },
+            Constant::String {
+                value: "\u{10f800}x\u{10ffff}".to_string(),
+                stand_ins: Some(0x10_f000),
+            },
+            Constant::Bytes {
+                value: b"\x00\xff\"'\\abc\n".to_vec(),
+            },
+            Constant::Tuple { elements: vec![] },
+            Constant::Tuple {
+                elements: vec![Constant::None],
+            },
+            Constant::Tuple {
+                elements: vec![
+                    Constant::Ellipsis,
+                    Constant::Tuple {
+                        elements: vec![Constant::Boolean { value: false }],
+                    },
+                ],
+            },
+        ];
+        let instructions = constants
+            .into_iter()
+            .map(|value| Instruction::LoadConst { value })
+            .collect();
+        roundtrip(&code_object("<module>", instructions));
+    }
+
+    #[test]
+    fn test_roundtrip_nested_code() {
+        let mut inner = code_object(
+            "f",
+            vec![
+                Instruction::LoadName {
+                    name: "x".to_string(),
+                    scope: NameScope::NonLocal,
+                },
+                Instruction::YieldValue,
+                Instruction::ReturnValue,
+            ],
+        );
+        inner.arg_names = vec!["a".to_string()];
+        inner.varargs = Varargs::Unnamed;
+        inner.kwonlyarg_names = vec!["b".to_string(), "c".to_string()];
+        inner.varkeywords = Varargs::Named("kwargs".to_string());
+        inner.freevars = vec!["x".to_string()];
+        inner.is_generator = true;
+        inner.doc = Some("A doc\nstring.\u{10f800}".to_string());
+        inner.doc_stand_ins = Some(0x10_f800);
+        inner.flags = CodeFlags::NEW_LOCALS | CodeFlags::OPTIMIZED;
+        let outer = code_object(
+            "<module>",
+            vec![
+                Instruction::LoadConst {
+                    value: Constant::Code {
+                        code: Box::new(inner),
+                    },
+                },
+                Instruction::LoadConst {
+                    value: Constant::String {
+                        value: "f".to_string(),
+                        stand_ins: None,
+                    },
+                },
+                Instruction::MakeFunction {
+                    flags: FunctionOpArg::HAS_DEFAULTS | FunctionOpArg::HAS_ANNOTATIONS,
+                },
+                Instruction::StoreName {
+                    name: "f".to_string(),
+                    scope: NameScope::Global,
+                },
+            ],
+        );
+        roundtrip(&outer);
+    }
+
+    #[test]
+    fn test_roundtrip_instructions() {
+        let mut code = code_object(
+            "<module>",
+            vec![
+                Instruction::Import {
+                    name: Some("os".to_string()),
+                    symbols: vec!["path".to_string(), "sep".to_string()],
+                    level: 0,
+                },
+                Instruction::Import {
+                    name: None,
+                    symbols: vec![],
+                    level: 2,
+                },
+                Instruction::ImportStar {
+                    name: None,
+                    level: 1,
+                },
+                Instruction::SetupLoop { start: 0, end: 2 },
+                Instruction::ForIter { target: 2 },
+                Instruction::BinaryOperation {
+                    op: BinaryOperator::FloorDivide,
+                    inplace: true,
+                },
+                Instruction::CompareOperation {
+                    op: ComparisonOperator::IsNot,
+                },
+                Instruction::UnaryOperation {
+                    op: UnaryOperator::Invert,
+                },
+                Instruction::CallFunction {
+                    typ: CallType::Keyword(3),
+                },
+                Instruction::CallFunction {
+                    typ: CallType::Ex(true),
+                },
+                Instruction::LoadMethod {
+                    name: "append".to_string(),
+                },
+                Instruction::CallMethod {
+                    typ: CallType::Positional(1),
+                },
+                Instruction::BuildMap {
+                    size: 2,
+                    unpack: true,
+                },
+                Instruction::UnpackEx {
+                    before: 1,
+                    after: 2,
+                },
+                Instruction::FormatValue {
+                    conversion: Some(ConversionFlag::Repr),
+                    spec: ">10".to_string(),
+                },
+                Instruction::FormatValue {
+                    conversion: None,
+                    spec: String::new(),
+                },
+                Instruction::Jump { target: 0 },
+                Instruction::PopBlock,
+                Instruction::Rotate { amount: 3 },
+                Instruction::SetupExcept { handler: 1 },
+                Instruction::PopException,
+            ],
+        );
+        code.label_map.insert(0, 4);
+        code.label_map.insert(1, 4);
+        code.label_map.insert(2, 15);
+        // A label can point past the last instruction.
+        code.label_map.insert(3, 19);
+        roundtrip(&code);
+    }
+
+    #[test]
+    fn test_assemble_without_locations() {
+        let code = assemble(
+            "code \"<module>\"
+                # A comment.
+                LoadConst 1
+                L0:
+                Jump L0
+            end",
+        )
+        .unwrap();
+        assert_eq!(code.source_path, "");
+        assert_eq!(code.first_line_number, 1);
+        assert!(code.locations.is_empty());
+        assert_eq!(
+            code.instructions,
+            vec![
+                Instruction::LoadConst {
+                    value: Constant::Integer {
+                        value: BigInt::from(1)
+                    }
+                },
+                Instruction::Jump { target: 0 },
+            ]
+        );
+        assert_eq!(code.label_map[&0], 1);
+    }
+
+    #[test]
+    fn test_assemble_errors() {
+        let error = |source: &str| assemble(source).unwrap_err();
+        assert_eq!(
+            error("code \"m\"\n  Jump L1\nend"),
+            AsmError {
+                line: 2,
+                message: "label L1 is not defined".to_string()
+            }
+        );
+        assert_eq!(
+            error("code \"m\"\n  L0:\n  L0:\nend").message,
+            "label L0 is defined twice"
+        );
+        assert_eq!(
+            error("code \"m\"\n  1 Pop\nend").message,
+            "offset 1 does not match the instruction's position 0"
+        );
+        assert_eq!(
+            error("code \"m\"\n  Pop 1:1 Pop\nend").message,
+            "an earlier instruction has no location"
+        );
+        assert_eq!(
+            error("code \"m\"\n  Frobnicate\nend"),
+            AsmError {
+                line: 2,
+                message: "unknown instruction Frobnicate".to_string()
+            }
+        );
+        assert_eq!(
+            error("code \"m\"\n  BuildTuple 2\nend").message,
+            "expected true or false, found 'end'"
+        );
+        assert_eq!(
+            error("code \"m\"\n  LoadConst \"x\n").message,
+            "unterminated string"
+        );
+        assert_eq!(
+            error("code \"m\"\nPop").message,
+            "expected a name, found the end of input"
+        );
+        assert_eq!(
+            error("code \"m\" end end").message,
+            "unexpected 'end' after the code object"
+        );
+    }
+}
@@ -5,7 +5,7 @@ use bitflags::bitflags;
 use num_bigint::BigInt;
 use num_complex::Complex64;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::fmt;
 
 /// Sourcode location.
@@ -41,10 +41,36 @@ pub struct CodeObject {
     pub varargs: Varargs,       // *args or *
     pub kwonlyarg_names: Vec<String>,
     pub varkeywords: Varargs, // **kwargs or **
+    /// Variables of this code that nested functions refer to.
+    pub cellvars: Vec<String>,
+    /// Variables of enclosing functions that this code refers to.
+    pub freevars: Vec<String>,
     pub source_path: String,
     pub first_line_number: usize,
     pub obj_name: String, // Name of the object that created this code object
     pub is_generator: bool,
+    /// The docstring of the module, class or function, if any.
+    pub doc: Option<String>,
+    /// Where the code points standing for lone surrogates in `doc` start,
+    /// as in `Constant::String`.
+    pub doc_stand_ins: Option<u32>,
+    pub flags: CodeFlags,
+    /// The largest number of values on the stack while running this code.
+    pub max_stackdepth: usize,
+}
+
+bitflags! {
+    /// Code object flags, with the same values as CPython's `co_flags`.
+    #[derive(Serialize, Deserialize)]
+    pub struct CodeFlags: u32 {
+        const OPTIMIZED = 0x0001;
+        const NEW_LOCALS = 0x0002;
+        const VARARGS = 0x0004;
+        const VARKEYWORDS = 0x0008;
+        const NESTED = 0x0010;
+        const GENERATOR = 0x0020;
+        const FUTURE_ANNOTATIONS = 0x0010_0000;
+    }
 }
 
 bitflags! {
@@ -206,6 +232,7 @@ pub enum Instruction {
     },
     PrintExpr,
     LoadBuildClass,
+    SetupAnnotation,
     UnpackSequence {
         size: usize,
     },
@@ -219,10 +246,81 @@ pub enum Instruction {
         spec: String,
     },
     PopException,
+    /// Looks up the method `name` for calling it right away, pushing the
+    /// unbound function and the object to call it with, or `None` and the
+    /// attribute when it isn't a plain method.
+    LoadMethod {
+        name: String,
+    },
+    /// Calls what `LoadMethod` pushed below the arguments.
+    CallMethod {
+        typ: CallType,
+    },
 }
 
 use self::Instruction::*;
 
+/// The names of all instructions, in declaration order. The position of a
+/// name is the opcode `dis` reports for it.
+pub const INSTRUCTION_NAMES: &[&str] = &[
+    "Import",
+    "ImportStar",
+    "ImportFrom",
+    "LoadName",
+    "StoreName",
+    "DeleteName",
+    "StoreSubscript",
+    "DeleteSubscript",
+    "StoreAttr",
+    "DeleteAttr",
+    "LoadConst",
+    "UnaryOperation",
+    "BinaryOperation",
+    "LoadAttr",
+    "CompareOperation",
+    "Pop",
+    "Rotate",
+    "Duplicate",
+    "GetIter",
+    "Pass",
+    "Continue",
+    "Break",
+    "Jump",
+    "JumpIf",
+    "JumpIfFalse",
+    "MakeFunction",
+    "CallFunction",
+    "ForIter",
+    "ReturnValue",
+    "YieldValue",
+    "YieldFrom",
+    "SetupLoop",
+    "SetupExcept",
+    "SetupWith",
+    "CleanupWith",
+    "PopBlock",
+    "Raise",
+    "BuildString",
+    "BuildTuple",
+    "BuildList",
+    "BuildSet",
+    "BuildMap",
+    "BuildSlice",
+    "ListAppend",
+    "SetAdd",
+    "MapAdd",
+    "PrintExpr",
+    "LoadBuildClass",
+    "SetupAnnotation",
+    "UnpackSequence",
+    "UnpackEx",
+    "Unpack",
+    "FormatValue",
+    "PopException",
+    "LoadMethod",
+    "CallMethod",
+];
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum CallType {
     Positional(usize),
@@ -232,14 +330,34 @@ pub enum CallType {
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Constant {
-    Integer { value: BigInt },
-    Float { value: f64 },
-    Complex { value: Complex64 },
-    Boolean { value: bool },
-    String { value: String },
-    Bytes { value: Vec<u8> },
-    Code { code: Box<CodeObject> },
-    Tuple { elements: Vec<Constant> },
+    Integer {
+        value: BigInt,
+    },
+    Float {
+        value: f64,
+    },
+    Complex {
+        value: Complex64,
+    },
+    Boolean {
+        value: bool,
+    },
+    /// A `str`. Lone surrogates in a literal are stood in for by valid
+    /// code points in a block that `value` does not otherwise use; the
+    /// block starts at `stand_ins`.
+    String {
+        value: String,
+        stand_ins: Option<u32>,
+    },
+    Bytes {
+        value: Vec<u8>,
+    },
+    Code {
+        code: Box<CodeObject>,
+    },
+    Tuple {
+        elements: Vec<Constant>,
+    },
     None,
     Ellipsis,
 }
@@ -317,11 +435,135 @@ impl CodeObject {
             varargs,
             kwonlyarg_names,
             varkeywords,
+            cellvars: Vec::new(),
+            freevars: Vec::new(),
             source_path,
             first_line_number,
             obj_name,
             is_generator: false,
+            doc: None,
+            doc_stand_ins: None,
+            flags: CodeFlags::empty(),
+            max_stackdepth: 0,
+        }
+    }
+
+    /// The flags of this code object, including the ones implied by its
+    /// arguments and by being a generator.
+    pub fn co_flags(&self) -> CodeFlags {
+        let mut flags = self.flags;
+        if let Varargs::Named(_) = self.varargs {
+            flags |= CodeFlags::VARARGS;
+        }
+        if let Varargs::Named(_) = self.varkeywords {
+            flags |= CodeFlags::VARKEYWORDS;
+        }
+        if self.is_generator {
+            flags |= CodeFlags::GENERATOR;
+        }
+        flags
+    }
+
+    /// Names of the local variables, starting with the arguments. Only code
+    /// with its own namespace has local variables. Cell variables are left
+    /// out unless they are arguments.
+    pub fn varnames(&self) -> Vec<&str> {
+        let mut varnames: Vec<&str> = Vec::new();
+        if !self.flags.contains(CodeFlags::NEW_LOCALS) {
+            return varnames;
+        }
+        for name in self.arg_names.iter().chain(self.kwonlyarg_names.iter()) {
+            varnames.push(name);
+        }
+        for varargs in [&self.varargs, &self.varkeywords].iter() {
+            if let Varargs::Named(name) = varargs {
+                varnames.push(name);
+            }
+        }
+        for instruction in &self.instructions {
+            match instruction {
+                StoreName {
+                    name,
+                    scope: NameScope::Local,
+                }
+                | DeleteName { name }
+                    if !varnames.contains(&name.as_str()) && !self.cellvars.contains(name) =>
+                {
+                    varnames.push(name)
+                }
+                _ => {}
+            }
+        }
+        varnames
+    }
+
+    /// Names of the globals, attributes and modules this code refers to.
+    pub fn names<'a>(&'a self) -> Vec<&'a str> {
+        let varnames = self.varnames();
+        let is_variable = |name: &String| {
+            varnames.contains(&name.as_str())
+                || self.cellvars.contains(name)
+                || self.freevars.contains(name)
+        };
+        let mut names: Vec<&str> = Vec::new();
+        let mut add = |name: &'a str| {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        };
+        for instruction in &self.instructions {
+            match instruction {
+                LoadName { name, scope } | StoreName { name, scope } => match scope {
+                    NameScope::NonLocal => {}
+                    _ if is_variable(name) => {}
+                    _ => add(name),
+                },
+                DeleteName { name } if !is_variable(name) => add(name),
+                LoadAttr { name }
+                | StoreAttr { name }
+                | DeleteAttr { name }
+                | LoadMethod { name } => add(name),
+                ImportFrom { name } => add(name),
+                Import {
+                    name: Some(name), ..
+                }
+                | ImportStar {
+                    name: Some(name), ..
+                } => add(name),
+                _ => {}
+            }
+        }
+        names
+    }
+
+    /// The line number table in the format of CPython's `co_lnotab`: pairs of
+    /// instruction offset and line increments, starting at the first line.
+    pub fn lnotab(&self) -> Vec<u8> {
+        let mut lnotab = Vec::new();
+        let mut last_offset = 0;
+        let mut last_line = self.first_line_number as i64;
+        for (offset, location) in self.locations.iter().enumerate() {
+            let line = location.row() as i64;
+            if line == last_line {
+                continue;
+            }
+            let mut offset_delta = offset - last_offset;
+            let mut line_delta = line - last_line;
+            while offset_delta > 255 {
+                lnotab.extend_from_slice(&[255, 0]);
+                offset_delta -= 255;
+            }
+            while !(-128..=127).contains(&line_delta) {
+                let step = if line_delta > 0 { 127 } else { -128 };
+                lnotab.extend_from_slice(&[offset_delta as u8, step as i8 as u8]);
+                offset_delta = 0;
+                line_delta -= step;
+            }
+            lnotab.extend_from_slice(&[offset_delta as u8, line_delta as i8 as u8]);
+            last_offset = offset;
+            last_line = line;
         }
+        lnotab
     }
 
     pub fn get_constants(&self) -> impl Iterator<Item = &Constant> {
@@ -335,138 +577,285 @@ impl CodeObject {
     }
 }
 
+/// Code objects display as a listing that `asm::assemble` reads back into an
+/// equal code object. Labels are written before the instruction they point
+/// at, and code constants are nested inline.
 impl fmt::Display for CodeObject {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let label_targets: HashSet<&usize> = self.label_map.values().collect();
+        self.fmt_listing(f, 0)
+    }
+}
+
+impl CodeObject {
+    fn fmt_listing(&self, f: &mut fmt::Formatter, level: usize) -> fmt::Result {
+        let indent = "    ".repeat(level + 1);
+        let fmt_names = |f: &mut fmt::Formatter, attribute: &str, names: &[String]| {
+            if names.is_empty() {
+                return Ok(());
+            }
+            write!(f, "{}.{}", indent, attribute)?;
+            for name in names {
+                write!(f, " {:?}", name)?;
+            }
+            writeln!(f)
+        };
+        let fmt_varargs = |f: &mut fmt::Formatter, attribute: &str, varargs: &Varargs| match varargs
+        {
+            Varargs::None => Ok(()),
+            Varargs::Unnamed => writeln!(f, "{}.{} *", indent, attribute),
+            Varargs::Named(name) => writeln!(f, "{}.{} {:?}", indent, attribute, name),
+        };
+
+        writeln!(f, "code {:?}", self.obj_name)?;
+        writeln!(f, "{}.source_path {:?}", indent, self.source_path)?;
+        writeln!(f, "{}.first_line {}", indent, self.first_line_number)?;
+        writeln!(f, "{}.flags {:#x}", indent, self.flags.bits())?;
+        writeln!(f, "{}.max_stackdepth {}", indent, self.max_stackdepth)?;
+        fmt_names(f, "args", &self.arg_names)?;
+        fmt_varargs(f, "varargs", &self.varargs)?;
+        fmt_names(f, "kwonlyargs", &self.kwonlyarg_names)?;
+        fmt_varargs(f, "varkeywords", &self.varkeywords)?;
+        fmt_names(f, "cellvars", &self.cellvars)?;
+        fmt_names(f, "freevars", &self.freevars)?;
+        if self.is_generator {
+            writeln!(f, "{}.generator", indent)?;
+        }
+        if let Some(doc) = &self.doc {
+            writeln!(f, "{}.doc {:?}", indent, doc)?;
+        }
+        if let Some(stand_ins) = self.doc_stand_ins {
+            writeln!(f, "{}.doc_stand_ins {}", indent, stand_ins)?;
+        }
+
+        let mut labels: Vec<(usize, Label)> = self
+            .label_map
+            .iter()
+            .map(|(label, offset)| (*offset, *label))
+            .collect();
+        labels.sort();
+        let mut labels = labels.into_iter().peekable();
         for (offset, instruction) in self.instructions.iter().enumerate() {
-            let arrow = if label_targets.contains(&offset) {
-                ">>"
-            } else {
-                "  "
+            while let Some((_, label)) = labels.next_if(|(target, _)| *target <= offset) {
+                writeln!(f, "{}L{}:", indent, label)?;
+            }
+            let location = match self.locations.get(offset) {
+                Some(location) => format!("{}:{}", location.row(), location.column()),
+                None => String::new(),
             };
-            write!(f, "          {} {:5} ", arrow, offset)?;
-            instruction.fmt_dis(f, &self.label_map)?;
+            write!(f, "{}{:>7} {:>5} ", indent, location, offset)?;
+            instruction.fmt_listing(f, level)?;
+            writeln!(f)?;
         }
-        Ok(())
+        for (_, label) in labels {
+            writeln!(f, "{}L{}:", indent, label)?;
+        }
+        write!(f, "{}end", "    ".repeat(level))
     }
 }
 
 impl Instruction {
-    fn fmt_dis(&self, f: &mut fmt::Formatter, label_map: &HashMap<Label, usize>) -> fmt::Result {
-        macro_rules! w {
-            ($variant:ident) => {
-                write!(f, "{:20}\n", stringify!($variant))
-            };
-            ($variant:ident, $var:expr) => {
-                write!(f, "{:20} ({})\n", stringify!($variant), $var)
-            };
-            ($variant:ident, $var1:expr, $var2:expr) => {
-                write!(f, "{:20} ({}, {})\n", stringify!($variant), $var1, $var2)
-            };
-            ($variant:ident, $var1:expr, $var2:expr, $var3:expr) => {
-                write!(
-                    f,
-                    "{:20} ({}, {}, {})\n",
-                    stringify!($variant),
-                    $var1,
-                    $var2,
-                    $var3
-                )
-            };
+    /// The name of this instruction, as shown in disassembly.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Import { .. } => "Import",
+            ImportStar { .. } => "ImportStar",
+            ImportFrom { .. } => "ImportFrom",
+            LoadName { .. } => "LoadName",
+            StoreName { .. } => "StoreName",
+            DeleteName { .. } => "DeleteName",
+            StoreSubscript => "StoreSubscript",
+            DeleteSubscript => "DeleteSubscript",
+            StoreAttr { .. } => "StoreAttr",
+            DeleteAttr { .. } => "DeleteAttr",
+            LoadConst { .. } => "LoadConst",
+            UnaryOperation { .. } => "UnaryOperation",
+            BinaryOperation { .. } => "BinaryOperation",
+            LoadAttr { .. } => "LoadAttr",
+            CompareOperation { .. } => "CompareOperation",
+            Pop => "Pop",
+            Rotate { .. } => "Rotate",
+            Duplicate => "Duplicate",
+            GetIter => "GetIter",
+            Pass => "Pass",
+            Continue => "Continue",
+            Break => "Break",
+            Jump { .. } => "Jump",
+            JumpIf { .. } => "JumpIf",
+            JumpIfFalse { .. } => "JumpIfFalse",
+            MakeFunction { .. } => "MakeFunction",
+            CallFunction { .. } => "CallFunction",
+            ForIter { .. } => "ForIter",
+            ReturnValue => "ReturnValue",
+            YieldValue => "YieldValue",
+            YieldFrom => "YieldFrom",
+            SetupLoop { .. } => "SetupLoop",
+            SetupExcept { .. } => "SetupExcept",
+            SetupWith { .. } => "SetupWith",
+            CleanupWith { .. } => "CleanupWith",
+            PopBlock => "PopBlock",
+            Raise { .. } => "Raise",
+            BuildString { .. } => "BuildString",
+            BuildTuple { .. } => "BuildTuple",
+            BuildList { .. } => "BuildList",
+            BuildSet { .. } => "BuildSet",
+            BuildMap { .. } => "BuildMap",
+            BuildSlice { .. } => "BuildSlice",
+            ListAppend { .. } => "ListAppend",
+            SetAdd { .. } => "SetAdd",
+            MapAdd { .. } => "MapAdd",
+            PrintExpr => "PrintExpr",
+            LoadBuildClass => "LoadBuildClass",
+            SetupAnnotation => "SetupAnnotation",
+            UnpackSequence { .. } => "UnpackSequence",
+            UnpackEx { .. } => "UnpackEx",
+            Unpack => "Unpack",
+            FormatValue { .. } => "FormatValue",
+            PopException => "PopException",
+            LoadMethod { .. } => "LoadMethod",
+            CallMethod { .. } => "CallMethod",
         }
+    }
 
+    /// The index of this instruction's name in `INSTRUCTION_NAMES`.
+    pub fn opcode(&self) -> u8 {
+        let name = self.name();
+        INSTRUCTION_NAMES.iter().position(|n| *n == name).unwrap() as u8
+    }
+
+    fn fmt_listing(&self, f: &mut fmt::Formatter, level: usize) -> fmt::Result {
+        write!(f, "{}", self.name())?;
         match self {
             Import {
                 name,
                 symbols,
                 level,
-            } => w!(
-                Import,
-                format!("{:?}", name),
-                format!("{:?}", symbols),
-                level
-            ),
-            ImportStar { name, level } => w!(ImportStar, format!("{:?}", name), level),
-            ImportFrom { name } => w!(ImportFrom, name),
-            LoadName { name, scope } => w!(LoadName, name, format!("{:?}", scope)),
-            StoreName { name, scope } => w!(StoreName, name, format!("{:?}", scope)),
-            DeleteName { name } => w!(DeleteName, name),
-            StoreSubscript => w!(StoreSubscript),
-            DeleteSubscript => w!(DeleteSubscript),
-            StoreAttr { name } => w!(StoreAttr, name),
-            DeleteAttr { name } => w!(DeleteAttr, name),
-            LoadConst { value } => w!(LoadConst, value),
-            UnaryOperation { op } => w!(UnaryOperation, format!("{:?}", op)),
-            BinaryOperation { op, inplace } => w!(BinaryOperation, format!("{:?}", op), inplace),
-            LoadAttr { name } => w!(LoadAttr, name),
-            CompareOperation { op } => w!(CompareOperation, format!("{:?}", op)),
-            Pop => w!(Pop),
-            Rotate { amount } => w!(Rotate, amount),
-            Duplicate => w!(Duplicate),
-            GetIter => w!(GetIter),
-            Pass => w!(Pass),
-            Continue => w!(Continue),
-            Break => w!(Break),
-            Jump { target } => w!(Jump, label_map[target]),
-            JumpIf { target } => w!(JumpIf, label_map[target]),
-            JumpIfFalse { target } => w!(JumpIfFalse, label_map[target]),
-            MakeFunction { flags } => w!(MakeFunction, format!("{:?}", flags)),
-            CallFunction { typ } => w!(CallFunction, format!("{:?}", typ)),
-            ForIter { target } => w!(ForIter, label_map[target]),
-            ReturnValue => w!(ReturnValue),
-            YieldValue => w!(YieldValue),
-            YieldFrom => w!(YieldFrom),
-            SetupLoop { start, end } => w!(SetupLoop, label_map[start], label_map[end]),
-            SetupExcept { handler } => w!(SetupExcept, handler),
-            SetupWith { end } => w!(SetupWith, end),
-            CleanupWith { end } => w!(CleanupWith, end),
-            PopBlock => w!(PopBlock),
-            Raise { argc } => w!(Raise, argc),
-            BuildString { size } => w!(BuildString, size),
-            BuildTuple { size, unpack } => w!(BuildTuple, size, unpack),
-            BuildList { size, unpack } => w!(BuildList, size, unpack),
-            BuildSet { size, unpack } => w!(BuildSet, size, unpack),
-            BuildMap { size, unpack } => w!(BuildMap, size, unpack),
-            BuildSlice { size } => w!(BuildSlice, size),
-            ListAppend { i } => w!(ListAppend, i),
-            SetAdd { i } => w!(SetAdd, i),
-            MapAdd { i } => w!(MapAdd, i),
-            PrintExpr => w!(PrintExpr),
-            LoadBuildClass => w!(LoadBuildClass),
-            UnpackSequence { size } => w!(UnpackSequence, size),
-            UnpackEx { before, after } => w!(UnpackEx, before, after),
-            Unpack => w!(Unpack),
-            FormatValue { spec, .. } => w!(FormatValue, spec), // TODO: write conversion
-            PopException => w!(PopException),
+            } => {
+                write!(f, " ")?;
+                fmt_optional_name(f, name)?;
+                write!(f, " [")?;
+                for (i, symbol) in symbols.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{:?}", symbol)?;
+                }
+                write!(f, "] {}", level)
+            }
+            ImportStar { name, level } => {
+                write!(f, " ")?;
+                fmt_optional_name(f, name)?;
+                write!(f, " {}", level)
+            }
+            LoadName { name, scope } | StoreName { name, scope } => {
+                write!(f, " {:?} {:?}", name, scope)
+            }
+            ImportFrom { name }
+            | DeleteName { name }
+            | StoreAttr { name }
+            | DeleteAttr { name }
+            | LoadAttr { name }
+            | LoadMethod { name } => write!(f, " {:?}", name),
+            LoadConst { value } => {
+                write!(f, " ")?;
+                value.fmt_listing(f, level + 1)
+            }
+            UnaryOperation { op } => write!(f, " {:?}", op),
+            BinaryOperation { op, inplace } => write!(f, " {:?} {}", op, inplace),
+            CompareOperation { op } => write!(f, " {:?}", op),
+            Jump { target }
+            | JumpIf { target }
+            | JumpIfFalse { target }
+            | ForIter { target }
+            | SetupExcept { handler: target }
+            | SetupWith { end: target }
+            | CleanupWith { end: target } => write!(f, " L{}", target),
+            SetupLoop { start, end } => write!(f, " L{} L{}", start, end),
+            MakeFunction { flags } => write!(f, " {}", flags.bits()),
+            CallFunction { typ } | CallMethod { typ } => match typ {
+                CallType::Positional(count) => write!(f, " Positional {}", count),
+                CallType::Keyword(count) => write!(f, " Keyword {}", count),
+                CallType::Ex(has_kwargs) => write!(f, " Ex {}", has_kwargs),
+            },
+            Rotate { amount: value }
+            | Raise { argc: value }
+            | BuildString { size: value }
+            | BuildSlice { size: value }
+            | ListAppend { i: value }
+            | SetAdd { i: value }
+            | MapAdd { i: value }
+            | UnpackSequence { size: value } => write!(f, " {}", value),
+            BuildTuple { size, unpack }
+            | BuildList { size, unpack }
+            | BuildSet { size, unpack }
+            | BuildMap { size, unpack } => write!(f, " {} {}", size, unpack),
+            UnpackEx { before, after } => write!(f, " {} {}", before, after),
+            FormatValue { conversion, spec } => match conversion {
+                Some(conversion) => write!(f, " {:?} {:?}", conversion, spec),
+                None => write!(f, " None {:?}", spec),
+            },
+            StoreSubscript | DeleteSubscript | Pop | Duplicate | GetIter | Pass | Continue
+            | Break | ReturnValue | YieldValue | YieldFrom | PopBlock | PrintExpr
+            | LoadBuildClass | SetupAnnotation | Unpack | PopException => Ok(()),
         }
     }
 }
 
-impl fmt::Display for Constant {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+fn fmt_optional_name(f: &mut fmt::Formatter, name: &Option<String>) -> fmt::Result {
+    match name {
+        Some(name) => write!(f, "{:?}", name),
+        None => write!(f, "None"),
+    }
+}
+
+impl Constant {
+    fn fmt_listing(&self, f: &mut fmt::Formatter, level: usize) -> fmt::Result {
         match self {
             Constant::Integer { value } => write!(f, "{}", value),
-            Constant::Float { value } => write!(f, "{}", value),
-            Constant::Complex { value } => write!(f, "{}", value),
-            Constant::Boolean { value } => write!(f, "{}", value),
-            Constant::String { value } => write!(f, "{:?}", value),
-            Constant::Bytes { value } => write!(f, "{:?}", value),
-            Constant::Code { code } => write!(f, "{:?}", code),
-            Constant::Tuple { elements } => write!(
-                f,
-                "({})",
-                elements
-                    .iter()
-                    .map(|e| format!("{}", e))
-                    .collect::<Vec<_>>()
-                    .join(", ")
-            ),
+            Constant::Float { value } => write!(f, "{:?}", value),
+            Constant::Complex { value } => write!(f, "complex({:?}, {:?})", value.re, value.im),
+            Constant::Boolean { value: true } => write!(f, "True"),
+            Constant::Boolean { value: false } => write!(f, "False"),
+            Constant::String {
+                value,
+                stand_ins: None,
+            } => write!(f, "{:?}", value),
+            Constant::String {
+                value,
+                stand_ins: Some(stand_ins),
+            } => write!(f, "str({:?}, {})", value, stand_ins),
+            Constant::Bytes { value } => {
+                write!(f, "b\"")?;
+                for byte in value {
+                    write!(f, "{}", std::ascii::escape_default(*byte))?;
+                }
+                write!(f, "\"")
+            }
+            Constant::Code { code } => code.fmt_listing(f, level),
+            Constant::Tuple { elements } => {
+                write!(f, "(")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    element.fmt_listing(f, level)?;
+                }
+                if elements.len() == 1 {
+                    write!(f, ",")?;
+                }
+                write!(f, ")")
+            }
             Constant::None => write!(f, "None"),
             Constant::Ellipsis => write!(f, "Ellipsis"),
         }
     }
 }
 
+impl fmt::Display for Constant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_listing(f, 0)
+    }
+}
+
 impl fmt::Debug for CodeObject {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
@@ -1,4 +1,6 @@
 #![doc(html_logo_url = "https://raw.githubusercontent.com/RustPython/RustPython/master/logo.png")]
 #![doc(html_root_url = "https://docs.rs/rustpython-bytecode/")]
 
+pub mod asm;
 pub mod bytecode;
+pub mod verify;
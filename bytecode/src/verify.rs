@@ -0,0 +1,608 @@
+//! Check bytecode that did not come from the compiler before running it.
+//!
+//! Frames trust the code they run: a jump to a missing label, a pop from an
+//! empty stack or a block instruction without its block makes them panic.
+//! The verifier follows every path through a code object to rule these out,
+//! and finds the deepest the value stack gets on the way.
+
+use std::error::Error;
+use std::fmt;
+
+use crate::bytecode::{CallType, CodeObject, Constant, FunctionOpArg, Instruction, Label};
+
+#[derive(Debug, PartialEq)]
+pub struct VerifyError {
+    /// Name of the code object the error is in.
+    pub obj_name: String,
+    pub offset: usize,
+    pub message: String,
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "invalid bytecode in '{}' at offset {}: {}",
+            self.obj_name, self.offset, self.message
+        )
+    }
+}
+
+impl Error for VerifyError {}
+
+type VerifyResult<T> = Result<T, VerifyError>;
+
+/// Verify a code object and the code objects among its constants, and set
+/// their `max_stackdepth`.
+pub fn verify(code: &mut CodeObject) -> VerifyResult<()> {
+    for instruction in code.instructions.iter_mut() {
+        if let Instruction::LoadConst { value } = instruction {
+            verify_constant(value)?;
+        }
+    }
+    code.max_stackdepth = max_stackdepth(code)?;
+    Ok(())
+}
+
+fn verify_constant(constant: &mut Constant) -> VerifyResult<()> {
+    match constant {
+        Constant::Code { code } => verify(code),
+        Constant::Tuple { elements } => elements.iter_mut().try_for_each(verify_constant),
+        _ => Ok(()),
+    }
+}
+
+/// Verify a code object without the code objects among its constants, and
+/// return the largest number of values on its stack.
+pub fn max_stackdepth(code: &CodeObject) -> VerifyResult<usize> {
+    let mut verifier = Verifier {
+        code,
+        states: vec![None; code.instructions.len()],
+        pending: vec![],
+        max_depth: 0,
+        offset: 0,
+    };
+    verifier.run()?;
+    Ok(verifier.max_depth)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum BlockType {
+    Loop { start: Label, end: Label },
+    TryExcept,
+    With { end: Label },
+    ExceptHandler,
+}
+
+/// A block as the frame will push it.
+#[derive(Debug, Clone, PartialEq)]
+struct Block {
+    typ: BlockType,
+    /// The stack depth the frame truncates to when the block is popped.
+    level: usize,
+}
+
+/// What is known about the frame before an instruction runs, the same on
+/// every path to it.
+#[derive(Debug, Clone, PartialEq)]
+struct State {
+    depth: usize,
+    blocks: Vec<Block>,
+}
+
+impl State {
+    /// Pop blocks down to `len` blocks, truncating the stack like the frame.
+    fn pop_blocks(&mut self, len: usize) {
+        for block in self.blocks.drain(len..) {
+            self.depth = self.depth.min(block.level);
+        }
+    }
+}
+
+struct Verifier<'a> {
+    code: &'a CodeObject,
+    states: Vec<Option<State>>,
+    /// Offsets whose state is known but that have not been checked yet.
+    pending: Vec<usize>,
+    max_depth: usize,
+    /// The instruction being checked.
+    offset: usize,
+}
+
+impl<'a> Verifier<'a> {
+    fn error<T>(&self, message: String) -> VerifyResult<T> {
+        Err(VerifyError {
+            obj_name: self.code.obj_name.clone(),
+            offset: self.offset,
+            message,
+        })
+    }
+
+    fn run(&mut self) -> VerifyResult<()> {
+        if self.code.instructions.is_empty() {
+            return self.error("code has no instructions".to_string());
+        }
+        for (offset, instruction) in self.code.instructions.iter().enumerate() {
+            self.offset = offset;
+            for label in labels(instruction) {
+                self.target(label)?;
+            }
+        }
+
+        // Sending a value into a generator pushes it, also to start it.
+        let depth = if self.code.is_generator { 1 } else { 0 };
+        self.offset = 0;
+        self.flow(
+            0,
+            State {
+                depth,
+                blocks: vec![],
+            },
+        )?;
+        while let Some(offset) = self.pending.pop() {
+            self.offset = offset;
+            let state = self.states[offset].clone().unwrap();
+            self.step(state)?;
+        }
+        Ok(())
+    }
+
+    fn target(&self, label: Label) -> VerifyResult<usize> {
+        match self.code.label_map.get(&label) {
+            Some(offset) if *offset < self.code.instructions.len() => Ok(*offset),
+            Some(_) => self.error(format!("label L{} points past the end of the code", label)),
+            None => self.error(format!("jump to undefined label L{}", label)),
+        }
+    }
+
+    /// Record that `state` reaches the instruction at `offset`.
+    fn flow(&mut self, offset: usize, state: State) -> VerifyResult<()> {
+        if offset >= self.code.instructions.len() {
+            return self.error("execution runs past the end of the code".to_string());
+        }
+        match &self.states[offset] {
+            None => {
+                self.max_depth = self.max_depth.max(state.depth);
+                self.states[offset] = Some(state);
+                self.pending.push(offset);
+                Ok(())
+            }
+            Some(known) if known.depth != state.depth => self.error(format!(
+                "stack depth {} at offset {} differs from depth {} on another path",
+                state.depth, offset, known.depth
+            )),
+            Some(known) if known.blocks != state.blocks => self.error(format!(
+                "blocks at offset {} differ from the blocks on another path",
+                offset
+            )),
+            Some(_) => Ok(()),
+        }
+    }
+
+    fn jump(&mut self, label: Label, state: State) -> VerifyResult<()> {
+        let offset = self.target(label)?;
+        self.flow(offset, state)
+    }
+
+    fn pop(&self, state: &mut State, count: usize) -> VerifyResult<()> {
+        if state.depth < count {
+            let instruction = &self.code.instructions[self.offset];
+            let values = if count == 1 { "value" } else { "values" };
+            return self.error(format!(
+                "{} needs {} {} on the stack, found {}",
+                instruction.name(),
+                count,
+                values,
+                state.depth
+            ));
+        }
+        state.depth -= count;
+        Ok(())
+    }
+
+    /// Check that the stack holds `count` values without popping them.
+    fn peek(&self, state: &State, count: usize) -> VerifyResult<()> {
+        self.pop(&mut state.clone(), count)
+    }
+
+    fn innermost_loop(&self, state: &State) -> VerifyResult<usize> {
+        match state
+            .blocks
+            .iter()
+            .rposition(|block| matches!(block.typ, BlockType::Loop { .. }))
+        {
+            Some(index) => Ok(index),
+            None => self.error(format!(
+                "{} outside of a loop",
+                self.code.instructions[self.offset].name()
+            )),
+        }
+    }
+
+    fn pop_block(&self, state: &mut State, expected: Option<BlockType>) -> VerifyResult<()> {
+        let name = self.code.instructions[self.offset].name();
+        match (state.blocks.last(), expected) {
+            (None, _) => return self.error(format!("{} without a block", name)),
+            (Some(block), Some(expected)) if block.typ != expected => {
+                return self.error(format!(
+                    "{} does not match the innermost block {:?}",
+                    name, block.typ
+                ))
+            }
+            _ => {}
+        }
+        state.pop_blocks(state.blocks.len() - 1);
+        Ok(())
+    }
+
+    /// Check one instruction and pass its state on to the instructions that
+    /// can run next.
+    fn step(&mut self, mut state: State) -> VerifyResult<()> {
+        use self::Instruction::*;
+
+        let instruction = &self.code.instructions[self.offset];
+        let (pops, pushes) = match instruction {
+            ImportStar { .. }
+            | DeleteName { .. }
+            | Pass
+            | SetupAnnotation
+            | SetupLoop { .. }
+            | SetupExcept { .. }
+            | CleanupWith { .. }
+            | PopBlock
+            | PopException => (0, 0),
+            Import { .. } | LoadName { .. } | LoadConst { .. } | LoadBuildClass => (0, 1),
+            StoreName { .. } | DeleteAttr { .. } | Pop | PrintExpr => (1, 0),
+            UnaryOperation { .. }
+            | LoadAttr { .. }
+            | GetIter
+            | YieldValue
+            | FormatValue { .. }
+            | SetupWith { .. } => (1, 1),
+            Duplicate | LoadMethod { .. } => (1, 2),
+            DeleteSubscript | StoreAttr { .. } => (2, 0),
+            BinaryOperation { .. } | CompareOperation { .. } => (2, 1),
+            StoreSubscript => (3, 0),
+            JumpIf { .. } | JumpIfFalse { .. } => (1, 0),
+            Rotate { amount } => {
+                if *amount < 2 {
+                    return self.error("Rotate needs an amount of at least 2".to_string());
+                }
+                (*amount, *amount)
+            }
+            MakeFunction { flags } => {
+                let extra = [
+                    FunctionOpArg::HAS_DEFAULTS,
+                    FunctionOpArg::HAS_KW_ONLY_DEFAULTS,
+                    FunctionOpArg::HAS_ANNOTATIONS,
+                ]
+                .iter()
+                .filter(|flag| flags.contains(**flag))
+                .count();
+                (2 + extra, 1)
+            }
+            CallFunction { typ } => match typ {
+                CallType::Positional(count) => (count + 1, 1),
+                CallType::Keyword(count) => (count + 2, 1),
+                CallType::Ex(has_kwargs) => (2 + *has_kwargs as usize, 1),
+            },
+            CallMethod { typ } => match typ {
+                CallType::Positional(count) => (count + 2, 1),
+                CallType::Keyword(count) => (count + 3, 1),
+                CallType::Ex(has_kwargs) => (3 + *has_kwargs as usize, 1),
+            },
+            BuildString { size }
+            | BuildTuple { size, .. }
+            | BuildList { size, .. }
+            | BuildSet { size, .. } => (*size, 1),
+            BuildMap { size, unpack } => (if *unpack { *size } else { 2 * size }, 1),
+            BuildSlice { size } => {
+                if *size != 2 && *size != 3 {
+                    return self.error(format!("BuildSlice of {} values", size));
+                }
+                (*size, 1)
+            }
+            ImportFrom { .. } => {
+                self.peek(&state, 1)?;
+                (0, 1)
+            }
+            ListAppend { i } | SetAdd { i } => {
+                self.peek(&state, i + 1)?;
+                (1, 0)
+            }
+            MapAdd { i } => {
+                self.peek(&state, i + 2)?;
+                (2, 0)
+            }
+            UnpackSequence { size } => (1, *size),
+            UnpackEx { before, after } => (1, before + after + 1),
+            Unpack => return self.error("the stack effect of Unpack is not known".to_string()),
+            ForIter { target } => {
+                self.peek(&state, 1)?;
+                let mut exhausted = state.clone();
+                exhausted.depth -= 1;
+                self.jump(*target, exhausted)?;
+                (0, 1)
+            }
+            // Pops the value sent in, and replaces the iterator with the result
+            // once it is exhausted. Until then, the frame runs it again after
+            // every value sent in.
+            YieldFrom => (2, 1),
+            ReturnValue => {
+                self.pop(&mut state, 1)?;
+                return Ok(());
+            }
+            Raise { argc } => {
+                if *argc > 2 {
+                    return self.error(format!("Raise with {} arguments", argc));
+                }
+                self.pop(&mut state, *argc)?;
+                return Ok(());
+            }
+            Jump { target } => return self.jump(*target, state),
+            Break => {
+                let index = self.innermost_loop(&state)?;
+                let end = match state.blocks[index].typ {
+                    BlockType::Loop { end, .. } => end,
+                    _ => unreachable!(),
+                };
+                state.pop_blocks(index);
+                return self.jump(end, state);
+            }
+            Continue => {
+                let index = self.innermost_loop(&state)?;
+                let start = match state.blocks[index].typ {
+                    BlockType::Loop { start, .. } => start,
+                    _ => unreachable!(),
+                };
+                state.pop_blocks(index + 1);
+                return self.jump(start, state);
+            }
+        };
+        self.pop(&mut state, pops)?;
+
+        match instruction {
+            JumpIf { target } | JumpIfFalse { target } => self.jump(*target, state.clone())?,
+            SetupLoop { start, end } => state.blocks.push(Block {
+                typ: BlockType::Loop {
+                    start: *start,
+                    end: *end,
+                },
+                level: state.depth,
+            }),
+            SetupExcept { handler } => {
+                // The frame pushes the exception for its handler.
+                let mut handling = state.clone();
+                handling.blocks.push(Block {
+                    typ: BlockType::ExceptHandler,
+                    level: state.depth,
+                });
+                handling.depth += 1;
+                self.jump(*handler, handling)?;
+                state.blocks.push(Block {
+                    typ: BlockType::TryExcept,
+                    level: state.depth,
+                });
+            }
+            SetupWith { end } => {
+                // An exception that `__exit__` suppresses continues at `end`.
+                self.jump(*end, state.clone())?;
+                state.blocks.push(Block {
+                    typ: BlockType::With { end: *end },
+                    level: state.depth,
+                });
+            }
+            CleanupWith { end } => {
+                self.pop_block(&mut state, Some(BlockType::With { end: *end }))?
+            }
+            PopBlock => self.pop_block(&mut state, None)?,
+            PopException => self.pop_block(&mut state, Some(BlockType::ExceptHandler))?,
+            _ => {}
+        }
+
+        state.depth += pushes;
+        let next = self.offset + 1;
+        self.flow(next, state)
+    }
+}
+
+/// The labels an instruction refers to.
+fn labels(instruction: &Instruction) -> Vec<Label> {
+    match instruction {
+        Instruction::Jump { target }
+        | Instruction::JumpIf { target }
+        | Instruction::JumpIfFalse { target }
+        | Instruction::ForIter { target }
+        | Instruction::SetupExcept { handler: target }
+        | Instruction::SetupWith { end: target }
+        | Instruction::CleanupWith { end: target } => vec![*target],
+        Instruction::SetupLoop { start, end } => vec![*start, *end],
+        _ => vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble;
+
+    fn check(listing: &str) -> VerifyResult<usize> {
+        max_stackdepth(&assemble(listing).unwrap())
+    }
+
+    fn error(listing: &str) -> String {
+        check(listing).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn test_max_stackdepth() {
+        let listing = r#"
+            code "<module>"
+                LoadName "f" Local
+                LoadConst 1
+                LoadConst 2
+                LoadConst 3
+                BuildTuple 2 false
+                CallFunction Positional 2
+                Pop
+                LoadConst None
+                ReturnValue
+            end
+        "#;
+        assert_eq!(check(listing), Ok(4));
+    }
+
+    #[test]
+    fn test_method_call() {
+        let listing = r#"
+            code "<module>"
+                LoadName "xs" Local
+                LoadMethod "insert"
+                LoadConst 0
+                LoadConst 1
+                CallMethod Positional 2
+                ReturnValue
+            end
+        "#;
+        assert_eq!(check(listing), Ok(4));
+    }
+
+    #[test]
+    fn test_loops_and_handlers() {
+        let listing = r#"
+            code "<module>"
+                SetupExcept L0
+                SetupLoop L1 L3
+                LoadName "xs" Local
+                GetIter
+            L1:
+                ForIter L2
+                StoreName "x" Local
+                Continue
+            L2:
+                PopBlock
+            L3:
+                PopBlock
+                Jump L4
+            L0:
+                Pop
+                PopException
+            L4:
+                LoadConst None
+                ReturnValue
+            end
+        "#;
+        assert_eq!(check(listing), Ok(2));
+    }
+
+    #[test]
+    fn test_generator_starts_with_sent_value() {
+        let listing = r#"
+            code "g"
+                .generator
+                LoadConst 1
+                YieldValue
+                Pop
+                Pop
+                LoadConst None
+                ReturnValue
+            end
+        "#;
+        assert_eq!(check(listing), Ok(2));
+    }
+
+    #[test]
+    fn test_invalid_code() {
+        assert_eq!(
+            error(
+                r#"code "f"
+                    LoadConst 1
+                    BinaryOperation Add false
+                    ReturnValue
+                end"#
+            ),
+            "invalid bytecode in 'f' at offset 1: BinaryOperation needs 2 values on the stack, found 1"
+        );
+        assert_eq!(
+            error(
+                r#"code "f"
+                    LoadConst True
+                    JumpIf L0
+                    LoadConst 1
+                L0:
+                    LoadConst None
+                    ReturnValue
+                end"#
+            ),
+            "invalid bytecode in 'f' at offset 2: \
+             stack depth 1 at offset 3 differs from depth 0 on another path"
+        );
+        assert_eq!(
+            error(
+                r#"code "f"
+                    LoadConst None
+                end"#
+            ),
+            "invalid bytecode in 'f' at offset 0: execution runs past the end of the code"
+        );
+        assert_eq!(
+            error(
+                r#"code "f"
+                    Jump L0
+                L0:
+                end"#
+            ),
+            "invalid bytecode in 'f' at offset 0: label L0 points past the end of the code"
+        );
+        assert_eq!(
+            error(
+                r#"code "f"
+                    Break
+                end"#
+            ),
+            "invalid bytecode in 'f' at offset 0: Break outside of a loop"
+        );
+        assert_eq!(
+            error(
+                r#"code "f"
+                    SetupExcept L0
+                    PopException
+                L0:
+                    Raise 0
+                end"#
+            ),
+            "invalid bytecode in 'f' at offset 1: \
+             PopException does not match the innermost block TryExcept"
+        );
+        assert_eq!(
+            error(
+                r#"code "f"
+                    PopBlock
+                end"#
+            ),
+            "invalid bytecode in 'f' at offset 0: PopBlock without a block"
+        );
+        assert_eq!(
+            error(r#"code "f" end"#),
+            "invalid bytecode in 'f' at offset 0: code has no instructions"
+        );
+    }
+
+    #[test]
+    fn test_verify_nested_code() {
+        let mut code = assemble(
+            r#"code "<module>"
+                LoadConst (code "f"
+                    Pop
+                    ReturnValue
+                end,)
+                ReturnValue
+            end"#,
+        )
+        .unwrap();
+        assert_eq!(
+            verify(&mut code).unwrap_err().to_string(),
+            "invalid bytecode in 'f' at offset 0: Pop needs 1 value on the stack, found 0"
+        );
+    }
+}
@@ -7,8 +7,10 @@
 
 use crate::error::{CompileError, CompileErrorType};
 use crate::symboltable::{make_symbol_table, statements_to_symbol_table, Symbol, SymbolScope};
+use crate::unparse::unparse_expression;
 use num_complex::Complex64;
 use rustpython_bytecode::bytecode::{self, CallType, CodeObject, Instruction, Varargs};
+use rustpython_bytecode::verify;
 use rustpython_parser::{ast, parser};
 
 struct Compiler {
@@ -20,6 +22,7 @@ struct Compiler {
     current_qualified_path: Option<String>,
     in_loop: bool,
     in_function_def: bool,
+    future_annotations: bool,
     optimize: u8,
 }
 
@@ -29,6 +32,19 @@ pub fn compile(
     mode: &Mode,
     source_path: String,
     optimize: u8,
+) -> Result<CodeObject, CompileError> {
+    compile_source(source, mode, source_path.clone(), optimize).map_err(|mut error| {
+        error.update_statement_info(source);
+        error.update_source_path(&source_path);
+        error
+    })
+}
+
+fn compile_source(
+    source: &str,
+    mode: &Mode,
+    source_path: String,
+    optimize: u8,
 ) -> Result<CodeObject, CompileError> {
     match mode {
         Mode::Exec => {
@@ -56,7 +72,7 @@ fn with_compiler(
     compiler.source_path = Some(source_path);
     compiler.push_new_code_object("<module>".to_string());
     f(&mut compiler)?;
-    let code = compiler.pop_code_object();
+    let code = compiler.pop_code_object()?;
     trace!("Compilation completed: {:?}", code);
     Ok(code)
 }
@@ -103,12 +119,6 @@ pub enum Mode {
     Single,
 }
 
-#[derive(Clone, Copy)]
-enum EvalContext {
-    Statement,
-    Expression,
-}
-
 type Label = usize;
 
 impl Default for Compiler {
@@ -124,10 +134,12 @@ impl Compiler {
             scope_stack: Vec::new(),
             nxt_label: 0,
             source_path: None,
-            current_source_location: ast::Location::default(),
+            // Module code starts at line 1, as in CPython.
+            current_source_location: ast::Location::new(1, 1),
             current_qualified_path: None,
             in_loop: false,
             in_function_def: false,
+            future_annotations: false,
             optimize,
         }
     }
@@ -145,9 +157,42 @@ impl Compiler {
         ));
     }
 
-    fn pop_code_object(&mut self) -> CodeObject {
+    fn pop_code_object(&mut self) -> Result<CodeObject, CompileError> {
         // self.scope_stack.pop().unwrap();
-        self.code_object_stack.pop().unwrap()
+        let mut code = self.code_object_stack.pop().unwrap();
+        if self.future_annotations {
+            code.flags |= bytecode::CodeFlags::FUTURE_ANNOTATIONS;
+        }
+        code.max_stackdepth = verify::max_stackdepth(&code).map_err(|err| CompileError {
+            statement: None,
+            error: CompileErrorType::InvalidBytecode(err),
+            location: self.current_source_location.clone(),
+            source_path: None,
+        })?;
+        Ok(code)
+    }
+
+    /// Pops the code object of a function, lambda or class body together
+    /// with its symbol scope, whose cell and free variables it records.
+    fn pop_code_object_and_scope(&mut self) -> Result<CodeObject, CompileError> {
+        let mut code = self.pop_code_object()?;
+        let scope = self.scope_stack.last().unwrap();
+        code.cellvars = scope.cell_names();
+        code.freevars = scope.free_names();
+        self.leave_scope();
+        Ok(code)
+    }
+
+    /// Flags for the code object of a function, lambda or comprehension
+    /// about to be pushed.
+    fn function_code_flags(&self) -> bytecode::CodeFlags {
+        let mut flags = bytecode::CodeFlags::OPTIMIZED | bytecode::CodeFlags::NEW_LOCALS;
+        if let Some(code) = self.code_object_stack.last() {
+            if code.flags.contains(bytecode::CodeFlags::NEW_LOCALS) {
+                flags |= bytecode::CodeFlags::NESTED;
+            }
+        }
+        flags
     }
 
     fn compile_program(
@@ -157,7 +202,12 @@ impl Compiler {
     ) -> Result<(), CompileError> {
         let size_before = self.code_object_stack.len();
         self.scope_stack.push(symbol_scope);
-        self.compile_statements(&program.statements)?;
+        let statements = self.take_docstring(&program.statements);
+        if find_annotations(statements) {
+            self.emit(Instruction::SetupAnnotation);
+        }
+        self.store_docstring();
+        self.compile_statements(statements)?;
         assert!(self.code_object_stack.len() == size_before);
 
         // Emit None at end:
@@ -175,6 +225,10 @@ impl Compiler {
     ) -> Result<(), CompileError> {
         self.scope_stack.push(symbol_scope);
 
+        if find_annotations(&program.statements) {
+            self.emit(Instruction::SetupAnnotation);
+        }
+
         let mut emitted_return = false;
 
         for (i, statement) in program.statements.iter().enumerate() {
@@ -215,11 +269,14 @@ impl Compiler {
         self.scope_stack.push(symbol_table);
         for statement in statements {
             if let ast::StatementType::Expression { ref expression } = statement.node {
+                self.set_source_location(&statement.location);
                 self.compile_expression(expression)?;
             } else {
                 return Err(CompileError {
+                    statement: None,
                     error: CompileErrorType::ExpectExpr,
                     location: statement.location.clone(),
+                    source_path: None,
                 });
             }
         }
@@ -290,6 +347,12 @@ impl Compiler {
             } => {
                 let import_star = names.iter().any(|n| n.symbol == "*");
 
+                if module.as_ref().map(String::as_str) == Some("__future__")
+                    && names.iter().any(|n| n.symbol == "annotations")
+                {
+                    self.future_annotations = true;
+                }
+
                 if import_star {
                     // from .... import *
                     self.emit(Instruction::ImportStar {
@@ -340,14 +403,14 @@ impl Compiler {
                 match orelse {
                     None => {
                         // Only if:
-                        self.compile_test(test, None, Some(end_label), EvalContext::Statement)?;
+                        self.compile_test(test, None, Some(end_label))?;
                         self.compile_statements(body)?;
                         self.set_label(end_label);
                     }
                     Some(statements) => {
                         // if - else:
                         let else_label = self.new_label();
-                        self.compile_test(test, None, Some(else_label), EvalContext::Statement)?;
+                        self.compile_test(test, None, Some(else_label))?;
                         self.compile_statements(body)?;
                         self.emit(Instruction::Jump { target: end_label });
 
@@ -369,7 +432,7 @@ impl Compiler {
 
                 self.set_label(start_label);
 
-                self.compile_test(test, None, Some(else_label), EvalContext::Statement)?;
+                self.compile_test(test, None, Some(else_label))?;
 
                 let was_in_loop = self.in_loop;
                 self.in_loop = true;
@@ -393,8 +456,12 @@ impl Compiler {
                 if *is_async {
                     unimplemented!("async with");
                 } else {
-                    let end_label = self.new_label();
+                    // Each item gets its own end label, so that when an inner
+                    // `__exit__` suppresses an exception the outer ones still run.
+                    let mut end_labels = vec![];
                     for item in items {
+                        let end_label = self.new_label();
+                        end_labels.push(end_label);
                         self.compile_expression(&item.context_expr)?;
                         self.emit(Instruction::SetupWith { end: end_label });
                         match &item.optional_vars {
@@ -408,10 +475,10 @@ impl Compiler {
                     }
 
                     self.compile_statements(body)?;
-                    for _ in 0..items.len() {
+                    for end_label in end_labels.into_iter().rev() {
                         self.emit(Instruction::CleanupWith { end: end_label });
+                        self.set_label(end_label);
                     }
-                    self.set_label(end_label);
                 }
             }
             For {
@@ -475,7 +542,7 @@ impl Compiler {
                 // if some flag, ignore all assert statements!
                 if self.optimize == 0 {
                     let end_label = self.new_label();
-                    self.compile_test(test, Some(end_label), None, EvalContext::Statement)?;
+                    self.compile_test(test, Some(end_label), None)?;
                     self.emit(Instruction::LoadName {
                         name: String::from("AssertionError"),
                         scope: bytecode::NameScope::Local,
@@ -500,8 +567,10 @@ impl Compiler {
             Break => {
                 if !self.in_loop {
                     return Err(CompileError {
+                        statement: None,
                         error: CompileErrorType::InvalidBreak,
                         location: statement.location.clone(),
+                        source_path: None,
                     });
                 }
                 self.emit(Instruction::Break);
@@ -509,8 +578,10 @@ impl Compiler {
             Continue => {
                 if !self.in_loop {
                     return Err(CompileError {
+                        statement: None,
                         error: CompileErrorType::InvalidContinue,
                         location: statement.location.clone(),
+                        source_path: None,
                     });
                 }
                 self.emit(Instruction::Continue);
@@ -518,8 +589,10 @@ impl Compiler {
             Return { value } => {
                 if !self.in_function_def {
                     return Err(CompileError {
+                        statement: None,
                         error: CompileErrorType::InvalidReturn,
                         location: statement.location.clone(),
+                        source_path: None,
                     });
                 }
                 match value {
@@ -553,6 +626,12 @@ impl Compiler {
                 self.compile_op(op, true);
                 self.compile_store(target)?;
             }
+            AnnAssign {
+                target,
+                annotation,
+                value,
+                simple,
+            } => self.compile_annotated_assign(target, annotation, value, *simple)?,
             Delete { targets } => {
                 for target in targets {
                     self.compile_delete(target)?;
@@ -590,8 +669,10 @@ impl Compiler {
             }
             _ => {
                 return Err(CompileError {
+                    statement: None,
                     error: CompileErrorType::Delete(expression.name()),
                     location: self.current_source_location.clone(),
+                    source_path: None,
                 });
             }
         }
@@ -602,6 +683,7 @@ impl Compiler {
         &mut self,
         name: &str,
         args: &ast::Parameters,
+        returns: Option<&ast::Expression>,
     ) -> Result<bytecode::FunctionOpArg, CompileError> {
         let have_defaults = !args.defaults.is_empty();
         if have_defaults {
@@ -622,6 +704,7 @@ impl Compiler {
                 self.emit(Instruction::LoadConst {
                     value: bytecode::Constant::String {
                         value: kw.arg.clone(),
+                        stand_ins: None,
                     },
                 });
                 self.compile_expression(default)?;
@@ -635,7 +718,54 @@ impl Compiler {
             });
         }
 
+        // Prepare type annotations, in the order of the signature:
+        let mut num_annotations = 0;
+
+        let mut parameters: Vec<&ast::Parameter> = args.args.iter().collect();
+        if let ast::Varargs::Named(vararg) = &args.vararg {
+            parameters.push(vararg);
+        }
+        parameters.extend(args.kwonlyargs.iter());
+        if let ast::Varargs::Named(kwarg) = &args.kwarg {
+            parameters.push(kwarg);
+        }
+
+        for parameter in parameters {
+            if let Some(annotation) = &parameter.annotation {
+                // key:
+                self.emit(Instruction::LoadConst {
+                    value: bytecode::Constant::String {
+                        value: parameter.arg.to_string(),
+                        stand_ins: None,
+                    },
+                });
+                // value:
+                self.compile_annotation(annotation)?;
+                num_annotations += 1;
+            }
+        }
+
+        // Return annotation:
+        if let Some(annotation) = returns {
+            self.emit(Instruction::LoadConst {
+                value: bytecode::Constant::String {
+                    value: "return".to_string(),
+                    stand_ins: None,
+                },
+            });
+            self.compile_annotation(annotation)?;
+            num_annotations += 1;
+        }
+
+        if num_annotations > 0 {
+            self.emit(Instruction::BuildMap {
+                size: num_annotations,
+                unpack: false,
+            });
+        }
+
         let line_number = self.get_source_line_number();
+        let code_flags = self.function_code_flags();
         self.code_object_stack.push(CodeObject::new(
             args.args.iter().map(|a| a.arg.clone()).collect(),
             compile_varargs(&args.vararg),
@@ -645,6 +775,7 @@ impl Compiler {
             line_number,
             name.to_string(),
         ));
+        self.current_code_object().flags = code_flags;
         self.enter_scope();
 
         let mut flags = bytecode::FunctionOpArg::empty();
@@ -654,6 +785,9 @@ impl Compiler {
         if num_kw_only_defaults > 0 {
             flags |= bytecode::FunctionOpArg::HAS_KW_ONLY_DEFAULTS;
         }
+        if num_annotations > 0 {
+            flags |= bytecode::FunctionOpArg::HAS_ANNOTATIONS;
+        }
 
         Ok(flags)
     }
@@ -782,7 +916,7 @@ impl Compiler {
         args: &ast::Parameters,
         body: &[ast::Statement],
         decorator_list: &[ast::Expression],
-        returns: &Option<ast::Expression>, // TODO: use type hint somehow..
+        returns: &Option<ast::Expression>,
     ) -> Result<(), CompileError> {
         // Create bytecode for this function:
         // remember to restore self.in_loop to the original after the function is compiled
@@ -792,15 +926,15 @@ impl Compiler {
         self.in_function_def = true;
 
         let old_qualified_path = self.current_qualified_path.clone();
-        let qualified_name = self.create_qualified_name(name, "");
-        self.current_qualified_path = Some(self.create_qualified_name(name, ".<locals>"));
+        let qualified_name = self.create_qualified_name(name);
+        self.current_qualified_path = Some(format!("{}.<locals>", qualified_name));
 
+        let location = self.current_source_location.clone();
         self.prepare_decorators(decorator_list)?;
 
-        let mut flags = self.enter_function(name, args)?;
-
-        let (new_body, doc_str) = get_doc(body);
+        let flags = self.enter_function(name, args, returns.as_ref())?;
 
+        let new_body = self.take_docstring(body);
         self.compile_statements(new_body)?;
 
         // Emit None at end:
@@ -808,44 +942,9 @@ impl Compiler {
             value: bytecode::Constant::None,
         });
         self.emit(Instruction::ReturnValue);
-        let code = self.pop_code_object();
-        self.leave_scope();
-
-        // Prepare type annotations:
-        let mut num_annotations = 0;
-
-        // Return annotation:
-        if let Some(annotation) = returns {
-            // key:
-            self.emit(Instruction::LoadConst {
-                value: bytecode::Constant::String {
-                    value: "return".to_string(),
-                },
-            });
-            // value:
-            self.compile_expression(annotation)?;
-            num_annotations += 1;
-        }
-
-        for arg in args.args.iter() {
-            if let Some(annotation) = &arg.annotation {
-                self.emit(Instruction::LoadConst {
-                    value: bytecode::Constant::String {
-                        value: arg.arg.to_string(),
-                    },
-                });
-                self.compile_expression(&annotation)?;
-                num_annotations += 1;
-            }
-        }
-
-        if num_annotations > 0 {
-            flags |= bytecode::FunctionOpArg::HAS_ANNOTATIONS;
-            self.emit(Instruction::BuildMap {
-                size: num_annotations,
-                unpack: false,
-            });
-        }
+        let code = self.pop_code_object_and_scope()?;
+        // The function object is made on the line of the `def`.
+        self.set_source_location(&location);
 
         self.emit(Instruction::LoadConst {
             value: bytecode::Constant::Code {
@@ -855,12 +954,12 @@ impl Compiler {
         self.emit(Instruction::LoadConst {
             value: bytecode::Constant::String {
                 value: qualified_name,
+                stand_ins: None,
             },
         });
 
         // Turn code object into function object:
         self.emit(Instruction::MakeFunction { flags });
-        self.store_docstring(doc_str);
         self.apply_decorators(decorator_list);
 
         self.store_name(name);
@@ -880,12 +979,15 @@ impl Compiler {
         decorator_list: &[ast::Expression],
     ) -> Result<(), CompileError> {
         let was_in_loop = self.in_loop;
+        let was_in_function_def = self.in_function_def;
         self.in_loop = false;
+        self.in_function_def = false;
 
         let old_qualified_path = self.current_qualified_path.clone();
-        let qualified_name = self.create_qualified_name(name, "");
+        let qualified_name = self.create_qualified_name(name);
         self.current_qualified_path = Some(qualified_name.clone());
 
+        let location = self.current_source_location.clone();
         self.prepare_decorators(decorator_list)?;
         self.emit(Instruction::LoadBuildClass);
         let line_number = self.get_source_line_number();
@@ -900,7 +1002,7 @@ impl Compiler {
         ));
         self.enter_scope();
 
-        let (new_body, doc_str) = get_doc(body);
+        let new_body = self.take_docstring(body);
 
         self.emit(Instruction::LoadName {
             name: "__name__".to_string(),
@@ -910,14 +1012,28 @@ impl Compiler {
             name: "__module__".to_string(),
             scope: bytecode::NameScope::Local,
         });
+        self.emit(Instruction::LoadConst {
+            value: bytecode::Constant::String {
+                value: qualified_name,
+                stand_ins: None,
+            },
+        });
+        self.emit(Instruction::StoreName {
+            name: "__qualname__".to_string(),
+            scope: bytecode::NameScope::Local,
+        });
+        self.store_docstring();
+        if find_annotations(new_body) {
+            self.emit(Instruction::SetupAnnotation);
+        }
         self.compile_statements(new_body)?;
         self.emit(Instruction::LoadConst {
             value: bytecode::Constant::None,
         });
         self.emit(Instruction::ReturnValue);
 
-        let code = self.pop_code_object();
-        self.leave_scope();
+        let code = self.pop_code_object_and_scope()?;
+        self.set_source_location(&location);
 
         self.emit(Instruction::LoadConst {
             value: bytecode::Constant::Code {
@@ -927,6 +1043,7 @@ impl Compiler {
         self.emit(Instruction::LoadConst {
             value: bytecode::Constant::String {
                 value: name.to_string(),
+                stand_ins: None,
             },
         });
 
@@ -937,7 +1054,8 @@ impl Compiler {
 
         self.emit(Instruction::LoadConst {
             value: bytecode::Constant::String {
-                value: qualified_name,
+                value: name.to_string(),
+                stand_ins: None,
             },
         });
 
@@ -951,6 +1069,7 @@ impl Compiler {
                 if let Some(name) = &keyword.name {
                     kwarg_names.push(bytecode::Constant::String {
                         value: name.to_string(),
+                        stand_ins: None,
                     });
                 } else {
                     // This means **kwargs!
@@ -973,34 +1092,141 @@ impl Compiler {
             });
         }
 
-        self.store_docstring(doc_str);
         self.apply_decorators(decorator_list);
 
         self.store_name(name);
         self.current_qualified_path = old_qualified_path;
         self.in_loop = was_in_loop;
+        self.in_function_def = was_in_function_def;
         Ok(())
     }
 
-    fn store_docstring(&mut self, doc_str: Option<String>) {
-        if let Some(doc_string) = doc_str {
-            // Duplicate top of stack (the function or class object)
-            self.emit(Instruction::Duplicate);
+    /// Split the docstring off a body and keep it on the code object being
+    /// compiled. Under `-OO` docstrings are dropped.
+    fn take_docstring<'a>(&mut self, body: &'a [ast::Statement]) -> &'a [ast::Statement] {
+        let (body, doc_str) = get_doc(body);
+        if self.optimize < 2 {
+            if let Some((doc, stand_ins)) = doc_str {
+                let code = self.current_code_object();
+                code.doc = Some(doc);
+                code.doc_stand_ins = stand_ins;
+            }
+        }
+        body
+    }
 
-            // Doc string value:
+    /// Bind the docstring of a module or class body to `__doc__`.
+    fn store_docstring(&mut self) {
+        let code = self.current_code_object();
+        if let Some(doc_string) = code.doc.clone() {
+            let stand_ins = code.doc_stand_ins;
             self.emit(Instruction::LoadConst {
                 value: bytecode::Constant::String {
-                    value: doc_string.to_string(),
+                    value: doc_string,
+                    stand_ins,
                 },
             });
-
-            self.emit(Instruction::Rotate { amount: 2 });
-            self.emit(Instruction::StoreAttr {
+            self.emit(Instruction::StoreName {
                 name: "__doc__".to_string(),
+                scope: bytecode::NameScope::Local,
             });
         }
     }
 
+    /// Push the value of an annotation, which is its source text
+    /// under `from __future__ import annotations`.
+    fn compile_annotation(&mut self, annotation: &ast::Expression) -> Result<(), CompileError> {
+        if self.future_annotations {
+            self.emit(Instruction::LoadConst {
+                value: bytecode::Constant::String {
+                    value: unparse_expression(annotation),
+                    stand_ins: None,
+                },
+            });
+        } else {
+            self.compile_expression(annotation)?;
+        }
+        Ok(())
+    }
+
+    fn compile_annotated_assign(
+        &mut self,
+        target: &ast::Expression,
+        annotation: &ast::Expression,
+        value: &Option<ast::Expression>,
+        simple: bool,
+    ) -> Result<(), CompileError> {
+        let simple_name = match &target.node {
+            ast::ExpressionType::Identifier { name } if simple => Some(name),
+            ast::ExpressionType::Identifier { .. } => Option::None,
+            ast::ExpressionType::Attribute { .. } | ast::ExpressionType::Subscript { .. } => {
+                Option::None
+            }
+            _ => {
+                let error = match &target.node {
+                    ast::ExpressionType::Tuple { .. } => {
+                        "only single target (not tuple) can be annotated"
+                    }
+                    ast::ExpressionType::List { .. } => {
+                        "only single target (not list) can be annotated"
+                    }
+                    _ => "illegal target for annotation",
+                };
+                return Err(CompileError {
+                    statement: None,
+                    error: CompileErrorType::SyntaxError(error.to_string()),
+                    location: target.location.clone(),
+                    source_path: None,
+                });
+            }
+        };
+
+        if let Some(value) = value {
+            self.compile_expression(value)?;
+            self.compile_store(target)?;
+        } else {
+            // Without a value, the parts of the target are still evaluated:
+            match &target.node {
+                ast::ExpressionType::Attribute { value, .. } => {
+                    self.compile_expression(value)?;
+                    self.emit(Instruction::Pop);
+                }
+                ast::ExpressionType::Subscript { a, b } => {
+                    self.compile_expression(a)?;
+                    self.emit(Instruction::Pop);
+                    self.compile_expression(b)?;
+                    self.emit(Instruction::Pop);
+                }
+                _ => {}
+            }
+        }
+
+        // Annotations are only evaluated in a module or class body.
+        if self.in_function_def {
+            return Ok(());
+        }
+
+        if let Some(name) = simple_name {
+            // __annotations__[name] = annotation
+            self.compile_annotation(annotation)?;
+            self.emit(Instruction::LoadName {
+                name: "__annotations__".to_string(),
+                scope: bytecode::NameScope::Local,
+            });
+            self.emit(Instruction::LoadConst {
+                value: bytecode::Constant::String {
+                    value: name.to_string(),
+                    stand_ins: None,
+                },
+            });
+            self.emit(Instruction::StoreSubscript);
+        } else if !self.future_annotations {
+            self.compile_expression(annotation)?;
+            self.emit(Instruction::Pop);
+        }
+        Ok(())
+    }
+
     fn compile_for(
         &mut self,
         target: &ast::Expression,
@@ -1143,8 +1369,10 @@ impl Compiler {
                     if let ast::ExpressionType::Starred { .. } = &element.node {
                         if seen_star {
                             return Err(CompileError {
+                                statement: None,
                                 error: CompileErrorType::StarArgs,
                                 location: self.current_source_location.clone(),
+                                source_path: None,
                             });
                         } else {
                             seen_star = true;
@@ -1172,8 +1400,10 @@ impl Compiler {
             }
             _ => {
                 return Err(CompileError {
+                    statement: None,
                     error: CompileErrorType::Assign(target.name()),
                     location: self.current_source_location.clone(),
+                    source_path: None,
                 });
             }
         }
@@ -1205,23 +1435,22 @@ impl Compiler {
         expression: &ast::Expression,
         true_label: Option<Label>,
         false_label: Option<Label>,
-        context: EvalContext,
     ) -> Result<(), CompileError> {
         // Compile expression for test, and jump to label if false
         match &expression.node {
             ast::ExpressionType::BoolOp { a, op, b } => match op {
                 ast::BooleanOperator::And => {
                     let f = false_label.unwrap_or_else(|| self.new_label());
-                    self.compile_test(a, None, Some(f), context)?;
-                    self.compile_test(b, true_label, false_label, context)?;
+                    self.compile_test(a, None, Some(f))?;
+                    self.compile_test(b, true_label, false_label)?;
                     if false_label.is_none() {
                         self.set_label(f);
                     }
                 }
                 ast::BooleanOperator::Or => {
                     let t = true_label.unwrap_or_else(|| self.new_label());
-                    self.compile_test(a, Some(t), None, context)?;
-                    self.compile_test(b, true_label, false_label, context)?;
+                    self.compile_test(a, Some(t), None)?;
+                    self.compile_test(b, true_label, false_label)?;
                     if true_label.is_none() {
                         self.set_label(t);
                     }
@@ -1229,37 +1458,59 @@ impl Compiler {
             },
             _ => {
                 self.compile_expression(expression)?;
-                match context {
-                    EvalContext::Statement => {
-                        if let Some(true_label) = true_label {
-                            self.emit(Instruction::JumpIf { target: true_label });
-                        }
-                        if let Some(false_label) = false_label {
-                            self.emit(Instruction::JumpIfFalse {
-                                target: false_label,
-                            });
-                        }
-                    }
-                    EvalContext::Expression => {
-                        if let Some(true_label) = true_label {
-                            self.emit(Instruction::Duplicate);
-                            self.emit(Instruction::JumpIf { target: true_label });
-                            self.emit(Instruction::Pop);
-                        }
-                        if let Some(false_label) = false_label {
-                            self.emit(Instruction::Duplicate);
-                            self.emit(Instruction::JumpIfFalse {
-                                target: false_label,
-                            });
-                            self.emit(Instruction::Pop);
-                        }
-                    }
+                if let Some(true_label) = true_label {
+                    self.emit(Instruction::JumpIf { target: true_label });
+                }
+                if let Some(false_label) = false_label {
+                    self.emit(Instruction::JumpIfFalse {
+                        target: false_label,
+                    });
                 }
             }
         }
         Ok(())
     }
 
+    fn compile_bool_op(
+        &mut self,
+        op: &ast::BooleanOperator,
+        a: &ast::Expression,
+        b: &ast::Expression,
+    ) -> Result<(), CompileError> {
+        let end_label = self.new_label();
+        self.compile_bool_operand(op, a, end_label)?;
+        self.compile_expression(b)?;
+        self.set_label(end_label);
+        Ok(())
+    }
+
+    fn compile_bool_operand(
+        &mut self,
+        op: &ast::BooleanOperator,
+        expression: &ast::Expression,
+        end_label: Label,
+    ) -> Result<(), CompileError> {
+        // Chains of the same operator jump straight to the end, so every
+        // operand is tested only once:
+        if let ast::ExpressionType::BoolOp { a, op: inner_op, b } = &expression.node {
+            if inner_op == op {
+                self.compile_bool_operand(op, a, end_label)?;
+                return self.compile_bool_operand(op, b, end_label);
+            }
+        }
+
+        // A short-circuiting operand is the result, so it stays on the
+        // stack when jumping to the end:
+        self.compile_expression(expression)?;
+        self.emit(Instruction::Duplicate);
+        match op {
+            ast::BooleanOperator::And => self.emit(Instruction::JumpIfFalse { target: end_label }),
+            ast::BooleanOperator::Or => self.emit(Instruction::JumpIf { target: end_label }),
+        }
+        self.emit(Instruction::Pop);
+        Ok(())
+    }
+
     fn compile_expression(&mut self, expression: &ast::Expression) -> Result<(), CompileError> {
         trace!("Compiling {:?}", expression);
         use ast::ExpressionType::*;
@@ -1269,12 +1520,7 @@ impl Compiler {
                 args,
                 keywords,
             } => self.compile_call(function, args, keywords)?,
-            BoolOp { .. } => self.compile_test(
-                expression,
-                Option::None,
-                Option::None,
-                EvalContext::Expression,
-            )?,
+            BoolOp { a, op, b } => self.compile_bool_op(op, a, b)?,
             Binop { a, op, b } => {
                 self.compile_expression(a)?;
                 self.compile_expression(b)?;
@@ -1381,8 +1627,10 @@ impl Compiler {
             Yield { value } => {
                 if !self.in_function_def {
                     return Err(CompileError {
+                        statement: Option::None,
                         error: CompileErrorType::InvalidYield,
                         location: self.current_source_location.clone(),
+                        source_path: Option::None,
                     });
                 }
                 self.mark_generator();
@@ -1437,23 +1685,38 @@ impl Compiler {
                 });
             }
             Identifier { name } => {
-                self.load_name(name);
+                if name == "__debug__" {
+                    // __debug__ is a compile time constant:
+                    self.emit(Instruction::LoadConst {
+                        value: bytecode::Constant::Boolean {
+                            value: self.optimize == 0,
+                        },
+                    });
+                } else {
+                    self.load_name(name);
+                }
             }
             Lambda { args, body } => {
                 let name = "<lambda>".to_string();
+                let old_qualified_path = self.current_qualified_path.clone();
+                let qualified_name = self.create_qualified_name(&name);
+                self.current_qualified_path = Some(format!("{}.<locals>", qualified_name));
                 // no need to worry about the self.loop_depth because there are no loops in lambda expressions
-                let flags = self.enter_function(&name, args)?;
+                let flags = self.enter_function(&name, args, Option::None)?;
                 self.compile_expression(body)?;
                 self.emit(Instruction::ReturnValue);
-                let code = self.pop_code_object();
-                self.leave_scope();
+                let code = self.pop_code_object_and_scope()?;
+                self.current_qualified_path = old_qualified_path;
                 self.emit(Instruction::LoadConst {
                     value: bytecode::Constant::Code {
                         code: Box::new(code),
                     },
                 });
                 self.emit(Instruction::LoadConst {
-                    value: bytecode::Constant::String { value: name },
+                    value: bytecode::Constant::String {
+                        value: qualified_name,
+                        stand_ins: Option::None,
+                    },
                 });
                 // Turn code object into function object:
                 self.emit(Instruction::MakeFunction { flags });
@@ -1469,8 +1732,7 @@ impl Compiler {
             IfExpression { test, body, orelse } => {
                 let no_label = self.new_label();
                 let end_label = self.new_label();
-                self.compile_test(test, Option::None, Option::None, EvalContext::Expression)?;
-                self.emit(Instruction::JumpIfFalse { target: no_label });
+                self.compile_test(test, Option::None, Some(no_label))?;
                 // True case
                 self.compile_expression(body)?;
                 self.emit(Instruction::Jump { target: end_label });
@@ -1490,14 +1752,24 @@ impl Compiler {
         args: &[ast::Expression],
         keywords: &[ast::Keyword],
     ) -> Result<(), CompileError> {
-        self.compile_expression(function)?;
+        // A call of an attribute looks the method up without binding it:
+        let is_method = if let ast::ExpressionType::Attribute { value, name } = &function.node {
+            self.compile_expression(value)?;
+            self.emit(Instruction::LoadMethod {
+                name: name.to_string(),
+            });
+            true
+        } else {
+            self.compile_expression(function)?;
+            false
+        };
         let count = args.len() + keywords.len();
 
         // Normal arguments:
         let must_unpack = self.gather_elements(args)?;
         let has_double_star = keywords.iter().any(|k| k.name.is_none());
 
-        if must_unpack || has_double_star {
+        let typ = if must_unpack || has_double_star {
             // Create a tuple with positional args:
             self.emit(Instruction::BuildTuple {
                 size: args.len(),
@@ -1511,6 +1783,7 @@ impl Compiler {
                         self.emit(Instruction::LoadConst {
                             value: bytecode::Constant::String {
                                 value: name.to_string(),
+                                stand_ins: None,
                             },
                         });
                         self.compile_expression(&keyword.value)?;
@@ -1531,13 +1804,9 @@ impl Compiler {
                     unpack: has_double_star,
                 });
 
-                self.emit(Instruction::CallFunction {
-                    typ: CallType::Ex(true),
-                });
+                CallType::Ex(true)
             } else {
-                self.emit(Instruction::CallFunction {
-                    typ: CallType::Ex(false),
-                });
+                CallType::Ex(false)
             }
         } else {
             // Keyword arguments:
@@ -1547,6 +1816,7 @@ impl Compiler {
                     if let Some(name) = &keyword.name {
                         kwarg_names.push(bytecode::Constant::String {
                             value: name.to_string(),
+                            stand_ins: None,
                         });
                     } else {
                         // This means **kwargs!
@@ -1560,15 +1830,16 @@ impl Compiler {
                         elements: kwarg_names,
                     },
                 });
-                self.emit(Instruction::CallFunction {
-                    typ: CallType::Keyword(count),
-                });
+                CallType::Keyword(count)
             } else {
-                self.emit(Instruction::CallFunction {
-                    typ: CallType::Positional(count),
-                });
+                CallType::Positional(count)
             }
-        }
+        };
+        self.emit(if is_method {
+            Instruction::CallMethod { typ }
+        } else {
+            Instruction::CallFunction { typ }
+        });
         Ok(())
     }
 
@@ -1617,7 +1888,13 @@ impl Compiler {
         }
         .to_string();
 
+        // Comprehension bodies don't add a `<locals>` level of their own:
+        let old_qualified_path = self.current_qualified_path.clone();
+        let qualified_name = self.create_qualified_name(&name);
+        self.current_qualified_path = Some(qualified_name.clone());
+
         let line_number = self.get_source_line_number();
+        let code_flags = self.function_code_flags();
         // Create magnificent function <listcomp>:
         self.code_object_stack.push(CodeObject::new(
             vec![".0".to_string()],
@@ -1628,6 +1905,7 @@ impl Compiler {
             line_number,
             name.clone(),
         ));
+        self.current_code_object().flags = code_flags;
 
         // Create empty object of proper type:
         match kind {
@@ -1683,12 +1961,7 @@ impl Compiler {
 
             // Now evaluate the ifs:
             for if_condition in &generator.ifs {
-                self.compile_test(
-                    if_condition,
-                    None,
-                    Some(start_label),
-                    EvalContext::Statement,
-                )?
+                self.compile_test(if_condition, None, Some(start_label))?
             }
         }
 
@@ -1736,7 +2009,8 @@ impl Compiler {
         self.emit(Instruction::ReturnValue);
 
         // Fetch code for listcomp function:
-        let code = self.pop_code_object();
+        let code = self.pop_code_object()?;
+        self.current_qualified_path = old_qualified_path;
 
         // List comprehension code:
         self.emit(Instruction::LoadConst {
@@ -1747,7 +2021,10 @@ impl Compiler {
 
         // List comprehension function name:
         self.emit(Instruction::LoadConst {
-            value: bytecode::Constant::String { value: name },
+            value: bytecode::Constant::String {
+                value: qualified_name,
+                stand_ins: None,
+            },
         });
 
         // Turn code object into function object:
@@ -1776,10 +2053,11 @@ impl Compiler {
                 }
                 self.emit(Instruction::BuildString { size: values.len() })
             }
-            ast::StringGroup::Constant { value } => {
+            ast::StringGroup::Constant { value, stand_ins } => {
                 self.emit(Instruction::LoadConst {
                     value: bytecode::Constant::String {
                         value: value.to_string(),
+                        stand_ins: *stand_ins,
                     },
                 });
             }
@@ -1853,11 +2131,22 @@ impl Compiler {
         self.current_source_location.row()
     }
 
-    fn create_qualified_name(&self, name: &str, suffix: &str) -> String {
-        if let Some(ref qualified_path) = self.current_qualified_path {
-            format!("{}.{}{}", qualified_path, name, suffix)
-        } else {
-            format!("{}{}", name, suffix)
+    fn create_qualified_name(&self, name: &str) -> String {
+        match self.current_qualified_path {
+            // Names declared global in the enclosing scope are reachable
+            // from the module, so they keep their bare name:
+            Some(ref qualified_path) if !self.is_declared_global(name) => {
+                format!("{}.{}", qualified_path, name)
+            }
+            _ => name.to_string(),
+        }
+    }
+
+    fn is_declared_global(&self, name: &str) -> bool {
+        let scope = self.scope_stack.last().unwrap();
+        match scope.lookup(name) {
+            Some(symbol) => symbol.is_global,
+            Option::None => false,
         }
     }
 
@@ -1866,13 +2155,15 @@ impl Compiler {
     }
 }
 
-fn get_doc(body: &[ast::Statement]) -> (&[ast::Statement], Option<String>) {
+/// Split off the docstring of a body. Only a plain string counts: the parser
+/// folds adjacent strings into one constant, and leaves f-strings joined.
+fn get_doc(body: &[ast::Statement]) -> (&[ast::Statement], Option<(String, Option<u32>)>) {
     if let Some(val) = body.get(0) {
         if let ast::StatementType::Expression { ref expression } = val.node {
             if let ast::ExpressionType::String { value } = &expression.node {
-                if let ast::StringGroup::Constant { ref value } = value {
+                if let ast::StringGroup::Constant { value, stand_ins } = value {
                     if let Some((_, body_rest)) = body.split_first() {
-                        return (body_rest, Some(value.to_string()));
+                        return (body_rest, Some((value.to_string(), *stand_ins)));
                     }
                 }
             }
@@ -1881,6 +2172,37 @@ fn get_doc(body: &[ast::Statement]) -> (&[ast::Statement], Option<String>) {
     (body, None)
 }
 
+/// Whether a module or class body contains annotated assignments,
+/// which need an `__annotations__` dict.
+fn find_annotations(body: &[ast::Statement]) -> bool {
+    use ast::StatementType::*;
+    let option_body = |body: &Option<Vec<ast::Statement>>| match body {
+        Some(body) => find_annotations(body),
+        None => false,
+    };
+    body.iter().any(|statement| match &statement.node {
+        AnnAssign { .. } => true,
+        If { body, orelse, .. } | While { body, orelse, .. } | For { body, orelse, .. } => {
+            find_annotations(body) || option_body(orelse)
+        }
+        With { body, .. } => find_annotations(body),
+        Try {
+            body,
+            handlers,
+            orelse,
+            finalbody,
+        } => {
+            find_annotations(body)
+                || handlers
+                    .iter()
+                    .any(|handler| find_annotations(&handler.body))
+                || option_body(orelse)
+                || option_body(finalbody)
+        }
+        _ => false,
+    })
+}
+
 fn compile_location(location: &ast::Location) -> bytecode::Location {
     bytecode::Location::new(location.row(), location.column())
 }
@@ -1917,7 +2239,7 @@ mod tests {
         let ast = parser::parse_program(&source.to_string()).unwrap();
         let symbol_scope = make_symbol_table(&ast).unwrap();
         compiler.compile_program(&ast, symbol_scope).unwrap();
-        compiler.pop_code_object()
+        compiler.pop_code_object().unwrap()
     }
 
     #[test]
@@ -1998,4 +2320,26 @@ mod tests {
             code.instructions
         );
     }
+
+    #[test]
+    fn test_listing_roundtrip() {
+        // The listing of every snippet assembles back into the same code.
+        let snippets = concat!(env!("CARGO_MANIFEST_DIR"), "/../tests/snippets");
+        for entry in std::fs::read_dir(snippets).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension() != Some("py".as_ref()) {
+                continue;
+            }
+            let source = std::fs::read_to_string(&path).unwrap();
+            let code = match parser::parse_program(&source) {
+                Ok(_) => compile_exec(&source),
+                Err(_) => continue,
+            };
+            let listing = code.to_string();
+            let assembled: CodeObject = listing
+                .parse()
+                .unwrap_or_else(|err| panic!("{}: {}", path.display(), err));
+            assert!(assembled == code, "{}", path.display());
+        }
+    }
 }
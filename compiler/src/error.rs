@@ -1,3 +1,4 @@
+use rustpython_bytecode::verify::VerifyError;
 use rustpython_parser::error::{ParseError, ParseErrorType};
 use rustpython_parser::location::Location;
 
@@ -6,15 +7,19 @@ use std::fmt;
 
 #[derive(Debug)]
 pub struct CompileError {
+    pub statement: Option<String>,
     pub error: CompileErrorType,
     pub location: Location,
+    pub source_path: Option<String>,
 }
 
 impl From<ParseError> for CompileError {
     fn from(error: ParseError) -> Self {
         CompileError {
+            statement: None,
             error: CompileErrorType::Parse(error.error),
             location: error.location,
+            source_path: None,
         }
     }
 }
@@ -38,11 +43,46 @@ pub enum CompileErrorType {
     InvalidContinue,
     InvalidReturn,
     InvalidYield,
+    /// The compiler emitted code that doesn't verify, which is a bug in the
+    /// compiler rather than in the source.
+    InvalidBytecode(VerifyError),
 }
 
-impl fmt::Display for CompileError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+impl CompileError {
+    /// Remember the line of source code the error points at.
+    pub fn update_statement_info(&mut self, source: &str) {
+        self.statement = match self.location.row() {
+            0 => None,
+            row => source
+                .split_inclusive('\n')
+                .nth(row - 1)
+                .map(|line| line.to_string()),
+        };
+    }
+
+    pub fn update_source_path(&mut self, source_path: &str) {
+        debug_assert!(self.source_path.is_none());
+        self.source_path = Some(source_path.to_string());
+    }
+
+    pub fn is_indentation_error(&self) -> bool {
+        match &self.error {
+            CompileErrorType::Parse(parse) => parse.is_indentation_error(),
+            _ => false,
+        }
+    }
+
+    pub fn is_tab_error(&self) -> bool {
         match &self.error {
+            CompileErrorType::Parse(parse) => parse.is_tab_error(),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for CompileErrorType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
             CompileErrorType::Assign(target) => write!(f, "can't assign to {}", target),
             CompileErrorType::Delete(target) => write!(f, "can't delete {}", target),
             CompileErrorType::ExpectExpr => write!(f, "Expecting expression, got statement"),
@@ -53,10 +93,15 @@ impl fmt::Display for CompileError {
             CompileErrorType::InvalidContinue => write!(f, "'continue' outside loop"),
             CompileErrorType::InvalidReturn => write!(f, "'return' outside function"),
             CompileErrorType::InvalidYield => write!(f, "'yield' outside function"),
-        }?;
+            CompileErrorType::InvalidBytecode(err) => write!(f, "compiler emitted {}", err),
+        }
+    }
+}
 
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         // Print line number:
-        write!(f, " at {}", self.location)
+        write!(f, "{} at {}", self.error, self.location)
     }
 }
 
@@ -9,3 +9,4 @@ extern crate log;
 pub mod compile;
 pub mod error;
 pub mod symboltable;
+pub mod unparse;
@@ -11,15 +11,18 @@ use crate::error::{CompileError, CompileErrorType};
 use indexmap::map::IndexMap;
 use rustpython_parser::ast;
 use rustpython_parser::location::Location;
+use rustpython_parser::visitor::{self, Visitor};
+use std::collections::HashSet;
 
 pub fn make_symbol_table(program: &ast::Program) -> Result<SymbolScope, SymbolTableError> {
     let mut builder: SymbolTableBuilder = Default::default();
-    builder.enter_scope();
-    builder.scan_program(program)?;
+    builder.enter_scope(SymbolScopeType::Module);
+    builder.visit_program(program)?;
     assert_eq!(builder.scopes.len(), 1);
 
-    let symbol_table = builder.scopes.pop().unwrap();
+    let mut symbol_table = builder.scopes.pop().unwrap();
     analyze_symbol_table(&symbol_table, None)?;
+    find_free_variables(&mut symbol_table, &HashSet::new());
     Ok(symbol_table)
 }
 
@@ -27,18 +30,35 @@ pub fn statements_to_symbol_table(
     statements: &[ast::Statement],
 ) -> Result<SymbolScope, SymbolTableError> {
     let mut builder: SymbolTableBuilder = Default::default();
-    builder.enter_scope();
-    builder.scan_statements(statements)?;
+    builder.enter_scope(SymbolScopeType::Module);
+    visitor::walk_statements(&mut builder, statements)?;
     assert_eq!(builder.scopes.len(), 1);
 
-    let symbol_table = builder.scopes.pop().unwrap();
+    let mut symbol_table = builder.scopes.pop().unwrap();
     analyze_symbol_table(&symbol_table, None)?;
+    find_free_variables(&mut symbol_table, &HashSet::new());
     Ok(symbol_table)
 }
 
+/// The kind of code a symbol scope belongs to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SymbolScopeType {
+    Module,
+    Class,
+    Function,
+}
+
+impl Default for SymbolScopeType {
+    fn default() -> Self {
+        SymbolScopeType::Module
+    }
+}
+
 /// Captures all symbols in the current scope, and has a list of subscopes in this scope.
 #[derive(Clone, Default)]
 pub struct SymbolScope {
+    pub typ: SymbolScopeType,
+
     /// A set of symbols present on this scope level.
     pub symbols: IndexMap<String, Symbol>,
 
@@ -57,7 +77,10 @@ pub struct Symbol {
     pub is_referenced: bool,
     pub is_assigned: bool,
     pub is_parameter: bool,
+    /// Refers to a variable of an enclosing function.
     pub is_free: bool,
+    /// A variable of this function that nested scopes refer to.
+    pub is_cell: bool,
 }
 
 impl Symbol {
@@ -72,6 +95,7 @@ impl Symbol {
             is_assigned: false,
             is_parameter: false,
             is_free: false,
+            is_cell: false,
         }
     }
 }
@@ -85,8 +109,10 @@ pub struct SymbolTableError {
 impl From<SymbolTableError> for CompileError {
     fn from(error: SymbolTableError) -> Self {
         CompileError {
+            statement: None,
             error: CompileErrorType::SyntaxError(error.error),
             location: error.location,
+            source_path: None,
         }
     }
 }
@@ -97,6 +123,36 @@ impl SymbolScope {
     pub fn lookup(&self, name: &str) -> Option<&Symbol> {
         self.symbols.get(name)
     }
+
+    /// Names of the cell variables of this scope, sorted like CPython's
+    /// `co_cellvars`.
+    pub fn cell_names(&self) -> Vec<String> {
+        self.sorted_names(|symbol| symbol.is_cell)
+    }
+
+    /// Names of the free variables of this scope, sorted like CPython's
+    /// `co_freevars`.
+    pub fn free_names(&self) -> Vec<String> {
+        self.sorted_names(|symbol| symbol.is_free)
+    }
+
+    fn sorted_names(&self, predicate: impl Fn(&Symbol) -> bool) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .symbols
+            .values()
+            .filter(|symbol| predicate(symbol))
+            .map(|symbol| symbol.name.clone())
+            .collect();
+        names.sort();
+        names
+    }
+}
+
+impl Symbol {
+    /// Whether this symbol is a variable of its own scope.
+    fn is_bound(&self) -> bool {
+        self.is_assigned && !self.is_global && !self.is_nonlocal
+    }
 }
 
 impl std::fmt::Debug for SymbolScope {
@@ -156,6 +212,65 @@ fn analyze_symbol(symbol: &Symbol, parent_symbol_scope: Option<&SymbolScope>) ->
     Ok(())
 }
 
+/// Marks the free variables of `symbol_scope` and its sub scopes, and the
+/// cell variables they refer to. `enclosing` holds the variables of the
+/// enclosing functions. Returns the names free in this scope, including the
+/// ones only passed through to a nested scope.
+fn find_free_variables(
+    symbol_scope: &mut SymbolScope,
+    enclosing: &HashSet<String>,
+) -> HashSet<String> {
+    let mut free = HashSet::new();
+    if symbol_scope.typ != SymbolScopeType::Module {
+        for symbol in symbol_scope.symbols.values_mut() {
+            if symbol.is_nonlocal
+                || (symbol.is_referenced
+                    && !symbol.is_assigned
+                    && !symbol.is_global
+                    && enclosing.contains(&symbol.name))
+            {
+                symbol.is_free = true;
+                free.insert(symbol.name.clone());
+            }
+        }
+    }
+
+    // Class and module variables are not visible to nested functions.
+    let mut visible = HashSet::new();
+    if symbol_scope.typ != SymbolScopeType::Module {
+        visible.extend(enclosing.iter().cloned());
+    }
+    if symbol_scope.typ == SymbolScopeType::Function {
+        for symbol in symbol_scope.symbols.values() {
+            if symbol.is_bound() {
+                visible.insert(symbol.name.clone());
+            }
+        }
+    }
+
+    let mut nested_free = HashSet::new();
+    for sub_scope in &mut symbol_scope.sub_scopes {
+        nested_free.extend(find_free_variables(sub_scope, &visible));
+    }
+    let is_function = symbol_scope.typ == SymbolScopeType::Function;
+    for name in nested_free {
+        let symbol = symbol_scope
+            .symbols
+            .entry(name.clone())
+            .or_insert_with(|| Symbol::new(&name));
+        if is_function && symbol.is_bound() {
+            symbol.is_cell = true;
+        } else {
+            // Passed through to the nested scope.
+            if !symbol.is_bound() {
+                symbol.is_free = true;
+            }
+            free.insert(name);
+        }
+    }
+    free
+}
+
 #[derive(Debug, Clone)]
 enum SymbolRole {
     Global,
@@ -168,11 +283,18 @@ enum SymbolRole {
 struct SymbolTableBuilder {
     // Scope stack.
     scopes: Vec<SymbolScope>,
+    // Whether the current scope is a function body.
+    in_function_def: bool,
+    // Whether `from __future__ import annotations` is in effect.
+    future_annotations: bool,
 }
 
 impl SymbolTableBuilder {
-    fn enter_scope(&mut self) {
-        let scope = Default::default();
+    fn enter_scope(&mut self, typ: SymbolScopeType) {
+        let scope = SymbolScope {
+            typ,
+            ..Default::default()
+        };
         self.scopes.push(scope);
         // self.work_scopes.push(Default::default());
     }
@@ -184,44 +306,134 @@ impl SymbolTableBuilder {
         self.scopes.last_mut().unwrap().sub_scopes.push(scope);
     }
 
-    fn scan_program(&mut self, program: &ast::Program) -> SymbolTableResult {
-        self.scan_statements(&program.statements)?;
-        Ok(())
-    }
+    fn enter_function(
+        &mut self,
+        args: &ast::Parameters,
+        returns: Option<&ast::Expression>,
+    ) -> SymbolTableResult {
+        // Default values and annotations are evaluated in the outer scope:
+        visitor::walk_parameters(self, args)?;
+        if let Some(expression) = returns {
+            self.visit_annotation(expression)?;
+        }
 
-    fn scan_statements(&mut self, statements: &[ast::Statement]) -> SymbolTableResult {
-        for statement in statements {
-            self.scan_statement(statement)?;
+        self.enter_scope(SymbolScopeType::Function);
+
+        // Fill scope with parameter names:
+        for parameter in args.args.iter().chain(args.kwonlyargs.iter()) {
+            self.register_name(&parameter.arg, SymbolRole::Assigned)?;
+        }
+        if let ast::Varargs::Named(parameter) = &args.vararg {
+            self.register_name(&parameter.arg, SymbolRole::Assigned)?;
+        }
+        if let ast::Varargs::Named(parameter) = &args.kwarg {
+            self.register_name(&parameter.arg, SymbolRole::Assigned)?;
         }
         Ok(())
     }
 
-    fn scan_parameters(&mut self, parameters: &[ast::Parameter]) -> SymbolTableResult {
-        for parameter in parameters {
-            self.scan_parameter(parameter)?;
+    /// Annotations that become strings are not compiled, so they should
+    /// not introduce scopes either.
+    fn visit_annotation(&mut self, annotation: &ast::Expression) -> SymbolTableResult {
+        if self.future_annotations {
+            Ok(())
+        } else {
+            self.visit_expression(annotation)
         }
-        Ok(())
     }
 
-    fn scan_parameter(&mut self, parameter: &ast::Parameter) -> SymbolTableResult {
-        self.register_name(&parameter.arg, SymbolRole::Assigned)
+    /// Registers the names bound by an assignment target; anything else in
+    /// the target, like the object of an attribute, is only used.
+    fn visit_target(&mut self, target: &ast::Expression) -> SymbolTableResult {
+        use ast::ExpressionType::*;
+        match &target.node {
+            Identifier { name } => self.register_name(name, SymbolRole::Assigned),
+            Tuple { elements } | List { elements } => {
+                for element in elements {
+                    self.visit_target(element)?;
+                }
+                Ok(())
+            }
+            Starred { value } => self.visit_target(value),
+            _ => self.visit_expression(target),
+        }
     }
 
-    fn scan_parameters_annotations(&mut self, parameters: &[ast::Parameter]) -> SymbolTableResult {
-        for parameter in parameters {
-            self.scan_parameter_annotation(parameter)?;
+    #[allow(clippy::single_match)]
+    fn register_name(&mut self, name: &str, role: SymbolRole) -> SymbolTableResult {
+        let scope_depth = self.scopes.len();
+        let current_scope = self.scopes.last_mut().unwrap();
+        let location = Default::default();
+
+        // Some checks:
+        if current_scope.symbols.contains_key(name) {
+            // Role already set..
+            match role {
+                SymbolRole::Global => {
+                    return Err(SymbolTableError {
+                        error: format!("name '{}' is used prior to global declaration", name),
+                        location,
+                    })
+                }
+                SymbolRole::Nonlocal => {
+                    return Err(SymbolTableError {
+                        error: format!("name '{}' is used prior to nonlocal declaration", name),
+                        location,
+                    })
+                }
+                _ => {
+                    // Ok?
+                }
+            }
+        }
+
+        // Some more checks:
+        match role {
+            SymbolRole::Nonlocal => {
+                if scope_depth < 2 {
+                    return Err(SymbolTableError {
+                        error: format!("cannot define nonlocal '{}' at top level.", name),
+                        location,
+                    });
+                }
+            }
+            _ => {
+                // Ok!
+            }
         }
-        Ok(())
-    }
 
-    fn scan_parameter_annotation(&mut self, parameter: &ast::Parameter) -> SymbolTableResult {
-        if let Some(annotation) = &parameter.annotation {
-            self.scan_expression(&annotation)?;
+        // Insert symbol when required:
+        if !current_scope.symbols.contains_key(name) {
+            let symbol = Symbol::new(name);
+            current_scope.symbols.insert(name.to_string(), symbol);
         }
+
+        // Set proper flags on symbol:
+        let symbol = current_scope.symbols.get_mut(name).unwrap();
+        match role {
+            SymbolRole::Nonlocal => {
+                symbol.is_nonlocal = true;
+            }
+            SymbolRole::Assigned => {
+                symbol.is_assigned = true;
+                // symbol.is_local = true;
+            }
+            SymbolRole::Global => {
+                symbol.is_global = true;
+            }
+            SymbolRole::Used => {
+                symbol.is_referenced = true;
+            }
+        }
+
         Ok(())
     }
+}
+
+impl Visitor for SymbolTableBuilder {
+    type Error = SymbolTableError;
 
-    fn scan_statement(&mut self, statement: &ast::Statement) -> SymbolTableResult {
+    fn visit_statement(&mut self, statement: &ast::Statement) -> SymbolTableResult {
         use ast::StatementType::*;
         match &statement.node {
             Global { names } => {
@@ -242,15 +454,14 @@ impl SymbolTableBuilder {
                 returns,
                 ..
             } => {
-                self.scan_expressions(decorator_list)?;
+                visitor::walk_expressions(self, decorator_list)?;
                 self.register_name(name, SymbolRole::Assigned)?;
 
-                self.enter_function(args)?;
-
-                self.scan_statements(body)?;
-                if let Some(expression) = returns {
-                    self.scan_expression(expression)?;
-                }
+                self.enter_function(args, returns.as_ref())?;
+                let was_in_function_def = self.in_function_def;
+                self.in_function_def = true;
+                visitor::walk_statements(self, body)?;
+                self.in_function_def = was_in_function_def;
                 self.leave_scope();
             }
             ClassDef {
@@ -260,353 +471,132 @@ impl SymbolTableBuilder {
                 keywords,
                 decorator_list,
             } => {
+                // Decorators are evaluated before the class body, bases after it.
+                visitor::walk_expressions(self, decorator_list)?;
                 self.register_name(name, SymbolRole::Assigned)?;
-                self.enter_scope();
-                self.scan_statements(body)?;
+                self.enter_scope(SymbolScopeType::Class);
+                let was_in_function_def = self.in_function_def;
+                self.in_function_def = false;
+                visitor::walk_statements(self, body)?;
+                self.in_function_def = was_in_function_def;
                 self.leave_scope();
-                self.scan_expressions(bases)?;
+                visitor::walk_expressions(self, bases)?;
                 for keyword in keywords {
-                    self.scan_expression(&keyword.value)?;
-                }
-                self.scan_expressions(decorator_list)?;
-            }
-            Expression { expression } => self.scan_expression(expression)?,
-            If { test, body, orelse } => {
-                self.scan_expression(test)?;
-                self.scan_statements(body)?;
-                if let Some(code) = orelse {
-                    self.scan_statements(code)?;
-                }
-            }
-            For {
-                target,
-                iter,
-                body,
-                orelse,
-                ..
-            } => {
-                self.scan_expression(target)?;
-                self.scan_expression(iter)?;
-                self.scan_statements(body)?;
-                if let Some(code) = orelse {
-                    self.scan_statements(code)?;
+                    self.visit_keyword(keyword)?;
                 }
             }
-            While { test, body, orelse } => {
-                self.scan_expression(test)?;
-                self.scan_statements(body)?;
-                if let Some(code) = orelse {
-                    self.scan_statements(code)?;
-                }
-            }
-            Break | Continue | Pass => {
-                // No symbols here.
-            }
-            Import { names } | ImportFrom { names, .. } => {
-                for name in names {
-                    if let Some(alias) = &name.alias {
-                        // `import mymodule as myalias`
-                        self.register_name(alias, SymbolRole::Assigned)?;
-                    } else {
-                        // `import module`
-                        self.register_name(&name.symbol, SymbolRole::Assigned)?;
-                    }
-                }
-            }
-            Return { value } => {
-                if let Some(expression) = value {
-                    self.scan_expression(expression)?;
-                }
-            }
-            Assert { test, msg } => {
-                self.scan_expression(test)?;
-                if let Some(expression) = msg {
-                    self.scan_expression(expression)?;
+            ImportFrom { module, names, .. } => {
+                if module.as_ref().map(String::as_str) == Some("__future__")
+                    && names.iter().any(|n| n.symbol == "annotations")
+                {
+                    self.future_annotations = true;
                 }
+                visitor::walk_statement(self, statement)?;
             }
             Delete { targets } => {
-                self.scan_expressions(targets)?;
+                for target in targets {
+                    self.visit_target(target)?;
+                }
             }
             Assign { targets, value } => {
-                self.scan_expressions(targets)?;
-                self.scan_expression(value)?;
+                for target in targets {
+                    self.visit_target(target)?;
+                }
+                self.visit_expression(value)?;
             }
             AugAssign { target, value, .. } => {
-                self.scan_expression(target)?;
-                self.scan_expression(value)?;
-            }
-            With { items, body, .. } => {
-                for item in items {
-                    self.scan_expression(&item.context_expr)?;
-                    if let Some(expression) = &item.optional_vars {
-                        self.scan_expression(expression)?;
-                    }
-                }
-                self.scan_statements(body)?;
+                self.visit_expression(target)?;
+                self.visit_target(target)?;
+                self.visit_expression(value)?;
             }
-            Try {
+            For {
+                target,
+                iter,
                 body,
-                handlers,
                 orelse,
-                finalbody,
+                ..
             } => {
-                self.scan_statements(body)?;
-                for handler in handlers {
-                    if let Some(expression) = &handler.typ {
-                        self.scan_expression(expression)?;
-                    }
-                    if let Some(name) = &handler.name {
-                        self.register_name(name, SymbolRole::Assigned)?;
-                    }
-                    self.scan_statements(&handler.body)?;
-                }
-                if let Some(code) = orelse {
-                    self.scan_statements(code)?;
-                }
-                if let Some(code) = finalbody {
-                    self.scan_statements(code)?;
+                self.visit_target(target)?;
+                self.visit_expression(iter)?;
+                visitor::walk_statements(self, body)?;
+                if let Some(orelse) = orelse {
+                    visitor::walk_statements(self, orelse)?;
                 }
             }
-            Raise { exception, cause } => {
-                if let Some(expression) = exception {
-                    self.scan_expression(expression)?;
+            AnnAssign {
+                target,
+                annotation,
+                value,
+                ..
+            } => {
+                self.visit_target(target)?;
+                // Annotations of local variables are never evaluated.
+                if !self.in_function_def {
+                    self.visit_annotation(annotation)?;
                 }
-                if let Some(expression) = cause {
-                    self.scan_expression(expression)?;
+                if let Some(value) = value {
+                    self.visit_expression(value)?;
                 }
             }
+            _ => visitor::walk_statement(self, statement)?,
         }
         Ok(())
     }
 
-    fn scan_expressions(&mut self, expressions: &[ast::Expression]) -> SymbolTableResult {
-        for expression in expressions {
-            self.scan_expression(expression)?;
-        }
-        Ok(())
-    }
-
-    fn scan_expression(&mut self, expression: &ast::Expression) -> SymbolTableResult {
+    fn visit_expression(&mut self, expression: &ast::Expression) -> SymbolTableResult {
         use ast::ExpressionType::*;
         match &expression.node {
-            Binop { a, b, .. } => {
-                self.scan_expression(a)?;
-                self.scan_expression(b)?;
-            }
-            BoolOp { a, b, .. } => {
-                self.scan_expression(a)?;
-                self.scan_expression(b)?;
-            }
-            Compare { vals, .. } => {
-                self.scan_expressions(vals)?;
-            }
-            Subscript { a, b } => {
-                self.scan_expression(a)?;
-                self.scan_expression(b)?;
-            }
-            Attribute { value, .. } => {
-                self.scan_expression(value)?;
-            }
-            Dict { elements } => {
-                for (key, value) in elements {
-                    if let Some(key) = key {
-                        self.scan_expression(key)?;
-                    } else {
-                        // dict unpacking marker
-                    }
-                    self.scan_expression(value)?;
-                }
-            }
-            Await { value } => {
-                self.scan_expression(value)?;
-            }
-            Yield { value } => {
-                if let Some(expression) = value {
-                    self.scan_expression(expression)?;
-                }
-            }
-            YieldFrom { value } => {
-                self.scan_expression(value)?;
-            }
-            Unop { a, .. } => {
-                self.scan_expression(a)?;
-            }
-            True | False | None | Ellipsis => {}
-            Number { .. } => {}
-            Starred { value } => {
-                self.scan_expression(value)?;
-            }
-            Bytes { .. } => {}
-            Tuple { elements } | Set { elements } | List { elements } | Slice { elements } => {
-                self.scan_expressions(elements)?;
-            }
-            Comprehension { kind, generators } => {
-                match **kind {
-                    ast::ComprehensionKind::GeneratorExpression { ref element }
-                    | ast::ComprehensionKind::List { ref element }
-                    | ast::ComprehensionKind::Set { ref element } => {
-                        self.scan_expression(element)?;
-                    }
-                    ast::ComprehensionKind::Dict { ref key, ref value } => {
-                        self.scan_expression(&key)?;
-                        self.scan_expression(&value)?;
-                    }
-                }
-
-                for generator in generators {
-                    self.scan_expression(&generator.target)?;
-                    self.scan_expression(&generator.iter)?;
-                    for if_expr in &generator.ifs {
-                        self.scan_expression(if_expr)?;
-                    }
-                }
-            }
-            Call {
-                function,
-                args,
-                keywords,
-            } => {
-                self.scan_expression(function)?;
-                self.scan_expressions(args)?;
-                for keyword in keywords {
-                    self.scan_expression(&keyword.value)?;
-                }
-            }
-            String { value } => {
-                self.scan_string_group(value)?;
-            }
             Identifier { name } => {
                 self.register_name(name, SymbolRole::Used)?;
             }
             Lambda { args, body } => {
-                self.enter_function(args)?;
-                self.scan_expression(body)?;
+                self.enter_function(args, Option::None)?;
+                self.visit_expression(body)?;
                 self.leave_scope();
             }
-            IfExpression { test, body, orelse } => {
-                self.scan_expression(test)?;
-                self.scan_expression(body)?;
-                self.scan_expression(orelse)?;
-            }
+            _ => visitor::walk_expression(self, expression)?,
         }
         Ok(())
     }
 
-    fn enter_function(&mut self, args: &ast::Parameters) -> SymbolTableResult {
-        // Evaluate eventual default parameters:
-        self.scan_expressions(&args.defaults)?;
-        for kw_default in &args.kw_defaults {
-            if let Some(expression) = kw_default {
-                self.scan_expression(&expression)?;
-            }
-        }
-
-        // Annotations are scanned in outer scope:
-        self.scan_parameters_annotations(&args.args)?;
-        self.scan_parameters_annotations(&args.kwonlyargs)?;
-        if let ast::Varargs::Named(name) = &args.vararg {
-            self.scan_parameter_annotation(name)?;
-        }
-        if let ast::Varargs::Named(name) = &args.kwarg {
-            self.scan_parameter_annotation(name)?;
-        }
-
-        self.enter_scope();
-
-        // Fill scope with parameter names:
-        self.scan_parameters(&args.args)?;
-        self.scan_parameters(&args.kwonlyargs)?;
-        if let ast::Varargs::Named(name) = &args.vararg {
-            self.scan_parameter(name)?;
-        }
-        if let ast::Varargs::Named(name) = &args.kwarg {
-            self.scan_parameter(name)?;
+    fn visit_parameter(&mut self, parameter: &ast::Parameter) -> SymbolTableResult {
+        if let Some(annotation) = &parameter.annotation {
+            self.visit_annotation(annotation)?;
         }
         Ok(())
     }
 
-    fn scan_string_group(&mut self, group: &ast::StringGroup) -> SymbolTableResult {
-        match group {
-            ast::StringGroup::Constant { .. } => {}
-            ast::StringGroup::FormattedValue { value, .. } => {
-                self.scan_expression(value)?;
-            }
-            ast::StringGroup::Joined { values } => {
-                for subgroup in values {
-                    self.scan_string_group(subgroup)?;
-                }
-            }
+    fn visit_import_symbol(&mut self, symbol: &ast::ImportSymbol) -> SymbolTableResult {
+        if let Some(alias) = &symbol.alias {
+            // `import mymodule as myalias`
+            self.register_name(alias, SymbolRole::Assigned)
+        } else {
+            // `import module`
+            self.register_name(&symbol.symbol, SymbolRole::Assigned)
         }
-        Ok(())
     }
 
-    #[allow(clippy::single_match)]
-    fn register_name(&mut self, name: &str, role: SymbolRole) -> SymbolTableResult {
-        let scope_depth = self.scopes.len();
-        let current_scope = self.scopes.last_mut().unwrap();
-        let location = Default::default();
-
-        // Some checks:
-        if current_scope.symbols.contains_key(name) {
-            // Role already set..
-            match role {
-                SymbolRole::Global => {
-                    return Err(SymbolTableError {
-                        error: format!("name '{}' is used prior to global declaration", name),
-                        location,
-                    })
-                }
-                SymbolRole::Nonlocal => {
-                    return Err(SymbolTableError {
-                        error: format!("name '{}' is used prior to nonlocal declaration", name),
-                        location,
-                    })
-                }
-                _ => {
-                    // Ok?
-                }
-            }
+    fn visit_with_item(&mut self, item: &ast::WithItem) -> SymbolTableResult {
+        self.visit_expression(&item.context_expr)?;
+        if let Some(optional_vars) = &item.optional_vars {
+            self.visit_target(optional_vars)?;
         }
+        Ok(())
+    }
 
-        // Some more checks:
-        match role {
-            SymbolRole::Nonlocal => {
-                if scope_depth < 2 {
-                    return Err(SymbolTableError {
-                        error: format!("cannot define nonlocal '{}' at top level.", name),
-                        location,
-                    });
-                }
-            }
-            _ => {
-                // Ok!
-            }
-        }
+    fn visit_comprehension(&mut self, comprehension: &ast::Comprehension) -> SymbolTableResult {
+        self.visit_target(&comprehension.target)?;
+        self.visit_expression(&comprehension.iter)?;
+        visitor::walk_expressions(self, &comprehension.ifs)
+    }
 
-        // Insert symbol when required:
-        if !current_scope.symbols.contains_key(name) {
-            let symbol = Symbol::new(name);
-            current_scope.symbols.insert(name.to_string(), symbol);
+    fn visit_except_handler(&mut self, handler: &ast::ExceptHandler) -> SymbolTableResult {
+        if let Some(expression) = &handler.typ {
+            self.visit_expression(expression)?;
         }
-
-        // Set proper flags on symbol:
-        let symbol = current_scope.symbols.get_mut(name).unwrap();
-        match role {
-            SymbolRole::Nonlocal => {
-                symbol.is_nonlocal = true;
-            }
-            SymbolRole::Assigned => {
-                symbol.is_assigned = true;
-                // symbol.is_local = true;
-            }
-            SymbolRole::Global => {
-                symbol.is_global = true;
-            }
-            SymbolRole::Used => {
-                symbol.is_referenced = true;
-            }
+        if let Some(name) = &handler.name {
+            self.register_name(name, SymbolRole::Assigned)?;
         }
-
-        Ok(())
+        visitor::walk_statements(self, &handler.body)
     }
 }
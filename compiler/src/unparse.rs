@@ -0,0 +1,642 @@
+//! Turn an expression ast back into python source code.
+//!
+//! This is used to store annotations as strings when
+//! `from __future__ import annotations` is in effect (PEP 563).
+//!
+//! Inspirational code:
+//!   https://github.com/python/cpython/blob/master/Python/ast_unparse.c
+
+use rustpython_parser::ast;
+use std::fmt::Write;
+
+// Operator precedence, from loosest to tightest binding.
+const PR_TUPLE: u8 = 0;
+const PR_TEST: u8 = 1; // 'if'-'else', 'lambda'
+const PR_OR: u8 = 2;
+const PR_AND: u8 = 3;
+const PR_NOT: u8 = 4;
+const PR_CMP: u8 = 5;
+const PR_EXPR: u8 = 6;
+const PR_BOR: u8 = PR_EXPR;
+const PR_BXOR: u8 = 7;
+const PR_BAND: u8 = 8;
+const PR_SHIFT: u8 = 9;
+const PR_ARITH: u8 = 10;
+const PR_TERM: u8 = 11;
+const PR_FACTOR: u8 = 12;
+const PR_POWER: u8 = 13;
+const PR_AWAIT: u8 = 14;
+const PR_ATOM: u8 = 15;
+
+/// Render an expression as python source code.
+pub fn unparse_expression(expression: &ast::Expression) -> String {
+    let mut unparser = Unparser {
+        source: String::new(),
+    };
+    unparser.expression(expression, PR_TEST);
+    unparser.source
+}
+
+struct Unparser {
+    source: String,
+}
+
+impl Unparser {
+    fn push(&mut self, text: &str) {
+        self.source.push_str(text);
+    }
+
+    fn open_paren(&mut self, level: u8, precedence: u8) {
+        if level > precedence {
+            self.push("(");
+        }
+    }
+
+    fn close_paren(&mut self, level: u8, precedence: u8) {
+        if level > precedence {
+            self.push(")");
+        }
+    }
+
+    fn expressions(&mut self, expressions: &[ast::Expression], level: u8) {
+        for (i, expression) in expressions.iter().enumerate() {
+            if i > 0 {
+                self.push(", ");
+            }
+            self.expression(expression, level);
+        }
+    }
+
+    fn expression(&mut self, expression: &ast::Expression, level: u8) {
+        use ast::ExpressionType::*;
+        match &expression.node {
+            BoolOp { a, op, b } => {
+                let (text, precedence) = match op {
+                    ast::BooleanOperator::And => (" and ", PR_AND),
+                    ast::BooleanOperator::Or => (" or ", PR_OR),
+                };
+                self.open_paren(level, precedence);
+                self.expression(a, precedence);
+                self.push(text);
+                self.expression(b, precedence + 1);
+                self.close_paren(level, precedence);
+            }
+            Binop { a, op, b } => {
+                let (text, precedence) = binary_operator(op);
+                // Power is the only right associative operator.
+                let (left, right) = match op {
+                    ast::Operator::Pow => (precedence + 1, precedence),
+                    _ => (precedence, precedence + 1),
+                };
+                self.open_paren(level, precedence);
+                self.expression(a, left);
+                self.push(text);
+                self.expression(b, right);
+                self.close_paren(level, precedence);
+            }
+            Unop { op, a } => {
+                let (text, precedence) = match op {
+                    ast::UnaryOperator::Not => ("not ", PR_NOT),
+                    ast::UnaryOperator::Inv => ("~", PR_FACTOR),
+                    ast::UnaryOperator::Pos => ("+", PR_FACTOR),
+                    ast::UnaryOperator::Neg => ("-", PR_FACTOR),
+                };
+                self.open_paren(level, precedence);
+                self.push(text);
+                self.expression(a, precedence);
+                self.close_paren(level, precedence);
+            }
+            Compare { vals, ops } => {
+                self.open_paren(level, PR_CMP);
+                self.expression(&vals[0], PR_CMP + 1);
+                for (op, val) in ops.iter().zip(vals[1..].iter()) {
+                    self.push(comparison(op));
+                    self.expression(val, PR_CMP + 1);
+                }
+                self.close_paren(level, PR_CMP);
+            }
+            Await { value } => {
+                self.open_paren(level, PR_AWAIT);
+                self.push("await ");
+                self.expression(value, PR_ATOM);
+                self.close_paren(level, PR_AWAIT);
+            }
+            Yield { value } => {
+                self.push("(yield");
+                if let Some(value) = value {
+                    self.push(" ");
+                    self.expression(value, PR_TEST);
+                }
+                self.push(")");
+            }
+            YieldFrom { value } => {
+                self.push("(yield from ");
+                self.expression(value, PR_TEST);
+                self.push(")");
+            }
+            Attribute { value, name } => {
+                self.expression(value, PR_ATOM);
+                // Integer literals need a space, or the dot would make them a float.
+                if let Number {
+                    value: ast::Number::Integer { .. },
+                } = value.node
+                {
+                    self.push(" ");
+                }
+                self.push(".");
+                self.push(name);
+            }
+            Subscript { a, b } => {
+                self.expression(a, PR_ATOM);
+                self.push("[");
+                self.expression(b, PR_TUPLE);
+                self.push("]");
+            }
+            Slice { elements } => {
+                self.slice_part(&elements[0]);
+                self.push(":");
+                self.slice_part(&elements[1]);
+                if let Some(step) = elements.get(2) {
+                    if !is_none(step) {
+                        self.push(":");
+                        self.expression(step, PR_TEST);
+                    }
+                }
+            }
+            Call {
+                function,
+                args,
+                keywords,
+            } => {
+                self.expression(function, PR_ATOM);
+                // A lone generator expression argument needs no extra parentheses.
+                if let ([argument], true) = (args.as_slice(), keywords.is_empty()) {
+                    if let Comprehension { kind, generators } = &argument.node {
+                        if let ast::ComprehensionKind::GeneratorExpression { element } = &**kind {
+                            self.push("(");
+                            self.expression(element, PR_TEST);
+                            self.comprehensions(generators);
+                            self.push(")");
+                            return;
+                        }
+                    }
+                }
+                self.push("(");
+                self.expressions(args, PR_TEST);
+                for (i, keyword) in keywords.iter().enumerate() {
+                    if i > 0 || !args.is_empty() {
+                        self.push(", ");
+                    }
+                    match &keyword.name {
+                        Some(name) => {
+                            self.push(name);
+                            self.push("=");
+                        }
+                        Option::None => self.push("**"),
+                    }
+                    self.expression(&keyword.value, PR_TEST);
+                }
+                self.push(")");
+            }
+            Number { value } => self.number(value),
+            List { elements } => {
+                self.push("[");
+                self.expressions(elements, PR_TEST);
+                self.push("]");
+            }
+            Tuple { elements } => {
+                if elements.is_empty() {
+                    self.push("()");
+                } else {
+                    self.open_paren(level, PR_TUPLE);
+                    self.expressions(elements, PR_TEST);
+                    if elements.len() == 1 {
+                        self.push(",");
+                    }
+                    self.close_paren(level, PR_TUPLE);
+                }
+            }
+            Set { elements } => {
+                self.push("{");
+                self.expressions(elements, PR_TEST);
+                self.push("}");
+            }
+            Dict { elements } => {
+                self.push("{");
+                for (i, (key, value)) in elements.iter().enumerate() {
+                    if i > 0 {
+                        self.push(", ");
+                    }
+                    match key {
+                        Some(key) => {
+                            self.expression(key, PR_TEST);
+                            self.push(": ");
+                            self.expression(value, PR_TEST);
+                        }
+                        Option::None => {
+                            self.push("**");
+                            self.expression(value, PR_EXPR);
+                        }
+                    }
+                }
+                self.push("}");
+            }
+            Comprehension { kind, generators } => {
+                let close = match &**kind {
+                    ast::ComprehensionKind::GeneratorExpression { element } => {
+                        self.push("(");
+                        self.expression(element, PR_TEST);
+                        ")"
+                    }
+                    ast::ComprehensionKind::List { element } => {
+                        self.push("[");
+                        self.expression(element, PR_TEST);
+                        "]"
+                    }
+                    ast::ComprehensionKind::Set { element } => {
+                        self.push("{");
+                        self.expression(element, PR_TEST);
+                        "}"
+                    }
+                    ast::ComprehensionKind::Dict { key, value } => {
+                        self.push("{");
+                        self.expression(key, PR_TEST);
+                        self.push(": ");
+                        self.expression(value, PR_TEST);
+                        "}"
+                    }
+                };
+                self.comprehensions(generators);
+                self.push(close);
+            }
+            Starred { value } => {
+                self.push("*");
+                self.expression(value, PR_EXPR);
+            }
+            String { value } => match value {
+                ast::StringGroup::Constant { value, stand_ins } => {
+                    self.push(&string_repr(value, *stand_ins))
+                }
+                _ => {
+                    let mut body = Unparser {
+                        source: std::string::String::new(),
+                    };
+                    body.fstring_part(value);
+                    self.push("f");
+                    self.push(&string_repr(&body.source, fstring_stand_ins(value)));
+                }
+            },
+            Bytes { value } => self.push(&bytes_repr(value)),
+            Identifier { name } => self.push(name),
+            Lambda { args, body } => {
+                self.open_paren(level, PR_TEST);
+                self.push("lambda");
+                if !is_empty_parameters(args) {
+                    self.push(" ");
+                    self.parameters(args);
+                }
+                self.push(": ");
+                self.expression(body, PR_TEST);
+                self.close_paren(level, PR_TEST);
+            }
+            IfExpression { test, body, orelse } => {
+                self.open_paren(level, PR_TEST);
+                self.expression(body, PR_TEST + 1);
+                self.push(" if ");
+                self.expression(test, PR_TEST + 1);
+                self.push(" else ");
+                self.expression(orelse, PR_TEST);
+                self.close_paren(level, PR_TEST);
+            }
+            True => self.push("True"),
+            False => self.push("False"),
+            None => self.push("None"),
+            Ellipsis => self.push("..."),
+        }
+    }
+
+    fn slice_part(&mut self, expression: &ast::Expression) {
+        if !is_none(expression) {
+            self.expression(expression, PR_TEST);
+        }
+    }
+
+    fn comprehensions(&mut self, generators: &[ast::Comprehension]) {
+        for generator in generators {
+            self.push(" for ");
+            self.expression(&generator.target, PR_TUPLE);
+            self.push(" in ");
+            self.expression(&generator.iter, PR_TEST + 1);
+            for condition in &generator.ifs {
+                self.push(" if ");
+                self.expression(condition, PR_TEST + 1);
+            }
+        }
+    }
+
+    fn parameters(&mut self, args: &ast::Parameters) {
+        let mut first = true;
+        let mut separator = |unparser: &mut Self| {
+            if !first {
+                unparser.push(", ");
+            }
+            first = false;
+        };
+
+        // Defaults belong to the last positional parameters.
+        let defaults_start = args.args.len() - args.defaults.len();
+        for (i, parameter) in args.args.iter().enumerate() {
+            separator(self);
+            self.push(&parameter.arg);
+            if i >= defaults_start {
+                self.push("=");
+                self.expression(&args.defaults[i - defaults_start], PR_TEST);
+            }
+        }
+
+        match &args.vararg {
+            ast::Varargs::Named(parameter) => {
+                separator(self);
+                self.push("*");
+                self.push(&parameter.arg);
+            }
+            ast::Varargs::Unnamed => {
+                separator(self);
+                self.push("*");
+            }
+            ast::Varargs::None => {}
+        }
+
+        for (parameter, default) in args.kwonlyargs.iter().zip(args.kw_defaults.iter()) {
+            separator(self);
+            self.push(&parameter.arg);
+            if let Some(default) = default {
+                self.push("=");
+                self.expression(default, PR_TEST);
+            }
+        }
+
+        if let ast::Varargs::Named(parameter) = &args.kwarg {
+            separator(self);
+            self.push("**");
+            self.push(&parameter.arg);
+        }
+    }
+
+    fn number(&mut self, value: &ast::Number) {
+        match value {
+            ast::Number::Integer { value } => self.push(&value.to_string()),
+            ast::Number::Float { value } => self.push(&float_repr(*value)),
+            ast::Number::Complex { real, imag } => {
+                if *real == 0.0 {
+                    self.push(float_repr(*imag).trim_end_matches(".0"));
+                    self.push("j");
+                } else {
+                    self.push("(");
+                    self.push(&float_repr(*real));
+                    self.push(" + ");
+                    self.push(&float_repr(*imag));
+                    self.push("j)");
+                }
+            }
+        }
+    }
+
+    /// Write the body of an f-string, without quotes.
+    fn fstring_part(&mut self, value: &ast::StringGroup) {
+        match value {
+            ast::StringGroup::Constant { value, .. } => {
+                self.push(&value.replace('{', "{{").replace('}', "}}"));
+            }
+            ast::StringGroup::FormattedValue {
+                value,
+                conversion,
+                spec,
+            } => {
+                let mut inner = Unparser {
+                    source: String::new(),
+                };
+                inner.expression(value, PR_TEST + 1);
+                self.push("{");
+                // Avoid writing a literal `{{`.
+                if inner.source.starts_with('{') {
+                    self.push(" ");
+                }
+                self.push(&inner.source);
+                match conversion {
+                    Some(ast::ConversionFlag::Str) => self.push("!s"),
+                    Some(ast::ConversionFlag::Ascii) => self.push("!a"),
+                    Some(ast::ConversionFlag::Repr) => self.push("!r"),
+                    None => {}
+                }
+                if !spec.is_empty() {
+                    self.push(":");
+                    self.push(spec);
+                }
+                self.push("}");
+            }
+            ast::StringGroup::Joined { values } => {
+                for value in values {
+                    self.fstring_part(value);
+                }
+            }
+        }
+    }
+}
+
+fn is_none(expression: &ast::Expression) -> bool {
+    if let ast::ExpressionType::None = expression.node {
+        true
+    } else {
+        false
+    }
+}
+
+fn is_empty_parameters(args: &ast::Parameters) -> bool {
+    args.args.is_empty()
+        && args.kwonlyargs.is_empty()
+        && if let (ast::Varargs::None, ast::Varargs::None) = (&args.vararg, &args.kwarg) {
+            true
+        } else {
+            false
+        }
+}
+
+fn binary_operator(op: &ast::Operator) -> (&'static str, u8) {
+    match op {
+        ast::Operator::Add => (" + ", PR_ARITH),
+        ast::Operator::Sub => (" - ", PR_ARITH),
+        ast::Operator::Mult => (" * ", PR_TERM),
+        ast::Operator::MatMult => (" @ ", PR_TERM),
+        ast::Operator::Div => (" / ", PR_TERM),
+        ast::Operator::Mod => (" % ", PR_TERM),
+        ast::Operator::FloorDiv => (" // ", PR_TERM),
+        ast::Operator::Pow => (" ** ", PR_POWER),
+        ast::Operator::LShift => (" << ", PR_SHIFT),
+        ast::Operator::RShift => (" >> ", PR_SHIFT),
+        ast::Operator::BitOr => (" | ", PR_BOR),
+        ast::Operator::BitXor => (" ^ ", PR_BXOR),
+        ast::Operator::BitAnd => (" & ", PR_BAND),
+    }
+}
+
+fn comparison(op: &ast::Comparison) -> &'static str {
+    match op {
+        ast::Comparison::Equal => " == ",
+        ast::Comparison::NotEqual => " != ",
+        ast::Comparison::Less => " < ",
+        ast::Comparison::LessOrEqual => " <= ",
+        ast::Comparison::Greater => " > ",
+        ast::Comparison::GreaterOrEqual => " >= ",
+        ast::Comparison::In => " in ",
+        ast::Comparison::NotIn => " not in ",
+        ast::Comparison::Is => " is ",
+        ast::Comparison::IsNot => " is not ",
+    }
+}
+
+/// Format a float the way python's repr does.
+fn float_repr(value: f64) -> String {
+    if value.is_infinite() {
+        // Overflows to infinity when parsed again.
+        return "1e309".to_string();
+    }
+    let repr = format!("{:?}", value);
+    match repr.find('e') {
+        Some(index) => {
+            let (mantissa, exponent) = (&repr[..index], &repr[index + 1..]);
+            let (sign, digits) = match exponent.strip_prefix('-') {
+                Some(digits) => ("-", digits),
+                None => ("+", exponent),
+            };
+            let mantissa = mantissa.trim_end_matches(".0");
+            format!("{}e{}{:0>2}", mantissa, sign, digits)
+        }
+        None => repr,
+    }
+}
+
+/// Quote a string the way python's repr does.
+fn string_repr(value: &str, stand_ins: Option<u32>) -> String {
+    let quote = if value.contains('\'') && !value.contains('"') {
+        '"'
+    } else {
+        '\''
+    };
+    let mut repr = String::with_capacity(value.len() + 2);
+    repr.push(quote);
+    for c in value.chars() {
+        match c {
+            '\\' => repr.push_str("\\\\"),
+            '\n' => repr.push_str("\\n"),
+            '\r' => repr.push_str("\\r"),
+            '\t' => repr.push_str("\\t"),
+            c if c == quote => {
+                repr.push('\\');
+                repr.push(c);
+            }
+            c if c < ' ' || c == '\x7f' => write!(repr, "\\x{:02x}", c as u32).unwrap(),
+            c => match surrogate(c, stand_ins) {
+                Some(surrogate) => write!(repr, "\\u{:04x}", surrogate).unwrap(),
+                None => repr.push(c),
+            },
+        }
+    }
+    repr.push(quote);
+    repr
+}
+
+/// Where the stand-ins for lone surrogates in the constant parts of an
+/// f-string start. The parts all come from one literal, so they share them.
+fn fstring_stand_ins(value: &ast::StringGroup) -> Option<u32> {
+    match value {
+        ast::StringGroup::Constant { stand_ins, .. } => *stand_ins,
+        ast::StringGroup::FormattedValue { .. } => None,
+        ast::StringGroup::Joined { values } => values.iter().find_map(fstring_stand_ins),
+    }
+}
+
+/// The lone surrogate that `c` stands in for in a string constant whose
+/// stand-ins start at `stand_ins`.
+fn surrogate(c: char, stand_ins: Option<u32>) -> Option<u32> {
+    let first = stand_ins?;
+    let offset = (c as u32).checked_sub(first)?;
+    if offset < 0x800 {
+        Some(0xd800 + offset)
+    } else {
+        None
+    }
+}
+
+/// Quote a bytes literal the way python's repr does.
+fn bytes_repr(value: &[u8]) -> String {
+    let quote = if value.contains(&b'\'') && !value.contains(&b'"') {
+        b'"'
+    } else {
+        b'\''
+    };
+    let mut repr = String::with_capacity(value.len() + 3);
+    repr.push('b');
+    repr.push(quote as char);
+    for &byte in value {
+        match byte {
+            b'\\' => repr.push_str("\\\\"),
+            b'\n' => repr.push_str("\\n"),
+            b'\r' => repr.push_str("\\r"),
+            b'\t' => repr.push_str("\\t"),
+            byte if byte == quote => {
+                repr.push('\\');
+                repr.push(byte as char);
+            }
+            0x20..=0x7e => repr.push(byte as char),
+            byte => write!(repr, "\\x{:02x}", byte).unwrap(),
+        }
+    }
+    repr.push(quote as char);
+    repr
+}
+
+#[cfg(test)]
+mod tests {
+    use super::unparse_expression;
+    use rustpython_parser::parser::parse_expression;
+
+    fn roundtrip(source: &str) -> String {
+        unparse_expression(&parse_expression(source).unwrap())
+    }
+
+    #[test]
+    fn test_unparse_roundtrip() {
+        for source in &[
+            "int",
+            "typing.List[int]",
+            "Dict[str, Tuple[int, ...]]",
+            "a + b * c",
+            "(a + b) * c",
+            "a ** b ** c",
+            "(a ** b) ** c",
+            "-x ** 2",
+            "not a and (b or c)",
+            "a if b else c",
+            "lambda x, *args, y=1, **kw: x",
+            "f(x, *args, key=1, **kw)",
+            "f(x for x in y if x)",
+            "[x * 2 for x in range(10)]",
+            "{k: v for k, v in items}",
+            "x[1:2, ::3]",
+            "\"it's\" + 'quote'",
+            "b'\\x00abc'",
+            "1 .real",
+            "0.5",
+            "1e+100",
+            "2j",
+            "{'a': 1, **other}",
+            "()",
+            "(1,)",
+            "a < b <= c is not None",
+            "f'{x!r:>10} {{braces}}'",
+        ] {
+            assert_eq!(&roundtrip(source), source);
+        }
+    }
+}
@@ -1,9 +1,28 @@
 //! Implement abstract syntax tree nodes for the python language.
 //!
 //! Roughly equivalent to this: https://docs.python.org/3/library/ast.html
+//!
+//! With the `serialize` feature enabled, all nodes implement serde's
+//! `Serialize` and `Deserialize`. The serialized form is part of the public
+//! interface of this crate and is kept stable:
+//!
+//! * structs serialize as maps of their fields,
+//! * every enum serializes as a map with a `"type"` entry naming the variant,
+//!   next to the fields of that variant (`{"type": "Break"}` for variants
+//!   without fields),
+//! * statements and expressions carry their `"location"` (`{"row", "column"}`)
+//!   alongside the fields of their kind,
+//! * integer literals are decimal strings, so that they survive json parsers
+//!   which only support 64-bit numbers.
+//!
+//! ```json
+//! {"location": {"row": 1, "column": 3}, "type": "Binop", "op": {"type": "Add"}, "a": ..., "b": ...}
+//! ```
 
 pub use crate::location::Location;
 use num_bigint::BigInt;
+#[cfg(feature = "serialize")]
+use serde::{Deserialize, Serialize};
 
 /*
 #[derive(Debug)]
@@ -22,19 +41,23 @@ pub enum Top {
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct Program {
     pub statements: Vec<Statement>,
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct ImportSymbol {
     pub symbol: String,
     pub alias: Option<String>,
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct Located<T> {
     pub location: Location,
+    #[cfg_attr(feature = "serialize", serde(flatten))]
     pub node: T,
 }
 
@@ -43,6 +66,8 @@ pub type Statement = Located<StatementType>;
 /// Abstract syntax tree nodes for python statements.
 #[allow(clippy::large_enum_variant)]
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialize", serde(tag = "type"))]
 pub enum StatementType {
     Break,
     Continue,
@@ -74,6 +99,14 @@ pub enum StatementType {
         op: Operator,
         value: Box<Expression>,
     },
+    AnnAssign {
+        target: Box<Expression>,
+        annotation: Box<Expression>,
+        value: Option<Expression>,
+        /// The target is a name without parentheses around it, which is all
+        /// that is recorded in `__annotations__`.
+        simple: bool,
+    },
     Expression {
         expression: Expression,
     },
@@ -133,6 +166,7 @@ pub enum StatementType {
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct WithItem {
     pub context_expr: Expression,
     pub optional_vars: Option<Expression>,
@@ -141,6 +175,8 @@ pub struct WithItem {
 pub type Expression = Located<ExpressionType>;
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialize", serde(tag = "type"))]
 pub enum ExpressionType {
     BoolOp {
         a: Box<Expression>,
@@ -282,6 +318,7 @@ impl Expression {
  * distinguish between function parameters and actual call arguments.
  */
 #[derive(Debug, PartialEq, Default)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct Parameters {
     pub args: Vec<Parameter>,
     pub kwonlyargs: Vec<Parameter>,
@@ -292,6 +329,7 @@ pub struct Parameters {
 }
 
 #[derive(Debug, PartialEq, Default)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct Parameter {
     pub arg: String,
     pub annotation: Option<Box<Expression>>,
@@ -299,6 +337,8 @@ pub struct Parameter {
 
 #[allow(clippy::large_enum_variant)]
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialize", serde(tag = "type"))]
 pub enum ComprehensionKind {
     GeneratorExpression { element: Expression },
     List { element: Expression },
@@ -307,6 +347,7 @@ pub enum ComprehensionKind {
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct Comprehension {
     pub target: Expression,
     pub iter: Expression,
@@ -314,12 +355,14 @@ pub struct Comprehension {
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct Keyword {
     pub name: Option<String>,
     pub value: Expression,
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct ExceptHandler {
     pub typ: Option<Expression>,
     pub name: Option<String>,
@@ -327,6 +370,8 @@ pub struct ExceptHandler {
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialize", serde(tag = "type"))]
 pub enum Operator {
     Add,
     Sub,
@@ -344,12 +389,16 @@ pub enum Operator {
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialize", serde(tag = "type"))]
 pub enum BooleanOperator {
     And,
     Or,
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialize", serde(tag = "type"))]
 pub enum UnaryOperator {
     Pos,
     Neg,
@@ -358,6 +407,8 @@ pub enum UnaryOperator {
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialize", serde(tag = "type"))]
 pub enum Comparison {
     Equal,
     NotEqual,
@@ -372,14 +423,26 @@ pub enum Comparison {
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialize", serde(tag = "type"))]
 pub enum Number {
-    Integer { value: BigInt },
-    Float { value: f64 },
-    Complex { real: f64, imag: f64 },
+    Integer {
+        #[cfg_attr(feature = "serialize", serde(with = "bigint_as_string"))]
+        value: BigInt,
+    },
+    Float {
+        value: f64,
+    },
+    Complex {
+        real: f64,
+        imag: f64,
+    },
 }
 
 /// Transforms a value prior to formatting it.
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialize", serde(tag = "type"))]
 pub enum ConversionFlag {
     /// Converts by calling `str(<value>)`.
     Str,
@@ -390,9 +453,14 @@ pub enum ConversionFlag {
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialize", serde(tag = "type"))]
 pub enum StringGroup {
     Constant {
         value: String,
+        /// Where the code points standing for lone surrogates in `value`
+        /// start, if it holds any; see `lexer::StringBuilder`.
+        stand_ins: Option<u32>,
     },
     FormattedValue {
         value: Box<Expression>,
@@ -405,6 +473,8 @@ pub enum StringGroup {
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialize", serde(tag = "type"))]
 pub enum Varargs {
     None,
     Unnamed,
@@ -428,3 +498,19 @@ impl From<Option<Option<Parameter>>> for Varargs {
         }
     }
 }
+
+/// Serializes big integers as their decimal representation.
+#[cfg(feature = "serialize")]
+mod bigint_as_string {
+    use num_bigint::BigInt;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &BigInt, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(value)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BigInt, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(de::Error::custom)
+    }
+}
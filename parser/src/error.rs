@@ -20,7 +20,16 @@ pub enum LexicalErrorType {
     StringError,
     UnicodeError,
     NestingError,
-    UnrecognizedToken { tok: char },
+    /// A dedent to a column that matches no enclosing indentation level.
+    IndentationError,
+    /// Tabs and spaces mixed so that the meaning depends on the tab size.
+    TabError,
+    /// The input ended inside a bracket, a triple-quoted string or after a
+    /// line continuation, so more input could make it valid.
+    EOF,
+    UnrecognizedToken {
+        tok: char,
+    },
     FStringError(FStringErrorType),
     OtherError(String),
 }
@@ -32,6 +41,13 @@ impl fmt::Display for LexicalErrorType {
             LexicalErrorType::FStringError(error) => write!(f, "Got error in f-string: {}", error),
             LexicalErrorType::UnicodeError => write!(f, "Got unexpected unicode"),
             LexicalErrorType::NestingError => write!(f, "Got unexpected nesting"),
+            LexicalErrorType::IndentationError => {
+                write!(f, "unindent does not match any outer indentation level")
+            }
+            LexicalErrorType::TabError => {
+                write!(f, "inconsistent use of tabs and spaces in indentation")
+            }
+            LexicalErrorType::EOF => write!(f, "Got unexpected EOF"),
             LexicalErrorType::UnrecognizedToken { tok } => {
                 write!(f, "Got unexpected token {}", tok)
             }
@@ -117,6 +133,16 @@ impl From<LalrpopError<Location, Tok, LexicalError>> for ParseError {
                 error: ParseErrorType::ExtraToken(token.1),
                 location: token.0,
             },
+            LalrpopError::User {
+                error:
+                    LexicalError {
+                        error: LexicalErrorType::EOF,
+                        location,
+                    },
+            } => ParseError {
+                error: ParseErrorType::EOF,
+                location,
+            },
             LalrpopError::User { error } => ParseError {
                 error: ParseErrorType::Lexical(error.error),
                 location: error.location,
@@ -150,6 +176,12 @@ impl fmt::Display for ParseErrorType {
             ParseErrorType::EOF => write!(f, "Got unexpected EOF"),
             ParseErrorType::ExtraToken(ref tok) => write!(f, "Got extraneous token: {:?}", tok),
             ParseErrorType::InvalidToken => write!(f, "Got invalid token"),
+            ParseErrorType::UnrecognizedToken(Tok::Indent, _) => write!(f, "unexpected indent"),
+            ParseErrorType::UnrecognizedToken(_, ref expected)
+                if expected.iter().any(|name| name == "indent") =>
+            {
+                write!(f, "expected an indented block")
+            }
             ParseErrorType::UnrecognizedToken(ref tok, _) => {
                 write!(f, "Got unexpected token: {:?}", tok)
             }
@@ -158,6 +190,29 @@ impl fmt::Display for ParseErrorType {
     }
 }
 
+impl ParseErrorType {
+    /// Whether this error is about indentation, python raises an
+    /// `IndentationError` for these.
+    pub fn is_indentation_error(&self) -> bool {
+        match self {
+            ParseErrorType::Lexical(LexicalErrorType::IndentationError) => true,
+            ParseErrorType::UnrecognizedToken(token, expected) => {
+                *token == Tok::Indent || expected.iter().any(|name| name == "indent")
+            }
+            _ => self.is_tab_error(),
+        }
+    }
+
+    /// Whether this error is about mixing tabs and spaces, python raises a
+    /// `TabError` for these.
+    pub fn is_tab_error(&self) -> bool {
+        match self {
+            ParseErrorType::Lexical(LexicalErrorType::TabError) => true,
+            _ => false,
+        }
+    }
+}
+
 impl Error for ParseError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         None
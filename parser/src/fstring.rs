@@ -12,12 +12,14 @@ use self::StringGroup::*;
 
 struct FStringParser<'a> {
     chars: iter::Peekable<str::Chars<'a>>,
+    stand_ins: Option<u32>,
 }
 
 impl<'a> FStringParser<'a> {
-    fn new(source: &'a str) -> Self {
+    fn new(source: &'a str, stand_ins: Option<u32>) -> Self {
         Self {
             chars: source.chars().peekable(),
+            stand_ins,
         }
     }
 
@@ -119,6 +121,7 @@ impl<'a> FStringParser<'a> {
                         if !content.is_empty() {
                             values.push(Constant {
                                 value: mem::replace(&mut content, String::new()),
+                                stand_ins: self.stand_ins,
                             });
                         }
 
@@ -140,12 +143,16 @@ impl<'a> FStringParser<'a> {
         }
 
         if !content.is_empty() {
-            values.push(Constant { value: content })
+            values.push(Constant {
+                value: content,
+                stand_ins: self.stand_ins,
+            })
         }
 
         Ok(match values.len() {
             0 => Constant {
                 value: String::new(),
+                stand_ins: None,
             },
             1 => values.into_iter().next().unwrap(),
             _ => Joined { values },
@@ -154,17 +161,18 @@ impl<'a> FStringParser<'a> {
 }
 
 /// Parse an f-string into a string group.
-fn parse_fstring(source: &str) -> Result<StringGroup, FStringErrorType> {
-    FStringParser::new(source).parse()
+fn parse_fstring(source: &str, stand_ins: Option<u32>) -> Result<StringGroup, FStringErrorType> {
+    FStringParser::new(source, stand_ins).parse()
 }
 
 /// Parse an fstring from a string, located at a certain position in the sourcecode.
 /// In case of errors, we will get the location and the error returned.
 pub fn parse_located_fstring(
     source: &str,
+    stand_ins: Option<u32>,
     location: Location,
 ) -> Result<StringGroup, FStringError> {
-    parse_fstring(source).map_err(|error| FStringError { error, location })
+    parse_fstring(source, stand_ins).map_err(|error| FStringError { error, location })
 }
 
 #[cfg(test)]
@@ -185,7 +193,7 @@ mod tests {
     #[test]
     fn test_parse_fstring() {
         let source = String::from("{a}{ b }{{foo}}");
-        let parse_ast = parse_fstring(&source).unwrap();
+        let parse_ast = parse_fstring(&source, None).unwrap();
 
         assert_eq!(
             parse_ast,
@@ -202,7 +210,8 @@ mod tests {
                         spec: String::new(),
                     },
                     Constant {
-                        value: "{foo}".to_owned()
+                        value: "{foo}".to_owned(),
+                        stand_ins: None,
                     }
                 ]
             }
@@ -212,19 +221,20 @@ mod tests {
     #[test]
     fn test_parse_empty_fstring() {
         assert_eq!(
-            parse_fstring(""),
+            parse_fstring("", None),
             Ok(Constant {
                 value: String::new(),
+                stand_ins: None,
             }),
         );
     }
 
     #[test]
     fn test_parse_invalid_fstring() {
-        assert_eq!(parse_fstring("{"), Err(UnclosedLbrace));
-        assert_eq!(parse_fstring("}"), Err(UnopenedRbrace));
+        assert_eq!(parse_fstring("{", None), Err(UnclosedLbrace));
+        assert_eq!(parse_fstring("}", None), Err(UnopenedRbrace));
 
         // TODO: check for InvalidExpression enum?
-        assert!(parse_fstring("{class}").is_err());
+        assert!(parse_fstring("{class}", None).is_err());
     }
 }
@@ -2,6 +2,7 @@
 //! code is translated into separate tokens.
 
 extern crate unic_emoji_char;
+extern crate unic_normal;
 extern crate unicode_xid;
 
 pub use super::token::Tok;
@@ -13,48 +14,49 @@ use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::str::FromStr;
 use unic_emoji_char::is_emoji_presentation;
+use unic_normal::StrNormalForm;
 use unicode_xid::UnicodeXID;
 use wtf8;
 
+/// The width of an indentation, measured like CPython does: once with tabs
+/// advancing to the next multiple of 8, and once with tabs as a single column.
+/// If the two disagree about how lines nest, the meaning of the code depends
+/// on the tab size, which is a `TabError`.
 #[derive(Clone, Copy, PartialEq, Debug, Default)]
 struct IndentationLevel {
-    tabs: usize,
-    spaces: usize,
+    column: usize,
+    alt_column: usize,
 }
 
+const TAB_SIZE: usize = 8;
+
 impl IndentationLevel {
+    fn add_space(&mut self) {
+        self.column += 1;
+        self.alt_column += 1;
+    }
+
+    fn add_tab(&mut self) {
+        self.column = (self.column / TAB_SIZE + 1) * TAB_SIZE;
+        self.alt_column += 1;
+    }
+
+    /// Compare with an enclosing level, this is only ambiguous when the new
+    /// level is the same or deeper. Dedents are checked once the matching
+    /// level is found.
     fn compare_strict(
         &self,
         other: &IndentationLevel,
         location: Location,
     ) -> Result<Ordering, LexicalError> {
-        // We only know for sure that we're smaller or bigger if tabs
-        // and spaces both differ in the same direction. Otherwise we're
-        // dependent on the size of tabs.
-        if self.tabs < other.tabs {
-            if self.spaces <= other.spaces {
-                Ok(Ordering::Less)
-            } else {
-                Err(LexicalError {
-                    location,
-                    error: LexicalErrorType::OtherError(
-                        "inconsistent use of tabs and spaces in indentation".to_string(),
-                    ),
-                })
-            }
-        } else if self.tabs > other.tabs {
-            if self.spaces >= other.spaces {
-                Ok(Ordering::Greater)
-            } else {
-                Err(LexicalError {
-                    location,
-                    error: LexicalErrorType::OtherError(
-                        "inconsistent use of tabs and spaces in indentation".to_string(),
-                    ),
-                })
-            }
+        let ordering = self.column.cmp(&other.column);
+        if ordering == Ordering::Less || ordering == self.alt_column.cmp(&other.alt_column) {
+            Ok(ordering)
         } else {
-            Ok(self.spaces.cmp(&other.spaces))
+            Err(LexicalError {
+                error: LexicalErrorType::TabError,
+                location,
+            })
         }
     }
 }
@@ -69,6 +71,7 @@ pub struct Lexer<T: Iterator<Item = char>> {
     chr1: Option<char>,
     location: Location,
     keywords: HashMap<String, Tok>,
+    keep_trivia: bool,
 }
 
 pub fn get_keywords() -> HashMap<String, Tok> {
@@ -120,80 +123,174 @@ pub type LexResult = Result<Spanned, LexicalError>;
 
 pub fn make_tokenizer<'a>(source: &'a str) -> impl Iterator<Item = LexResult> + 'a {
     let nlh = NewlineHandler::new(source.chars());
-    let lch = LineContinationHandler::new(nlh);
-    Lexer::new(lch)
+    Lexer::new(nlh)
 }
 
-// The newline handler is an iterator which collapses different newline
-// types into \n always.
-pub struct NewlineHandler<T: Iterator<Item = char>> {
-    source: T,
-    chr0: Option<char>,
-    chr1: Option<char>,
+/// Like `make_tokenizer`, but also yields comments and non-logical newlines,
+/// which the parser has no use for but source tools (like the `tokenize`
+/// module) do.
+pub fn make_tokenizer_with_trivia<'a>(source: &'a str) -> impl Iterator<Item = LexResult> + 'a {
+    let nlh = NewlineHandler::new(source.chars());
+    let mut lexer = Lexer::new(nlh);
+    lexer.keep_trivia = true;
+    lexer
 }
 
-impl<T> NewlineHandler<T>
-where
-    T: Iterator<Item = char>,
-{
-    pub fn new(source: T) -> Self {
-        let mut nlh = NewlineHandler {
-            source,
-            chr0: None,
-            chr1: None,
-        };
-        nlh.shift();
-        nlh.shift();
-        nlh
+// Characters with the Other_ID_Start and Other_ID_Continue properties, which
+// PEP 3131 allows on top of XID_Start and XID_Continue. U+309B and U+309C are
+// left out, because their NFKC form starts with a space.
+const OTHER_ID_START: &[char] = &['\u{1885}', '\u{1886}', '\u{2118}', '\u{212e}'];
+const OTHER_ID_CONTINUE: &[char] = &[
+    '\u{00b7}', '\u{0387}', '\u{1369}', '\u{136a}', '\u{136b}', '\u{136c}', '\u{136d}', '\u{136e}',
+    '\u{136f}', '\u{1370}', '\u{1371}', '\u{19da}',
+];
+
+/// Check whether a character may start an identifier (PEP 3131).
+pub fn is_identifier_start(c: char) -> bool {
+    match c {
+        'a'..='z' | 'A'..='Z' | '_' => true,
+        c => UnicodeXID::is_xid_start(c) || OTHER_ID_START.contains(&c),
     }
+}
 
-    fn shift(&mut self) -> Option<char> {
-        let result = self.chr0;
-        self.chr0 = self.chr1;
-        self.chr1 = self.source.next();
-        result
+/// Check whether a character may appear in an identifier after the first one.
+pub fn is_identifier_continuation(c: char) -> bool {
+    match c {
+        'a'..='z' | 'A'..='Z' | '_' | '0'..='9' => true,
+        c => {
+            UnicodeXID::is_xid_continue(c)
+                || OTHER_ID_START.contains(&c)
+                || OTHER_ID_CONTINUE.contains(&c)
+        }
     }
 }
 
-impl<T> Iterator for NewlineHandler<T>
-where
-    T: Iterator<Item = char>,
-{
-    type Item = char;
+/// Check whether a string is a valid identifier, this is what `str.isidentifier`
+/// uses. Keywords are identifiers too.
+pub fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().map_or(false, is_identifier_start) && chars.all(is_identifier_continuation)
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        // Collapse \r\n into \n
-        loop {
-            if self.chr0 == Some('\r') {
-                if self.chr1 == Some('\n') {
-                    // Transform windows EOL into \n
-                    self.shift();
-                } else {
-                    // Transform MAC EOL into \n
-                    self.chr0 = Some('\n')
-                }
+/// Identifiers are compared in NFKC form, so `ﬁ` and `fi` are the same name.
+pub fn normalize_identifier(name: String) -> String {
+    if name.is_ascii() {
+        name
+    } else {
+        name.nfkc().collect()
+    }
+}
+
+/// The number of code points standing for lone surrogates in a string, one
+/// for each surrogate.
+const STAND_INS_LEN: u32 = 0x800;
+/// Where the stand-ins start unless the string holds those code points
+/// itself.
+const DEFAULT_STAND_INS: u32 = 0x11_0000 - STAND_INS_LEN;
+
+/// Builds the value of a string literal. A string holds each lone surrogate
+/// as one of a block of 2048 supplementary code points, which is U+10F800 to
+/// U+10FFFF unless the string holds some of those itself, and then the
+/// highest block it doesn't use. The value comes with the first code point
+/// of the block if it holds any surrogates. This has to agree with
+/// `rustpython_vm::surrogates`.
+#[derive(Default)]
+pub struct StringBuilder {
+    value: String,
+    /// Where the stand-ins for surrogates start in `value`. They are the
+    /// default ones until `finish` looks for others.
+    surrogates: Vec<usize>,
+}
+
+impl StringBuilder {
+    pub fn push(&mut self, c: char) {
+        self.value.push(c);
+    }
+
+    pub fn push_str(&mut self, s: &str) {
+        self.value.push_str(s);
+    }
+
+    /// Push a character or a lone surrogate.
+    pub fn push_code_point(&mut self, code_point: u32) {
+        match std::char::from_u32(code_point) {
+            Some(c) => self.push(c),
+            None => {
+                self.surrogates.push(self.value.len());
+                let stand_in = code_point - 0xD800 + DEFAULT_STAND_INS;
+                self.push(std::char::from_u32(stand_in).unwrap());
+            }
+        }
+    }
+
+    /// Push the value of another string, with its stand-ins.
+    pub fn push_value(&mut self, value: &str, stand_ins: Option<u32>) {
+        let first = match stand_ins {
+            Some(first) => first,
+            None => return self.push_str(value),
+        };
+        for c in value.chars() {
+            let offset = (c as u32).wrapping_sub(first);
+            if offset < STAND_INS_LEN {
+                self.push_code_point(0xD800 + offset);
             } else {
-                break;
+                self.push(c);
             }
         }
+    }
 
-        self.shift()
+    /// The value built and its stand-ins, or `None` if the value uses every
+    /// block of supplementary code points, leaving none for its surrogates.
+    pub fn finish(self) -> Option<(String, Option<u32>)> {
+        if self.surrogates.is_empty() {
+            return Some((self.value, None));
+        }
+        let is_default = |c: char| (c as u32).wrapping_sub(DEFAULT_STAND_INS) < STAND_INS_LEN;
+        if self.value.chars().filter(|&c| is_default(c)).count() == self.surrogates.len() {
+            return Some((self.value, Some(DEFAULT_STAND_INS)));
+        }
+        // Some of the default stand-ins are in the string as themselves.
+        let surrogates = &self.surrogates;
+        let is_surrogate_at = |offset| surrogates.binary_search(&offset).is_ok();
+        let mut taken = vec![false; (0x11_0000 / STAND_INS_LEN) as usize];
+        for (offset, c) in self.value.char_indices() {
+            if !is_surrogate_at(offset) {
+                taken[(c as u32 / STAND_INS_LEN) as usize] = true;
+            }
+        }
+        let block = (0x1_0000 / STAND_INS_LEN..0x11_0000 / STAND_INS_LEN)
+            .rev()
+            .find(|&block| !taken[block as usize])?;
+        let first = block * STAND_INS_LEN;
+        let value = self
+            .value
+            .char_indices()
+            .map(|(offset, c)| {
+                if is_surrogate_at(offset) {
+                    std::char::from_u32(c as u32 - DEFAULT_STAND_INS + first).unwrap()
+                } else {
+                    c
+                }
+            })
+            .collect();
+        Some((value, Some(first)))
     }
 }
 
-// Glues \ and \n into a single line:
-pub struct LineContinationHandler<T: Iterator<Item = char>> {
+// The newline handler is an iterator which collapses different newline
+// types into \n always.
+pub struct NewlineHandler<T: Iterator<Item = char>> {
     source: T,
     chr0: Option<char>,
     chr1: Option<char>,
 }
 
-impl<T> LineContinationHandler<T>
+impl<T> NewlineHandler<T>
 where
     T: Iterator<Item = char>,
 {
     pub fn new(source: T) -> Self {
-        let mut nlh = LineContinationHandler {
+        let mut nlh = NewlineHandler {
             source,
             chr0: None,
             chr1: None,
@@ -211,7 +308,7 @@ where
     }
 }
 
-impl<T> Iterator for LineContinationHandler<T>
+impl<T> Iterator for NewlineHandler<T>
 where
     T: Iterator<Item = char>,
 {
@@ -220,13 +317,14 @@ where
     fn next(&mut self) -> Option<Self::Item> {
         // Collapse \r\n into \n
         loop {
-            if self.chr0 == Some('\\') && self.chr1 == Some('\n') {
-                // Skip backslash and newline
-                self.shift();
-                self.shift();
-            // Idea: insert trailing newline here:
-            // } else if self.chr0 != Some('\n') && self.chr1.is_none() {
-            //     self.chr1 = Some('\n');
+            if self.chr0 == Some('\r') {
+                if self.chr1 == Some('\n') {
+                    // Transform windows EOL into \n
+                    self.shift();
+                } else {
+                    // Transform MAC EOL into \n
+                    self.chr0 = Some('\n')
+                }
             } else {
                 break;
             }
@@ -251,6 +349,7 @@ where
             location: Location::new(0, 0),
             chr1: None,
             keywords: get_keywords(),
+            keep_trivia: false,
         };
         lxr.next_char();
         lxr.next_char();
@@ -292,7 +391,7 @@ where
 
             // Check if we have a string:
             if self.chr0 == Some('"') || self.chr0 == Some('\'') {
-                return self.lex_string(saw_b, saw_r, saw_u, saw_f);
+                return self.lex_string(start_pos, saw_b, saw_r, saw_u, saw_f);
             }
         }
 
@@ -304,6 +403,7 @@ where
         if self.keywords.contains_key(&name) {
             Ok((start_pos, self.keywords[&name].clone(), end_pos))
         } else {
+            let name = normalize_identifier(name);
             Ok((start_pos, Tok::Name { name }, end_pos))
         }
     }
@@ -423,20 +523,25 @@ where
         }
     }
 
-    /// Skip everything until end of line
+    /// Skip everything until end of line, emitting a comment token if trivia is kept.
     fn lex_comment(&mut self) {
-        self.next_char();
+        let tok_start = self.get_pos();
+        let mut value = String::new();
         loop {
             match self.chr0 {
-                Some('\n') => return,
-                Some(_) => {}
-                None => return,
+                Some('\n') | None => break,
+                Some(_) => value.push(self.next_char().unwrap()),
             }
-            self.next_char();
+        }
+        if self.keep_trivia {
+            let tok_end = self.get_pos();
+            self.emit((tok_start, Tok::Comment { value }, tok_end));
         }
     }
 
-    fn unicode_literal(&mut self, literal_number: usize) -> Result<char, LexicalError> {
+    /// The code point of a `\x`, `\u` or `\U` escape, which can be a lone
+    /// surrogate.
+    fn unicode_literal(&mut self, literal_number: usize) -> Result<u32, LexicalError> {
         let mut p: u32 = 0u32;
         let unicode_error = Err(LexicalError {
             error: LexicalErrorType::UnicodeError,
@@ -452,21 +557,21 @@ where
             }
         }
         match wtf8::CodePoint::from_u32(p) {
-            Some(cp) => Ok(cp.to_char_lossy()),
+            Some(cp) => Ok(cp.to_u32()),
             None => unicode_error,
         }
     }
 
     fn lex_string(
         &mut self,
+        start_pos: Location,
         is_bytes: bool,
         is_raw: bool,
         _is_unicode: bool,
         is_fstring: bool,
     ) -> LexResult {
         let quote_char = self.next_char().unwrap();
-        let mut string_content = String::new();
-        let start_pos = self.get_pos();
+        let mut string_content = StringBuilder::default();
 
         // If the next two characters are also the quote character, then we have a triple-quoted
         // string; consume those two characters and ensure that we require a triple-quote to close
@@ -514,9 +619,11 @@ where
                             Some('t') => {
                                 string_content.push('\t');
                             }
-                            Some('u') => string_content.push(self.unicode_literal(4)?),
-                            Some('U') => string_content.push(self.unicode_literal(8)?),
-                            Some('x') if !is_bytes => string_content.push(self.unicode_literal(2)?),
+                            Some('u') => string_content.push_code_point(self.unicode_literal(4)?),
+                            Some('U') => string_content.push_code_point(self.unicode_literal(8)?),
+                            Some('x') if !is_bytes => {
+                                string_content.push_code_point(self.unicode_literal(2)?)
+                            }
                             Some('v') => string_content.push('\x0b'),
                             Some(c) => {
                                 string_content.push('\\');
@@ -557,14 +664,23 @@ where
                     }
                 }
                 None => {
+                    let error = if triple_quoted {
+                        LexicalErrorType::EOF
+                    } else {
+                        LexicalErrorType::StringError
+                    };
                     return Err(LexicalError {
-                        error: LexicalErrorType::StringError,
+                        error,
                         location: self.get_pos(),
                     });
                 }
             }
         }
         let end_pos = self.get_pos();
+        let (string_content, stand_ins) = string_content.finish().ok_or(LexicalError {
+            error: LexicalErrorType::UnicodeError,
+            location: end_pos.clone(),
+        })?;
 
         let tok = if is_bytes {
             if string_content.is_ascii() {
@@ -583,6 +699,7 @@ where
         } else {
             Tok::String {
                 value: string_content,
+                stand_ins,
                 is_fstring,
             }
         };
@@ -590,22 +707,8 @@ where
         Ok((start_pos, tok, end_pos))
     }
 
-    fn is_identifier_start(&self, c: char) -> bool {
-        match c {
-            '_' => true,
-            c => UnicodeXID::is_xid_start(c),
-        }
-    }
-
     fn is_identifier_continuation(&self) -> bool {
-        if let Some(c) = self.chr0 {
-            match c {
-                '_' | '0'..='9' => true,
-                c => UnicodeXID::is_xid_continue(c),
-            }
-        } else {
-            false
-        }
+        self.chr0.map_or(false, is_identifier_continuation)
     }
 
     fn take_number(&mut self, radix: u32) -> Option<char> {
@@ -655,57 +758,36 @@ where
     /// Given we are at the start of a line, count the number of spaces and/or tabs until the first character.
     fn eat_indentation(&mut self) -> Result<IndentationLevel, LexicalError> {
         // Determine indentation:
-        let mut spaces: usize = 0;
-        let mut tabs: usize = 0;
+        let mut indentation_level = IndentationLevel::default();
         loop {
             match self.chr0 {
                 Some(' ') => {
-                    /*
-                    if tabs != 0 {
-                        // Don't allow spaces after tabs as part of indentation.
-                        // This is technically stricter than python3 but spaces after
-                        // tabs is even more insane than mixing spaces and tabs.
-                        return Some(Err(LexicalError {
-                            error: LexicalErrorType::OtherError("Spaces not allowed as part of indentation after tabs".to_string()),
-                            location: self.get_pos(),
-                        }));
-                    }
-                    */
                     self.next_char();
-                    spaces += 1;
+                    indentation_level.add_space();
                 }
                 Some('\t') => {
-                    if spaces != 0 {
-                        // Don't allow tabs after spaces as part of indentation.
-                        // This is technically stricter than python3 but spaces before
-                        // tabs is even more insane than mixing spaces and tabs.
-                        return Err(LexicalError {
-                            error: LexicalErrorType::OtherError(
-                                "Tabs not allowed as part of indentation after spaces".to_string(),
-                            ),
-                            location: self.get_pos(),
-                        });
-                    }
                     self.next_char();
-                    tabs += 1;
+                    indentation_level.add_tab();
                 }
                 Some('#') => {
                     self.lex_comment();
-                    spaces = 0;
-                    tabs = 0;
+                    indentation_level = IndentationLevel::default();
                 }
                 Some('\x0C') => {
                     // Form feed character!
                     // Reset indentation for the Emacs user.
                     self.next_char();
-                    spaces = 0;
-                    tabs = 0;
+                    indentation_level = IndentationLevel::default();
                 }
                 Some('\n') => {
                     // Empty line!
+                    let tok_start = self.get_pos();
                     self.next_char();
-                    spaces = 0;
-                    tabs = 0;
+                    if self.keep_trivia {
+                        let tok_end = self.get_pos();
+                        self.emit((tok_start, Tok::NonLogicalNewline, tok_end));
+                    }
+                    indentation_level = IndentationLevel::default();
                 }
                 None => {
                     break;
@@ -717,7 +799,7 @@ where
             }
         }
 
-        Ok(IndentationLevel { spaces, tabs })
+        Ok(indentation_level)
     }
 
     fn handle_indentations(&mut self) -> Result<(), LexicalError> {
@@ -744,9 +826,7 @@ where
 
                     loop {
                         let current_indentation = self.indentation_stack.last().unwrap();
-                        let ordering = indentation_level
-                            .compare_strict(current_indentation, self.get_pos())?;
-                        match ordering {
+                        match indentation_level.column.cmp(&current_indentation.column) {
                             Ordering::Less => {
                                 self.indentation_stack.pop();
                                 let tok_start = self.get_pos();
@@ -755,14 +835,13 @@ where
                             }
                             Ordering::Equal => {
                                 // We arrived at proper level of indentation.
+                                indentation_level
+                                    .compare_strict(current_indentation, self.get_pos())?;
                                 break;
                             }
                             Ordering::Greater => {
-                                // TODO: handle wrong indentations
                                 return Err(LexicalError {
-                                    error: LexicalErrorType::OtherError(
-                                        "Non matching indentation levels!".to_string(),
-                                    ),
+                                    error: LexicalErrorType::IndentationError,
                                     location: self.get_pos(),
                                 });
                             }
@@ -780,7 +859,7 @@ where
         // Check if we have some character:
         if let Some(c) = self.chr0 {
             // First check identifier:
-            if self.is_identifier_start(c) {
+            if is_identifier_start(c) {
                 let identifier = self.lex_identifier()?;
                 self.emit(identifier);
             } else if is_emoji_presentation(c) {
@@ -804,7 +883,7 @@ where
             // First of all, we need all nestings to be finished.
             if self.nesting > 0 {
                 return Err(LexicalError {
-                    error: LexicalErrorType::NestingError,
+                    error: LexicalErrorType::EOF,
                     location: tok_pos,
                 });
             }
@@ -838,7 +917,8 @@ where
                 self.lex_comment();
             }
             '"' | '\'' => {
-                let string = self.lex_string(false, false, false, false)?;
+                let tok_start = self.get_pos();
+                let string = self.lex_string(tok_start, false, false, false, false)?;
                 self.emit(string);
             }
             '=' => {
@@ -1028,11 +1108,12 @@ where
                 self.nesting += 1;
             }
             ')' => {
+                let tok_start = self.get_pos();
                 self.eat_single_char(Tok::Rpar);
                 if self.nesting == 0 {
                     return Err(LexicalError {
                         error: LexicalErrorType::NestingError,
-                        location: self.get_pos(),
+                        location: tok_start,
                     });
                 }
                 self.nesting -= 1;
@@ -1042,11 +1123,12 @@ where
                 self.nesting += 1;
             }
             ']' => {
+                let tok_start = self.get_pos();
                 self.eat_single_char(Tok::Rsqb);
                 if self.nesting == 0 {
                     return Err(LexicalError {
                         error: LexicalErrorType::NestingError,
-                        location: self.get_pos(),
+                        location: tok_start,
                     });
                 }
                 self.nesting -= 1;
@@ -1056,11 +1138,12 @@ where
                 self.nesting += 1;
             }
             '}' => {
+                let tok_start = self.get_pos();
                 self.eat_single_char(Tok::Rbrace);
                 if self.nesting == 0 {
                     return Err(LexicalError {
                         error: LexicalErrorType::NestingError,
-                        location: self.get_pos(),
+                        location: tok_start,
                     });
                 }
                 self.nesting -= 1;
@@ -1162,6 +1245,37 @@ where
                 if self.nesting == 0 {
                     self.at_begin_of_line = true;
                     self.emit((tok_start, Tok::Newline, tok_end));
+                } else if self.keep_trivia {
+                    self.emit((tok_start, Tok::NonLogicalNewline, tok_end));
+                }
+            }
+            '\\' => {
+                // Explicit line joining: skip the backslash and the newline.
+                let tok_start = self.get_pos();
+                self.next_char();
+                match self.chr0 {
+                    Some('\n') => {
+                        self.next_char();
+                        if self.chr0.is_none() {
+                            // The continued line never came.
+                            return Err(LexicalError {
+                                error: LexicalErrorType::EOF,
+                                location: self.get_pos(),
+                            });
+                        }
+                    }
+                    None => {
+                        return Err(LexicalError {
+                            error: LexicalErrorType::EOF,
+                            location: self.get_pos(),
+                        });
+                    }
+                    Some(_) => {
+                        return Err(LexicalError {
+                            error: LexicalErrorType::UnrecognizedToken { tok: '\\' },
+                            location: tok_start,
+                        });
+                    }
                 }
             }
             ' ' | '\t' | '\x0C' => {
@@ -1296,7 +1410,9 @@ fn lex_byte(s: String) -> Result<Vec<u8>, LexicalErrorType> {
 
 #[cfg(test)]
 mod tests {
-    use super::{make_tokenizer, NewlineHandler, Tok};
+    use super::{is_identifier, make_tokenizer, make_tokenizer_with_trivia, NewlineHandler, Tok};
+    use crate::error::{LexicalError, LexicalErrorType};
+    use crate::location::Location;
     use num_bigint::BigInt;
     use std::iter::FromIterator;
     use std::iter::Iterator;
@@ -1329,10 +1445,12 @@ mod tests {
             vec![
                 Tok::String {
                     value: "\\\\".to_string(),
+                    stand_ins: None,
                     is_fstring: false,
                 },
                 Tok::String {
                     value: "\\".to_string(),
+                    stand_ins: None,
                     is_fstring: false,
                 },
                 Tok::Newline,
@@ -1571,6 +1689,28 @@ mod tests {
         test_double_dedent_tabs_unix_eol: UNIX_EOL,
     }
 
+    fn lex_error(source: &str) -> Option<LexicalError> {
+        make_tokenizer(source).find_map(Result::err)
+    }
+
+    #[test]
+    fn test_indentation_errors() {
+        // Eight spaces and a tab are the same with a tab size of 8, but not 1:
+        let error = lex_error("if x:\n        a\n\tb\n").unwrap();
+        assert_eq!(error.error, LexicalErrorType::TabError);
+        assert_eq!(error.location, Location::new(3, 2));
+        let error = lex_error("if x:\n    if y:\n\ta\n").unwrap();
+        assert_eq!(error.error, LexicalErrorType::TabError);
+
+        let error = lex_error("if x:\n    a\n  b\n").unwrap();
+        assert_eq!(error.error, LexicalErrorType::IndentationError);
+        assert_eq!(error.location, Location::new(3, 3));
+
+        // Consistent with both tab sizes:
+        assert_eq!(lex_error("if x:\n\ta\n\t  b\nc\n"), None);
+        assert_eq!(lex_error("if x:\n  \ta\n  \tb\n"), None);
+    }
+
     macro_rules! test_newline_in_brackets {
         ($($name:ident: $eol:expr,)*) => {
         $(
@@ -1630,30 +1770,64 @@ mod tests {
             vec![
                 Tok::String {
                     value: String::from("double"),
+                    stand_ins: None,
                     is_fstring: false,
                 },
                 Tok::String {
                     value: String::from("single"),
+                    stand_ins: None,
                     is_fstring: false,
                 },
                 Tok::String {
                     value: String::from("can't"),
+                    stand_ins: None,
                     is_fstring: false,
                 },
                 Tok::String {
                     value: String::from("\\\""),
+                    stand_ins: None,
                     is_fstring: false,
                 },
                 Tok::String {
                     value: String::from("\t\r\n"),
+                    stand_ins: None,
                     is_fstring: false,
                 },
                 Tok::String {
                     value: String::from("\\g"),
+                    stand_ins: None,
                     is_fstring: false,
                 },
                 Tok::String {
                     value: String::from("raw\'"),
+                    stand_ins: None,
+                    is_fstring: false,
+                },
+                Tok::Newline,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_string_surrogates() {
+        let source = String::from(r#"'\ud800x' '\udfff\U0010ffff' '\U0010ffff'"#);
+        let tokens = lex_source(&source);
+        assert_eq!(
+            tokens,
+            vec![
+                Tok::String {
+                    value: String::from("\u{10f800}x"),
+                    stand_ins: Some(0x10_f800),
+                    is_fstring: false,
+                },
+                Tok::String {
+                    value: String::from("\u{10f7ff}\u{10ffff}"),
+                    stand_ins: Some(0x10_f000),
+                    is_fstring: false,
+                },
+                Tok::String {
+                    value: String::from("\u{10ffff}"),
+                    stand_ins: None,
                     is_fstring: false,
                 },
                 Tok::Newline,
@@ -1673,6 +1847,7 @@ mod tests {
                     vec![
                         Tok::String {
                             value: String::from("abcdef"),
+                            stand_ins: None,
                             is_fstring: false,
                         },
                         Tok::Newline,
@@ -1718,4 +1893,100 @@ mod tests {
         let res = vec![111, 109, 107, 109, 111, 107, 92, 88, 97, 97];
         assert_eq!(tokens, vec![Tok::Bytes { value: res }, Tok::Newline]);
     }
+
+    #[test]
+    fn test_trivia() {
+        let source = "# leading\n\nx = (1,  # one\n     2)\n";
+        let tokens: Vec<Tok> = make_tokenizer_with_trivia(source)
+            .map(|x| x.unwrap().1)
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Tok::Comment {
+                    value: String::from("# leading"),
+                },
+                Tok::NonLogicalNewline,
+                Tok::NonLogicalNewline,
+                Tok::Name {
+                    name: String::from("x"),
+                },
+                Tok::Equal,
+                Tok::Lpar,
+                Tok::Int {
+                    value: BigInt::from(1)
+                },
+                Tok::Comma,
+                Tok::Comment {
+                    value: String::from("# one"),
+                },
+                Tok::NonLogicalNewline,
+                Tok::Int {
+                    value: BigInt::from(2)
+                },
+                Tok::Rpar,
+                Tok::Newline,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_line_continuation_locations() {
+        let source = "x = \\\n  'a' + rb'b'\n";
+        let spans: Vec<(Location, Location)> = make_tokenizer(source)
+            .map(|x| x.unwrap())
+            .map(|(start, _, end)| (start, end))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                (Location::new(1, 1), Location::new(1, 2)),
+                (Location::new(1, 3), Location::new(1, 4)),
+                (Location::new(2, 3), Location::new(2, 6)),
+                (Location::new(2, 7), Location::new(2, 8)),
+                (Location::new(2, 9), Location::new(2, 14)),
+                (Location::new(2, 14), Location::new(3, 1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unicode_identifiers() {
+        let source = String::from("\u{fb01} = \u{2118}x\u{00b7}1 + \u{1d41f}\n");
+        let tokens = lex_source(&source);
+        assert_eq!(
+            tokens,
+            vec![
+                Tok::Name {
+                    name: String::from("fi"),
+                },
+                Tok::Equal,
+                Tok::Name {
+                    name: String::from("\u{2118}x\u{00b7}1"),
+                },
+                Tok::Plus,
+                Tok::Name {
+                    name: String::from("f"),
+                },
+                Tok::Newline,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_is_identifier() {
+        for name in &[
+            "x",
+            "_1",
+            "if",
+            "\u{00e9}t\u{00e9}",
+            "\u{1885}",
+            "a\u{0387}",
+        ] {
+            assert!(is_identifier(name), "{:?}", name);
+        }
+        for name in &["", "1x", "a b", "\u{00b7}", "\u{309b}", "x-y"] {
+            assert!(!is_identifier(name), "{:?}", name);
+        }
+    }
 }
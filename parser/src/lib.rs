@@ -16,3 +16,4 @@ lalrpop_mod!(
     python
 );
 pub mod token;
+pub mod visitor;
@@ -1,7 +1,7 @@
 use std::iter;
 
 use crate::ast;
-use crate::error::ParseError;
+use crate::error::{ParseError, ParseErrorType};
 use crate::lexer;
 use crate::python;
 use crate::token;
@@ -73,12 +73,82 @@ pub fn parse_expression(source: &str) -> Result<ast::Expression, ParseError> {
     do_lalr_parsing!(source, Expression, StartExpression)
 }
 
+/// How far along a piece of interactive input is, see `classify_input`.
+#[derive(Debug, PartialEq)]
+pub enum InputStatus {
+    /// The input is a complete statement and can be compiled.
+    Complete,
+    /// The input is valid so far, but needs more lines.
+    Incomplete,
+    /// The input is a syntax error that no further lines can fix.
+    Invalid(ParseError),
+}
+
+/// Classify input typed at an interactive prompt.
+///
+/// The source holds the lines entered so far joined by newlines, so a
+/// trailing newline means the last line entered was blank. Like in CPython,
+/// a compound statement is only complete once it is followed by a blank
+/// line, since another clause or more of the body may follow.
+///
+/// # Example
+/// ```
+/// use rustpython_parser::parser::{classify_input, InputStatus};
+///
+/// assert_eq!(classify_input("x = 1"), InputStatus::Complete);
+/// assert_eq!(classify_input("x = (1,"), InputStatus::Incomplete);
+/// assert_eq!(classify_input("if x:\n    pass"), InputStatus::Incomplete);
+/// assert_eq!(classify_input("if x:\n    pass\n"), InputStatus::Complete);
+/// ```
+pub fn classify_input(source: &str) -> InputStatus {
+    // Blank lines and comments alone have nothing left to wait for.
+    let is_empty = source.lines().all(|line| {
+        let line = line.trim_start();
+        line.is_empty() || line.starts_with('#')
+    });
+    if is_empty {
+        return InputStatus::Complete;
+    }
+    match parse_statement(source) {
+        Ok(statements) => {
+            let ends_with_block = statements.last().map_or(false, |statement| {
+                use ast::StatementType::*;
+                match statement.node {
+                    If { .. }
+                    | While { .. }
+                    | With { .. }
+                    | For { .. }
+                    | Try { .. }
+                    | ClassDef { .. }
+                    | FunctionDef { .. } => true,
+                    _ => false,
+                }
+            });
+            let ends_with_blank_line = source
+                .rsplit('\n')
+                .next()
+                .map_or(true, |line| line.trim().is_empty());
+            if ends_with_block && !ends_with_blank_line {
+                InputStatus::Incomplete
+            } else {
+                InputStatus::Complete
+            }
+        }
+        Err(ParseError {
+            error: ParseErrorType::EOF,
+            ..
+        }) => InputStatus::Incomplete,
+        Err(err) => InputStatus::Invalid(err),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::ast;
     use super::parse_expression;
     use super::parse_program;
     use super::parse_statement;
+    use super::{classify_input, InputStatus};
     use num_bigint::BigInt;
 
     fn mk_ident(name: &str, row: usize, col: usize) -> ast::Expression {
@@ -107,6 +177,7 @@ mod tests {
             node: ast::ExpressionType::String {
                 value: ast::StringGroup::Constant {
                     value: String::from(value),
+                    stand_ins: None,
                 },
             },
         }
@@ -139,7 +210,7 @@ mod tests {
                             location: ast::Location::new(1, 6),
                             node: ast::ExpressionType::Call {
                                 function: Box::new(mk_ident("print", 1, 1)),
-                                args: vec![make_string("Hello world", 1, 8)],
+                                args: vec![make_string("Hello world", 1, 7)],
                                 keywords: vec![],
                             }
                         },
@@ -163,7 +234,7 @@ mod tests {
                             location: ast::Location::new(1, 6),
                             node: ast::ExpressionType::Call {
                                 function: Box::new(mk_ident("print", 1, 1)),
-                                args: vec![make_string("Hello world", 1, 8), make_int(2, 1, 22),],
+                                args: vec![make_string("Hello world", 1, 7), make_int(2, 1, 22),],
                                 keywords: vec![],
                             },
                         },
@@ -187,7 +258,7 @@ mod tests {
                             location: ast::Location::new(1, 8),
                             node: ast::ExpressionType::Call {
                                 function: Box::new(mk_ident("my_func", 1, 1)),
-                                args: vec![make_string("positional", 1, 10)],
+                                args: vec![make_string("positional", 1, 9)],
                                 keywords: vec![ast::Keyword {
                                     name: Some("keyword".to_string()),
                                     value: make_int(2, 1, 31),
@@ -263,6 +334,39 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_parse_string_groups() {
+        let string_group = |source: &str| match parse_expression(source).unwrap().node {
+            ast::ExpressionType::String { value } => value,
+            node => panic!("not a string: {:?}", node),
+        };
+        let constant = |value: &str| ast::StringGroup::Constant {
+            value: value.to_owned(),
+            stand_ins: None,
+        };
+
+        assert_eq!(string_group("'a' \"b\" 'c'"), constant("abc"));
+        assert_eq!(string_group("''"), constant(""));
+        assert_eq!(
+            string_group("f'doc'"),
+            ast::StringGroup::Joined {
+                values: vec![constant("doc")]
+            }
+        );
+        assert_eq!(
+            string_group("f''"),
+            ast::StringGroup::Joined { values: vec![] }
+        );
+        match string_group("'a' f'b{x}c' 'd' 'e'") {
+            ast::StringGroup::Joined { values } => {
+                assert_eq!(values.len(), 3);
+                assert_eq!(values[0], constant("ab"));
+                assert_eq!(values[2], constant("cde"));
+            }
+            group => panic!("not joined: {:?}", group),
+        }
+    }
+
     #[test]
     fn test_parse_tuples() {
         let source = String::from("a, b = 4, 5");
@@ -289,6 +393,50 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_parse_annotated_assignment() {
+        let source = String::from("x: int = 5\ny: str\n");
+
+        assert_eq!(
+            parse_program(&source),
+            Ok(ast::Program {
+                statements: vec![
+                    ast::Statement {
+                        location: ast::Location::new(1, 1),
+                        node: ast::StatementType::AnnAssign {
+                            target: Box::new(mk_ident("x", 1, 1)),
+                            annotation: Box::new(mk_ident("int", 1, 4)),
+                            value: Some(make_int(5, 1, 10)),
+                            simple: true,
+                        }
+                    },
+                    ast::Statement {
+                        location: ast::Location::new(2, 1),
+                        node: ast::StatementType::AnnAssign {
+                            target: Box::new(mk_ident("y", 2, 1)),
+                            annotation: Box::new(mk_ident("str", 2, 4)),
+                            value: None,
+                            simple: true,
+                        }
+                    },
+                ]
+            })
+        )
+    }
+
+    #[test]
+    fn test_parse_annotated_assignment_simple() {
+        let simple = |source: &str| match &parse_statement(source).unwrap()[0].node {
+            ast::StatementType::AnnAssign { simple, .. } => *simple,
+            node => panic!("not an annotated assignment: {:?}", node),
+        };
+        assert!(simple("x: int"));
+        assert!(!simple("(x): int"));
+        assert!(!simple("( x ): int = 1"));
+        assert!(!simple("x.y: int"));
+        assert!(!simple("x[0]: int"));
+    }
+
     #[test]
     fn test_parse_class() {
         let source = String::from(
@@ -346,7 +494,7 @@ mod tests {
                                     kwonlyargs: vec![],
                                     vararg: ast::Varargs::None,
                                     kwarg: ast::Varargs::None,
-                                    defaults: vec![make_string("default", 4, 37)],
+                                    defaults: vec![make_string("default", 4, 36)],
                                     kw_defaults: vec![],
                                 },
                                 body: vec![ast::Statement {
@@ -434,4 +582,71 @@ mod tests {
             }
         );
     }
+
+    #[cfg(feature = "serialize")]
+    #[test]
+    fn test_serialize_json_schema() {
+        let source = "x = 1 + 2\n";
+        let parse_ast = parse_program(source).unwrap();
+        let json = serde_json::to_value(&parse_ast).unwrap();
+        let value = &json["statements"][0]["value"];
+        assert_eq!(value["type"], "Binop");
+        assert_eq!(value["location"]["row"], 1);
+        assert_eq!(value["location"]["column"], 7);
+        assert_eq!(value["op"]["type"], "Add");
+        assert_eq!(value["b"]["value"]["value"], "2");
+        assert_eq!(json["statements"][0]["targets"][0]["name"], "x");
+    }
+
+    #[cfg(feature = "serialize")]
+    #[test]
+    fn test_serialize_roundtrip() {
+        let source = "@d\nasync def f(a, *b, c=1.5, **d) -> t:\n    return {k: f'{v!r}' for k, v in a if k}, b'x', 2j, 10**100\n";
+        let parse_ast = parse_program(source).unwrap();
+        let json = serde_json::to_string(&parse_ast).unwrap();
+        let decoded: ast::Program = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, parse_ast);
+    }
+
+    #[test]
+    fn test_classify_input() {
+        for source in &[
+            "x = [1,\n",
+            "s = \"\"\"abc\n",
+            "x = 1 + \\\n",
+            "if x:",
+            "if x:\n    pass",
+            "if x:\n    pass\nelse:",
+            "@decorator",
+            "def f():\n    return 1",
+        ] {
+            assert_eq!(
+                classify_input(source),
+                InputStatus::Incomplete,
+                "{:?}",
+                source
+            );
+        }
+        for source in &[
+            "",
+            "  # comment\n",
+            "x = 1\n",
+            "x = [1,\n 2]\n",
+            "if x: pass\n",
+            "def f():\n    return 1\n  \n",
+        ] {
+            assert_eq!(
+                classify_input(source),
+                InputStatus::Complete,
+                "{:?}",
+                source
+            );
+        }
+        for source in &["x = )\n", "x = 'abc\n", "if x:\npass\n"] {
+            match classify_input(source) {
+                InputStatus::Invalid(_) => {}
+                status => panic!("{:?} classified as {:?}", source, status),
+            }
+        }
+    }
 }
@@ -5,13 +5,35 @@ use num_bigint::BigInt;
 /// Python source code can be tokenized in a sequence of these tokens.
 #[derive(Clone, Debug, PartialEq)]
 pub enum Tok {
-    Name { name: String },
-    Int { value: BigInt },
-    Float { value: f64 },
-    Complex { real: f64, imag: f64 },
-    String { value: String, is_fstring: bool },
-    Bytes { value: Vec<u8> },
+    Name {
+        name: String,
+    },
+    Int {
+        value: BigInt,
+    },
+    Float {
+        value: f64,
+    },
+    Complex {
+        real: f64,
+        imag: f64,
+    },
+    /// `stand_ins` is where the code points standing for lone surrogates in
+    /// `value` start, if it holds any; see `lexer::StringBuilder`.
+    String {
+        value: String,
+        stand_ins: Option<u32>,
+        is_fstring: bool,
+    },
+    Bytes {
+        value: Vec<u8>,
+    },
     Newline,
+    // Trivia, only produced by the tokenizer when asked for:
+    NonLogicalNewline, // blank line, or newline inside brackets
+    Comment {
+        value: String,
+    },
     Indent,
     Dedent,
     StartProgram,
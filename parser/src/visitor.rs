@@ -0,0 +1,448 @@
+//! Traversal of the abstract syntax tree.
+//!
+//! Two traits are generated from the same description of the tree:
+//! `Visitor` walks a tree by shared reference, `VisitorMut` walks it by
+//! mutable reference so that nodes can be rewritten in place.
+//!
+//! Every `visit_*` method has a default implementation that recurses into
+//! the children of the node by calling the matching `walk_*` function.
+//! An implementation overrides only the nodes it is interested in, and
+//! calls the `walk_*` function itself when it wants to keep descending.
+//!
+//! # Example
+//! ```
+//! use rustpython_parser::{ast, parser, visitor::{self, Visitor}};
+//!
+//! struct NameCollector(Vec<String>);
+//!
+//! impl Visitor for NameCollector {
+//!     type Error = ();
+//!
+//!     fn visit_expression(&mut self, expression: &ast::Expression) -> Result<(), ()> {
+//!         if let ast::ExpressionType::Identifier { name } = &expression.node {
+//!             self.0.push(name.clone());
+//!         }
+//!         visitor::walk_expression(self, expression)
+//!     }
+//! }
+//!
+//! let program = parser::parse_program("x = y + z\n").unwrap();
+//! let mut collector = NameCollector(vec![]);
+//! collector.visit_program(&program).unwrap();
+//! assert_eq!(collector.0, vec!["x", "y", "z"]);
+//! ```
+
+use crate::ast;
+
+macro_rules! make_visitor {
+    ($visitor:ident, $($mutability:tt)*) => {
+        pub trait $visitor {
+            /// Error type used to abort the traversal.
+            type Error;
+
+            fn visit_program(&mut self, program: &$($mutability)* ast::Program) -> Result<(), Self::Error> {
+                walk_program(self, program)
+            }
+
+            fn visit_statement(&mut self, statement: &$($mutability)* ast::Statement) -> Result<(), Self::Error> {
+                walk_statement(self, statement)
+            }
+
+            fn visit_expression(&mut self, expression: &$($mutability)* ast::Expression) -> Result<(), Self::Error> {
+                walk_expression(self, expression)
+            }
+
+            fn visit_import_symbol(&mut self, _symbol: &$($mutability)* ast::ImportSymbol) -> Result<(), Self::Error> {
+                Ok(())
+            }
+
+            fn visit_with_item(&mut self, item: &$($mutability)* ast::WithItem) -> Result<(), Self::Error> {
+                walk_with_item(self, item)
+            }
+
+            fn visit_except_handler(&mut self, handler: &$($mutability)* ast::ExceptHandler) -> Result<(), Self::Error> {
+                walk_except_handler(self, handler)
+            }
+
+            fn visit_parameters(&mut self, parameters: &$($mutability)* ast::Parameters) -> Result<(), Self::Error> {
+                walk_parameters(self, parameters)
+            }
+
+            fn visit_parameter(&mut self, parameter: &$($mutability)* ast::Parameter) -> Result<(), Self::Error> {
+                walk_parameter(self, parameter)
+            }
+
+            fn visit_keyword(&mut self, keyword: &$($mutability)* ast::Keyword) -> Result<(), Self::Error> {
+                walk_keyword(self, keyword)
+            }
+
+            fn visit_comprehension(&mut self, comprehension: &$($mutability)* ast::Comprehension) -> Result<(), Self::Error> {
+                walk_comprehension(self, comprehension)
+            }
+
+            fn visit_string_group(&mut self, group: &$($mutability)* ast::StringGroup) -> Result<(), Self::Error> {
+                walk_string_group(self, group)
+            }
+        }
+
+        pub fn walk_program<V: $visitor + ?Sized>(visitor: &mut V, program: &$($mutability)* ast::Program) -> Result<(), V::Error> {
+            walk_statements(visitor, &$($mutability)* program.statements)
+        }
+
+        pub fn walk_statements<V: $visitor + ?Sized>(visitor: &mut V, statements: &$($mutability)* [ast::Statement]) -> Result<(), V::Error> {
+            for statement in statements {
+                visitor.visit_statement(statement)?;
+            }
+            Ok(())
+        }
+
+        pub fn walk_expressions<V: $visitor + ?Sized>(visitor: &mut V, expressions: &$($mutability)* [ast::Expression]) -> Result<(), V::Error> {
+            for expression in expressions {
+                visitor.visit_expression(expression)?;
+            }
+            Ok(())
+        }
+
+        pub fn walk_statement<V: $visitor + ?Sized>(visitor: &mut V, statement: &$($mutability)* ast::Statement) -> Result<(), V::Error> {
+            use crate::ast::StatementType::*;
+            match &$($mutability)* statement.node {
+                Break | Continue | Pass | Global { .. } | Nonlocal { .. } => {}
+                Return { value } => {
+                    if let Some(value) = value {
+                        visitor.visit_expression(value)?;
+                    }
+                }
+                Import { names } | ImportFrom { names, .. } => {
+                    for name in names {
+                        visitor.visit_import_symbol(name)?;
+                    }
+                }
+                Assert { test, msg } => {
+                    visitor.visit_expression(test)?;
+                    if let Some(msg) = msg {
+                        visitor.visit_expression(msg)?;
+                    }
+                }
+                Delete { targets } => walk_expressions(visitor, targets)?,
+                Assign { targets, value } => {
+                    walk_expressions(visitor, targets)?;
+                    visitor.visit_expression(value)?;
+                }
+                AugAssign { target, value, .. } => {
+                    visitor.visit_expression(target)?;
+                    visitor.visit_expression(value)?;
+                }
+                AnnAssign {
+                    target,
+                    annotation,
+                    value,
+                    ..
+                } => {
+                    visitor.visit_expression(target)?;
+                    visitor.visit_expression(annotation)?;
+                    if let Some(value) = value {
+                        visitor.visit_expression(value)?;
+                    }
+                }
+                Expression { expression } => visitor.visit_expression(expression)?,
+                If { test, body, orelse } | While { test, body, orelse } => {
+                    visitor.visit_expression(test)?;
+                    walk_statements(visitor, body)?;
+                    if let Some(orelse) = orelse {
+                        walk_statements(visitor, orelse)?;
+                    }
+                }
+                With { items, body, .. } => {
+                    for item in items {
+                        visitor.visit_with_item(item)?;
+                    }
+                    walk_statements(visitor, body)?;
+                }
+                For {
+                    target,
+                    iter,
+                    body,
+                    orelse,
+                    ..
+                } => {
+                    visitor.visit_expression(target)?;
+                    visitor.visit_expression(iter)?;
+                    walk_statements(visitor, body)?;
+                    if let Some(orelse) = orelse {
+                        walk_statements(visitor, orelse)?;
+                    }
+                }
+                Raise { exception, cause } => {
+                    if let Some(exception) = exception {
+                        visitor.visit_expression(exception)?;
+                    }
+                    if let Some(cause) = cause {
+                        visitor.visit_expression(cause)?;
+                    }
+                }
+                Try {
+                    body,
+                    handlers,
+                    orelse,
+                    finalbody,
+                } => {
+                    walk_statements(visitor, body)?;
+                    for handler in handlers {
+                        visitor.visit_except_handler(handler)?;
+                    }
+                    if let Some(orelse) = orelse {
+                        walk_statements(visitor, orelse)?;
+                    }
+                    if let Some(finalbody) = finalbody {
+                        walk_statements(visitor, finalbody)?;
+                    }
+                }
+                ClassDef {
+                    body,
+                    bases,
+                    keywords,
+                    decorator_list,
+                    ..
+                } => {
+                    walk_expressions(visitor, decorator_list)?;
+                    walk_expressions(visitor, bases)?;
+                    for keyword in keywords {
+                        visitor.visit_keyword(keyword)?;
+                    }
+                    walk_statements(visitor, body)?;
+                }
+                FunctionDef {
+                    args,
+                    body,
+                    decorator_list,
+                    returns,
+                    ..
+                } => {
+                    walk_expressions(visitor, decorator_list)?;
+                    visitor.visit_parameters(args)?;
+                    if let Some(returns) = returns {
+                        visitor.visit_expression(returns)?;
+                    }
+                    walk_statements(visitor, body)?;
+                }
+            }
+            Ok(())
+        }
+
+        pub fn walk_expression<V: $visitor + ?Sized>(visitor: &mut V, expression: &$($mutability)* ast::Expression) -> Result<(), V::Error> {
+            use crate::ast::ExpressionType::*;
+            match &$($mutability)* expression.node {
+                BoolOp { a, b, .. } | Binop { a, b, .. } | Subscript { a, b } => {
+                    visitor.visit_expression(a)?;
+                    visitor.visit_expression(b)?;
+                }
+                Unop { a, .. } => visitor.visit_expression(a)?,
+                Await { value } | YieldFrom { value } | Starred { value } => {
+                    visitor.visit_expression(value)?;
+                }
+                Yield { value } => {
+                    if let Some(value) = value {
+                        visitor.visit_expression(value)?;
+                    }
+                }
+                Compare { vals, .. } => walk_expressions(visitor, vals)?,
+                Attribute { value, .. } => visitor.visit_expression(value)?,
+                Call {
+                    function,
+                    args,
+                    keywords,
+                } => {
+                    visitor.visit_expression(function)?;
+                    walk_expressions(visitor, args)?;
+                    for keyword in keywords {
+                        visitor.visit_keyword(keyword)?;
+                    }
+                }
+                List { elements } | Tuple { elements } | Set { elements } | Slice { elements } => {
+                    walk_expressions(visitor, elements)?;
+                }
+                Dict { elements } => {
+                    for (key, value) in elements {
+                        if let Some(key) = key {
+                            visitor.visit_expression(key)?;
+                        }
+                        visitor.visit_expression(value)?;
+                    }
+                }
+                Comprehension { kind, generators } => {
+                    match &$($mutability)* **kind {
+                        ast::ComprehensionKind::GeneratorExpression { element }
+                        | ast::ComprehensionKind::List { element }
+                        | ast::ComprehensionKind::Set { element } => {
+                            visitor.visit_expression(element)?;
+                        }
+                        ast::ComprehensionKind::Dict { key, value } => {
+                            visitor.visit_expression(key)?;
+                            visitor.visit_expression(value)?;
+                        }
+                    }
+                    for generator in generators {
+                        visitor.visit_comprehension(generator)?;
+                    }
+                }
+                String { value } => visitor.visit_string_group(value)?,
+                Lambda { args, body } => {
+                    visitor.visit_parameters(args)?;
+                    visitor.visit_expression(body)?;
+                }
+                IfExpression { test, body, orelse } => {
+                    visitor.visit_expression(test)?;
+                    visitor.visit_expression(body)?;
+                    visitor.visit_expression(orelse)?;
+                }
+                Number { .. } | Bytes { .. } | Identifier { .. } | True | False | None
+                | Ellipsis => {}
+            }
+            Ok(())
+        }
+
+        pub fn walk_with_item<V: $visitor + ?Sized>(visitor: &mut V, item: &$($mutability)* ast::WithItem) -> Result<(), V::Error> {
+            visitor.visit_expression(&$($mutability)* item.context_expr)?;
+            if let Some(optional_vars) = &$($mutability)* item.optional_vars {
+                visitor.visit_expression(optional_vars)?;
+            }
+            Ok(())
+        }
+
+        pub fn walk_except_handler<V: $visitor + ?Sized>(visitor: &mut V, handler: &$($mutability)* ast::ExceptHandler) -> Result<(), V::Error> {
+            if let Some(typ) = &$($mutability)* handler.typ {
+                visitor.visit_expression(typ)?;
+            }
+            walk_statements(visitor, &$($mutability)* handler.body)
+        }
+
+        /// Visits the default values first, then every parameter in
+        /// declaration order: positional, `*args`, keyword-only, `**kwargs`.
+        pub fn walk_parameters<V: $visitor + ?Sized>(visitor: &mut V, parameters: &$($mutability)* ast::Parameters) -> Result<(), V::Error> {
+            walk_expressions(visitor, &$($mutability)* parameters.defaults)?;
+            for default in &$($mutability)* parameters.kw_defaults {
+                if let Some(default) = default {
+                    visitor.visit_expression(default)?;
+                }
+            }
+            for parameter in &$($mutability)* parameters.args {
+                visitor.visit_parameter(parameter)?;
+            }
+            if let ast::Varargs::Named(parameter) = &$($mutability)* parameters.vararg {
+                visitor.visit_parameter(parameter)?;
+            }
+            for parameter in &$($mutability)* parameters.kwonlyargs {
+                visitor.visit_parameter(parameter)?;
+            }
+            if let ast::Varargs::Named(parameter) = &$($mutability)* parameters.kwarg {
+                visitor.visit_parameter(parameter)?;
+            }
+            Ok(())
+        }
+
+        pub fn walk_parameter<V: $visitor + ?Sized>(visitor: &mut V, parameter: &$($mutability)* ast::Parameter) -> Result<(), V::Error> {
+            if let Some(annotation) = &$($mutability)* parameter.annotation {
+                visitor.visit_expression(annotation)?;
+            }
+            Ok(())
+        }
+
+        pub fn walk_keyword<V: $visitor + ?Sized>(visitor: &mut V, keyword: &$($mutability)* ast::Keyword) -> Result<(), V::Error> {
+            visitor.visit_expression(&$($mutability)* keyword.value)
+        }
+
+        pub fn walk_comprehension<V: $visitor + ?Sized>(visitor: &mut V, comprehension: &$($mutability)* ast::Comprehension) -> Result<(), V::Error> {
+            visitor.visit_expression(&$($mutability)* comprehension.target)?;
+            visitor.visit_expression(&$($mutability)* comprehension.iter)?;
+            walk_expressions(visitor, &$($mutability)* comprehension.ifs)
+        }
+
+        pub fn walk_string_group<V: $visitor + ?Sized>(visitor: &mut V, group: &$($mutability)* ast::StringGroup) -> Result<(), V::Error> {
+            match group {
+                ast::StringGroup::Constant { .. } => {}
+                ast::StringGroup::FormattedValue { value, .. } => visitor.visit_expression(value)?,
+                ast::StringGroup::Joined { values } => {
+                    for value in values {
+                        visitor.visit_string_group(value)?;
+                    }
+                }
+            }
+            Ok(())
+        }
+    };
+}
+
+make_visitor!(Visitor,);
+
+/// Rewriting counterpart of `Visitor`: the same traversal over mutable references.
+pub mod visit_mut {
+    use crate::ast;
+
+    make_visitor!(VisitorMut, mut);
+}
+
+pub use self::visit_mut::VisitorMut;
+
+#[cfg(test)]
+mod tests {
+    use super::visit_mut::{self, VisitorMut};
+    use super::{walk_expression, Visitor};
+    use crate::ast;
+    use crate::parser::parse_program;
+
+    struct NameCollector {
+        names: Vec<String>,
+    }
+
+    impl Visitor for NameCollector {
+        type Error = ();
+
+        fn visit_expression(&mut self, expression: &ast::Expression) -> Result<(), ()> {
+            if let ast::ExpressionType::Identifier { name } = &expression.node {
+                self.names.push(name.clone());
+            }
+            walk_expression(self, expression)
+        }
+
+        fn visit_parameter(&mut self, parameter: &ast::Parameter) -> Result<(), ()> {
+            self.names.push(parameter.arg.clone());
+            Ok(())
+        }
+    }
+
+    struct Renamer;
+
+    impl VisitorMut for Renamer {
+        type Error = ();
+
+        fn visit_expression(&mut self, expression: &mut ast::Expression) -> Result<(), ()> {
+            if let ast::ExpressionType::Identifier { name } = &mut expression.node {
+                name.make_ascii_uppercase();
+            }
+            visit_mut::walk_expression(self, expression)
+        }
+    }
+
+    fn collect_names(program: &ast::Program) -> Vec<String> {
+        let mut collector = NameCollector { names: vec![] };
+        collector.visit_program(program).unwrap();
+        collector.names
+    }
+
+    #[test]
+    fn test_visit_nested_nodes() {
+        let source = "@deco\ndef f(a, *b, c=d, **e):\n    return [x for x in a if f'{c}']\n";
+        let program = parse_program(source).unwrap();
+        assert_eq!(
+            collect_names(&program),
+            vec!["deco", "d", "a", "b", "c", "e", "x", "x", "a", "c"]
+        );
+    }
+
+    #[test]
+    fn test_visit_mut_rewrites_tree() {
+        let mut program = parse_program("a = b(c, key=d)\n").unwrap();
+        Renamer.visit_program(&mut program).unwrap();
+        assert_eq!(collect_names(&program), vec!["A", "B", "C", "D"]);
+    }
+}
@@ -1,4 +0,0 @@
-extern crate cpython;
-extern crate python27_sys;
-
-pub mod python_compiler;
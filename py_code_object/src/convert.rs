@@ -1,237 +1,1009 @@
+//! Translate CPython 3.7 code objects into RustPython code objects.
+//!
+//! CPython wordcode is decoded instruction by instruction and mapped onto the
+//! closest RustPython instruction, or a short sequence of them. Jump targets are
+//! CPython byte offsets, which are used as RustPython labels directly.
+//!
+//! Exception handling blocks (`try`, `with`) and coroutines have no equivalent
+//! in RustPython's block model yet, so code using them is rejected with an
+//! error naming the offending opcode.
 
-// A function which takes CPython bytecode (from json) and transforms
-// this into RustPython bytecode. This to decouple RustPython from CPython
-// internal bytecode representations.
+use crate::marshal::Value;
+use crate::PyCodeObject;
+use num_traits::ToPrimitive;
+use rustpython_bytecode::bytecode::{
+    BinaryOperator, CallType, CodeFlags, CodeObject, ComparisonOperator, Constant, ConversionFlag,
+    FunctionOpArg, Instruction, Label, Location, NameScope, UnaryOperator, Varargs,
+};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
 
-use rustpython_vm::bytecode;
-use py_code_object::{PyCodeObject, NativeType};
+const CO_VARARGS: u32 = 0x0004;
+const CO_VARKEYWORDS: u32 = 0x0008;
+const CO_NEWLOCALS: u32 = 0x0002;
+const CO_GENERATOR: u32 = 0x0020;
+const CO_COROUTINE: u32 = 0x0080;
+const CO_ITERABLE_COROUTINE: u32 = 0x0100;
+const CO_ASYNC_GENERATOR: u32 = 0x0200;
 
-pub fn convert(cpython_bytecode: PyCodeObject) -> bytecode::CodeObject {
-    let mut c = Converter::new();
-    c.convert(cpython_bytecode);
-    c.code2
+const EXTENDED_ARG: u8 = 144;
+
+/// MAKE_FUNCTION flag for a tuple of closure cells.
+const MAKE_FUNCTION_CLOSURE: u32 = 0x08;
+/// FORMAT_VALUE flag for a format spec on the stack.
+const FORMAT_VALUE_SPEC: u32 = 0x04;
+
+#[derive(Debug, PartialEq)]
+pub struct ConvertError {
+    pub error: ConvertErrorType,
+    /// Name of the code object being translated.
+    pub code_name: String,
+    /// Byte offset of the offending instruction in `co_code`.
+    pub offset: usize,
 }
 
+#[derive(Debug, PartialEq)]
+pub enum ConvertErrorType {
+    /// An opcode that RustPython cannot execute.
+    UnsupportedOpcode(&'static str),
+    /// A byte that is not a CPython 3.7 opcode.
+    UnknownOpcode(u8),
+    /// A constant of a type RustPython bytecode cannot hold.
+    UnsupportedConstant(&'static str),
+    /// A code flag RustPython has no support for.
+    UnsupportedFlag(&'static str),
+    /// A `COMPARE_OP` argument RustPython has no comparison for.
+    UnsupportedComparison(u32),
+    /// An opcode argument indexing past the end of a table.
+    InvalidArgument(&'static str),
+    /// An opcode whose operands were not loaded in the expected way.
+    UnexpectedOperand(&'static str),
+    /// A jump to an offset that is not the start of an instruction.
+    InvalidJumpTarget(usize),
+    /// `co_code` ends in the middle of an instruction.
+    TruncatedCode,
+}
+
+impl fmt::Display for ConvertErrorType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConvertErrorType::UnsupportedOpcode(name) => write!(f, "unsupported opcode {}", name),
+            ConvertErrorType::UnknownOpcode(op) => write!(f, "unknown opcode {}", op),
+            ConvertErrorType::UnsupportedConstant(typ) => {
+                write!(f, "unsupported constant of type '{}'", typ)
+            }
+            ConvertErrorType::UnsupportedFlag(name) => write!(f, "unsupported code flag {}", name),
+            ConvertErrorType::UnsupportedComparison(op) => {
+                write!(f, "unsupported comparison {} in COMPARE_OP", op)
+            }
+            ConvertErrorType::InvalidArgument(name) => {
+                write!(f, "argument of {} is out of range", name)
+            }
+            ConvertErrorType::UnexpectedOperand(name) => {
+                write!(f, "{} is not preceded by the constants it expects", name)
+            }
+            ConvertErrorType::InvalidJumpTarget(target) => {
+                write!(f, "jump to offset {} is not an instruction", target)
+            }
+            ConvertErrorType::TruncatedCode => {
+                write!(f, "code ends in the middle of an instruction")
+            }
+        }
+    }
+}
+
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} at offset {} in code object '{}'",
+            self.error, self.offset, self.code_name
+        )
+    }
+}
+
+impl Error for ConvertError {}
+
+/// Translate a CPython code object, and the code objects among its constants,
+/// into a RustPython code object.
+pub fn convert(code: &PyCodeObject) -> Result<CodeObject, ConvertError> {
+    Converter::new(code).convert()
+}
 
-// TODO: think of an appropriate name for this thing:
-pub struct Converter {
-    code: Option<PyCodeObject>,
-    code2: bytecode::CodeObject,
+/// A decoded CPython instruction, with any `EXTENDED_ARG` prefixes folded in.
+struct RawInstruction {
+    /// Offset of the first byte, including prefixes.
+    offset: usize,
+    opcode: u8,
+    arg: u32,
+    /// Offset of the following instruction; relative jumps count from here.
+    next: usize,
 }
 
-impl Converter {
-    pub fn new() -> Converter {
+impl RawInstruction {
+    fn jump_target(&self) -> Option<usize> {
+        let arg = self.arg as usize;
+        match opname(self.opcode)? {
+            "JUMP_ABSOLUTE"
+            | "POP_JUMP_IF_FALSE"
+            | "POP_JUMP_IF_TRUE"
+            | "JUMP_IF_FALSE_OR_POP"
+            | "JUMP_IF_TRUE_OR_POP"
+            | "CONTINUE_LOOP" => Some(arg),
+            "JUMP_FORWARD" | "FOR_ITER" | "SETUP_LOOP" | "SETUP_EXCEPT" | "SETUP_FINALLY"
+            | "SETUP_WITH" | "SETUP_ASYNC_WITH" => Some(self.next + arg),
+            _ => None,
+        }
+    }
+}
+
+fn decode(code: &[u8]) -> Result<Vec<RawInstruction>, ConvertError> {
+    let mut instructions = vec![];
+    let mut arg: u32 = 0;
+    let mut start = None;
+    for (index, unit) in code.chunks(2).enumerate() {
+        let offset = index * 2;
+        if unit.len() != 2 {
+            return Err(ConvertError {
+                error: ConvertErrorType::TruncatedCode,
+                code_name: String::new(),
+                offset,
+            });
+        }
+        arg = (arg << 8) | u32::from(unit[1]);
+        let offset = *start.get_or_insert(offset);
+        if unit[0] == EXTENDED_ARG {
+            continue;
+        }
+        instructions.push(RawInstruction {
+            offset,
+            opcode: unit[0],
+            arg,
+            next: index * 2 + 2,
+        });
+        arg = 0;
+        start = None;
+    }
+    Ok(instructions)
+}
+
+struct Converter<'a> {
+    code: &'a PyCodeObject,
+    instructions: Vec<Instruction>,
+    locations: Vec<Location>,
+    label_map: HashMap<Label, usize>,
+    /// Offsets at which a line starts, with that line, in increasing order.
+    line_starts: Vec<(usize, usize)>,
+    current_offset: usize,
+}
+
+impl<'a> Converter<'a> {
+    fn new(code: &'a PyCodeObject) -> Self {
         Converter {
-            code: None,
-            code2: bytecode::CodeObject::new(),
+            code,
+            instructions: vec![],
+            locations: vec![],
+            label_map: HashMap::new(),
+            line_starts: line_starts(code),
+            current_offset: 0,
         }
     }
 
-    pub fn convert(&mut self, code: PyCodeObject) {
-        // self.code = Some(code);
-        for op_code in &code.co_code {
-           self.dispatch(&code, op_code);
+    fn error(&self, error: ConvertErrorType) -> ConvertError {
+        ConvertError {
+            error,
+            code_name: self.code.name.clone(),
+            offset: self.current_offset,
         }
     }
 
-    fn dispatch(&mut self, code: &PyCodeObject, op_code: &(usize, String, Option<usize>)) {
-        debug!("Converting op code: {:?}", op_code);
-        match (op_code.1.as_ref(), op_code.2) {
-            ("LOAD_CONST", Some(consti)) => {
-                // println!("Loading const at index: {}", consti);
-                let cons = &code.co_consts[consti];
-                let value = match cons {
-                    // NativeType::Boolean { value } => { bytecode::Constant::Boolean { true } },
-                    NativeType::Int { 0: value } => { bytecode::Constant::Integer { value: *value } },
-                    NativeType::Str { 0: ref value } => { bytecode::Constant::String { value: value.clone() } }
-                    NativeType::NoneType => { bytecode::Constant::None }
-                    _ => { panic!("Not impl {:?}", cons); }
-                };
-                self.emit(bytecode::Instruction::LoadConst { value: value });
-            },
+    fn convert(mut self) -> Result<CodeObject, ConvertError> {
+        let code = self.code;
+        if code.flags & (CO_COROUTINE | CO_ITERABLE_COROUTINE) != 0 {
+            return Err(self.error(ConvertErrorType::UnsupportedFlag("CO_COROUTINE")));
+        }
+        if code.flags & CO_ASYNC_GENERATOR != 0 {
+            return Err(self.error(ConvertErrorType::UnsupportedFlag("CO_ASYNC_GENERATOR")));
+        }
 
-            // TODO: universal stack element type
-            ("LOAD_CONST", None) => {
-                self.emit(bytecode::Instruction::LoadConst { value: bytecode::Constant::None });
+        let raw_instructions = decode(&code.code).map_err(|err| ConvertError {
+            code_name: code.name.clone(),
+            ..err
+        })?;
+        let mut targets = HashSet::new();
+        for raw in &raw_instructions {
+            if let Some(target) = raw.jump_target() {
+                targets.insert(target);
+            }
+            if opname(raw.opcode) == Some("SETUP_LOOP") {
+                // `continue` inside a loop jumps back to the instruction following SETUP_LOOP.
+                targets.insert(raw.next);
+            }
+        }
+
+        for raw in &raw_instructions {
+            self.current_offset = raw.offset;
+            if targets.contains(&raw.offset) {
+                self.label_map.insert(raw.offset, self.instructions.len());
+            }
+            self.convert_instruction(raw)?;
+        }
+
+        for target in targets {
+            if !self.label_map.contains_key(&target) {
+                return Err(self.error(ConvertErrorType::InvalidJumpTarget(target)));
+            }
+        }
+
+        let (arg_names, varargs, kwonlyarg_names, varkeywords) = self.arguments()?;
+        let mut code_object = CodeObject::new(
+            arg_names,
+            varargs,
+            kwonlyarg_names,
+            varkeywords,
+            code.filename.clone(),
+            code.firstlineno as usize,
+            code.name.clone(),
+        );
+        code_object.instructions = self.instructions;
+        code_object.locations = self.locations;
+        code_object.label_map = self.label_map;
+        code_object.cellvars = code.cellvars.clone();
+        code_object.freevars = code.freevars.clone();
+        code_object.is_generator = code.flags & CO_GENERATOR != 0;
+        code_object.flags = CodeFlags::from_bits_truncate(code.flags);
+        // Functions keep their docstring as the first constant, or None there.
+        // Lambdas and comprehensions don't reserve that slot.
+        if code.flags & CO_NEWLOCALS != 0 && !code.name.starts_with('<') {
+            if let Some(Value::Str(doc)) = code.consts.first() {
+                code_object.doc = Some(doc.clone());
+            }
+        }
+        Ok(code_object)
+    }
+
+    fn arguments(&self) -> Result<(Vec<String>, Varargs, Vec<String>, Varargs), ConvertError> {
+        let code = self.code;
+        let mut names = code.varnames.iter().cloned();
+        let mut take = |count: usize| -> Result<Vec<String>, ConvertError> {
+            let taken: Vec<String> = names.by_ref().take(count).collect();
+            if taken.len() == count {
+                Ok(taken)
+            } else {
+                Err(self.error(ConvertErrorType::InvalidArgument("co_argcount")))
+            }
+        };
+        let arg_names = take(code.argcount as usize)?;
+        let kwonlyarg_names = take(code.kwonlyargcount as usize)?;
+        let varargs = if code.flags & CO_VARARGS != 0 {
+            Varargs::Named(take(1)?.remove(0))
+        } else {
+            Varargs::None
+        };
+        let varkeywords = if code.flags & CO_VARKEYWORDS != 0 {
+            Varargs::Named(take(1)?.remove(0))
+        } else {
+            Varargs::None
+        };
+        Ok((arg_names, varargs, kwonlyarg_names, varkeywords))
+    }
+
+    fn emit(&mut self, instruction: Instruction) {
+        let offset = self.current_offset;
+        let row = match self
+            .line_starts
+            .iter()
+            .rev()
+            .find(|(start, _)| *start <= offset)
+        {
+            Some((_, line)) => *line,
+            None => self.code.firstlineno as usize,
+        };
+        self.instructions.push(instruction);
+        self.locations.push(Location::new(row, 0));
+    }
+
+    /// Take back the last emitted instruction, which pushed an operand that
+    /// the current opcode folds into its own arguments.
+    fn take_operand(&mut self) -> Option<Instruction> {
+        self.locations.pop();
+        self.instructions.pop()
+    }
+
+    fn take_constant_operand(&mut self, opname: &'static str) -> Result<Constant, ConvertError> {
+        match self.take_operand() {
+            Some(Instruction::LoadConst { value }) => Ok(value),
+            _ => Err(self.error(ConvertErrorType::UnexpectedOperand(opname))),
+        }
+    }
+
+    fn name(
+        &self,
+        table: &[String],
+        index: u32,
+        opname: &'static str,
+    ) -> Result<String, ConvertError> {
+        table
+            .get(index as usize)
+            .cloned()
+            .ok_or_else(|| self.error(ConvertErrorType::InvalidArgument(opname)))
+    }
+
+    fn deref_name(&self, index: u32, opname: &'static str) -> Result<String, ConvertError> {
+        let code = self.code;
+        let index = index as usize;
+        match code.cellvars.get(index) {
+            Some(name) => Ok(name.clone()),
+            None => self.name(&code.freevars, (index - code.cellvars.len()) as u32, opname),
+        }
+    }
+
+    fn constant(&self, value: &Value) -> Result<Constant, ConvertError> {
+        Ok(match value {
+            Value::None => Constant::None,
+            Value::Ellipsis => Constant::Ellipsis,
+            Value::Bool(value) => Constant::Boolean { value: *value },
+            Value::Int(value) => Constant::Integer {
+                value: value.clone(),
             },
-            ("POP_TOP", None) => {
-                self.emit(bytecode::Instruction::Pop);
+            Value::Float(value) => Constant::Float { value: *value },
+            Value::Complex(value) => Constant::Complex { value: *value },
+            Value::Str(value) => Constant::String {
+                value: value.clone(),
+                stand_ins: None,
             },
-            ("STORE_NAME", Some(namei)) => {
-                // println!("Loading const at index: {}", consti);
-                let name = code.co_names[namei].clone();
-                self.emit(bytecode::Instruction::StoreName { name });
+            Value::SurrogateStr(_) => {
+                return Err(self.error(ConvertErrorType::UnsupportedConstant(
+                    "str with lone surrogates",
+                )))
+            }
+            Value::Bytes(value) => Constant::Bytes {
+                value: value.clone(),
             },
-            ("LOAD_NAME", Some(namei)) => {
-                // println!("Loading const at index: {}", consti);
-                let name = code.co_names[namei].clone();
-                self.emit(bytecode::Instruction::LoadName { name });
+            Value::Tuple(elements) => Constant::Tuple {
+                elements: elements
+                    .iter()
+                    .map(|element| self.constant(element))
+                    .collect::<Result<_, _>>()?,
             },
-            ("LOAD_GLOBAL", Some(namei)) => {
-                // We need to load the underlying value the name points to, but stuff like
-                // AssertionError is in the names right after compile, so we load the string
-                // instead for now
-                // let curr_frame = self.curr_frame();
-                // let name = &curr_frame.code.co_names[namei];
+            Value::Code(code) => Constant::Code {
+                code: Box::new(convert(code)?),
             },
+            other => {
+                return Err(self.error(ConvertErrorType::UnsupportedConstant(other.type_name())))
+            }
+        })
+    }
+
+    fn convert_instruction(&mut self, raw: &RawInstruction) -> Result<(), ConvertError> {
+        let opname = opname(raw.opcode)
+            .ok_or_else(|| self.error(ConvertErrorType::UnknownOpcode(raw.opcode)))?;
+        let arg = raw.arg;
+        let size = arg as usize;
+        let code = self.code;
 
-            ("BUILD_LIST", Some(count)) => {
-                self.emit(bytecode::Instruction::BuildList { size: count });
+        let binary = |op, inplace| Instruction::BinaryOperation { op, inplace };
+        let load = |name, scope| Instruction::LoadName { name, scope };
+        let store = |name, scope| Instruction::StoreName { name, scope };
+
+        let instruction = match opname {
+            "POP_TOP" => Instruction::Pop,
+            "ROT_TWO" => Instruction::Rotate { amount: 2 },
+            "ROT_THREE" => Instruction::Rotate { amount: 3 },
+            "DUP_TOP" => Instruction::Duplicate,
+            "DUP_TOP_TWO" => {
+                // a b -> a b b -> b a b -> b b a -> b b a a -> b a b a -> a b a b
+                self.emit(Instruction::Duplicate);
+                self.emit(Instruction::Rotate { amount: 3 });
+                self.emit(Instruction::Rotate { amount: 3 });
+                self.emit(Instruction::Duplicate);
+                self.emit(Instruction::Rotate { amount: 3 });
+                Instruction::Rotate { amount: 4 }
+            }
+            "NOP" => Instruction::Pass,
+
+            "UNARY_POSITIVE" => Instruction::UnaryOperation {
+                op: UnaryOperator::Plus,
+            },
+            "UNARY_NEGATIVE" => Instruction::UnaryOperation {
+                op: UnaryOperator::Minus,
+            },
+            "UNARY_NOT" => Instruction::UnaryOperation {
+                op: UnaryOperator::Not,
+            },
+            "UNARY_INVERT" => Instruction::UnaryOperation {
+                op: UnaryOperator::Invert,
             },
 
-            ("BUILD_SLICE", Some(count)) => {
-                assert!(count == 2 || count == 3);
-                self.emit(bytecode::Instruction::BuildSlice { size: count });
+            "BINARY_POWER" => binary(BinaryOperator::Power, false),
+            "BINARY_MULTIPLY" => binary(BinaryOperator::Multiply, false),
+            "BINARY_MATRIX_MULTIPLY" => binary(BinaryOperator::MatrixMultiply, false),
+            "BINARY_FLOOR_DIVIDE" => binary(BinaryOperator::FloorDivide, false),
+            "BINARY_TRUE_DIVIDE" => binary(BinaryOperator::Divide, false),
+            "BINARY_MODULO" => binary(BinaryOperator::Modulo, false),
+            "BINARY_ADD" => binary(BinaryOperator::Add, false),
+            "BINARY_SUBTRACT" => binary(BinaryOperator::Subtract, false),
+            "BINARY_SUBSCR" => binary(BinaryOperator::Subscript, false),
+            "BINARY_LSHIFT" => binary(BinaryOperator::Lshift, false),
+            "BINARY_RSHIFT" => binary(BinaryOperator::Rshift, false),
+            "BINARY_AND" => binary(BinaryOperator::And, false),
+            "BINARY_XOR" => binary(BinaryOperator::Xor, false),
+            "BINARY_OR" => binary(BinaryOperator::Or, false),
+            "INPLACE_POWER" => binary(BinaryOperator::Power, true),
+            "INPLACE_MULTIPLY" => binary(BinaryOperator::Multiply, true),
+            "INPLACE_MATRIX_MULTIPLY" => binary(BinaryOperator::MatrixMultiply, true),
+            "INPLACE_FLOOR_DIVIDE" => binary(BinaryOperator::FloorDivide, true),
+            "INPLACE_TRUE_DIVIDE" => binary(BinaryOperator::Divide, true),
+            "INPLACE_MODULO" => binary(BinaryOperator::Modulo, true),
+            "INPLACE_ADD" => binary(BinaryOperator::Add, true),
+            "INPLACE_SUBTRACT" => binary(BinaryOperator::Subtract, true),
+            "INPLACE_LSHIFT" => binary(BinaryOperator::Lshift, true),
+            "INPLACE_RSHIFT" => binary(BinaryOperator::Rshift, true),
+            "INPLACE_AND" => binary(BinaryOperator::And, true),
+            "INPLACE_XOR" => binary(BinaryOperator::Xor, true),
+            "INPLACE_OR" => binary(BinaryOperator::Or, true),
+
+            "STORE_SUBSCR" => Instruction::StoreSubscript,
+            "DELETE_SUBSCR" => Instruction::DeleteSubscript,
+            "GET_ITER" | "GET_YIELD_FROM_ITER" => Instruction::GetIter,
+            "PRINT_EXPR" => Instruction::PrintExpr,
+            "LOAD_BUILD_CLASS" => Instruction::LoadBuildClass,
+            "YIELD_FROM" => Instruction::YieldFrom,
+            "YIELD_VALUE" => Instruction::YieldValue,
+            "RETURN_VALUE" => Instruction::ReturnValue,
+            "SETUP_ANNOTATIONS" => Instruction::SetupAnnotation,
+            "POP_BLOCK" => Instruction::PopBlock,
+            "BREAK_LOOP" => Instruction::Break,
+
+            "IMPORT_NAME" => {
+                let symbols = match self.take_constant_operand(opname)? {
+                    Constant::None => vec![],
+                    Constant::Tuple { elements } => elements
+                        .into_iter()
+                        .map(|element| match element {
+                            Constant::String { value, .. } => Ok(value),
+                            _ => Err(self.error(ConvertErrorType::UnexpectedOperand(opname))),
+                        })
+                        .collect::<Result<_, _>>()?,
+                    _ => return Err(self.error(ConvertErrorType::UnexpectedOperand(opname))),
+                };
+                let level = match self.take_constant_operand(opname)? {
+                    Constant::Integer { value } => value
+                        .to_usize()
+                        .ok_or_else(|| self.error(ConvertErrorType::UnexpectedOperand(opname)))?,
+                    _ => return Err(self.error(ConvertErrorType::UnexpectedOperand(opname))),
+                };
+                let name = self.name(&code.names, arg, opname)?;
+                Instruction::Import {
+                    name: if name.is_empty() { None } else { Some(name) },
+                    symbols,
+                    level,
+                }
+            }
+            "IMPORT_STAR" => match self.take_operand() {
+                Some(Instruction::Import { name, level, .. }) => {
+                    Instruction::ImportStar { name, level }
+                }
+                _ => return Err(self.error(ConvertErrorType::UnexpectedOperand(opname))),
+            },
+            "IMPORT_FROM" => Instruction::ImportFrom {
+                name: self.name(&code.names, arg, opname)?,
             },
 
-            ("GET_ITER", None) => {
-                self.emit(bytecode::Instruction::GetIter);
+            "STORE_NAME" => store(self.name(&code.names, arg, opname)?, NameScope::Local),
+            "LOAD_NAME" => load(self.name(&code.names, arg, opname)?, NameScope::Local),
+            "DELETE_NAME" | "DELETE_GLOBAL" => Instruction::DeleteName {
+                name: self.name(&code.names, arg, opname)?,
+            },
+            "STORE_GLOBAL" => store(self.name(&code.names, arg, opname)?, NameScope::Global),
+            "LOAD_GLOBAL" => load(self.name(&code.names, arg, opname)?, NameScope::Global),
+            "STORE_FAST" => store(self.name(&code.varnames, arg, opname)?, NameScope::Local),
+            "LOAD_FAST" => load(self.name(&code.varnames, arg, opname)?, NameScope::Local),
+            "DELETE_FAST" => Instruction::DeleteName {
+                name: self.name(&code.varnames, arg, opname)?,
+            },
+            // Closures are resolved by name through the scope chain, so cells
+            // become plain name accesses.
+            "LOAD_DEREF" | "LOAD_CLASSDEREF" => {
+                load(self.deref_name(arg, opname)?, NameScope::Local)
+            }
+            "STORE_DEREF" => {
+                let scope = if size < code.cellvars.len() {
+                    NameScope::Local
+                } else {
+                    NameScope::NonLocal
+                };
+                store(self.deref_name(arg, opname)?, scope)
+            }
+            "DELETE_DEREF" => Instruction::DeleteName {
+                name: self.deref_name(arg, opname)?,
+            },
+            "LOAD_CLOSURE" => Instruction::LoadConst {
+                value: Constant::None,
             },
 
-            ("FOR_ITER", Some(delta)) => {
-                self.emit(bytecode::Instruction::ForIter);
+            "STORE_ATTR" => Instruction::StoreAttr {
+                name: self.name(&code.names, arg, opname)?,
+            },
+            "DELETE_ATTR" => Instruction::DeleteAttr {
+                name: self.name(&code.names, arg, opname)?,
+            },
+            "LOAD_ATTR" => Instruction::LoadAttr {
+                name: self.name(&code.names, arg, opname)?,
+            },
+            "LOAD_METHOD" => Instruction::LoadMethod {
+                name: self.name(&code.names, arg, opname)?,
             },
 
-            ("COMPARE_OP", Some(cmp_op_i)) => {
-                let op = match cmp_op_i {
-                    0 => bytecode::ComparisonOperator::Less,
-                    1 => bytecode::ComparisonOperator::LessOrEqual,
-                    2 => bytecode::ComparisonOperator::Equal,
-                    3 => bytecode::ComparisonOperator::NotEqual,
-                    4 => bytecode::ComparisonOperator::Greater,
-                    5 => bytecode::ComparisonOperator::GreaterOrEqual,
-                    _ => { panic!("Not impl {:?}", cmp_op_i); }
-                };
-                self.emit(bytecode::Instruction::CompareOperation { op: op} );
-            },
-            ("POP_JUMP_IF_TRUE", Some(ref target)) => {
-                self.emit(bytecode::Instruction::JumpIf { target: *target} );
-            }
-            /*
-            ("POP_JUMP_IF_FALSE", Some(ref target)) => {
-                // Convert into two internal bytecodes:
-                self.emit(bytecode::Instruction::UnaryOperation { op: bytecode::UnaryOperation::Not } );
-                self.emit(bytecode::Instruction::JumpIf { target: target} );
-            }*/
-/*
-            ("JUMP_FORWARD", Some(ref delta)) => {
-                let curr_frame = self.curr_frame();
-                let last_offset = curr_frame.get_bytecode_offset().unwrap();
-                curr_frame.lasti = curr_frame.labels.get(&(last_offset + delta)).unwrap().clone();
-                None
-            },
-            ("JUMP_ABSOLUTE", Some(ref target)) => {
-                let curr_frame = self.curr_frame();
-                curr_frame.lasti = curr_frame.labels.get(target).unwrap().clone();
-                None
-            },
-            ("BREAK_LOOP", None) => {
-                // Do we still need to return the why if we use unwind from jsapy?
-                self.unwind("break".to_string());
-                None //?
-            },
-            */
-            ("RAISE_VARARGS", Some(argc)) => {
-                self.emit(bytecode::Instruction::Raise { argc: argc });
-            }
-            /*
-            ("INPLACE_ADD", None) => {
-                self.emit(bytecode::Instruction::BinaryOperation { op: BinaryOperator::Add });
-            },
-            
-            ("STORE_SUBSCR", None) => {
-                let curr_frame = self.curr_frame();
-                let tos = curr_frame.stack.pop().unwrap();
-                let tos1 = curr_frame.stack.pop().unwrap();
-                let tos2 = curr_frame.stack.pop().unwrap();
-                match (tos1.deref(), tos.deref()) {
-                    (&NativeType::List(ref refl), &NativeType::Int(index)) => {
-                        refl.borrow_mut()[index as usize] = (*tos2).clone();
-                    },
-                    (&NativeType::Str(_), &NativeType::Int(_)) => {
-                        // TODO: raise TypeError: 'str' object does not support item assignment
-                        panic!("TypeError: 'str' object does not support item assignment")
-                    },
-                    _ => panic!("TypeError in STORE_SUBSCR")
+            "LOAD_CONST" => {
+                let value = code
+                    .consts
+                    .get(size)
+                    .ok_or_else(|| self.error(ConvertErrorType::InvalidArgument(opname)))?;
+                Instruction::LoadConst {
+                    value: self.constant(value)?,
                 }
-                curr_frame.stack.push(tos1);
+            }
+
+            "UNPACK_SEQUENCE" => Instruction::UnpackSequence { size },
+            "UNPACK_EX" => Instruction::UnpackEx {
+                before: size & 0xff,
+                after: size >> 8,
             },
-*/
-            ("BINARY_ADD", None) => {
-                self.emit(bytecode::Instruction::BinaryOperation { op: bytecode::BinaryOperator::Add });
+            "BUILD_TUPLE" => Instruction::BuildTuple {
+                size,
+                unpack: false,
             },
-            ("BINARY_POWER", None) => {
-                self.emit(bytecode::Instruction::BinaryOperation { op: bytecode::BinaryOperator::Power });
+            "BUILD_LIST" => Instruction::BuildList {
+                size,
+                unpack: false,
             },
-            ("BINARY_MULTIPLY", None) => {
-                self.emit(bytecode::Instruction::BinaryOperation { op: bytecode::BinaryOperator::Multiply });
+            "BUILD_SET" => Instruction::BuildSet {
+                size,
+                unpack: false,
             },
-            ("BINARY_TRUE_DIVIDE", None) => {
-                self.emit(bytecode::Instruction::BinaryOperation { op: bytecode::BinaryOperator::Divide });
+            "BUILD_MAP" => Instruction::BuildMap {
+                size,
+                unpack: false,
             },
-            ("BINARY_MODULO", None) => {
-                self.emit(bytecode::Instruction::BinaryOperation { op: bytecode::BinaryOperator::Modulo });
+            "BUILD_TUPLE_UNPACK" | "BUILD_TUPLE_UNPACK_WITH_CALL" => {
+                Instruction::BuildTuple { size, unpack: true }
+            }
+            "BUILD_LIST_UNPACK" => Instruction::BuildList { size, unpack: true },
+            "BUILD_SET_UNPACK" => Instruction::BuildSet { size, unpack: true },
+            "BUILD_MAP_UNPACK" | "BUILD_MAP_UNPACK_WITH_CALL" => {
+                Instruction::BuildMap { size, unpack: true }
+            }
+            "BUILD_CONST_KEY_MAP" => {
+                let keys = match self.take_constant_operand(opname)? {
+                    Constant::Tuple { elements } if elements.len() == size => elements,
+                    _ => return Err(self.error(ConvertErrorType::UnexpectedOperand(opname))),
+                };
+                self.emit_const_key_map(keys);
+                return Ok(());
+            }
+            "BUILD_STRING" => Instruction::BuildString { size },
+            "BUILD_SLICE" => Instruction::BuildSlice { size },
+            "LIST_APPEND" => Instruction::ListAppend { i: size },
+            "SET_ADD" => Instruction::SetAdd { i: size },
+            "MAP_ADD" => Instruction::MapAdd { i: size },
+
+            "COMPARE_OP" => Instruction::CompareOperation {
+                op: match arg {
+                    0 => ComparisonOperator::Less,
+                    1 => ComparisonOperator::LessOrEqual,
+                    2 => ComparisonOperator::Equal,
+                    3 => ComparisonOperator::NotEqual,
+                    4 => ComparisonOperator::Greater,
+                    5 => ComparisonOperator::GreaterOrEqual,
+                    6 => ComparisonOperator::In,
+                    7 => ComparisonOperator::NotIn,
+                    8 => ComparisonOperator::Is,
+                    9 => ComparisonOperator::IsNot,
+                    _ => return Err(self.error(ConvertErrorType::UnsupportedComparison(arg))),
+                },
+            },
+
+            "JUMP_FORWARD" | "JUMP_ABSOLUTE" | "CONTINUE_LOOP" => Instruction::Jump {
+                target: raw.jump_target().unwrap(),
             },
-            ("BINARY_SUBTRACT", None) => {
-                self.emit(bytecode::Instruction::BinaryOperation { op: bytecode::BinaryOperator::Subtract });
+            "POP_JUMP_IF_FALSE" => Instruction::JumpIfFalse {
+                target: raw.jump_target().unwrap(),
             },
-            ("BINARY_SUBSCR", None) => {
-                self.emit(bytecode::Instruction::BinaryOperation { op: bytecode::BinaryOperator::Subscript });
+            "POP_JUMP_IF_TRUE" => Instruction::JumpIf {
+                target: raw.jump_target().unwrap(),
             },
-            ("ROT_TWO", None) => {
-                self.emit(bytecode::Instruction::Rotate { amount: 2 });
+            "JUMP_IF_FALSE_OR_POP" | "JUMP_IF_TRUE_OR_POP" => {
+                let target = raw.jump_target().unwrap();
+                self.emit(Instruction::Duplicate);
+                if opname == "JUMP_IF_FALSE_OR_POP" {
+                    self.emit(Instruction::JumpIfFalse { target });
+                } else {
+                    self.emit(Instruction::JumpIf { target });
+                }
+                Instruction::Pop
             }
-            ("UNARY_NEGATIVE", None) => {
-                self.emit(bytecode::Instruction::UnaryOperation { op: bytecode::UnaryOperator::Minus });
+            "FOR_ITER" => Instruction::ForIter {
+                target: raw.jump_target().unwrap(),
             },
-            ("UNARY_POSITIVE", None) => {
-                self.emit(bytecode::Instruction::UnaryOperation { op: bytecode::UnaryOperator::Plus });
+            "SETUP_LOOP" => Instruction::SetupLoop {
+                start: raw.next,
+                end: raw.jump_target().unwrap(),
             },
-            /*
-            ("PRINT_ITEM", None) => {
-                // TODO: Print without the (...)
-                println!("{:?}", curr_frame.stack.pop().unwrap());
+
+            "RAISE_VARARGS" => Instruction::Raise { argc: size },
+            "CALL_FUNCTION" => Instruction::CallFunction {
+                typ: CallType::Positional(size),
             },
-            ("PRINT_NEWLINE", None) => {
-                print!("\n");
-            },*/
-            /*
-            ("MAKE_FUNCTION", Some(argc)) => {
-                // https://docs.python.org/3.4/library/dis.html#opcode-MAKE_FUNCTION
-                self.emit(bytecode::Instruction::MakeFunction { });
+            "CALL_METHOD" => Instruction::CallMethod {
+                typ: CallType::Positional(size),
             },
-            */
-            ("CALL_FUNCTION", Some(argc)) => {
-                let kw_count = (argc >> 8) as u8;
-                let pos_count = (argc & 0xFF) as usize;
-                self.emit(bytecode::Instruction::CallFunction { count: pos_count });
+            "CALL_FUNCTION_KW" => Instruction::CallFunction {
+                typ: CallType::Keyword(size),
             },
-            ("RETURN_VALUE", None) => {
-                self.emit(bytecode::Instruction::ReturnValue);
+            "CALL_FUNCTION_EX" => Instruction::CallFunction {
+                typ: CallType::Ex(arg & 0x01 != 0),
             },
-            /*
-            ("SETUP_LOOP", Some(delta)) => {
-                let curr_frame = self.curr_frame();
-                let curr_offset = curr_frame.get_bytecode_offset().unwrap();
-                curr_frame.blocks.push(Block {
-                    block_type: "loop".to_string(),
-                    handler: *curr_frame.labels.get(&(curr_offset + delta)).unwrap(),
+            "MAKE_FUNCTION" => {
+                if arg & MAKE_FUNCTION_CLOSURE != 0 {
+                    // Drop the closure tuple sitting below the code object and
+                    // the qualified name.
+                    self.emit(Instruction::Rotate { amount: 3 });
+                    self.emit(Instruction::Rotate { amount: 3 });
+                    self.emit(Instruction::Pop);
+                }
+                Instruction::MakeFunction {
+                    flags: FunctionOpArg::from_bits_truncate(arg as u8),
+                }
+            }
+            "FORMAT_VALUE" => {
+                let spec = if arg & FORMAT_VALUE_SPEC != 0 {
+                    match self.take_constant_operand(opname)? {
+                        Constant::String { value, .. } => value,
+                        _ => return Err(self.error(ConvertErrorType::UnexpectedOperand(opname))),
+                    }
+                } else {
+                    String::new()
+                };
+                let conversion = match arg & 0x03 {
+                    1 => Some(ConversionFlag::Str),
+                    2 => Some(ConversionFlag::Repr),
+                    3 => Some(ConversionFlag::Ascii),
+                    _ => None,
+                };
+                Instruction::FormatValue { conversion, spec }
+            }
+
+            _ => return Err(self.error(ConvertErrorType::UnsupportedOpcode(opname))),
+        };
+        self.emit(instruction);
+        Ok(())
+    }
+
+    /// Build the dict of `BUILD_CONST_KEY_MAP` one entry at a time, starting
+    /// from the last value, which is on top of the stack.
+    fn emit_const_key_map(&mut self, keys: Vec<Constant>) {
+        if keys.is_empty() {
+            self.emit(Instruction::BuildMap {
+                size: 0,
+                unpack: false,
+            });
+            return;
+        }
+        let count = keys.len();
+        for (index, key) in keys.into_iter().enumerate().rev() {
+            let merge = index + 1 < count;
+            if merge {
+                // ... value, dict -> ... dict, value
+                self.emit(Instruction::Rotate { amount: 2 });
+            }
+            // ... value -> ... key, value -> ... {key: value}
+            self.emit(Instruction::LoadConst { value: key });
+            self.emit(Instruction::Rotate { amount: 2 });
+            self.emit(Instruction::BuildMap {
+                size: 1,
+                unpack: false,
+            });
+            if merge {
+                // ... dict, {key: value} -> ... {key: value}, dict -> ... merged
+                self.emit(Instruction::Rotate { amount: 2 });
+                self.emit(Instruction::BuildMap {
+                    size: 2,
+                    unpack: true,
                 });
-            },
-            */
-            ("POP_BLOCK", None) => {
-                self.emit(bytecode::Instruction::PopBlock);
             }
-            ("SetLineno", _) | ("LABEL", _)=> {
-                // Skip
-            },
-            (name, _) => {
-                panic!("Unrecongnizable op code: {}", name);
+        }
+    }
+}
+
+/// Decode `co_lnotab` into (offset, line) pairs, following `dis.findlinestarts`.
+fn line_starts(code: &PyCodeObject) -> Vec<(usize, usize)> {
+    let mut starts = vec![];
+    let mut last_line = None;
+    let mut line = code.firstlineno as i64;
+    let mut offset = 0;
+    for pair in code.lnotab.chunks(2) {
+        if pair.len() != 2 {
+            break;
+        }
+        if pair[0] != 0 {
+            if last_line != Some(line) {
+                starts.push((offset, line.max(0) as usize));
+                last_line = Some(line);
+            }
+            offset += pair[0] as usize;
+        }
+        line += i64::from(pair[1] as i8);
+    }
+    if last_line != Some(line) {
+        starts.push((offset, line.max(0) as usize));
+    }
+    starts
+}
+
+/// The name of a CPython 3.7 opcode.
+pub fn opname(opcode: u8) -> Option<&'static str> {
+    Some(match opcode {
+        1 => "POP_TOP",
+        2 => "ROT_TWO",
+        3 => "ROT_THREE",
+        4 => "DUP_TOP",
+        5 => "DUP_TOP_TWO",
+        9 => "NOP",
+        10 => "UNARY_POSITIVE",
+        11 => "UNARY_NEGATIVE",
+        12 => "UNARY_NOT",
+        15 => "UNARY_INVERT",
+        16 => "BINARY_MATRIX_MULTIPLY",
+        17 => "INPLACE_MATRIX_MULTIPLY",
+        19 => "BINARY_POWER",
+        20 => "BINARY_MULTIPLY",
+        22 => "BINARY_MODULO",
+        23 => "BINARY_ADD",
+        24 => "BINARY_SUBTRACT",
+        25 => "BINARY_SUBSCR",
+        26 => "BINARY_FLOOR_DIVIDE",
+        27 => "BINARY_TRUE_DIVIDE",
+        28 => "INPLACE_FLOOR_DIVIDE",
+        29 => "INPLACE_TRUE_DIVIDE",
+        50 => "GET_AITER",
+        51 => "GET_ANEXT",
+        52 => "BEFORE_ASYNC_WITH",
+        55 => "INPLACE_ADD",
+        56 => "INPLACE_SUBTRACT",
+        57 => "INPLACE_MULTIPLY",
+        59 => "INPLACE_MODULO",
+        60 => "STORE_SUBSCR",
+        61 => "DELETE_SUBSCR",
+        62 => "BINARY_LSHIFT",
+        63 => "BINARY_RSHIFT",
+        64 => "BINARY_AND",
+        65 => "BINARY_XOR",
+        66 => "BINARY_OR",
+        67 => "INPLACE_POWER",
+        68 => "GET_ITER",
+        69 => "GET_YIELD_FROM_ITER",
+        70 => "PRINT_EXPR",
+        71 => "LOAD_BUILD_CLASS",
+        72 => "YIELD_FROM",
+        73 => "GET_AWAITABLE",
+        75 => "INPLACE_LSHIFT",
+        76 => "INPLACE_RSHIFT",
+        77 => "INPLACE_AND",
+        78 => "INPLACE_XOR",
+        79 => "INPLACE_OR",
+        80 => "BREAK_LOOP",
+        81 => "WITH_CLEANUP_START",
+        82 => "WITH_CLEANUP_FINISH",
+        83 => "RETURN_VALUE",
+        84 => "IMPORT_STAR",
+        85 => "SETUP_ANNOTATIONS",
+        86 => "YIELD_VALUE",
+        87 => "POP_BLOCK",
+        88 => "END_FINALLY",
+        89 => "POP_EXCEPT",
+        90 => "STORE_NAME",
+        91 => "DELETE_NAME",
+        92 => "UNPACK_SEQUENCE",
+        93 => "FOR_ITER",
+        94 => "UNPACK_EX",
+        95 => "STORE_ATTR",
+        96 => "DELETE_ATTR",
+        97 => "STORE_GLOBAL",
+        98 => "DELETE_GLOBAL",
+        100 => "LOAD_CONST",
+        101 => "LOAD_NAME",
+        102 => "BUILD_TUPLE",
+        103 => "BUILD_LIST",
+        104 => "BUILD_SET",
+        105 => "BUILD_MAP",
+        106 => "LOAD_ATTR",
+        107 => "COMPARE_OP",
+        108 => "IMPORT_NAME",
+        109 => "IMPORT_FROM",
+        110 => "JUMP_FORWARD",
+        111 => "JUMP_IF_FALSE_OR_POP",
+        112 => "JUMP_IF_TRUE_OR_POP",
+        113 => "JUMP_ABSOLUTE",
+        114 => "POP_JUMP_IF_FALSE",
+        115 => "POP_JUMP_IF_TRUE",
+        116 => "LOAD_GLOBAL",
+        119 => "CONTINUE_LOOP",
+        120 => "SETUP_LOOP",
+        121 => "SETUP_EXCEPT",
+        122 => "SETUP_FINALLY",
+        124 => "LOAD_FAST",
+        125 => "STORE_FAST",
+        126 => "DELETE_FAST",
+        130 => "RAISE_VARARGS",
+        131 => "CALL_FUNCTION",
+        132 => "MAKE_FUNCTION",
+        133 => "BUILD_SLICE",
+        135 => "LOAD_CLOSURE",
+        136 => "LOAD_DEREF",
+        137 => "STORE_DEREF",
+        138 => "DELETE_DEREF",
+        141 => "CALL_FUNCTION_KW",
+        142 => "CALL_FUNCTION_EX",
+        143 => "SETUP_WITH",
+        144 => "EXTENDED_ARG",
+        145 => "LIST_APPEND",
+        146 => "SET_ADD",
+        147 => "MAP_ADD",
+        148 => "LOAD_CLASSDEREF",
+        149 => "BUILD_LIST_UNPACK",
+        150 => "BUILD_MAP_UNPACK",
+        151 => "BUILD_MAP_UNPACK_WITH_CALL",
+        152 => "BUILD_TUPLE_UNPACK",
+        153 => "BUILD_SET_UNPACK",
+        154 => "SETUP_ASYNC_WITH",
+        155 => "FORMAT_VALUE",
+        156 => "BUILD_CONST_KEY_MAP",
+        157 => "BUILD_STRING",
+        158 => "BUILD_TUPLE_UNPACK_WITH_CALL",
+        160 => "LOAD_METHOD",
+        161 => "CALL_METHOD",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pyc::load_pyc;
+
+    fn code(bytes: &[u8], consts: Vec<Value>) -> PyCodeObject {
+        PyCodeObject {
+            argcount: 0,
+            kwonlyargcount: 0,
+            nlocals: 0,
+            stacksize: 1,
+            flags: 0,
+            code: bytes.to_vec(),
+            consts,
+            names: vec!["x".to_string()],
+            varnames: vec![],
+            freevars: vec![],
+            cellvars: vec![],
+            filename: "test.py".to_string(),
+            name: "<module>".to_string(),
+            firstlineno: 1,
+            lnotab: vec![],
+        }
+    }
+
+    fn code_constant<'a>(code: &'a CodeObject, name: &str) -> &'a CodeObject {
+        code.instructions
+            .iter()
+            .filter_map(|instruction| match instruction {
+                Instruction::LoadConst {
+                    value: Constant::Code { code },
+                } if code.obj_name == name => Some(code.as_ref()),
+                _ => None,
+            })
+            .next()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_sample_pyc() {
+        let code = load_pyc(include_bytes!("../tests/sample.pyc")).unwrap();
+        assert_eq!(code.obj_name, "<module>");
+        assert_eq!(code.source_path, "sample.py");
+        assert_eq!(code.instructions.len(), code.locations.len());
+        assert_eq!(code.locations[0].row(), 1);
+
+        let add = code_constant(&code, "add");
+        assert_eq!(add.arg_names, vec!["a", "b"]);
+        assert_eq!(add.kwonlyarg_names, vec!["c", "d"]);
+        assert_eq!(add.varargs, Varargs::Named("args".to_string()));
+        assert_eq!(add.varkeywords, Varargs::Named("kwargs".to_string()));
+        assert_eq!(add.doc, Some("Add things up.".to_string()));
+        assert_eq!(add.first_line_number, 10);
+
+        let gen = code_constant(&code, "gen");
+        assert!(gen.is_generator);
+        assert!(gen
+            .label_map
+            .values()
+            .all(|index| *index < gen.instructions.len()));
+    }
+
+    #[test]
+    fn test_load_store() {
+        // x = 1; del x
+        let code = code(
+            &[100, 0, 90, 0, 91, 0, 100, 1, 83, 0],
+            vec![Value::Int(1.into()), Value::None],
+        );
+        assert_eq!(
+            convert(&code).unwrap().instructions,
+            vec![
+                Instruction::LoadConst {
+                    value: Constant::Integer { value: 1.into() }
+                },
+                Instruction::StoreName {
+                    name: "x".to_string(),
+                    scope: NameScope::Local
+                },
+                Instruction::DeleteName {
+                    name: "x".to_string()
+                },
+                Instruction::LoadConst {
+                    value: Constant::None
+                },
+                Instruction::ReturnValue,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extended_arg_jump() {
+        // EXTENDED_ARG 1; JUMP_ABSOLUTE 256 followed by padding up to offset 256.
+        let mut bytes = vec![144, 1, 113, 0];
+        while bytes.len() < 256 {
+            bytes.extend_from_slice(&[9, 0]);
+        }
+        bytes.extend_from_slice(&[100, 0, 83, 0]);
+        let converted = convert(&code(&bytes, vec![Value::None])).unwrap();
+        assert_eq!(converted.instructions[0], Instruction::Jump { target: 256 });
+        assert_eq!(converted.label_map[&256], 127);
+        assert_eq!(
+            converted.instructions[127],
+            Instruction::LoadConst {
+                value: Constant::None
             }
-        } // end match
-    } // end dispatch function
+        );
+    }
+
+    #[test]
+    fn test_unsupported() {
+        // try: pass / except: pass
+        let err = convert(&code(&[9, 0, 121, 4, 87, 0], vec![])).unwrap_err();
+        assert_eq!(
+            err.error,
+            ConvertErrorType::UnsupportedOpcode("SETUP_EXCEPT")
+        );
+        assert_eq!(err.offset, 2);
+        assert_eq!(
+            err.to_string(),
+            "unsupported opcode SETUP_EXCEPT at offset 2 in code object '<module>'"
+        );
+
+        let err = convert(&code(&[255, 0], vec![])).unwrap_err();
+        assert_eq!(err.error, ConvertErrorType::UnknownOpcode(255));
+
+        let err = convert(&code(&[100, 3], vec![])).unwrap_err();
+        assert_eq!(err.error, ConvertErrorType::InvalidArgument("LOAD_CONST"));
+
+        let err = convert(&code(&[100, 0], vec![Value::FrozenSet(vec![])])).unwrap_err();
+        assert_eq!(
+            err.error,
+            ConvertErrorType::UnsupportedConstant("frozenset")
+        );
 
-    fn emit(&mut self, instruction: bytecode::Instruction) {
-        self.code2.instructions.push(instruction);
+        let err = convert(&code(&[113, 1], vec![])).unwrap_err();
+        assert_eq!(err.error, ConvertErrorType::InvalidJumpTarget(1));
     }
 }
@@ -1,59 +1,35 @@
-#[macro_use]
-extern crate serde_derive;
-extern crate serde_json;
+//! Read CPython 3.7 `.pyc` files and translate the code objects they contain
+//! into RustPython bytecode.
+//!
+//! The [`marshal`] module reads and writes CPython's marshal format, [`pyc`] validates the
+//! `.pyc` header and [`convert`] maps CPython opcodes onto
+//! [`rustpython_bytecode::bytecode::Instruction`]s.
 
-use std::cell::RefCell;
-use std::rc::Rc;
-
-#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
-pub enum NativeType{
-    NoneType,
-    Boolean(bool),
-    Int(i32),
-    Float(f64),
-    Str(String),
-    Unicode(String),
-    #[serde(skip_serializing, skip_deserializing)]
-    List(RefCell<Vec<NativeType>>),
-    Tuple(Vec<NativeType>),
-    Iter(Vec<NativeType>), // TODO: use Iterator instead
-    Code(PyCodeObject),
-    Function(Function),
-    Slice(Option<i32>, Option<i32>, Option<i32>), // start, stop, step
-    #[serde(skip_serializing, skip_deserializing)]
-    NativeFunction(fn(Vec<Rc<NativeType>>) -> NativeType ),
-}
+pub mod convert;
+pub mod marshal;
+pub mod pyc;
 
+pub use convert::{convert, ConvertError, ConvertErrorType};
+pub use marshal::{MarshalError, Value};
+pub use pyc::{load_pyc, read_pyc, PycError};
 
-#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+/// A CPython 3.7 code object, as found in marshal data.
+#[derive(Clone, Debug, PartialEq)]
 pub struct PyCodeObject {
-    pub co_consts: Vec<NativeType>,
-    pub co_names: Vec<String>,
-    // TODO: use vector of bytecode objects instead of strings?
-    pub co_code: Vec<(usize, String, Option<usize>)>, //size, name, args
-    pub co_varnames: Vec<String>,
-}
-
-impl PyCodeObject {
-    pub fn new() -> PyCodeObject {
-        PyCodeObject {
-            co_consts: Vec::<NativeType>::new(),
-            co_names: Vec::<String>::new(),
-            co_code: Vec::<(usize, String, Option<usize>)>::new(), //size, name, args
-            co_varnames: Vec::<String>::new(),
-        }
-    }
-}
-
-#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
-pub struct Function {
-    pub code: PyCodeObject
-}
-
-impl Function {
-    pub fn new(code: PyCodeObject) -> Function {
-        Function {
-            code: code
-        }
-    }
+    pub argcount: u32,
+    pub kwonlyargcount: u32,
+    pub nlocals: u32,
+    pub stacksize: u32,
+    pub flags: u32,
+    /// The raw CPython wordcode.
+    pub code: Vec<u8>,
+    pub consts: Vec<Value>,
+    pub names: Vec<String>,
+    pub varnames: Vec<String>,
+    pub freevars: Vec<String>,
+    pub cellvars: Vec<String>,
+    pub filename: String,
+    pub name: String,
+    pub firstlineno: u32,
+    pub lnotab: Vec<u8>,
 }
@@ -0,0 +1,855 @@
+//! CPython's marshal format, the serialization used by `.pyc` files and the
+//! `marshal` module.
+
+use crate::PyCodeObject;
+use num_bigint::{BigInt, BigUint, Sign};
+use num_complex::Complex64;
+use num_traits::{ToPrimitive, Zero};
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read};
+
+const TYPE_NULL: u8 = b'0';
+const TYPE_NONE: u8 = b'N';
+const TYPE_FALSE: u8 = b'F';
+const TYPE_TRUE: u8 = b'T';
+const TYPE_STOPITER: u8 = b'S';
+const TYPE_ELLIPSIS: u8 = b'.';
+const TYPE_INT: u8 = b'i';
+const TYPE_INT64: u8 = b'I';
+const TYPE_FLOAT: u8 = b'f';
+const TYPE_BINARY_FLOAT: u8 = b'g';
+const TYPE_COMPLEX: u8 = b'x';
+const TYPE_BINARY_COMPLEX: u8 = b'y';
+const TYPE_LONG: u8 = b'l';
+const TYPE_STRING: u8 = b's';
+const TYPE_INTERNED: u8 = b't';
+const TYPE_REF: u8 = b'r';
+const TYPE_TUPLE: u8 = b'(';
+const TYPE_LIST: u8 = b'[';
+const TYPE_DICT: u8 = b'{';
+const TYPE_CODE: u8 = b'c';
+const TYPE_UNICODE: u8 = b'u';
+const TYPE_SET: u8 = b'<';
+const TYPE_FROZENSET: u8 = b'>';
+const TYPE_ASCII: u8 = b'a';
+const TYPE_ASCII_INTERNED: u8 = b'A';
+const TYPE_SMALL_TUPLE: u8 = b')';
+const TYPE_SHORT_ASCII: u8 = b'z';
+const TYPE_SHORT_ASCII_INTERNED: u8 = b'Z';
+
+/// Set on a type code when the object is stored in the reference table.
+const FLAG_REF: u8 = 0x80;
+
+/// How deeply containers may nest, as in CPython.
+const MAX_DEPTH: usize = 2000;
+
+/// Longs are stored as 15 bit digits, least significant first.
+const LONG_SHIFT: usize = 15;
+
+/// A value read from marshal data.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    None,
+    StopIteration,
+    Ellipsis,
+    Bool(bool),
+    Int(BigInt),
+    Float(f64),
+    Complex(Complex64),
+    Str(String),
+    /// A `str` holding lone surrogates, as the UTF-8 CPython writes for it
+    /// with `surrogatepass`: each surrogate is a three byte sequence.
+    SurrogateStr(Vec<u8>),
+    Bytes(Vec<u8>),
+    Tuple(Vec<Value>),
+    List(Vec<Value>),
+    Dict(Vec<(Value, Value)>),
+    Set(Vec<Value>),
+    FrozenSet(Vec<Value>),
+    Code(Box<PyCodeObject>),
+}
+
+impl Value {
+    /// The name of the Python type this value unmarshals to.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::None => "NoneType",
+            Value::StopIteration => "type",
+            Value::Ellipsis => "ellipsis",
+            Value::Bool(_) => "bool",
+            Value::Int(_) => "int",
+            Value::Float(_) => "float",
+            Value::Complex(_) => "complex",
+            Value::Str(_) | Value::SurrogateStr(_) => "str",
+            Value::Bytes(_) => "bytes",
+            Value::Tuple(_) => "tuple",
+            Value::List(_) => "list",
+            Value::Dict(_) => "dict",
+            Value::Set(_) => "set",
+            Value::FrozenSet(_) => "frozenset",
+            Value::Code(_) => "code",
+        }
+    }
+}
+
+/// The format version written by default, the newest CPython 3.7 knows.
+pub const VERSION: u32 = 4;
+
+#[derive(Debug, PartialEq)]
+pub enum MarshalError {
+    /// The data ended where an object should start.
+    Eof,
+    /// The data ended in the middle of an object.
+    TooShort,
+    /// Reading the underlying stream failed.
+    Io(io::ErrorKind),
+    /// An unknown type code.
+    UnknownType(u8),
+    /// A reference to an object that has not been read (yet).
+    InvalidReference(u32),
+    /// A NULL object where a value was expected.
+    UnexpectedNull,
+    /// A string that is not valid UTF-8.
+    InvalidString,
+    /// A textual float that does not parse.
+    InvalidFloat,
+    /// A digit of a long that does not fit in 15 bits.
+    InvalidDigit,
+    /// A negative size.
+    InvalidSize,
+    /// Containers nested deeper than `MAX_DEPTH`.
+    TooDeep,
+    /// A code object field holding a value of the wrong type.
+    UnexpectedValue {
+        expected: &'static str,
+        found: &'static str,
+    },
+}
+
+impl fmt::Display for MarshalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MarshalError::Eof => write!(f, "EOF read where object expected"),
+            MarshalError::TooShort => write!(f, "marshal data too short"),
+            MarshalError::Io(kind) => write!(f, "error reading marshal data: {:?}", kind),
+            MarshalError::UnknownType(_) => write!(f, "bad marshal data (unknown type code)"),
+            MarshalError::InvalidReference(_) => write!(f, "bad marshal data (invalid reference)"),
+            MarshalError::UnexpectedNull => write!(f, "NULL object in marshal data for object"),
+            MarshalError::InvalidString => write!(f, "bad marshal data (invalid utf-8 string)"),
+            MarshalError::InvalidFloat => write!(f, "bad marshal data (invalid float)"),
+            MarshalError::InvalidDigit => {
+                write!(f, "bad marshal data (digit out of range in long)")
+            }
+            MarshalError::InvalidSize => write!(f, "bad marshal data (size out of range)"),
+            MarshalError::TooDeep => write!(f, "recursion limit exceeded"),
+            MarshalError::UnexpectedValue { expected, found } => write!(
+                f,
+                "bad marshal data (expected {} in code object, found {})",
+                expected, found
+            ),
+        }
+    }
+}
+
+impl Error for MarshalError {}
+
+/// Read one value from the start of `data`, ignoring any trailing bytes.
+pub fn loads(data: &[u8]) -> Result<Value, MarshalError> {
+    let mut data = data;
+    read_value(&mut data)
+}
+
+/// Read one value from `source`, consuming exactly the bytes it takes up.
+pub fn read_value<R: Read>(source: &mut R) -> Result<Value, MarshalError> {
+    let mut reader = Reader {
+        source,
+        refs: Vec::new(),
+        depth: 0,
+    };
+    reader.read_object()
+}
+
+struct Reader<'a, R: Read> {
+    source: &'a mut R,
+    /// Objects flagged with `FLAG_REF`, in the order they were started.
+    refs: Vec<Option<Value>>,
+    depth: usize,
+}
+
+impl<'a, R: Read> Reader<'a, R> {
+    fn read_bytes(&mut self, count: usize) -> Result<Vec<u8>, MarshalError> {
+        // Don't trust the count for the allocation; broken data could
+        // otherwise ask for an arbitrarily large buffer.
+        let mut bytes = Vec::new();
+        self.source
+            .take(count as u64)
+            .read_to_end(&mut bytes)
+            .map_err(|err| MarshalError::Io(err.kind()))?;
+        if bytes.len() == count {
+            Ok(bytes)
+        } else {
+            Err(MarshalError::TooShort)
+        }
+    }
+
+    fn read_u8(&mut self) -> Result<u8, MarshalError> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_i32(&mut self) -> Result<i32, MarshalError> {
+        let bytes = self.read_bytes(4)?;
+        Ok(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn read_size(&mut self) -> Result<usize, MarshalError> {
+        let size = self.read_i32()?;
+        if size < 0 {
+            Err(MarshalError::InvalidSize)
+        } else {
+            Ok(size as usize)
+        }
+    }
+
+    fn read_f64(&mut self) -> Result<f64, MarshalError> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&self.read_bytes(8)?);
+        Ok(f64::from_le_bytes(bytes))
+    }
+
+    fn read_float_str(&mut self) -> Result<f64, MarshalError> {
+        let size = self.read_u8()? as usize;
+        let text =
+            String::from_utf8(self.read_bytes(size)?).map_err(|_| MarshalError::InvalidFloat)?;
+        match text.as_str() {
+            "inf" => Ok(f64::INFINITY),
+            "-inf" => Ok(f64::NEG_INFINITY),
+            "nan" => Ok(f64::NAN),
+            _ => text.parse().map_err(|_| MarshalError::InvalidFloat),
+        }
+    }
+
+    fn read_str(&mut self, size: usize) -> Result<Value, MarshalError> {
+        match String::from_utf8(self.read_bytes(size)?) {
+            Ok(value) => Ok(Value::Str(value)),
+            Err(err) if is_surrogate_utf8(err.as_bytes()) => {
+                Ok(Value::SurrogateStr(err.into_bytes()))
+            }
+            Err(_) => Err(MarshalError::InvalidString),
+        }
+    }
+
+    fn read_long(&mut self) -> Result<BigInt, MarshalError> {
+        let size = self.read_i32()?;
+        let sign = match size {
+            0 => Sign::NoSign,
+            n if n < 0 => Sign::Minus,
+            _ => Sign::Plus,
+        };
+        let mut value = BigInt::from(0);
+        for index in 0..size.unsigned_abs() as usize {
+            let bytes = self.read_bytes(2)?;
+            let digit = u16::from_le_bytes([bytes[0], bytes[1]]);
+            if digit >= 1 << LONG_SHIFT {
+                return Err(MarshalError::InvalidDigit);
+            }
+            value += BigInt::from(digit) << (index * LONG_SHIFT);
+        }
+        Ok(if sign == Sign::Minus { -value } else { value })
+    }
+
+    fn read_sequence(&mut self, typ: u8) -> Result<Value, MarshalError> {
+        let size = if typ == TYPE_SMALL_TUPLE {
+            self.read_u8()? as usize
+        } else {
+            self.read_size()?
+        };
+        let mut elements = Vec::new();
+        for _ in 0..size {
+            elements.push(self.read_object()?);
+        }
+        Ok(match typ {
+            TYPE_LIST => Value::List(elements),
+            TYPE_SET => Value::Set(elements),
+            TYPE_FROZENSET => Value::FrozenSet(elements),
+            _ => Value::Tuple(elements),
+        })
+    }
+
+    fn read_dict(&mut self) -> Result<Value, MarshalError> {
+        let mut items = Vec::new();
+        while let Some(key) = self.read_object_or_null()? {
+            let value = self.read_object()?;
+            items.push((key, value));
+        }
+        Ok(Value::Dict(items))
+    }
+
+    fn read_object(&mut self) -> Result<Value, MarshalError> {
+        self.read_object_or_null()?
+            .ok_or(MarshalError::UnexpectedNull)
+    }
+
+    fn read_object_or_null(&mut self) -> Result<Option<Value>, MarshalError> {
+        if self.depth >= MAX_DEPTH {
+            return Err(MarshalError::TooDeep);
+        }
+        self.depth += 1;
+        let result = self.read_object_inner();
+        self.depth -= 1;
+        result
+    }
+
+    fn read_object_inner(&mut self) -> Result<Option<Value>, MarshalError> {
+        let code = match self.read_u8() {
+            Err(MarshalError::TooShort) => return Err(MarshalError::Eof),
+            result => result?,
+        };
+        let typ = code & !FLAG_REF;
+        if typ == TYPE_NULL {
+            return Ok(None);
+        }
+        if typ == TYPE_REF {
+            let index = self.read_i32()? as u32;
+            return match self.refs.get(index as usize) {
+                Some(Some(value)) => Ok(Some(value.clone())),
+                _ => Err(MarshalError::InvalidReference(index)),
+            };
+        }
+
+        // Containers may be referred to from within themselves, so the slot
+        // is reserved before their contents are read.
+        let ref_index = if code & FLAG_REF != 0 {
+            self.refs.push(None);
+            Some(self.refs.len() - 1)
+        } else {
+            None
+        };
+
+        let value = match typ {
+            TYPE_TUPLE | TYPE_SMALL_TUPLE | TYPE_LIST | TYPE_SET | TYPE_FROZENSET => {
+                self.read_sequence(typ)?
+            }
+            TYPE_DICT => self.read_dict()?,
+            TYPE_CODE => Value::Code(Box::new(self.read_code()?)),
+            _ => self.read_scalar(code)?,
+        };
+
+        if let Some(index) = ref_index {
+            self.refs[index] = Some(value.clone());
+        }
+        Ok(Some(value))
+    }
+
+    /// Read a value that holds no other objects. This is kept out of
+    /// `read_object_inner` to keep its stack frame small, since nested
+    /// containers recurse through it.
+    fn read_scalar(&mut self, code: u8) -> Result<Value, MarshalError> {
+        Ok(match code & !FLAG_REF {
+            TYPE_NONE => Value::None,
+            TYPE_FALSE => Value::Bool(false),
+            TYPE_TRUE => Value::Bool(true),
+            TYPE_STOPITER => Value::StopIteration,
+            TYPE_ELLIPSIS => Value::Ellipsis,
+            TYPE_INT => Value::Int(BigInt::from(self.read_i32()?)),
+            TYPE_INT64 => {
+                let low = self.read_i32()? as u32 as i64;
+                let high = self.read_i32()? as i64;
+                Value::Int(BigInt::from(low | (high << 32)))
+            }
+            TYPE_LONG => Value::Int(self.read_long()?),
+            TYPE_FLOAT => Value::Float(self.read_float_str()?),
+            TYPE_BINARY_FLOAT => Value::Float(self.read_f64()?),
+            TYPE_COMPLEX => {
+                let re = self.read_float_str()?;
+                let im = self.read_float_str()?;
+                Value::Complex(Complex64::new(re, im))
+            }
+            TYPE_BINARY_COMPLEX => {
+                let re = self.read_f64()?;
+                let im = self.read_f64()?;
+                Value::Complex(Complex64::new(re, im))
+            }
+            TYPE_STRING => {
+                let size = self.read_size()?;
+                Value::Bytes(self.read_bytes(size)?)
+            }
+            TYPE_UNICODE | TYPE_INTERNED | TYPE_ASCII | TYPE_ASCII_INTERNED => {
+                let size = self.read_size()?;
+                self.read_str(size)?
+            }
+            TYPE_SHORT_ASCII | TYPE_SHORT_ASCII_INTERNED => {
+                let size = self.read_u8()? as usize;
+                self.read_str(size)?
+            }
+            _ => return Err(MarshalError::UnknownType(code)),
+        })
+    }
+
+    fn read_code(&mut self) -> Result<PyCodeObject, MarshalError> {
+        let argcount = self.read_size()? as u32;
+        let kwonlyargcount = self.read_size()? as u32;
+        let nlocals = self.read_size()? as u32;
+        let stacksize = self.read_size()? as u32;
+        let flags = self.read_i32()? as u32;
+        let code = self.read_bytes_object()?;
+        let consts = self.read_tuple()?;
+        let names = self.read_names()?;
+        let varnames = self.read_names()?;
+        let freevars = self.read_names()?;
+        let cellvars = self.read_names()?;
+        let filename = self.read_str_object()?;
+        let name = self.read_str_object()?;
+        let firstlineno = self.read_size()? as u32;
+        let lnotab = self.read_bytes_object()?;
+        Ok(PyCodeObject {
+            argcount,
+            kwonlyargcount,
+            nlocals,
+            stacksize,
+            flags,
+            code,
+            consts,
+            names,
+            varnames,
+            freevars,
+            cellvars,
+            filename,
+            name,
+            firstlineno,
+            lnotab,
+        })
+    }
+
+    fn read_bytes_object(&mut self) -> Result<Vec<u8>, MarshalError> {
+        match self.read_object()? {
+            Value::Bytes(bytes) => Ok(bytes),
+            other => Err(unexpected("bytes", &other)),
+        }
+    }
+
+    fn read_str_object(&mut self) -> Result<String, MarshalError> {
+        match self.read_object()? {
+            Value::Str(value) => Ok(value),
+            other => Err(unexpected("str", &other)),
+        }
+    }
+
+    fn read_tuple(&mut self) -> Result<Vec<Value>, MarshalError> {
+        match self.read_object()? {
+            Value::Tuple(elements) => Ok(elements),
+            other => Err(unexpected("tuple", &other)),
+        }
+    }
+
+    fn read_names(&mut self) -> Result<Vec<String>, MarshalError> {
+        self.read_tuple()?
+            .into_iter()
+            .map(|name| match name {
+                Value::Str(name) => Ok(name),
+                other => Err(unexpected("str", &other)),
+            })
+            .collect()
+    }
+}
+
+/// Serialize `value` in the given format version.
+pub fn dumps(value: &Value, version: u32) -> Vec<u8> {
+    let mut writer = Writer {
+        data: Vec::new(),
+        version,
+    };
+    writer.write_object(value);
+    writer.data
+}
+
+struct Writer {
+    data: Vec<u8>,
+    version: u32,
+}
+
+impl Writer {
+    fn write_u8(&mut self, value: u8) {
+        self.data.push(value);
+    }
+
+    fn write_i32(&mut self, value: i32) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn write_size(&mut self, size: usize) {
+        self.write_i32(size as i32);
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        self.write_size(bytes.len());
+        self.data.extend_from_slice(bytes);
+    }
+
+    fn write_float_str(&mut self, value: f64) {
+        let text = format_float(value);
+        self.write_u8(text.len() as u8);
+        self.data.extend_from_slice(text.as_bytes());
+    }
+
+    fn write_long(&mut self, value: &BigInt) {
+        let (sign, bytes) = value.to_bytes_le();
+        let mut magnitude = BigUint::from_bytes_le(&bytes);
+        let mut digits = vec![];
+        while !magnitude.is_zero() {
+            digits.push((&magnitude & BigUint::from(0x7fffu32)).to_u16().unwrap());
+            magnitude >>= LONG_SHIFT;
+        }
+        let size = digits.len() as i32;
+        self.write_i32(if sign == Sign::Minus { -size } else { size });
+        for digit in digits {
+            self.data.extend_from_slice(&digit.to_le_bytes());
+        }
+    }
+
+    fn write_str(&mut self, value: &str) {
+        if self.version >= 4 && value.is_ascii() {
+            if value.len() < 256 {
+                self.write_u8(TYPE_SHORT_ASCII);
+                self.write_u8(value.len() as u8);
+                self.data.extend_from_slice(value.as_bytes());
+            } else {
+                self.write_u8(TYPE_ASCII);
+                self.write_bytes(value.as_bytes());
+            }
+        } else {
+            self.write_u8(TYPE_UNICODE);
+            self.write_bytes(value.as_bytes());
+        }
+    }
+
+    fn write_sequence(&mut self, typ: u8, elements: &[Value]) {
+        self.write_u8(typ);
+        self.write_size(elements.len());
+        for element in elements {
+            self.write_object(element);
+        }
+    }
+
+    fn write_tuple(&mut self, elements: &[Value]) {
+        if self.version >= 4 && elements.len() < 256 {
+            self.write_u8(TYPE_SMALL_TUPLE);
+            self.write_u8(elements.len() as u8);
+            for element in elements {
+                self.write_object(element);
+            }
+        } else {
+            self.write_sequence(TYPE_TUPLE, elements);
+        }
+    }
+
+    fn write_names(&mut self, names: &[String]) {
+        let names: Vec<Value> = names.iter().map(|name| Value::Str(name.clone())).collect();
+        self.write_tuple(&names);
+    }
+
+    fn write_object(&mut self, value: &Value) {
+        match value {
+            Value::None => self.write_u8(TYPE_NONE),
+            Value::StopIteration => self.write_u8(TYPE_STOPITER),
+            Value::Ellipsis => self.write_u8(TYPE_ELLIPSIS),
+            Value::Bool(false) => self.write_u8(TYPE_FALSE),
+            Value::Bool(true) => self.write_u8(TYPE_TRUE),
+            Value::Int(value) => match value.to_i32() {
+                Some(value) => {
+                    self.write_u8(TYPE_INT);
+                    self.write_i32(value);
+                }
+                None => {
+                    self.write_u8(TYPE_LONG);
+                    self.write_long(value);
+                }
+            },
+            Value::Float(value) => {
+                if self.version > 1 {
+                    self.write_u8(TYPE_BINARY_FLOAT);
+                    self.data.extend_from_slice(&value.to_le_bytes());
+                } else {
+                    self.write_u8(TYPE_FLOAT);
+                    self.write_float_str(*value);
+                }
+            }
+            Value::Complex(value) => {
+                if self.version > 1 {
+                    self.write_u8(TYPE_BINARY_COMPLEX);
+                    self.data.extend_from_slice(&value.re.to_le_bytes());
+                    self.data.extend_from_slice(&value.im.to_le_bytes());
+                } else {
+                    self.write_u8(TYPE_COMPLEX);
+                    self.write_float_str(value.re);
+                    self.write_float_str(value.im);
+                }
+            }
+            Value::Str(value) => self.write_str(value),
+            Value::SurrogateStr(value) => {
+                self.write_u8(TYPE_UNICODE);
+                self.write_bytes(value);
+            }
+            Value::Bytes(value) => {
+                self.write_u8(TYPE_STRING);
+                self.write_bytes(value);
+            }
+            Value::Tuple(elements) => self.write_tuple(elements),
+            Value::List(elements) => self.write_sequence(TYPE_LIST, elements),
+            Value::Set(elements) => self.write_sequence(TYPE_SET, elements),
+            Value::FrozenSet(elements) => self.write_sequence(TYPE_FROZENSET, elements),
+            Value::Dict(items) => {
+                self.write_u8(TYPE_DICT);
+                for (key, value) in items {
+                    self.write_object(key);
+                    self.write_object(value);
+                }
+                self.write_u8(TYPE_NULL);
+            }
+            Value::Code(code) => {
+                self.write_u8(TYPE_CODE);
+                self.write_i32(code.argcount as i32);
+                self.write_i32(code.kwonlyargcount as i32);
+                self.write_i32(code.nlocals as i32);
+                self.write_i32(code.stacksize as i32);
+                self.write_i32(code.flags as i32);
+                self.write_object(&Value::Bytes(code.code.clone()));
+                self.write_tuple(&code.consts);
+                self.write_names(&code.names);
+                self.write_names(&code.varnames);
+                self.write_names(&code.freevars);
+                self.write_names(&code.cellvars);
+                self.write_str(&code.filename);
+                self.write_str(&code.name);
+                self.write_i32(code.firstlineno as i32);
+                self.write_object(&Value::Bytes(code.lnotab.clone()));
+            }
+        }
+    }
+}
+
+/// Format a float like C's `%.17g`, which is what CPython writes for the
+/// textual float types.
+fn format_float(value: f64) -> String {
+    if value.is_nan() {
+        return "nan".to_string();
+    }
+    if value.is_infinite() {
+        return if value > 0.0 { "inf" } else { "-inf" }.to_string();
+    }
+    if value == 0.0 {
+        return if value.is_sign_negative() { "-0" } else { "0" }.to_string();
+    }
+    let scientific = format!("{:.16e}", value);
+    let (mantissa, exponent) = scientific.split_at(scientific.find('e').unwrap());
+    let exponent: i32 = exponent[1..].parse().unwrap();
+    let trim = |digits: &str| -> String {
+        if digits.contains('.') {
+            digits
+                .trim_end_matches('0')
+                .trim_end_matches('.')
+                .to_string()
+        } else {
+            digits.to_string()
+        }
+    };
+    if !(-4..17).contains(&exponent) {
+        format!(
+            "{}e{}{:02}",
+            trim(mantissa),
+            if exponent < 0 { '-' } else { '+' },
+            exponent.abs()
+        )
+    } else {
+        trim(&format!("{:.*}", (16 - exponent) as usize, value))
+    }
+}
+
+/// Whether `bytes` is UTF-8 apart from encoded surrogates, U+D800..U+DFFF,
+/// which is what `surrogatepass` accepts.
+fn is_surrogate_utf8(mut bytes: &[u8]) -> bool {
+    loop {
+        let rest = match std::str::from_utf8(bytes) {
+            Ok(_) => return true,
+            Err(err) => &bytes[err.valid_up_to()..],
+        };
+        match rest {
+            [0xed, 0xa0..=0xbf, 0x80..=0xbf, ..] => bytes = &rest[3..],
+            _ => return false,
+        }
+    }
+}
+
+fn unexpected(expected: &'static str, found: &Value) -> MarshalError {
+    MarshalError::UnexpectedValue {
+        expected,
+        found: found.type_name(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_simple_values() {
+        assert_eq!(loads(b"N"), Ok(Value::None));
+        assert_eq!(loads(b"T"), Ok(Value::Bool(true)));
+        assert_eq!(
+            loads(b"i\xfe\xff\xff\xff"),
+            Ok(Value::Int(BigInt::from(-2)))
+        );
+        assert_eq!(
+            loads(b"g\x00\x00\x00\x00\x00\x00\xf8\x3f"),
+            Ok(Value::Float(1.5))
+        );
+        assert_eq!(loads(b"\xda\x03abc"), Ok(Value::Str("abc".to_string())));
+        assert_eq!(
+            loads(b"s\x02\x00\x00\x00hi"),
+            Ok(Value::Bytes(b"hi".to_vec()))
+        );
+    }
+
+    #[test]
+    fn test_long() {
+        // marshal.dumps(-2**40)
+        assert_eq!(
+            loads(b"\xec\xfd\xff\xff\xff\x00\x00\x00\x00\x00\x04"),
+            Ok(Value::Int(-(BigInt::from(1) << 40)))
+        );
+    }
+
+    #[test]
+    fn test_refs() {
+        // marshal.dumps(("ab", "ab")), the second string is a reference.
+        let data = b"\xa9\x02\xda\x02abr\x01\x00\x00\x00";
+        assert_eq!(
+            loads(data),
+            Ok(Value::Tuple(vec![
+                Value::Str("ab".to_string()),
+                Value::Str("ab".to_string())
+            ]))
+        );
+        assert_eq!(
+            loads(b"r\x05\x00\x00\x00"),
+            Err(MarshalError::InvalidReference(5))
+        );
+    }
+
+    #[test]
+    fn test_dict() {
+        let data = b"\xfb\xda\x01a\xe9\x01\x00\x00\x000";
+        assert_eq!(
+            loads(data),
+            Ok(Value::Dict(vec![(
+                Value::Str("a".to_string()),
+                Value::Int(BigInt::from(1))
+            )]))
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(loads(b""), Err(MarshalError::Eof));
+        assert_eq!(loads(b"i\x01\x00"), Err(MarshalError::TooShort));
+        assert_eq!(loads(b"("), Err(MarshalError::TooShort));
+        assert_eq!(loads(b"(\x01\x00\x00\x00"), Err(MarshalError::Eof));
+        assert_eq!(loads(b"\x01"), Err(MarshalError::UnknownType(1)));
+        assert_eq!(loads(b"0"), Err(MarshalError::UnexpectedNull));
+    }
+
+    #[test]
+    fn test_too_deep() {
+        let mut nested = Vec::new();
+        for _ in 0..=MAX_DEPTH {
+            nested.extend_from_slice(b"[\x01\x00\x00\x00");
+        }
+        // Unoptimized builds need more stack than the test harness gives
+        // for MAX_DEPTH nested reads.
+        let result = std::thread::Builder::new()
+            .stack_size(64 * 1024 * 1024)
+            .spawn(move || loads(&nested))
+            .unwrap()
+            .join()
+            .unwrap();
+        assert_eq!(result, Err(MarshalError::TooDeep));
+    }
+
+    #[test]
+    fn test_dumps() {
+        // The same bytes as CPython's marshal.dumps(value, 2).
+        assert_eq!(dumps(&Value::Int(1.into()), 2), b"i\x01\x00\x00\x00");
+        assert_eq!(
+            dumps(&Value::Int(BigInt::from(1) << 40), 2),
+            b"l\x03\x00\x00\x00\x00\x00\x00\x00\x00\x04"
+        );
+        assert_eq!(dumps(&Value::Float(1.5), 1), b"f\x031.5");
+        assert_eq!(
+            dumps(&Value::Complex(Complex64::new(0.0, 1.0)), 1),
+            b"x\x010\x011"
+        );
+        assert_eq!(
+            dumps(&Value::Str("abc".to_string()), 2),
+            b"u\x03\x00\x00\x00abc"
+        );
+        assert_eq!(
+            dumps(
+                &Value::Dict(vec![(Value::Int(1.into()), Value::Int(2.into()))]),
+                2
+            ),
+            b"{i\x01\x00\x00\x00i\x02\x00\x00\x000"
+        );
+        // Version 4 has short strings and tuples.
+        assert_eq!(
+            dumps(&Value::Tuple(vec![Value::Str("ab".to_string())]), 4),
+            b")\x01z\x02ab"
+        );
+    }
+
+    #[test]
+    fn test_surrogate_str() {
+        // CPython's marshal.dumps('\udcff').
+        let data = b"\xf5\x03\x00\x00\x00\xed\xb3\xbf";
+        let value = Value::SurrogateStr(vec![0xed, 0xb3, 0xbf]);
+        assert_eq!(loads(data), Ok(value.clone()));
+        assert_eq!(dumps(&value, 4), b"u\x03\x00\x00\x00\xed\xb3\xbf");
+        assert_eq!(
+            loads(b"\xf5\x01\x00\x00\x00\xff"),
+            Err(MarshalError::InvalidString)
+        );
+        // Encoded code points past the surrogates are not valid either.
+        assert_eq!(
+            loads(b"\xf5\x03\x00\x00\x00\xed\xbf\xc0"),
+            Err(MarshalError::InvalidString)
+        );
+    }
+
+    #[test]
+    fn test_format_float() {
+        assert_eq!(format_float(1.5), "1.5");
+        assert_eq!(format_float(0.1), "0.10000000000000001");
+        assert_eq!(format_float(1e100), "1e+100");
+        assert_eq!(format_float(-2.5e-7), "-2.4999999999999999e-07");
+        assert_eq!(format_float(-0.0), "-0");
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let value = Value::List(vec![
+            Value::None,
+            Value::Bool(false),
+            Value::Int(-(BigInt::from(7) << 100)),
+            Value::Float(-0.1),
+            Value::Complex(Complex64::new(1.0, -2.0)),
+            Value::Str("h\u{e9}llo".to_string()),
+            Value::Bytes(vec![0, 255]),
+            Value::Set(vec![Value::Ellipsis]),
+            Value::FrozenSet(vec![]),
+            Value::Dict(vec![]),
+            Value::Tuple(vec![Value::StopIteration; 300]),
+        ]);
+        for version in 0..=4 {
+            assert_eq!(loads(&dumps(&value, version)), Ok(value.clone()));
+        }
+    }
+}
@@ -0,0 +1,102 @@
+//! The `.pyc` file format: a 16 byte header followed by a marshalled code object.
+
+use crate::convert::{convert, ConvertError};
+use crate::marshal::{self, MarshalError, Value};
+use crate::PyCodeObject;
+use rustpython_bytecode::bytecode::CodeObject;
+use std::error::Error;
+use std::fmt;
+
+/// The magic number of CPython 3.7 `.pyc` files (3394).
+pub const MAGIC_NUMBER: [u8; 4] = [0x42, 0x0d, 0x0d, 0x0a];
+
+/// Magic number, flags and either the source mtime and size or a source hash.
+const HEADER_SIZE: usize = 16;
+
+#[derive(Debug, PartialEq)]
+pub enum PycError {
+    /// The file is shorter than the `.pyc` header.
+    TruncatedHeader,
+    /// The file was written by another Python version.
+    BadMagicNumber([u8; 4]),
+    /// The marshal data is broken.
+    Marshal(MarshalError),
+    /// The marshalled object is not a code object.
+    NotCode(&'static str),
+    /// The code object can't be translated.
+    Convert(ConvertError),
+}
+
+impl fmt::Display for PycError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PycError::TruncatedHeader => write!(f, "truncated .pyc header"),
+            PycError::BadMagicNumber(_) => {
+                write!(f, "bad magic number in .pyc file (expected CPython 3.7)")
+            }
+            PycError::Marshal(err) => write!(f, "{}", err),
+            PycError::NotCode(typ) => write!(f, ".pyc file holds a {} instead of code", typ),
+            PycError::Convert(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for PycError {}
+
+impl From<MarshalError> for PycError {
+    fn from(error: MarshalError) -> Self {
+        PycError::Marshal(error)
+    }
+}
+
+impl From<ConvertError> for PycError {
+    fn from(error: ConvertError) -> Self {
+        PycError::Convert(error)
+    }
+}
+
+/// Check the header of a CPython 3.7 `.pyc` file and read its code object.
+pub fn read_pyc(data: &[u8]) -> Result<PyCodeObject, PycError> {
+    if data.len() < HEADER_SIZE {
+        return Err(PycError::TruncatedHeader);
+    }
+    if data[..4] != MAGIC_NUMBER {
+        return Err(PycError::BadMagicNumber([
+            data[0], data[1], data[2], data[3],
+        ]));
+    }
+    match marshal::loads(&data[HEADER_SIZE..])? {
+        Value::Code(code) => Ok(*code),
+        other => Err(PycError::NotCode(other.type_name())),
+    }
+}
+
+/// Read a CPython 3.7 `.pyc` file and translate its code object to RustPython
+/// bytecode.
+pub fn load_pyc(data: &[u8]) -> Result<CodeObject, PycError> {
+    Ok(convert(&read_pyc(data)?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header() {
+        assert_eq!(
+            read_pyc(b"\x42\x0d\x0d\x0a"),
+            Err(PycError::TruncatedHeader)
+        );
+        let mut data = b"\x33\x0d\x0d\x0a".to_vec();
+        data.extend_from_slice(&[0; 12]);
+        data.push(b'N');
+        assert_eq!(
+            read_pyc(&data),
+            Err(PycError::BadMagicNumber([0x33, 0x0d, 0x0d, 0x0a]))
+        );
+        data[0] = 0x42;
+        assert_eq!(read_pyc(&data), Err(PycError::NotCode("NoneType")));
+        data.pop();
+        assert_eq!(read_pyc(&data), Err(PycError::Marshal(MarshalError::Eof)));
+    }
+}
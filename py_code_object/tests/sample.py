@@ -0,0 +1,97 @@
+"""Exercises the CPython 3.7 opcodes the .pyc loader translates."""
+import math
+from math import pi as p
+from math import *
+
+assert p == math.pi
+assert floor(2.5) == 2
+
+
+def add(a, b=2, *args, c, d=4, **kwargs):
+    "Add things up."
+    return a + b + sum(args) + c + d + sum(kwargs.values())
+
+
+assert add(1, c=3) == 10
+assert add(1, 2, 3, c=4, e=5) == 19
+assert add.__doc__ == "Add things up."
+assert add(*[1, 2], **{"c": 3}) == 10
+
+
+def counter():
+    count = 0
+
+    def increment(step=1):
+        nonlocal count
+        count += step
+        return count
+
+    return increment
+
+
+inc = counter()
+inc()
+assert inc(2) == 3
+
+
+class Base:
+    kind = "base"
+
+    def describe(self):
+        return "I am " + self.kind
+
+
+class Child(Base):
+    """A child."""
+
+    kind = "child"
+
+    def describe(self):
+        return super().describe() + "!"
+
+
+assert Child().describe() == "I am child!"
+assert Child.__doc__ == "A child."
+assert Child.__qualname__ == "Child"
+
+
+def gen(n):
+    for i in range(n):
+        if i % 2:
+            continue
+        if i > 6:
+            break
+        yield i
+    yield from [100, 200]
+
+
+assert list(gen(10)) == [0, 2, 4, 6, 100, 200]
+
+squares = [x * x for x in range(5) if x]
+assert squares == [1, 4, 9, 16]
+assert {x: -x for x in range(3)} == {0: 0, 1: -1, 2: -2}
+assert {x % 3 for x in range(10)} == {0, 1, 2}
+assert sum(x for x in range(4)) == 6
+
+d = {"a": 1, "b": 2}
+d["a"] += 10
+assert d == {"a": 11, "b": 2}
+assert {**d, "c": 3} == {"a": 11, "b": 2, "c": 3}
+
+first, *rest = [1, 2, 3]
+assert (first, rest) == (1, [2, 3])
+a, b = b, a = 1, 2
+
+x = 5
+assert (x > 3 and x < 10) or x == 0
+assert not (x < 3 or x > 10)
+assert [1, 2, 3][1:] == [2, 3]
+assert f"{x!r}|{x:>3}" == "5|  5"
+assert (lambda y: y * x)(2) == 10
+
+n = 0
+while n < 100:
+    n += 7
+else:
+    n = -n
+assert n == -105
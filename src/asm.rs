@@ -0,0 +1,70 @@
+//! Assemble a textual bytecode listing and run it, for testing the virtual
+//! machine without going through the parser and the compiler.
+#[macro_use]
+extern crate clap;
+extern crate env_logger;
+
+use clap::{App, Arg};
+use rustpython_compiler::compile;
+use rustpython_vm::{
+    bytecode::CodeObject, import, obj::objcode, print_exception, util, VirtualMachine,
+};
+use std::process;
+
+fn main() {
+    env_logger::init();
+    let matches = App::new("rustpython-asm")
+        .version(crate_version!())
+        .author(crate_authors!())
+        .about("Assemble and run RustPython bytecode listings")
+        .arg(Arg::with_name("file").required(true).index(1))
+        .arg(
+            Arg::with_name("check")
+                .long("check")
+                .conflicts_with("compile")
+                .help("Print the assembled listing back instead of running it"),
+        )
+        .arg(
+            Arg::with_name("compile")
+                .long("compile")
+                .help("Compile a Python source file and print its listing"),
+        )
+        .get_matches();
+
+    let file = matches.value_of("file").unwrap();
+    let source = util::read_file(file.as_ref()).unwrap_or_else(|err| {
+        eprintln!("can't open file '{}': {}", file, err);
+        process::exit(1);
+    });
+
+    if matches.is_present("compile") {
+        match compile::compile(&source, &compile::Mode::Exec, file.to_string(), 0) {
+            Ok(code) => println!("{}", code),
+            Err(err) => {
+                eprintln!("{}: {}", file, err);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let code: CodeObject = source.parse().unwrap_or_else(|err| {
+        eprintln!("{}: {}", file, err);
+        process::exit(1);
+    });
+    if matches.is_present("check") {
+        println!("{}", code);
+        return;
+    }
+
+    let vm: VirtualMachine = Default::default();
+    let result = import::init_importlib(&vm, true).and_then(|_| {
+        // Listings can describe code that would crash the frame.
+        let code = objcode::verify_code(code, &vm)?;
+        vm.run_code_obj(vm.ctx.new_code_object(code), vm.new_scope_with_builtins())
+    });
+    if let Err(err) = result {
+        print_exception(&vm, &err);
+        process::exit(1);
+    }
+}
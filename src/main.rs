@@ -5,11 +5,14 @@ extern crate env_logger;
 extern crate log;
 
 use clap::{App, Arg, ArgMatches};
-use rustpython_compiler::{compile, error::CompileError, error::CompileErrorType};
-use rustpython_parser::error::ParseErrorType;
+use rustpython_compiler::compile;
+use rustpython_parser::parser;
 use rustpython_vm::{
-    import,
-    obj::objstr,
+    gil, import,
+    obj::{
+        objcode::{self, PyCodeRef},
+        objstr,
+    },
     print_exception,
     pyobject::{ItemProtocol, PyResult},
     scope::Scope,
@@ -18,7 +21,8 @@ use rustpython_vm::{
 use std::convert::TryInto;
 
 use std::env;
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::process;
 use std::str::FromStr;
 
@@ -29,11 +33,29 @@ fn main() {
     let app = App::new("RustPython");
     let matches = parse_arguments(app);
     let settings = create_settings(&matches);
+
+    if let Some(kind) = matches.value_of("dump") {
+        match dump_json(&matches, kind, settings.optimize) {
+            Ok(json) => println!("{}", json),
+            Err(err) => {
+                eprintln!("{}", err);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
     let vm = VirtualMachine::new(settings);
 
     let res = run_rustpython(&vm, &matches);
     // See if any exception leaked out:
-    handle_exception(&vm, res);
+    if let Err(err) = &res {
+        print_exception(&vm, err);
+    }
+    run_exit_functions(&vm);
+    if res.is_err() {
+        process::exit(1);
+    }
 
     #[cfg(feature = "flame-it")]
     {
@@ -106,6 +128,13 @@ fn parse_arguments<'a>(app: App<'a, '_>) -> ArgMatches<'a> {
                 .takes_value(true)
                 .help("run library module as script"),
         )
+        .arg(
+            Arg::with_name("dump")
+                .long("dump")
+                .takes_value(true)
+                .possible_values(&["ast", "bytecode"])
+                .help("print the ast or code object as json instead of running"),
+        )
         .arg(Arg::from_usage("[pyargs] 'args for python'").multiple(true));
     #[cfg(feature = "flame-it")]
     let app = app
@@ -211,6 +240,32 @@ fn get_paths(env_variable_name: &str) -> Vec<String> {
     }
 }
 
+/// Parse or compile the script (or `-c` command) and serialize the result as json.
+fn dump_json(matches: &ArgMatches, kind: &str, optimize: u8) -> Result<String, String> {
+    let (source, source_path) = if let Some(command) = matches.value_of("c") {
+        (command.to_string(), "<stdin>".to_string())
+    } else if let Some(script) = matches.value_of("script") {
+        let source = util::read_file(Path::new(script))
+            .map_err(|err| format!("can't open file '{}': {}", script, err))?;
+        (source, script.to_string())
+    } else {
+        return Err("--dump requires a script or a -c command".to_string());
+    };
+
+    let json = match kind {
+        "ast" => {
+            let program = parser::parse_program(&source).map_err(|err| err.to_string())?;
+            serde_json::to_string_pretty(&program)
+        }
+        _ => {
+            let code = compile::compile(&source, &compile::Mode::Exec, source_path, optimize)
+                .map_err(|err| err.to_string())?;
+            serde_json::to_string_pretty(&code)
+        }
+    };
+    json.map_err(|err| err.to_string())
+}
+
 #[cfg(feature = "flame-it")]
 fn write_profile(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
     use std::fs::File;
@@ -297,15 +352,29 @@ fn _run_string(vm: &VirtualMachine, source: &str, source_path: String) -> PyResu
         .compile(source, &compile::Mode::Exec, source_path.clone())
         .map_err(|err| vm.new_syntax_error(&err))?;
     // trace!("Code object: {:?}", code_obj.borrow());
+    _run_code(vm, code_obj, source_path)
+}
+
+fn _run_code(vm: &VirtualMachine, code_obj: PyCodeRef, source_path: String) -> PyResult {
     let attrs = vm.ctx.new_dict();
+    attrs.set_item("__name__", vm.new_str("__main__".to_string()), vm)?;
     attrs.set_item("__file__", vm.new_str(source_path), vm)?;
+    attrs.set_item("__doc__", vm.get_none(), vm)?;
     vm.run_code_obj(code_obj, Scope::with_builtins(None, attrs, vm))
 }
 
-fn handle_exception<T>(vm: &VirtualMachine, result: PyResult<T>) {
-    if let Err(err) = result {
-        print_exception(vm, &err);
-        process::exit(1);
+/// Run the functions registered with the `atexit` module, if it was imported.
+fn run_exit_functions(vm: &VirtualMachine) {
+    let atexit = vm
+        .get_attribute(vm.sys_module.clone(), "modules")
+        .and_then(|modules| modules.get_item_option("atexit", vm));
+    if let Ok(Some(atexit)) = atexit {
+        let result = vm
+            .get_attribute(atexit, "_run_exitfuncs")
+            .and_then(|run_exitfuncs| vm.invoke(run_exitfuncs, vec![]));
+        if let Err(err) = result {
+            print_exception(vm, &err);
+        }
     }
 }
 
@@ -351,6 +420,10 @@ fn run_script(vm: &VirtualMachine, script_file: &str) -> PyResult<()> {
     let sys_path = vm.get_attribute(vm.sys_module.clone(), "path").unwrap();
     vm.call_method(&sys_path, "insert", vec![vm.new_int(0), vm.new_str(dir)])?;
 
+    if file_path.extension().map_or(false, |ext| ext == "pyc") {
+        return run_pyc(vm, &file_path);
+    }
+
     match util::read_file(&file_path) {
         Ok(source) => {
             _run_string(vm, &source, file_path.to_str().unwrap().to_string())?;
@@ -367,6 +440,34 @@ fn run_script(vm: &VirtualMachine, script_file: &str) -> PyResult<()> {
     Ok(())
 }
 
+/// Run a CPython 3.7 `.pyc` file by translating its code object.
+fn run_pyc(vm: &VirtualMachine, file_path: &Path) -> PyResult<()> {
+    let data = match fs::read(file_path) {
+        Ok(data) => data,
+        Err(err) => {
+            error!(
+                "Failed reading file '{}': {:?}",
+                file_path.to_str().unwrap(),
+                err.kind()
+            );
+            process::exit(1);
+        }
+    };
+    let code = py_code_object::load_pyc(&data).map_err(|err| {
+        vm.new_exception(
+            vm.ctx.exceptions.runtime_error.clone(),
+            format!("{}: {}", file_path.to_str().unwrap(), err),
+        )
+    })?;
+    let code = objcode::verify_code(code, vm)?;
+    _run_code(
+        vm,
+        vm.ctx.new_code_object(code),
+        file_path.to_str().unwrap().to_string(),
+    )?;
+    Ok(())
+}
+
 #[test]
 fn test_run_script() {
     let vm: VirtualMachine = Default::default();
@@ -380,7 +481,16 @@ fn test_run_script() {
     assert!(r.is_ok());
 }
 
-fn shell_exec(vm: &VirtualMachine, source: &str, scope: Scope) -> Result<(), CompileError> {
+#[test]
+fn test_run_pyc() {
+    let vm: VirtualMachine = Default::default();
+    import::init_importlib(&vm, false).unwrap();
+
+    let r = run_script(&vm, "py_code_object/tests/sample.pyc");
+    assert!(r.is_ok());
+}
+
+fn shell_exec(vm: &VirtualMachine, source: &str, scope: Scope) {
     match vm.compile(source, &compile::Mode::Single, "<stdin>".to_string()) {
         Ok(code) => {
             match vm.run_code_obj(code, scope.clone()) {
@@ -399,20 +509,10 @@ fn shell_exec(vm: &VirtualMachine, source: &str, scope: Scope) -> Result<(), Com
                     print_exception(vm, &err);
                 }
             }
-
-            Ok(())
         }
-        // Don't inject syntax errors for line continuation
-        Err(
-            err @ CompileError {
-                error: CompileErrorType::Parse(ParseErrorType::EOF),
-                ..
-            },
-        ) => Err(err),
         Err(err) => {
             let exc = vm.new_syntax_error(&err);
             print_exception(vm, &exc);
-            Err(err)
         }
     }
 }
@@ -449,6 +549,8 @@ fn run_shell(vm: &VirtualMachine) -> PyResult<()> {
         crate_version!()
     );
     let vars = vm.new_scope_with_builtins();
+    vars.globals
+        .set_item("__name__", vm.new_str("__main__".to_string()), vm)?;
 
     // Read a single line:
     let mut input = String::new();
@@ -468,37 +570,36 @@ fn run_shell(vm: &VirtualMachine) -> PyResult<()> {
         } else {
             get_prompt(vm, "ps1")
         };
-        match repl.readline(&prompt) {
+        let readline = {
+            let repl = &mut repl;
+            let prompt = prompt.as_str();
+            // Other threads keep running while waiting for input.
+            gil::allow_threads(move || repl.readline(prompt))
+        };
+        match readline {
             Ok(line) => {
                 debug!("You entered {:?}", line);
-                input.push_str(&line);
-                input.push('\n');
+                // Lines read from a pipe still have their line ending
+                input.push_str(line.trim_end_matches('\n'));
                 repl.add_history_entry(line.trim_end());
 
-                if continuing {
-                    if line.is_empty() {
-                        continuing = false;
-                    } else {
-                        continue;
-                    }
+                // Ask for more lines until the parser has a whole statement,
+                // invalid input is compiled right away to report the error.
+                let status = parser::classify_input(&input);
+                input.push('\n');
+                if status == parser::InputStatus::Incomplete {
+                    continuing = true;
+                    continue;
                 }
 
-                match shell_exec(vm, &input, vars.clone()) {
-                    Err(CompileError {
-                        error: CompileErrorType::Parse(ParseErrorType::EOF),
-                        ..
-                    }) => {
-                        continuing = true;
-                        continue;
-                    }
-                    _ => {
-                        input = String::new();
-                    }
-                }
+                shell_exec(vm, &input, vars.clone());
+                input = String::new();
+                continuing = false;
             }
             Err(ReadlineError::Interrupted) => {
                 // TODO: Raise a real KeyboardInterrupt exception
                 println!("^C");
+                input = String::new();
                 continuing = false;
                 continue;
             }
@@ -525,6 +626,8 @@ fn run_shell(vm: &VirtualMachine) -> PyResult<()> {
         crate_version!()
     );
     let vars = vm.new_scope_with_builtins();
+    vars.globals
+        .set_item("__name__", vm.new_str("__main__".to_string()), vm)?;
 
     let stdin = io::stdin();
     let stdout = io::stdout();
@@ -535,7 +638,7 @@ fn run_shell(vm: &VirtualMachine) -> PyResult<()> {
     for line in stdin.lock().lines() {
         let mut line = line.expect("line failed");
         line.push('\n');
-        let _ = shell_exec(vm, &line, vars.clone());
+        shell_exec(vm, &line, vars.clone());
         print!("{}", get_prompt(vm, "ps1"));
         stdout.flush().expect("flush failed");
     }
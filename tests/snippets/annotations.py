@@ -0,0 +1,95 @@
+from testutils import assertRaises
+
+x: int = 5
+y: "str"
+assert x == 5
+assert __annotations__ == {'x': int, 'y': 'str'}
+
+# A name in parentheses is assigned but not recorded, and its annotation is
+# still evaluated.
+(q): len('abc') = 3
+assert q == 3
+assert 'q' not in __annotations__
+try:
+    (r): undefined_name
+except NameError:
+    pass
+else:
+    assert False, "the annotation should be evaluated"
+
+try:
+    y
+except NameError:
+    pass
+else:
+    assert False, "y should not be bound"
+
+
+class A:
+    a: int
+    b: str = "b"
+    if True:
+        c: list = []
+    (d): float = 1.0
+
+    def method(self) -> None:
+        pass
+
+assert A.__annotations__ == {'a': int, 'b': str, 'c': list}
+assert A.b == "b"
+assert A.d == 1.0
+assert A.c == []
+assert not hasattr(A, 'a')
+
+
+class B:
+    pass
+
+assert '__annotations__' not in B.__dict__
+
+
+def f(a: int, b=1, *args: tuple, c: str, d: float = 2.0, **kwargs: dict) -> bool:
+    local: undefined_name = 1
+    return local
+
+assert f.__annotations__ == {
+    'a': int, 'args': tuple, 'c': str, 'd': float, 'kwargs': dict, 'return': bool,
+}
+assert list(f.__annotations__) == ['a', 'args', 'c', 'd', 'kwargs', 'return']
+assert f(1, c="") == 1
+
+
+def g(a, b):
+    pass
+
+assert g.__annotations__ == {}
+
+
+# Annotations are evaluated before the function body is compiled.
+def h(a: (lambda: int)()) -> (lambda: str)():
+    return (lambda: a)()
+
+assert h.__annotations__ == {'a': int, 'return': str}
+assert h(3) == 3
+
+
+# Targets that are not a simple name are evaluated, but not stored.
+class C:
+    pass
+
+obj = C()
+obj.attr: int = 3
+assert obj.attr == 3
+d = {}
+d['key']: str = 'value'
+assert d == {'key': 'value'}
+assert 'attr' not in __annotations__
+
+with assertRaises(NameError):
+    undefined_object.attr: int
+
+with assertRaises(SyntaxError):
+    exec("a, b: int = 1, 2")
+
+with assertRaises(SyntaxError):
+    exec("[a]: int")
@@ -0,0 +1,29 @@
+from __future__ import annotations
+import __future__
+
+assert 'annotations' in __future__.all_feature_names
+
+x: List[int] = [1]
+assert __annotations__ == {'x': 'List[int]'}
+
+
+def f(a: Dict[str, int], *, b: a + b * c = 1) -> Optional[Tuple[int, ...]]:
+    pass
+
+assert f.__annotations__ == {
+    'a': 'Dict[str, int]',
+    'b': 'a + b * c',
+    'return': 'Optional[Tuple[int, ...]]',
+}
+
+
+class A:
+    attr: 'quoted'
+    other: lambda x, *y, z=1: (x, y)
+    cond: int if x else None
+
+assert A.__annotations__ == {
+    'attr': "'quoted'",
+    'other': 'lambda x, *y, z=1: (x, y)',
+    'cond': 'int if x else None',
+}
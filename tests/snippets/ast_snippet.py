@@ -38,3 +38,15 @@ assert i.module is None
 assert i.names[0].name == 'a'
 assert i.names[0].asname is None
 
+
+n = ast.parse("@d\nclass A(B, metaclass=M):\n    def f(self, a, *b, c=1, **d):\n        pass\n")
+c = n.body[0]
+assert c.name == 'A'
+assert [b.id for b in c.bases] == ['B']
+assert c.keywords[0].arg == 'metaclass'
+assert c.decorator_list[0].id == 'd'
+args = c.body[0].args
+assert [a.arg for a in args.args] == ['self', 'a']
+assert args.vararg.arg == 'b'
+assert [a.arg for a in args.kwonlyargs] == ['c']
+assert args.kwarg.arg == 'd'
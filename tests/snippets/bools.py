@@ -77,3 +77,9 @@ assert False.__xor__(0) is not False
 assert False.__xor__(False) is False
 assert False.__rxor__(0) is not False
 assert False.__rxor__(False) is False
+
+# Mixed and/or expressions leave exactly one value on the stack
+for c in "a ":
+    assert (c.isspace() and c or "_") == (" " if c == " " else "_")
+assert (0 and 1 or 2) == 2
+assert (1 and 0 or 0 and 2) == 0
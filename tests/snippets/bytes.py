@@ -135,6 +135,36 @@ assert b"AaBbCc123'@/".swapcase() == b"aAbBcC123'@/"
 # hex from hex
 assert bytes([0, 1, 9, 23, 90, 234]).hex() == "000109175aea"
 
+# bool
+assert not b""
+assert b"\x00"
+
+# decode
+assert b"abc".decode() == "abc"
+assert "\u00e9t\u00e9".encode().decode("utf-8") == "\u00e9t\u00e9"
+with assertRaises(UnicodeDecodeError):
+    b"\xff".decode()
+assert b"caf\xe9".decode("latin-1") == "caf\u00e9"
+assert b"caf\xe9".decode("ISO-8859-1") == "caf\u00e9"
+assert "caf\u00e9".encode("latin-1") == b"caf\xe9"
+assert b"abc".decode("ascii") == "abc"
+assert b"a\xffb".decode("ascii", "replace") == "a\ufffdb"
+assert b"a\xffb".decode("ascii", "surrogateescape") == "a\udcffb"
+assert "a\u00e9b".encode("ascii", "replace") == b"a?b"
+try:
+    b"ab\xff".decode("ascii")
+except UnicodeDecodeError as e:
+    assert "'ascii' codec can't decode byte 0xff in position 2: ordinal not in range(128)" in str(e)
+else:
+    assert False
+try:
+    "ab\u20ac".encode("latin-1")
+except UnicodeEncodeError as e:
+    assert "'latin-1' codec can't encode character" in str(e)
+    assert "in position 2: ordinal not in range(256)" in str(e)
+else:
+    assert False
+
 bytes.fromhex("62 6c7a 34350a ") == b"blz45\n"
 try:
     bytes.fromhex("62 a 21")
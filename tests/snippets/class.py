@@ -1,3 +1,5 @@
+from testutils import assertRaises
+
 __name__ = "class"
 
 
@@ -55,6 +57,11 @@ Bar.fubar(2)
 bar.kungfu(3)
 Bar.kungfu(3)
 
+assert Bar.__dict__['kungfu'].__func__ is Bar.kungfu
+assert bar.fubar.__self__ is Bar
+assert bar.get_x.__self__ is bar
+assert bar.get_x.__func__ is Bar.get_x
+
 
 class Bar2(Bar):
     def __init__(self):
@@ -155,3 +162,76 @@ assert T4.t1.__doc__ == "t1"
 
 cm = classmethod(lambda cls: cls)
 assert cm.__func__(int) is int
+
+
+# Lookups see changes to a class and to its bases, whatever was looked up
+# before.
+class Base:
+    def greet(self):
+        return "base"
+
+
+class Middle(Base):
+    pass
+
+
+class Leaf(Middle):
+    pass
+
+
+leaf = Leaf()
+assert leaf.greet() == "base"
+assert not hasattr(leaf, "extra")
+
+Middle.greet = lambda self: "middle"
+Base.extra = 1
+assert leaf.greet() == "middle"
+assert leaf.extra == 1
+
+Leaf.greet = lambda self: "leaf"
+assert leaf.greet() == "leaf"
+assert Middle().greet() == "middle"
+
+with assertRaises(TypeError):
+    leaf + leaf
+Base.__add__ = lambda self, other: "added"
+assert leaf + leaf == "added"
+
+Base.__getattr__ = lambda self, name: name.upper()
+assert leaf.missing == "MISSING"
+
+
+class Meta(type):
+    pass
+
+
+class WithMeta(metaclass=Meta):
+    pass
+
+
+assert not hasattr(WithMeta, "from_meta")
+Meta.from_meta = property(lambda cls: cls.__name__)
+assert WithMeta.from_meta == "WithMeta"
+
+
+# The cache doesn't keep replaced attributes alive.
+import weakref
+
+
+class Holder:
+    pass
+
+
+class Held:
+    pass
+
+
+held = Held()
+Holder.attr = held
+assert Holder.attr is held
+assert Holder().attr is held
+held_ref = weakref.ref(held)
+Holder.attr = None
+del held
+assert held_ref() is None
+assert Holder.attr is None
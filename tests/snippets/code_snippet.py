@@ -0,0 +1,114 @@
+c1 = compile("1 + 1", "", 'eval')
+
+code_class = type(c1)
+
+def f(x, y, *args, power=1, **kwargs):
+    print("Constant String", 2, None, (2, 4))
+    assert code_class == type(c1)
+    z = x * y
+    return z ** power
+
+c2 = f.__code__
+# print(c2)
+assert type(c2) == code_class
+# print(dir(c2))
+assert c2.co_argcount == 2
+assert c2.co_cellvars == ()
+# assert isinstance(c2.co_code, bytes)
+assert "Constant String" in c2.co_consts, c2.co_consts
+print(c2.co_consts)
+assert 2 in c2.co_consts, c2.co_consts
+assert "code_snippet.py" in c2.co_filename
+assert c2.co_firstlineno == 5, str(c2.co_firstlineno)
+assert isinstance(c2.co_flags, int) # 'OPTIMIZED, NEWLOCALS, NOFREE'
+assert c2.co_flags & 0xf == 0xf, hex(c2.co_flags) # 'OPTIMIZED, NEWLOCALS, VARARGS, VARKEYWORDS'
+assert not c2.co_flags & 0x20
+assert c2.co_freevars == (), str(c2.co_freevars)
+assert c2.co_kwonlyargcount == 1, (c2.co_kwonlyargcount)
+assert isinstance(c2.co_lnotab, bytes)
+assert len(c2.co_lnotab) % 2 == 0
+assert c2.co_name == 'f', c2.co_name
+assert {'print', 'code_class', 'type', 'c1'} <= set(c2.co_names), c2.co_names
+assert c2.co_nlocals == 6, c2.co_nlocals
+# assert c2.co_stacksize == 2, 'co_stacksize',
+assert c2.co_varnames == ('x', 'y', 'power', 'args', 'kwargs', 'z'), c2.co_varnames
+
+
+def gen():
+    yield 1
+
+assert gen.__code__.co_flags & 0x20 # 'GENERATOR'
+assert gen.__code__.co_varnames == ()
+
+def outer():
+    def inner(a):
+        return a.attr
+    return inner
+
+assert not outer.__code__.co_flags & 0x10
+assert outer().__code__.co_flags & 0x10 # 'NESTED'
+assert outer().__code__.co_varnames == ('a',)
+assert outer().__code__.co_names == ('attr',)
+
+# Variables that nested functions refer to are cells, not plain locals.
+def closure(arg):
+    local = 1
+    cell = 2
+    def reader():
+        return cell + arg
+    def writer():
+        nonlocal local
+        local = 3
+    return reader, writer
+
+reader, writer = closure(0)
+assert closure.__code__.co_varnames == ('arg', 'reader', 'writer'), closure.__code__.co_varnames
+assert closure.__code__.co_cellvars == ('arg', 'cell', 'local'), closure.__code__.co_cellvars
+assert closure.__code__.co_freevars == ()
+assert 'cell' not in closure.__code__.co_names
+assert reader.__code__.co_freevars == ('arg', 'cell'), reader.__code__.co_freevars
+assert reader.__code__.co_cellvars == ()
+assert reader.__code__.co_varnames == ()
+assert reader.__code__.co_names == ()
+assert writer.__code__.co_freevars == ('local',), writer.__code__.co_freevars
+
+# A function between the cell and its user passes the variable through.
+def passing():
+    x = 1
+    def middle():
+        def inner():
+            return x
+        return inner
+    return middle
+
+assert passing.__code__.co_cellvars == ('x',)
+assert passing().__code__.co_freevars == ('x',)
+assert passing()().__code__.co_freevars == ('x',)
+
+# Class and module variables don't make cells.
+def with_class():
+    y = 1
+    class C:
+        z = 2
+        def method(self):
+            return y, z
+    return C
+
+assert with_class.__code__.co_cellvars == ('y',)
+assert with_class().method.__code__.co_freevars == ('y',)
+assert 'z' in with_class().method.__code__.co_names
+
+module_code = compile("import os\nx = os.sep", "", "exec")
+assert module_code.co_varnames == ()
+assert set(module_code.co_names) == {'os', 'x', 'sep'}, module_code.co_names
+
+import __future__
+import codeop
+
+future_code = compile("from __future__ import annotations", "", "exec")
+assert future_code.co_flags & __future__.annotations.compiler_flag
+assert not c2.co_flags & __future__.annotations.compiler_flag
+
+compiler = codeop.Compile()
+compiler("from __future__ import annotations", "<input>", "single")
+assert compiler.flags & __future__.annotations.compiler_flag
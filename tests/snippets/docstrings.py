@@ -0,0 +1,96 @@
+"""Module docstring."""
+
+from testutils import assertRaises
+
+assert __doc__ == "Module docstring."
+
+
+def no_doc():
+    x = "not a docstring"
+
+assert no_doc.__doc__ is None
+assert no_doc.__code__.co_consts[0] is None
+
+
+def with_doc():
+    """Function docstring."""
+
+assert with_doc.__doc__ == "Function docstring."
+assert with_doc.__code__.co_consts[0] == "Function docstring."
+assert (lambda: None).__doc__ is None
+
+
+class Meta(type):
+    def __new__(mcs, name, bases, namespace):
+        assert namespace['__doc__'] == "Class docstring."
+        return type.__new__(mcs, name, bases, namespace)
+
+
+class A(metaclass=Meta):
+    """Class docstring."""
+
+    def method(self):
+        "Method docstring."
+
+assert A.__doc__ == "Class docstring."
+assert A.__dict__['__doc__'] == "Class docstring."
+assert A.method.__doc__ == "Method docstring."
+
+
+source = '''
+"""module"""
+def f():
+    """function"""
+class C:
+    """class"""
+debug = __debug__
+assert False, "assert"
+'''
+
+
+def run(optimize):
+    namespace = {}
+    exec(compile(source, "<docs>", "exec", optimize=optimize), namespace)
+    return namespace
+
+namespace = {}
+with assertRaises(AssertionError):
+    exec(compile(source, "<docs>", "exec", optimize=0), namespace)
+assert namespace['__doc__'] == "module"
+assert namespace['f'].__doc__ == "function"
+assert namespace['C'].__doc__ == "class"
+assert namespace['debug'] is True
+
+# Asserts are removed and __debug__ is False from level 1.
+namespace = run(1)
+assert namespace['__doc__'] == "module"
+assert namespace['f'].__doc__ == "function"
+assert namespace['debug'] is False
+
+# Docstrings are stripped from level 2.
+namespace = run(2)
+assert '__doc__' not in namespace
+assert namespace['f'].__doc__ is None
+assert namespace['C'].__doc__ is None
+assert namespace['debug'] is False
+
+with assertRaises(ValueError):
+    compile(source, "<docs>", "exec", optimize=3)
+
+
+def concatenated_doc():
+    "Concatenated " 'docstring.'
+
+assert concatenated_doc.__doc__ == "Concatenated docstring."
+
+
+def fstring_doc():
+    f"Not a docstring."
+
+assert fstring_doc.__doc__ is None
+
+
+class FStringDoc:
+    f"Not a docstring."
+
+assert FStringDoc.__doc__ is None
@@ -0,0 +1,165 @@
+import gc
+import weakref
+
+log = []
+
+
+class A:
+    def __init__(self, name):
+        self.name = name
+
+    def __del__(self):
+        log.append(self.name)
+
+
+# __del__ runs once the last reference goes away.
+a = A("a")
+del a
+assert log == ["a"]
+log.clear()
+
+
+def scope():
+    b = A("b")
+
+
+scope()
+assert log == ["b"]
+log.clear()
+
+l = [A("c"), A("d")]
+l.clear()
+assert sorted(log) == ["c", "d"]
+log.clear()
+
+# An object resurrected by __del__ doesn't get finalized a second time.
+saved = []
+
+
+class Resurrect:
+    def __del__(self):
+        log.append("resurrect")
+        saved.append(self)
+
+
+r = Resurrect()
+del r
+assert log == ["resurrect"]
+assert len(saved) == 1
+saved.clear()
+assert log == ["resurrect"]
+log.clear()
+
+
+# __del__ runs on the object itself, which its weak references still refer to.
+class Identity:
+    def __del__(self):
+        log.append((id(self), self_ref() is self))
+
+
+i = Identity()
+i_id = id(i)
+self_ref = weakref.ref(i)
+del i
+assert log == [(i_id, True)]
+assert self_ref() is None
+log.clear()
+
+# A resurrected object is the same object, and its weak references live on
+# until it is freed for good.
+r = Resurrect()
+r_id = id(r)
+r_ref = weakref.ref(r, lambda wr: log.append("freed"))
+del r
+assert log == ["resurrect"]
+assert id(saved[0]) == r_id
+assert r_ref() is saved[0]
+saved.clear()
+assert r_ref() is None
+assert log == ["resurrect", "freed"]
+log.clear()
+
+
+# Exceptions in __del__ are reported but don't propagate.
+class Bad:
+    def __del__(self):
+        raise ValueError("oops")
+
+
+b = Bad()
+del b
+log.append("after")
+assert log == ["after"]
+log.clear()
+
+# Finalizers of objects in a reference cycle are called by the collector,
+# while the objects are still intact.
+c = A("cycle")
+c.self = c
+del c
+gc.collect()
+assert log == ["cycle"]
+log.clear()
+
+
+# Weak reference callbacks get the weak reference once the referent is freed.
+class B:
+    pass
+
+
+def callback(wr):
+    log.append(wr)
+
+
+o = B()
+w = weakref.ref(o, callback)
+assert w.__callback__ is callback
+assert weakref.getweakrefcount(o) == 1
+assert weakref.getweakrefs(o) == [w]
+del o
+assert log == [w]
+assert w() is None
+log.clear()
+
+# Plain weak references to the same object are shared.
+o = B()
+assert weakref.ref(o) is weakref.ref(o)
+assert weakref.ref(o, callback) is not weakref.ref(o)
+
+# No callback when the weak reference dies first.
+w = weakref.ref(o, callback)
+del w
+del o
+assert log == []
+
+# Callbacks of the newest weak references run first.
+o = B()
+w1 = weakref.ref(o, lambda wr: log.append(1))
+w2 = weakref.ref(o, lambda wr: log.append(2))
+del o
+assert log == [2, 1]
+log.clear()
+
+# Proxies take callbacks too.
+o = B()
+p = weakref.proxy(o, callback)
+del o
+assert len(log) == 1
+log.clear()
+
+# weakref.finalize and WeakValueDictionary rely on the callbacks.
+o = B()
+f = weakref.finalize(o, log.append, "finalized")
+assert f.alive
+del o
+assert log == ["finalized"]
+assert not f.alive
+log.clear()
+
+d = weakref.WeakValueDictionary()
+o = B()
+d["key"] = o
+assert d["key"] is o
+del o
+assert "key" not in d
+assert len(d) == 0
@@ -1,3 +1,11 @@
+from testutils import assertRaises
+
+
+def main_function():
+    pass
+
+assert __name__ == "__main__"
+assert main_function.__module__ == "__main__"
 
 __name__ = "function"
 
@@ -72,3 +80,42 @@ def f6():
 
 
 f6()
+
+
+# The attributes of a function are not kept in its __dict__.
+def f7(a: int) -> str:
+    "test7"
+
+assert f7.__dict__ == {}
+f7.custom = 1
+assert f7.__dict__ == {'custom': 1}
+assert f7.__annotations__ == {'a': int, 'return': str}
+
+f7.__name__ = 'renamed'
+f7.__qualname__ = 'Renamed.renamed'
+assert (f7.__name__, f7.__qualname__) == ('renamed', 'Renamed.renamed')
+with assertRaises(TypeError):
+    f7.__name__ = 5
+with assertRaises(TypeError):
+    f7.__qualname__ = 5
+assert f7.__qualname__ == 'Renamed.renamed'
+
+f7.__annotations__ = None
+assert f7.__annotations__ == {}
+f7.__annotations__['x'] = 1
+assert f7.__annotations__ == {'x': 1}
+with assertRaises(TypeError):
+    f7.__annotations__ = 5
+
+f7.__module__ = 'elsewhere'
+assert f7.__module__ == 'elsewhere'
+f7.__doc__ = None
+assert f7.__doc__ is None
+assert f7.__dict__ == {'custom': 1}
+
+assert (lambda: None).__name__ == '<lambda>'
+assert (lambda: None).__doc__ is None
+
+namespace = {}
+exec("def g(): pass", namespace)
+assert namespace['g'].__module__ is None
@@ -37,6 +37,12 @@ r = list(g3())
 # print(r)
 assert r == [23, 1, 2, 3, 44]
 
+def g5():
+    x = yield from make_numbers()
+    yield x
+
+assert list(g5()) == [1, 2, 3, None]
+
 def g4():
     yield
     yield 2,
@@ -19,3 +19,50 @@ class TestClass():
         assert sys._getframe().f_locals['self'] == self
 
 TestClass()
+
+import traceback
+
+def callee():
+    frame = sys._getframe()
+    assert frame.f_code is callee.__code__
+    assert frame.f_globals is globals()
+    assert frame.f_lineno == 29
+    assert frame.f_back.f_code is caller.__code__
+    assert frame.f_back.f_lineno == 37
+    assert frame.f_back.f_back.f_code.co_name == '<module>'
+    assert isinstance(frame.f_lasti, int)
+    return [(f.f_code.co_name, lineno) for f, lineno in traceback.walk_stack(frame)]
+
+def caller():
+    return callee()
+
+stack = caller()
+assert stack[:2] == [("callee", 34), ("caller", 37)], stack
+assert stack[2][0] == '<module>'
+
+# Code made for a def or class statement, like applying its decorators, is
+# on the line of the statement rather than the last line of the body. CPython
+# reports the line of the decorator.
+def line_of_caller(f):
+    f.line = sys._getframe(1).f_lineno
+    return f
+
+@line_of_caller
+def decorated():
+    pass
+    pass
+
+assert decorated.line in (50, 51), decorated.line
+
+@line_of_caller
+class Decorated:
+    pass
+    pass
+
+assert Decorated.line in (57, 58), Decorated.line
+
+# Module and eval code start on line 1.
+assert eval('sys._getframe().f_lineno') == 1
+namespace = {'sys': sys}
+exec('\n\nline = sys._getframe().f_lineno', namespace)
+assert namespace['line'] == 3
@@ -0,0 +1,122 @@
+import builtins
+
+from testutils import assertRaises
+
+# Each lookup runs the same instructions several times, so that a cached
+# result would be used if it were not invalidated.
+
+def get_x():
+    return x
+
+
+with assertRaises(NameError):
+    get_x()
+
+x = 1
+assert [get_x() for _ in range(3)] == [1, 1, 1]
+x = 2
+assert [get_x() for _ in range(3)] == [2, 2, 2]
+del x
+with assertRaises(NameError):
+    get_x()
+
+
+def get_len():
+    return len
+
+
+# A global shadows a builtin, and deleting it uncovers the builtin again.
+assert [get_len() for _ in range(3)] == [len] * 3
+len = 'shadowed'
+assert [get_len() for _ in range(3)] == ['shadowed'] * 3
+del len
+assert [get_len() for _ in range(3)] == [builtins.len] * 3
+
+# Changing builtins is seen.
+original_len = builtins.len
+builtins.len = 'patched'
+try:
+    assert [get_len() for _ in range(3)] == ['patched'] * 3
+finally:
+    builtins.len = original_len
+assert get_len() is original_len
+
+
+def new_builtin():
+    return my_new_builtin
+
+
+with assertRaises(NameError):
+    new_builtin()
+builtins.my_new_builtin = 42
+try:
+    assert new_builtin() == 42
+finally:
+    del builtins.my_new_builtin
+with assertRaises(NameError):
+    new_builtin()
+
+
+# Declared globals.
+def get_y():
+    global y
+    return y
+
+
+y = 'a'
+assert get_y() == 'a'
+y = 'b'
+assert get_y() == 'b'
+
+# Changing globals through globals() is seen.
+globals()['y'] = 'c'
+assert get_y() == 'c'
+
+# A local of an enclosing class or function still comes first.
+def outer():
+    def inner():
+        return y
+    return inner
+
+
+assert outer()() == 'c'
+
+class C:
+    y = 'class'
+    found = y
+
+assert C.found == 'class'
+
+# The same code run with different globals.
+code = compile('result = z', '<test>', 'exec')
+for value in range(3):
+    namespace = {'z': value}
+    exec(code, namespace)
+    assert namespace['result'] == value
+
+namespace = {}
+with assertRaises(NameError):
+    exec(code, namespace)
+namespace['z'] = 'late'
+exec(code, namespace)
+assert namespace['result'] == 'late'
+
+# A copy of the globals gives the same answer as the original.
+first = {'z': 'first'}
+exec(code, first)
+second = first.copy()
+second['z'] = 'second'
+exec(code, second)
+assert second['result'] == 'second'
+exec(code, first)
+assert first['result'] == 'first'
+
+# Subclasses of dict can supply missing names.
+class Defaults(dict):
+    def __missing__(self, key):
+        return 'missing ' + key
+
+
+namespace = Defaults()
+exec(code, namespace)
+assert namespace['result'] == 'missing z'
@@ -12,6 +12,13 @@ assert a == 2
 b()
 assert a == 4
 
+# Global names fall back to the builtins:
+def c():
+    global len
+    return len([1, 2])
+
+assert c() == 2
+
 
 def x():
     def y():
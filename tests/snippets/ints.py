@@ -146,3 +146,26 @@ class F(float):
         return 3
 
 assert int(F(1.2)) == 3
+
+# Results crossing the range of machine words
+i64_max = 2 ** 63 - 1
+i64_min = -2 ** 63
+assert i64_max + 1 == 9223372036854775808
+assert i64_min - 1 == -9223372036854775809
+assert i64_max * 2 == 18446744073709551614
+assert i64_min * -1 == 9223372036854775808
+assert -i64_min == 9223372036854775808
+assert abs(i64_min) == 9223372036854775808
+assert i64_min // -1 == 9223372036854775808
+assert (i64_max + 1) - 1 == i64_max
+assert 1 << 63 == 9223372036854775808
+assert -1 << 63 == i64_min
+assert (i64_max + 1) >> 1 == 2 ** 62
+assert -9 >> 100 == -1
+assert 3 ** 40 == 12157665459056928801
+assert i64_min.bit_length() == 64
+assert i64_max < i64_max + 1
+assert i64_min > i64_min - 1
+assert hash(i64_max + 1 - 1) == hash(i64_max)
+assert -7 // 2 == -4
+assert 7 // -2 == -4
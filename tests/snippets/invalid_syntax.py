@@ -16,3 +16,46 @@ else:
 
 with assertRaises(SyntaxError):
     compile('0xX', 'test.py', 'exec')
+
+def check_error(src, exc_type, msg, lineno):
+    try:
+        compile(src, 'test.py', 'exec')
+    except SyntaxError as ex:
+        assert type(ex) is exc_type, type(ex)
+        assert msg in str(ex), str(ex)
+        assert ex.lineno == lineno, ex.lineno
+    else:
+        raise AssertionError("Must throw {}".format(exc_type.__name__))
+
+check_error("if x:\n    a\n  b\n", IndentationError, "unindent does not match any outer indentation level", 3)
+check_error("x = 1\n    y = 2\n", IndentationError, "unexpected indent", 2)
+check_error("def f():\nreturn 1\n", IndentationError, "expected an indented block", 2)
+check_error("if x:\n        a\n\tb\n", TabError, "inconsistent use of tabs and spaces in indentation", 3)
+check_error("if x:\n\ta\n        b\n", TabError, "inconsistent use of tabs and spaces in indentation", 3)
+
+assert issubclass(TabError, IndentationError)
+assert issubclass(IndentationError, SyntaxError)
+
+# Mixing is fine as long as it does not depend on the tab size
+compile("if x:\n  \ta\n  \tb\n", 'test.py', 'exec')
+
+try:
+    compile("x = 1\nif x:\n    y = )\n", '/some/dir/test.py', 'exec')
+except SyntaxError as ex:
+    assert ex.filename == '/some/dir/test.py'
+    assert ex.lineno == 3
+    assert ex.offset == 9
+    assert ex.text.rstrip('\n') == '    y = )'
+    assert ex.args[0] == ex.msg
+    assert ex.args[1][:3] == ('/some/dir/test.py', 3, 9)
+    assert str(ex) == ex.msg + ' (test.py, line 3)'
+else:
+    raise AssertionError("Must throw syntax error")
+
+ex = SyntaxError('msg', ('file.py', 2, 4, 'a b\n'))
+assert (ex.msg, ex.filename, ex.lineno, ex.offset, ex.text) == ('msg', 'file.py', 2, 4, 'a b\n')
+assert str(ex) == 'msg (file.py, line 2)'
+
+ex = SyntaxError('msg')
+assert (ex.msg, ex.filename, ex.lineno, ex.offset, ex.text) == ('msg', None, None, None, None)
+assert str(ex) == 'msg'
@@ -18,3 +18,9 @@ assert 'b' in A.__dict__
 assert 'c' not in A.__dict__
 
 assert '__dict__' in A.__dict__
+
+assert sorted(A.__dict__.keys()) == sorted(A.__dict__)
+assert 'a' in A.__dict__.keys() and 'keys' not in A.__dict__.keys()
+assert dict(A.__dict__.items())['a'] is A.a
+assert A.a in list(A.__dict__.values())
+assert A.__dict__.copy()['b'] is A.b
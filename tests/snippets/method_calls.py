@@ -0,0 +1,124 @@
+from testutils import assertRaises
+
+
+class A:
+    def m(self, x=1, *args, **kwargs):
+        return (self, x, args, kwargs)
+
+    @staticmethod
+    def s(x):
+        return x * 2
+
+    @classmethod
+    def c(cls, x):
+        return (cls, x)
+
+    @property
+    def p(self):
+        return lambda x: ('property', x)
+
+
+a = A()
+assert a.m() == (a, 1, (), {})
+assert a.m(2) == (a, 2, (), {})
+assert a.m(2, 3, k=4) == (a, 2, (3,), {'k': 4})
+assert a.m(*[5, 6], **{'k': 7}) == (a, 5, (6,), {'k': 7})
+assert a.m(x=8) == (a, 8, (), {})
+assert a.s(4) == 8
+assert a.c(5) == (A, 5)
+assert A.c(5) == (A, 5)
+assert A.s(3) == 6
+assert a.p(9) == ('property', 9)
+
+# Calling through the class passes self explicitly.
+assert A.m(a, 3) == (a, 3, (), {})
+
+# An instance attribute shadows the method.
+a.m = lambda *args: ('instance', args)
+assert a.m(1, 2) == ('instance', (1, 2))
+del a.m
+assert a.m(1) == (a, 1, (), {})
+
+# A method added to the class later is found.
+A.later = lambda self, y: ('later', y)
+assert a.later(1) == ('later', 1)
+
+# __getattr__ is only consulted for missing attributes.
+class G:
+    def present(self):
+        return 'present'
+
+    def __getattr__(self, name):
+        return lambda: name
+
+
+g = G()
+assert g.present() == 'present'
+assert g.missing() == 'missing'
+
+# A custom __getattribute__ sees every lookup.
+class H:
+    def m(self):
+        return 'method'
+
+    def __getattribute__(self, name):
+        if name == 'm':
+            return lambda: 'intercepted'
+        return object.__getattribute__(self, name)
+
+
+assert H().m() == 'intercepted'
+
+# A data descriptor on the class wins over the instance.
+class Descriptor:
+    def __get__(self, obj, cls):
+        return lambda: 'descriptor'
+
+    def __set__(self, obj, value):
+        pass
+
+
+class D:
+    d = Descriptor()
+
+
+assert D().d() == 'descriptor'
+
+# Methods of builtin types and modules.
+xs = []
+xs.append(1)
+xs.extend([2, 3])
+assert xs == [1, 2, 3]
+assert 'a,b'.split(',') == ['a', 'b']
+assert {'k': 1}.get('k') == 1
+
+import math
+assert math.sqrt(4) == 2
+
+# Calling a type's methods on itself goes through the metaclass.
+assert int.from_bytes(b'\x01', 'big') == 1
+
+# Errors look the same as without the shortcut.
+with assertRaises(AttributeError):
+    a.nonexistent()
+
+with assertRaises(TypeError):
+    a.m(1, x=2)
+
+# The object is evaluated once, before the arguments.
+order = []
+
+
+def obj():
+    order.append('obj')
+    return xs
+
+
+def arg():
+    order.append('arg')
+    return 4
+
+
+obj().append(arg())
+assert order == ['obj', 'arg']
+assert xs == [1, 2, 3, 4]
@@ -13,3 +13,12 @@ assert MyObject().__lt__(MyObject()) == NotImplemented
 assert MyObject().__le__(MyObject()) == NotImplemented
 assert MyObject().__gt__(MyObject()) == NotImplemented
 assert MyObject().__ge__(MyObject()) == NotImplemented
+
+assert MyObject().__dict__ == {}
+assert not hasattr(object(), '__dict__')
+try:
+    (1).__dict__
+except AttributeError as e:
+    assert "has no attribute '__dict__'" in str(e)
+else:
+    assert False, "AttributeError was not raised"
@@ -0,0 +1,105 @@
+import functools
+
+from testutils import assert_raises
+
+
+def outer():
+    def inner():
+        pass
+
+    class Local:
+        def method(self):
+            pass
+
+    return inner, Local, lambda: None, [lambda: None for _ in range(1)][0]
+
+
+inner, Local, lam, comp_lam = outer()
+assert outer.__qualname__ == "outer"
+assert inner.__name__ == "inner"
+assert inner.__qualname__ == "outer.<locals>.inner"
+assert Local.__name__ == "Local"
+assert Local.__qualname__ == "outer.<locals>.Local"
+assert Local.method.__qualname__ == "outer.<locals>.Local.method"
+assert lam.__qualname__ == "outer.<locals>.<lambda>"
+assert comp_lam.__qualname__ == "outer.<locals>.<listcomp>.<lambda>"
+
+
+class A:
+    class B:
+        def f(self):
+            pass
+
+    g = lambda self: None
+
+
+assert A.__qualname__ == "A"
+assert A.B.__qualname__ == "A.B"
+assert A.B.f.__qualname__ == "A.B.f"
+assert A.g.__qualname__ == "A.<lambda>"
+assert (lambda: 0).__qualname__ == "<lambda>"
+
+
+def make_global():
+    global declared_global
+
+    def declared_global():
+        pass
+
+
+make_global()
+assert declared_global.__qualname__ == "declared_global"
+
+A.__qualname__ = "Renamed"
+assert A.__qualname__ == "Renamed"
+assert A.__name__ == "A"
+
+
+def set_qualname():
+    A.__qualname__ = 5
+
+
+assert_raises(TypeError, set_qualname)
+
+assert int.__qualname__ == "int"
+assert type(1).__qualname__ == "int"
+
+
+def defaults(a, b=1, *, c=2):
+    return a, b, c
+
+
+assert defaults.__defaults__ == (1,)
+assert defaults.__kwdefaults__ == {"c": 2}
+assert defaults.__globals__ is globals()
+defaults.__defaults__ = (10,)
+defaults.__kwdefaults__ = {"c": 20}
+assert defaults(0) == (0, 10, 20)
+defaults.__defaults__ = None
+assert defaults.__defaults__ is None
+assert_raises(TypeError, defaults, 0)
+
+defaults.__module__ = "elsewhere"
+assert defaults.__module__ == "elsewhere"
+
+
+def decorator(func):
+    @functools.wraps(func)
+    def wrapper(*args, **kwargs):
+        return func(*args, **kwargs)
+
+    return wrapper
+
+
+@decorator
+def wrapped(x):
+    """Docs."""
+    return x + 1
+
+
+assert wrapped(1) == 2
+assert wrapped.__name__ == "wrapped"
+assert wrapped.__qualname__ == "wrapped"
+assert wrapped.__doc__ == "Docs."
+assert wrapped.__wrapped__(1) == 2
+assert wrapped.__wrapped__.__qualname__ == "wrapped"
@@ -0,0 +1,57 @@
+import code
+import codeop
+
+from testutils import assert_raises
+
+# Complete input compiles
+assert codeop.compile_command("x = 1") is not None
+assert codeop.compile_command("1 + 2", symbol="eval") is not None
+assert codeop.compile_command("") is not None
+assert codeop.compile_command("# just a comment") is not None
+assert codeop.compile_command("if x:\n    pass\n") is not None
+
+# Incomplete input needs more lines
+assert codeop.compile_command("if x:") is None
+assert codeop.compile_command("if x:\n    pass") is None
+assert codeop.compile_command("def f():") is None
+assert codeop.compile_command("x = (1,") is None
+assert codeop.compile_command("x = [1,\n 2,") is None
+assert codeop.compile_command("s = '''abc") is None
+assert codeop.compile_command("x = 1 + \\") is None
+
+# Invalid input raises right away
+assert_raises(SyntaxError, lambda: codeop.compile_command("x = )"))
+assert_raises(SyntaxError, lambda: codeop.compile_command("if x:\n    pass\n  pass\n"))
+
+compiler = codeop.CommandCompiler()
+assert compiler("if x:") is None
+assert compiler("x = 2") is not None
+
+
+class Console(code.InteractiveConsole):
+    def __init__(self):
+        super().__init__()
+        self.output = []
+
+    def write(self, data):
+        self.output.append(data)
+
+
+console = Console()
+assert not console.push("x = 5")
+assert console.locals["x"] == 5
+
+assert console.push("def f(a):")
+assert console.push("    return a * 2")
+assert not console.push("")
+assert console.locals["f"](4) == 8
+
+assert console.push("y = (1,")
+assert not console.push("2)")
+assert console.locals["y"] == (1, 2)
+assert not console.output
+
+# Syntax errors are reported through write() and reset the buffer
+assert not console.push("x = )")
+assert "SyntaxError" in "".join(console.output)
+assert not console.buffer
@@ -0,0 +1,105 @@
+import dis
+import io
+
+
+def f(a, b=2):
+    if a:
+        return a + b
+    return 12345
+
+
+instructions = list(dis.get_instructions(f))
+assert instructions
+assert all(isinstance(i, dis.Instruction) for i in instructions)
+assert instructions[0].offset == 0
+assert [i.offset for i in instructions] == sorted(i.offset for i in instructions)
+assert all(i.opname == dis.opname[i.opcode] for i in instructions)
+assert any(i.argval == 12345 and i.argrepr == '12345' for i in instructions)
+assert any(i.argval == 'a' for i in instructions)
+
+# Line numbers.
+first_line = f.__code__.co_firstlineno
+lines = [i.starts_line for i in instructions if i.starts_line is not None]
+assert first_line + 1 in lines
+assert first_line + 3 in lines
+assert dict(dis.findlinestarts(f.__code__))
+
+shifted = list(dis.get_instructions(f, first_line=100))
+assert 101 in [i.starts_line for i in shifted]
+
+# Jumps resolve to the offset of an instruction marked as a jump target.
+targets = {i.offset for i in instructions if i.is_jump_target}
+jumps = [i for i in instructions if i.argrepr.startswith('to ')]
+assert jumps
+assert all(i.argval in targets for i in jumps)
+
+assert all(dis.opname[op] == name for name, op in dis.opmap.items())
+assert '<' in dis.cmp_op
+
+bytecode = dis.Bytecode(f)
+assert list(bytecode) == instructions
+assert bytecode.codeobj is f.__code__
+assert bytecode.first_line == first_line
+assert bytecode.info() == dis.code_info(f)
+assert repr(bytecode).startswith('Bytecode(')
+assert '12345' in bytecode.dis()
+
+info = dis.code_info(f)
+assert 'Name:              f' in info
+assert 'Argument count:    2' in info
+assert 'OPTIMIZED, NEWLOCALS' in info
+assert '12345' in info
+
+output = io.StringIO()
+dis.show_code(f, file=output)
+assert output.getvalue() == info + '\n'
+
+
+def make_closure():
+    value = 1
+    def closure():
+        return value
+    return closure
+
+assert 'Cell variables:\n   0: value' in dis.code_info(make_closure)
+assert 'Free variables:\n   0: value' in dis.code_info(make_closure())
+
+
+def disassembly(x, **kwargs):
+    output = io.StringIO()
+    dis.dis(x, file=output, **kwargs)
+    return output.getvalue()
+
+
+assert '(12345)' in disassembly(f)
+assert '(12345)' in disassembly(f.__code__)
+assert '(54321)' in disassembly("x = 54321")
+
+# Nested code objects are disassembled too, unless the depth forbids it.
+nested = "def g():\n    return 4242\n"
+assert 'Disassembly of <code object g' in disassembly(nested)
+assert '(4242)' in disassembly(nested)
+assert '(4242)' not in disassembly(nested, depth=0)
+
+
+class A:
+    def m(self):
+        return 777
+
+    @staticmethod
+    def s():
+        return 888
+
+
+text = disassembly(A)
+assert 'Disassembly of m:' in text
+assert 'Disassembly of s:' in text
+assert '(777)' in text
+assert '(777)' in disassembly(A().m)
+
+try:
+    dis.dis(1)
+except TypeError:
+    pass
+else:
+    assert False, "dis.dis(1) should raise TypeError"
@@ -0,0 +1,109 @@
+import gc
+import weakref
+
+from testutils import assertRaises
+
+
+class Node:
+    pass
+
+
+# An object referring to itself is freed by a collection.
+a = Node()
+a.self = a
+r = weakref.ref(a)
+del a
+assert r() is not None
+assert gc.collect() >= 1
+assert r() is None
+
+# So is a cycle through containers.
+parent = Node()
+parent.children = [Node(), Node()]
+for child in parent.children:
+    child.parent = parent
+r = weakref.ref(parent)
+rs = [weakref.ref(child) for child in parent.children]
+del parent, child
+gc.collect()
+assert r() is None
+assert all(r() is None for r in rs)
+
+
+# And a closure referring to itself.
+def outer():
+    def inner():
+        return inner
+    return inner
+
+
+f = outer()
+assert f() is f
+r = weakref.ref(f)
+del f
+gc.collect()
+assert r() is None
+
+# Objects that are still referenced are left alone.
+l = []
+l.append(l)
+a = Node()
+a.l = l
+gc.collect()
+assert l[0] is l
+assert a.l is l
+
+# DEBUG_SAVEALL keeps the unreachable objects in gc.garbage.
+gc.set_debug(gc.DEBUG_SAVEALL)
+assert gc.get_debug() == gc.DEBUG_SAVEALL
+c = []
+c.append(c)
+cid = id(c)
+del c
+gc.collect()
+gc.set_debug(0)
+assert any(id(o) == cid for o in gc.garbage)
+gc.garbage.clear()
+
+# Referents and referrers.
+x = Node()
+y = [x, "a"]
+referents = gc.get_referents(y)
+assert len(referents) == 2
+assert any(o is x for o in referents)
+assert "a" in referents
+assert any(o is y for o in gc.get_referrers(x))
+assert any(o is y for o in gc.get_objects())
+assert gc.is_tracked(y)
+assert not gc.is_tracked(1)
+assert not gc.is_tracked("a")
+
+# Enabling and thresholds.
+assert gc.isenabled()
+gc.disable()
+assert not gc.isenabled()
+gc.enable()
+assert gc.isenabled()
+
+old = gc.get_threshold()
+assert len(old) == 3
+gc.set_threshold(500, 5, 5)
+assert gc.get_threshold() == (500, 5, 5)
+gc.set_threshold(100)
+assert gc.get_threshold() == (100, 5, 5)
+gc.set_threshold(*old)
+assert gc.get_threshold() == old
+assert len(gc.get_count()) == 3
+
+with assertRaises(ValueError):
+    gc.collect(3)
+
+# Cycles are also collected automatically.
+r = None
+for i in range(2000):
+    n = Node()
+    n.self = n
+    if r is None:
+        r = weakref.ref(n)
+del n
+assert r() is None
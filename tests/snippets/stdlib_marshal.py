@@ -1,7 +1,87 @@
+import io
 import marshal
+
+from testutils import assertRaises
+
 orig = compile("1 + 1", "", 'eval')
 
 dumped = marshal.dumps(orig)
 loaded = marshal.loads(dumped)
 
 assert eval(loaded) == eval(orig)
+
+
+def f(a, b=2):
+    return a + b
+
+code = marshal.loads(marshal.dumps(f.__code__))
+assert code.co_name == 'f'
+assert code.co_varnames[:2] == ('a', 'b')
+assert code.co_stacksize == f.__code__.co_stacksize >= 2
+
+values = [
+    None, True, False, ..., StopIteration,
+    0, 1, -1, 2 ** 31 - 1, -2 ** 31, 2 ** 31, 2 ** 100, -2 ** 100,
+    0.0, 1.5, -2.25, 1e100, float('inf'), float('-inf'),
+    1j, 1.5 - 2j,
+    '', 'abc', 'x' * 300, 'héllo', '€',
+    b'', b'abc',
+    (), (1, 2), tuple(range(300)), [], [1, [2, 3]],
+    {}, {'a': 1, 2: [3]}, set(), {1, 2, 3}, frozenset(), frozenset({'a'}),
+]
+
+for version in range(5):
+    for value in values:
+        data = marshal.dumps(value, version)
+        assert type(data) is bytes
+        loaded = marshal.loads(data)
+        assert type(loaded) is type(value), (value, version)
+        assert loaded == value, (value, version)
+    nan = marshal.loads(marshal.dumps(float('nan'), version))
+    assert nan != nan
+
+assert marshal.loads(marshal.dumps(bytearray(b'ab'))) == b'ab'
+assert marshal.loads(bytearray(marshal.dumps(1))) == 1
+assert marshal.version == 4
+
+# The same bytes as CPython's marshal.
+assert marshal.dumps(1, 2) == b'i\x01\x00\x00\x00'
+assert marshal.dumps(2 ** 40, 2) == b'l\x03\x00\x00\x00\x00\x00\x00\x00\x00\x04'
+assert marshal.dumps(1.5, 1) == b'f\x031.5'
+assert marshal.dumps(1.5, 2) == b'g\x00\x00\x00\x00\x00\x00\xf8?'
+assert marshal.dumps('abc', 2) == b'u\x03\x00\x00\x00abc'
+assert marshal.dumps((None, True), 2) == b'(\x02\x00\x00\x00NT'
+assert marshal.dumps([b'x'], 2) == b'[\x01\x00\x00\x00s\x01\x00\x00\x00x'
+assert marshal.dumps({1: 2}, 2) == b'{i\x01\x00\x00\x00i\x02\x00\x00\x000'
+
+assert marshal.loads(b'\xe9\x01\x00\x00\x00') == 1
+assert marshal.loads(b'\xda\x03abc') == 'abc'
+assert marshal.loads(b'[\x02\x00\x00\x00\xda\x01ar\x00\x00\x00\x00') == ['a', 'a']
+assert marshal.loads(b'I\x00\x00\x00\x00\x01\x00\x00\x00') == 2 ** 32
+assert marshal.loads(b'i\x01\x00\x00\x00trailing') == 1
+
+with assertRaises(EOFError):
+    marshal.loads(b'')
+with assertRaises(EOFError):
+    marshal.loads(b'i\x01')
+with assertRaises(EOFError):
+    marshal.loads(b'(\x02\x00\x00\x00N')
+with assertRaises(ValueError):
+    marshal.loads(b'\x01')
+with assertRaises(ValueError):
+    marshal.loads(b'r\x00\x00\x00\x00')
+with assertRaises(ValueError):
+    marshal.dumps(object())
+with assertRaises(ValueError):
+    marshal.dumps([1, object()])
+with assertRaises(TypeError):
+    marshal.loads('i')
+
+buffer = io.BytesIO()
+marshal.dump([1, 'two'], buffer)
+marshal.dump(3.0, buffer, 2)
+buffer.seek(0)
+assert marshal.load(buffer) == [1, 'two']
+assert marshal.load(buffer) == 3.0
+with assertRaises(EOFError):
+    marshal.load(buffer)
@@ -247,7 +247,26 @@ with TestWithTempDir() as tmpdir:
 		assert os.getcwd() == os.path.realpath(tmpdir)
 		os.path.exists(FILE_NAME)
 
+	# File names that aren't UTF-8 round-trip through lone surrogates.
+	if os.name != "nt":
+		odd_name = os.fsdecode(b"odd\xff\xfename")
+		assert odd_name == "odd\udcff\udcfename"
+		assert os.fsencode(odd_name) == b"odd\xff\xfename"
+		odd_path = os.path.join(tmpdir, odd_name)
+		fd = os.open(odd_path, os.O_WRONLY | os.O_CREAT)
+		os.close(fd)
+		assert odd_name in os.listdir(tmpdir)
+		assert odd_name in [entry.name for entry in os.scandir(tmpdir)]
+		assert os.path.isfile(odd_path)
+		os.remove(odd_path)
+		assert odd_name not in os.listdir(tmpdir)
+
 # supports
 assert isinstance(os.supports_fd, set)
 assert isinstance(os.supports_dir_fd, set)
 assert isinstance(os.supports_follow_symlinks, set)
+
+if os.name != "nt":
+	os.putenv("RUSTPYTHON_TEST_ENV", "\udcff\udcfe")
+	os.unsetenv("RUSTPYTHON_TEST_ENV")
+assert_raises(UnicodeEncodeError, lambda: os.fsencode("\ud800"))
@@ -0,0 +1,125 @@
+import _thread
+import time
+
+from testutils import assertRaises
+
+# Locks
+lock = _thread.allocate_lock()
+assert isinstance(lock, _thread.LockType)
+assert not lock.locked()
+assert lock.acquire()
+assert lock.locked()
+assert not lock.acquire(False)
+assert not lock.acquire(blocking=False)
+start = time.time()
+assert not lock.acquire(timeout=0.05)
+assert time.time() - start >= 0.04
+lock.release()
+assert not lock.locked()
+with assertRaises(RuntimeError):
+    lock.release()
+
+with lock:
+    assert lock.locked()
+assert not lock.locked()
+
+with assertRaises(ValueError):
+    lock.acquire(False, 1)
+with assertRaises(ValueError):
+    lock.acquire(timeout=-2)
+with assertRaises(OverflowError):
+    lock.acquire(timeout=_thread.TIMEOUT_MAX * 2)
+
+# Reentrant locks
+rlock = _thread.RLock()
+assert rlock.acquire()
+assert rlock.acquire()
+assert rlock._is_owned()
+rlock.release()
+rlock.release()
+assert not rlock._is_owned()
+with assertRaises(RuntimeError):
+    rlock.release()
+with rlock:
+    with rlock:
+        assert rlock._is_owned()
+assert not rlock._is_owned()
+
+# Threads
+main_ident = _thread.get_ident()
+assert main_ident == _thread.get_ident()
+
+count_lock = _thread.allocate_lock()
+done = _thread.allocate_lock()
+done.acquire()
+state = {'count': 0, 'finished': 0, 'idents': set()}
+NUM_THREADS = 4
+
+
+def worker(increments, step=1):
+    for _ in range(increments):
+        with count_lock:
+            state['count'] += step
+    with count_lock:
+        state['idents'].add(_thread.get_ident())
+        state['finished'] += 1
+        if state['finished'] == NUM_THREADS:
+            done.release()
+
+
+for _ in range(NUM_THREADS):
+    _thread.start_new_thread(worker, (100,), {'step': 2})
+assert done.acquire(timeout=10)
+assert state['count'] == NUM_THREADS * 200
+assert len(state['idents']) == NUM_THREADS
+assert main_ident not in state['idents']
+
+# A lock can be released by another thread than the one holding it.
+handoff = _thread.allocate_lock()
+handoff.acquire()
+_thread.start_new_thread(handoff.release, ())
+assert handoff.acquire(timeout=10)
+
+# A thread which never blocks still lets the others run.
+flag = [0]
+
+
+def spin():
+    while flag[0] == 0:
+        pass
+    flag[0] = 2
+
+
+_thread.start_new_thread(spin, ())
+time.sleep(0.01)
+flag[0] = 1
+deadline = time.time() + 10
+while flag[0] != 2 and time.time() < deadline:
+    pass
+assert flag[0] == 2
+
+# Threads blocked on an RLock another thread owns wait for it.
+rlock.acquire()
+got_it = _thread.allocate_lock()
+got_it.acquire()
+
+
+def take_rlock():
+    with rlock:
+        got_it.release()
+
+
+_thread.start_new_thread(take_rlock, ())
+time.sleep(0.05)
+assert got_it.locked()
+rlock.release()
+assert got_it.acquire(timeout=10)
+
+with assertRaises(TypeError):
+    _thread.start_new_thread(worker, [1])
+with assertRaises(TypeError):
+    _thread.start_new_thread(1, ())
+
+assert _thread.stack_size() == 0
+with assertRaises(ValueError):
+    _thread.stack_size(1)
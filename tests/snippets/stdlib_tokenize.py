@@ -0,0 +1,89 @@
+import io
+import token
+import tokenize
+
+from testutils import assertRaises
+
+
+def tokens(source):
+    return [(token.tok_name[t.type], t.string, t.start, t.end)
+            for t in tokenize.generate_tokens(io.StringIO(source).readline)]
+
+
+assert tokens("x = 1 + 2\n") == [
+    ('NAME', 'x', (1, 0), (1, 1)),
+    ('OP', '=', (1, 2), (1, 3)),
+    ('NUMBER', '1', (1, 4), (1, 5)),
+    ('OP', '+', (1, 6), (1, 7)),
+    ('NUMBER', '2', (1, 8), (1, 9)),
+    ('NEWLINE', '\n', (1, 9), (1, 10)),
+    ('ENDMARKER', '', (2, 0), (2, 0)),
+]
+
+source = """# comment
+if a:
+    b = ('x',
+         rb'y')  # trailing
+"""
+assert tokens(source) == [
+    ('COMMENT', '# comment', (1, 0), (1, 9)),
+    ('NL', '\n', (1, 9), (1, 10)),
+    ('NAME', 'if', (2, 0), (2, 2)),
+    ('NAME', 'a', (2, 3), (2, 4)),
+    ('OP', ':', (2, 4), (2, 5)),
+    ('NEWLINE', '\n', (2, 5), (2, 6)),
+    ('INDENT', '    ', (3, 0), (3, 4)),
+    ('NAME', 'b', (3, 4), (3, 5)),
+    ('OP', '=', (3, 6), (3, 7)),
+    ('OP', '(', (3, 8), (3, 9)),
+    ('STRING', "'x'", (3, 9), (3, 12)),
+    ('OP', ',', (3, 12), (3, 13)),
+    ('NL', '\n', (3, 13), (3, 14)),
+    ('STRING', "rb'y'", (4, 9), (4, 14)),
+    ('OP', ')', (4, 14), (4, 15)),
+    ('COMMENT', '# trailing', (4, 17), (4, 27)),
+    ('NEWLINE', '\n', (4, 27), (4, 28)),
+    ('DEDENT', '', (5, 0), (5, 0)),
+    ('ENDMARKER', '', (5, 0), (5, 0)),
+]
+
+# Backslash continuations keep the real positions:
+assert tokens("x = \\\n  '''a\nb'''\n")[2:4] == [
+    ('STRING', "'''a\nb'''", (2, 2), (3, 4)),
+    ('NEWLINE', '\n', (3, 4), (3, 5)),
+]
+
+toks = list(tokenize.tokenize(io.BytesIO(b"a **= b\n").readline))
+assert toks[0] == (tokenize.ENCODING, 'utf-8', (0, 0), (0, 0), '')
+assert toks[2].type == tokenize.OP
+assert toks[2].exact_type == token.DOUBLESTAREQUAL
+assert toks[2].line == 'a **= b\n'
+
+encoding, lines = tokenize.detect_encoding(
+    io.BytesIO(b"# -*- coding: latin-1 -*-\nx = 1\n").readline)
+assert encoding == 'iso-8859-1'
+assert lines == [b"# -*- coding: latin-1 -*-\n"]
+
+toks = list(tokenize.tokenize(io.BytesIO(
+    b"# -*- coding: latin-1 -*-\ns = '\xe9'\n").readline))
+assert toks[0].string == 'iso-8859-1'
+assert toks[5].type == tokenize.STRING
+assert toks[5].string == "'\xe9'"
+assert toks[5].line == "s = '\xe9'\n"
+
+toks = list(tokenize.tokenize(io.BytesIO(
+    b"# coding: ascii\nx = 1\n").readline))
+assert toks[0].string == 'ascii'
+assert [t.string for t in toks[3:6]] == ['x', '=', '1']
+
+source = "def f(a):\n    return a  # done\n"
+toks = list(tokenize.generate_tokens(io.StringIO(source).readline))
+assert tokenize.untokenize(toks) == source
+
+with assertRaises(tokenize.TokenError):
+    list(tokenize.generate_tokens(io.StringIO("(1,\n").readline))
+
+assert token.ISTERMINAL(token.NAME)
+assert token.ISNONTERMINAL(token.NT_OFFSET)
+assert token.ISEOF(token.ENDMARKER)
+assert token.tok_name[token.OP] == 'OP'
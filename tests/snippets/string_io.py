@@ -49,8 +49,20 @@ def test_04():
     assert f.seek(0) == 0
     assert f.read(4) == 'Test'
 
+def test_05():
+    """
+        Tests that the readline method returns one line
+        at a time and an empty value at the end
+    """
+    f = StringIO('line 1\nline 2')
+
+    assert f.readline() == 'line 1\n'
+    assert f.readline() == 'line 2'
+    assert f.readline() == ''
+
 if __name__ == "__main__":
     test_01()
     test_02()
     test_03()
     test_04()
+    test_05()
@@ -180,6 +180,11 @@ assert '_'.isidentifier()
 assert '유니코드'.isidentifier()
 assert not '😂'.isidentifier()
 assert not '123'.isidentifier()
+assert '\u2118'.isidentifier()
+assert 'a\u00b7'.isidentifier()
+assert not '\u00b7'.isidentifier()
+assert not '\u309b'.isidentifier()
+assert '\ufb01'.isidentifier()
 
 # String Formatting
 assert "{} {}".format(1, 2) == "1 2"
@@ -293,3 +298,33 @@ assert next(str_iter_reversed) == "2"
 assert next(str_iter_reversed) == "1"
 assert next(str_iter_reversed, None) == None
 assert_raises(StopIteration, lambda: next(str_iter_reversed))
+
+# Strings are indexed by code point, whatever their contents.
+s = "añb€c😀d" * 20
+assert len(s) == 140
+assert s[1] == "ñ"
+assert s[5] == "😀"
+assert s[-2] == "😀"
+assert s[101] == "€"
+assert s[98:105] == "añb€c😀d"
+assert s[::-1][:7] == "d😀c€bña"
+assert s[1:14:7] == "ññ"
+assert s[-2:-10:-7] == "😀😀"
+assert list(s[:7]) == ["a", "ñ", "b", "€", "c", "😀", "d"]
+assert list(reversed(s[:3])) == ["b", "ñ", "a"]
+assert s.find("😀") == 5
+assert s.find("😀", 6) == 12
+assert s.rfind("€") == 136
+assert s.index("d", 50) == 55
+assert s.rindex("ñ", 0, 50) == 43
+assert s.count("😀", 7, 40) == 4
+assert s.find("c", 135) == 137
+assert s.startswith("€c", 3)
+assert s.endswith("c😀", 0, 6)
+assert not s.startswith("ñ", 2)
+assert "é".ljust(3, "·") == "é··"
+assert "é".rjust(3) == "  é"
+assert "é".zfill(3) == "00é"
+assert "é".center(4, "€") == "€é€€"
+assert "😀"[0] == "😀"
+assert_raises(IndexError, lambda: "😀"[1])
@@ -15,6 +15,12 @@ assert x * -1 == ()  # integers less than zero treated as 0
 
 assert y < x, "tuple __lt__ failed"
 assert x > y, "tuple __gt__ failed"
+assert (2, 'a') > (1, 'b')
+assert (1, 'b') < (2, 'a')
+assert (1, 2) <= (1, 2) and (1, 2) >= (1, 2)
+assert (1, 2) <= (1, 3) and not (1, 3) <= (1, 2)
+assert (1, 2, 0) >= (1, 2) and not (1, 2) >= (1, 2, 0)
+assert sorted([(32, 'g'), (16, 'n'), (2, 'l'), (1, 'o')]) == [(1, 'o'), (2, 'l'), (16, 'n'), (32, 'g')]
 
 
 b = (1,2,3)
@@ -0,0 +1,26 @@
+# Identifiers are NFKC normalized (PEP 3131)
+ﬁ = 1
+assert fi == 1
+
+ℌ = 2
+assert H == 2
+
+def ｆｕｎｃ(ａｒｇ):
+    return arg * 2
+
+assert func(3) == 6
+assert func.__name__ == 'func'
+
+class Ｃ:
+    ｘ = 4
+
+assert C.x == 4
+assert 'x' in C.__dict__
+
+# Other_ID_Start and Other_ID_Continue characters
+℘ = 5
+a· = 6
+assert ℘ + a· == 11
+
+# Attribute names are normalized too
+assert C.ｘ == 4
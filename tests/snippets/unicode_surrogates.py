@@ -0,0 +1,143 @@
+from testutils import assertRaises
+
+# Strings hold lone surrogates as code points of their own.
+s = "a\udc80b\ud800"
+assert len(s) == 4
+assert ord(s[1]) == 0xdc80
+assert ord(s[3]) == 0xd800
+assert chr(0xdfff) == "\udfff"
+assert repr(s) == "'a\\udc80b\\ud800'"
+assert s[1:3] == "\udc80b"
+assert s.split("b") == ["a\udc80", "\ud800"]
+assert s + "\udc80" != s + "\udc81"
+assert {s: 1}["a\udc80b\ud800"] == 1
+
+# Surrogates are unassigned, uncased code points.
+for c in ["\udcff", "\ud800"]:
+    assert not c.isprintable()
+    assert not c.isalpha() and not c.isalnum()
+    assert not c.isdecimal() and not c.isdigit() and not c.isnumeric()
+    assert not c.isspace() and not c.isascii() and not c.isidentifier()
+    assert not c.isupper() and not c.islower() and not c.istitle()
+assert not "a\udcff".isprintable()
+assert "A\udcffB".istitle()
+
+# Encoding
+with assertRaises(UnicodeEncodeError):
+    s.encode()
+with assertRaises(UnicodeEncodeError):
+    s.encode("utf-8", "surrogateescape")
+with assertRaises(LookupError):
+    s.encode("utf-8", "nosuchhandler")
+assert "abc".encode("utf-8", "nosuchhandler") == b"abc"
+assert "a\udcffb".encode("utf-8", "surrogateescape") == b"a\xffb"
+assert s.encode("utf-8", "surrogatepass") == b"a\xed\xb2\x80b\xed\xa0\x80"
+assert s.encode("utf-8", "ignore") == b"ab"
+assert s.encode("utf-8", "replace") == b"a?b?"
+assert bytes("\udc80", "utf-8", "surrogateescape") == b"\x80"
+
+try:
+    "ab\udc80\udc81c".encode()
+except UnicodeEncodeError as e:
+    assert "can't encode characters in position 2-3: surrogates not allowed" in str(e)
+else:
+    assert False
+
+# Decoding
+with assertRaises(UnicodeDecodeError):
+    b"a\xffb".decode()
+assert b"a\xffb".decode("utf-8", "surrogateescape") == "a\udcffb"
+assert b"a\xed\xb2\x80b".decode("utf-8", "surrogatepass") == "a\udc80b"
+assert b"a\xed\xb2\x80b".decode("utf-8", "surrogateescape") == "a\udced\udcb2\udc80b"
+assert b"a\xffb\xe2\x82".decode("utf-8", "replace") == "a�b�"
+assert b"a\xffb".decode("utf-8", "ignore") == "ab"
+
+for data in [b"\x80abc\xff", b"caf\xe9", "déjà vu".encode(), b"\xe2\x82"]:
+    assert data.decode("utf-8", "surrogateescape").encode("utf-8", "surrogateescape") == data
+
+try:
+    b"ab\xff".decode()
+except UnicodeDecodeError as e:
+    assert "can't decode byte 0xff in position 2: invalid start byte" in str(e)
+else:
+    assert False
+
+try:
+    b"ab\xe2\x82".decode()
+except UnicodeDecodeError as e:
+    assert "can't decode bytes in position 2-3: unexpected end of data" in str(e)
+else:
+    assert False
+
+# The characters U+10F800 to U+10FFFF are themselves, next to lone surrogates
+# or not.
+top = "\U0010ffff"
+assert len(top) == 1
+assert ord(top) == 0x10ffff
+assert ord(chr(0x10ffff)) == 0x10ffff
+assert top != "\udfff"
+assert top.encode() == b"\xf4\x8f\xbf\xbf"
+assert b"\xf4\x8f\xbf\xbf".decode() == top
+
+mixed = "\udfff" + top + "\ud800\U0010f800"
+assert len(mixed) == 4
+assert [ord(c) for c in mixed] == [0xdfff, 0x10ffff, 0xd800, 0x10f800]
+assert mixed == "\udfff\U0010ffff\ud800\U0010f800"
+assert mixed.encode("utf-8", "surrogatepass") == b"\xed\xbf\xbf\xf4\x8f\xbf\xbf\xed\xa0\x80\xf4\x8f\xa0\x80"
+assert mixed.encode("utf-8", "surrogatepass").decode("utf-8", "surrogatepass") == mixed
+assert repr(mixed).startswith("'\\udfff") and "\\ud800" in repr(mixed)
+assert mixed[1:3] == top + "\ud800"
+assert mixed[::-1] == "\U0010f800\ud800" + top + "\udfff"
+assert mixed.split(top) == ["\udfff", "\ud800\U0010f800"]
+assert "-".join([top, "\udfff"]) == "\U0010ffff-\udfff"
+assert "{}{}".format("\udfff", top) == "\udfff\U0010ffff"
+assert "%s%s" % (top, "\udfff") == "\U0010ffff\udfff"
+assert f"{top}\udfff" == "\U0010ffff\udfff"
+assert mixed.find("\ud800") == 2
+assert top in mixed and "\ud800" in mixed and "\udc00" not in mixed
+with assertRaises(UnicodeEncodeError):
+    mixed.encode()
+assert ("\udfff" + top).encode("utf-8", "replace") == b"?\xf4\x8f\xbf\xbf"
+
+for code_point in range(0x10f800, 0x110000):
+    c = chr(code_point)
+    assert ord(c) == code_point
+    data = c.encode()
+    assert data.decode() == c
+    assert ord((c + "\udc80")[0]) == code_point
+    assert (c + "\udc80").encode("utf-8", "surrogateescape") == data + b"\x80"
+
+crowded = "\U0010f000\U0010ffff" + chr(0xdc80) + "\U0010e800"
+assert [ord(c) for c in crowded] == [0x10f000, 0x10ffff, 0xdc80, 0x10e800]
+assert crowded[2] == "\udc80" and crowded[:2] + crowded[3:] == "\U0010f000\U0010ffff\U0010e800"
+
+def documented():
+    "doc \udc80 \U0010ffff"
+assert [ord(c) for c in documented.__doc__[4::2]] == [0xdc80, 0x10ffff]
+
+# Text I/O
+import io
+import marshal
+import os
+import time
+
+buf = io.StringIO("\udc80")
+assert buf.write("a\udcff") == 2
+assert buf.getvalue() == "a\udcff"
+buf.seek(0)
+assert buf.read() == "a\udcff"
+
+path = os.path.join("/tmp", "rustpython_test_surrogates_" + str(int(time.time())))
+with open(path, "w") as f:
+    with assertRaises(UnicodeEncodeError):
+        f.write("a\udcff")
+    f.write("\U0010ffff")
+with open(path, "rb") as f:
+    assert f.read() == b"\xf4\x8f\xbf\xbf"
+os.remove(path)
+
+# marshal passes surrogates through, like CPython.
+assert marshal.loads(marshal.dumps("a\udcff")) == "a\udcff"
+assert marshal.loads(b"\xf5\x03\x00\x00\x00\xed\xb3\xbf") == "\udcff"
+with assertRaises(ValueError):
+    marshal.loads(b"\xf5\x01\x00\x00\x00\xff")
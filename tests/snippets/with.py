@@ -67,3 +67,9 @@ class assertRaises:
 
 with assertRaises(RuntimeError):
     raise RuntimeError('w00t')
+
+# An inner context manager suppressing the exception still leaves the outer:
+ls = []
+with ContextManager(), assertRaises(RuntimeError):
+    raise RuntimeError('w00t')
+assert ls == [1, 2]
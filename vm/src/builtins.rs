@@ -3,7 +3,6 @@
 //! Implements functions listed here: https://docs.python.org/3/library/builtins.html
 
 use std::cell::Cell;
-use std::char;
 use std::io::{self, Write};
 use std::str;
 
@@ -28,6 +27,7 @@ use crate::pyobject::{
     TryFromObject, TypeProtocol,
 };
 use crate::scope::Scope;
+use crate::surrogates::{self, ErrorHandler};
 use crate::vm::VirtualMachine;
 
 use crate::obj::objbyteinner::PyByteInner;
@@ -76,10 +76,12 @@ fn builtin_callable(obj: PyObjectRef, vm: &VirtualMachine) -> bool {
     vm.is_callable(&obj)
 }
 
-fn builtin_chr(i: u32, vm: &VirtualMachine) -> PyResult<String> {
-    match char::from_u32(i) {
-        Some(value) => Ok(value.to_string()),
-        None => Err(vm.new_value_error("chr() arg not in range(0x110000)".to_string())),
+fn builtin_chr(i: u32, vm: &VirtualMachine) -> PyResult<PyString> {
+    let mut string = surrogates::Builder::default();
+    if string.push_code_point(i) {
+        Ok(string.finish().unwrap())
+    } else {
+        Err(vm.new_value_error("chr() arg not in range(0x110000)".to_string()))
     }
 }
 
@@ -104,16 +106,81 @@ struct CompileArgs {
 fn builtin_compile(args: CompileArgs, vm: &VirtualMachine) -> PyResult<PyCodeRef> {
     // TODO: compile::compile should probably get bytes
     let source = match args.source {
-        Either::A(string) => string.value.to_string(),
+        Either::A(string) => string.as_str().to_string(),
         Either::B(bytes) => str::from_utf8(&bytes).unwrap().to_string(),
     };
 
-    let mode = get_compile_mode(vm, &args.mode.value)?;
+    let mode = get_compile_mode(vm, args.mode.as_str())?;
+
+    // -1 selects the optimization level of the interpreter.
+    let optimize = match args.optimize {
+        OptionalArg::Present(optimize) => match optimize.as_bigint().to_i32() {
+            Some(-1) => vm.settings.optimize,
+            Some(optimize @ 0..=2) => optimize as u8,
+            _ => return Err(vm.new_value_error("compile(): invalid optimize value".to_string())),
+        },
+        OptionalArg::Missing => vm.settings.optimize,
+    };
 
-    vm.compile(&source, &mode, args.filename.value.to_string())
+    #[cfg(feature = "rustpython-parser")]
+    {
+        let flags = match args.flags {
+            OptionalArg::Present(flags) => flags.as_bigint().to_i32().unwrap_or(0),
+            OptionalArg::Missing => 0,
+        };
+        if flags & PY_CF_DONT_IMPLY_DEDENT != 0 {
+            return compile_command(
+                &source,
+                &mode,
+                args.filename.as_str().to_string(),
+                optimize,
+                vm,
+            );
+        }
+    }
+
+    vm.compile_with_optimize(&source, &mode, args.filename.as_str().to_string(), optimize)
         .map_err(|err| vm.new_syntax_error(&err))
 }
 
+/// Matches `PyCF_DONT_IMPLY_DEDENT` in CPython, the flag the codeop module compiles with.
+#[cfg(all(feature = "rustpython-compiler", feature = "rustpython-parser"))]
+const PY_CF_DONT_IMPLY_DEDENT: i32 = 0x200;
+
+/// Compile input that may still be incomplete, like the REPL does. Incomplete
+/// input raises `_IncompleteInputError`, a subclass of `SyntaxError`.
+#[cfg(all(feature = "rustpython-compiler", feature = "rustpython-parser"))]
+fn compile_command(
+    source: &str,
+    mode: &compile::Mode,
+    source_path: String,
+    optimize: u8,
+    vm: &VirtualMachine,
+) -> PyResult<PyCodeRef> {
+    use rustpython_compiler::error::{CompileError, CompileErrorType};
+    use rustpython_parser::error::ParseErrorType;
+    use rustpython_parser::parser::{self, InputStatus};
+
+    let incomplete = || {
+        let incomplete_input_error = vm.ctx.exceptions.incomplete_input_error.clone();
+        vm.new_exception(incomplete_input_error, "incomplete input".to_string())
+    };
+
+    if let compile::Mode::Single = mode {
+        if parser::classify_input(source) == InputStatus::Incomplete {
+            return Err(incomplete());
+        }
+    }
+    vm.compile_with_optimize(source, mode, source_path, optimize)
+        .map_err(|err| match err {
+            CompileError {
+                error: CompileErrorType::Parse(ParseErrorType::EOF),
+                ..
+            } => incomplete(),
+            err => vm.new_syntax_error(&err),
+        })
+}
+
 fn builtin_delattr(obj: PyObjectRef, attr: PyStringRef, vm: &VirtualMachine) -> PyResult<()> {
     vm.del_attr(&obj, attr.into_object())
 }
@@ -254,12 +321,9 @@ fn builtin_format(
     format_spec: OptionalArg<PyStringRef>,
     vm: &VirtualMachine,
 ) -> PyResult<PyStringRef> {
-    let format_spec = format_spec.into_option().unwrap_or_else(|| {
-        PyString {
-            value: "".to_string(),
-        }
-        .into_ref(vm)
-    });
+    let format_spec = format_spec
+        .into_option()
+        .unwrap_or_else(|| PyString::from("").into_ref(vm));
 
     vm.call_method(&value, "__format__", vec![format_spec.into_object()])?
         .downcast()
@@ -518,16 +582,16 @@ fn builtin_oct(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
 fn builtin_ord(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     arg_check!(vm, args, required = [(string, None)]);
     if objtype::isinstance(string, &vm.ctx.str_type()) {
-        let string = objstr::borrow_value(string);
-        let string_len = string.chars().count();
+        let string = objstr::borrow_str(string);
+        let string_len = string.char_len();
         if string_len != 1 {
             return Err(vm.new_type_error(format!(
                 "ord() expected a character, but string of length {} found",
                 string_len
             )));
         }
-        match string.chars().next() {
-            Some(character) => Ok(vm.context().new_int(character as i32)),
+        match string.code_points().next() {
+            Some(code_point) => Ok(vm.context().new_int(code_point)),
             None => Err(vm.new_type_error(
                 "ord() could not guess the integer representing this character".to_string(),
             )),
@@ -608,7 +672,8 @@ trait Printer {
 
 impl Printer for &'_ PyObjectRef {
     fn write(&mut self, vm: &VirtualMachine, obj: PyObjectRef) -> PyResult<()> {
-        vm.call_method(self, "write", vec![obj])?;
+        let s = vm.to_str(&obj)?;
+        vm.call_method(self, "write", vec![s.into_object()])?;
         Ok(())
     }
 
@@ -620,8 +685,10 @@ impl Printer for &'_ PyObjectRef {
 
 impl Printer for std::io::StdoutLock<'_> {
     fn write(&mut self, vm: &VirtualMachine, obj: PyObjectRef) -> PyResult<()> {
-        let s = &vm.to_str(&obj)?.value;
-        write!(self, "{}", s).unwrap();
+        let s = vm.to_str(&obj)?;
+        let bytes = surrogates::encode_utf8(&s, ErrorHandler::SurrogateEscape)
+            .map_err(|err| err.into_exception("surrogateescape", vm))?;
+        self.write_all(&bytes).unwrap();
         Ok(())
     }
 
@@ -653,7 +720,7 @@ pub fn builtin_print(objects: Args, options: PrintOptions, vm: &VirtualMachine)
     let sep = options
         .sep
         .as_ref()
-        .map_or(" ", |sep| &sep.value)
+        .map_or(" ", |sep| sep.as_str())
         .into_pyobject(vm)
         .unwrap();
 
@@ -671,7 +738,7 @@ pub fn builtin_print(objects: Args, options: PrintOptions, vm: &VirtualMachine)
     let end = options
         .end
         .as_ref()
-        .map_or("\n", |end| &end.value)
+        .map_or("\n", |end| end.as_str())
         .into_pyobject(vm)
         .unwrap();
     printer.write(vm, end)?;
@@ -864,6 +931,9 @@ pub fn make_module(vm: &VirtualMachine, module: PyObjectRef) {
         "RuntimeError" => ctx.exceptions.runtime_error.clone(),
         "ReferenceError" => ctx.exceptions.reference_error.clone(),
         "SyntaxError" =>  ctx.exceptions.syntax_error.clone(),
+        "_IncompleteInputError" => ctx.exceptions.incomplete_input_error.clone(),
+        "IndentationError" => ctx.exceptions.indentation_error.clone(),
+        "TabError" => ctx.exceptions.tab_error.clone(),
         "NotImplementedError" => ctx.exceptions.not_implemented_error.clone(),
         "TypeError" => ctx.exceptions.type_error.clone(),
         "ValueError" => ctx.exceptions.value_error.clone(),
@@ -901,13 +971,12 @@ pub fn make_module(vm: &VirtualMachine, module: PyObjectRef) {
 
 pub fn builtin_build_class_(
     function: PyObjectRef,
-    qualified_name: PyStringRef,
+    name: PyStringRef,
     bases: Args<PyClassRef>,
     mut kwargs: KwArgs,
     vm: &VirtualMachine,
 ) -> PyResult {
-    let name = qualified_name.value.split('.').next_back().unwrap();
-    let name_obj = vm.new_str(name.to_string());
+    let name_obj = name.into_object();
 
     let mut metaclass = if let Some(metaclass) = kwargs.pop_kwarg("metaclass") {
         PyClassRef::try_from_object(vm, metaclass)?
@@ -935,9 +1004,6 @@ pub fn builtin_build_class_(
 
     vm.invoke_with_locals(function, cells.clone(), namespace.clone())?;
 
-    namespace.set_item("__name__", name_obj.clone(), vm)?;
-    namespace.set_item("__qualname__", qualified_name.into_object(), vm)?;
-
     let class = vm.call_method(
         metaclass.as_object(),
         "__call__",
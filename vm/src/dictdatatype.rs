@@ -8,6 +8,7 @@ use crate::vm::VirtualMachine;
 /// And: http://code.activestate.com/recipes/578375/
 use std::collections::{hash_map::DefaultHasher, HashMap};
 use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
 
 /// hash value of an object returned by __hash__
 type HashValue = pyhash::PyHash;
@@ -16,11 +17,17 @@ type HashIndex = pyhash::PyHash;
 /// entry index mapped in indices
 type EntryIndex = usize;
 
+static NEXT_VERSION: AtomicU64 = AtomicU64::new(1);
+
 #[derive(Clone)]
 pub struct Dict<T = PyObjectRef> {
     size: usize,
     indices: HashMap<HashIndex, EntryIndex>,
     entries: Vec<Option<DictEntry<T>>>,
+    /// Changes whenever the contents do. Versions are never reused, not even
+    /// by another dict, so two dicts with the same version have the same
+    /// contents.
+    version: u64,
 }
 
 impl<T> Default for Dict<T> {
@@ -29,6 +36,7 @@ impl<T> Default for Dict<T> {
             size: 0,
             indices: HashMap::new(),
             entries: Vec::new(),
+            version: 0,
         }
     }
 }
@@ -63,11 +71,21 @@ impl<T: Clone> Dict<T> {
         self.entries.push(Some(entry));
         self.indices.insert(hash_index, entry_index);
         self.size += 1;
+        self.modified();
     }
 
     fn unchecked_delete(&mut self, entry_index: EntryIndex) {
         self.entries[entry_index] = None;
         self.size -= 1;
+        self.modified();
+    }
+
+    fn modified(&mut self) {
+        self.version = NEXT_VERSION.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn version(&self) -> u64 {
+        self.version
     }
 
     /// Store a key
@@ -77,6 +95,7 @@ impl<T: Clone> Dict<T> {
                 // Update existing key
                 if let Some(ref mut entry) = self.entries[index] {
                     entry.value = value;
+                    self.modified();
                     Ok(())
                 } else {
                     panic!("Lookup returned invalid index into entries!");
@@ -119,10 +138,31 @@ impl<T: Clone> Dict<T> {
         }
     }
 
+    /// Retrieve a key and the index of its entry, for `get_by_index`.
+    pub fn get_entry(
+        &self,
+        vm: &VirtualMachine,
+        key: &PyObjectRef,
+    ) -> PyResult<Option<(EntryIndex, T)>> {
+        if let LookupResult::Existing(index) = self.lookup(vm, key)? {
+            Ok(Some((index, self.unchecked_get(index))))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Retrieve the value at an index `get_entry` gave while the dict had
+    /// the same version as now.
+    pub fn get_by_index(&self, index: EntryIndex) -> Option<T> {
+        let entry = self.entries.get(index)?.as_ref()?;
+        Some(entry.value.clone())
+    }
+
     pub fn clear(&mut self) {
         self.entries.clear();
         self.indices.clear();
-        self.size = 0
+        self.size = 0;
+        self.modified();
     }
 
     /// Delete a key
@@ -318,4 +358,33 @@ mod tests {
 
         assert_eq!(true, dict.contains(&vm, &key1).unwrap());
     }
+
+    #[test]
+    fn test_version() {
+        let vm: VirtualMachine = Default::default();
+        let mut dict = Dict::default();
+        let key = vm.new_str("x".to_string());
+        let mut versions = vec![dict.version()];
+
+        dict.insert(&vm, &key, vm.new_int(1)).unwrap();
+        versions.push(dict.version());
+        dict.get(&vm, &key).unwrap();
+        assert_eq!(dict.version(), *versions.last().unwrap());
+
+        dict.insert(&vm, &key, vm.new_int(2)).unwrap();
+        versions.push(dict.version());
+        dict.delete(&vm, &key).unwrap();
+        versions.push(dict.version());
+        dict.clear();
+        versions.push(dict.version());
+
+        let mut other = Dict::default();
+        other.insert(&vm, &key, vm.new_int(1)).unwrap();
+        versions.push(other.version());
+
+        let mut unique = versions.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), versions.len());
+    }
 }
@@ -1,12 +1,16 @@
 use crate::function::PyFuncArgs;
+use crate::obj::objint::PyInt;
 use crate::obj::objsequence;
+use crate::obj::objstr::PyString;
 use crate::obj::objtuple::{PyTuple, PyTupleRef};
 use crate::obj::objtype;
 use crate::obj::objtype::PyClassRef;
 use crate::pyobject::{create_type, IdProtocol, PyContext, PyObjectRef, PyResult, TypeProtocol};
 use crate::vm::VirtualMachine;
+use num_traits::ToPrimitive;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
+use std::path::Path;
 
 fn exception_init(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     let exc_self = args.args[0].clone();
@@ -15,6 +19,9 @@ fn exception_init(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
 
     let traceback = vm.ctx.new_list(Vec::new());
     vm.set_attr(&exc_self, "__traceback__", traceback)?;
+    vm.set_attr(&exc_self, "__cause__", vm.get_none())?;
+    vm.set_attr(&exc_self, "__context__", vm.get_none())?;
+    vm.set_attr(&exc_self, "__suppress_context__", vm.new_bool(false))?;
     Ok(vm.get_none())
 }
 
@@ -39,6 +46,17 @@ pub fn print_exception(vm: &VirtualMachine, exc: &PyObjectRef) {
     print_exception_inner(vm, exc)
 }
 
+/// Print an exception that can't be raised to anyone, like one from a
+/// `__del__` method, along with the object whose call raised it.
+pub fn print_unraisable(vm: &VirtualMachine, exc: &PyObjectRef, obj: &PyObjectRef) {
+    let repr = vm.to_repr(obj).map_or_else(
+        |_| "<object repr() failed>".to_string(),
+        |repr| repr.as_str().to_string(),
+    );
+    println!("Exception ignored in: {}", repr);
+    print_exception(vm, exc);
+}
+
 fn print_source_line(filename: String, lineno: usize) {
     // TODO: use io.open() method instead, when available, according to https://github.com/python/cpython/blob/master/Python/traceback.c#L393
     // TODO: support different encodings
@@ -66,19 +84,19 @@ fn print_traceback_entry(vm: &VirtualMachine, tb_entry: &PyObjectRef) {
     if objtype::isinstance(&tb_entry, &vm.ctx.tuple_type()) {
         let location_attrs = objsequence::get_elements_tuple(&tb_entry);
         let filename = if let Ok(x) = vm.to_str(&location_attrs[0]) {
-            x.value.clone()
+            x.as_str().to_string()
         } else {
             "<error>".to_string()
         };
 
         let lineno = if let Ok(x) = vm.to_str(&location_attrs[1]) {
-            x.value.clone()
+            x.as_str().to_string()
         } else {
             "<error>".to_string()
         };
 
         let obj_name = if let Ok(x) = vm.to_str(&location_attrs[2]) {
-            x.value.clone()
+            x.as_str().to_string()
         } else {
             "<error>".to_string()
         };
@@ -94,14 +112,54 @@ fn print_traceback_entry(vm: &VirtualMachine, tb_entry: &PyObjectRef) {
     }
 }
 
+/// Print the source line of a syntax error with a caret under the offending
+/// column, like CPython does.
+fn print_syntax_error_location(vm: &VirtualMachine, exc: &PyObjectRef) {
+    let attribute = |name| {
+        vm.get_attribute(exc.clone(), name)
+            .ok()
+            .filter(|value| !value.is(&vm.get_none()))
+    };
+    let filename = match attribute("filename").and_then(|value| vm.to_str(&value).ok()) {
+        Some(filename) => filename.as_str().to_string(),
+        None => "<string>".to_string(),
+    };
+    let lineno = attribute("lineno")
+        .and_then(|value| vm.to_str(&value).ok())
+        .map_or_else(|| "?".to_string(), |lineno| lineno.as_str().to_string());
+    println!(r##"  File "{}", line {}"##, filename, lineno);
+
+    let text = match attribute("text").and_then(|value| vm.to_str(&value).ok()) {
+        Some(text) => text.as_str().to_string(),
+        None => return,
+    };
+    let offset = attribute("offset")
+        .and_then(|value| {
+            value
+                .payload::<PyInt>()
+                .and_then(|int| int.as_bigint().to_usize())
+        })
+        .unwrap_or(0);
+    // The offset counts characters from the start of the line, which loses
+    // its indentation when it is printed.
+    let line = text.trim_end();
+    let indent = line.chars().count() - line.trim_start().chars().count();
+    println!("    {}", line.trim_start());
+    if offset > indent {
+        println!("    {}^", " ".repeat(offset - indent - 1));
+    }
+}
+
 /// Print exception with traceback
 pub fn print_exception_inner(vm: &VirtualMachine, exc: &PyObjectRef) {
     if let Ok(tb) = vm.get_attribute(exc.clone(), "__traceback__") {
-        println!("Traceback (most recent call last):");
         if objtype::isinstance(&tb, &vm.ctx.list_type()) {
             let mut tb_entries = objsequence::get_elements_list(&tb).to_vec();
             tb_entries.reverse();
 
+            if !tb_entries.is_empty() {
+                println!("Traceback (most recent call last):");
+            }
             for exc_location in tb_entries.iter() {
                 print_traceback_entry(vm, exc_location);
             }
@@ -110,6 +168,19 @@ pub fn print_exception_inner(vm: &VirtualMachine, exc: &PyObjectRef) {
         println!("No traceback set on exception");
     }
 
+    if objtype::isinstance(exc, &vm.ctx.exceptions.syntax_error) {
+        print_syntax_error_location(vm, exc);
+        let msg = vm
+            .get_attribute(exc.clone(), "msg")
+            .and_then(|msg| vm.to_str(&msg))
+            .map_or_else(
+                |_| "<msg str() failed>".to_string(),
+                |msg| msg.as_str().to_string(),
+            );
+        println!("{}: {}", exc.class().name, msg);
+        return;
+    }
+
     let varargs = vm
         .get_attribute(exc.clone(), "args")
         .unwrap()
@@ -130,7 +201,7 @@ fn exception_args_as_string(vm: &VirtualMachine, varargs: PyTupleRef) -> Vec<Str
         0 => vec![],
         1 => {
             let args0_repr = match vm.to_repr(&varargs.elements[0]) {
-                Ok(args0_repr) => args0_repr.value.clone(),
+                Ok(args0_repr) => args0_repr.as_str().to_string(),
                 Err(_) => "<element repr() failed>".to_string(),
             };
             vec![args0_repr]
@@ -139,7 +210,7 @@ fn exception_args_as_string(vm: &VirtualMachine, varargs: PyTupleRef) -> Vec<Str
             let mut args_vec = Vec::with_capacity(varargs.elements.len());
             for vararg in &varargs.elements {
                 let arg_repr = match vm.to_repr(vararg) {
-                    Ok(arg_repr) => arg_repr.value.clone(),
+                    Ok(arg_repr) => arg_repr.as_str().to_string(),
                     Err(_) => "<element repr() failed>".to_string(),
                 };
                 args_vec.push(arg_repr);
@@ -191,7 +262,7 @@ fn exception_repr(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     Ok(vm.new_str(joined_str))
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ExceptionZoo {
     pub arithmetic_error: PyClassRef,
     pub assertion_error: PyClassRef,
@@ -201,6 +272,8 @@ pub struct ExceptionZoo {
     pub file_not_found_error: PyClassRef,
     pub file_exists_error: PyClassRef,
     pub import_error: PyClassRef,
+    pub incomplete_input_error: PyClassRef,
+    pub indentation_error: PyClassRef,
     pub index_error: PyClassRef,
     pub key_error: PyClassRef,
     pub lookup_error: PyClassRef,
@@ -215,6 +288,7 @@ pub struct ExceptionZoo {
     pub stop_iteration: PyClassRef,
     pub syntax_error: PyClassRef,
     pub system_error: PyClassRef,
+    pub tab_error: PyClassRef,
     pub type_error: PyClassRef,
     pub value_error: PyClassRef,
     pub unicode_error: PyClassRef,
@@ -266,6 +340,10 @@ impl ExceptionZoo {
         let permission_error = create_type("PermissionError", &type_type, &os_error);
         let file_exists_error = create_type("FileExistsError", &type_type, &os_error);
         let eof_error = create_type("EOFError", &type_type, &exception_type);
+        let indentation_error = create_type("IndentationError", &type_type, &syntax_error);
+        let tab_error = create_type("TabError", &type_type, &indentation_error);
+        let incomplete_input_error =
+            create_type("_IncompleteInputError", &type_type, &syntax_error);
         let unicode_error = create_type("UnicodeError", &type_type, &value_error);
         let unicode_decode_error = create_type("UnicodeDecodeError", &type_type, &unicode_error);
         let unicode_encode_error = create_type("UnicodeEncodeError", &type_type, &unicode_error);
@@ -294,6 +372,8 @@ impl ExceptionZoo {
             file_not_found_error,
             file_exists_error,
             import_error,
+            incomplete_input_error,
+            indentation_error,
             index_error,
             key_error,
             lookup_error,
@@ -307,6 +387,7 @@ impl ExceptionZoo {
             stop_iteration,
             syntax_error,
             system_error,
+            tab_error,
             type_error,
             value_error,
             unicode_error,
@@ -360,6 +441,63 @@ fn import_error_init(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     Ok(vm.get_none())
 }
 
+fn syntax_error_init(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
+    // TODO: call super().__init__(*args) instead
+    exception_init(vm, args.clone())?;
+
+    let exc_self = args.args[0].clone();
+    let msg = args.args.get(1).cloned().unwrap_or_else(|| vm.get_none());
+    vm.set_attr(&exc_self, "msg", msg)?;
+
+    // SyntaxError(msg, (filename, lineno, offset, text))
+    let details = match args.args.get(2) {
+        Some(details) if args.args.len() == 3 => vm.extract_elements(details)?,
+        _ => vec![vm.get_none(); 4],
+    };
+    if details.len() != 4 {
+        return Err(vm.new_type_error(format!(
+            "SyntaxError details must have 4 items, not {}",
+            details.len()
+        )));
+    }
+    for (name, value) in ["filename", "lineno", "offset", "text"].iter().zip(details) {
+        vm.set_attr(&exc_self, *name, value)?;
+    }
+    vm.set_attr(&exc_self, "print_file_and_line", vm.get_none())?;
+    Ok(vm.get_none())
+}
+
+fn syntax_error_str(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
+    arg_check!(
+        vm,
+        args,
+        required = [(exc, Some(vm.ctx.exceptions.syntax_error.clone()))]
+    );
+    let msg = vm.get_attribute(exc.clone(), "msg")?;
+    let msg = vm.to_str(&msg)?.as_str().to_string();
+    let filename = vm.get_attribute(exc.clone(), "filename")?;
+    let filename = filename.payload::<PyString>().map(|filename| {
+        // Only the file name is shown, not the whole path
+        Path::new(filename.as_str())
+            .file_name()
+            .map_or(filename.as_str().to_string(), |name| {
+                name.to_string_lossy().into_owned()
+            })
+    });
+    let lineno = vm.get_attribute(exc.clone(), "lineno")?;
+    let lineno = lineno
+        .payload::<PyInt>()
+        .map(|lineno| lineno.as_bigint().clone());
+
+    let formatted = match (filename, lineno) {
+        (Some(filename), Some(lineno)) => format!("{} ({}, line {})", msg, filename, lineno),
+        (Some(filename), None) => format!("{} ({})", msg, filename),
+        (None, Some(lineno)) => format!("{} (line {})", msg, lineno),
+        (None, None) => msg,
+    };
+    Ok(vm.new_str(formatted))
+}
+
 pub fn init(context: &PyContext) {
     let base_exception_type = &context.exceptions.base_exception_type;
     extend_class!(context, base_exception_type, {
@@ -376,4 +514,10 @@ pub fn init(context: &PyContext) {
     extend_class!(context, import_error_type, {
         "__init__" => context.new_rustfunc(import_error_init)
     });
+
+    let syntax_error_type = &context.exceptions.syntax_error;
+    extend_class!(context, syntax_error_type, {
+        "__init__" => context.new_rustfunc(syntax_error_init),
+        "__str__" => context.new_rustfunc(syntax_error_str),
+    });
 }
@@ -1,12 +1,16 @@
 use std::cell::RefCell;
 use std::fmt;
+use std::mem;
 
 use crate::builtins;
 use crate::bytecode;
 use crate::function::PyFuncArgs;
+use crate::gc;
+use crate::gil;
 use crate::obj::objbool;
-use crate::obj::objcode::PyCodeRef;
+use crate::obj::objcode::{CachedGlobal, PyCodeRef};
 use crate::obj::objdict::{PyDict, PyDictRef};
+use crate::obj::objfunction::PyFunction;
 use crate::obj::objiter;
 use crate::obj::objlist;
 use crate::obj::objslice::PySlice;
@@ -50,7 +54,7 @@ enum BlockType {
 pub type FrameRef = PyRef<Frame>;
 
 pub struct Frame {
-    pub code: bytecode::CodeObject,
+    pub code: PyCodeRef,
     // We need 1 stack per frame
     stack: RefCell<Vec<PyObjectRef>>, // The main data frame of the stack machine
     blocks: RefCell<Vec<Block>>,      // Block frames, for controlling loops and exceptions
@@ -59,9 +63,38 @@ pub struct Frame {
 }
 
 impl PyValue for Frame {
+    const HAVE_GC: bool = true;
+
     fn class(vm: &VirtualMachine) -> PyClassRef {
         vm.ctx.frame_type()
     }
+
+    fn traverse(&self, visit: &mut dyn FnMut(&PyObjectRef)) {
+        visit(self.code.as_object());
+        if let Ok(stack) = self.stack.try_borrow() {
+            for value in stack.iter() {
+                visit(value);
+            }
+        }
+        if let Ok(blocks) = self.blocks.try_borrow() {
+            for block in blocks.iter() {
+                if let BlockType::With {
+                    ref context_manager,
+                    ..
+                } = block.typ
+                {
+                    visit(context_manager);
+                }
+            }
+        }
+        self.scope.traverse(visit);
+    }
+
+    fn clear_refs(&self) {
+        let stack = self.stack.try_borrow_mut().map(|mut s| mem::take(&mut *s));
+        let blocks = self.blocks.try_borrow_mut().map(|mut b| mem::take(&mut *b));
+        drop((stack, blocks));
+    }
 }
 
 // Running a frame can result in one of the below:
@@ -86,9 +119,10 @@ impl Frame {
         // let locals = globals;
         // locals.extend(callargs);
 
+        let stack = Vec::with_capacity(code.code.max_stackdepth);
         Frame {
-            code: code.code.clone(),
-            stack: RefCell::new(vec![]),
+            code,
+            stack: RefCell::new(stack),
             blocks: RefCell::new(vec![]),
             // save the callargs as locals
             // globals: locals.clone(),
@@ -99,15 +133,21 @@ impl Frame {
 
     // #[cfg_attr(feature = "flame-it", flame("Frame"))]
     pub fn run(&self, vm: &VirtualMachine) -> PyResult<ExecutionResult> {
-        flame_guard!(format!("Frame::run({})", self.code.obj_name));
+        flame_guard!(format!("Frame::run({})", self.code.code.obj_name));
 
-        let filename = &self.code.source_path.to_string();
+        let filename = &self.code.code.source_path.to_string();
 
         // This is the name of the object being run:
-        let run_obj_name = &self.code.obj_name.to_string();
+        let run_obj_name = &self.code.code.obj_name.to_string();
 
         // Execute until return or exception:
         loop {
+            if gc::pending() {
+                gc::run_pending(vm);
+            }
+            if gil::drop_requested() {
+                gil::switch();
+            }
             let lineno = self.get_lineno();
             let result = self.execute_instruction(vm);
             match result {
@@ -155,7 +195,7 @@ impl Frame {
     }
 
     pub fn fetch_instruction(&self) -> &bytecode::Instruction {
-        let ins2 = &self.code.instructions[*self.lasti.borrow()];
+        let ins2 = &self.code.code.instructions[*self.lasti.borrow()];
         *self.lasti.borrow_mut() += 1;
         ins2
     }
@@ -243,13 +283,11 @@ impl Frame {
                 Ok(None)
             }
             bytecode::Instruction::BuildString { size } => {
-                let s = self
-                    .pop_multiple(*size)
-                    .into_iter()
-                    .map(|pyobj| objstr::get_value(&pyobj))
-                    .collect::<String>();
-                let str_obj = vm.ctx.new_str(s);
-                self.push_value(str_obj);
+                let parts = self.pop_multiple(*size);
+                let parts: Vec<_> = parts.iter().map(objstr::borrow_str).collect();
+                let empty = objstr::PyString::from("");
+                let s = objstr::PyString::concat(&parts, &empty, vm)?;
+                self.push_value(s.into_ref(vm).into_object());
                 Ok(None)
             }
             bytecode::Instruction::BuildList { size, unpack } => {
@@ -366,8 +404,10 @@ impl Frame {
                         Ok(Some(ExecutionResult::Yield(value)))
                     }
                     None => {
-                        // Pop iterator from stack:
+                        // Replace the iterator with the value of the expression,
+                        // which is None as generators do not return values yet.
                         self.pop_value();
+                        self.push_value(vm.get_none());
                         Ok(None)
                     }
                 }
@@ -447,48 +487,27 @@ impl Frame {
             }
             bytecode::Instruction::MakeFunction { flags } => self.execute_make_function(vm, *flags),
             bytecode::Instruction::CallFunction { typ } => {
-                let args = match typ {
-                    bytecode::CallType::Positional(count) => {
-                        let args: Vec<PyObjectRef> = self.pop_multiple(*count);
-                        PyFuncArgs {
-                            args,
-                            kwargs: IndexMap::new(),
-                        }
-                    }
-                    bytecode::CallType::Keyword(count) => {
-                        let kwarg_names = self.pop_value();
-                        let args: Vec<PyObjectRef> = self.pop_multiple(*count);
-
-                        let kwarg_names = vm
-                            .extract_elements(&kwarg_names)?
-                            .iter()
-                            .map(|pyobj| objstr::get_value(pyobj))
-                            .collect();
-                        PyFuncArgs::new(args, kwarg_names)
-                    }
-                    bytecode::CallType::Ex(has_kwargs) => {
-                        let kwargs = if *has_kwargs {
-                            let kw_dict: PyDictRef =
-                                self.pop_value().downcast().expect("Kwargs must be a dict.");
-                            kw_dict
-                                .into_iter()
-                                .map(|elem| (objstr::get_value(&elem.0), elem.1))
-                                .collect()
-                        } else {
-                            IndexMap::new()
-                        };
-                        let args = self.pop_value();
-                        let args = vm.extract_elements(&args)?;
-                        PyFuncArgs { args, kwargs }
-                    }
-                };
-
-                // Call function:
+                let args = self.pop_call_args(vm, typ)?;
                 let func_ref = self.pop_value();
                 let value = vm.invoke(func_ref, args)?;
                 self.push_value(value);
                 Ok(None)
             }
+            bytecode::Instruction::LoadMethod { ref name } => self.load_method(vm, name),
+            bytecode::Instruction::CallMethod { typ } => {
+                let mut args = self.pop_call_args(vm, typ)?;
+                let obj = self.pop_value();
+                let function = self.pop_value();
+                let func_ref = if vm.is_none(&function) {
+                    obj
+                } else {
+                    args.args.insert(0, obj);
+                    function
+                };
+                let value = vm.invoke(func_ref, args)?;
+                self.push_value(value);
+                Ok(None)
+            }
             bytecode::Instruction::Jump { target } => {
                 self.jump(*target);
                 Ok(None)
@@ -584,6 +603,13 @@ impl Frame {
                 self.push_value(vm.ctx.new_rustfunc(builtins::builtin_build_class_));
                 Ok(None)
             }
+            bytecode::Instruction::SetupAnnotation => {
+                let locals = self.scope.get_locals();
+                if !locals.contains_key("__annotations__", vm) {
+                    locals.set_item("__annotations__", vm.ctx.new_dict().into_object(), vm)?;
+                }
+                Ok(None)
+            }
             bytecode::Instruction::UnpackSequence { size } => {
                 let value = self.pop_value();
                 let elements = vm.extract_elements(&value)?;
@@ -903,9 +929,12 @@ impl Frame {
         name_scope: &bytecode::NameScope,
     ) -> FrameResult {
         let optional_value = match name_scope {
-            bytecode::NameScope::Global => self.scope.load_global(vm, name),
+            bytecode::NameScope::Global => self.load_global(vm, name),
             bytecode::NameScope::NonLocal => self.scope.load_cell(vm, name),
-            bytecode::NameScope::Local => self.scope.load_name(&vm, name),
+            bytecode::NameScope::Local => self
+                .scope
+                .load_local(vm, name)
+                .or_else(|| self.load_global(vm, name)),
         };
 
         let value = match optional_value {
@@ -919,6 +948,56 @@ impl Frame {
         Ok(None)
     }
 
+    /// Look `name` up in the globals and then the builtins, like
+    /// `Scope::load_global`, going straight to the entry this instruction
+    /// found last time if neither changed since.
+    fn load_global(&self, vm: &VirtualMachine, name: &str) -> Option<PyObjectRef> {
+        let globals = &self.scope.globals;
+        let builtins = match vm.builtins.dict {
+            Some(ref builtins) => builtins,
+            None => return self.scope.load_global(vm, name),
+        };
+        // Subclasses of dict may look names up differently.
+        let dict_type = vm.ctx.dict_type();
+        if !globals.class().is(&dict_type) || !builtins.class().is(&dict_type) {
+            return self.scope.load_global(vm, name);
+        }
+
+        let offset = *self.lasti.borrow() - 1;
+        let globals_version = globals.version();
+        let builtins_version = builtins.version();
+        let cached = match self
+            .code
+            .cached_global(offset, globals_version, builtins_version)
+        {
+            Some(CachedGlobal::InGlobals(index)) => globals.get_by_index(index),
+            Some(CachedGlobal::InBuiltins(index)) => builtins.get_by_index(index),
+            None => None,
+        };
+        if cached.is_some() {
+            return cached;
+        }
+
+        // Comparing keys may run code that changes the dicts, which would
+        // leave the index meaningless for the versions read above.
+        let unchanged =
+            || globals.version() == globals_version && builtins.version() == builtins_version;
+        if let Some((index, value)) = globals.get_entry(name, vm).unwrap() {
+            if unchanged() {
+                self.code.cache_global(offset, globals_version, None, index);
+            }
+            return Some(value);
+        }
+        if let Some((index, value)) = builtins.get_entry(name, vm).unwrap() {
+            if unchanged() {
+                self.code
+                    .cache_global(offset, globals_version, Some(builtins_version), index);
+            }
+            return Some(value);
+        }
+        vm.get_attribute(vm.builtins.clone(), name).ok()
+    }
+
     fn execute_store_subscript(&self, vm: &VirtualMachine) -> FrameResult {
         let idx = self.pop_value();
         let obj = self.pop_value();
@@ -935,7 +1014,7 @@ impl Frame {
     }
 
     fn jump(&self, label: bytecode::Label) {
-        let target_pc = self.code.label_map[&label];
+        let target_pc = self.code.code.label_map[&label];
         #[cfg(feature = "vm-tracing-logging")]
         trace!("jump from {:?} to {:?}", self.lasti, target_pc);
         *self.lasti.borrow_mut() = target_pc;
@@ -950,15 +1029,19 @@ impl Frame {
             .pop_value()
             .downcast::<PyString>()
             .expect("qualified name to be a string");
-        let code_obj = self
+        let code_obj: PyCodeRef = self
             .pop_value()
             .downcast()
             .expect("Second to top value on the stack must be a code object");
 
         let annotations = if flags.contains(bytecode::FunctionOpArg::HAS_ANNOTATIONS) {
-            self.pop_value()
+            Some(
+                self.pop_value()
+                    .downcast::<PyDict>()
+                    .expect("Stack value for annotations expected to be a dict"),
+            )
         } else {
-            vm.ctx.new_dict().into_object()
+            None
         };
 
         let kw_only_defaults = if flags.contains(bytecode::FunctionOpArg::HAS_KW_ONLY_DEFAULTS) {
@@ -984,20 +1067,16 @@ impl Frame {
         // pop argc arguments
         // argument: name, args, globals
         let scope = self.scope.clone();
-        let func_obj = vm
-            .ctx
-            .new_function(code_obj, scope, defaults, kw_only_defaults);
-
-        let name = qualified_name.value.split('.').next_back().unwrap();
-        vm.set_attr(&func_obj, "__name__", vm.new_str(name.to_string()))?;
-        vm.set_attr(&func_obj, "__qualname__", qualified_name)?;
-        let module = self
-            .scope
-            .globals
-            .get_item_option("__name__", vm)?
-            .unwrap_or_else(|| vm.get_none());
-        vm.set_attr(&func_obj, "__module__", module)?;
-        vm.set_attr(&func_obj, "__annotations__", annotations)?;
+        let function = PyFunction::new(
+            code_obj,
+            scope,
+            defaults,
+            kw_only_defaults,
+            qualified_name,
+            annotations,
+            vm,
+        )?;
+        let func_obj = vm.ctx.new_function(function);
 
         self.push_value(func_obj);
         Ok(None)
@@ -1119,6 +1198,45 @@ impl Frame {
         Ok(None)
     }
 
+    /// Pops the arguments of a call off the stack.
+    fn pop_call_args(&self, vm: &VirtualMachine, typ: &bytecode::CallType) -> PyResult<PyFuncArgs> {
+        match typ {
+            bytecode::CallType::Positional(count) => {
+                let args: Vec<PyObjectRef> = self.pop_multiple(*count);
+                Ok(PyFuncArgs {
+                    args,
+                    kwargs: IndexMap::new(),
+                })
+            }
+            bytecode::CallType::Keyword(count) => {
+                let kwarg_names = self.pop_value();
+                let args: Vec<PyObjectRef> = self.pop_multiple(*count);
+
+                let kwarg_names = vm
+                    .extract_elements(&kwarg_names)?
+                    .iter()
+                    .map(|pyobj| objstr::get_value(pyobj))
+                    .collect();
+                Ok(PyFuncArgs::new(args, kwarg_names))
+            }
+            bytecode::CallType::Ex(has_kwargs) => {
+                let kwargs = if *has_kwargs {
+                    let kw_dict: PyDictRef =
+                        self.pop_value().downcast().expect("Kwargs must be a dict.");
+                    kw_dict
+                        .into_iter()
+                        .map(|elem| (objstr::get_value(&elem.0), elem.1))
+                        .collect()
+                } else {
+                    IndexMap::new()
+                };
+                let args = self.pop_value();
+                let args = vm.extract_elements(&args)?;
+                Ok(PyFuncArgs { args, kwargs })
+            }
+        }
+    }
+
     fn load_attr(&self, vm: &VirtualMachine, attr_name: &str) -> FrameResult {
         let parent = self.pop_value();
         let obj = vm.get_attribute(parent, attr_name)?;
@@ -1126,6 +1244,57 @@ impl Frame {
         Ok(None)
     }
 
+    fn load_method(&self, vm: &VirtualMachine, name: &str) -> FrameResult {
+        let obj = self.pop_value();
+        match self.find_method(vm, &obj, name)? {
+            Some(function) => {
+                self.push_value(function);
+                self.push_value(obj);
+            }
+            None => {
+                let attr = vm.get_attribute(obj, name)?;
+                self.push_value(vm.get_none());
+                self.push_value(attr);
+            }
+        }
+        Ok(None)
+    }
+
+    /// The function to call with `obj` prepended in place of `obj.name`, when
+    /// `object.__getattribute__` would find it as a plain function on the type
+    /// and bind it. `None` means the attribute needs the full lookup.
+    fn find_method(
+        &self,
+        vm: &VirtualMachine,
+        obj: &PyObjectRef,
+        name: &str,
+    ) -> PyResult<Option<PyObjectRef>> {
+        let cls = obj.class();
+        let getattribute = objtype::class_get_attr(&cls, "__getattribute__");
+        let default_getattribute = objtype::class_get_attr(&vm.ctx.object(), "__getattribute__");
+        match (getattribute, default_getattribute) {
+            (Some(getattribute), Some(default)) if getattribute.is(&default) => {}
+            _ => return Ok(None),
+        }
+
+        let attr = match objtype::class_get_attr(&cls, name) {
+            Some(attr) => attr,
+            None => return Ok(None),
+        };
+        let attr_class = attr.class();
+        if !attr_class.is(&vm.ctx.function_type())
+            && !attr_class.is(&vm.ctx.builtin_function_or_method_type())
+        {
+            return Ok(None);
+        }
+        if let Some(ref dict) = obj.dict {
+            if dict.get_item_option(name, vm)?.is_some() {
+                return Ok(None);
+            }
+        }
+        Ok(Some(attr))
+    }
+
     fn store_attr(&self, vm: &VirtualMachine, attr_name: &str) -> FrameResult {
         let parent = self.pop_value();
         let value = self.pop_value();
@@ -1141,7 +1310,13 @@ impl Frame {
     }
 
     pub fn get_lineno(&self) -> bytecode::Location {
-        self.code.locations[*self.lasti.borrow()].clone()
+        // Hand-written bytecode may come without source locations.
+        self.code
+            .code
+            .locations
+            .get(*self.lasti.borrow())
+            .cloned()
+            .unwrap_or_default()
     }
 
     fn push_block(&self, typ: BlockType) {
@@ -1243,3 +1418,86 @@ impl fmt::Debug for Frame {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::bytecode::CodeObject;
+    use crate::obj::{objcode, objtype};
+    use crate::vm::VirtualMachine;
+
+    /// Run a bytecode listing as a module and return the repr of its result.
+    fn run_listing(listing: &str) -> String {
+        let vm: VirtualMachine = Default::default();
+        let code: CodeObject = listing.parse().unwrap();
+        let code = objcode::verify_code(code, &vm).unwrap();
+        let code = vm.ctx.new_code_object(code);
+        let result = vm.run_code_obj(code, vm.new_scope_with_builtins()).unwrap();
+        vm.to_repr(&result).unwrap().as_str().to_string()
+    }
+
+    #[test]
+    fn test_rotate() {
+        let listing = r#"
+            code "<module>"
+                LoadConst 1
+                LoadConst 2
+                LoadConst 3
+                Rotate 3
+                BuildTuple 3 false
+                ReturnValue
+            end
+        "#;
+        assert_eq!(run_listing(listing), "(3, 1, 2)");
+    }
+
+    #[test]
+    fn test_unpack_ex() {
+        let listing = r#"
+            code "<module>"
+                LoadConst (1, 2, 3, 4)
+                UnpackEx 1 1
+                BuildTuple 3 false
+                ReturnValue
+            end
+        "#;
+        assert_eq!(run_listing(listing), "(4, [2, 3], 1)");
+    }
+
+    #[test]
+    fn test_break_leaves_loop() {
+        let listing = r#"
+            code "<module>"
+                SetupLoop L0 L2
+                LoadName "range" Local
+                LoadConst 10
+                CallFunction Positional 1
+                GetIter
+            L0:
+                ForIter L1
+                StoreName "i" Local
+                LoadName "i" Local
+                LoadConst 5
+                CompareOperation Equal
+                JumpIfFalse L0
+                Break
+            L1:
+                PopBlock
+            L2:
+                LoadName "i" Local
+                ReturnValue
+            end
+        "#;
+        assert_eq!(run_listing(listing), "5");
+    }
+
+    #[test]
+    fn test_invalid_code_is_rejected() {
+        let vm: VirtualMachine = Default::default();
+        let code: CodeObject = r#"code "f" Pop ReturnValue end"#.parse().unwrap();
+        let exception = objcode::verify_code(code, &vm).unwrap_err();
+        assert!(objtype::isinstance(
+            &exception,
+            &vm.ctx.exceptions.value_error
+        ));
+    }
+}
@@ -0,0 +1,497 @@
+//! The cycle collector.
+//!
+//! Objects are reference counted, so a group of objects referring to each
+//! other would never be freed. Every object which can be part of such a cycle
+//! (containers, functions, frames, classes and every object with a
+//! `__dict__`) is tracked here, in three generations like in CPython.
+//!
+//! A collection counts, for each tracked object, the references it gets from
+//! the other tracked objects being collected. Objects with more references
+//! than that are referenced from somewhere else (a frame's stack, a Rust
+//! variable or an older object), and so is everything reachable from them.
+//! The remaining objects are only kept alive by their cycles, which are
+//! broken by clearing the references the objects hold.
+//!
+//! This module also runs the finalizers: `__del__` methods and the callbacks
+//! of weak references. References can go away at any point, even while Rust
+//! code holds borrows a finalizer would need. So an object whose last
+//! reference goes away is queued, still alive, and its `__del__` runs before
+//! the next instruction, together with any collection that is due. Only if
+//! `__del__` didn't resurrect it, the object is freed and its weak references
+//! die.
+
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::iter;
+use std::mem;
+use std::rc::Rc;
+
+use crate::exceptions;
+use crate::obj::objlist::PyListRef;
+use crate::obj::objtype::{self, PyClassRef};
+use crate::obj::objweakproxy::PyWeakProxy;
+use crate::obj::objweakref::PyWeak;
+use crate::pyobject::{IdProtocol, ObjectFlags, PyObjectRef, TryFromObject, TypeProtocol};
+use crate::vm::VirtualMachine;
+
+pub const NUM_GENERATIONS: usize = 3;
+
+pub const DEBUG_STATS: u32 = 1;
+pub const DEBUG_COLLECTABLE: u32 = 2;
+pub const DEBUG_UNCOLLECTABLE: u32 = 4;
+pub const DEBUG_SAVEALL: u32 = 32;
+pub const DEBUG_LEAK: u32 = DEBUG_COLLECTABLE | DEBUG_UNCOLLECTABLE | DEBUG_SAVEALL;
+
+/// The youngest generation is cleaned of freed objects when it grows past
+/// this many entries, even if no collection runs.
+const MIN_PRUNE_LIMIT: usize = 1000;
+
+struct GcState {
+    generations: [Vec<PyWeak>; NUM_GENERATIONS],
+    thresholds: [usize; NUM_GENERATIONS],
+    counts: [usize; NUM_GENERATIONS],
+    enabled: bool,
+    collecting: bool,
+    debug: u32,
+    garbage: Option<PyListRef>,
+    prune_limit: usize,
+}
+
+impl Default for GcState {
+    fn default() -> Self {
+        GcState {
+            generations: Default::default(),
+            thresholds: [700, 10, 10],
+            counts: [0; NUM_GENERATIONS],
+            enabled: true,
+            collecting: false,
+            debug: 0,
+            garbage: None,
+            prune_limit: MIN_PRUNE_LIMIT,
+        }
+    }
+}
+
+enum Finalizer {
+    /// Call the `__del__` method of an object.
+    Del(PyObjectRef),
+    /// Call the callback of a weak reference whose referent was freed.
+    Callback {
+        callback: PyObjectRef,
+        weakref: PyObjectRef,
+    },
+}
+
+#[derive(Default)]
+struct FinalizerState {
+    queue: VecDeque<Finalizer>,
+    running: bool,
+    /// The weak reference objects made to each object, by the object's id.
+    weakrefs: HashMap<usize, Vec<PyWeak>>,
+}
+
+/// The collector and finalizer state. Each OS thread starts with its own;
+/// threads started from Python share the state of the thread which started
+/// them, and only use it while holding the GIL.
+#[derive(Default)]
+pub struct Heap {
+    state: RefCell<GcState>,
+    finalizers: RefCell<FinalizerState>,
+    // Checked before every instruction, so kept apart from the rest.
+    pending: Cell<bool>,
+}
+
+thread_local! {
+    static HEAP: RefCell<Rc<Heap>> = RefCell::new(Rc::new(Heap::default()));
+}
+
+/// The state used by the current thread.
+pub fn current_heap() -> Rc<Heap> {
+    HEAP.with(|heap| heap.borrow().clone())
+}
+
+/// Make the current thread use `heap`, and return the state it used before.
+pub fn set_heap(heap: Rc<Heap>) -> Rc<Heap> {
+    HEAP.with(|current| mem::replace(&mut *current.borrow_mut(), heap))
+}
+
+fn with_heap<R>(f: impl FnOnce(&Heap) -> R) -> R {
+    HEAP.with(|heap| f(&heap.borrow()))
+}
+
+/// Like `with_heap`, but does nothing once the thread is shutting down.
+fn try_with_heap(f: impl FnOnce(&Heap)) {
+    let _ = HEAP.try_with(|heap| f(&heap.borrow()));
+}
+
+fn with_state<R>(f: impl FnOnce(&mut GcState) -> R) -> R {
+    with_heap(|heap| f(&mut heap.state.borrow_mut()))
+}
+
+fn with_finalizers<R>(f: impl FnOnce(&mut FinalizerState) -> R) -> R {
+    with_heap(|heap| f(&mut heap.finalizers.borrow_mut()))
+}
+
+/// Start tracking a newly created object.
+pub fn track(obj: &PyObjectRef) {
+    // Objects created while the thread is shutting down are not tracked.
+    try_with_heap(|heap| {
+        let state = &mut *heap.state.borrow_mut();
+        let young = &mut state.generations[0];
+        young.push(PyWeak::downgrade(obj));
+        if young.len() > state.prune_limit {
+            young.retain(|weak| weak.upgrade().is_some());
+            state.prune_limit = MIN_PRUNE_LIMIT.max(2 * state.generations[0].len());
+        }
+        state.counts[0] += 1;
+        if state.enabled
+            && !state.collecting
+            && state.thresholds[0] > 0
+            && state.counts[0] > state.thresholds[0]
+        {
+            heap.pending.set(true);
+        }
+    });
+}
+
+/// Whether there are finalizers to run, or enough objects were created since
+/// the last collection to run another one.
+#[inline]
+pub fn pending() -> bool {
+    with_heap(|heap| heap.pending.get())
+}
+
+/// Run the queued finalizers, then the collection that is due, if any; the
+/// oldest generation whose count exceeds its threshold is collected.
+pub fn run_pending(vm: &VirtualMachine) {
+    with_heap(|heap| heap.pending.set(false));
+    run_finalizers(vm);
+    let generation = with_state(|state| {
+        (0..NUM_GENERATIONS)
+            .rev()
+            .find(|&i| state.thresholds[i] > 0 && state.counts[i] > state.thresholds[i])
+    });
+    if let Some(generation) = generation {
+        collect(generation, vm);
+    }
+}
+
+/// Collect the given generation and all younger ones, returning the number
+/// of unreachable objects found.
+pub fn collect(generation: usize, vm: &VirtualMachine) -> usize {
+    let tracked = with_state(|state| {
+        if state.collecting {
+            return None;
+        }
+        state.collecting = true;
+        if generation + 1 < NUM_GENERATIONS {
+            state.counts[generation + 1] += 1;
+        }
+        for count in &mut state.counts[..=generation] {
+            *count = 0;
+        }
+        let mut tracked = vec![];
+        for young in &mut state.generations[..=generation] {
+            tracked.append(young);
+        }
+        Some(tracked)
+    });
+    let tracked = match tracked {
+        Some(tracked) => tracked,
+        None => return 0,
+    };
+    let debug = get_debug();
+    if debug & DEBUG_STATS != 0 {
+        eprintln!("gc: collecting generation {}...", generation);
+    }
+
+    let objects: Vec<PyObjectRef> = tracked.iter().filter_map(PyWeak::upgrade).collect();
+    drop(tracked);
+    let mut reachable = mark_reachable(&objects);
+
+    // Finalizers run before anything is cleared, so they see intact objects.
+    // They can make the objects reachable again, so look once more after.
+    let mut finalized = false;
+    for (obj, _) in objects.iter().zip(&reachable).filter(|(_, r)| !**r) {
+        let flags = obj.flags.get();
+        if obj.dict.is_some() && !flags.contains(ObjectFlags::FINALIZED) && has_finalizer(&obj.typ)
+        {
+            obj.flags.set(flags | ObjectFlags::FINALIZED);
+            call_finalizer(obj, vm);
+            finalized = true;
+        }
+    }
+    if finalized {
+        reachable = mark_reachable(&objects);
+    }
+    let (survivors, unreachable): (Vec<_>, Vec<_>) = objects
+        .iter()
+        .zip(reachable)
+        .partition(|(_, reachable)| *reachable);
+
+    let older = (generation + 1).min(NUM_GENERATIONS - 1);
+    with_state(|state| {
+        let survivors = survivors.iter().map(|(obj, _)| PyWeak::downgrade(obj));
+        state.generations[older].extend(survivors)
+    });
+
+    let unreachable: Vec<&PyObjectRef> = unreachable.into_iter().map(|(obj, _)| obj).collect();
+    if debug & DEBUG_COLLECTABLE != 0 {
+        for obj in &unreachable {
+            eprintln!("gc: collectable <{} {:#x}>", obj.class().name, obj.get_id());
+        }
+    }
+    if debug & DEBUG_SAVEALL != 0 {
+        let garbage = garbage(vm);
+        let mut elements = garbage.elements.borrow_mut();
+        elements.extend(unreachable.iter().map(|&obj| obj.clone()));
+    } else {
+        for obj in &unreachable {
+            obj.payload.gc_clear();
+        }
+    }
+    if debug & DEBUG_STATS != 0 {
+        eprintln!(
+            "gc: done, {} unreachable, {} tracked objects survived",
+            unreachable.len(),
+            survivors.len()
+        );
+    }
+
+    let found = unreachable.len();
+    drop(survivors);
+    drop(unreachable);
+    // Dropping the last references frees the objects whose cycles were broken.
+    drop(objects);
+    with_state(|state| state.collecting = false);
+    found
+}
+
+/// Find out which objects are reachable from outside of `objects`; the others
+/// are only referenced by each other.
+fn mark_reachable(objects: &[PyObjectRef]) -> Vec<bool> {
+    let index: HashMap<usize, usize> = objects
+        .iter()
+        .enumerate()
+        .map(|(i, obj)| (obj.get_id(), i))
+        .collect();
+
+    // The references not accounted for by the objects being collected; the
+    // one held by `objects` itself is left out from the start.
+    let mut external_refs: Vec<usize> = objects
+        .iter()
+        .map(|obj| PyObjectRef::strong_count(obj) - 1)
+        .collect();
+    for obj in objects {
+        obj.traverse(&mut |child| {
+            if let Some(&i) = index.get(&child.get_id()) {
+                external_refs[i] = external_refs[i].saturating_sub(1);
+            }
+        });
+    }
+
+    let mut reachable = vec![false; objects.len()];
+    let mut pending = vec![];
+    for (i, &refs) in external_refs.iter().enumerate() {
+        if refs > 0 {
+            reachable[i] = true;
+            pending.push(i);
+        }
+    }
+    while let Some(i) = pending.pop() {
+        objects[i].traverse(&mut |child| {
+            if let Some(&j) = index.get(&child.get_id()) {
+                if !reachable[j] {
+                    reachable[j] = true;
+                    pending.push(j);
+                }
+            }
+        });
+    }
+
+    reachable
+}
+
+pub fn is_enabled() -> bool {
+    with_state(|state| state.enabled)
+}
+
+pub fn set_enabled(enabled: bool) {
+    with_state(|state| state.enabled = enabled);
+}
+
+pub fn get_debug() -> u32 {
+    with_state(|state| state.debug)
+}
+
+pub fn set_debug(flags: u32) {
+    with_state(|state| state.debug = flags);
+}
+
+pub fn get_thresholds() -> [usize; NUM_GENERATIONS] {
+    with_state(|state| state.thresholds)
+}
+
+pub fn set_thresholds(thresholds: [usize; NUM_GENERATIONS]) {
+    with_state(|state| state.thresholds = thresholds);
+}
+
+pub fn get_counts() -> [usize; NUM_GENERATIONS] {
+    with_state(|state| state.counts)
+}
+
+/// The live objects of the given generation, or of all generations.
+pub fn get_objects(generation: Option<usize>) -> Vec<PyObjectRef> {
+    let generations = match generation {
+        Some(generation) => generation..generation + 1,
+        None => 0..NUM_GENERATIONS,
+    };
+    with_state(|state| {
+        state.generations[generations]
+            .iter()
+            .flatten()
+            .filter_map(PyWeak::upgrade)
+            .collect()
+    })
+}
+
+/// The tracked objects holding a reference to any of `objs`.
+pub fn get_referrers(objs: &[PyObjectRef]) -> Vec<PyObjectRef> {
+    let ids: HashSet<usize> = objs.iter().map(|obj| obj.get_id()).collect();
+    get_objects(None)
+        .into_iter()
+        .filter(|referrer| {
+            let mut refers = false;
+            referrer.traverse(&mut |child| refers = refers || ids.contains(&child.get_id()));
+            refers
+        })
+        .collect()
+}
+
+/// The objects any of `objs` hold a reference to.
+pub fn get_referents(objs: &[PyObjectRef]) -> Vec<PyObjectRef> {
+    let mut referents = vec![];
+    for obj in objs {
+        obj.traverse(&mut |child| referents.push(child.clone()));
+    }
+    referents
+}
+
+/// The list of objects found unreachable while `DEBUG_SAVEALL` is set.
+pub fn garbage(vm: &VirtualMachine) -> PyListRef {
+    if let Some(garbage) = with_state(|state| state.garbage.clone()) {
+        return garbage;
+    }
+    let garbage = PyListRef::try_from_object(vm, vm.ctx.new_list(vec![])).unwrap();
+    with_state(|state| state.garbage = Some(garbage.clone()));
+    garbage
+}
+
+/// Whether instances of the class have a `__del__` method.
+///
+/// This is used while objects are dropped, when the attributes of a class
+/// might be borrowed; those classes are taken to have no finalizer.
+pub fn has_finalizer(cls: &PyClassRef) -> bool {
+    iter::once(cls)
+        .chain(cls.mro.iter())
+        .any(|cls| cls.has_own_attr("__del__"))
+}
+
+/// Queue a call to the `__del__` method of an object whose last reference
+/// went away. The queue keeps the object alive until then.
+pub fn schedule_finalizer(obj: PyObjectRef) {
+    obj.flags.set(obj.flags.get() | ObjectFlags::FINALIZED);
+    let mut obj = Some(obj);
+    try_with_heap(|heap| {
+        if let Ok(mut finalizers) = heap.finalizers.try_borrow_mut() {
+            finalizers
+                .queue
+                .push_back(Finalizer::Del(obj.take().unwrap()));
+            heap.pending.set(true);
+        }
+    });
+    // Without a queue to wait in, the object is freed unfinalized.
+    drop(obj);
+}
+
+/// Remember a weak reference object made to `referent`.
+pub fn track_weakref(referent: &PyObjectRef, weakref: &PyObjectRef) {
+    referent
+        .flags
+        .set(referent.flags.get() | ObjectFlags::HAS_WEAKREFS);
+    with_finalizers(|finalizers| {
+        let weakrefs = finalizers.weakrefs.entry(referent.get_id()).or_default();
+        weakrefs.retain(|weakref| weakref.upgrade().is_some());
+        weakrefs.push(PyWeak::downgrade(weakref));
+    });
+}
+
+/// The live weak reference objects made to `referent`.
+pub fn get_weakrefs(referent: &PyObjectRef) -> Vec<PyObjectRef> {
+    if !referent.flags.get().contains(ObjectFlags::HAS_WEAKREFS) {
+        return vec![];
+    }
+    with_finalizers(|finalizers| {
+        finalizers
+            .weakrefs
+            .get(&referent.get_id())
+            .map_or_else(Vec::new, |weakrefs| {
+                weakrefs.iter().filter_map(PyWeak::upgrade).collect()
+            })
+    })
+}
+
+/// Queue the callbacks of the weak references to an object that was dropped.
+pub fn referent_dropped(id: usize) {
+    try_with_heap(|heap| {
+        let finalizers = &mut *heap.finalizers.borrow_mut();
+        let weakrefs = match finalizers.weakrefs.remove(&id) {
+            Some(weakrefs) => weakrefs,
+            None => return,
+        };
+        // The newest weak reference gets called first, like in CPython.
+        for weakref in weakrefs.iter().rev().filter_map(PyWeak::upgrade) {
+            if let Some(callback) = weakref_callback(&weakref) {
+                let finalizer = Finalizer::Callback { callback, weakref };
+                finalizers.queue.push_back(finalizer);
+                heap.pending.set(true);
+            }
+        }
+    });
+}
+
+fn weakref_callback(weakref: &PyObjectRef) -> Option<PyObjectRef> {
+    if let Some(weak) = weakref.payload::<PyWeak>() {
+        weak.callback().cloned()
+    } else {
+        let proxy = weakref.payload::<PyWeakProxy>()?;
+        proxy.callback().cloned()
+    }
+}
+
+fn run_finalizers(vm: &VirtualMachine) {
+    // Finalizers run by other finalizers are left to the outermost loop.
+    let running = with_finalizers(|f| mem::replace(&mut f.running, true));
+    if running {
+        return;
+    }
+    while let Some(finalizer) = with_finalizers(|f| f.queue.pop_front()) {
+        match finalizer {
+            Finalizer::Del(obj) => call_finalizer(&obj, vm),
+            Finalizer::Callback { callback, weakref } => {
+                if let Err(exc) = vm.invoke(callback.clone(), vec![weakref]) {
+                    exceptions::print_unraisable(vm, &exc, &callback);
+                }
+            }
+        }
+    }
+    with_finalizers(|f| f.running = false);
+}
+
+fn call_finalizer(obj: &PyObjectRef, vm: &VirtualMachine) {
+    if let Some(del) = objtype::class_get_attr(&obj.class(), "__del__") {
+        if let Err(exc) = vm.invoke(del.clone(), vec![obj.clone()]) {
+            exceptions::print_unraisable(vm, &exc, &del);
+        }
+    }
+}
@@ -0,0 +1,149 @@
+//! The global interpreter lock.
+//!
+//! Objects are reference counted with `Rc` and mutated through `RefCell`s, so
+//! only one thread at a time may touch them. The lock is taken for the first
+//! time when a thread is started from Python; until then the interpreter runs
+//! without it, and so do threads running a `VirtualMachine` of their own.
+//!
+//! A thread holding the lock releases it while it blocks, and hands it over
+//! between two instructions when another thread has been waiting for it for
+//! longer than `SWITCH_INTERVAL`.
+
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+
+/// How long a thread waits for the lock before asking for it to be handed
+/// over.
+pub const SWITCH_INTERVAL: Duration = Duration::from_millis(5);
+
+struct GilState {
+    locked: bool,
+    waiters: usize,
+    /// Incremented every time the lock is taken.
+    switches: u64,
+}
+
+struct Gil {
+    state: Mutex<GilState>,
+    /// Notified when the lock is released.
+    released: Condvar,
+    /// Notified when the lock is taken.
+    taken: Condvar,
+}
+
+lazy_static! {
+    static ref GIL: Gil = Gil {
+        state: Mutex::new(GilState {
+            locked: false,
+            waiters: 0,
+            switches: 0,
+        }),
+        released: Condvar::new(),
+        taken: Condvar::new(),
+    };
+}
+
+// Checked before every instruction, so kept apart from the rest.
+static DROP_REQUEST: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    static HOLDING: Cell<bool> = Cell::new(false);
+}
+
+/// Whether the current thread holds the lock.
+pub fn is_held() -> bool {
+    HOLDING.with(Cell::get)
+}
+
+/// Take the lock in the current thread, unless it already holds it. Called
+/// before starting the first thread.
+pub fn ensure_held() {
+    if !is_held() {
+        acquire();
+    }
+}
+
+/// Wait for the lock and take it.
+pub fn acquire() {
+    let mut state = GIL.state.lock().unwrap();
+    if state.locked {
+        state.waiters += 1;
+        while state.locked {
+            let switches = state.switches;
+            let (guard, timeout) = GIL.released.wait_timeout(state, SWITCH_INTERVAL).unwrap();
+            state = guard;
+            if timeout.timed_out() && state.locked && state.switches == switches {
+                DROP_REQUEST.store(true, Ordering::Relaxed);
+            }
+        }
+        state.waiters -= 1;
+    }
+    state.locked = true;
+    state.switches += 1;
+    DROP_REQUEST.store(false, Ordering::Relaxed);
+    drop(state);
+    GIL.taken.notify_all();
+    HOLDING.with(|holding| holding.set(true));
+}
+
+/// Release the lock held by the current thread.
+pub fn release() {
+    HOLDING.with(|holding| holding.set(false));
+    GIL.state.lock().unwrap().locked = false;
+    GIL.released.notify_one();
+}
+
+/// Whether a waiting thread asked for the lock to be handed over.
+#[inline]
+pub fn drop_requested() -> bool {
+    DROP_REQUEST.load(Ordering::Relaxed)
+}
+
+/// Hand the lock over to a waiting thread, and wait to get it back.
+pub fn switch() {
+    if !is_held() {
+        return;
+    }
+    HOLDING.with(|holding| holding.set(false));
+    let mut state = GIL.state.lock().unwrap();
+    state.locked = false;
+    GIL.released.notify_one();
+    let switches = state.switches;
+    while state.waiters > 0 && state.switches == switches {
+        state = GIL.taken.wait(state).unwrap();
+    }
+    drop(state);
+    acquire();
+}
+
+/// Run `f` with the lock released, so that other threads can run Python code
+/// meanwhile. `f` must be `Send`, which keeps it from touching any object.
+pub fn allow_threads<F, R>(f: F) -> R
+where
+    F: FnOnce() -> R + Send,
+    R: Send,
+{
+    struct Reacquire;
+
+    impl Drop for Reacquire {
+        fn drop(&mut self) {
+            acquire();
+        }
+    }
+
+    if is_held() {
+        release();
+        let _reacquire = Reacquire;
+        f()
+    } else {
+        f()
+    }
+}
+
+/// Moves values that are not `Send` to another thread. The receiving thread
+/// must hold the lock while it uses or drops them.
+pub struct GilSend<T>(pub T);
+
+unsafe impl<T> Send for GilSend<T> {}
@@ -43,7 +43,10 @@ pub fn import_frozen(vm: &VirtualMachine, module_name: &str) -> PyResult {
         .borrow()
         .get(module_name)
         .ok_or_else(|| vm.new_import_error(format!("Cannot import frozen module {}", module_name)))
-        .and_then(|frozen| import_codeobj(vm, module_name, frozen.code.clone(), false))
+        .and_then(|frozen| {
+            let code = objcode::verify_code(frozen.code.clone(), vm)?;
+            import_codeobj(vm, module_name, code, false)
+        })
 }
 
 pub fn import_builtin(vm: &VirtualMachine, module_name: &str) -> PyResult {
@@ -84,6 +87,7 @@ pub fn import_codeobj(
 ) -> PyResult {
     let attrs = vm.ctx.new_dict();
     attrs.set_item("__name__", vm.new_str(module_name.to_string()), vm)?;
+    attrs.set_item("__doc__", vm.get_none(), vm)?;
     if set_file_attr {
         attrs.set_item("__file__", vm.new_str(code_obj.source_path.to_owned()), vm)?;
     }
@@ -59,6 +59,8 @@ pub mod format;
 mod frame;
 mod frozen;
 pub mod function;
+pub mod gc;
+pub mod gil;
 pub mod import;
 pub mod obj;
 pub mod py_serde;
@@ -66,6 +68,7 @@ mod pyhash;
 pub mod pyobject;
 pub mod scope;
 pub mod stdlib;
+pub mod surrogates;
 mod sysmodule;
 mod traceback;
 pub mod util;
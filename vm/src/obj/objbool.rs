@@ -1,5 +1,3 @@
-use num_traits::Zero;
-
 use crate::function::PyFuncArgs;
 use crate::pyobject::{IntoPyObject, PyContext, PyObjectRef, PyResult, TryFromObject};
 use crate::vm::VirtualMachine;
@@ -28,7 +26,7 @@ pub fn boolval(vm: &VirtualMachine, obj: PyObjectRef) -> PyResult<bool> {
             let method = method_or_err?;
             let bool_obj = vm.invoke(method, PyFuncArgs::default())?;
             match bool_obj.payload::<PyInt>() {
-                Some(int_obj) => !int_obj.as_bigint().is_zero(),
+                Some(int_obj) => int_obj.as_i64() != Some(0),
                 None => return Err(vm.new_type_error(String::from(""))),
             }
         }
@@ -70,7 +68,7 @@ pub fn not(vm: &VirtualMachine, obj: &PyObjectRef) -> PyResult {
 
 // Retrieve inner int value:
 pub fn get_value(obj: &PyObjectRef) -> bool {
-    !obj.payload::<PyInt>().unwrap().as_bigint().is_zero()
+    obj.payload::<PyInt>().unwrap().as_i64() != Some(0)
 }
 
 fn bool_repr(vm: &VirtualMachine, args: PyFuncArgs) -> Result<PyObjectRef, PyObjectRef> {
@@ -89,7 +87,7 @@ fn bool_format(
     format_spec: PyStringRef,
     vm: &VirtualMachine,
 ) -> PyResult<PyStringRef> {
-    if format_spec.value.is_empty() {
+    if format_spec.as_str().is_empty() {
         vm.to_str(&obj)
     } else {
         Err(vm.new_type_error("unsupported format string passed to bool.__format__".to_string()))
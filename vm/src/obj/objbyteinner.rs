@@ -8,6 +8,7 @@ use crate::pyobject::PyRef;
 use crate::pyobject::PyValue;
 use crate::pyobject::TryFromObject;
 use crate::pyobject::{PyIterable, PyObjectRef};
+use crate::surrogates;
 use core::convert::TryFrom;
 use core::ops::Range;
 use num_bigint::BigInt;
@@ -72,6 +73,8 @@ pub struct ByteInnerNewOptions {
     val_option: OptionalArg<PyObjectRef>,
     #[pyarg(positional_or_keyword, optional = true)]
     encoding: OptionalArg<PyStringRef>,
+    #[pyarg(positional_or_keyword, optional = true)]
+    errors: OptionalArg<PyStringRef>,
 }
 
 //same algorithm as cpython
@@ -93,13 +96,21 @@ pub fn normalize_encoding(encoding: &str) -> String {
     res
 }
 
+fn lookup_encoding(encoding: &str, vm: &VirtualMachine) -> PyResult<surrogates::Encoding> {
+    surrogates::Encoding::from_normalized_name(&normalize_encoding(encoding)).ok_or_else(|| {
+        vm.new_value_error(format!("unknown encoding: {}", encoding)) // should be lookup error
+    })
+}
+
 impl ByteInnerNewOptions {
     pub fn get_value(self, vm: &VirtualMachine) -> PyResult<PyByteInner> {
         // First handle bytes(string, encoding[, errors])
         if let OptionalArg::Present(enc) = self.encoding {
             if let OptionalArg::Present(eval) = self.val_option {
                 if let Ok(input) = eval.downcast::<PyString>() {
-                    let inner = PyByteInner::from_string(&input.value, enc.as_str(), vm)?;
+                    let errors = self.errors.into_option();
+                    let errors = errors.as_ref().map_or("strict", |e| e.as_str());
+                    let inner = PyByteInner::from_string(&input, enc.as_str(), errors, vm)?;
                     return Ok(inner);
                 } else {
                     return Err(vm.new_type_error("encoding without a string argument".to_string()));
@@ -320,18 +331,27 @@ impl ByteInnerSplitlinesOptions {
 }
 
 impl PyByteInner {
-    pub fn from_string(value: &str, encoding: &str, vm: &VirtualMachine) -> PyResult<Self> {
-        let normalized = normalize_encoding(encoding);
-        if normalized == "utf_8" || normalized == "utf8" || normalized == "u8" {
-            Ok(PyByteInner {
-                elements: value.as_bytes().to_vec(),
-            })
-        } else {
-            // TODO: different encoding
-            Err(
-                vm.new_value_error(format!("unknown encoding: {}", encoding)), // should be lookup error
-            )
-        }
+    pub fn from_string(
+        value: &PyString,
+        encoding: &str,
+        errors: &str,
+        vm: &VirtualMachine,
+    ) -> PyResult<Self> {
+        let codec = lookup_encoding(encoding, vm)?;
+        let elements =
+            surrogates::encode(value, codec, surrogates::ErrorHandler::from_name(errors))
+                .map_err(|err| err.into_exception(errors, vm))?;
+        Ok(PyByteInner { elements })
+    }
+
+    pub fn decode(&self, encoding: &str, errors: &str, vm: &VirtualMachine) -> PyResult<PyString> {
+        let codec = lookup_encoding(encoding, vm)?;
+        surrogates::decode(
+            &self.elements,
+            codec,
+            surrogates::ErrorHandler::from_name(errors),
+        )
+        .map_err(|err| err.into_exception(errors, vm))
     }
 
     pub fn repr(&self) -> PyResult<String> {
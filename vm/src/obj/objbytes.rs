@@ -1,6 +1,6 @@
 use crate::obj::objint::PyIntRef;
 use crate::obj::objslice::PySliceRef;
-use crate::obj::objstr::PyStringRef;
+use crate::obj::objstr::{PyString, PyStringRef};
 use crate::obj::objtuple::PyTupleRef;
 use crate::pyhash;
 
@@ -46,9 +46,14 @@ impl PyBytes {
         }
     }
 
-    pub fn from_string(value: &str, encoding: &str, vm: &VirtualMachine) -> PyResult<Self> {
+    pub fn from_string(
+        value: &PyString,
+        encoding: &str,
+        errors: &str,
+        vm: &VirtualMachine,
+    ) -> PyResult<Self> {
         Ok(PyBytes {
-            inner: PyByteInner::from_string(value, encoding, vm)?,
+            inner: PyByteInner::from_string(value, encoding, errors, vm)?,
         })
     }
 
@@ -110,6 +115,11 @@ impl PyBytesRef {
         self.inner.len()
     }
 
+    #[pymethod(name = "__bool__")]
+    fn bool(self, _vm: &VirtualMachine) -> bool {
+        !self.inner.is_empty()
+    }
+
     #[pymethod(name = "__eq__")]
     fn eq(self, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
         self.inner.eq(other, vm)
@@ -228,6 +238,21 @@ impl PyBytesRef {
         self.inner.hex(vm)
     }
 
+    #[pymethod(name = "decode")]
+    fn decode(
+        self,
+        encoding: OptionalArg<PyStringRef>,
+        errors: OptionalArg<PyStringRef>,
+        vm: &VirtualMachine,
+    ) -> PyResult<PyString> {
+        let errors = errors.into_option();
+        let errors = errors.as_ref().map_or("strict", |e| e.as_str());
+        match encoding.into_option() {
+            Some(encoding) => self.inner.decode(encoding.as_str(), errors, vm),
+            None => self.inner.decode("utf-8", errors, vm),
+        }
+    }
+
     fn fromhex(string: PyStringRef, vm: &VirtualMachine) -> PyResult {
         Ok(vm.ctx.new_bytes(PyByteInner::fromhex(string.as_str(), vm)?))
     }
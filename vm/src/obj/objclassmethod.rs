@@ -31,10 +31,15 @@ pub type PyClassMethodRef = PyRef<PyClassMethod>;
 
 impl PyValue for PyClassMethod {
     const HAVE_DICT: bool = true;
+    const HAVE_GC: bool = true;
 
     fn class(vm: &VirtualMachine) -> PyClassRef {
         vm.ctx.classmethod_type()
     }
+
+    fn traverse(&self, visit: &mut dyn FnMut(&PyObjectRef)) {
+        visit(&self.callable);
+    }
 }
 
 #[pyimpl]
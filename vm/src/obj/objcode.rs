@@ -2,23 +2,95 @@
 
 */
 
+use std::cell::RefCell;
 use std::fmt;
 
 use crate::bytecode;
 use crate::obj::objtype::PyClassRef;
 use crate::pyobject::{IdProtocol, PyContext, PyObjectRef, PyRef, PyResult, PyValue};
+use crate::verify;
 use crate::vm::VirtualMachine;
 
 pub type PyCodeRef = PyRef<PyCode>;
 
 pub struct PyCode {
     pub code: bytecode::CodeObject,
+    /// Where each `LoadName` found its name in the globals or builtins the
+    /// last time it ran, by instruction offset.
+    global_cache: RefCell<Vec<Option<GlobalCacheEntry>>>,
+}
+
+#[derive(Clone, Copy)]
+struct GlobalCacheEntry {
+    globals_version: u64,
+    /// `None` if the name was found in the globals, so that the builtins
+    /// don't matter.
+    builtins_version: Option<u64>,
+    index: usize,
+}
+
+/// The index of the dict entry a `LoadName` found last time.
+pub enum CachedGlobal {
+    InGlobals(usize),
+    InBuiltins(usize),
 }
 
 impl PyCode {
     pub fn new(code: bytecode::CodeObject) -> PyCode {
-        PyCode { code }
+        PyCode {
+            code,
+            global_cache: RefCell::new(Vec::new()),
+        }
     }
+
+    /// Where the `LoadName` at `offset` found its name last time, if the
+    /// globals and builtins it looked in are still the same.
+    pub fn cached_global(
+        &self,
+        offset: usize,
+        globals_version: u64,
+        builtins_version: u64,
+    ) -> Option<CachedGlobal> {
+        let entry = (*self.global_cache.borrow().get(offset)?)?;
+        if entry.globals_version != globals_version {
+            return None;
+        }
+        match entry.builtins_version {
+            None => Some(CachedGlobal::InGlobals(entry.index)),
+            Some(version) if version == builtins_version => {
+                Some(CachedGlobal::InBuiltins(entry.index))
+            }
+            Some(_) => None,
+        }
+    }
+
+    pub fn cache_global(
+        &self,
+        offset: usize,
+        globals_version: u64,
+        builtins_version: Option<u64>,
+        index: usize,
+    ) {
+        let mut cache = self.global_cache.borrow_mut();
+        if cache.is_empty() {
+            cache.resize(self.code.instructions.len(), None);
+        }
+        cache[offset] = Some(GlobalCacheEntry {
+            globals_version,
+            builtins_version,
+            index,
+        });
+    }
+}
+
+/// Verify code that did not come from the compiler, like marshalled or
+/// frozen code, so that running it can not crash a frame.
+pub fn verify_code(
+    mut code: bytecode::CodeObject,
+    vm: &VirtualMachine,
+) -> PyResult<bytecode::CodeObject> {
+    verify::verify(&mut code).map_err(|err| vm.new_value_error(err.to_string()))?;
+    Ok(code)
 }
 
 impl fmt::Debug for PyCode {
@@ -67,10 +139,15 @@ impl PyCodeRef {
     }
 
     fn co_consts(self, vm: &VirtualMachine) -> PyObjectRef {
-        let consts = self
-            .code
-            .get_constants()
-            .map(|x| vm.ctx.unwrap_constant(x))
+        // Like CPython, the first constant is the docstring:
+        let doc = match &self.code.doc {
+            Some(doc) => vm
+                .ctx
+                .new_str_with_stand_ins(doc.to_string(), self.code.doc_stand_ins),
+            None => vm.get_none(),
+        };
+        let consts = std::iter::once(doc)
+            .chain(self.code.get_constants().map(|x| vm.ctx.unwrap_constant(x)))
             .collect();
         vm.ctx.new_tuple(consts)
     }
@@ -78,6 +155,62 @@ impl PyCodeRef {
     fn co_name(self, _vm: &VirtualMachine) -> String {
         self.code.obj_name.clone()
     }
+
+    fn co_flags(self, _vm: &VirtualMachine) -> u32 {
+        self.code.co_flags().bits()
+    }
+
+    fn co_stacksize(self, _vm: &VirtualMachine) -> usize {
+        self.code.max_stackdepth
+    }
+
+    fn co_varnames(self, vm: &VirtualMachine) -> PyObjectRef {
+        let varnames = self
+            .code
+            .varnames()
+            .into_iter()
+            .map(|name| vm.new_str(name.to_string()))
+            .collect();
+        vm.ctx.new_tuple(varnames)
+    }
+
+    fn co_cellvars(self, vm: &VirtualMachine) -> PyObjectRef {
+        let cellvars = self
+            .code
+            .cellvars
+            .iter()
+            .map(|name| vm.new_str(name.clone()))
+            .collect();
+        vm.ctx.new_tuple(cellvars)
+    }
+
+    fn co_freevars(self, vm: &VirtualMachine) -> PyObjectRef {
+        let freevars = self
+            .code
+            .freevars
+            .iter()
+            .map(|name| vm.new_str(name.clone()))
+            .collect();
+        vm.ctx.new_tuple(freevars)
+    }
+
+    fn co_nlocals(self, _vm: &VirtualMachine) -> usize {
+        self.code.varnames().len()
+    }
+
+    fn co_names(self, vm: &VirtualMachine) -> PyObjectRef {
+        let names = self
+            .code
+            .names()
+            .into_iter()
+            .map(|name| vm.new_str(name.to_string()))
+            .collect();
+        vm.ctx.new_tuple(names)
+    }
+
+    fn co_lnotab(self, vm: &VirtualMachine) -> PyObjectRef {
+        vm.ctx.new_bytes(self.code.lnotab())
+    }
 }
 
 pub fn init(context: &PyContext) {
@@ -86,10 +219,18 @@ pub fn init(context: &PyContext) {
         "__repr__" => context.new_rustfunc(PyCodeRef::repr),
 
         "co_argcount" => context.new_property(PyCodeRef::co_argcount),
+        "co_cellvars" => context.new_property(PyCodeRef::co_cellvars),
         "co_consts" => context.new_property(PyCodeRef::co_consts),
         "co_filename" => context.new_property(PyCodeRef::co_filename),
         "co_firstlineno" => context.new_property(PyCodeRef::co_firstlineno),
+        "co_flags" => context.new_property(PyCodeRef::co_flags),
+        "co_freevars" => context.new_property(PyCodeRef::co_freevars),
         "co_kwonlyargcount" => context.new_property(PyCodeRef::co_kwonlyargcount),
+        "co_lnotab" => context.new_property(PyCodeRef::co_lnotab),
         "co_name" => context.new_property(PyCodeRef::co_name),
+        "co_names" => context.new_property(PyCodeRef::co_names),
+        "co_nlocals" => context.new_property(PyCodeRef::co_nlocals),
+        "co_stacksize" => context.new_property(PyCodeRef::co_stacksize),
+        "co_varnames" => context.new_property(PyCodeRef::co_varnames),
     });
 }
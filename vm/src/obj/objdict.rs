@@ -1,5 +1,6 @@
 use std::cell::{Cell, RefCell};
 use std::fmt;
+use std::mem;
 
 use crate::function::{KwArgs, OptionalArg};
 use crate::pyobject::{
@@ -31,9 +32,29 @@ impl fmt::Debug for PyDict {
 }
 
 impl PyValue for PyDict {
+    const HAVE_GC: bool = true;
+
     fn class(vm: &VirtualMachine) -> PyClassRef {
         vm.ctx.dict_type()
     }
+
+    fn traverse(&self, visit: &mut dyn FnMut(&PyObjectRef)) {
+        if let Ok(entries) = self.entries.try_borrow() {
+            let mut position = 0;
+            while let Some((key, value)) = entries.next_entry(&mut position) {
+                visit(key);
+                visit(value);
+            }
+        }
+    }
+
+    fn clear_refs(&self) {
+        let entries = self
+            .entries
+            .try_borrow_mut()
+            .map(|mut e| mem::take(&mut *e));
+        drop(entries);
+    }
 }
 
 // Python dict methods:
@@ -155,7 +176,7 @@ impl PyDictRef {
             for (key, value) in self {
                 let key_repr = vm.to_repr(&key)?;
                 let value_repr = vm.to_repr(&value)?;
-                str_parts.push(format!("{}: {}", key_repr.value, value_repr.value));
+                str_parts.push(format!("{}: {}", key_repr.as_str(), value_repr.as_str()));
             }
 
             format!("{{{}}}", str_parts.join(", "))
@@ -316,6 +337,27 @@ impl PyDictRef {
     pub fn size(&self) -> dictdatatype::DictSize {
         self.entries.borrow().size()
     }
+
+    /// See `Dict::version`.
+    pub fn version(&self) -> u64 {
+        self.entries.borrow().version()
+    }
+
+    /// Look `key` up without going through `__getitem__`, and give the index
+    /// of its entry too. See `Dict::get_entry`.
+    pub fn get_entry<T: IntoPyObject>(
+        &self,
+        key: T,
+        vm: &VirtualMachine,
+    ) -> PyResult<Option<(usize, PyObjectRef)>> {
+        let key = key.into_pyobject(vm)?;
+        self.entries.borrow().get_entry(vm, &key)
+    }
+
+    /// See `Dict::get_by_index`.
+    pub fn get_by_index(&self, index: usize) -> Option<PyObjectRef> {
+        self.entries.borrow().get_by_index(index)
+    }
 }
 
 impl ItemProtocol for PyDictRef {
@@ -4,8 +4,9 @@
 
 use super::objcode::PyCodeRef;
 use super::objdict::PyDictRef;
+use crate::bytecode;
 use crate::frame::FrameRef;
-use crate::pyobject::{PyContext, PyObjectRef, PyResult};
+use crate::pyobject::{IdProtocol, PyContext, PyObjectRef, PyResult};
 use crate::vm::VirtualMachine;
 
 pub fn init(context: &PyContext) {
@@ -17,6 +18,7 @@ pub fn init(context: &PyContext) {
         "f_code" => context.new_property(FrameRef::fcode),
         "f_back" => context.new_property(FrameRef::f_back),
         "f_lasti" => context.new_property(FrameRef::f_lasti),
+        "f_lineno" => context.new_property(FrameRef::f_lineno),
     });
 }
 
@@ -38,16 +40,32 @@ impl FrameRef {
         self.scope.get_locals()
     }
 
-    fn fcode(self, vm: &VirtualMachine) -> PyCodeRef {
-        vm.ctx.new_code_object(self.code.clone())
+    fn fcode(self, _vm: &VirtualMachine) -> PyCodeRef {
+        self.code.clone()
     }
 
-    fn f_back(self, vm: &VirtualMachine) -> PyObjectRef {
-        // TODO: how to retrieve the upper stack frame??
-        vm.ctx.none()
+    fn f_back(self, vm: &VirtualMachine) -> Option<FrameRef> {
+        // Only frames on the call stack have a caller:
+        let frames = vm.frames.borrow();
+        let position = frames.iter().position(|frame| frame.is(&self))?;
+        if position > 0 {
+            Some(frames[position - 1].clone())
+        } else {
+            None
+        }
     }
 
     fn f_lasti(self, vm: &VirtualMachine) -> PyObjectRef {
         vm.ctx.new_int(*self.lasti.borrow())
     }
+
+    fn f_lineno(self, _vm: &VirtualMachine) -> usize {
+        // lasti already points past the instruction being executed:
+        let lasti = self.lasti.borrow().saturating_sub(1);
+        self.code
+            .code
+            .locations
+            .get(lasti)
+            .map_or(0, bytecode::Location::row)
+    }
 }
@@ -1,9 +1,15 @@
+use std::cell::RefCell;
+
 use crate::function::{Args, KwArgs};
 use crate::obj::objcode::PyCodeRef;
 use crate::obj::objdict::PyDictRef;
+use crate::obj::objproperty::PropertyBuilder;
+use crate::obj::objstr::{PyString, PyStringRef};
 use crate::obj::objtuple::PyTupleRef;
 use crate::obj::objtype::PyClassRef;
-use crate::pyobject::{IdProtocol, PyContext, PyObjectRef, PyRef, PyResult, PyValue, TypeProtocol};
+use crate::pyobject::{
+    IdProtocol, ItemProtocol, PyContext, PyObjectRef, PyRef, PyResult, PyValue, TypeProtocol,
+};
 use crate::scope::Scope;
 use crate::vm::VirtualMachine;
 
@@ -14,30 +20,99 @@ pub struct PyFunction {
     // TODO: these shouldn't be public
     pub code: PyCodeRef,
     pub scope: Scope,
-    pub defaults: Option<PyTupleRef>,
-    pub kw_only_defaults: Option<PyDictRef>,
+    pub defaults: RefCell<Option<PyTupleRef>>,
+    pub kw_only_defaults: RefCell<Option<PyDictRef>>,
+    name: RefCell<PyStringRef>,
+    qualname: RefCell<PyStringRef>,
+    module: RefCell<Option<PyObjectRef>>,
+    annotations: RefCell<Option<PyDictRef>>,
+    doc: RefCell<Option<PyObjectRef>>,
 }
 
 impl PyFunction {
+    /// Make a function of `code`, named after it and with the docstring of
+    /// the code. Like in CPython, its module is `__name__` of the globals.
     pub fn new(
         code: PyCodeRef,
         scope: Scope,
         defaults: Option<PyTupleRef>,
         kw_only_defaults: Option<PyDictRef>,
-    ) -> Self {
-        PyFunction {
+        qualname: PyStringRef,
+        annotations: Option<PyDictRef>,
+        vm: &VirtualMachine,
+    ) -> PyResult<Self> {
+        let name = PyString::from(code.code.obj_name.clone()).into_ref(vm);
+        let module = scope.globals.get_item_option("__name__", vm)?;
+        let doc = code
+            .code
+            .doc
+            .clone()
+            .map(|doc| vm.ctx.new_str_with_stand_ins(doc, code.code.doc_stand_ins));
+        Ok(PyFunction {
             code,
             scope,
-            defaults,
-            kw_only_defaults,
-        }
+            defaults: RefCell::new(defaults),
+            kw_only_defaults: RefCell::new(kw_only_defaults),
+            name: RefCell::new(name),
+            qualname: RefCell::new(qualname),
+            module: RefCell::new(module),
+            annotations: RefCell::new(annotations),
+            doc: RefCell::new(doc),
+        })
     }
 }
 
 impl PyValue for PyFunction {
+    const HAVE_GC: bool = true;
+
     fn class(vm: &VirtualMachine) -> PyClassRef {
         vm.ctx.function_type()
     }
+
+    fn traverse(&self, visit: &mut dyn FnMut(&PyObjectRef)) {
+        visit(self.code.as_object());
+        self.scope.traverse(visit);
+        if let Ok(defaults) = self.defaults.try_borrow() {
+            if let Some(ref defaults) = *defaults {
+                visit(defaults.as_object());
+            }
+        }
+        if let Ok(kw_only_defaults) = self.kw_only_defaults.try_borrow() {
+            if let Some(ref kw_only_defaults) = *kw_only_defaults {
+                visit(kw_only_defaults.as_object());
+            }
+        }
+        if let Ok(name) = self.name.try_borrow() {
+            visit(name.as_object());
+        }
+        if let Ok(qualname) = self.qualname.try_borrow() {
+            visit(qualname.as_object());
+        }
+        if let Ok(module) = self.module.try_borrow() {
+            if let Some(ref module) = *module {
+                visit(module);
+            }
+        }
+        if let Ok(annotations) = self.annotations.try_borrow() {
+            if let Some(ref annotations) = *annotations {
+                visit(annotations.as_object());
+            }
+        }
+        if let Ok(doc) = self.doc.try_borrow() {
+            if let Some(ref doc) = *doc {
+                visit(doc);
+            }
+        }
+    }
+
+    fn clear_refs(&self) {
+        let defaults = self.defaults.try_borrow_mut().map(|mut d| d.take());
+        let kw_only_defaults = self.kw_only_defaults.try_borrow_mut().map(|mut d| d.take());
+        let module = self.module.try_borrow_mut().map(|mut m| m.take());
+        let annotations = self.annotations.try_borrow_mut().map(|mut a| a.take());
+        let doc = self.doc.try_borrow_mut().map(|mut d| d.take());
+        drop((defaults, kw_only_defaults, module, annotations, doc));
+    }
 }
 
 impl PyFunctionRef {
@@ -49,12 +124,78 @@ impl PyFunctionRef {
         self.code.clone()
     }
 
+    fn globals(self, _vm: &VirtualMachine) -> PyDictRef {
+        self.scope.globals.clone()
+    }
+
     fn defaults(self, _vm: &VirtualMachine) -> Option<PyTupleRef> {
-        self.defaults.clone()
+        self.defaults.borrow().clone()
+    }
+
+    fn set_defaults(self, defaults: Option<PyTupleRef>, vm: &VirtualMachine) -> PyResult {
+        self.defaults.replace(defaults);
+        Ok(vm.get_none())
     }
 
     fn kwdefaults(self, _vm: &VirtualMachine) -> Option<PyDictRef> {
-        self.kw_only_defaults.clone()
+        self.kw_only_defaults.borrow().clone()
+    }
+
+    fn set_kwdefaults(self, kwdefaults: Option<PyDictRef>, vm: &VirtualMachine) -> PyResult {
+        self.kw_only_defaults.replace(kwdefaults);
+        Ok(vm.get_none())
+    }
+
+    fn name(self, _vm: &VirtualMachine) -> PyStringRef {
+        self.name.borrow().clone()
+    }
+
+    fn set_name(self, name: PyStringRef, vm: &VirtualMachine) -> PyResult {
+        self.name.replace(name);
+        Ok(vm.get_none())
+    }
+
+    fn qualname(self, _vm: &VirtualMachine) -> PyStringRef {
+        self.qualname.borrow().clone()
+    }
+
+    fn set_qualname(self, qualname: PyStringRef, vm: &VirtualMachine) -> PyResult {
+        self.qualname.replace(qualname);
+        Ok(vm.get_none())
+    }
+
+    fn module(self, vm: &VirtualMachine) -> PyObjectRef {
+        self.module
+            .borrow()
+            .clone()
+            .unwrap_or_else(|| vm.get_none())
+    }
+
+    fn set_module(self, module: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+        self.module.replace(Some(module));
+        Ok(vm.get_none())
+    }
+
+    /// The annotations, made empty on first use if there are none.
+    fn annotations(self, vm: &VirtualMachine) -> PyDictRef {
+        self.annotations
+            .borrow_mut()
+            .get_or_insert_with(|| vm.ctx.new_dict())
+            .clone()
+    }
+
+    fn set_annotations(self, annotations: Option<PyDictRef>, vm: &VirtualMachine) -> PyResult {
+        self.annotations.replace(annotations);
+        Ok(vm.get_none())
+    }
+
+    fn doc(self, vm: &VirtualMachine) -> PyObjectRef {
+        self.doc.borrow().clone().unwrap_or_else(|| vm.get_none())
+    }
+
+    fn set_doc(self, doc: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+        self.doc.replace(Some(doc));
+        Ok(vm.get_none())
     }
 }
 
@@ -71,10 +212,29 @@ impl PyMethod {
     }
 }
 
+pub type PyMethodRef = PyRef<PyMethod>;
+
+impl PyMethodRef {
+    fn func(self, _vm: &VirtualMachine) -> PyObjectRef {
+        self.function.clone()
+    }
+
+    fn self_(self, _vm: &VirtualMachine) -> PyObjectRef {
+        self.object.clone()
+    }
+}
+
 impl PyValue for PyMethod {
+    const HAVE_GC: bool = true;
+
     fn class(vm: &VirtualMachine) -> PyClassRef {
         vm.ctx.bound_method_type()
     }
+
+    fn traverse(&self, visit: &mut dyn FnMut(&PyObjectRef)) {
+        visit(&self.object);
+        visit(&self.function);
+    }
 }
 
 pub fn init(context: &PyContext) {
@@ -83,8 +243,48 @@ pub fn init(context: &PyContext) {
         "__get__" => context.new_rustfunc(bind_method),
         "__call__" => context.new_rustfunc(PyFunctionRef::call),
         "__code__" => context.new_property(PyFunctionRef::code),
-        "__defaults__" => context.new_property(PyFunctionRef::defaults),
-        "__kwdefaults__" => context.new_property(PyFunctionRef::kwdefaults),
+        "__globals__" => context.new_property(PyFunctionRef::globals),
+        "__defaults__" =>
+        PropertyBuilder::new(context)
+            .add_getter(PyFunctionRef::defaults)
+            .add_setter(PyFunctionRef::set_defaults)
+            .create(),
+        "__kwdefaults__" =>
+        PropertyBuilder::new(context)
+            .add_getter(PyFunctionRef::kwdefaults)
+            .add_setter(PyFunctionRef::set_kwdefaults)
+            .create(),
+        "__name__" =>
+        PropertyBuilder::new(context)
+            .add_getter(PyFunctionRef::name)
+            .add_setter(PyFunctionRef::set_name)
+            .create(),
+        "__qualname__" =>
+        PropertyBuilder::new(context)
+            .add_getter(PyFunctionRef::qualname)
+            .add_setter(PyFunctionRef::set_qualname)
+            .create(),
+        "__module__" =>
+        PropertyBuilder::new(context)
+            .add_getter(PyFunctionRef::module)
+            .add_setter(PyFunctionRef::set_module)
+            .create(),
+        "__annotations__" =>
+        PropertyBuilder::new(context)
+            .add_getter(PyFunctionRef::annotations)
+            .add_setter(PyFunctionRef::set_annotations)
+            .create(),
+        "__doc__" =>
+        PropertyBuilder::new(context)
+            .add_getter(PyFunctionRef::doc)
+            .add_setter(PyFunctionRef::set_doc)
+            .create(),
+    });
+
+    let bound_method_type = &context.bound_method_type;
+    extend_class!(context, bound_method_type, {
+        "__func__" => context.new_property(PyMethodRef::func),
+        "__self__" => context.new_property(PyMethodRef::self_),
     });
 
     let builtin_function_or_method_type = &context.builtin_function_or_method_type;
@@ -16,9 +16,15 @@ pub struct PyGenerator {
 }
 
 impl PyValue for PyGenerator {
+    const HAVE_GC: bool = true;
+
     fn class(vm: &VirtualMachine) -> PyClassRef {
         vm.ctx.generator_type()
     }
+
+    fn traverse(&self, visit: &mut dyn FnMut(&PyObjectRef)) {
+        visit(self.frame.as_object());
+    }
 }
 
 #[pyimpl]
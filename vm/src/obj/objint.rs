@@ -1,8 +1,11 @@
+use std::cmp::Ordering;
+use std::convert::TryFrom;
 use std::fmt;
 
 use num_bigint::{BigInt, Sign};
 use num_integer::Integer;
 use num_traits::{One, Pow, Signed, ToPrimitive, Zero};
+use once_cell::unsync::OnceCell;
 
 use crate::format::FormatSpec;
 use crate::function::{KwArgs, OptionalArg, PyFuncArgs};
@@ -36,27 +39,90 @@ use crate::obj::objtype::PyClassRef;
 #[pyclass]
 #[derive(Debug)]
 pub struct PyInt {
-    value: BigInt,
+    /// The value, when it fits in an `i64`. Operations on such values stay on
+    /// machine words until they overflow.
+    small: Option<i64>,
+    /// The value as a `BigInt`. Always set when the value doesn't fit in an
+    /// `i64`, and otherwise only made when asked for.
+    big: OnceCell<BigInt>,
 }
 
 impl fmt::Display for PyInt {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        BigInt::fmt(&self.value, f)
+        match self.small {
+            Some(value) => i64::fmt(&value, f),
+            None => BigInt::fmt(self.as_bigint(), f),
+        }
     }
 }
 
 pub type PyIntRef = PyRef<PyInt>;
 
 impl PyInt {
-    pub fn new<T: Into<BigInt>>(i: T) -> Self {
-        PyInt { value: i.into() }
+    pub fn new<T: Into<PyInt>>(i: T) -> Self {
+        i.into()
     }
 
     pub fn as_bigint(&self) -> &BigInt {
-        &self.value
+        self.big.get_or_init(|| BigInt::from(self.small.unwrap()))
+    }
+
+    /// The value, if it fits in an `i64`.
+    pub fn as_i64(&self) -> Option<i64> {
+        self.small
     }
+
+    fn is_zero(&self) -> bool {
+        self.small == Some(0)
+    }
+
+    fn is_negative(&self) -> bool {
+        match self.small {
+            Some(value) => value < 0,
+            None => self.as_bigint().is_negative(),
+        }
+    }
+}
+
+impl From<BigInt> for PyInt {
+    fn from(value: BigInt) -> Self {
+        PyInt {
+            small: value.to_i64(),
+            big: OnceCell::from(value),
+        }
+    }
+}
+
+macro_rules! impl_from_small_int {
+    ($($t:ty)*) => {$(
+        impl From<$t> for PyInt {
+            fn from(value: $t) -> Self {
+                PyInt {
+                    small: Some(i64::from(value)),
+                    big: OnceCell::new(),
+                }
+            }
+        }
+    )*};
 }
 
+impl_from_small_int!(i8 i16 i32 i64 u8 u16 u32);
+
+macro_rules! impl_from_int {
+    ($($t:ty)*) => {$(
+        impl From<$t> for PyInt {
+            fn from(value: $t) -> Self {
+                match value.to_i64() {
+                    Some(small) => PyInt::from(small),
+                    None => PyInt::from(BigInt::from(value)),
+                }
+            }
+        }
+    )*};
+}
+
+impl_from_int!(isize usize u64);
+
 impl IntoPyObject for BigInt {
     fn into_pyobject(self, vm: &VirtualMachine) -> PyResult {
         Ok(vm.ctx.new_int(self))
@@ -79,13 +145,18 @@ macro_rules! impl_into_pyobject_int {
     )*};
 }
 
-impl_into_pyobject_int!(isize i8 i16 i32 i64 usize u8 u16 u32 u64) ;
+impl_into_pyobject_int!(isize i8 i16 i32 i64 usize u8 u16 u32 u64);
 
 macro_rules! impl_try_from_object_int {
     ($(($t:ty, $to_prim:ident),)*) => {$(
         impl TryFromObject for $t {
             fn try_from_object(vm: &VirtualMachine, obj: PyObjectRef) -> PyResult<Self> {
-                match PyRef::<PyInt>::try_from_object(vm, obj)?.value.$to_prim() {
+                let int = PyRef::<PyInt>::try_from_object(vm, obj)?;
+                let value = match int.small {
+                    Some(value) => value.$to_prim(),
+                    None => int.as_bigint().$to_prim(),
+                };
+                match value {
                     Some(value) => Ok(value),
                     None => Err(
                         vm.new_overflow_error(concat!(
@@ -112,19 +183,48 @@ impl_try_from_object_int!(
     (u64, to_u64),
 );
 
+/// Apply an operation to two ints: `small` when both fit in an `i64`, unless
+/// it overflows, and `big` otherwise.
+#[inline]
+fn binop<S, B>(int1: &PyInt, int2: &PyInt, small: S, big: B) -> PyInt
+where
+    S: FnOnce(i64, i64) -> Option<i64>,
+    B: FnOnce(&BigInt, &BigInt) -> BigInt,
+{
+    if let (Some(v1), Some(v2)) = (int1.small, int2.small) {
+        if let Some(value) = small(v1, v2) {
+            return PyInt::from(value);
+        }
+    }
+    PyInt::from(big(int1.as_bigint(), int2.as_bigint()))
+}
+
+fn compare(int1: &PyInt, int2: &PyInt) -> Ordering {
+    match (int1.small, int2.small) {
+        (Some(v1), Some(v2)) => v1.cmp(&v2),
+        _ => int1.as_bigint().cmp(int2.as_bigint()),
+    }
+}
+
 #[allow(clippy::collapsible_if)]
 fn inner_pow(int1: &PyInt, int2: &PyInt, vm: &VirtualMachine) -> PyResult {
-    let result = if int2.value.is_negative() {
+    let result = if int2.is_negative() {
         let v1 = int1.float(vm)?;
         let v2 = int2.float(vm)?;
         vm.ctx.new_float(v1.pow(v2))
     } else {
-        if let Some(v2) = int2.value.to_u64() {
-            vm.ctx.new_int(int1.value.pow(v2))
-        } else if int1.value.is_one() || int1.value.is_zero() {
-            vm.ctx.new_int(int1.value.clone())
-        } else if int1.value == BigInt::from(-1) {
-            if int2.value.is_odd() {
+        if let (Some(v1), Some(v2)) = (int1.small, int2.small) {
+            if let Some(value) = u32::try_from(v2).ok().and_then(|v2| v1.checked_pow(v2)) {
+                return Ok(vm.ctx.new_int(value));
+            }
+        }
+        let value = int1.as_bigint();
+        if let Some(v2) = int2.as_bigint().to_u64() {
+            vm.ctx.new_int(value.pow(v2))
+        } else if value.is_one() || value.is_zero() {
+            vm.ctx.new_int(value.clone())
+        } else if *value == BigInt::from(-1) {
+            if int2.as_bigint().is_odd() {
                 vm.ctx.new_int(-1)
             } else {
                 vm.ctx.new_int(1)
@@ -139,11 +239,12 @@ fn inner_pow(int1: &PyInt, int2: &PyInt, vm: &VirtualMachine) -> PyResult {
 }
 
 fn inner_mod(int1: &PyInt, int2: &PyInt, vm: &VirtualMachine) -> PyResult {
-    if int2.value != BigInt::zero() {
-        Ok(vm.ctx.new_int(&int1.value % &int2.value))
-    } else {
-        Err(vm.new_zero_division_error("integer modulo by zero".to_string()))
+    if int2.is_zero() {
+        return Err(vm.new_zero_division_error("integer modulo by zero".to_string()));
     }
+    Ok(vm
+        .ctx
+        .new_int(binop(int1, int2, i64::checked_rem, |v1, v2| v1 % v2)))
 }
 
 #[pyimpl]
@@ -151,7 +252,8 @@ impl PyInt {
     #[pymethod(name = "__eq__")]
     fn eq(&self, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
         if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            vm.ctx.new_bool(self.value == *get_value(&other))
+            vm.ctx
+                .new_bool(compare(self, get_py_int(&other)) == Ordering::Equal)
         } else {
             vm.ctx.not_implemented()
         }
@@ -160,7 +262,8 @@ impl PyInt {
     #[pymethod(name = "__ne__")]
     fn ne(&self, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
         if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            vm.ctx.new_bool(self.value != *get_value(&other))
+            vm.ctx
+                .new_bool(compare(self, get_py_int(&other)) != Ordering::Equal)
         } else {
             vm.ctx.not_implemented()
         }
@@ -169,7 +272,8 @@ impl PyInt {
     #[pymethod(name = "__lt__")]
     fn lt(&self, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
         if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            vm.ctx.new_bool(self.value < *get_value(&other))
+            vm.ctx
+                .new_bool(compare(self, get_py_int(&other)) == Ordering::Less)
         } else {
             vm.ctx.not_implemented()
         }
@@ -178,7 +282,8 @@ impl PyInt {
     #[pymethod(name = "__le__")]
     fn le(&self, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
         if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            vm.ctx.new_bool(self.value <= *get_value(&other))
+            vm.ctx
+                .new_bool(compare(self, get_py_int(&other)) != Ordering::Greater)
         } else {
             vm.ctx.not_implemented()
         }
@@ -187,7 +292,8 @@ impl PyInt {
     #[pymethod(name = "__gt__")]
     fn gt(&self, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
         if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            vm.ctx.new_bool(self.value > *get_value(&other))
+            vm.ctx
+                .new_bool(compare(self, get_py_int(&other)) == Ordering::Greater)
         } else {
             vm.ctx.not_implemented()
         }
@@ -196,7 +302,8 @@ impl PyInt {
     #[pymethod(name = "__ge__")]
     fn ge(&self, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
         if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            vm.ctx.new_bool(self.value >= *get_value(&other))
+            vm.ctx
+                .new_bool(compare(self, get_py_int(&other)) != Ordering::Less)
         } else {
             vm.ctx.not_implemented()
         }
@@ -205,7 +312,12 @@ impl PyInt {
     #[pymethod(name = "__add__")]
     fn add(&self, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
         if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            vm.ctx.new_int((&self.value) + get_value(&other))
+            vm.ctx.new_int(binop(
+                self,
+                get_py_int(&other),
+                i64::checked_add,
+                |v1, v2| v1 + v2,
+            ))
         } else {
             vm.ctx.not_implemented()
         }
@@ -219,7 +331,12 @@ impl PyInt {
     #[pymethod(name = "__sub__")]
     fn sub(&self, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
         if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            vm.ctx.new_int((&self.value) - get_value(&other))
+            vm.ctx.new_int(binop(
+                self,
+                get_py_int(&other),
+                i64::checked_sub,
+                |v1, v2| v1 - v2,
+            ))
         } else {
             vm.ctx.not_implemented()
         }
@@ -228,7 +345,12 @@ impl PyInt {
     #[pymethod(name = "__rsub__")]
     fn rsub(&self, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
         if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            vm.ctx.new_int(get_value(&other) - (&self.value))
+            vm.ctx.new_int(binop(
+                get_py_int(&other),
+                self,
+                i64::checked_sub,
+                |v1, v2| v1 - v2,
+            ))
         } else {
             vm.ctx.not_implemented()
         }
@@ -237,7 +359,12 @@ impl PyInt {
     #[pymethod(name = "__mul__")]
     fn mul(&self, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
         if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            vm.ctx.new_int((&self.value) * get_value(&other))
+            vm.ctx.new_int(binop(
+                self,
+                get_py_int(&other),
+                i64::checked_mul,
+                |v1, v2| v1 * v2,
+            ))
         } else {
             vm.ctx.not_implemented()
         }
@@ -251,7 +378,7 @@ impl PyInt {
     #[pymethod(name = "__truediv__")]
     fn truediv(&self, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
         if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            div_ints(vm, &self.value, &get_value(&other))
+            div_ints(vm, self, get_py_int(&other))
         } else {
             Ok(vm.ctx.not_implemented())
         }
@@ -260,7 +387,7 @@ impl PyInt {
     #[pymethod(name = "__rtruediv__")]
     fn rtruediv(&self, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
         if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            div_ints(vm, &get_value(&other), &self.value)
+            div_ints(vm, get_py_int(&other), self)
         } else {
             Ok(vm.ctx.not_implemented())
         }
@@ -269,10 +396,17 @@ impl PyInt {
     #[pymethod(name = "__floordiv__")]
     fn floordiv(&self, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
         if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            let v2 = get_value(&other);
-            if *v2 != BigInt::zero() {
-                let modulo = (&self.value % v2 + v2) % v2;
-                Ok(vm.ctx.new_int((&self.value - modulo) / v2))
+            let other = get_py_int(&other);
+            if !other.is_zero() {
+                Ok(vm.ctx.new_int(binop(
+                    self,
+                    other,
+                    |v1, v2| v1.checked_div(v2).map(|_| Integer::div_floor(&v1, &v2)),
+                    |v1, v2| {
+                        let modulo = (v1 % v2 + v2) % v2;
+                        (v1 - modulo) / v2
+                    },
+                )))
             } else {
                 Err(vm.new_zero_division_error("integer floordiv by zero".to_string()))
             }
@@ -288,7 +422,12 @@ impl PyInt {
         }
 
         if let Some(n_bits) = get_value(&other).to_usize() {
-            return Ok(vm.ctx.new_int((&self.value) << n_bits));
+            if let Some(value) = self.small {
+                if n_bits < 63 && value.unsigned_abs() < 1 << (63 - n_bits) {
+                    return Ok(vm.ctx.new_int(value << n_bits));
+                }
+            }
+            return Ok(vm.ctx.new_int(self.as_bigint() << n_bits));
         }
 
         // i2 failed `to_usize()` conversion
@@ -308,7 +447,10 @@ impl PyInt {
         }
 
         if let Some(n_bits) = get_value(&other).to_usize() {
-            return Ok(vm.ctx.new_int((&self.value) >> n_bits));
+            if let Some(value) = self.small {
+                return Ok(vm.ctx.new_int(value >> n_bits.min(63)));
+            }
+            return Ok(vm.ctx.new_int(self.as_bigint() >> n_bits));
         }
 
         // i2 failed `to_usize()` conversion
@@ -324,7 +466,12 @@ impl PyInt {
     #[pymethod(name = "__xor__")]
     pub fn xor(&self, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
         if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            vm.ctx.new_int((&self.value) ^ get_value(&other))
+            vm.ctx.new_int(binop(
+                self,
+                get_py_int(&other),
+                |v1, v2| Some(v1 ^ v2),
+                |v1, v2| v1 ^ v2,
+            ))
         } else {
             vm.ctx.not_implemented()
         }
@@ -338,7 +485,12 @@ impl PyInt {
     #[pymethod(name = "__or__")]
     pub fn or(&self, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
         if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            vm.ctx.new_int((&self.value) | get_value(&other))
+            vm.ctx.new_int(binop(
+                self,
+                get_py_int(&other),
+                |v1, v2| Some(v1 | v2),
+                |v1, v2| v1 | v2,
+            ))
         } else {
             vm.ctx.not_implemented()
         }
@@ -347,8 +499,12 @@ impl PyInt {
     #[pymethod(name = "__and__")]
     pub fn and(&self, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
         if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            let v2 = get_value(&other);
-            vm.ctx.new_int((&self.value) & v2)
+            vm.ctx.new_int(binop(
+                self,
+                get_py_int(&other),
+                |v1, v2| Some(v1 & v2),
+                |v1, v2| v1 & v2,
+            ))
         } else {
             vm.ctx.not_implemented()
         }
@@ -397,9 +553,17 @@ impl PyInt {
     #[pymethod(name = "__divmod__")]
     fn divmod(&self, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
         if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            let v2 = get_value(&other);
-            if *v2 != BigInt::zero() {
-                let (r1, r2) = self.value.div_rem(v2);
+            let other = get_py_int(&other);
+            if !other.is_zero() {
+                let (r1, r2) = match (self.small, other.small) {
+                    (Some(v1), Some(v2)) if v1.checked_div(v2).is_some() => {
+                        (PyInt::from(v1 / v2), PyInt::from(v1 % v2))
+                    }
+                    _ => {
+                        let (r1, r2) = self.as_bigint().div_rem(other.as_bigint());
+                        (PyInt::from(r1), PyInt::from(r2))
+                    }
+                };
                 Ok(vm
                     .ctx
                     .new_tuple(vec![vm.ctx.new_int(r1), vm.ctx.new_int(r2)]))
@@ -412,21 +576,27 @@ impl PyInt {
     }
 
     #[pymethod(name = "__neg__")]
-    fn neg(&self, _vm: &VirtualMachine) -> BigInt {
-        -(&self.value)
+    fn neg(&self, vm: &VirtualMachine) -> PyObjectRef {
+        match self.small.and_then(i64::checked_neg) {
+            Some(value) => vm.ctx.new_int(value),
+            None => vm.ctx.new_int(-self.as_bigint()),
+        }
     }
 
     #[pymethod(name = "__hash__")]
     pub fn hash(&self, _vm: &VirtualMachine) -> pyhash::PyHash {
-        match self.value.to_i64() {
-            Some(value) => (value % pyhash::MODULUS as i64),
-            None => (&self.value % pyhash::MODULUS).to_i64().unwrap(),
+        match self.small {
+            Some(value) => value % pyhash::MODULUS as i64,
+            None => (self.as_bigint() % pyhash::MODULUS).to_i64().unwrap(),
         }
     }
 
     #[pymethod(name = "__abs__")]
-    fn abs(&self, _vm: &VirtualMachine) -> BigInt {
-        self.value.abs()
+    fn abs(&self, vm: &VirtualMachine) -> PyObjectRef {
+        match self.small.and_then(i64::checked_abs) {
+            Some(value) => vm.ctx.new_int(value),
+            None => vm.ctx.new_int(self.as_bigint().abs()),
+        }
     }
 
     #[pymethod(name = "__round__")]
@@ -450,9 +620,11 @@ impl PyInt {
 
     #[pymethod(name = "__float__")]
     fn float(&self, vm: &VirtualMachine) -> PyResult<f64> {
-        self.value
-            .to_f64()
-            .ok_or_else(|| vm.new_overflow_error("int too large to convert to float".to_string()))
+        let value = match self.small {
+            Some(value) => Some(value as f64),
+            None => self.as_bigint().to_f64(),
+        };
+        value.ok_or_else(|| vm.new_overflow_error("int too large to convert to float".to_string()))
     }
 
     #[pymethod(name = "__trunc__")]
@@ -476,19 +648,22 @@ impl PyInt {
     }
 
     #[pymethod(name = "__invert__")]
-    fn invert(&self, _vm: &VirtualMachine) -> BigInt {
-        !(&self.value)
+    fn invert(&self, vm: &VirtualMachine) -> PyObjectRef {
+        match self.small {
+            Some(value) => vm.ctx.new_int(!value),
+            None => vm.ctx.new_int(!self.as_bigint()),
+        }
     }
 
     #[pymethod(name = "__repr__")]
     fn repr(&self, _vm: &VirtualMachine) -> String {
-        self.value.to_string()
+        self.to_string()
     }
 
     #[pymethod(name = "__format__")]
     fn format(&self, spec: PyStringRef, vm: &VirtualMachine) -> PyResult<String> {
-        let format_spec = FormatSpec::parse(&spec.value);
-        match format_spec.format_int(&self.value) {
+        let format_spec = FormatSpec::parse(spec.as_str());
+        match format_spec.format_int(self.as_bigint()) {
             Ok(string) => Ok(string),
             Err(err) => Err(vm.new_value_error(err.to_string())),
         }
@@ -496,12 +671,15 @@ impl PyInt {
 
     #[pymethod(name = "__bool__")]
     fn bool(&self, _vm: &VirtualMachine) -> bool {
-        !self.value.is_zero()
+        !self.is_zero()
     }
 
     #[pymethod]
     fn bit_length(&self, _vm: &VirtualMachine) -> usize {
-        self.value.bits()
+        match self.small {
+            Some(value) => 64 - value.unsigned_abs().leading_zeros() as usize,
+            None => self.as_bigint().bits(),
+        }
     }
 
     #[pymethod]
@@ -528,12 +706,12 @@ impl PyInt {
             }
         }
         let x;
-        if byteorder.value == "big" {
+        if byteorder.as_str() == "big" {
             x = match signed {
                 true => BigInt::from_signed_bytes_be(&bytes.elements),
                 false => BigInt::from_bytes_be(Sign::Plus, &bytes.elements),
             }
-        } else if byteorder.value == "little" {
+        } else if byteorder.as_str() == "little" {
             x = match signed {
                 true => BigInt::from_signed_bytes_le(&bytes.elements),
                 false => BigInt::from_bytes_le(Sign::Plus, &bytes.elements),
@@ -575,7 +753,7 @@ impl PyInt {
             return Err(vm.new_value_error("length parameter is illegal".to_string()));
         }
 
-        let mut origin_bytes = match byteorder.value.as_str() {
+        let mut origin_bytes = match byteorder.as_str() {
             "big" => match signed {
                 true => value.to_signed_bytes_be(),
                 false => value.to_bytes_be().1,
@@ -600,7 +778,7 @@ impl PyInt {
             _ => vec![0u8; byte_len - origin_len],
         };
         let mut bytes = vec![];
-        match byteorder.value.as_str() {
+        match byteorder.as_str() {
             "big" => {
                 bytes = append_bytes;
                 bytes.append(&mut origin_bytes);
@@ -696,19 +874,27 @@ pub fn to_int(vm: &VirtualMachine, obj: &PyObjectRef, base: u32) -> PyResult<Big
 
 // Retrieve inner int value:
 pub fn get_value(obj: &PyObjectRef) -> &BigInt {
-    &obj.payload::<PyInt>().unwrap().value
+    get_py_int(obj).as_bigint()
+}
+
+fn get_py_int(obj: &PyObjectRef) -> &PyInt {
+    obj.payload::<PyInt>().unwrap()
 }
 
 pub fn get_float_value(obj: &PyObjectRef, vm: &VirtualMachine) -> PyResult<f64> {
-    obj.payload::<PyInt>().unwrap().float(vm)
+    get_py_int(obj).float(vm)
 }
 
 #[inline]
-fn div_ints(vm: &VirtualMachine, i1: &BigInt, i2: &BigInt) -> PyResult {
+fn div_ints(vm: &VirtualMachine, i1: &PyInt, i2: &PyInt) -> PyResult {
     if i2.is_zero() {
         return Err(vm.new_zero_division_error("integer division by zero".to_string()));
     }
 
+    if let (Some(v1), Some(v2)) = (i1.small, i2.small) {
+        return Ok(vm.ctx.new_float(v1 as f64 / v2 as f64));
+    }
+    let (i1, i2) = (i1.as_bigint(), i2.as_bigint());
     if let (Some(f1), Some(f2)) = (i1.to_f64(), i2.to_f64()) {
         Ok(vm.ctx.new_float(f1 / f2))
     } else {
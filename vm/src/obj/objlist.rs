@@ -1,5 +1,6 @@
 use std::cell::{Cell, RefCell};
 use std::fmt;
+use std::mem;
 
 use std::ops::Range;
 
@@ -46,9 +47,27 @@ impl From<Vec<PyObjectRef>> for PyList {
 }
 
 impl PyValue for PyList {
+    const HAVE_GC: bool = true;
+
     fn class(vm: &VirtualMachine) -> PyClassRef {
         vm.ctx.list_type()
     }
+
+    fn traverse(&self, visit: &mut dyn FnMut(&PyObjectRef)) {
+        if let Ok(elements) = self.elements.try_borrow() {
+            for element in elements.iter() {
+                visit(element);
+            }
+        }
+    }
+
+    fn clear_refs(&self) {
+        let elements = self
+            .elements
+            .try_borrow_mut()
+            .map(|mut e| mem::take(&mut *e));
+        drop(elements);
+    }
 }
 
 impl PyList {
@@ -385,7 +404,7 @@ impl PyListRef {
             let mut str_parts = vec![];
             for elem in self.elements.borrow().iter() {
                 let s = vm.to_repr(elem)?;
-                str_parts.push(s.value.clone());
+                str_parts.push(s.as_str().to_string());
             }
             format!("[{}]", str_parts.join(", "))
         } else {
@@ -456,7 +475,7 @@ impl PyListRef {
                 return Ok(index);
             }
         }
-        let needle_str = &vm.to_str(&needle)?.value;
+        let needle_str = vm.to_str(&needle)?;
         Err(vm.new_value_error(format!("'{}' is not in list", needle_str)))
     }
 
@@ -493,7 +512,7 @@ impl PyListRef {
             self.elements.borrow_mut().remove(index);
             Ok(())
         } else {
-            let needle_str = &vm.to_str(&needle)?.value;
+            let needle_str = vm.to_str(&needle)?;
             Err(vm.new_value_error(format!("'{}' is not in list", needle_str)))
         }
     }
@@ -1,6 +1,7 @@
+use super::objdict::PyDictRef;
 use super::objstr::PyStringRef;
 use super::objtype::{self, PyClassRef};
-use crate::pyobject::{PyClassImpl, PyContext, PyRef, PyResult, PyValue};
+use crate::pyobject::{ItemProtocol, PyClassImpl, PyContext, PyRef, PyResult, PyValue};
 use crate::vm::VirtualMachine;
 
 #[pyclass]
@@ -35,6 +36,36 @@ impl PyMappingProxy {
     pub fn contains(&self, attr: PyStringRef, _vm: &VirtualMachine) -> bool {
         objtype::class_has_attr(&self.class, attr.as_str())
     }
+
+    /// A dict of the attributes defined on the class itself.
+    #[pymethod]
+    pub fn copy(&self, vm: &VirtualMachine) -> PyResult<PyDictRef> {
+        let dict = vm.ctx.new_dict();
+        for (name, value) in self.class.attributes().iter() {
+            dict.set_item(name, value.clone(), vm)?;
+        }
+        Ok(dict)
+    }
+
+    #[pymethod]
+    fn keys(&self, vm: &VirtualMachine) -> PyResult {
+        vm.call_method(self.copy(vm)?.as_object(), "keys", vec![])
+    }
+
+    #[pymethod]
+    fn values(&self, vm: &VirtualMachine) -> PyResult {
+        vm.call_method(self.copy(vm)?.as_object(), "values", vec![])
+    }
+
+    #[pymethod]
+    fn items(&self, vm: &VirtualMachine) -> PyResult {
+        vm.call_method(self.copy(vm)?.as_object(), "items", vec![])
+    }
+
+    #[pymethod(name = "__iter__")]
+    fn iter(&self, vm: &VirtualMachine) -> PyResult {
+        vm.call_method(self.copy(vm)?.as_object(), "__iter__", vec![])
+    }
 }
 
 pub fn init(context: &PyContext) {
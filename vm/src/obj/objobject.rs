@@ -69,7 +69,7 @@ fn object_setattr(
     vm_trace!("object.__setattr__({:?}, {}, {:?})", obj, attr_name, value);
     let cls = obj.class();
 
-    if let Some(attr) = objtype::class_get_attr(&cls, &attr_name.value) {
+    if let Some(attr) = objtype::class_get_attr(&cls, attr_name.as_str()) {
         if let Some(descriptor) = objtype::class_get_attr(&attr.class(), "__set__") {
             return vm
                 .invoke(descriptor, vec![attr, obj.clone(), value])
@@ -84,7 +84,7 @@ fn object_setattr(
         Err(vm.new_attribute_error(format!(
             "'{}' object has no attribute '{}'",
             obj.class().name,
-            &attr_name.value
+            attr_name.as_str()
         )))
     }
 }
@@ -92,7 +92,7 @@ fn object_setattr(
 fn object_delattr(obj: PyObjectRef, attr_name: PyStringRef, vm: &VirtualMachine) -> PyResult<()> {
     let cls = obj.class();
 
-    if let Some(attr) = objtype::class_get_attr(&cls, &attr_name.value) {
+    if let Some(attr) = objtype::class_get_attr(&cls, attr_name.as_str()) {
         if let Some(descriptor) = objtype::class_get_attr(&attr.class(), "__delete__") {
             return vm.invoke(descriptor, vec![attr, obj.clone()]).map(|_| ());
         }
@@ -105,7 +105,7 @@ fn object_delattr(obj: PyObjectRef, attr_name: PyStringRef, vm: &VirtualMachine)
         Err(vm.new_attribute_error(format!(
             "'{}' object has no attribute '{}'",
             obj.class().name,
-            &attr_name.value
+            attr_name.as_str()
         )))
     }
 }
@@ -141,7 +141,7 @@ fn object_format(
     format_spec: PyStringRef,
     vm: &VirtualMachine,
 ) -> PyResult<PyStringRef> {
-    if format_spec.value.is_empty() {
+    if format_spec.as_str().is_empty() {
         vm.to_str(&obj)
     } else {
         Err(vm.new_type_error("unsupported format string passed to object.__format__".to_string()))
@@ -204,7 +204,10 @@ fn object_dict(object: PyObjectRef, vm: &VirtualMachine) -> PyResult<PyDictRef>
     if let Some(ref dict) = object.dict {
         Ok(dict.clone())
     } else {
-        Err(vm.new_type_error("TypeError: no dictionary.".to_string()))
+        Err(vm.new_attribute_error(format!(
+            "'{}' object has no attribute '__dict__'",
+            object.class().name
+        )))
     }
 }
 
@@ -219,7 +222,7 @@ fn object_dict_setter(
 }
 
 fn object_getattribute(obj: PyObjectRef, name_str: PyStringRef, vm: &VirtualMachine) -> PyResult {
-    let name = &name_str.value;
+    let name = name_str.as_str();
     vm_trace!("object.__getattribute__({:?}, {:?})", obj, name);
     let cls = obj.class();
 
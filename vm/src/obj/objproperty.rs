@@ -18,9 +18,15 @@ pub struct PyReadOnlyProperty {
 }
 
 impl PyValue for PyReadOnlyProperty {
+    const HAVE_GC: bool = true;
+
     fn class(vm: &VirtualMachine) -> PyClassRef {
         vm.ctx.readonly_property_type()
     }
+
+    fn traverse(&self, visit: &mut dyn FnMut(&PyObjectRef)) {
+        visit(&self.getter);
+    }
 }
 
 pub type PyReadOnlyPropertyRef = PyRef<PyReadOnlyProperty>;
@@ -84,9 +90,18 @@ pub struct PyProperty {
 }
 
 impl PyValue for PyProperty {
+    const HAVE_GC: bool = true;
+
     fn class(vm: &VirtualMachine) -> PyClassRef {
         vm.ctx.property_type()
     }
+
+    fn traverse(&self, visit: &mut dyn FnMut(&PyObjectRef)) {
+        let accessors = [&self.getter, &self.setter, &self.deleter, &self.doc];
+        for accessor in accessors.iter().filter_map(|accessor| accessor.as_ref()) {
+            visit(accessor);
+        }
+    }
 }
 
 pub type PyPropertyRef = PyRef<PyProperty>;
@@ -173,7 +173,7 @@ impl TryFromObject for SequenceIndex {
 /// Get the index into a sequence like type. Get it from a python integer
 /// object, accounting for negative index, and out of bounds issues.
 pub fn get_sequence_index(vm: &VirtualMachine, index: &PyIntRef, length: usize) -> PyResult<usize> {
-    if let Some(value) = index.as_bigint().to_i64() {
+    if let Some(value) = index.as_i64() {
         if value < 0 {
             let from_end: usize = -value as usize;
             if from_end > length {
@@ -202,7 +202,7 @@ pub fn get_item(
     subscript: PyObjectRef,
 ) -> PyResult {
     if let Some(i) = subscript.payload::<PyInt>() {
-        return match i.as_bigint().to_i32() {
+        return match i.as_i64().and_then(|value| value.to_i32()) {
             Some(value) => {
                 if let Some(pos_index) = elements.to_vec().get_pos(value) {
                     let obj = elements[pos_index].clone();
@@ -290,83 +290,55 @@ pub fn seq_equal(
     }
 }
 
-pub fn seq_lt(
+/// The first pair of items at which the sequences differ, if any.
+fn seq_first_difference<'a>(
     vm: &VirtualMachine,
-    zelf: &dyn SimpleSeq,
-    other: &dyn SimpleSeq,
-) -> Result<bool, PyObjectRef> {
-    if zelf.len() == other.len() {
-        for (a, b) in Iterator::zip(zelf.iter(), other.iter()) {
-            let lt = vm._lt(a.clone(), b.clone())?;
-            let value = objbool::boolval(vm, lt)?;
-            if !value {
-                return Ok(false);
-            }
-        }
-        Ok(true)
-    } else {
-        // This case is more complicated because it can still return true if
-        // `zelf` is the head of `other` e.g. [1,2,3] < [1,2,3,4] should return true
-        let mut head = true; // true if `zelf` is the head of `other`
-
-        for (a, b) in Iterator::zip(zelf.iter(), other.iter()) {
-            let lt = vm._lt(a.clone(), b.clone())?;
+    zelf: &'a dyn SimpleSeq,
+    other: &'a dyn SimpleSeq,
+) -> Result<Option<(&'a PyObjectRef, &'a PyObjectRef)>, PyObjectRef> {
+    for (a, b) in Iterator::zip(zelf.iter(), other.iter()) {
+        if !a.is(b) {
             let eq = vm._eq(a.clone(), b.clone())?;
-            let lt_value = objbool::boolval(vm, lt)?;
-            let eq_value = objbool::boolval(vm, eq)?;
-
-            if !lt_value && !eq_value {
-                return Ok(false);
-            } else if !eq_value {
-                head = false;
+            if !objbool::boolval(vm, eq)? {
+                return Ok(Some((a, b)));
             }
         }
-
-        if head {
-            Ok(zelf.len() < other.len())
-        } else {
-            Ok(true)
-        }
     }
+    Ok(None)
 }
 
-pub fn seq_gt(
+/// Compare sequences lexicographically: by the first differing items, or by
+/// length if one is a prefix of the other.
+fn seq_compare(
     vm: &VirtualMachine,
     zelf: &dyn SimpleSeq,
     other: &dyn SimpleSeq,
+    compare_items: fn(&VirtualMachine, PyObjectRef, PyObjectRef) -> PyResult,
+    compare_lengths: fn(&usize, &usize) -> bool,
 ) -> Result<bool, PyObjectRef> {
-    if zelf.len() == other.len() {
-        for (a, b) in Iterator::zip(zelf.iter(), other.iter()) {
-            let gt = vm._gt(a.clone(), b.clone())?;
-            let value = objbool::boolval(vm, gt)?;
-            if !value {
-                return Ok(false);
-            }
+    match seq_first_difference(vm, zelf, other)? {
+        Some((a, b)) => {
+            let value = compare_items(vm, a.clone(), b.clone())?;
+            objbool::boolval(vm, value)
         }
-        Ok(true)
-    } else {
-        let mut head = true; // true if `other` is the head of `zelf`
-        for (a, b) in Iterator::zip(zelf.iter(), other.iter()) {
-            // This case is more complicated because it can still return true if
-            // `other` is the head of `zelf` e.g. [1,2,3,4] > [1,2,3] should return true
-            let gt = vm._gt(a.clone(), b.clone())?;
-            let eq = vm._eq(a.clone(), b.clone())?;
-            let gt_value = objbool::boolval(vm, gt)?;
-            let eq_value = objbool::boolval(vm, eq)?;
+        None => Ok(compare_lengths(&zelf.len(), &other.len())),
+    }
+}
 
-            if !gt_value && !eq_value {
-                return Ok(false);
-            } else if !eq_value {
-                head = false;
-            }
-        }
+pub fn seq_lt(
+    vm: &VirtualMachine,
+    zelf: &dyn SimpleSeq,
+    other: &dyn SimpleSeq,
+) -> Result<bool, PyObjectRef> {
+    seq_compare(vm, zelf, other, VirtualMachine::_lt, usize::lt)
+}
 
-        if head {
-            Ok(zelf.len() > other.len())
-        } else {
-            Ok(true)
-        }
-    }
+pub fn seq_gt(
+    vm: &VirtualMachine,
+    zelf: &dyn SimpleSeq,
+    other: &dyn SimpleSeq,
+) -> Result<bool, PyObjectRef> {
+    seq_compare(vm, zelf, other, VirtualMachine::_gt, usize::gt)
 }
 
 pub fn seq_ge(
@@ -374,7 +346,7 @@ pub fn seq_ge(
     zelf: &dyn SimpleSeq,
     other: &dyn SimpleSeq,
 ) -> Result<bool, PyObjectRef> {
-    Ok(seq_gt(vm, zelf, other)? || seq_equal(vm, zelf, other)?)
+    seq_compare(vm, zelf, other, VirtualMachine::_ge, usize::ge)
 }
 
 pub fn seq_le(
@@ -382,7 +354,7 @@ pub fn seq_le(
     zelf: &dyn SimpleSeq,
     other: &dyn SimpleSeq,
 ) -> Result<bool, PyObjectRef> {
-    Ok(seq_lt(vm, zelf, other)? || seq_equal(vm, zelf, other)?)
+    seq_compare(vm, zelf, other, VirtualMachine::_le, usize::le)
 }
 
 pub struct SeqMul<'a> {
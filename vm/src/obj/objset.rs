@@ -4,6 +4,7 @@
 
 use std::cell::{Cell, RefCell};
 use std::fmt;
+use std::mem;
 
 use crate::dictdatatype;
 use crate::function::OptionalArg;
@@ -56,15 +57,34 @@ impl fmt::Debug for PyFrozenSet {
 }
 
 impl PyValue for PySet {
+    const HAVE_GC: bool = true;
+
     fn class(vm: &VirtualMachine) -> PyClassRef {
         vm.ctx.set_type()
     }
+
+    fn traverse(&self, visit: &mut dyn FnMut(&PyObjectRef)) {
+        if let Ok(inner) = self.inner.try_borrow() {
+            inner.traverse(visit);
+        }
+    }
+
+    fn clear_refs(&self) {
+        let inner = self.inner.try_borrow_mut().map(|mut i| mem::take(&mut *i));
+        drop(inner);
+    }
 }
 
 impl PyValue for PyFrozenSet {
+    const HAVE_GC: bool = true;
+
     fn class(vm: &VirtualMachine) -> PyClassRef {
         vm.ctx.frozenset_type()
     }
+
+    fn traverse(&self, visit: &mut dyn FnMut(&PyObjectRef)) {
+        self.inner.traverse(visit);
+    }
 }
 
 #[derive(Default, Clone)]
@@ -73,6 +93,13 @@ struct PySetInner {
 }
 
 impl PySetInner {
+    fn traverse(&self, visit: &mut dyn FnMut(&PyObjectRef)) {
+        let mut position = 0;
+        while let Some((key, _)) = self.content.next_entry(&mut position) {
+            visit(key);
+        }
+    }
+
     fn new(iterable: PyIterable, vm: &VirtualMachine) -> PyResult<PySetInner> {
         let mut set = PySetInner::default();
         for item in iterable.iter(vm)? {
@@ -243,7 +270,7 @@ impl PySetInner {
         let mut str_parts = vec![];
         for key in self.content.keys() {
             let part = vm.to_repr(&key)?;
-            str_parts.push(part.value.clone());
+            str_parts.push(part.as_str().to_string());
         }
 
         Ok(format!("{{{}}}", str_parts.join(", ")))
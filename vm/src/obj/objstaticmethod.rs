@@ -9,9 +9,15 @@ pub struct PyStaticMethod {
 pub type PyStaticMethodRef = PyRef<PyStaticMethod>;
 
 impl PyValue for PyStaticMethod {
+    const HAVE_GC: bool = true;
+
     fn class(vm: &VirtualMachine) -> PyClassRef {
         vm.ctx.staticmethod_type()
     }
+
+    fn traverse(&self, visit: &mut dyn FnMut(&PyObjectRef)) {
+        visit(&self.callable);
+    }
 }
 
 impl PyStaticMethodRef {
@@ -29,6 +35,10 @@ impl PyStaticMethodRef {
     fn get(self, _inst: PyObjectRef, _owner: PyObjectRef, _vm: &VirtualMachine) -> PyResult {
         Ok(self.callable.clone())
     }
+
+    fn func(self, _vm: &VirtualMachine) -> PyObjectRef {
+        self.callable.clone()
+    }
 }
 
 pub fn init(context: &PyContext) {
@@ -36,5 +46,6 @@ pub fn init(context: &PyContext) {
     extend_class!(context, staticmethod_type, {
         "__get__" => context.new_rustfunc(PyStaticMethodRef::get),
         "__new__" => context.new_rustfunc(PyStaticMethodRef::new),
+        "__func__" => context.new_property(PyStaticMethodRef::func),
     });
 }
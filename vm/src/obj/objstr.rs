@@ -1,17 +1,18 @@
 extern crate unicode_categories;
 extern crate unicode_xid;
 
+use std::borrow::Cow;
 use std::cell::Cell;
 use std::char;
+use std::cmp::Ordering;
 use std::fmt;
 use std::ops::Range;
 use std::str::FromStr;
 use std::string::ToString;
 
 use num_traits::ToPrimitive;
+use once_cell::unsync::OnceCell;
 use unicode_casing::CharExt;
-use unicode_segmentation::UnicodeSegmentation;
-use unicode_xid::UnicodeXID;
 
 use crate::cformat::{
     CFormatPart, CFormatPreconversor, CFormatQuantity, CFormatSpec, CFormatString, CFormatType,
@@ -24,6 +25,7 @@ use crate::pyobject::{
     IdProtocol, IntoPyObject, ItemProtocol, PyClassImpl, PyContext, PyIterable, PyObjectRef, PyRef,
     PyResult, PyValue, TryFromObject, TryIntoRef, TypeProtocol,
 };
+use crate::surrogates::{self, StandIns};
 use crate::vm::VirtualMachine;
 
 use super::objbytes::PyBytes;
@@ -51,44 +53,278 @@ use unicode_categories::UnicodeCategories;
 #[pyclass(name = "str")]
 #[derive(Clone, Debug)]
 pub struct PyString {
-    // TODO: shouldn't be public
-    pub value: String,
+    /// The text, with lone surrogates as `stand_ins`. Read it through
+    /// `as_str`, which is only the text as Rust sees it, or through the
+    /// `surrogates` helpers.
+    value: String,
+    /// The code points of `value` standing for lone surrogates, if it holds
+    /// any, see `surrogates`.
+    stand_ins: Option<StandIns>,
+    chars: OnceCell<CharIndex>,
+}
+
+/// Where the code points of a string start, so that the string can be indexed
+/// by code point in constant time.
+#[derive(Clone, Debug)]
+struct CharIndex {
+    /// The number of code points.
+    len: usize,
+    /// The byte offset of every `CHAR_INDEX_STRIDE`th code point. Left empty
+    /// for ASCII strings, where code points and bytes are the same.
+    offsets: Vec<usize>,
+}
+
+const CHAR_INDEX_STRIDE: usize = 32;
+
+impl CharIndex {
+    fn new(s: &str) -> Self {
+        if s.is_ascii() {
+            return CharIndex {
+                len: s.len(),
+                offsets: Vec::new(),
+            };
+        }
+        let mut len = 0;
+        let mut offsets = Vec::new();
+        for (offset, _) in s.char_indices() {
+            if len % CHAR_INDEX_STRIDE == 0 {
+                offsets.push(offset);
+            }
+            len += 1;
+        }
+        CharIndex { len, offsets }
+    }
 }
 
 impl PyString {
+    /// A string of `text`, in which the code points of `stand_ins` stand for
+    /// lone surrogates.
+    pub fn with_stand_ins(text: String, stand_ins: Option<StandIns>) -> PyString {
+        let (value, stand_ins) = match stand_ins {
+            Some(stand_ins) => surrogates::normalize(text, stand_ins),
+            None => (text, None),
+        };
+        PyString {
+            value,
+            stand_ins,
+            chars: OnceCell::new(),
+        }
+    }
+
+    /// The text as Rust sees it: lone surrogates are still their stand-ins.
     pub fn as_str(&self) -> &str {
         &self.value
     }
+
+    pub fn stand_ins(&self) -> Option<StandIns> {
+        self.stand_ins
+    }
+
+    pub fn code_points<'a>(&'a self) -> impl Iterator<Item = u32> + 'a {
+        let stand_ins = self.stand_ins;
+        self.value
+            .chars()
+            .map(move |c| surrogates::code_point(c, stand_ins))
+    }
+
+    /// The characters, with `None` for each lone surrogate, which has none of
+    /// the properties the `is*` methods test for.
+    fn chars_or_surrogates<'a>(&'a self) -> impl Iterator<Item = Option<char>> + 'a {
+        self.code_points().map(std::char::from_u32)
+    }
+
+    /// The characters that aren't stand-ins.
+    fn own_chars<'a>(&'a self) -> impl Iterator<Item = char> + 'a {
+        let stand_ins = self.stand_ins;
+        self.value
+            .chars()
+            .filter(move |&c| stand_ins.map_or(true, |stand_ins| !stand_ins.contains(c)))
+    }
+
+    /// A string of `text`, made from this one, with the same stand-ins.
+    fn derive(&self, text: String) -> PyString {
+        PyString::with_stand_ins(text, self.stand_ins)
+    }
+
+    /// The text of each of `strings`, holding their lone surrogates as the
+    /// same stand-ins, and those stand-ins.
+    fn unify<'a>(
+        strings: &[&'a PyString],
+        vm: &VirtualMachine,
+    ) -> PyResult<(Vec<Cow<'a, str>>, Option<StandIns>)> {
+        let mut found = None;
+        let mut shared = true;
+        for string in strings {
+            match (found, string.stand_ins) {
+                (None, stand_ins) => found = stand_ins,
+                (Some(found), Some(stand_ins)) if found != stand_ins => shared = false,
+                _ => {}
+            }
+        }
+        let borrowed = || strings.iter().map(|s| Cow::Borrowed(s.as_str())).collect();
+        let found = match found {
+            Some(found) => found,
+            None => return Ok((borrowed(), None)),
+        };
+        if shared
+            && strings
+                .iter()
+                .all(|s| s.stand_ins.is_some() || !s.value.chars().any(|c| found.contains(c)))
+        {
+            return Ok((borrowed(), Some(found)));
+        }
+        let stand_ins = StandIns::avoiding(strings.iter().flat_map(|s| s.own_chars()))
+            .ok_or_else(|| surrogates::no_stand_ins_error(vm))?;
+        let texts = strings
+            .iter()
+            .map(|s| match s.stand_ins {
+                Some(own) if own != stand_ins => Cow::Owned(own.move_to(&s.value, stand_ins)),
+                _ => Cow::Borrowed(s.as_str()),
+            })
+            .collect();
+        Ok((texts, Some(stand_ins)))
+    }
+
+    /// `strings` joined by `separator`.
+    pub fn concat(
+        strings: &[&PyString],
+        separator: &PyString,
+        vm: &VirtualMachine,
+    ) -> PyResult<PyString> {
+        let mut all = Vec::with_capacity(strings.len() + 1);
+        all.push(separator);
+        all.extend_from_slice(strings);
+        let (texts, stand_ins) = PyString::unify(&all, vm)?;
+        Ok(PyString::with_stand_ins(
+            texts[1..].join(&*texts[0]),
+            stand_ins,
+        ))
+    }
+
+    /// Strings are ordered by code point, so a lone surrogate comes before
+    /// the supplementary character standing in for it.
+    fn compare(&self, other: &PyString) -> Ordering {
+        if self.stand_ins.is_none() && other.stand_ins.is_none() {
+            self.value.cmp(&other.value)
+        } else {
+            self.code_points().cmp(other.code_points())
+        }
+    }
+
+    /// The code point at `index` as a string of its own.
+    fn char_at(&self, index: usize) -> PyString {
+        #[allow(clippy::range_plus_one)]
+        let value = self.char_slice(index..index + 1);
+        self.derive(value.to_string())
+    }
+
+    /// The character index is built the first time it is needed, since most
+    /// strings are never indexed.
+    fn char_index(&self) -> &CharIndex {
+        self.chars.get_or_init(|| CharIndex::new(&self.value))
+    }
+
+    /// The length of the string in code points.
+    pub fn char_len(&self) -> usize {
+        self.char_index().len
+    }
+
+    pub fn is_ascii(&self) -> bool {
+        self.char_len() == self.value.len()
+    }
+
+    /// The byte offset of the code point at `index`, or the length in bytes
+    /// past the last one.
+    fn byte_offset(&self, index: usize) -> usize {
+        let chars = self.char_index();
+        if index >= chars.len {
+            return self.value.len();
+        }
+        if chars.offsets.is_empty() {
+            return index;
+        }
+        let start = chars.offsets[index / CHAR_INDEX_STRIDE];
+        let (offset, _) = self.value[start..]
+            .char_indices()
+            .nth(index % CHAR_INDEX_STRIDE)
+            .unwrap();
+        start + offset
+    }
+
+    /// The code point index of the character starting at byte `offset`.
+    fn char_position(&self, offset: usize) -> usize {
+        let chars = self.char_index();
+        if chars.offsets.is_empty() {
+            return offset;
+        }
+        let block = match chars.offsets.binary_search(&offset) {
+            Ok(block) => return block * CHAR_INDEX_STRIDE,
+            Err(block) => block - 1,
+        };
+        let start = chars.offsets[block];
+        block * CHAR_INDEX_STRIDE + self.value[start..offset].chars().count()
+    }
+
+    /// The code points in `range`.
+    pub fn char_slice(&self, range: Range<usize>) -> &str {
+        &self.value[self.byte_offset(range.start)..self.byte_offset(range.end)]
+    }
+
+    /// The byte range matching optional code point indices as given to
+    /// `find` and friends, or `None` if it is empty.
+    fn byte_range(
+        &self,
+        start: OptionalArg<isize>,
+        end: OptionalArg<isize>,
+    ) -> Option<Range<usize>> {
+        adjust_indices(start, end, self.char_len())
+            .map(|(start, end)| self.byte_offset(start)..self.byte_offset(end))
+    }
 }
 
-impl From<&str> for PyString {
-    fn from(s: &str) -> PyString {
+impl From<String> for PyString {
+    fn from(value: String) -> PyString {
         PyString {
-            value: s.to_string(),
+            value,
+            stand_ins: None,
+            chars: OnceCell::new(),
         }
     }
 }
 
+impl From<&str> for PyString {
+    fn from(s: &str) -> PyString {
+        PyString::from(s.to_string())
+    }
+}
+
 pub type PyStringRef = PyRef<PyString>;
 
+/// Lone surrogates, which Rust strings can't hold, show as `\udcff` escapes.
 impl fmt::Display for PyString {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        fmt::Display::fmt(&self.value, f)
+        match self.stand_ins {
+            None => fmt::Display::fmt(&self.value, f),
+            Some(stand_ins) => self
+                .value
+                .chars()
+                .try_for_each(|c| match stand_ins.surrogate(c) {
+                    Some(surrogate) => write!(f, "\\u{:04x}", surrogate),
+                    None => write!(f, "{}", c),
+                }),
+        }
     }
 }
 
 impl TryIntoRef<PyString> for String {
     fn try_into_ref(self, vm: &VirtualMachine) -> PyResult<PyRef<PyString>> {
-        Ok(PyString { value: self }.into_ref(vm))
+        Ok(PyString::from(self).into_ref(vm))
     }
 }
 
 impl TryIntoRef<PyString> for &str {
     fn try_into_ref(self, vm: &VirtualMachine) -> PyResult<PyRef<PyString>> {
-        Ok(PyString {
-            value: self.to_string(),
-        }
-        .into_ref(vm))
+        Ok(PyString::from(self).into_ref(vm))
     }
 }
 
@@ -111,13 +347,9 @@ impl PyStringIterator {
     fn next(&self, vm: &VirtualMachine) -> PyResult {
         let pos = self.position.get();
 
-        if pos < self.string.value.chars().count() {
+        if pos < self.string.char_len() {
             self.position.set(self.position.get() + 1);
-
-            #[allow(clippy::range_plus_one)]
-            let value = self.string.value.do_slice(pos..pos + 1);
-
-            value.into_pyobject(vm)
+            self.string.char_at(pos).into_pyobject(vm)
         } else {
             Err(objiter::new_stop_iteration(vm))
         }
@@ -148,12 +380,8 @@ impl PyStringReverseIterator {
     fn next(&self, vm: &VirtualMachine) -> PyResult {
         if self.position.get() > 0 {
             let position: usize = self.position.get() - 1;
-
-            #[allow(clippy::range_plus_one)]
-            let value = self.string.value.do_slice(position..position + 1);
-
             self.position.set(position);
-            value.into_pyobject(vm)
+            self.string.char_at(position).into_pyobject(vm)
         } else {
             Err(objiter::new_stop_iteration(vm))
         }
@@ -188,9 +416,10 @@ impl PyString {
         }
     }
     #[pymethod(name = "__add__")]
-    fn add(&self, rhs: PyObjectRef, vm: &VirtualMachine) -> PyResult<String> {
+    fn add(&self, rhs: PyObjectRef, vm: &VirtualMachine) -> PyResult<PyString> {
         if objtype::isinstance(&rhs, &vm.ctx.str_type()) {
-            Ok(format!("{}{}", self.value, get_value(&rhs)))
+            let (texts, stand_ins) = PyString::unify(&[self, borrow_str(&rhs)], vm)?;
+            Ok(PyString::with_stand_ins(texts.concat(), stand_ins))
         } else {
             Err(vm.new_type_error(format!("Cannot add {} and {}", self, rhs)))
         }
@@ -204,26 +433,28 @@ impl PyString {
     #[pymethod(name = "__eq__")]
     fn eq(&self, rhs: PyObjectRef, vm: &VirtualMachine) -> bool {
         if objtype::isinstance(&rhs, &vm.ctx.str_type()) {
-            self.value == get_value(&rhs)
+            let rhs = borrow_str(&rhs);
+            self.value == rhs.value && self.stand_ins == rhs.stand_ins
         } else {
             false
         }
     }
 
     #[pymethod(name = "__contains__")]
-    fn contains(&self, needle: PyStringRef, _vm: &VirtualMachine) -> bool {
-        self.value.contains(&needle.value)
+    fn contains(&self, needle: PyStringRef, vm: &VirtualMachine) -> PyResult<bool> {
+        let (texts, _) = PyString::unify(&[self, &needle], vm)?;
+        Ok(texts[0].contains(&*texts[1]))
     }
 
     #[pymethod(name = "__getitem__")]
     fn getitem(&self, needle: PyObjectRef, vm: &VirtualMachine) -> PyResult {
-        subscript(vm, &self.value, needle)
+        subscript(vm, self, needle)
     }
 
     #[pymethod(name = "__gt__")]
     fn gt(&self, rhs: PyObjectRef, vm: &VirtualMachine) -> PyResult<bool> {
         if objtype::isinstance(&rhs, &vm.ctx.str_type()) {
-            Ok(self.value > get_value(&rhs))
+            Ok(self.compare(borrow_str(&rhs)) == Ordering::Greater)
         } else {
             Err(vm.new_type_error(format!("Cannot compare {} and {}", self, rhs)))
         }
@@ -232,7 +463,7 @@ impl PyString {
     #[pymethod(name = "__ge__")]
     fn ge(&self, rhs: PyObjectRef, vm: &VirtualMachine) -> PyResult<bool> {
         if objtype::isinstance(&rhs, &vm.ctx.str_type()) {
-            Ok(self.value >= get_value(&rhs))
+            Ok(self.compare(borrow_str(&rhs)) != Ordering::Less)
         } else {
             Err(vm.new_type_error(format!("Cannot compare {} and {}", self, rhs)))
         }
@@ -241,7 +472,7 @@ impl PyString {
     #[pymethod(name = "__lt__")]
     fn lt(&self, rhs: PyObjectRef, vm: &VirtualMachine) -> PyResult<bool> {
         if objtype::isinstance(&rhs, &vm.ctx.str_type()) {
-            Ok(self.value < get_value(&rhs))
+            Ok(self.compare(borrow_str(&rhs)) == Ordering::Less)
         } else {
             Err(vm.new_type_error(format!("Cannot compare {} and {}", self, rhs)))
         }
@@ -250,7 +481,7 @@ impl PyString {
     #[pymethod(name = "__le__")]
     fn le(&self, rhs: PyObjectRef, vm: &VirtualMachine) -> PyResult<bool> {
         if objtype::isinstance(&rhs, &vm.ctx.str_type()) {
-            Ok(self.value <= get_value(&rhs))
+            Ok(self.compare(borrow_str(&rhs)) != Ordering::Greater)
         } else {
             Err(vm.new_type_error(format!("Cannot compare {} and {}", self, rhs)))
         }
@@ -263,11 +494,11 @@ impl PyString {
 
     #[pymethod(name = "__len__")]
     fn len(&self, _vm: &VirtualMachine) -> usize {
-        self.value.chars().count()
+        self.char_len()
     }
 
     #[pymethod(name = "__mul__")]
-    fn mul(&self, val: PyObjectRef, vm: &VirtualMachine) -> PyResult<String> {
+    fn mul(&self, val: PyObjectRef, vm: &VirtualMachine) -> PyResult<PyString> {
         if !objtype::isinstance(&val, &vm.ctx.int_type()) {
             return Err(vm.new_type_error(format!("Cannot multiply {} and {}", self, val)));
         }
@@ -275,14 +506,14 @@ impl PyString {
             .to_isize()
             .map(|multiplier| multiplier.max(0))
             .and_then(|multiplier| multiplier.to_usize())
-            .map(|multiplier| self.value.repeat(multiplier))
+            .map(|multiplier| self.derive(self.value.repeat(multiplier)))
             .ok_or_else(|| {
                 vm.new_overflow_error("cannot fit 'int' into an index-sized integer".to_string())
             })
     }
 
     #[pymethod(name = "__rmul__")]
-    fn rmul(&self, val: PyObjectRef, vm: &VirtualMachine) -> PyResult<String> {
+    fn rmul(&self, val: PyObjectRef, vm: &VirtualMachine) -> PyResult<PyString> {
         self.mul(val, vm)
     }
 
@@ -314,6 +545,8 @@ impl PyString {
             } else if c == '\r' {
                 formatted.push('\\');
                 formatted.push('r');
+            } else if let Some(surrogate) = self.stand_ins.and_then(|s| s.surrogate(c)) {
+                formatted.push_str(&format!("\\u{:04x}", surrogate));
             } else {
                 formatted.push(c);
             }
@@ -323,25 +556,25 @@ impl PyString {
     }
 
     #[pymethod]
-    fn lower(&self, _vm: &VirtualMachine) -> String {
-        self.value.to_lowercase()
+    fn lower(&self, _vm: &VirtualMachine) -> PyString {
+        self.derive(self.value.to_lowercase())
     }
 
     // casefold is much more aggressive than lower
     #[pymethod]
-    fn casefold(&self, _vm: &VirtualMachine) -> String {
-        caseless::default_case_fold_str(&self.value)
+    fn casefold(&self, _vm: &VirtualMachine) -> PyString {
+        self.derive(caseless::default_case_fold_str(&self.value))
     }
 
     #[pymethod]
-    fn upper(&self, _vm: &VirtualMachine) -> String {
-        self.value.to_uppercase()
+    fn upper(&self, _vm: &VirtualMachine) -> PyString {
+        self.derive(self.value.to_uppercase())
     }
 
     #[pymethod]
-    fn capitalize(&self, _vm: &VirtualMachine) -> String {
+    fn capitalize(&self, _vm: &VirtualMachine) -> PyString {
         let (first_part, lower_str) = self.value.split_at(1);
-        format!("{}{}", first_part.to_uppercase(), lower_str)
+        self.derive(format!("{}{}", first_part.to_uppercase(), lower_str))
     }
 
     #[pymethod]
@@ -350,20 +583,17 @@ impl PyString {
         pattern: OptionalArg<PyStringRef>,
         num: OptionalArg<usize>,
         vm: &VirtualMachine,
-    ) -> PyObjectRef {
-        let value = &self.value;
-        let pattern = match pattern {
-            OptionalArg::Present(ref s) => &s.value,
-            OptionalArg::Missing => " ",
-        };
+    ) -> PyResult {
+        let (texts, stand_ins) = self.with_pattern(&pattern, vm)?;
+        let (value, pattern) = (&*texts[0], &*texts[1]);
         let num_splits = num
             .into_option()
             .unwrap_or_else(|| value.split(pattern).count());
         let elements = value
             .splitn(num_splits + 1, pattern)
-            .map(|o| vm.ctx.new_str(o.to_string()))
+            .map(|o| new_str(o.to_string(), stand_ins, vm))
             .collect();
-        vm.ctx.new_list(elements)
+        Ok(vm.ctx.new_list(elements))
     }
 
     #[pymethod]
@@ -372,54 +602,69 @@ impl PyString {
         pattern: OptionalArg<PyStringRef>,
         num: OptionalArg<usize>,
         vm: &VirtualMachine,
-    ) -> PyObjectRef {
-        let value = &self.value;
-        let pattern = match pattern {
-            OptionalArg::Present(ref s) => &s.value,
-            OptionalArg::Missing => " ",
-        };
+    ) -> PyResult {
+        let (texts, stand_ins) = self.with_pattern(&pattern, vm)?;
+        let (value, pattern) = (&*texts[0], &*texts[1]);
         let num_splits = num
             .into_option()
             .unwrap_or_else(|| value.split(pattern).count());
         let mut elements: Vec<_> = value
             .rsplitn(num_splits + 1, pattern)
-            .map(|o| vm.ctx.new_str(o.to_string()))
+            .map(|o| new_str(o.to_string(), stand_ins, vm))
             .collect();
         // Unlike Python rsplit, Rust rsplitn returns an iterator that
         // starts from the end of the string.
         elements.reverse();
-        vm.ctx.new_list(elements)
+        Ok(vm.ctx.new_list(elements))
+    }
+
+    /// The texts of this string and of the pattern to `split` it by, which
+    /// is a space if missing, with the same stand-ins.
+    fn with_pattern<'a>(
+        &'a self,
+        pattern: &'a OptionalArg<PyStringRef>,
+        vm: &VirtualMachine,
+    ) -> PyResult<(Vec<Cow<'a, str>>, Option<StandIns>)> {
+        match pattern {
+            OptionalArg::Present(ref pattern) => PyString::unify(&[self, pattern], vm),
+            OptionalArg::Missing => Ok((
+                vec![Cow::Borrowed(self.as_str()), Cow::Borrowed(" ")],
+                self.stand_ins,
+            )),
+        }
     }
 
     #[pymethod]
-    fn strip(&self, chars: OptionalArg<PyStringRef>, _vm: &VirtualMachine) -> String {
+    fn strip(&self, chars: OptionalArg<PyStringRef>, vm: &VirtualMachine) -> PyResult<PyString> {
         let chars = match chars {
-            OptionalArg::Present(ref chars) => &chars.value,
-            OptionalArg::Missing => return self.value.trim().to_string(),
+            OptionalArg::Present(ref chars) => chars,
+            OptionalArg::Missing => return Ok(self.derive(self.value.trim().to_string())),
         };
-        self.value.trim_matches(|c| chars.contains(c)).to_string()
+        let (texts, stand_ins) = PyString::unify(&[self, chars], vm)?;
+        let stripped = texts[0].trim_matches(|c| texts[1].contains(c));
+        Ok(PyString::with_stand_ins(stripped.to_string(), stand_ins))
     }
 
     #[pymethod]
-    fn lstrip(&self, chars: OptionalArg<PyStringRef>, _vm: &VirtualMachine) -> String {
+    fn lstrip(&self, chars: OptionalArg<PyStringRef>, vm: &VirtualMachine) -> PyResult<PyString> {
         let chars = match chars {
-            OptionalArg::Present(ref chars) => &chars.value,
-            OptionalArg::Missing => return self.value.trim_start().to_string(),
+            OptionalArg::Present(ref chars) => chars,
+            OptionalArg::Missing => return Ok(self.derive(self.value.trim_start().to_string())),
         };
-        self.value
-            .trim_start_matches(|c| chars.contains(c))
-            .to_string()
+        let (texts, stand_ins) = PyString::unify(&[self, chars], vm)?;
+        let stripped = texts[0].trim_start_matches(|c| texts[1].contains(c));
+        Ok(PyString::with_stand_ins(stripped.to_string(), stand_ins))
     }
 
     #[pymethod]
-    fn rstrip(&self, chars: OptionalArg<PyStringRef>, _vm: &VirtualMachine) -> String {
+    fn rstrip(&self, chars: OptionalArg<PyStringRef>, vm: &VirtualMachine) -> PyResult<PyString> {
         let chars = match chars {
-            OptionalArg::Present(ref chars) => &chars.value,
-            OptionalArg::Missing => return self.value.trim_end().to_string(),
+            OptionalArg::Present(ref chars) => chars,
+            OptionalArg::Missing => return Ok(self.derive(self.value.trim_end().to_string())),
         };
-        self.value
-            .trim_end_matches(|c| chars.contains(c))
-            .to_string()
+        let (texts, stand_ins) = PyString::unify(&[self, chars], vm)?;
+        let stripped = texts[0].trim_end_matches(|c| texts[1].contains(c));
+        Ok(PyString::with_stand_ins(stripped.to_string(), stand_ins))
     }
 
     #[pymethod]
@@ -430,11 +675,14 @@ impl PyString {
         end: OptionalArg<isize>,
         vm: &VirtualMachine,
     ) -> PyResult<bool> {
-        if let Some((start, end)) = adjust_indices(start, end, self.value.len()) {
-            let value = &self.value[start..end];
+        if let Some(range) = self.byte_range(start, end) {
             single_or_tuple_any(
                 suffix,
-                |s: PyStringRef| Ok(value.ends_with(&s.value)),
+                |s: PyStringRef| {
+                    // Stand-ins are all four bytes long, so `range` stays put.
+                    let (texts, _) = PyString::unify(&[self, &s], vm)?;
+                    Ok(texts[0][range.clone()].ends_with(&*texts[1]))
+                },
                 |o| {
                     format!(
                         "endswith first arg must be str or a tuple of str, not {}",
@@ -456,11 +704,14 @@ impl PyString {
         end: OptionalArg<isize>,
         vm: &VirtualMachine,
     ) -> PyResult<bool> {
-        if let Some((start, end)) = adjust_indices(start, end, self.value.len()) {
-            let value = &self.value[start..end];
+        if let Some(range) = self.byte_range(start, end) {
             single_or_tuple_any(
                 prefix,
-                |s: PyStringRef| Ok(value.starts_with(&s.value)),
+                |s: PyStringRef| {
+                    // Stand-ins are all four bytes long, so `range` stays put.
+                    let (texts, _) = PyString::unify(&[self, &s], vm)?;
+                    Ok(texts[0][range.clone()].starts_with(&*texts[1]))
+                },
                 |o| {
                     format!(
                         "startswith first arg must be str or a tuple of str, not {}",
@@ -476,12 +727,18 @@ impl PyString {
 
     #[pymethod]
     fn isalnum(&self, _vm: &VirtualMachine) -> bool {
-        !self.value.is_empty() && self.value.chars().all(char::is_alphanumeric)
+        !self.value.is_empty()
+            && self
+                .chars_or_surrogates()
+                .all(|c| c.map_or(false, char::is_alphanumeric))
     }
 
     #[pymethod]
     fn isnumeric(&self, _vm: &VirtualMachine) -> bool {
-        !self.value.is_empty() && self.value.chars().all(char::is_numeric)
+        !self.value.is_empty()
+            && self
+                .chars_or_surrogates()
+                .all(|c| c.map_or(false, char::is_numeric))
     }
 
     #[pymethod]
@@ -494,10 +751,9 @@ impl PyString {
         if self.value.is_empty() {
             false
         } else {
-            self.value
-                .chars()
-                .filter(|c| !c.is_digit(10))
-                .all(|c| valid_unicodes.contains(&(c as u16)))
+            self.chars_or_surrogates()
+                .filter(|c| c.map_or(true, |c| !c.is_digit(10)))
+                .all(|c| c.map_or(false, |c| valid_unicodes.contains(&(c as u16))))
         }
     }
 
@@ -506,7 +762,8 @@ impl PyString {
         if self.value.is_empty() {
             false
         } else {
-            self.value.chars().all(|c| c.is_ascii_digit())
+            self.chars_or_surrogates()
+                .all(|c| c.map_or(false, |c| c.is_ascii_digit()))
         }
     }
 
@@ -515,7 +772,7 @@ impl PyString {
         let format_string_text = &self.value;
         let format_string = CFormatString::from_str(format_string_text)
             .map_err(|err| vm.new_value_error(format!("{}", err)))?;
-        do_cformat(vm, format_string, values.clone())
+        do_cformat(vm, self, format_string, values.clone())
     }
 
     #[pymethod]
@@ -535,9 +792,9 @@ impl PyString {
                 actual_type
             )));
         }
-        let format_string_text = get_value(zelf);
-        match FormatString::from_str(format_string_text.as_str()) {
-            Ok(format_string) => perform_format(vm, &format_string, &args),
+        let zelf = borrow_str(zelf);
+        match FormatString::from_str(zelf.as_str()) {
+            Ok(format_string) => perform_format(vm, zelf, &format_string, &args),
             Err(err) => match err {
                 FormatParseError::UnmatchedBracket => {
                     Err(vm.new_value_error("expected '}' before end of string".to_string()))
@@ -550,7 +807,7 @@ impl PyString {
     /// Return a titlecased version of the string where words start with an
     /// uppercase character and the remaining characters are lowercase.
     #[pymethod]
-    fn title(&self, _vm: &VirtualMachine) -> String {
+    fn title(&self, _vm: &VirtualMachine) -> PyString {
         let mut title = String::with_capacity(self.value.len());
         let mut previous_is_cased = false;
         for c in self.value.chars() {
@@ -573,11 +830,11 @@ impl PyString {
                 title.push(c);
             }
         }
-        title
+        self.derive(title)
     }
 
     #[pymethod]
-    fn swapcase(&self, _vm: &VirtualMachine) -> String {
+    fn swapcase(&self, _vm: &VirtualMachine) -> PyString {
         let mut swapped_str = String::with_capacity(self.value.len());
         for c in self.value.chars() {
             // to_uppercase returns an iterator, to_ascii_uppercase returns the char
@@ -589,12 +846,15 @@ impl PyString {
                 swapped_str.push(c);
             }
         }
-        swapped_str
+        self.derive(swapped_str)
     }
 
     #[pymethod]
     fn isalpha(&self, _vm: &VirtualMachine) -> bool {
-        !self.value.is_empty() && self.value.chars().all(char::is_alphanumeric)
+        !self.value.is_empty()
+            && self
+                .chars_or_surrogates()
+                .all(|c| c.map_or(false, char::is_alphanumeric))
     }
 
     #[pymethod]
@@ -603,12 +863,14 @@ impl PyString {
         old: PyStringRef,
         new: PyStringRef,
         num: OptionalArg<usize>,
-        _vm: &VirtualMachine,
-    ) -> String {
-        match num.into_option() {
-            Some(num) => self.value.replacen(&old.value, &new.value, num),
-            None => self.value.replace(&old.value, &new.value),
-        }
+        vm: &VirtualMachine,
+    ) -> PyResult<PyString> {
+        let (texts, stand_ins) = PyString::unify(&[self, &old, &new], vm)?;
+        let replaced = match num.into_option() {
+            Some(num) => texts[0].replacen(&*texts[1], &texts[2], num),
+            None => texts[0].replace(&*texts[1], &texts[2]),
+        };
+        Ok(PyString::with_stand_ins(replaced, stand_ins))
     }
 
     /// Return true if all characters in the string are printable or the string is empty,
@@ -628,9 +890,10 @@ impl PyString {
     ///   * Zs (Separator, Space) other than ASCII space('\x20').
     #[pymethod]
     fn isprintable(&self, _vm: &VirtualMachine) -> bool {
-        self.value.chars().all(|c| match c {
-            '\u{0020}' => true,
-            _ => !(c.is_other_control() | c.is_separator()),
+        self.chars_or_surrogates().all(|c| match c {
+            Some('\u{0020}') => true,
+            Some(c) => !(c.is_other_control() | c.is_separator()),
+            None => false,
         })
     }
 
@@ -638,32 +901,36 @@ impl PyString {
     // which is why isspace is using is_ascii_whitespace. Same for isupper & islower
     #[pymethod]
     fn isspace(&self, _vm: &VirtualMachine) -> bool {
-        !self.value.is_empty() && self.value.chars().all(|c| c.is_ascii_whitespace())
+        !self.value.is_empty()
+            && self
+                .chars_or_surrogates()
+                .all(|c| c.map_or(false, |c| c.is_ascii_whitespace()))
     }
 
     #[pymethod]
     fn isupper(&self, _vm: &VirtualMachine) -> bool {
         !self.value.is_empty()
             && self
-                .value
-                .chars()
-                .filter(|x| !x.is_ascii_whitespace())
-                .all(char::is_uppercase)
+                .chars_or_surrogates()
+                .filter(|x| x.map_or(true, |x| !x.is_ascii_whitespace()))
+                .all(|c| c.map_or(false, char::is_uppercase))
     }
 
     #[pymethod]
     fn islower(&self, _vm: &VirtualMachine) -> bool {
         !self.value.is_empty()
             && self
-                .value
-                .chars()
-                .filter(|x| !x.is_ascii_whitespace())
-                .all(char::is_lowercase)
+                .chars_or_surrogates()
+                .filter(|x| x.map_or(true, |x| !x.is_ascii_whitespace()))
+                .all(|c| c.map_or(false, char::is_lowercase))
     }
 
     #[pymethod]
     fn isascii(&self, _vm: &VirtualMachine) -> bool {
-        !self.value.is_empty() && self.value.chars().all(|c| c.is_ascii())
+        !self.value.is_empty()
+            && self
+                .chars_or_surrogates()
+                .all(|c| c.map_or(false, |c| c.is_ascii()))
     }
 
     // doesn't implement keep new line delimiter just yet
@@ -672,24 +939,16 @@ impl PyString {
         let elements = self
             .value
             .split('\n')
-            .map(|e| vm.ctx.new_str(e.to_string()))
+            .map(|e| new_str(e.to_string(), self.stand_ins, vm))
             .collect();
         vm.ctx.new_list(elements)
     }
 
     #[pymethod]
-    fn join(&self, iterable: PyIterable<PyStringRef>, vm: &VirtualMachine) -> PyResult<String> {
-        let mut joined = String::new();
-
-        for (idx, elem) in iterable.iter(vm)?.enumerate() {
-            let elem = elem?;
-            if idx != 0 {
-                joined.push_str(&self.value);
-            }
-            joined.push_str(&elem.value)
-        }
-
-        Ok(joined)
+    fn join(&self, iterable: PyIterable<PyStringRef>, vm: &VirtualMachine) -> PyResult<PyString> {
+        let elements = iterable.iter(vm)?.collect::<PyResult<Vec<_>>>()?;
+        let elements: Vec<&PyString> = elements.iter().map(|elem| &**elem).collect();
+        PyString::concat(&elements, self, vm)
     }
 
     #[pymethod]
@@ -698,16 +957,16 @@ impl PyString {
         sub: PyStringRef,
         start: OptionalArg<isize>,
         end: OptionalArg<isize>,
-        _vm: &VirtualMachine,
-    ) -> isize {
-        let value = &self.value;
-        if let Some((start, end)) = adjust_indices(start, end, value.len()) {
-            match value[start..end].find(&sub.value) {
-                Some(num) => (start + num) as isize,
+        vm: &VirtualMachine,
+    ) -> PyResult<isize> {
+        if let Some(range) = self.byte_range(start, end) {
+            let (texts, _) = PyString::unify(&[self, &sub], vm)?;
+            Ok(match texts[0][range.clone()].find(&*texts[1]) {
+                Some(num) => self.char_position(range.start + num) as isize,
                 None => -1 as isize,
-            }
+            })
         } else {
-            -1 as isize
+            Ok(-1 as isize)
         }
     }
 
@@ -717,16 +976,16 @@ impl PyString {
         sub: PyStringRef,
         start: OptionalArg<isize>,
         end: OptionalArg<isize>,
-        _vm: &VirtualMachine,
-    ) -> isize {
-        let value = &self.value;
-        if let Some((start, end)) = adjust_indices(start, end, value.len()) {
-            match value[start..end].rfind(&sub.value) {
-                Some(num) => (start + num) as isize,
+        vm: &VirtualMachine,
+    ) -> PyResult<isize> {
+        if let Some(range) = self.byte_range(start, end) {
+            let (texts, _) = PyString::unify(&[self, &sub], vm)?;
+            Ok(match texts[0][range.clone()].rfind(&*texts[1]) {
+                Some(num) => self.char_position(range.start + num) as isize,
                 None => -1 as isize,
-            }
+            })
         } else {
-            -1 as isize
+            Ok(-1 as isize)
         }
     }
 
@@ -738,10 +997,10 @@ impl PyString {
         end: OptionalArg<isize>,
         vm: &VirtualMachine,
     ) -> PyResult<usize> {
-        let value = &self.value;
-        if let Some((start, end)) = adjust_indices(start, end, value.len()) {
-            match value[start..end].find(&sub.value) {
-                Some(num) => Ok(start + num),
+        if let Some(range) = self.byte_range(start, end) {
+            let (texts, _) = PyString::unify(&[self, &sub], vm)?;
+            match texts[0][range.clone()].find(&*texts[1]) {
+                Some(num) => Ok(self.char_position(range.start + num)),
                 None => Err(vm.new_value_error("substring not found".to_string())),
             }
         } else {
@@ -757,10 +1016,10 @@ impl PyString {
         end: OptionalArg<isize>,
         vm: &VirtualMachine,
     ) -> PyResult<usize> {
-        let value = &self.value;
-        if let Some((start, end)) = adjust_indices(start, end, value.len()) {
-            match value[start..end].rfind(&sub.value) {
-                Some(num) => Ok(start + num),
+        if let Some(range) = self.byte_range(start, end) {
+            let (texts, _) = PyString::unify(&[self, &sub], vm)?;
+            match texts[0][range.clone()].rfind(&*texts[1]) {
+                Some(num) => Ok(self.char_position(range.start + num)),
                 None => Err(vm.new_value_error("substring not found".to_string())),
             }
         } else {
@@ -769,42 +1028,42 @@ impl PyString {
     }
 
     #[pymethod]
-    fn partition(&self, sub: PyStringRef, vm: &VirtualMachine) -> PyObjectRef {
-        let value = &self.value;
-        let sub = &sub.value;
+    fn partition(&self, sub: PyStringRef, vm: &VirtualMachine) -> PyResult {
+        let (texts, stand_ins) = PyString::unify(&[self, &sub], vm)?;
+        let (value, sub) = (&*texts[0], &*texts[1]);
         let mut new_tup = Vec::new();
         if value.contains(sub) {
             new_tup = value
                 .splitn(2, sub)
-                .map(|s| vm.ctx.new_str(s.to_string()))
+                .map(|s| new_str(s.to_string(), stand_ins, vm))
                 .collect();
-            new_tup.insert(1, vm.ctx.new_str(sub.clone()));
+            new_tup.insert(1, new_str(sub.to_string(), stand_ins, vm));
         } else {
-            new_tup.push(vm.ctx.new_str(value.clone()));
+            new_tup.push(new_str(value.to_string(), stand_ins, vm));
             new_tup.push(vm.ctx.new_str("".to_string()));
             new_tup.push(vm.ctx.new_str("".to_string()));
         }
-        vm.ctx.new_tuple(new_tup)
+        Ok(vm.ctx.new_tuple(new_tup))
     }
 
     #[pymethod]
-    fn rpartition(&self, sub: PyStringRef, vm: &VirtualMachine) -> PyObjectRef {
-        let value = &self.value;
-        let sub = &sub.value;
+    fn rpartition(&self, sub: PyStringRef, vm: &VirtualMachine) -> PyResult {
+        let (texts, stand_ins) = PyString::unify(&[self, &sub], vm)?;
+        let (value, sub) = (&*texts[0], &*texts[1]);
         let mut new_tup = Vec::new();
         if value.contains(sub) {
             new_tup = value
                 .rsplitn(2, sub)
-                .map(|s| vm.ctx.new_str(s.to_string()))
+                .map(|s| new_str(s.to_string(), stand_ins, vm))
                 .collect();
             new_tup.swap(0, 1); // so it's in the right order
-            new_tup.insert(1, vm.ctx.new_str(sub.clone()));
+            new_tup.insert(1, new_str(sub.to_string(), stand_ins, vm));
         } else {
             new_tup.push(vm.ctx.new_str("".to_string()));
             new_tup.push(vm.ctx.new_str("".to_string()));
-            new_tup.push(vm.ctx.new_str(value.clone()));
+            new_tup.push(new_str(value.to_string(), stand_ins, vm));
         }
-        vm.ctx.new_tuple(new_tup)
+        Ok(vm.ctx.new_tuple(new_tup))
     }
 
     /// Return `true` if the sequence is ASCII titlecase and the sequence is not
@@ -817,21 +1076,23 @@ impl PyString {
 
         let mut cased = false;
         let mut previous_is_cased = false;
-        for c in self.value.chars() {
-            if c.is_uppercase() || c.is_titlecase() {
-                if previous_is_cased {
-                    return false;
+        for c in self.chars_or_surrogates() {
+            match c {
+                Some(c) if c.is_uppercase() || c.is_titlecase() => {
+                    if previous_is_cased {
+                        return false;
+                    }
+                    previous_is_cased = true;
+                    cased = true;
                 }
-                previous_is_cased = true;
-                cased = true;
-            } else if c.is_lowercase() {
-                if !previous_is_cased {
-                    return false;
+                Some(c) if c.is_lowercase() => {
+                    if !previous_is_cased {
+                        return false;
+                    }
+                    previous_is_cased = true;
+                    cased = true;
                 }
-                previous_is_cased = true;
-                cased = true;
-            } else {
-                previous_is_cased = false;
+                _ => previous_is_cased = false,
             }
         }
         cased
@@ -843,41 +1104,54 @@ impl PyString {
         sub: PyStringRef,
         start: OptionalArg<isize>,
         end: OptionalArg<isize>,
-        _vm: &VirtualMachine,
-    ) -> usize {
-        let value = &self.value;
-        if let Some((start, end)) = adjust_indices(start, end, value.len()) {
-            self.value[start..end].matches(&sub.value).count()
+        vm: &VirtualMachine,
+    ) -> PyResult<usize> {
+        if let Some(range) = self.byte_range(start, end) {
+            let (texts, _) = PyString::unify(&[self, &sub], vm)?;
+            Ok(texts[0][range].matches(&*texts[1]).count())
         } else {
-            0
+            Ok(0)
         }
     }
 
     #[pymethod]
-    fn zfill(&self, len: usize, _vm: &VirtualMachine) -> String {
+    fn zfill(&self, len: usize, _vm: &VirtualMachine) -> PyString {
         let value = &self.value;
-        if len <= value.len() {
-            value.to_string()
+        let value_len = self.char_len();
+        if len <= value_len {
+            self.clone()
         } else {
-            format!("{}{}", "0".repeat(len - value.len()), value)
+            self.derive(format!("{}{}", "0".repeat(len - value_len), value))
         }
     }
 
-    fn get_fill_char<'a>(
-        rep: &'a OptionalArg<PyStringRef>,
+    /// This string with `left` and `right` copies of the fill character
+    /// `rep`, or of a space if it is missing, on either side.
+    fn pad(
+        &self,
+        left: usize,
+        right: usize,
+        rep: &OptionalArg<PyStringRef>,
         vm: &VirtualMachine,
-    ) -> PyResult<&'a str> {
-        let rep_str = match rep {
-            OptionalArg::Present(ref st) => &st.value,
-            OptionalArg::Missing => " ",
+    ) -> PyResult<PyString> {
+        let space = PyString::from(" ");
+        let rep = match rep {
+            OptionalArg::Present(ref rep) => rep,
+            OptionalArg::Missing => &space,
         };
-        if rep_str.len() == 1 {
-            Ok(rep_str)
-        } else {
-            Err(vm.new_type_error(
+        if rep.char_len() != 1 {
+            return Err(vm.new_type_error(
                 "The fill character must be exactly one character long".to_string(),
-            ))
+            ));
         }
+        let (texts, stand_ins) = PyString::unify(&[self, rep], vm)?;
+        let padded = format!(
+            "{}{}{}",
+            texts[1].repeat(left),
+            texts[0],
+            texts[1].repeat(right)
+        );
+        Ok(PyString::with_stand_ins(padded, stand_ins))
     }
 
     #[pymethod]
@@ -886,14 +1160,9 @@ impl PyString {
         len: usize,
         rep: OptionalArg<PyStringRef>,
         vm: &VirtualMachine,
-    ) -> PyResult<String> {
-        let value = &self.value;
-        let rep_char = Self::get_fill_char(&rep, vm)?;
-        if len <= value.len() {
-            Ok(value.to_string())
-        } else {
-            Ok(format!("{}{}", value, rep_char.repeat(len - value.len())))
-        }
+    ) -> PyResult<PyString> {
+        let value_len = self.char_len();
+        self.pad(0, len.saturating_sub(value_len), &rep, vm)
     }
 
     #[pymethod]
@@ -902,14 +1171,9 @@ impl PyString {
         len: usize,
         rep: OptionalArg<PyStringRef>,
         vm: &VirtualMachine,
-    ) -> PyResult<String> {
-        let value = &self.value;
-        let rep_char = Self::get_fill_char(&rep, vm)?;
-        if len <= value.len() {
-            Ok(value.to_string())
-        } else {
-            Ok(format!("{}{}", rep_char.repeat(len - value.len()), value))
-        }
+    ) -> PyResult<PyString> {
+        let value_len = self.char_len();
+        self.pad(len.saturating_sub(value_len), 0, &rep, vm)
     }
 
     #[pymethod]
@@ -918,13 +1182,11 @@ impl PyString {
         len: usize,
         rep: OptionalArg<PyStringRef>,
         vm: &VirtualMachine,
-    ) -> PyResult<String> {
-        let value = &self.value;
-        let rep_char = Self::get_fill_char(&rep, vm)?;
-        let value_len = self.value.chars().count();
+    ) -> PyResult<PyString> {
+        let value_len = self.char_len();
 
         if len <= value_len {
-            return Ok(value.to_string());
+            return self.pad(0, 0, &rep, vm);
         }
         let diff: usize = len - value_len;
         let mut left_buff: usize = diff / 2;
@@ -937,16 +1199,11 @@ impl PyString {
         if diff % 2 != 0 && value_len % 2 != 0 {
             right_buff += 1
         }
-        Ok(format!(
-            "{}{}{}",
-            rep_char.repeat(left_buff),
-            value,
-            rep_char.repeat(right_buff)
-        ))
+        self.pad(left_buff, right_buff, &rep, vm)
     }
 
     #[pymethod]
-    fn expandtabs(&self, tab_stop: OptionalArg<usize>, _vm: &VirtualMachine) -> String {
+    fn expandtabs(&self, tab_stop: OptionalArg<usize>, _vm: &VirtualMachine) -> PyString {
         let tab_stop = tab_stop.into_option().unwrap_or(8 as usize);
         let mut expanded_str = String::with_capacity(self.value.len());
         let mut tab_size = tab_stop;
@@ -966,42 +1223,58 @@ impl PyString {
                 tab_size += tab_stop;
             }
         }
-        expanded_str
+        self.derive(expanded_str)
     }
 
     #[pymethod]
     fn isidentifier(&self, _vm: &VirtualMachine) -> bool {
-        let mut chars = self.value.chars();
-        let is_identifier_start = match chars.next() {
-            Some('_') => true,
-            Some(c) => UnicodeXID::is_xid_start(c),
-            None => false,
-        };
-        // a string is not an identifier if it has whitespace or starts with a number
-        is_identifier_start && chars.all(UnicodeXID::is_xid_continue)
+        if self.chars_or_surrogates().any(|c| c.is_none()) {
+            return false;
+        }
+        // Use the same rules as the lexer, so that names which parse are identifiers
+        #[cfg(feature = "rustpython-parser")]
+        {
+            rustpython_parser::lexer::is_identifier(&self.value)
+        }
+        #[cfg(not(feature = "rustpython-parser"))]
+        {
+            use unicode_xid::UnicodeXID;
+
+            let mut chars = self.value.chars();
+            let is_identifier_start = match chars.next() {
+                Some('_') => true,
+                Some(c) => UnicodeXID::is_xid_start(c),
+                None => false,
+            };
+            // a string is not an identifier if it has whitespace or starts with a number
+            is_identifier_start && chars.all(UnicodeXID::is_xid_continue)
+        }
     }
 
     // https://docs.python.org/3/library/stdtypes.html#str.translate
     #[pymethod]
-    fn translate(&self, table: PyObjectRef, vm: &VirtualMachine) -> PyResult<String> {
+    fn translate(&self, table: PyObjectRef, vm: &VirtualMachine) -> PyResult<PyString> {
         vm.get_method_or_type_error(table.clone(), "__getitem__", || {
             format!("'{}' object is not subscriptable", table.class().name)
         })?;
 
-        let mut translated = String::new();
-        for c in self.value.chars() {
-            match table.get_item(c as u32, vm) {
+        let mut translated = surrogates::Builder::default();
+        for (c, code_point) in self.value.chars().zip(self.code_points()) {
+            match table.get_item(code_point, vm) {
                 Ok(value) => {
                     if let Some(text) = value.payload::<PyString>() {
-                        translated.push_str(&text.value);
+                        for code_point in text.code_points() {
+                            translated.push_code_point(code_point);
+                        }
                     } else if let Some(bigint) = value.payload::<PyInt>() {
-                        match bigint.as_bigint().to_u32().and_then(std::char::from_u32) {
-                            Some(ch) => translated.push(ch as char),
-                            None => {
-                                return Err(vm.new_value_error(
-                                    "character mapping must be in range(0x110000)".to_owned(),
-                                ));
-                            }
+                        let pushed = bigint
+                            .as_bigint()
+                            .to_u32()
+                            .map_or(false, |code_point| translated.push_code_point(code_point));
+                        if !pushed {
+                            return Err(vm.new_value_error(
+                                "character mapping must be in range(0x110000)".to_owned(),
+                            ));
                         }
                     } else if value.payload::<PyNone>().is_some() {
                         // Do Nothing
@@ -1011,10 +1284,13 @@ impl PyString {
                         ));
                     }
                 }
-                _ => translated.push(c),
+                _ if code_point == c as u32 => translated.push(c),
+                _ => translated.push_surrogate(code_point),
             }
         }
-        Ok(translated)
+        translated
+            .finish()
+            .ok_or_else(|| surrogates::no_stand_ins_error(vm))
     }
 
     #[pymethod]
@@ -1029,12 +1305,12 @@ impl PyString {
             match dict_or_str.downcast::<PyString>() {
                 Ok(from_str) => {
                     if to_str.len(vm) == from_str.len(vm) {
-                        for (c1, c2) in from_str.value.chars().zip(to_str.value.chars()) {
-                            new_dict.set_item(c1 as u32, vm.new_int(c2 as u32), vm)?;
+                        for (c1, c2) in from_str.code_points().zip(to_str.code_points()) {
+                            new_dict.set_item(c1, vm.new_int(c2), vm)?;
                         }
                         if let OptionalArg::Present(none_str) = none_str {
-                            for c in none_str.value.chars() {
-                                new_dict.set_item(c as u32, vm.get_none(), vm)?;
+                            for c in none_str.code_points() {
+                                new_dict.set_item(c, vm.get_none(), vm)?;
                             }
                         }
                         new_dict.into_pyobject(vm)
@@ -1058,7 +1334,7 @@ impl PyString {
                             new_dict.set_item(num.as_bigint().to_i32(), val, vm)?;
                         } else if let Some(string) = key.payload::<PyString>() {
                             if string.len(vm) == 1 {
-                                let num_value = string.value.chars().next().unwrap() as u32;
+                                let num_value = string.code_points().next().unwrap();
                                 new_dict.set_item(num_value, val, vm)?;
                             } else {
                                 return Err(vm.new_value_error(
@@ -1080,7 +1356,7 @@ impl PyString {
     fn encode(
         &self,
         encoding: OptionalArg<PyObjectRef>,
-        _errors: OptionalArg<PyObjectRef>,
+        errors: OptionalArg<PyStringRef>,
         vm: &VirtualMachine,
     ) -> PyResult {
         let encoding = encoding.map_or_else(
@@ -1097,7 +1373,9 @@ impl PyString {
             },
         )?;
 
-        let encoded = PyBytes::from_string(&self.value, &encoding, vm)?;
+        let errors = errors.into_option();
+        let errors = errors.as_ref().map_or("strict", |e| e.as_str());
+        let encoded = PyBytes::from_string(self, &encoding, errors, vm)?;
         Ok(encoded.into_pyobject(vm)?)
     }
 
@@ -1111,7 +1389,7 @@ impl PyString {
 
     #[pymethod(name = "__reversed__")]
     fn reversed(zelf: PyRef<Self>, _vm: &VirtualMachine) -> PyStringReverseIterator {
-        let begin = zelf.value.chars().count();
+        let begin = zelf.char_len();
 
         PyStringReverseIterator {
             position: Cell::new(begin),
@@ -1159,6 +1437,17 @@ pub fn borrow_value(obj: &PyObjectRef) -> &str {
     &obj.payload::<PyString>().unwrap().value
 }
 
+pub fn borrow_str(obj: &PyObjectRef) -> &PyString {
+    obj.payload::<PyString>().unwrap()
+}
+
+/// A new `str` object of `text`, holding lone surrogates as `stand_ins`.
+fn new_str(text: String, stand_ins: Option<StandIns>, vm: &VirtualMachine) -> PyObjectRef {
+    PyString::with_stand_ins(text, stand_ins)
+        .into_ref(vm)
+        .into_object()
+}
+
 fn count_char(s: &str, c: char) -> usize {
     s.chars().filter(|x| *x == c).count()
 }
@@ -1190,7 +1479,7 @@ fn do_cformat_specifier(
     vm: &VirtualMachine,
     format_spec: &mut CFormatSpec,
     obj: PyObjectRef,
-) -> Result<String, PyObjectRef> {
+) -> Result<PyString, PyObjectRef> {
     use CNumberType::*;
     // do the formatting by type
     let format_type = &format_spec.format_type;
@@ -1202,7 +1491,8 @@ fn do_cformat_specifier(
                 CFormatPreconversor::Repr => vm.call_method(&obj.clone(), "__repr__", vec![])?,
                 CFormatPreconversor::Ascii => vm.call_method(&obj.clone(), "__repr__", vec![])?,
             };
-            Ok(format_spec.format_string(get_value(&result)))
+            let result = borrow_str(&result);
+            Ok(result.derive(format_spec.format_string(result.value.clone())))
         }
         CFormatType::Number(_) => {
             if !objtype::isinstance(&obj, &vm.ctx.int_type()) {
@@ -1218,25 +1508,26 @@ fn do_cformat_specifier(
                     obj.class()
                 )));
             }
-            Ok(format_spec.format_number(objint::get_value(&obj)))
+            Ok(PyString::from(
+                format_spec.format_number(objint::get_value(&obj)),
+            ))
         }
         CFormatType::Character => {
             let char_string = {
                 if objtype::isinstance(&obj, &vm.ctx.int_type()) {
                     // BigInt truncation is fine in this case because only the unicode range is relevant
                     match objint::get_value(&obj).to_u32().and_then(char::from_u32) {
-                        Some(value) => Ok(value.to_string()),
+                        Some(value) => Ok(PyString::from(value.to_string())),
                         None => {
                             Err(vm.new_overflow_error("%c arg not in range(0x110000)".to_string()))
                         }
                     }
                 } else if objtype::isinstance(&obj, &vm.ctx.str_type()) {
-                    let s: String = get_value(&obj);
-                    let num_chars = s.chars().count();
-                    if num_chars != 1 {
+                    let s = borrow_str(&obj);
+                    if s.char_len() != 1 {
                         Err(vm.new_type_error("%c requires int or char".to_string()))
                     } else {
-                        Ok(s.chars().next().unwrap().to_string())
+                        Ok(s.clone())
                     }
                 } else {
                     // TODO re-arrange this block so this error is only created once
@@ -1244,7 +1535,7 @@ fn do_cformat_specifier(
                 }
             }?;
             format_spec.precision = Some(CFormatQuantity::Amount(1));
-            Ok(format_spec.format_string(char_string))
+            Ok(char_string.derive(format_spec.format_string(char_string.value.clone())))
         }
         _ => Err(vm.new_not_implemented_error(format!(
             "Not yet implemented for %{}",
@@ -1285,10 +1576,11 @@ fn try_update_quantity_from_tuple(
 
 fn do_cformat(
     vm: &VirtualMachine,
+    zelf: &PyString,
     mut format_string: CFormatString,
     values_obj: PyObjectRef,
 ) -> PyResult {
-    let mut final_string = String::new();
+    let mut parts = Vec::new();
     let num_specifiers = format_string
         .format_parts
         .iter()
@@ -1330,7 +1622,7 @@ fn do_cformat(
 
     let mut tuple_index: usize = 0;
     for (_, part) in &mut format_string.format_parts {
-        let result_string: String = match part {
+        let result_string: PyString = match part {
             CFormatPart::Spec(format_spec) => {
                 // try to get the object
                 let obj: PyObjectRef = match &format_spec.mapping_key {
@@ -1369,9 +1661,9 @@ fn do_cformat(
                 };
                 do_cformat_specifier(vm, format_spec, obj)
             }
-            CFormatPart::Literal(literal) => Ok(literal.clone()),
+            CFormatPart::Literal(literal) => Ok(zelf.derive(literal.clone())),
         }?;
-        final_string.push_str(&result_string);
+        parts.push(result_string);
     }
 
     // check that all arguments were converted
@@ -1385,15 +1677,16 @@ fn do_cformat(
             vm.new_type_error("not all arguments converted during string formatting".to_string())
         );
     }
-    Ok(vm.ctx.new_str(final_string))
+    concat_parts(&parts, vm)
 }
 
 fn perform_format(
     vm: &VirtualMachine,
+    zelf: &PyString,
     format_string: &FormatString,
     arguments: &PyFuncArgs,
 ) -> PyResult {
-    let mut final_string = String::new();
+    let mut parts = Vec::new();
     if format_string.format_parts.iter().any(FormatPart::is_auto)
         && format_string.format_parts.iter().any(FormatPart::is_index)
     {
@@ -1404,7 +1697,7 @@ fn perform_format(
     }
     let mut auto_argument_index: usize = 1;
     for part in &format_string.format_parts {
-        let result_string: String = match part {
+        let result_string: PyString = match part {
             FormatPart::AutoSpec(format_spec) => {
                 let result = match arguments.args.get(auto_argument_index) {
                     Some(argument) => call_object_format(vm, argument.clone(), &format_spec)?,
@@ -1413,7 +1706,7 @@ fn perform_format(
                     }
                 };
                 auto_argument_index += 1;
-                get_value(&result)
+                borrow_str(&result).clone()
             }
             FormatPart::IndexSpec(index, format_spec) => {
                 let result = match arguments.args.get(*index + 1) {
@@ -1422,7 +1715,7 @@ fn perform_format(
                         return Err(vm.new_index_error("tuple index out of range".to_string()));
                     }
                 };
-                get_value(&result)
+                borrow_str(&result).clone()
             }
             FormatPart::KeywordSpec(keyword, format_spec) => {
                 let result = match arguments.get_optional_kwarg(&keyword) {
@@ -1431,84 +1724,67 @@ fn perform_format(
                         return Err(vm.new_key_error(vm.new_str(keyword.to_string())));
                     }
                 };
-                get_value(&result)
+                borrow_str(&result).clone()
             }
-            FormatPart::Literal(literal) => literal.clone(),
+            FormatPart::Literal(literal) => zelf.derive(literal.clone()),
         };
-        final_string.push_str(&result_string);
+        parts.push(result_string);
     }
-    Ok(vm.ctx.new_str(final_string))
+    concat_parts(&parts, vm)
 }
 
-impl PySliceableSequence for String {
+fn concat_parts(parts: &[PyString], vm: &VirtualMachine) -> PyResult {
+    let parts: Vec<&PyString> = parts.iter().collect();
+    PyString::concat(&parts, &PyString::from(""), vm)?.into_pyobject(vm)
+}
+
+impl PySliceableSequence for PyString {
     fn do_slice(&self, range: Range<usize>) -> Self {
-        to_graphemes(self)
-            .get(range)
-            .map_or(String::default(), |c| c.join(""))
+        self.derive(self.char_slice(range).to_string())
     }
 
     fn do_slice_reverse(&self, range: Range<usize>) -> Self {
-        to_graphemes(self)
-            .get_mut(range)
-            .map_or(String::default(), |slice| {
-                slice.reverse();
-                slice.join("")
-            })
+        self.derive(self.char_slice(range).chars().rev().collect::<String>())
     }
 
     fn do_stepped_slice(&self, range: Range<usize>, step: usize) -> Self {
-        if let Some(s) = to_graphemes(self).get(range) {
-            return s
-                .iter()
-                .cloned()
+        self.derive(
+            self.char_slice(range)
+                .chars()
                 .step_by(step)
-                .collect::<Vec<String>>()
-                .join("");
-        }
-        String::default()
+                .collect::<String>(),
+        )
     }
 
     fn do_stepped_slice_reverse(&self, range: Range<usize>, step: usize) -> Self {
-        if let Some(s) = to_graphemes(self).get(range) {
-            return s
-                .iter()
+        self.derive(
+            self.char_slice(range)
+                .chars()
                 .rev()
-                .cloned()
                 .step_by(step)
-                .collect::<Vec<String>>()
-                .join("");
-        }
-        String::default()
+                .collect::<String>(),
+        )
     }
 
     fn empty() -> Self {
-        String::default()
+        PyString::from(String::new())
     }
 
     fn len(&self) -> usize {
-        to_graphemes(self).len()
+        self.char_len()
     }
 
     fn is_empty(&self) -> bool {
-        self.is_empty()
+        self.value.is_empty()
     }
 }
 
-/// Convert a string-able `value` to a vec of graphemes
-/// represents the string according to user perceived characters
-fn to_graphemes<S: AsRef<str>>(value: S) -> Vec<String> {
-    UnicodeSegmentation::graphemes(value.as_ref(), true)
-        .map(String::from)
-        .collect()
-}
-
-pub fn subscript(vm: &VirtualMachine, value: &str, b: PyObjectRef) -> PyResult {
+pub fn subscript(vm: &VirtualMachine, value: &PyString, b: PyObjectRef) -> PyResult {
     if objtype::isinstance(&b, &vm.ctx.int_type()) {
         match objint::get_value(&b).to_i32() {
             Some(pos) => {
-                let graphemes = to_graphemes(value);
-                if let Some(idx) = graphemes.get_pos(pos) {
-                    Ok(vm.new_str(graphemes[idx].to_string()))
+                if let Some(idx) = value.get_pos(pos) {
+                    value.char_at(idx).into_pyobject(vm)
                 } else {
                     Err(vm.new_index_error("string index out of range".to_string()))
                 }
@@ -1518,8 +1794,8 @@ pub fn subscript(vm: &VirtualMachine, value: &str, b: PyObjectRef) -> PyResult {
             }
         }
     } else if b.payload::<PySlice>().is_some() {
-        let string = value.to_string().get_slice_items(vm, &b)?;
-        Ok(vm.new_str(string))
+        let string = value.get_slice_items(vm, &b)?;
+        string.into_pyobject(vm)
     } else {
         panic!(
             "TypeError: indexing type {:?} with index {:?} is not supported (yet?)",
@@ -1632,7 +1908,7 @@ mod tests {
         .unwrap();
         let text = PyString::from("abc");
         let translated = text.translate(translated, &vm).unwrap();
-        assert_eq!(translated, "🎅xda".to_owned());
+        assert_eq!(translated.as_str(), "🎅xda");
         let translated = text.translate(vm.new_int(3), &vm);
         assert_eq!(translated.unwrap_err().class().name, "TypeError".to_owned());
     }
@@ -123,7 +123,7 @@ fn super_new(
         obj.clone()
     } else {
         let frame = vm.current_frame().expect("no current frame for super()");
-        if let Some(first_arg) = frame.code.arg_names.get(0) {
+        if let Some(first_arg) = frame.code.code.arg_names.get(0) {
             match vm.get_locals().get_item_option(first_arg, vm)? {
                 Some(obj) => obj.clone(),
                 _ => {
@@ -32,9 +32,17 @@ impl From<Vec<PyObjectRef>> for PyTuple {
 }
 
 impl PyValue for PyTuple {
+    const HAVE_GC: bool = true;
+
     fn class(vm: &VirtualMachine) -> PyClassRef {
         vm.ctx.tuple_type()
     }
+
+    fn traverse(&self, visit: &mut dyn FnMut(&PyObjectRef)) {
+        for element in &self.elements {
+            visit(element);
+        }
+    }
 }
 
 impl PyTuple {
@@ -149,7 +157,7 @@ impl PyTupleRef {
             let mut str_parts = vec![];
             for elem in self.elements.iter() {
                 let s = vm.to_repr(elem)?;
-                str_parts.push(s.value.clone());
+                str_parts.push(s.as_str().to_string());
             }
 
             if str_parts.len() == 1 {
@@ -1,6 +1,9 @@
-use std::cell::RefCell;
+use std::cell::{Cell, Ref, RefCell};
 use std::collections::HashMap;
 use std::fmt;
+use std::iter;
+use std::mem;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 use crate::function::{Args, KwArgs, PyFuncArgs};
 use crate::pyobject::{
@@ -22,7 +25,31 @@ pub struct PyClass {
     pub name: String,
     pub mro: Vec<PyClassRef>,
     pub subclasses: RefCell<Vec<PyWeak>>,
-    pub attributes: RefCell<PyAttributes>,
+    attributes: RefCell<PyAttributes>,
+    /// Changes whenever the attributes of the class or of one of its bases
+    /// do, which makes what was cached under an older version stale.
+    version: Cell<u64>,
+    /// What looking up a name along the MRO found, if anything.
+    attribute_cache: RefCell<HashMap<String, CachedAttribute>>,
+}
+
+/// The result of looking up a name in a class, valid as long as the class
+/// has the same version. It only holds a weak reference, which the class
+/// keeps alive until it changes.
+#[derive(Debug)]
+struct CachedAttribute {
+    version: u64,
+    value: Option<PyWeak>,
+}
+
+/// The cache of a class is emptied when it holds that many names, which only
+/// happens with names made up at runtime.
+const ATTRIBUTE_CACHE_SIZE: usize = 1024;
+
+static NEXT_VERSION: AtomicU64 = AtomicU64::new(1);
+
+fn next_version() -> u64 {
+    NEXT_VERSION.fetch_add(1, Ordering::Relaxed)
 }
 
 impl fmt::Display for PyClass {
@@ -34,9 +61,79 @@ impl fmt::Display for PyClass {
 pub type PyClassRef = PyRef<PyClass>;
 
 impl PyValue for PyClass {
+    const HAVE_GC: bool = true;
+
     fn class(vm: &VirtualMachine) -> PyClassRef {
         vm.ctx.type_type()
     }
+
+    fn traverse(&self, visit: &mut dyn FnMut(&PyObjectRef)) {
+        for cls in &self.mro {
+            visit(cls.as_object());
+        }
+        if let Ok(attributes) = self.attributes.try_borrow() {
+            for value in attributes.values() {
+                visit(value);
+            }
+        }
+    }
+
+    fn clear_refs(&self) {
+        let attributes = self
+            .attributes
+            .try_borrow_mut()
+            .map(|mut a| mem::take(&mut *a));
+        drop(attributes);
+        self.modified();
+    }
+}
+
+impl PyClass {
+    pub fn new(name: &str, mro: Vec<PyClassRef>, attributes: PyAttributes) -> Self {
+        PyClass {
+            name: String::from(name),
+            mro,
+            subclasses: RefCell::new(vec![]),
+            attributes: RefCell::new(attributes),
+            version: Cell::new(next_version()),
+            attribute_cache: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Give this class and its subclasses a new version after their
+    /// attributes changed, so what was looked up in them is looked up again.
+    fn modified(&self) {
+        self.version.set(next_version());
+        let subclasses: Vec<PyObjectRef> = match self.subclasses.try_borrow() {
+            Ok(subclasses) => subclasses.iter().filter_map(PyWeak::upgrade).collect(),
+            Err(_) => return,
+        };
+        for subclass in subclasses {
+            if let Some(subclass) = subclass.payload::<PyClass>() {
+                subclass.modified();
+            }
+        }
+    }
+
+    fn insert_attr(&self, attr_name: String, value: PyObjectRef) {
+        let old = self.attributes.borrow_mut().insert(attr_name, value);
+        self.modified();
+        drop(old);
+    }
+
+    /// The attributes of this class itself, without the ones of its bases.
+    /// Changes go through `insert_attr`, which keeps the lookup cache right.
+    pub fn attributes(&self) -> Ref<PyAttributes> {
+        self.attributes.borrow()
+    }
+
+    /// Whether this class itself has the attribute. This is false while the
+    /// attributes are being changed.
+    pub fn has_own_attr(&self, attr_name: &str) -> bool {
+        self.attributes
+            .try_borrow()
+            .map_or(false, |attributes| attributes.contains_key(attr_name))
+    }
 }
 
 struct IterMro<'a> {
@@ -104,6 +201,40 @@ impl PyClassRef {
         self.name.clone()
     }
 
+    fn qualname(self, vm: &VirtualMachine) -> PyObjectRef {
+        self.attributes
+            .borrow()
+            .get("__qualname__")
+            .cloned()
+            .unwrap_or_else(|| vm.new_str(self.name.clone()))
+    }
+
+    fn module(self, vm: &VirtualMachine) -> PyObjectRef {
+        // Builtin types don't carry a module in their attributes:
+        self.attributes
+            .borrow()
+            .get("__module__")
+            .cloned()
+            .unwrap_or_else(|| vm.new_str("builtins".to_string()))
+    }
+
+    fn set_module(self, value: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+        self.insert_attr("__module__".to_string(), value);
+        Ok(vm.get_none())
+    }
+
+    fn set_qualname(self, value: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+        if !isinstance(&value, &vm.ctx.str_type()) {
+            return Err(vm.new_type_error(format!(
+                "can only assign string to {}.__qualname__, not '{}'",
+                self.name,
+                value.class().name
+            )));
+        }
+        self.insert_attr("__qualname__".to_string(), value);
+        Ok(vm.get_none())
+    }
+
     fn repr(self, _vm: &VirtualMachine) -> String {
         format!("<class '{}'>", self.name)
     }
@@ -113,7 +244,7 @@ impl PyClassRef {
     }
 
     fn getattribute(self, name_ref: PyStringRef, vm: &VirtualMachine) -> PyResult {
-        let name = &name_ref.value;
+        let name = name_ref.as_str();
         vm_trace!("type.__getattribute__({:?}, {:?})", self, name);
         let mcl = self.class();
 
@@ -154,24 +285,20 @@ impl PyClassRef {
         value: PyObjectRef,
         vm: &VirtualMachine,
     ) -> PyResult<()> {
-        if let Some(attr) = class_get_attr(&self.class(), &attr_name.value) {
+        if let Some(attr) = class_get_attr(&self.class(), attr_name.as_str()) {
             if let Some(descriptor) = class_get_attr(&attr.class(), "__set__") {
                 vm.invoke(descriptor, vec![attr, self.into_object(), value])?;
                 return Ok(());
             }
         }
 
-        self.attributes
-            .borrow_mut()
-            .insert(attr_name.to_string(), value);
+        self.insert_attr(attr_name.to_string(), value);
         Ok(())
     }
 
     // This is used for class initialisation where the vm is not yet available.
     pub fn set_str_attr<V: Into<PyObjectRef>>(&self, attr_name: &str, value: V) {
-        self.attributes
-            .borrow_mut()
-            .insert(attr_name.to_string(), value.into());
+        self.insert_attr(attr_name.to_string(), value.into());
     }
 
     fn subclasses(self, _vm: &VirtualMachine) -> PyList {
@@ -209,6 +336,16 @@ pub fn init(ctx: &PyContext) {
                 .add_setter(PyClassRef::set_mro)
                 .create(),
         "__name__" => ctx.new_property(PyClassRef::name),
+        "__module__" =>
+            PropertyBuilder::new(ctx)
+                .add_getter(PyClassRef::module)
+                .add_setter(PyClassRef::set_module)
+                .create(),
+        "__qualname__" =>
+            PropertyBuilder::new(ctx)
+                .add_getter(PyClassRef::qualname)
+                .add_setter(PyClassRef::set_qualname)
+                .create(),
         "__repr__" => ctx.new_rustfunc(PyClassRef::repr),
         "__prepare__" => ctx.new_rustfunc(PyClassRef::prepare),
         "__getattribute__" => ctx.new_rustfunc(PyClassRef::getattribute),
@@ -262,7 +399,12 @@ pub fn type_new_class(
 ) -> PyResult<PyClassRef> {
     let mut bases: Vec<PyClassRef> = bases.iter(vm)?.collect::<Result<Vec<_>, _>>()?;
     bases.push(vm.ctx.object());
-    new(typ.clone(), &name.value, bases, dict.to_attributes())
+    let mut attributes = dict.to_attributes();
+    // Classes without a docstring still get their own __doc__:
+    attributes
+        .entry("__doc__".to_string())
+        .or_insert_with(|| vm.get_none());
+    new(typ.clone(), name.as_str(), bases, attributes)
 }
 
 pub fn type_call(class: PyClassRef, args: Args, kwargs: KwArgs, vm: &VirtualMachine) -> PyResult {
@@ -293,24 +435,39 @@ fn type_dict_setter(_instance: PyClassRef, _value: PyObjectRef, vm: &VirtualMach
 
 /// This is the internal get_attr implementation for fast lookup on a class.
 pub fn class_get_attr(class: &PyClassRef, attr_name: &str) -> Option<PyObjectRef> {
-    if let Some(item) = class.attributes.borrow().get(attr_name).cloned() {
-        return Some(item);
-    }
-    for class in &class.mro {
-        if let Some(item) = class.attributes.borrow().get(attr_name).cloned() {
-            return Some(item);
+    let version = class.version.get();
+    if let Some(cached) = class.attribute_cache.borrow().get(attr_name) {
+        if cached.version == version {
+            match &cached.value {
+                None => return None,
+                Some(value) => {
+                    if let Some(value) = value.upgrade() {
+                        return Some(value);
+                    }
+                }
+            }
         }
     }
-    None
+    let item = iter::once(class)
+        .chain(class.mro.iter())
+        .find_map(|class| class.attributes.borrow().get(attr_name).cloned());
+    let mut cache = class.attribute_cache.borrow_mut();
+    if cache.len() >= ATTRIBUTE_CACHE_SIZE {
+        cache.clear();
+    }
+    cache.insert(
+        attr_name.to_string(),
+        CachedAttribute {
+            version,
+            value: item.as_ref().map(PyWeak::downgrade),
+        },
+    );
+    item
 }
 
 // This is the internal has_attr implementation for fast lookup on a class.
 pub fn class_has_attr(class: &PyClassRef, attr_name: &str) -> bool {
-    class.attributes.borrow().contains_key(attr_name)
-        || class
-            .mro
-            .iter()
-            .any(|c| c.attributes.borrow().contains_key(attr_name))
+    class_get_attr(class, attr_name).is_some()
 }
 
 pub fn get_attributes(cls: PyClassRef) -> PyAttributes {
@@ -379,17 +536,7 @@ pub fn new(
 ) -> PyResult<PyClassRef> {
     let mros = bases.iter().map(|x| _mro(&x)).collect();
     let mro = linearise_mro(mros).unwrap();
-    let new_type = PyObject {
-        payload: PyClass {
-            name: String::from(name),
-            mro,
-            subclasses: RefCell::new(vec![]),
-            attributes: RefCell::new(dict),
-        },
-        dict: None,
-        typ,
-    }
-    .into_ref();
+    let new_type = PyObject::new(PyClass::new(name, mro, dict), typ, None);
     for base in bases {
         base.subclasses
             .borrow_mut()
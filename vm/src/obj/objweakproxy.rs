@@ -1,5 +1,6 @@
 use super::objweakref::PyWeak;
 use crate::function::OptionalArg;
+use crate::gc;
 use crate::obj::objtype::PyClassRef;
 use crate::pyobject::{PyClassImpl, PyContext, PyObjectRef, PyRef, PyResult, PyValue};
 use crate::vm::VirtualMachine;
@@ -18,9 +19,14 @@ impl PyValue for PyWeakProxy {
 
 pub type PyWeakProxyRef = PyRef<PyWeakProxy>;
 
+impl PyWeakProxy {
+    pub fn callback(&self) -> Option<&PyObjectRef> {
+        self.weak.callback()
+    }
+}
+
 #[pyimpl]
 impl PyWeakProxy {
-    // TODO: callbacks
     #[pymethod(name = "__new__")]
     fn create(
         cls: PyClassRef,
@@ -28,13 +34,15 @@ impl PyWeakProxy {
         callback: OptionalArg<PyObjectRef>,
         vm: &VirtualMachine,
     ) -> PyResult<PyWeakProxyRef> {
-        if callback.is_present() {
-            panic!("Passed a callback to weakproxy, but weakproxy does not yet support proxies.");
-        }
-        PyWeakProxy {
-            weak: PyWeak::downgrade(&referent),
+        let callback = callback
+            .into_option()
+            .filter(|callback| !vm.is_none(callback));
+        let proxy = PyWeakProxy {
+            weak: PyWeak::downgrade_with_callback(&referent, callback),
         }
-        .into_ref_with_type(vm, cls)
+        .into_ref_with_type(vm, cls)?;
+        gc::track_weakref(&referent, proxy.as_object());
+        Ok(proxy)
     }
 
     #[pymethod(name = "__getattr__")]
@@ -1,55 +1,99 @@
 use crate::function::OptionalArg;
+use crate::gc;
 use crate::obj::objtype::PyClassRef;
-use crate::pyobject::PyValue;
-use crate::pyobject::{PyContext, PyObject, PyObjectPayload, PyObjectRef, PyRef, PyResult};
+use crate::pyobject::{IdProtocol, PyValue, TypeProtocol};
+use crate::pyobject::{PyContext, PyObjectRef, PyObjectWeak, PyRef, PyResult};
 use crate::vm::VirtualMachine;
 
-use std::rc::{Rc, Weak};
-
 #[derive(Debug)]
 pub struct PyWeak {
-    referent: Weak<PyObject<dyn PyObjectPayload>>,
+    referent: PyObjectWeak,
+    callback: Option<PyObjectRef>,
 }
 
 impl PyWeak {
     pub fn downgrade(obj: &PyObjectRef) -> PyWeak {
         PyWeak {
-            referent: Rc::downgrade(obj),
+            referent: PyObjectRef::downgrade(obj),
+            callback: None,
+        }
+    }
+
+    /// Make a weak reference whose callback gets called with the weak
+    /// reference object once `obj` is freed.
+    pub fn downgrade_with_callback(obj: &PyObjectRef, callback: Option<PyObjectRef>) -> PyWeak {
+        PyWeak {
+            referent: PyObjectRef::downgrade(obj),
+            callback,
         }
     }
 
     pub fn upgrade(&self) -> Option<PyObjectRef> {
         self.referent.upgrade()
     }
+
+    pub fn callback(&self) -> Option<&PyObjectRef> {
+        self.callback.as_ref()
+    }
 }
 
 impl PyValue for PyWeak {
+    const HAVE_GC: bool = true;
+
     fn class(vm: &VirtualMachine) -> PyClassRef {
         vm.ctx.weakref_type()
     }
+
+    fn traverse(&self, visit: &mut dyn FnMut(&PyObjectRef)) {
+        if let Some(ref callback) = self.callback {
+            visit(callback);
+        }
+    }
 }
 
 pub type PyWeakRef = PyRef<PyWeak>;
 
 impl PyWeakRef {
-    // TODO callbacks
     fn create(
         cls: PyClassRef,
         referent: PyObjectRef,
-        _callback: OptionalArg<PyObjectRef>,
+        callback: OptionalArg<PyObjectRef>,
         vm: &VirtualMachine,
     ) -> PyResult<Self> {
-        PyWeak::downgrade(&referent).into_ref_with_type(vm, cls)
+        let callback = callback
+            .into_option()
+            .filter(|callback| !vm.is_none(callback));
+        // Like in CPython, plain weak references without a callback are shared.
+        if callback.is_none() && cls.is(&vm.ctx.weakref_type()) {
+            let existing = gc::get_weakrefs(&referent).into_iter().find(|weakref| {
+                weakref.class().is(&cls)
+                    && weakref
+                        .payload::<PyWeak>()
+                        .map_or(false, |weak| weak.callback.is_none())
+            });
+            if let Some(existing) = existing {
+                return Ok(existing.downcast().unwrap());
+            }
+        }
+        let weakref =
+            PyWeak::downgrade_with_callback(&referent, callback).into_ref_with_type(vm, cls)?;
+        gc::track_weakref(&referent, weakref.as_object());
+        Ok(weakref)
     }
 
     fn call(self, vm: &VirtualMachine) -> PyObjectRef {
         self.referent.upgrade().unwrap_or_else(|| vm.get_none())
     }
+
+    fn get_callback(self, vm: &VirtualMachine) -> PyObjectRef {
+        self.callback.clone().unwrap_or_else(|| vm.get_none())
+    }
 }
 
 pub fn init(context: &PyContext) {
     extend_class!(context, &context.weakref_type, {
         "__new__" => context.new_rustfunc(PyWeakRef::create),
-        "__call__" => context.new_rustfunc(PyWeakRef::call)
+        "__call__" => context.new_rustfunc(PyWeakRef::call),
+        "__callback__" => context.new_property(PyWeakRef::get_callback)
     });
 }
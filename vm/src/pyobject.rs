@@ -1,21 +1,19 @@
 use std::any::Any;
 use std::cell::Cell;
-use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fmt;
 use std::marker::PhantomData;
-use std::mem;
+use std::mem::{self, ManuallyDrop};
 use std::ops::Deref;
 use std::ptr;
-use std::rc::Rc;
+use std::rc::{Rc, Weak};
 
-use num_bigint::BigInt;
 use num_complex::Complex64;
-use num_traits::{One, Zero};
 
 use crate::bytecode;
 use crate::exceptions;
 use crate::function::{IntoPyNativeFunc, PyFuncArgs};
+use crate::gc;
 use crate::obj::objbool;
 use crate::obj::objbuiltinfunc::PyBuiltinFunction;
 use crate::obj::objbytearray;
@@ -55,7 +53,7 @@ use crate::obj::objtype::{self, PyClass, PyClassRef};
 use crate::obj::objweakproxy;
 use crate::obj::objweakref;
 use crate::obj::objzip;
-use crate::scope::Scope;
+use crate::surrogates::StandIns;
 use crate::vm::VirtualMachine;
 use indexmap::IndexMap;
 
@@ -78,7 +76,19 @@ Basically reference counting, but then done by rust.
 /// this reference counting is accounted for by this type. Use the `.clone()`
 /// method to create a new reference and increment the amount of references
 /// to the python object by 1.
-pub type PyObjectRef = Rc<PyObject<dyn PyObjectPayload>>;
+///
+/// When the last reference to an object with a `__del__` method goes away,
+/// it is handed to the finalizer queue instead, so that `__del__` runs on the
+/// object itself before it is freed.
+pub struct PyObjectRef {
+    rc: ManuallyDrop<Rc<PyObject<dyn PyObjectPayload>>>,
+}
+
+/// A weak reference to a python object, which doesn't keep it alive.
+#[derive(Clone, Debug)]
+pub struct PyObjectWeak {
+    weak: Weak<PyObject<dyn PyObjectPayload>>,
+}
 
 /// Use this type for function which return a python object or and exception.
 /// Both the python object and the python exception are `PyObjectRef` types
@@ -110,7 +120,7 @@ impl fmt::Display for PyObject<dyn PyObjectPayload> {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct PyContext {
     pub bytes_type: PyClassRef,
     pub bytesiterator_type: PyClassRef,
@@ -132,6 +142,7 @@ pub struct PyContext {
     pub complex_type: PyClassRef,
     pub true_value: PyIntRef,
     pub false_value: PyIntRef,
+    small_ints: Vec<PyIntRef>,
     pub list_type: PyClassRef,
     pub listiterator_type: PyClassRef,
     pub listreverseiterator_type: PyClassRef,
@@ -174,6 +185,10 @@ pub struct PyContext {
     pub exceptions: exceptions::ExceptionZoo,
 }
 
+// The ints made once and shared by `new_int`, like in CPython.
+const SMALL_INT_MIN: i64 = -5;
+const SMALL_INT_MAX: i64 = 256;
+
 pub fn create_type(name: &str, type_type: &PyClassRef, base: &PyClassRef) -> PyClassRef {
     let dict = PyAttributes::new();
     objtype::new(type_type.clone(), name, vec![base.clone()], dict).unwrap()
@@ -210,24 +225,20 @@ fn init_type_hierarchy() -> (PyClassRef, PyClassRef) {
         let object_type = PyObject {
             typ: mem::uninitialized(), // !
             dict: None,
-            payload: PyClass {
-                name: String::from("object"),
-                mro: vec![],
-                subclasses: RefCell::new(vec![]),
-                attributes: RefCell::new(PyAttributes::new()),
-            },
+            flags: Cell::new(ObjectFlags::empty()),
+            payload: PyClass::new("object", vec![], PyAttributes::new()),
         }
         .into_ref();
 
         let type_type = PyObject {
             typ: mem::uninitialized(), // !
             dict: None,
-            payload: PyClass {
-                name: String::from("type"),
-                mro: vec![object_type.clone().downcast().unwrap()],
-                subclasses: RefCell::new(vec![]),
-                attributes: RefCell::new(PyAttributes::new()),
-            },
+            flags: Cell::new(ObjectFlags::empty()),
+            payload: PyClass::new(
+                "type",
+                vec![object_type.clone().downcast().unwrap()],
+                PyAttributes::new(),
+            ),
         }
         .into_ref();
 
@@ -327,8 +338,11 @@ impl PyContext {
         let not_implemented_type = create_type("NotImplementedType", &type_type, &object_type);
         let not_implemented = create_object(PyNotImplemented, &not_implemented_type);
 
-        let true_value = create_object(PyInt::new(BigInt::one()), &bool_type);
-        let false_value = create_object(PyInt::new(BigInt::zero()), &bool_type);
+        let true_value = create_object(PyInt::new(1), &bool_type);
+        let false_value = create_object(PyInt::new(0), &bool_type);
+        let small_ints = (SMALL_INT_MIN..=SMALL_INT_MAX)
+            .map(|value| create_object(PyInt::new(value), &int_type))
+            .collect();
 
         let empty_tuple = create_object(PyTuple::from(vec![]), &tuple_type);
 
@@ -361,6 +375,7 @@ impl PyContext {
             frozenset_type,
             true_value,
             false_value,
+            small_ints,
             tuple_type,
             tupleiterator_type,
             iter_type,
@@ -627,8 +642,15 @@ impl PyContext {
         self.object.clone()
     }
 
-    pub fn new_int<T: Into<BigInt>>(&self, i: T) -> PyObjectRef {
-        PyObject::new(PyInt::new(i), self.int_type(), None)
+    pub fn new_int<T: Into<PyInt>>(&self, i: T) -> PyObjectRef {
+        let int = i.into();
+        match int.as_i64() {
+            Some(value) if (SMALL_INT_MIN..=SMALL_INT_MAX).contains(&value) => self.small_ints
+                [(value - SMALL_INT_MIN) as usize]
+                .clone()
+                .into_object(),
+            _ => PyObject::new(int, self.int_type(), None),
+        }
     }
 
     pub fn new_float(&self, value: f64) -> PyObjectRef {
@@ -640,7 +662,18 @@ impl PyContext {
     }
 
     pub fn new_str(&self, s: String) -> PyObjectRef {
-        PyObject::new(objstr::PyString { value: s }, self.str_type(), None)
+        PyObject::new(objstr::PyString::from(s), self.str_type(), None)
+    }
+
+    /// A `str` from a string constant, whose lone surrogates are stood in
+    /// for by the block starting at `stand_ins`.
+    pub fn new_str_with_stand_ins(&self, s: String, stand_ins: Option<u32>) -> PyObjectRef {
+        let stand_ins = stand_ins.map(StandIns::starting_at);
+        PyObject::new(
+            objstr::PyString::with_stand_ins(s, stand_ins),
+            self.str_type(),
+            None,
+        )
     }
 
     pub fn new_bytes(&self, data: Vec<u8>) -> PyObjectRef {
@@ -742,18 +775,8 @@ impl PyContext {
             .unwrap()
     }
 
-    pub fn new_function(
-        &self,
-        code_obj: PyCodeRef,
-        scope: Scope,
-        defaults: Option<PyTupleRef>,
-        kw_only_defaults: Option<PyDictRef>,
-    ) -> PyObjectRef {
-        PyObject::new(
-            PyFunction::new(code_obj, scope, defaults, kw_only_defaults),
-            self.function_type(),
-            Some(self.new_dict()),
-        )
+    pub fn new_function(&self, function: PyFunction) -> PyObjectRef {
+        PyObject::new(function, self.function_type(), Some(self.new_dict()))
     }
 
     pub fn new_bound_method(&self, function: PyObjectRef, object: PyObjectRef) -> PyObjectRef {
@@ -765,12 +788,7 @@ impl PyContext {
     }
 
     pub fn new_instance(&self, class: PyClassRef, dict: Option<PyDictRef>) -> PyObjectRef {
-        PyObject {
-            typ: class,
-            dict,
-            payload: objobject::PyInstance,
-        }
-        .into_ref()
+        PyObject::new(objobject::PyInstance, class, dict)
     }
 
     pub fn unwrap_constant(&self, value: &bytecode::Constant) -> PyObjectRef {
@@ -778,7 +796,10 @@ impl PyContext {
             bytecode::Constant::Integer { ref value } => self.new_int(value.clone()),
             bytecode::Constant::Float { ref value } => self.new_float(*value),
             bytecode::Constant::Complex { ref value } => self.new_complex(*value),
-            bytecode::Constant::String { ref value } => self.new_str(value.clone()),
+            bytecode::Constant::String {
+                ref value,
+                stand_ins,
+            } => self.new_str_with_stand_ins(value.clone(), stand_ins),
             bytecode::Constant::Bytes { ref value } => self.new_bytes(value.clone()),
             bytecode::Constant::Boolean { ref value } => self.new_bool(value.clone()),
             bytecode::Constant::Code { ref code } => {
@@ -803,6 +824,16 @@ impl Default for PyContext {
     }
 }
 
+bitflags! {
+    /// The state of an object kept for its finalization.
+    pub struct ObjectFlags: u8 {
+        /// Weak reference objects to this object were created.
+        const HAS_WEAKREFS = 0x01;
+        /// The `__del__` method of this object was called, or is about to be.
+        const FINALIZED = 0x02;
+    }
+}
+
 /// This is an actual python object. It consists of a `typ` which is the
 /// python class, and carries some rust payload optionally. This rust
 /// payload can be a rust float or rust int in case of float and int objects.
@@ -812,10 +843,45 @@ where
 {
     pub typ: PyClassRef,
     pub dict: Option<PyDictRef>, // __dict__ member
+    pub flags: Cell<ObjectFlags>,
     pub payload: T,
 }
 
-impl PyObject<dyn PyObjectPayload> {
+impl<T> Drop for PyObject<T>
+where
+    T: ?Sized + PyObjectPayload,
+{
+    fn drop(&mut self) {
+        // The finalizer ran already, if there was one, and the object was
+        // not resurrected; only now its weak references die.
+        if self.flags.get().contains(ObjectFlags::HAS_WEAKREFS) {
+            gc::referent_dropped(self.get_id());
+        }
+    }
+}
+
+impl PyObjectRef {
+    fn new(rc: Rc<PyObject<dyn PyObjectPayload>>) -> Self {
+        PyObjectRef {
+            rc: ManuallyDrop::new(rc),
+        }
+    }
+
+    pub fn downgrade(this: &Self) -> PyObjectWeak {
+        PyObjectWeak {
+            weak: Rc::downgrade(&this.rc),
+        }
+    }
+
+    pub fn strong_count(this: &Self) -> usize {
+        Rc::strong_count(&this.rc)
+    }
+
+    pub fn into_raw(this: Self) -> *const PyObject<dyn PyObjectPayload> {
+        let mut this = ManuallyDrop::new(this);
+        Rc::into_raw(unsafe { ManuallyDrop::take(&mut this.rc) })
+    }
+
     /// Attempt to downcast this reference to a subclass.
     ///
     /// If the downcast fails, the original ref is returned in as `Err` so
@@ -823,7 +889,7 @@ impl PyObject<dyn PyObjectPayload> {
     ///
     /// Note: The returned `Result` is _not_ a `PyResult`, even though the
     ///       types are compatible.
-    pub fn downcast<T: PyObjectPayload>(self: Rc<Self>) -> Result<PyRef<T>, PyObjectRef> {
+    pub fn downcast<T: PyObjectPayload>(self) -> Result<PyRef<T>, PyObjectRef> {
         if self.payload_is::<T>() {
             Ok({
                 PyRef {
@@ -837,6 +903,50 @@ impl PyObject<dyn PyObjectPayload> {
     }
 }
 
+impl Clone for PyObjectRef {
+    fn clone(&self) -> Self {
+        PyObjectRef {
+            rc: self.rc.clone(),
+        }
+    }
+}
+
+impl Deref for PyObjectRef {
+    type Target = PyObject<dyn PyObjectPayload>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.rc
+    }
+}
+
+impl Drop for PyObjectRef {
+    fn drop(&mut self) {
+        // The reference is taken out once, and not used afterwards.
+        let rc = unsafe { ManuallyDrop::take(&mut self.rc) };
+        if Rc::strong_count(&rc) == 1 && rc.needs_finalizer() {
+            gc::schedule_finalizer(PyObjectRef::new(rc));
+        }
+    }
+}
+
+impl fmt::Debug for PyObjectRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl fmt::Display for PyObjectRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+impl PyObjectWeak {
+    pub fn upgrade(&self) -> Option<PyObjectRef> {
+        self.weak.upgrade().map(PyObjectRef::new)
+    }
+}
+
 /// A reference to a Python object.
 ///
 /// Note that a `PyRef<T>` can only deref to a shared / immutable reference.
@@ -998,7 +1108,7 @@ impl<T: ?Sized + PyObjectPayload> IdProtocol for PyObject<T> {
     }
 }
 
-impl<T: ?Sized + IdProtocol> IdProtocol for Rc<T> {
+impl IdProtocol for PyObjectRef {
     fn get_id(&self) -> usize {
         (**self).get_id()
     }
@@ -1279,12 +1389,23 @@ where
 {
     #[allow(clippy::new_ret_no_self)]
     pub fn new(payload: T, typ: PyClassRef, dict: Option<PyDictRef>) -> PyObjectRef {
-        PyObject { typ, dict, payload }.into_ref()
+        PyObject {
+            typ,
+            dict,
+            flags: Cell::new(ObjectFlags::empty()),
+            payload,
+        }
+        .into_ref()
     }
 
     // Move this object into a reference object, transferring ownership.
     pub fn into_ref(self) -> PyObjectRef {
-        Rc::new(self)
+        let tracked = self.payload.is_gc() || self.dict.is_some();
+        let obj = PyObjectRef::new(Rc::new(self));
+        if tracked {
+            gc::track(&obj);
+        }
+        obj
     }
 }
 
@@ -1298,10 +1419,37 @@ impl PyObject<dyn PyObjectPayload> {
     pub fn payload_is<T: PyObjectPayload>(&self) -> bool {
         self.payload.as_any().is::<T>()
     }
+
+    /// Whether `__del__` has to run before the object can be freed.
+    fn needs_finalizer(&self) -> bool {
+        self.dict.is_some()
+            && !self.flags.get().contains(ObjectFlags::FINALIZED)
+            && gc::has_finalizer(&self.typ)
+    }
+
+    /// Whether the cycle collector keeps track of this object.
+    pub fn is_gc_tracked(&self) -> bool {
+        self.payload.is_gc() || self.dict.is_some()
+    }
+
+    /// Call `visit` with every object this object holds a reference to.
+    ///
+    /// The class is only visited for objects with a `__dict__`, which are
+    /// the instances of classes defined in Python.
+    pub fn traverse(&self, visit: &mut dyn FnMut(&PyObjectRef)) {
+        if let Some(ref dict) = self.dict {
+            visit(self.typ.as_object());
+            visit(dict.as_object());
+        }
+        self.payload.gc_traverse(visit);
+    }
 }
 
 pub trait PyValue: fmt::Debug + Sized + 'static {
     const HAVE_DICT: bool = false;
+    /// Objects of this type can be part of a reference cycle, and are
+    /// tracked by the cycle collector.
+    const HAVE_GC: bool = false;
 
     fn class(vm: &VirtualMachine) -> PyClassRef;
 
@@ -1330,10 +1478,23 @@ pub trait PyValue: fmt::Debug + Sized + 'static {
             Err(vm.new_type_error(format!("{} is not a subtype of {}", subtype, basetype)))
         }
     }
+
+    /// Call `visit` with every object this payload holds a reference to.
+    ///
+    /// Each reference must be visited exactly once, since the cycle
+    /// collector counts them against the objects' reference counts.
+    fn traverse(&self, _visit: &mut dyn FnMut(&PyObjectRef)) {}
+
+    /// Drop the references held by this payload, to break a reference
+    /// cycle the cycle collector found to be unreachable.
+    fn clear_refs(&self) {}
 }
 
 pub trait PyObjectPayload: Any + fmt::Debug + 'static {
     fn as_any(&self) -> &dyn Any;
+    fn is_gc(&self) -> bool;
+    fn gc_traverse(&self, visit: &mut dyn FnMut(&PyObjectRef));
+    fn gc_clear(&self);
 }
 
 impl<T: PyValue + 'static> PyObjectPayload for T {
@@ -1341,6 +1502,19 @@ impl<T: PyValue + 'static> PyObjectPayload for T {
     fn as_any(&self) -> &dyn Any {
         self
     }
+
+    #[inline]
+    fn is_gc(&self) -> bool {
+        T::HAVE_GC
+    }
+
+    fn gc_traverse(&self, visit: &mut dyn FnMut(&PyObjectRef)) {
+        self.traverse(visit)
+    }
+
+    fn gc_clear(&self) {
+        self.clear_refs()
+    }
 }
 
 pub enum Either<A, B> {
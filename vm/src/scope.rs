@@ -1,66 +1,20 @@
 use std::fmt;
-use std::rc::Rc;
 
 use crate::obj::objdict::PyDictRef;
 use crate::pyobject::{ItemProtocol, PyContext, PyObjectRef, PyResult};
 use crate::vm::VirtualMachine;
 
 /*
- * So a scope is a linked list of scopes.
+ * So a scope is a stack of dictionaries, innermost first.
  * When a name is looked up, it is check in its scope.
+ *
+ * Every scope holds its own references to the dictionaries (rather than
+ * sharing a linked list with its parent), so that the cycle collector can
+ * count them exactly.
  */
-#[derive(Debug)]
-struct RcListNode<T> {
-    elem: T,
-    next: Option<Rc<RcListNode<T>>>,
-}
-
-#[derive(Debug, Clone)]
-struct RcList<T> {
-    head: Option<Rc<RcListNode<T>>>,
-}
-
-struct Iter<'a, T: 'a> {
-    next: Option<&'a RcListNode<T>>,
-}
-
-impl<T> RcList<T> {
-    pub fn new() -> Self {
-        RcList { head: None }
-    }
-
-    pub fn insert(self, elem: T) -> Self {
-        RcList {
-            head: Some(Rc::new(RcListNode {
-                elem,
-                next: self.head,
-            })),
-        }
-    }
-
-    #[cfg_attr(feature = "flame-it", flame("RcList"))]
-    pub fn iter(&self) -> Iter<T> {
-        Iter {
-            next: self.head.as_ref().map(|node| &**node),
-        }
-    }
-}
-
-impl<'a, T> Iterator for Iter<'a, T> {
-    type Item = &'a T;
-
-    #[cfg_attr(feature = "flame-it", flame("Iter"))]
-    fn next(&mut self) -> Option<Self::Item> {
-        self.next.map(|node| {
-            self.next = node.next.as_ref().map(|node| &**node);
-            &node.elem
-        })
-    }
-}
-
 #[derive(Clone)]
 pub struct Scope {
-    locals: RcList<PyDictRef>,
+    locals: Vec<PyDictRef>,
     pub globals: PyDictRef,
 }
 
@@ -73,10 +27,7 @@ impl fmt::Debug for Scope {
 
 impl Scope {
     pub fn new(locals: Option<PyDictRef>, globals: PyDictRef) -> Scope {
-        let locals = match locals {
-            Some(dict) => RcList::new().insert(dict),
-            None => RcList::new(),
-        };
+        let locals = locals.into_iter().collect();
         Scope { locals, globals }
     }
 
@@ -95,19 +46,32 @@ impl Scope {
     }
 
     pub fn get_locals(&self) -> PyDictRef {
-        match self.locals.iter().next() {
+        match self.locals.first() {
             Some(dict) => dict.clone(),
             None => self.globals.clone(),
         }
     }
 
     pub fn get_only_locals(&self) -> Option<PyDictRef> {
-        self.locals.iter().next().cloned()
+        self.locals.first().cloned()
+    }
+
+    /// Look `name` up in the local dictionaries only, innermost first.
+    pub fn load_local(&self, vm: &VirtualMachine, name: &str) -> Option<PyObjectRef> {
+        for dict in self.locals.iter() {
+            if let Some(value) = dict.get_item_option(name, vm).unwrap() {
+                return Some(value);
+            }
+        }
+        None
     }
 
     pub fn new_child_scope_with_locals(&self, locals: PyDictRef) -> Scope {
+        let mut scope_locals = Vec::with_capacity(self.locals.len() + 1);
+        scope_locals.push(locals);
+        scope_locals.extend(self.locals.iter().cloned());
         Scope {
-            locals: self.locals.clone().insert(locals),
+            locals: scope_locals,
             globals: self.globals.clone(),
         }
     }
@@ -115,6 +79,14 @@ impl Scope {
     pub fn new_child_scope(&self, ctx: &PyContext) -> Scope {
         self.new_child_scope_with_locals(ctx.new_dict())
     }
+
+    /// Visit the dictionaries of this scope, for the cycle collector.
+    pub fn traverse(&self, visit: &mut dyn FnMut(&PyObjectRef)) {
+        for dict in &self.locals {
+            visit(dict.as_object());
+        }
+        visit(self.globals.as_object());
+    }
 }
 
 pub trait NameProtocol {
@@ -130,17 +102,8 @@ pub trait NameProtocol {
 impl NameProtocol for Scope {
     #[cfg_attr(feature = "flame-it", flame("Scope"))]
     fn load_name(&self, vm: &VirtualMachine, name: &str) -> Option<PyObjectRef> {
-        for dict in self.locals.iter() {
-            if let Some(value) = dict.get_item_option(name, vm).unwrap() {
-                return Some(value);
-            }
-        }
-
-        if let Some(value) = self.globals.get_item_option(name, vm).unwrap() {
-            return Some(value);
-        }
-
-        vm.get_attribute(vm.builtins.clone(), name).ok()
+        self.load_local(vm, name)
+            .or_else(|| self.load_global(vm, name))
     }
 
     #[cfg_attr(feature = "flame-it", flame("Scope"))]
@@ -155,8 +118,7 @@ impl NameProtocol for Scope {
 
     fn store_cell(&self, vm: &VirtualMachine, name: &str, value: PyObjectRef) {
         self.locals
-            .iter()
-            .nth(1)
+            .get(1)
             .expect("no outer scope for non-local")
             .set_item(name, value, vm)
             .unwrap();
@@ -172,7 +134,11 @@ impl NameProtocol for Scope {
 
     #[cfg_attr(feature = "flame-it", flame("Scope"))]
     fn load_global(&self, vm: &VirtualMachine, name: &str) -> Option<PyObjectRef> {
-        self.globals.get_item_option(name, vm).unwrap()
+        if let Some(value) = self.globals.get_item_option(name, vm).unwrap() {
+            return Some(value);
+        }
+
+        vm.get_attribute(vm.builtins.clone(), name).ok()
     }
 
     fn store_global(&self, vm: &VirtualMachine, name: &str, value: PyObjectRef) {
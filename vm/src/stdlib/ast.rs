@@ -7,9 +7,9 @@ use std::ops::Deref;
 
 use num_complex::Complex64;
 
+use rustpython_parser::visitor::{self, Visitor};
 use rustpython_parser::{ast, parser};
 
-use crate::obj::objlist::PyListRef;
 use crate::obj::objstr::PyStringRef;
 use crate::obj::objtype::PyClassRef;
 use crate::pyobject::{PyObjectRef, PyRef, PyResult, PyValue};
@@ -48,455 +48,590 @@ macro_rules! node {
     }
 }
 
-fn program_to_ast(vm: &VirtualMachine, program: &ast::Program) -> PyResult<AstNodeRef> {
-    let py_body = statements_to_ast(vm, &program.statements)?;
-    Ok(node!(vm, Module, { body => py_body }))
-}
-
 // Create a node class instance
 fn create_node(vm: &VirtualMachine, name: &str) -> PyResult<AstNodeRef> {
     AstNode.into_ref_with_type(vm, vm.class("ast", name))
 }
 
-fn statements_to_ast(vm: &VirtualMachine, statements: &[ast::Statement]) -> PyResult<PyListRef> {
-    let body: PyResult<Vec<_>> = statements
-        .iter()
-        .map(|statement| Ok(statement_to_ast(&vm, statement)?.into_object()))
-        .collect();
-    Ok(vm.ctx.new_list(body?).downcast().unwrap())
+/// Converts the rust ast into python ast nodes.
+///
+/// The default traversal of `Visitor` descends into the children of a node
+/// before the node itself is converted. Each converted child is pushed onto
+/// `stack`, so that the parent can pick up its children in traversal order.
+struct AstConverter<'a> {
+    vm: &'a VirtualMachine,
+    stack: Vec<PyObjectRef>,
 }
 
-fn statement_to_ast(vm: &VirtualMachine, statement: &ast::Statement) -> PyResult<AstNodeRef> {
-    use ast::StatementType::*;
-    let node = match &statement.node {
-        ClassDef {
-            name,
-            body,
-            keywords,
-            decorator_list,
-            ..
-        } => node!(vm, ClassDef, {
-            name => vm.ctx.new_str(name.to_string()),
-            keywords => map_ast(keyword_to_ast, vm, keywords)?,
-            body => statements_to_ast(vm, body)?,
-            decorator_list => expressions_to_ast(vm, decorator_list)?,
-        }),
-        FunctionDef {
-            is_async,
-            name,
-            args,
-            body,
-            decorator_list,
-            returns,
-        } => {
-            if *is_async {
-                node!(vm, AsyncFunctionDef, {
-                    name => vm.ctx.new_str(name.to_string()),
-                    args => parameters_to_ast(vm, args)?,
-                    body => statements_to_ast(vm, body)?,
-                    decorator_list => expressions_to_ast(vm, decorator_list)?,
-                    returns => optional_expression_to_ast(vm, returns)?
-                })
-            } else {
-                node!(vm, FunctionDef, {
-                    name => vm.ctx.new_str(name.to_string()),
-                    args => parameters_to_ast(vm, args)?,
-                    body => statements_to_ast(vm, body)?,
-                    decorator_list => expressions_to_ast(vm, decorator_list)?,
-                    returns => optional_expression_to_ast(vm, returns)?
-                })
-            }
-        }
-        Continue => node!(vm, Continue),
-        Break => node!(vm, Break),
-        Pass => node!(vm, Pass),
-        Assert { test, msg } => node!(vm, Assert, {
-            test => expression_to_ast(vm, test)?,
-            msg => optional_expression_to_ast(vm, msg)?
-        }),
-        Delete { targets } => {
-            let targets: PyResult<_> = targets
-                .iter()
-                .map(|v| Ok(expression_to_ast(vm, v)?.into_object()))
-                .collect();
-            let py_targets = vm.ctx.new_tuple(targets?);
-            node!(vm, Delete, { targets => py_targets })
-        }
-        Return { value } => node!(vm, Return, {
-            value => optional_expression_to_ast(vm, value)?
-        }),
-        If { test, body, orelse } => node!(vm, If, {
-            test => expression_to_ast(vm, test)?,
-            body => statements_to_ast(vm, body)?,
-            orelse => optional_statements_to_ast(vm, orelse)?
-        }),
-        For {
-            is_async,
-            target,
-            iter,
-            body,
-            orelse,
-        } => {
-            if *is_async {
-                node!(vm, AsyncFor, {
-                    target => expression_to_ast(vm, target)?,
-                    iter => expression_to_ast(vm, iter)?,
-                    body => statements_to_ast(vm, body)?,
-                    orelse => optional_statements_to_ast(vm, orelse)?
-                })
-            } else {
-                node!(vm, For, {
-                    target => expression_to_ast(vm, target)?,
-                    iter => expression_to_ast(vm, iter)?,
-                    body => statements_to_ast(vm, body)?,
-                    orelse => optional_statements_to_ast(vm, orelse)?
-                })
-            }
-        }
-        While { test, body, orelse } => node!(vm, While, {
-            test => expression_to_ast(vm, test)?,
-            body => statements_to_ast(vm, body)?,
-            orelse => optional_statements_to_ast(vm, orelse)?
-        }),
-        With {
-            is_async,
-            items,
-            body,
-        } => {
-            if *is_async {
-                node!(vm, AsyncWith, {
-                    items => map_ast(with_item_to_ast, vm, items)?,
-                    body => statements_to_ast(vm, body)?
-                })
-            } else {
-                node!(vm, With, {
-                    items => map_ast(with_item_to_ast, vm, items)?,
-                    body => statements_to_ast(vm, body)?
-                })
-            }
-        }
-        Try {
-            body,
-            handlers,
-            orelse,
-            finalbody,
-        } => node!(vm, Try, {
-            body => statements_to_ast(vm, body)?,
-            handlers => map_ast(handler_to_ast, vm, handlers)?,
-            orelse => optional_statements_to_ast(vm, orelse)?,
-            finalbody => optional_statements_to_ast(vm, finalbody)?
-        }),
-        Expression { expression } => node!(vm, Expr, {
-            value => expression_to_ast(vm, expression)?
-        }),
-        Import { names } => node!(vm, Import, {
-            names => map_ast(alias_to_ast, vm, names)?
-        }),
-        ImportFrom {
-            level,
-            module,
-            names,
-        } => node!(vm, ImportFrom, {
-            level => vm.ctx.new_int(*level),
-            module => optional_string_to_py_obj(vm, module),
-            names => map_ast(alias_to_ast, vm, names)?
-        }),
-        Nonlocal { names } => node!(vm, Nonlocal, {
-            names => make_string_list(vm, names)
-        }),
-        Global { names } => node!(vm, Global, {
-            names => make_string_list(vm, names)
-        }),
-        Assign { targets, value } => node!(vm, Assign, {
-            targets => expressions_to_ast(vm, targets)?,
-            value => expression_to_ast(vm, value)?,
-        }),
-        AugAssign { target, op, value } => node!(vm, AugAssign, {
-            target => expression_to_ast(vm, target)?,
-            op => vm.ctx.new_str(operator_string(op)),
-            value => expression_to_ast(vm, value)?,
-        }),
-        Raise { exception, cause } => node!(vm, Raise, {
-            exc => optional_expression_to_ast(vm, exception)?,
-            cause => optional_expression_to_ast(vm, cause)?,
-        }),
-    };
-
-    // set lineno on node:
-    let lineno = vm.ctx.new_int(statement.location.row());
-    vm.set_attr(node.as_object(), "lineno", lineno).unwrap();
+/// The python nodes created for the children of a single rust node.
+struct Children(std::vec::IntoIter<PyObjectRef>);
 
-    Ok(node)
-}
+impl Children {
+    fn take(&mut self) -> PyObjectRef {
+        self.0.next().expect("ast child was not converted")
+    }
 
-fn alias_to_ast(vm: &VirtualMachine, alias: &ast::ImportSymbol) -> PyResult<AstNodeRef> {
-    Ok(node!(vm, alias, {
-        name => vm.ctx.new_str(alias.symbol.to_string()),
-        asname => optional_string_to_py_obj(vm, &alias.alias)
-    }))
-}
+    fn take_list(&mut self, vm: &VirtualMachine, count: usize) -> PyObjectRef {
+        vm.ctx.new_list(self.0.by_ref().take(count).collect())
+    }
 
-fn optional_statements_to_ast(
-    vm: &VirtualMachine,
-    statements: &Option<Vec<ast::Statement>>,
-) -> PyResult {
-    let statements = if let Some(statements) = statements {
-        statements_to_ast(vm, statements)?.into_object()
-    } else {
-        vm.ctx.none()
-    };
-    Ok(statements)
-}
+    fn take_optional(&mut self, vm: &VirtualMachine, present: bool) -> PyObjectRef {
+        if present {
+            self.take()
+        } else {
+            vm.ctx.none()
+        }
+    }
 
-fn with_item_to_ast(vm: &VirtualMachine, with_item: &ast::WithItem) -> PyResult<AstNodeRef> {
-    let node = node!(vm, withitem, {
-        context_expr => expression_to_ast(vm, &with_item.context_expr)?,
-        optional_vars => optional_expression_to_ast(vm, &with_item.optional_vars)?
-    });
-    Ok(node)
+    fn take_optional_list<T>(
+        &mut self,
+        vm: &VirtualMachine,
+        items: &Option<Vec<T>>,
+    ) -> PyObjectRef {
+        match items {
+            Some(items) => self.take_list(vm, items.len()),
+            Option::None => vm.ctx.none(),
+        }
+    }
 }
 
-fn handler_to_ast(vm: &VirtualMachine, handler: &ast::ExceptHandler) -> PyResult<AstNodeRef> {
-    let node = node!(vm, ExceptHandler, {
-        typ => optional_expression_to_ast(vm, &handler.typ)?,
-        name => optional_string_to_py_obj(vm, &handler.name),
-        body => statements_to_ast(vm, &handler.body)?,
-    });
-    Ok(node)
-}
+impl<'a> AstConverter<'a> {
+    fn new(vm: &'a VirtualMachine) -> Self {
+        AstConverter { vm, stack: vec![] }
+    }
 
-fn make_string_list(vm: &VirtualMachine, names: &[String]) -> PyObjectRef {
-    vm.ctx.new_list(
-        names
-            .iter()
-            .map(|x| vm.ctx.new_str(x.to_string()))
-            .collect(),
-    )
-}
+    /// Run `walk` and collect the python nodes it created.
+    fn children<F>(&mut self, walk: F) -> PyResult<Children>
+    where
+        F: FnOnce(&mut Self) -> PyResult<()>,
+    {
+        let mark = self.stack.len();
+        walk(self)?;
+        Ok(Children(self.stack.split_off(mark).into_iter()))
+    }
 
-fn optional_expression_to_ast(vm: &VirtualMachine, value: &Option<ast::Expression>) -> PyResult {
-    let value = if let Some(value) = value {
-        expression_to_ast(vm, value)?.into_object()
-    } else {
-        vm.ctx.none()
-    };
-    Ok(value)
-}
+    fn push(&mut self, node: AstNodeRef) {
+        self.stack.push(node.into_object());
+    }
 
-fn expressions_to_ast(vm: &VirtualMachine, expressions: &[ast::Expression]) -> PyResult<PyListRef> {
-    let py_expression_nodes: PyResult<_> = expressions
-        .iter()
-        .map(|expression| Ok(expression_to_ast(vm, expression)?.into_object()))
-        .collect();
-    Ok(vm.ctx.new_list(py_expression_nodes?).downcast().unwrap())
-}
+    fn convert_program(&mut self, program: &ast::Program) -> PyResult<AstNodeRef> {
+        let vm = self.vm;
+        let mut children = self.children(|c| c.visit_program(program))?;
+        Ok(node!(vm, Module, {
+            body => children.take_list(vm, program.statements.len())
+        }))
+    }
 
-fn expression_to_ast(vm: &VirtualMachine, expression: &ast::Expression) -> PyResult<AstNodeRef> {
-    use ast::ExpressionType::*;
-    let node = match &expression.node {
-        Call {
-            function,
-            args,
-            keywords,
-        } => node!(vm, Call, {
-            func => expression_to_ast(vm, function)?,
-            args => expressions_to_ast(vm, args)?,
-            keywords => map_ast(keyword_to_ast, vm, keywords)?,
-        }),
-        Binop { a, op, b } => {
-            // Operator:
-            node!(vm, BinOp, {
-                left => expression_to_ast(vm, a)?,
-                op => vm.ctx.new_str(operator_string(op)),
-                right => expression_to_ast(vm, b)?,
-            })
-        }
-        Unop { op, a } => {
-            let op = match op {
-                ast::UnaryOperator::Not => "Not",
-                ast::UnaryOperator::Inv => "Invert",
-                ast::UnaryOperator::Neg => "USub",
-                ast::UnaryOperator::Pos => "UAdd",
-            };
-            node!(vm, UnaryOp, {
-                op => vm.ctx.new_str(op.to_string()),
-                operand => expression_to_ast(vm, a)?,
-            })
-        }
-        BoolOp { a, op, b } => {
-            // Attach values:
-            let py_a = expression_to_ast(vm, a)?.into_object();
-            let py_b = expression_to_ast(vm, b)?.into_object();
-            let py_values = vm.ctx.new_tuple(vec![py_a, py_b]);
-
-            let str_op = match op {
-                ast::BooleanOperator::And => "And",
-                ast::BooleanOperator::Or => "Or",
-            };
-            let py_op = vm.ctx.new_str(str_op.to_string());
-
-            node!(vm, BoolOp, {
-                op => py_op,
-                values => py_values,
-            })
-        }
-        Compare { vals, ops } => {
-            let left = expression_to_ast(vm, &vals[0])?;
-
-            // Operator:
-            let to_operator = |op: &ast::Comparison| match op {
-                ast::Comparison::Equal => "Eq",
-                ast::Comparison::NotEqual => "NotEq",
-                ast::Comparison::Less => "Lt",
-                ast::Comparison::LessOrEqual => "LtE",
-                ast::Comparison::Greater => "Gt",
-                ast::Comparison::GreaterOrEqual => "GtE",
-                ast::Comparison::In => "In",
-                ast::Comparison::NotIn => "NotIn",
-                ast::Comparison::Is => "Is",
-                ast::Comparison::IsNot => "IsNot",
-            };
-            let ops = vm.ctx.new_list(
-                ops.iter()
-                    .map(|x| vm.ctx.new_str(to_operator(x).to_string()))
-                    .collect(),
-            );
-
-            let comparators: PyResult<_> = vals
-                .iter()
-                .skip(1)
-                .map(|x| Ok(expression_to_ast(vm, x)?.into_object()))
-                .collect();
-            let comparators = vm.ctx.new_list(comparators?);
-            node!(vm, Compare, {
-                left => left,
-                ops => ops,
-                comparators => comparators,
-            })
-        }
-        Identifier { name } => node!(vm, Name, {
-            id => vm.ctx.new_str(name.clone()),
-            ctx => vm.ctx.none()   // TODO: add context.
-        }),
-        Lambda { args, body } => node!(vm, Lambda, {
-            args => parameters_to_ast(vm, args)?,
-            body => expression_to_ast(vm, body)?,
-        }),
-        IfExpression { test, body, orelse } => node!(vm, IfExp, {
-            text => expression_to_ast(vm, test)?,
-            body => expression_to_ast(vm, body)?,
-            or_else => expression_to_ast(vm, orelse)?,
-        }),
-        Number { value } => {
-            let py_n = match value {
-                ast::Number::Integer { value } => vm.ctx.new_int(value.clone()),
-                ast::Number::Float { value } => vm.ctx.new_float(*value),
-                ast::Number::Complex { real, imag } => {
-                    vm.ctx.new_complex(Complex64::new(*real, *imag))
+    fn convert_statement(&mut self, statement: &ast::Statement) -> PyResult<AstNodeRef> {
+        use ast::StatementType::*;
+        let vm = self.vm;
+        let mut c = self.children(|c| visitor::walk_statement(c, statement))?;
+        let node = match &statement.node {
+            ClassDef {
+                name,
+                body,
+                bases,
+                keywords,
+                decorator_list,
+            } => {
+                let decorator_list = c.take_list(vm, decorator_list.len());
+                node!(vm, ClassDef, {
+                    name => vm.ctx.new_str(name.to_string()),
+                    bases => c.take_list(vm, bases.len()),
+                    keywords => c.take_list(vm, keywords.len()),
+                    body => c.take_list(vm, body.len()),
+                    decorator_list => decorator_list,
+                })
+            }
+            FunctionDef {
+                is_async,
+                name,
+                body,
+                decorator_list,
+                returns,
+                ..
+            } => {
+                let decorator_list = c.take_list(vm, decorator_list.len());
+                let args = c.take();
+                let returns = c.take_optional(vm, returns.is_some());
+                let body = c.take_list(vm, body.len());
+                if *is_async {
+                    node!(vm, AsyncFunctionDef, {
+                        name => vm.ctx.new_str(name.to_string()),
+                        args => args,
+                        body => body,
+                        decorator_list => decorator_list,
+                        returns => returns
+                    })
+                } else {
+                    node!(vm, FunctionDef, {
+                        name => vm.ctx.new_str(name.to_string()),
+                        args => args,
+                        body => body,
+                        decorator_list => decorator_list,
+                        returns => returns
+                    })
                 }
-            };
-            node!(vm, Num, {
-                n => py_n
-            })
-        }
-        True => node!(vm, NameConstant, {
-            value => vm.ctx.new_bool(true)
-        }),
-        False => node!(vm, NameConstant, {
-            value => vm.ctx.new_bool(false)
-        }),
-        None => node!(vm, NameConstant, {
-            value => vm.ctx.none()
-        }),
-        Ellipsis => node!(vm, Ellipsis),
-        List { elements } => node!(vm, List, {
-            elts => expressions_to_ast(vm, &elements)?
-        }),
-        Tuple { elements } => node!(vm, Tuple, {
-            elts => expressions_to_ast(vm, &elements)?
-        }),
-        Set { elements } => node!(vm, Set, {
-            elts => expressions_to_ast(vm, &elements)?
-        }),
-        Dict { elements } => {
-            let mut keys = Vec::new();
-            let mut values = Vec::new();
-            for (k, v) in elements {
-                if let Some(k) = k {
-                    keys.push(expression_to_ast(vm, k)?.into_object());
+            }
+            Continue => node!(vm, Continue),
+            Break => node!(vm, Break),
+            Pass => node!(vm, Pass),
+            Assert { msg, .. } => node!(vm, Assert, {
+                test => c.take(),
+                msg => c.take_optional(vm, msg.is_some())
+            }),
+            Delete { targets } => node!(vm, Delete, {
+                targets => c.take_list(vm, targets.len())
+            }),
+            Return { value } => node!(vm, Return, {
+                value => c.take_optional(vm, value.is_some())
+            }),
+            If { body, orelse, .. } => node!(vm, If, {
+                test => c.take(),
+                body => c.take_list(vm, body.len()),
+                orelse => c.take_optional_list(vm, orelse)
+            }),
+            For {
+                is_async,
+                body,
+                orelse,
+                ..
+            } => {
+                let target = c.take();
+                let iter = c.take();
+                let body = c.take_list(vm, body.len());
+                let orelse = c.take_optional_list(vm, orelse);
+                if *is_async {
+                    node!(vm, AsyncFor, {
+                        target => target,
+                        iter => iter,
+                        body => body,
+                        orelse => orelse
+                    })
+                } else {
+                    node!(vm, For, {
+                        target => target,
+                        iter => iter,
+                        body => body,
+                        orelse => orelse
+                    })
+                }
+            }
+            While { body, orelse, .. } => node!(vm, While, {
+                test => c.take(),
+                body => c.take_list(vm, body.len()),
+                orelse => c.take_optional_list(vm, orelse)
+            }),
+            With {
+                is_async,
+                items,
+                body,
+            } => {
+                let items = c.take_list(vm, items.len());
+                let body = c.take_list(vm, body.len());
+                if *is_async {
+                    node!(vm, AsyncWith, {
+                        items => items,
+                        body => body
+                    })
                 } else {
-                    keys.push(vm.ctx.none());
+                    node!(vm, With, {
+                        items => items,
+                        body => body
+                    })
                 }
-                values.push(expression_to_ast(vm, v)?.into_object());
             }
+            Try {
+                body,
+                handlers,
+                orelse,
+                finalbody,
+            } => node!(vm, Try, {
+                body => c.take_list(vm, body.len()),
+                handlers => c.take_list(vm, handlers.len()),
+                orelse => c.take_optional_list(vm, orelse),
+                finalbody => c.take_optional_list(vm, finalbody)
+            }),
+            Expression { .. } => node!(vm, Expr, {
+                value => c.take()
+            }),
+            Import { names } => node!(vm, Import, {
+                names => c.take_list(vm, names.len())
+            }),
+            ImportFrom {
+                level,
+                module,
+                names,
+            } => node!(vm, ImportFrom, {
+                level => vm.ctx.new_int(*level),
+                module => optional_string_to_py_obj(vm, module),
+                names => c.take_list(vm, names.len())
+            }),
+            Nonlocal { names } => node!(vm, Nonlocal, {
+                names => make_string_list(vm, names)
+            }),
+            Global { names } => node!(vm, Global, {
+                names => make_string_list(vm, names)
+            }),
+            Assign { targets, .. } => node!(vm, Assign, {
+                targets => c.take_list(vm, targets.len()),
+                value => c.take(),
+            }),
+            AugAssign { op, .. } => node!(vm, AugAssign, {
+                target => c.take(),
+                op => vm.ctx.new_str(operator_string(op)),
+                value => c.take(),
+            }),
+            AnnAssign { value, simple, .. } => node!(vm, AnnAssign, {
+                target => c.take(),
+                annotation => c.take(),
+                value => c.take_optional(vm, value.is_some()),
+                simple => vm.ctx.new_int(*simple as i32),
+            }),
+            Raise { exception, cause } => node!(vm, Raise, {
+                exc => c.take_optional(vm, exception.is_some()),
+                cause => c.take_optional(vm, cause.is_some()),
+            }),
+        };
+
+        // set lineno on node:
+        let lineno = vm.ctx.new_int(statement.location.row());
+        vm.set_attr(node.as_object(), "lineno", lineno).unwrap();
+
+        Ok(node)
+    }
 
-            node!(vm, Dict, {
-                keys => vm.ctx.new_list(keys),
-                values => vm.ctx.new_list(values),
-            })
-        }
-        Comprehension { kind, generators } => {
-            let py_generators = map_ast(comprehension_to_ast, vm, generators)?;
+    fn convert_expression(&mut self, expression: &ast::Expression) -> PyResult<AstNodeRef> {
+        use ast::ExpressionType::*;
+        let vm = self.vm;
+        let mut c = self.children(|c| visitor::walk_expression(c, expression))?;
+        let node = match &expression.node {
+            Call { args, keywords, .. } => node!(vm, Call, {
+                func => c.take(),
+                args => c.take_list(vm, args.len()),
+                keywords => c.take_list(vm, keywords.len()),
+            }),
+            Binop { op, .. } => {
+                // Operator:
+                node!(vm, BinOp, {
+                    left => c.take(),
+                    op => vm.ctx.new_str(operator_string(op)),
+                    right => c.take(),
+                })
+            }
+            Unop { op, .. } => {
+                let op = match op {
+                    ast::UnaryOperator::Not => "Not",
+                    ast::UnaryOperator::Inv => "Invert",
+                    ast::UnaryOperator::Neg => "USub",
+                    ast::UnaryOperator::Pos => "UAdd",
+                };
+                node!(vm, UnaryOp, {
+                    op => vm.ctx.new_str(op.to_string()),
+                    operand => c.take(),
+                })
+            }
+            BoolOp { op, .. } => {
+                // Attach values:
+                let py_values = vm.ctx.new_tuple(vec![c.take(), c.take()]);
+
+                let str_op = match op {
+                    ast::BooleanOperator::And => "And",
+                    ast::BooleanOperator::Or => "Or",
+                };
+                let py_op = vm.ctx.new_str(str_op.to_string());
+
+                node!(vm, BoolOp, {
+                    op => py_op,
+                    values => py_values,
+                })
+            }
+            Compare { vals, ops } => {
+                let left = c.take();
+
+                // Operator:
+                let to_operator = |op: &ast::Comparison| match op {
+                    ast::Comparison::Equal => "Eq",
+                    ast::Comparison::NotEqual => "NotEq",
+                    ast::Comparison::Less => "Lt",
+                    ast::Comparison::LessOrEqual => "LtE",
+                    ast::Comparison::Greater => "Gt",
+                    ast::Comparison::GreaterOrEqual => "GtE",
+                    ast::Comparison::In => "In",
+                    ast::Comparison::NotIn => "NotIn",
+                    ast::Comparison::Is => "Is",
+                    ast::Comparison::IsNot => "IsNot",
+                };
+                let ops = vm.ctx.new_list(
+                    ops.iter()
+                        .map(|x| vm.ctx.new_str(to_operator(x).to_string()))
+                        .collect(),
+                );
+
+                node!(vm, Compare, {
+                    left => left,
+                    ops => ops,
+                    comparators => c.take_list(vm, vals.len() - 1),
+                })
+            }
+            Identifier { name } => node!(vm, Name, {
+                id => vm.ctx.new_str(name.clone()),
+                ctx => vm.ctx.none()   // TODO: add context.
+            }),
+            Lambda { .. } => node!(vm, Lambda, {
+                args => c.take(),
+                body => c.take(),
+            }),
+            IfExpression { .. } => node!(vm, IfExp, {
+                text => c.take(),
+                body => c.take(),
+                or_else => c.take(),
+            }),
+            Number { value } => {
+                let py_n = match value {
+                    ast::Number::Integer { value } => vm.ctx.new_int(value.clone()),
+                    ast::Number::Float { value } => vm.ctx.new_float(*value),
+                    ast::Number::Complex { real, imag } => {
+                        vm.ctx.new_complex(Complex64::new(*real, *imag))
+                    }
+                };
+                node!(vm, Num, {
+                    n => py_n
+                })
+            }
+            True => node!(vm, NameConstant, {
+                value => vm.ctx.new_bool(true)
+            }),
+            False => node!(vm, NameConstant, {
+                value => vm.ctx.new_bool(false)
+            }),
+            None => node!(vm, NameConstant, {
+                value => vm.ctx.none()
+            }),
+            Ellipsis => node!(vm, Ellipsis),
+            List { elements } => node!(vm, List, {
+                elts => c.take_list(vm, elements.len())
+            }),
+            Tuple { elements } => node!(vm, Tuple, {
+                elts => c.take_list(vm, elements.len())
+            }),
+            Set { elements } => node!(vm, Set, {
+                elts => c.take_list(vm, elements.len())
+            }),
+            Dict { elements } => {
+                let mut keys = Vec::new();
+                let mut values = Vec::new();
+                for (k, _) in elements {
+                    keys.push(c.take_optional(vm, k.is_some()));
+                    values.push(c.take());
+                }
 
-            match kind.deref() {
+                node!(vm, Dict, {
+                    keys => vm.ctx.new_list(keys),
+                    values => vm.ctx.new_list(values),
+                })
+            }
+            Comprehension { kind, generators } => match kind.deref() {
                 ast::ComprehensionKind::GeneratorExpression { .. } => {
-                    node!(vm, GeneratorExp, {generators => py_generators})
+                    node!(vm, GeneratorExp, {
+                        elt => c.take(),
+                        generators => c.take_list(vm, generators.len())
+                    })
                 }
                 ast::ComprehensionKind::List { .. } => {
-                    node!(vm, ListComp, {generators => py_generators})
+                    node!(vm, ListComp, {
+                        elt => c.take(),
+                        generators => c.take_list(vm, generators.len())
+                    })
                 }
                 ast::ComprehensionKind::Set { .. } => {
-                    node!(vm, SetComp, {generators => py_generators})
+                    node!(vm, SetComp, {
+                        elt => c.take(),
+                        generators => c.take_list(vm, generators.len())
+                    })
                 }
                 ast::ComprehensionKind::Dict { .. } => {
-                    node!(vm, DictComp, {generators => py_generators})
+                    node!(vm, DictComp, {
+                        key => c.take(),
+                        value => c.take(),
+                        generators => c.take_list(vm, generators.len())
+                    })
                 }
+            },
+            Await { .. } => node!(vm, Await, {
+                value => c.take()
+            }),
+            Yield { value } => node!(vm, Yield, {
+                value => c.take_optional(vm, value.is_some())
+            }),
+            YieldFrom { .. } => node!(vm, YieldFrom, {
+                value => c.take()
+            }),
+            Subscript { .. } => node!(vm, Subscript, {
+                value => c.take(),
+                slice => c.take(),
+            }),
+            Attribute { name, .. } => node!(vm, Attribute, {
+                value => c.take(),
+                attr => vm.ctx.new_str(name.to_string()),
+                ctx => vm.ctx.none()
+            }),
+            Starred { .. } => node!(vm, Starred, {
+                value => c.take()
+            }),
+            Slice { elements } => node!(vm, Slice, {
+                bounds => c.take_list(vm, elements.len())
+            }),
+            String { .. } => c.take().downcast().unwrap(),
+            Bytes { value } => node!(vm, Bytes, { s => vm.ctx.new_bytes(value.clone()) }),
+        };
+
+        let lineno = vm.ctx.new_int(expression.location.row());
+        vm.set_attr(node.as_object(), "lineno", lineno).unwrap();
+        Ok(node)
+    }
+}
+
+impl<'a> Visitor for AstConverter<'a> {
+    type Error = PyObjectRef;
+
+    fn visit_statement(&mut self, statement: &ast::Statement) -> PyResult<()> {
+        let node = self.convert_statement(statement)?;
+        self.push(node);
+        Ok(())
+    }
+
+    fn visit_expression(&mut self, expression: &ast::Expression) -> PyResult<()> {
+        let node = self.convert_expression(expression)?;
+        self.push(node);
+        Ok(())
+    }
+
+    fn visit_import_symbol(&mut self, alias: &ast::ImportSymbol) -> PyResult<()> {
+        let vm = self.vm;
+        let node = node!(vm, alias, {
+            name => vm.ctx.new_str(alias.symbol.to_string()),
+            asname => optional_string_to_py_obj(vm, &alias.alias)
+        });
+        self.push(node);
+        Ok(())
+    }
+
+    fn visit_with_item(&mut self, with_item: &ast::WithItem) -> PyResult<()> {
+        let vm = self.vm;
+        let mut c = self.children(|c| visitor::walk_with_item(c, with_item))?;
+        let node = node!(vm, withitem, {
+            context_expr => c.take(),
+            optional_vars => c.take_optional(vm, with_item.optional_vars.is_some())
+        });
+        self.push(node);
+        Ok(())
+    }
+
+    fn visit_except_handler(&mut self, handler: &ast::ExceptHandler) -> PyResult<()> {
+        let vm = self.vm;
+        let mut c = self.children(|c| visitor::walk_except_handler(c, handler))?;
+        let node = node!(vm, ExceptHandler, {
+            typ => c.take_optional(vm, handler.typ.is_some()),
+            name => optional_string_to_py_obj(vm, &handler.name),
+            body => c.take_list(vm, handler.body.len()),
+        });
+        self.push(node);
+        Ok(())
+    }
+
+    fn visit_parameters(&mut self, args: &ast::Parameters) -> PyResult<()> {
+        let vm = self.vm;
+        let mut c = self.children(|c| visitor::walk_parameters(c, args))?;
+        let defaults = c.take_list(vm, args.defaults.len());
+        let kw_defaults = args
+            .kw_defaults
+            .iter()
+            .map(|default| c.take_optional(vm, default.is_some()))
+            .collect();
+        let py_args = c.take_list(vm, args.args.len());
+        let vararg = c.take_optional(vm, is_named(&args.vararg));
+        let kwonlyargs = c.take_list(vm, args.kwonlyargs.len());
+        let kwarg = c.take_optional(vm, is_named(&args.kwarg));
+        let node = node!(vm, arguments, {
+            args => py_args,
+            vararg => vararg,
+            kwonlyargs => kwonlyargs,
+            kw_defaults => vm.ctx.new_list(kw_defaults),
+            kwarg => kwarg,
+            defaults => defaults,
+        });
+        self.push(node);
+        Ok(())
+    }
+
+    fn visit_parameter(&mut self, parameter: &ast::Parameter) -> PyResult<()> {
+        let vm = self.vm;
+        let mut c = self.children(|c| visitor::walk_parameter(c, parameter))?;
+        let node = node!(vm, arg, {
+            arg => vm.ctx.new_str(parameter.arg.to_string()),
+            annotation => c.take_optional(vm, parameter.annotation.is_some())
+        });
+        self.push(node);
+        Ok(())
+    }
+
+    fn visit_keyword(&mut self, keyword: &ast::Keyword) -> PyResult<()> {
+        let vm = self.vm;
+        let mut c = self.children(|c| visitor::walk_keyword(c, keyword))?;
+        let node = node!(vm, keyword, {
+            arg => optional_string_to_py_obj(vm, &keyword.name),
+            value => c.take()
+        });
+        self.push(node);
+        Ok(())
+    }
+
+    fn visit_comprehension(&mut self, comprehension: &ast::Comprehension) -> PyResult<()> {
+        let vm = self.vm;
+        let mut c = self.children(|c| visitor::walk_comprehension(c, comprehension))?;
+        let node = node!(vm, comprehension, {
+            target => c.take(),
+            iter => c.take(),
+            ifs => c.take_list(vm, comprehension.ifs.len()),
+        });
+        self.push(node);
+        Ok(())
+    }
+
+    fn visit_string_group(&mut self, string: &ast::StringGroup) -> PyResult<()> {
+        let vm = self.vm;
+        let mut c = self.children(|c| visitor::walk_string_group(c, string))?;
+        let node = match string {
+            ast::StringGroup::Constant { value, stand_ins } => {
+                node!(vm, Str, { s => vm.ctx.new_str_with_stand_ins(value.clone(), *stand_ins) })
             }
-        }
-        Await { value } => {
-            let py_value = expression_to_ast(vm, value)?;
-            node!(vm, Await, {
-                value => py_value
-            })
-        }
-        Yield { value } => {
-            let py_value = if let Some(value) = value {
-                expression_to_ast(vm, value)?.into_object()
-            } else {
-                vm.ctx.none()
-            };
-            node!(vm, Yield, {
-                value => py_value
-            })
-        }
-        YieldFrom { value } => {
-            let py_value = expression_to_ast(vm, value)?;
-            node!(vm, YieldFrom, {
-                value => py_value
-            })
-        }
-        Subscript { a, b } => node!(vm, Subscript, {
-            value => expression_to_ast(vm, a)?,
-            slice => expression_to_ast(vm, b)?,
-        }),
-        Attribute { value, name } => node!(vm, Attribute, {
-            value => expression_to_ast(vm, value)?,
-            attr => vm.ctx.new_str(name.to_string()),
-            ctx => vm.ctx.none()
-        }),
-        Starred { value } => node!(vm, Starred, {
-            value => expression_to_ast(vm, value)?
-        }),
-        Slice { elements } => node!(vm, Slice, {
-            bounds => expressions_to_ast(vm, elements)?
-        }),
-        String { value } => string_to_ast(vm, value)?,
-        Bytes { value } => node!(vm, Bytes, { s => vm.ctx.new_bytes(value.clone()) }),
-    };
+            ast::StringGroup::FormattedValue { .. } => {
+                node!(vm, FormattedValue, { value => c.take() })
+            }
+            ast::StringGroup::Joined { values } => {
+                node!(vm, JoinedStr, { values => c.take_list(vm, values.len()) })
+            }
+        };
+        self.push(node);
+        Ok(())
+    }
+}
 
-    let lineno = vm.ctx.new_int(expression.location.row());
-    vm.set_attr(node.as_object(), "lineno", lineno).unwrap();
-    Ok(node)
+fn is_named(varargs: &ast::Varargs) -> bool {
+    match varargs {
+        ast::Varargs::Named(_) => true,
+        _ => false,
+    }
+}
+
+fn make_string_list(vm: &VirtualMachine, names: &[String]) -> PyObjectRef {
+    vm.ctx.new_list(
+        names
+            .iter()
+            .map(|x| vm.ctx.new_str(x.to_string()))
+            .collect(),
+    )
 }
 
 fn operator_string(op: &ast::Operator) -> String {
@@ -519,24 +654,6 @@ fn operator_string(op: &ast::Operator) -> String {
     .to_string()
 }
 
-fn parameters_to_ast(vm: &VirtualMachine, args: &ast::Parameters) -> PyResult<AstNodeRef> {
-    let args = map_ast(parameter_to_ast, vm, &args.args)?;
-    Ok(node!(vm, arguments, { args => args }))
-}
-
-fn parameter_to_ast(vm: &VirtualMachine, parameter: &ast::Parameter) -> PyResult<AstNodeRef> {
-    let py_annotation = if let Some(annotation) = &parameter.annotation {
-        expression_to_ast(vm, annotation)?.into_object()
-    } else {
-        vm.ctx.none()
-    };
-
-    Ok(node!(vm, arg, {
-        arg => vm.ctx.new_str(parameter.arg.to_string()),
-        annotation => py_annotation
-    }))
-}
-
 fn optional_string_to_py_obj(vm: &VirtualMachine, name: &Option<String>) -> PyObjectRef {
     if let Some(name) = name {
         vm.ctx.new_str(name.to_string())
@@ -545,55 +662,10 @@ fn optional_string_to_py_obj(vm: &VirtualMachine, name: &Option<String>) -> PyOb
     }
 }
 
-fn keyword_to_ast(vm: &VirtualMachine, keyword: &ast::Keyword) -> PyResult<AstNodeRef> {
-    Ok(node!(vm, keyword, {
-        arg => optional_string_to_py_obj(vm, &keyword.name),
-        value => expression_to_ast(vm, &keyword.value)?
-    }))
-}
-
-fn map_ast<T>(
-    f: fn(vm: &VirtualMachine, &T) -> PyResult<AstNodeRef>,
-    vm: &VirtualMachine,
-    items: &[T],
-) -> PyResult<PyObjectRef> {
-    let list: PyResult<Vec<PyObjectRef>> =
-        items.iter().map(|x| Ok(f(vm, x)?.into_object())).collect();
-    Ok(vm.ctx.new_list(list?))
-}
-
-fn comprehension_to_ast(
-    vm: &VirtualMachine,
-    comprehension: &ast::Comprehension,
-) -> PyResult<AstNodeRef> {
-    Ok(node!(vm, comprehension, {
-        target => expression_to_ast(vm, &comprehension.target)?,
-        iter => expression_to_ast(vm, &comprehension.iter)?,
-        ifs => expressions_to_ast(vm, &comprehension.ifs)?,
-    }))
-}
-
-fn string_to_ast(vm: &VirtualMachine, string: &ast::StringGroup) -> PyResult<AstNodeRef> {
-    let string = match string {
-        ast::StringGroup::Constant { value } => {
-            node!(vm, Str, { s => vm.ctx.new_str(value.clone()) })
-        }
-        ast::StringGroup::FormattedValue { value, .. } => {
-            node!(vm, FormattedValue, { value => expression_to_ast(vm, value)? })
-        }
-        ast::StringGroup::Joined { values } => {
-            let py_values = map_ast(string_to_ast, vm, &values)?;
-            node!(vm, JoinedStr, { values => py_values })
-        }
-    };
-    Ok(string)
-}
-
 fn ast_parse(source: PyStringRef, vm: &VirtualMachine) -> PyResult<AstNodeRef> {
-    let internal_ast = parser::parse_program(&source.value)
+    let internal_ast = parser::parse_program(source.as_str())
         .map_err(|err| vm.new_value_error(format!("{}", err)))?;
-    // source.clone();
-    program_to_ast(&vm, &internal_ast)
+    AstConverter::new(vm).convert_program(&internal_ast)
 }
 
 pub fn make_module(vm: &VirtualMachine) -> PyObjectRef {
@@ -605,6 +677,7 @@ pub fn make_module(vm: &VirtualMachine) -> PyObjectRef {
         "AST" => ast_base.clone(),
         // TODO: There's got to be a better way!
         "alias" => py_class!(ctx, "alias", ast_base.clone(), {}),
+        "AnnAssign" => py_class!(ctx, "AnnAssign", ast_base.clone(), {}),
         "arg" => py_class!(ctx, "arg", ast_base.clone(), {}),
         "arguments" => py_class!(ctx, "arguments", ast_base.clone(), {}),
         "Assign" => py_class!(ctx, "Assign", ast_base.clone(), {}),
@@ -1,27 +1,264 @@
+/*
+ * Native helpers for the python dis module.
+ *
+ * RustPython instructions carry their operands instead of indices into
+ * tables, so this module decodes them into CPython-style instruction tuples.
+ * Lib/dis.py wraps those into Instruction objects and does the formatting.
+ */
+
+use std::collections::HashSet;
+
+use crate::bytecode::{
+    self, CallType, ComparisonOperator, ConversionFlag, FunctionOpArg, Instruction, Label,
+    NameScope,
+};
 use crate::obj::objcode::PyCodeRef;
-use crate::pyobject::{PyObjectRef, PyResult, TryFromObject};
+use crate::pyobject::{PyObjectRef, PyResult};
 use crate::vm::VirtualMachine;
 
-fn dis_dis(obj: PyObjectRef, vm: &VirtualMachine) -> PyResult {
-    // Method or function:
-    if let Ok(co) = vm.get_attribute(obj.clone(), "__code__") {
-        return dis_disassemble(co, vm);
+/// Comparison operators in the order of CPython's `dis.cmp_op`.
+const COMPARISONS: &[&str] = &[
+    "<", "<=", "==", "!=", ">", ">=", "in", "not in", "is", "is not",
+];
+
+fn comparison_symbol(op: &ComparisonOperator) -> &'static str {
+    match op {
+        ComparisonOperator::Less => "<",
+        ComparisonOperator::LessOrEqual => "<=",
+        ComparisonOperator::Equal => "==",
+        ComparisonOperator::NotEqual => "!=",
+        ComparisonOperator::Greater => ">",
+        ComparisonOperator::GreaterOrEqual => ">=",
+        ComparisonOperator::In => "in",
+        ComparisonOperator::NotIn => "not in",
+        ComparisonOperator::Is => "is",
+        ComparisonOperator::IsNot => "is not",
+    }
+}
+
+/// Tables of a code object that instruction arguments index into, like
+/// CPython's `co_consts`, `co_names` and `co_varnames`.
+struct Tables<'a> {
+    code: &'a bytecode::CodeObject,
+    varnames: Vec<&'a str>,
+    names: Vec<&'a str>,
+    /// Index of the next `LoadConst` in `co_consts`, which starts with the
+    /// docstring.
+    next_const: usize,
+}
+
+impl<'a> Tables<'a> {
+    fn new(code: &'a bytecode::CodeObject) -> Self {
+        Tables {
+            code,
+            varnames: code.varnames(),
+            names: code.names(),
+            next_const: 1,
+        }
     }
 
-    dis_disassemble(obj, vm)
+    fn name_index(&self, name: &str, scope: Option<&NameScope>) -> Option<usize> {
+        if let Some(NameScope::NonLocal) = scope {
+            return None;
+        }
+        if scope.is_some() {
+            if let Some(index) = self.varnames.iter().position(|n| *n == name) {
+                return Some(index);
+            }
+        }
+        self.names.iter().position(|n| *n == name)
+    }
+
+    fn target(&self, label: &Label) -> usize {
+        self.code.label_map[label]
+    }
+}
+
+/// The operands of an instruction as `(arg, argval, argrepr)`, as in
+/// CPython: `arg` is an index into one of the code object's tables, a jump
+/// label or a count, `argval` the value it resolves to and `argrepr` a
+/// human readable form of it.
+fn operands(
+    instruction: &Instruction,
+    tables: &mut Tables,
+    vm: &VirtualMachine,
+) -> PyResult<(PyObjectRef, PyObjectRef, String)> {
+    let int = |value: usize| vm.new_int(value);
+    let index = |index: Option<usize>| match index {
+        Some(index) => int(index),
+        None => vm.get_none(),
+    };
+    let simple = |value: usize| (int(value), int(value), value.to_string());
+    let jump = |label: &Label, offset: usize| (int(*label), int(offset), format!("to {}", offset));
+    let build = |size: usize, unpack: bool| {
+        let repr = if unpack { "unpack" } else { "" };
+        (int(size), int(size), repr.to_string())
+    };
+    let text =
+        |arg: PyObjectRef, text: &str, repr: String| (arg, vm.new_str(text.to_string()), repr);
+
+    let operands = match instruction {
+        Instruction::Import {
+            name: Some(module),
+            symbols,
+            ..
+        } => {
+            let repr = if symbols.is_empty() {
+                module.clone()
+            } else {
+                format!("{} ({})", module, symbols.join(", "))
+            };
+            text(index(tables.name_index(module, None)), module, repr)
+        }
+        Instruction::ImportStar {
+            name: Some(module), ..
+        } => text(
+            index(tables.name_index(module, None)),
+            module,
+            module.clone(),
+        ),
+        Instruction::LoadName { name, scope } | Instruction::StoreName { name, scope } => {
+            let repr = match scope {
+                NameScope::Local => name.clone(),
+                NameScope::NonLocal => format!("{} (nonlocal)", name),
+                NameScope::Global => format!("{} (global)", name),
+            };
+            text(index(tables.name_index(name, Some(scope))), name, repr)
+        }
+        Instruction::DeleteName { name } => text(
+            index(tables.name_index(name, Some(&NameScope::Local))),
+            name,
+            name.clone(),
+        ),
+        Instruction::ImportFrom { name }
+        | Instruction::StoreAttr { name }
+        | Instruction::DeleteAttr { name }
+        | Instruction::LoadAttr { name }
+        | Instruction::LoadMethod { name } => {
+            text(index(tables.name_index(name, None)), name, name.clone())
+        }
+        Instruction::LoadConst { value } => {
+            let value = vm.ctx.unwrap_constant(value);
+            let repr = vm.to_repr(&value)?.as_str().to_string();
+            tables.next_const += 1;
+            (int(tables.next_const - 1), value, repr)
+        }
+        Instruction::UnaryOperation { op } => {
+            let op = format!("{:?}", op);
+            (vm.get_none(), vm.new_str(op.clone()), op)
+        }
+        Instruction::BinaryOperation { op, inplace } => {
+            let op = format!("{:?}", op);
+            let repr = if *inplace {
+                format!("inplace {}", op)
+            } else {
+                op.clone()
+            };
+            (vm.get_none(), vm.new_str(op), repr)
+        }
+        Instruction::CompareOperation { op } => {
+            let symbol = comparison_symbol(op);
+            let position = COMPARISONS.iter().position(|s| *s == symbol);
+            text(index(position), symbol, symbol.to_string())
+        }
+        Instruction::Jump { target: label }
+        | Instruction::JumpIf { target: label }
+        | Instruction::JumpIfFalse { target: label }
+        | Instruction::ForIter { target: label }
+        | Instruction::SetupLoop { end: label, .. }
+        | Instruction::SetupExcept { handler: label }
+        | Instruction::SetupWith { end: label }
+        | Instruction::CleanupWith { end: label } => jump(label, tables.target(label)),
+        Instruction::MakeFunction { flags } => {
+            let bits = flags.bits() as usize;
+            let names = [
+                (FunctionOpArg::HAS_DEFAULTS, "defaults"),
+                (FunctionOpArg::HAS_KW_ONLY_DEFAULTS, "kwdefaults"),
+                (FunctionOpArg::HAS_ANNOTATIONS, "annotations"),
+            ];
+            let repr: Vec<&str> = names
+                .iter()
+                .filter(|(flag, _)| flags.contains(*flag))
+                .map(|(_, name)| *name)
+                .collect();
+            (int(bits), int(bits), repr.join(", "))
+        }
+        Instruction::CallFunction { typ } | Instruction::CallMethod { typ } => {
+            let (count, repr) = match typ {
+                CallType::Positional(count) => (int(*count), "positional"),
+                CallType::Keyword(count) => (int(*count), "keyword"),
+                CallType::Ex(has_kwargs) => (int(*has_kwargs as usize), "ex"),
+            };
+            (count.clone(), count, repr.to_string())
+        }
+        Instruction::BuildTuple { size, unpack }
+        | Instruction::BuildList { size, unpack }
+        | Instruction::BuildSet { size, unpack }
+        | Instruction::BuildMap { size, unpack } => build(*size, *unpack),
+        Instruction::Rotate { amount: value }
+        | Instruction::Raise { argc: value }
+        | Instruction::BuildString { size: value }
+        | Instruction::BuildSlice { size: value }
+        | Instruction::ListAppend { i: value }
+        | Instruction::SetAdd { i: value }
+        | Instruction::MapAdd { i: value }
+        | Instruction::UnpackSequence { size: value } => simple(*value),
+        Instruction::UnpackEx { before, after } => {
+            // Packed like CPython's UNPACK_EX argument.
+            let arg = int(before | (after << 8));
+            (arg.clone(), arg, format!("{}, {}", before, after))
+        }
+        Instruction::FormatValue { conversion, spec } => {
+            let conversion = match conversion {
+                Some(ConversionFlag::Str) => "!s",
+                Some(ConversionFlag::Ascii) => "!a",
+                Some(ConversionFlag::Repr) => "!r",
+                None => "",
+            };
+            let repr = if spec.is_empty() {
+                conversion.to_string()
+            } else {
+                format!("{}:{}", conversion, spec)
+            };
+            text(vm.get_none(), spec, repr)
+        }
+        _ => (vm.get_none(), vm.get_none(), String::new()),
+    };
+    Ok(operands)
 }
 
-fn dis_disassemble(co: PyObjectRef, vm: &VirtualMachine) -> PyResult {
-    let code = &PyCodeRef::try_from_object(vm, co)?.code;
-    print!("{}", code);
-    Ok(vm.get_none())
+/// The instructions of a code object as tuples of `(opname, opcode, arg,
+/// argval, argrepr, offset, is_jump_target)`.
+fn dis_get_instructions(code: PyCodeRef, vm: &VirtualMachine) -> PyResult {
+    let code = &code.code;
+    let mut tables = Tables::new(code);
+    let jump_targets: HashSet<usize> = code.label_map.values().cloned().collect();
+    let mut instructions = vec![];
+    for (offset, instruction) in code.instructions.iter().enumerate() {
+        let (arg, argval, argrepr) = operands(instruction, &mut tables, vm)?;
+        instructions.push(vm.ctx.new_tuple(vec![
+            vm.new_str(instruction.name().to_string()),
+            vm.new_int(instruction.opcode()),
+            arg,
+            argval,
+            vm.new_str(argrepr),
+            vm.new_int(offset),
+            vm.new_bool(jump_targets.contains(&offset)),
+        ]));
+    }
+    Ok(vm.ctx.new_list(instructions))
 }
 
 pub fn make_module(vm: &VirtualMachine) -> PyObjectRef {
     let ctx = &vm.ctx;
 
-    py_module!(vm, "dis", {
-        "dis" => ctx.new_rustfunc(dis_dis),
-        "disassemble" => ctx.new_rustfunc(dis_disassemble)
+    let opname = bytecode::INSTRUCTION_NAMES
+        .iter()
+        .map(|name| ctx.new_str(name.to_string()))
+        .collect();
+
+    py_module!(vm, "_dis", {
+        "opname" => ctx.new_list(opname),
+        "get_instructions" => ctx.new_rustfunc(dis_get_instructions),
     })
 }
@@ -0,0 +1,120 @@
+//! Implementation in line with the python `gc` module.
+//!
+//! See also:
+//! - [python gc module](https://docs.python.org/3/library/gc.html)
+
+use crate::function::{Args, OptionalArg};
+use crate::gc;
+use crate::pyobject::{PyObjectRef, PyResult};
+use crate::vm::VirtualMachine;
+
+fn gc_collect(generation: OptionalArg<usize>, vm: &VirtualMachine) -> PyResult<usize> {
+    let generation = generation.unwrap_or(gc::NUM_GENERATIONS - 1);
+    if generation >= gc::NUM_GENERATIONS {
+        return Err(vm.new_value_error("invalid generation".to_string()));
+    }
+    Ok(gc::collect(generation, vm))
+}
+
+fn gc_enable(_vm: &VirtualMachine) {
+    gc::set_enabled(true);
+}
+
+fn gc_disable(_vm: &VirtualMachine) {
+    gc::set_enabled(false);
+}
+
+fn gc_isenabled(_vm: &VirtualMachine) -> bool {
+    gc::is_enabled()
+}
+
+fn gc_get_debug(_vm: &VirtualMachine) -> u32 {
+    gc::get_debug()
+}
+
+fn gc_set_debug(flags: u32, _vm: &VirtualMachine) {
+    gc::set_debug(flags);
+}
+
+fn gc_get_threshold(vm: &VirtualMachine) -> PyObjectRef {
+    let thresholds = gc::get_thresholds();
+    vm.ctx.new_tuple(
+        thresholds
+            .iter()
+            .map(|&threshold| vm.ctx.new_int(threshold))
+            .collect(),
+    )
+}
+
+fn gc_set_threshold(
+    threshold0: usize,
+    threshold1: OptionalArg<usize>,
+    threshold2: OptionalArg<usize>,
+    _vm: &VirtualMachine,
+) {
+    let [_, old1, old2] = gc::get_thresholds();
+    gc::set_thresholds([
+        threshold0,
+        threshold1.unwrap_or(old1),
+        threshold2.unwrap_or(old2),
+    ]);
+}
+
+fn gc_get_count(vm: &VirtualMachine) -> PyObjectRef {
+    let counts = gc::get_counts();
+    vm.ctx
+        .new_tuple(counts.iter().map(|&count| vm.ctx.new_int(count)).collect())
+}
+
+fn gc_get_objects(generation: OptionalArg<Option<usize>>, vm: &VirtualMachine) -> PyResult {
+    let generation = generation.into_option().and_then(|generation| generation);
+    if let Some(generation) = generation {
+        if generation >= gc::NUM_GENERATIONS {
+            return Err(vm.new_value_error(format!(
+                "generation parameter must be less than the number of available generations ({})",
+                gc::NUM_GENERATIONS
+            )));
+        }
+    }
+    Ok(vm.ctx.new_list(gc::get_objects(generation)))
+}
+
+fn gc_get_referrers(objs: Args, vm: &VirtualMachine) -> PyObjectRef {
+    let objs: Vec<PyObjectRef> = objs.into_iter().collect();
+    vm.ctx.new_list(gc::get_referrers(&objs))
+}
+
+fn gc_get_referents(objs: Args, vm: &VirtualMachine) -> PyObjectRef {
+    let objs: Vec<PyObjectRef> = objs.into_iter().collect();
+    vm.ctx.new_list(gc::get_referents(&objs))
+}
+
+fn gc_is_tracked(obj: PyObjectRef, _vm: &VirtualMachine) -> bool {
+    obj.is_gc_tracked()
+}
+
+pub fn make_module(vm: &VirtualMachine) -> PyObjectRef {
+    let ctx = &vm.ctx;
+
+    py_module!(vm, "gc", {
+        "collect" => ctx.new_rustfunc(gc_collect),
+        "enable" => ctx.new_rustfunc(gc_enable),
+        "disable" => ctx.new_rustfunc(gc_disable),
+        "isenabled" => ctx.new_rustfunc(gc_isenabled),
+        "get_debug" => ctx.new_rustfunc(gc_get_debug),
+        "set_debug" => ctx.new_rustfunc(gc_set_debug),
+        "get_threshold" => ctx.new_rustfunc(gc_get_threshold),
+        "set_threshold" => ctx.new_rustfunc(gc_set_threshold),
+        "get_count" => ctx.new_rustfunc(gc_get_count),
+        "get_objects" => ctx.new_rustfunc(gc_get_objects),
+        "get_referrers" => ctx.new_rustfunc(gc_get_referrers),
+        "get_referents" => ctx.new_rustfunc(gc_get_referents),
+        "is_tracked" => ctx.new_rustfunc(gc_is_tracked),
+        "garbage" => gc::garbage(vm).into_object(),
+        "DEBUG_STATS" => ctx.new_int(gc::DEBUG_STATS),
+        "DEBUG_COLLECTABLE" => ctx.new_int(gc::DEBUG_COLLECTABLE),
+        "DEBUG_UNCOLLECTABLE" => ctx.new_int(gc::DEBUG_UNCOLLECTABLE),
+        "DEBUG_SAVEALL" => ctx.new_int(gc::DEBUG_SAVEALL),
+        "DEBUG_LEAK" => ctx.new_int(gc::DEBUG_LEAK),
+    })
+}
@@ -86,7 +86,7 @@ fn hashlib_new(
     data: OptionalArg<PyBytesRef>,
     vm: &VirtualMachine,
 ) -> PyResult<PyHasher> {
-    let hasher = match name.value.as_ref() {
+    let hasher = match name.as_str() {
         "md5" => Ok(PyHasher::new("md5", HashWrapper::md5())),
         "sha1" => Ok(PyHasher::new("sha1", HashWrapper::sha1())),
         "sha224" => Ok(PyHasher::new("sha224", HashWrapper::sha224())),
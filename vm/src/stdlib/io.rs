@@ -11,6 +11,7 @@ use num_traits::ToPrimitive;
 
 use super::os;
 use crate::function::{OptionalArg, PyFuncArgs};
+use crate::gil;
 use crate::obj::objbytearray::PyByteArray;
 use crate::obj::objbytes;
 use crate::obj::objbytes::PyBytes;
@@ -19,7 +20,8 @@ use crate::obj::objstr;
 use crate::obj::objtype;
 use crate::obj::objtype::PyClassRef;
 use crate::pyobject::TypeProtocol;
-use crate::pyobject::{BufferProtocol, PyObjectRef, PyRef, PyResult, PyValue};
+use crate::pyobject::{BufferProtocol, IntoPyObject, PyObjectRef, PyRef, PyResult, PyValue};
+use crate::surrogates::{self, ErrorHandler};
 use crate::vm::VirtualMachine;
 
 fn byte_count(bytes: OptionalArg<Option<PyObjectRef>>) -> i64 {
@@ -85,6 +87,29 @@ impl BufferedIO {
 
         Some(buffer)
     }
+
+    //Read up to and including the next newline.
+    fn readline(&mut self) -> Option<Vec<u8>> {
+        let mut buffer = Vec::new();
+        match self.cursor.read_until(b'\n', &mut buffer) {
+            Ok(_) => Some(buffer),
+            Err(_) => None,
+        }
+    }
+}
+
+/// StringIO keeps its text as UTF-8 with lone surrogates passed through, so
+/// they come back out as they went in.
+fn encode_text(text: &objstr::PyString, vm: &VirtualMachine) -> PyResult<Vec<u8>> {
+    surrogates::encode_utf8(text, ErrorHandler::SurrogatePass)
+        .map_err(|err| err.into_exception("surrogatepass", vm))
+}
+
+fn decode_text(bytes: &[u8], vm: &VirtualMachine) -> PyResult {
+    match surrogates::decode_utf8(bytes, ErrorHandler::SurrogatePass) {
+        Ok(text) => text.into_pyobject(vm),
+        Err(_) => Err(vm.new_value_error("Error Retrieving Value".to_string())),
+    }
 }
 
 #[derive(Debug)]
@@ -103,20 +128,17 @@ impl PyValue for PyStringIO {
 impl PyStringIORef {
     //write string to underlying vector
     fn write(self, data: objstr::PyStringRef, vm: &VirtualMachine) -> PyResult {
-        let bytes = &data.value.clone().into_bytes();
+        let bytes = encode_text(&data, vm)?;
 
-        match self.buffer.borrow_mut().write(bytes.to_vec()) {
-            Some(value) => Ok(vm.ctx.new_int(value)),
+        match self.buffer.borrow_mut().write(bytes) {
+            Some(_) => Ok(vm.ctx.new_int(data.char_len())),
             None => Err(vm.new_type_error("Error Writing String".to_string())),
         }
     }
 
     //return the entire contents of the underlying
     fn getvalue(self, vm: &VirtualMachine) -> PyResult {
-        match String::from_utf8(self.buffer.borrow().getvalue()) {
-            Ok(result) => Ok(vm.ctx.new_str(result)),
-            Err(_) => Err(vm.new_value_error("Error Retrieving Value".to_string())),
-        }
+        decode_text(&self.buffer.borrow().getvalue(), vm)
     }
 
     //skip to the jth position
@@ -141,10 +163,14 @@ impl PyStringIORef {
             None => Vec::new(),
         };
 
-        match String::from_utf8(data) {
-            Ok(value) => Ok(vm.ctx.new_str(value)),
-            Err(_) => Err(vm.new_value_error("Error Retrieving Value".to_string())),
-        }
+        decode_text(&data, vm)
+    }
+
+    //Read a single line, an empty string signals the end of the buffer.
+    fn readline(self, vm: &VirtualMachine) -> PyResult {
+        let data = self.buffer.borrow_mut().readline().unwrap_or_default();
+
+        decode_text(&data, vm)
     }
 }
 
@@ -153,13 +179,13 @@ fn string_io_new(
     object: OptionalArg<Option<PyObjectRef>>,
     vm: &VirtualMachine,
 ) -> PyResult<PyStringIORef> {
-    let raw_string = match object {
-        OptionalArg::Present(Some(ref input)) => objstr::get_value(input),
-        _ => String::new(),
+    let raw_bytes = match object {
+        OptionalArg::Present(Some(ref input)) => encode_text(objstr::borrow_str(input), vm)?,
+        _ => Vec::new(),
     };
 
     PyStringIO {
-        buffer: RefCell::new(BufferedIO::new(Cursor::new(raw_string.into_bytes()))),
+        buffer: RefCell::new(BufferedIO::new(Cursor::new(raw_bytes))),
     }
     .into_ref_with_type(vm, cls)
 }
@@ -201,6 +227,14 @@ impl PyBytesIORef {
         }
     }
 
+    //Read a single line, an empty bytes object signals the end of the buffer.
+    fn readline(self, vm: &VirtualMachine) -> PyResult {
+        match self.buffer.borrow_mut().readline() {
+            Some(value) => Ok(vm.ctx.new_bytes(value)),
+            None => Err(vm.new_value_error("Error Retrieving Value".to_string())),
+        }
+    }
+
     //skip to the jth position
     fn seek(self, offset: PyObjectRef, vm: &VirtualMachine) -> PyResult {
         let position = objint::get_value(&offset).to_u64().unwrap();
@@ -354,7 +388,7 @@ fn file_io_read(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     let mut handle = os::rust_file(raw_fd);
 
     let mut bytes = vec![];
-    match handle.read_to_end(&mut bytes) {
+    match gil::allow_threads(|| handle.read_to_end(&mut bytes)) {
         Ok(_) => {}
         Err(_) => return Err(vm.new_value_error("Error reading from Buffer".to_string())),
     }
@@ -418,7 +452,8 @@ fn file_io_write(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
                 )))
     );
 
-    match handle.write(&bytes?) {
+    let bytes = bytes?;
+    match gil::allow_threads(|| handle.write(&bytes)) {
         Ok(len) => {
             //reset raw fd on the FileIO object
             let updated = os::raw_file_number(handle);
@@ -502,6 +537,7 @@ fn text_io_base_write(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
         args,
         required = [(text_io_base, None), (obj, Some(vm.ctx.str_type()))]
     );
+    let text = objstr::borrow_str(obj);
 
     let buffered_writer_class = vm.try_class("_io", "BufferedWriter")?;
     let raw = vm.get_attribute(text_io_base.clone(), "buffer").unwrap();
@@ -511,7 +547,8 @@ fn text_io_base_write(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
         return Err(vm.new_value_error("not writable".to_string()));
     }
 
-    let bytes = objstr::get_value(obj).into_bytes();
+    let bytes = surrogates::encode_utf8(text, ErrorHandler::Strict)
+        .map_err(|err| err.into_exception("strict", vm))?;
 
     let len = vm.call_method(&raw, "write", vec![vm.ctx.new_bytes(bytes.clone())])?;
     let len = objint::get_value(&len).to_usize().ok_or_else(|| {
@@ -712,6 +749,7 @@ pub fn make_module(vm: &VirtualMachine) -> PyObjectRef {
         "seek" => ctx.new_rustfunc(PyStringIORef::seek),
         "seekable" => ctx.new_rustfunc(PyStringIORef::seekable),
         "read" => ctx.new_rustfunc(PyStringIORef::read),
+        "readline" => ctx.new_rustfunc(PyStringIORef::readline),
         "write" => ctx.new_rustfunc(PyStringIORef::write),
         "getvalue" => ctx.new_rustfunc(PyStringIORef::getvalue)
     });
@@ -721,6 +759,7 @@ pub fn make_module(vm: &VirtualMachine) -> PyObjectRef {
         "__new__" => ctx.new_rustfunc(bytes_io_new),
         "read" => ctx.new_rustfunc(PyBytesIORef::read),
         "read1" => ctx.new_rustfunc(PyBytesIORef::read),
+        "readline" => ctx.new_rustfunc(PyBytesIORef::readline),
         "seek" => ctx.new_rustfunc(PyBytesIORef::seek),
         "seekable" => ctx.new_rustfunc(PyBytesIORef::seekable),
         "write" => ctx.new_rustfunc(PyBytesIORef::write),
@@ -1,24 +1,264 @@
+use std::io::{self, Read};
+
+use py_code_object::marshal::{self, MarshalError, Value, VERSION};
+use py_code_object::PyCodeObject;
+
 use crate::bytecode;
-use crate::obj::objbytes::{PyBytes, PyBytesRef};
-use crate::obj::objcode::{PyCode, PyCodeRef};
-use crate::pyobject::{IntoPyObject, PyObjectRef, PyResult};
+use crate::function::OptionalArg;
+use crate::obj::objbytearray::PyByteArray;
+use crate::obj::objbyteinner::PyByteInner;
+use crate::obj::objbytes::{self, PyBytes};
+use crate::obj::objcode::{self, PyCode};
+use crate::obj::objcomplex::{self, PyComplex};
+use crate::obj::objdict::PyDict;
+use crate::obj::objfloat::PyFloat;
+use crate::obj::objint::PyInt;
+use crate::obj::objlist::PyList;
+use crate::obj::objset::{PyFrozenSet, PySet};
+use crate::obj::objstr::PyString;
+use crate::obj::objtuple::PyTuple;
+use crate::pyobject::{
+    IdProtocol, IntoPyObject, ItemProtocol, PyObjectRef, PyResult, TypeProtocol,
+};
+use crate::surrogates::{self, ErrorHandler};
 use crate::vm::VirtualMachine;
 
-fn marshal_dumps(co: PyCodeRef, vm: &VirtualMachine) -> PyResult {
-    PyBytes::new(bincode::serialize(&co.code).unwrap()).into_pyobject(vm)
+/// Nesting limit for both directions, as in CPython.
+const MAX_MARSHAL_STACK_DEPTH: usize = 2000;
+
+/// Set in the flags of marshalled RustPython code objects, whose `co_code`
+/// holds serialized RustPython bytecode instead of CPython wordcode. Code
+/// objects without it are translated from CPython 3.7 bytecode.
+const CO_RUSTPYTHON: u32 = 0x4000_0000;
+
+fn to_value(obj: &PyObjectRef, depth: usize, vm: &VirtualMachine) -> PyResult<Value> {
+    if depth > MAX_MARSHAL_STACK_DEPTH {
+        return Err(vm.new_value_error("object too deeply nested to marshal".to_string()));
+    }
+    let elements = |elements: &[PyObjectRef]| -> PyResult<Vec<Value>> {
+        elements
+            .iter()
+            .map(|element| to_value(element, depth + 1, vm))
+            .collect()
+    };
+
+    if obj.is(&vm.get_none()) {
+        return Ok(Value::None);
+    } else if obj.is(&vm.ctx.ellipsis()) {
+        return Ok(Value::Ellipsis);
+    } else if obj.is(&vm.ctx.exceptions.stop_iteration) {
+        return Ok(Value::StopIteration);
+    } else if obj.class().is(&vm.ctx.bool_type()) {
+        return Ok(Value::Bool(obj.is(&vm.ctx.new_bool(true))));
+    }
+
+    let value = match_class!(obj.clone(),
+        i @ PyInt => Value::Int(i.as_bigint().clone()),
+        f @ PyFloat => Value::Float(f.to_f64()),
+        c @ PyComplex => Value::Complex(objcomplex::get_value(c.as_object())),
+        s @ PyString => {
+            let bytes = surrogates::encode_utf8(&s, ErrorHandler::SurrogatePass)
+                .map_err(|err| err.into_exception("surrogatepass", vm))?;
+            String::from_utf8(bytes)
+                .map(Value::Str)
+                .unwrap_or_else(|err| Value::SurrogateStr(err.into_bytes()))
+        },
+        b @ PyBytes => Value::Bytes(b.get_value().to_vec()),
+        b @ PyByteArray => Value::Bytes(b.inner.borrow().elements.clone()),
+        t @ PyTuple => Value::Tuple(elements(&t.elements)?),
+        l @ PyList => Value::List(elements(&l.elements.borrow())?),
+        d @ PyDict => {
+            let mut items = vec![];
+            for (key, value) in d {
+                items.push((to_value(&key, depth + 1, vm)?, to_value(&value, depth + 1, vm)?));
+            }
+            Value::Dict(items)
+        },
+        s @ PySet => Value::Set(elements(&vm.extract_elements(s.as_object())?)?),
+        s @ PyFrozenSet => Value::FrozenSet(elements(&vm.extract_elements(s.as_object())?)?),
+        c @ PyCode => Value::Code(Box::new(code_to_value(&c.code, vm)?)),
+        _ => return Err(vm.new_value_error("unmarshallable object".to_string())),
+    );
+    Ok(value)
+}
+
+/// Lay a RustPython code object out like a CPython one, with the RustPython
+/// bytecode serialized into `co_code`.
+fn code_to_value(code: &bytecode::CodeObject, vm: &VirtualMachine) -> PyResult<PyCodeObject> {
+    let to_strings = |names: Vec<&str>| names.into_iter().map(str::to_string).collect();
+    let varnames: Vec<String> = to_strings(code.varnames());
+    Ok(PyCodeObject {
+        argcount: code.arg_names.len() as u32,
+        kwonlyargcount: code.kwonlyarg_names.len() as u32,
+        nlocals: varnames.len() as u32,
+        stacksize: code.max_stackdepth as u32,
+        flags: code.co_flags().bits() | CO_RUSTPYTHON,
+        code: bincode::serialize(code).map_err(|err| vm.new_value_error(err.to_string()))?,
+        consts: vec![],
+        names: to_strings(code.names()),
+        varnames,
+        freevars: code.freevars.clone(),
+        cellvars: code.cellvars.clone(),
+        filename: code.source_path.clone(),
+        name: code.obj_name.clone(),
+        firstlineno: code.first_line_number as u32,
+        lnotab: code.lnotab(),
+    })
+}
+
+fn from_value(value: Value, vm: &VirtualMachine) -> PyResult {
+    let elements = |elements: Vec<Value>| -> PyResult<Vec<PyObjectRef>> {
+        elements
+            .into_iter()
+            .map(|element| from_value(element, vm))
+            .collect()
+    };
+    Ok(match value {
+        Value::None => vm.get_none(),
+        Value::StopIteration => vm.ctx.exceptions.stop_iteration.clone().into_object(),
+        Value::Ellipsis => vm.ctx.ellipsis(),
+        Value::Bool(value) => vm.new_bool(value),
+        Value::Int(value) => vm.new_int(value),
+        Value::Float(value) => vm.ctx.new_float(value),
+        Value::Complex(value) => vm.ctx.new_complex(value),
+        Value::Str(value) => vm.new_str(value),
+        Value::SurrogateStr(value) => surrogates::decode_utf8(&value, ErrorHandler::SurrogatePass)
+            .map_err(|err| err.into_exception("surrogatepass", vm))?
+            .into_pyobject(vm)?,
+        Value::Bytes(value) => vm.ctx.new_bytes(value),
+        Value::Tuple(values) => vm.ctx.new_tuple(elements(values)?),
+        Value::List(values) => vm.ctx.new_list(elements(values)?),
+        Value::Dict(items) => {
+            let dict = vm.ctx.new_dict();
+            for (key, value) in items {
+                dict.set_item(from_value(key, vm)?, from_value(value, vm)?, vm)?;
+            }
+            dict.into_object()
+        }
+        Value::Set(values) => {
+            let set = vm.ctx.new_set();
+            for element in elements(values)? {
+                vm.call_method(&set, "add", vec![element])?;
+            }
+            set
+        }
+        Value::FrozenSet(values) => {
+            let elements = vm.ctx.new_tuple(elements(values)?);
+            vm.invoke(vm.ctx.frozenset_type().into_object(), vec![elements])?
+        }
+        Value::Code(code) => vm
+            .ctx
+            .new_code_object(code_from_value(*code, vm)?)
+            .into_object(),
+    })
+}
+
+fn code_from_value(code: PyCodeObject, vm: &VirtualMachine) -> PyResult<bytecode::CodeObject> {
+    let code = if code.flags & CO_RUSTPYTHON != 0 {
+        bincode::deserialize(&code.code)
+            .map_err(|_| vm.new_value_error("bad marshal data (invalid code object)".to_string()))?
+    } else {
+        py_code_object::convert(&code).map_err(|err| vm.new_value_error(err.to_string()))?
+    };
+    objcode::verify_code(code, vm)
+}
+
+fn marshal_error(error: MarshalError, vm: &VirtualMachine) -> PyObjectRef {
+    match error {
+        MarshalError::Eof | MarshalError::TooShort => {
+            vm.new_exception(vm.ctx.exceptions.eof_error.clone(), error.to_string())
+        }
+        _ => vm.new_value_error(error.to_string()),
+    }
+}
+
+fn read_value<R: Read>(source: &mut R, vm: &VirtualMachine) -> PyResult {
+    let value = marshal::read_value(source).map_err(|err| marshal_error(err, vm))?;
+    from_value(value, vm)
+}
+
+/// Reads from a Python file object by calling its `read` method, keeping any
+/// exception it raises.
+struct PyFileReader<'a> {
+    file: PyObjectRef,
+    vm: &'a VirtualMachine,
+    exception: Option<PyObjectRef>,
+}
+
+impl<'a> Read for PyFileReader<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let vm = self.vm;
+        let data = vm
+            .call_method(&self.file, "read", vec![vm.new_int(buf.len())])
+            .and_then(|data| match data.payload::<PyBytes>() {
+                Some(bytes) if bytes.get_value().len() <= buf.len() => Ok(data.clone()),
+                Some(_) => Err(vm.new_value_error("read() returned too much data".to_string())),
+                None => Err(vm.new_type_error(format!(
+                    "file.read() returned not bytes but {}",
+                    data.class().name
+                ))),
+            });
+        match data {
+            Ok(data) => {
+                let data = objbytes::get_value(&data);
+                buf[..data.len()].copy_from_slice(&data);
+                Ok(data.len())
+            }
+            Err(exception) => {
+                self.exception = Some(exception);
+                Err(io::Error::new(io::ErrorKind::Other, "read() failed"))
+            }
+        }
+    }
+}
+
+fn version_arg(version: OptionalArg<i32>) -> u32 {
+    version.unwrap_or(VERSION as i32).max(0) as u32
+}
+
+fn marshal_dumps(value: PyObjectRef, version: OptionalArg<i32>, vm: &VirtualMachine) -> PyResult {
+    let value = to_value(&value, 0, vm)?;
+    Ok(vm
+        .ctx
+        .new_bytes(marshal::dumps(&value, version_arg(version))))
+}
+
+fn marshal_dump(
+    value: PyObjectRef,
+    file: PyObjectRef,
+    version: OptionalArg<i32>,
+    vm: &VirtualMachine,
+) -> PyResult {
+    let data = marshal_dumps(value, version, vm)?;
+    vm.call_method(&file, "write", vec![data])?;
+    Ok(vm.get_none())
+}
+
+fn marshal_loads(data: PyByteInner, vm: &VirtualMachine) -> PyResult {
+    read_value(&mut data.elements.as_slice(), vm)
 }
 
-fn marshal_loads(code_bytes: PyBytesRef, vm: &VirtualMachine) -> PyResult {
-    let code = bincode::deserialize::<bytecode::CodeObject>(&code_bytes).unwrap();
-    let pycode = PyCode { code };
-    pycode.into_pyobject(vm)
+fn marshal_load(file: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+    let mut reader = PyFileReader {
+        file,
+        vm,
+        exception: None,
+    };
+    let result = read_value(&mut reader, vm);
+    match reader.exception {
+        Some(exception) => Err(exception),
+        None => result,
+    }
 }
 
 pub fn make_module(vm: &VirtualMachine) -> PyObjectRef {
     let ctx = &vm.ctx;
 
     py_module!(vm, "marshal", {
+        "version" => ctx.new_int(VERSION),
         "loads" => ctx.new_rustfunc(marshal_loads),
-        "dumps" => ctx.new_rustfunc(marshal_dumps)
+        "load" => ctx.new_rustfunc(marshal_load),
+        "dumps" => ctx.new_rustfunc(marshal_dumps),
+        "dump" => ctx.new_rustfunc(marshal_dump),
     })
 }
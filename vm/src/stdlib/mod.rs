@@ -5,6 +5,7 @@ mod codecs;
 mod collections;
 mod dis;
 mod errno;
+mod gc;
 mod hashlib;
 mod imp;
 mod itertools;
@@ -47,10 +48,11 @@ pub fn get_module_inits() -> HashMap<String, StdlibInitFunc> {
     #[allow(unused_mut)]
     let mut modules = hashmap! {
         "binascii".to_string() => Box::new(binascii::make_module) as StdlibInitFunc,
-        "dis".to_string() => Box::new(dis::make_module),
+        "_dis".to_string() => Box::new(dis::make_module),
         "_codecs".to_string() => Box::new(codecs::make_module),
         "_collections".to_string() => Box::new(collections::make_module),
         "errno".to_string() => Box::new(errno::make_module),
+        "gc".to_string() => Box::new(gc::make_module),
         "hashlib".to_string() => Box::new(hashlib::make_module),
         "itertools".to_string() => Box::new(itertools::make_module),
         "json".to_string() => Box::new(json::make_module),
@@ -77,7 +79,7 @@ pub fn get_module_inits() -> HashMap<String, StdlibInitFunc> {
             Box::new(ast::make_module) as StdlibInitFunc,
         );
         modules.insert("keyword".to_string(), Box::new(keyword::make_module));
-        modules.insert("tokenize".to_string(), Box::new(tokenize::make_module));
+        modules.insert("_tokenize".to_string(), Box::new(tokenize::make_module));
     }
 
     // Insert compiler related modules:
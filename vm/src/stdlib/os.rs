@@ -1,4 +1,5 @@
 use std::cell::RefCell;
+use std::ffi::OsStr;
 use std::fs::File;
 use std::fs::OpenOptions;
 use std::io::{self, ErrorKind, Read, Write};
@@ -9,6 +10,7 @@ use bitflags::bitflags;
 use num_traits::cast::ToPrimitive;
 
 use crate::function::{IntoPyNativeFunc, PyFuncArgs};
+use crate::gil;
 use crate::obj::objbytes::PyBytesRef;
 use crate::obj::objdict::PyDictRef;
 use crate::obj::objint::{self, PyInt, PyIntRef};
@@ -17,8 +19,9 @@ use crate::obj::objset::PySet;
 use crate::obj::objstr::{self, PyString, PyStringRef};
 use crate::obj::objtype::{self, PyClassRef};
 use crate::pyobject::{
-    ItemProtocol, PyClassImpl, PyObjectRef, PyRef, PyResult, PyValue, TryIntoRef, TypeProtocol,
+    IntoPyObject, ItemProtocol, PyClassImpl, PyObjectRef, PyRef, PyResult, PyValue, TypeProtocol,
 };
+use crate::surrogates::{fsdecode, fsencode};
 use crate::vm::VirtualMachine;
 
 #[cfg(unix)]
@@ -117,7 +120,7 @@ pub fn os_open(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     } else {
         DirFd::default()
     };
-    let fname = &make_path(vm, name, &dir_fd).value;
+    let fname = fsencode(&make_path(vm, name, &dir_fd), vm)?;
 
     let flags = FileCreationFlags::from_bits(objint::get_value(flags).to_u32().unwrap())
         .ok_or_else(|| vm.new_value_error("Unsupported flag".to_string()))?;
@@ -202,8 +205,7 @@ fn os_fsync(fd: PyIntRef, vm: &VirtualMachine) -> PyResult<()> {
 fn os_read(fd: PyIntRef, n: PyIntRef, vm: &VirtualMachine) -> PyResult {
     let mut buffer = vec![0u8; n.as_bigint().to_usize().unwrap()];
     let mut file = rust_file(fd.as_bigint().to_i64().unwrap());
-    file.read_exact(&mut buffer)
-        .map_err(|err| convert_io_error(vm, err))?;
+    gil::allow_threads(|| file.read_exact(&mut buffer)).map_err(|err| convert_io_error(vm, err))?;
 
     // Avoid closing the fd
     raw_file_number(file);
@@ -212,7 +214,9 @@ fn os_read(fd: PyIntRef, n: PyIntRef, vm: &VirtualMachine) -> PyResult {
 
 fn os_write(fd: PyIntRef, data: PyBytesRef, vm: &VirtualMachine) -> PyResult {
     let mut file = rust_file(fd.as_bigint().to_i64().unwrap());
-    let written = file.write(&data).map_err(|err| convert_io_error(vm, err))?;
+    let data: &[u8] = &data;
+    let written =
+        gil::allow_threads(|| file.write(data)).map_err(|err| convert_io_error(vm, err))?;
 
     // Avoid closing the fd
     raw_file_number(file);
@@ -221,29 +225,29 @@ fn os_write(fd: PyIntRef, data: PyBytesRef, vm: &VirtualMachine) -> PyResult {
 
 fn os_remove(path: PyStringRef, dir_fd: DirFd, vm: &VirtualMachine) -> PyResult<()> {
     let path = make_path(vm, path, &dir_fd);
-    fs::remove_file(&path.value).map_err(|err| convert_io_error(vm, err))
+    fs::remove_file(fsencode(&path, vm)?).map_err(|err| convert_io_error(vm, err))
 }
 
 fn os_mkdir(path: PyStringRef, dir_fd: DirFd, vm: &VirtualMachine) -> PyResult<()> {
     let path = make_path(vm, path, &dir_fd);
-    fs::create_dir(&path.value).map_err(|err| convert_io_error(vm, err))
+    fs::create_dir(fsencode(&path, vm)?).map_err(|err| convert_io_error(vm, err))
 }
 
 fn os_mkdirs(path: PyStringRef, vm: &VirtualMachine) -> PyResult<()> {
-    fs::create_dir_all(&path.value).map_err(|err| convert_io_error(vm, err))
+    fs::create_dir_all(fsencode(&path, vm)?).map_err(|err| convert_io_error(vm, err))
 }
 
 fn os_rmdir(path: PyStringRef, dir_fd: DirFd, vm: &VirtualMachine) -> PyResult<()> {
     let path = make_path(vm, path, &dir_fd);
-    fs::remove_dir(&path.value).map_err(|err| convert_io_error(vm, err))
+    fs::remove_dir(fsencode(&path, vm)?).map_err(|err| convert_io_error(vm, err))
 }
 
 fn os_listdir(path: PyStringRef, vm: &VirtualMachine) -> PyResult {
-    match fs::read_dir(&path.value) {
+    match fs::read_dir(fsencode(&path, vm)?) {
         Ok(iter) => {
             let res: PyResult<Vec<PyObjectRef>> = iter
                 .map(|entry| match entry {
-                    Ok(path) => Ok(vm.ctx.new_str(path.file_name().into_string().unwrap())),
+                    Ok(path) => fsdecode(&path.file_name(), vm)?.into_pyobject(vm),
                     Err(s) => Err(convert_io_error(vm, s)),
                 })
                 .collect();
@@ -253,20 +257,23 @@ fn os_listdir(path: PyStringRef, vm: &VirtualMachine) -> PyResult {
     }
 }
 
-fn os_putenv(key: PyStringRef, value: PyStringRef, _vm: &VirtualMachine) {
-    env::set_var(&key.value, &value.value)
+fn os_putenv(key: PyStringRef, value: PyStringRef, vm: &VirtualMachine) -> PyResult<()> {
+    env::set_var(fsencode(&key, vm)?, fsencode(&value, vm)?);
+    Ok(())
 }
 
-fn os_unsetenv(key: PyStringRef, _vm: &VirtualMachine) {
-    env::remove_var(&key.value)
+fn os_unsetenv(key: PyStringRef, vm: &VirtualMachine) -> PyResult<()> {
+    env::remove_var(fsencode(&key, vm)?);
+    Ok(())
 }
 
-fn _os_environ(vm: &VirtualMachine) -> PyDictRef {
+fn _os_environ(vm: &VirtualMachine) -> PyResult<PyDictRef> {
     let environ = vm.ctx.new_dict();
-    for (key, value) in env::vars() {
-        environ.set_item(&key, vm.new_str(value), vm).unwrap();
+    for (key, value) in env::vars_os() {
+        let value = fsdecode(&value, vm)?.into_pyobject(vm)?;
+        environ.set_item(fsdecode(&key, vm)?, value, vm)?;
     }
-    environ
+    Ok(environ)
 }
 
 #[derive(Debug)]
@@ -295,12 +302,12 @@ struct FollowSymlinks {
 }
 
 impl DirEntryRef {
-    fn name(self, _vm: &VirtualMachine) -> String {
-        self.entry.file_name().into_string().unwrap()
+    fn name(self, vm: &VirtualMachine) -> PyResult<PyString> {
+        fsdecode(&self.entry.file_name(), vm)
     }
 
-    fn path(self, _vm: &VirtualMachine) -> String {
-        self.entry.path().to_str().unwrap().to_string()
+    fn path(self, vm: &VirtualMachine) -> PyResult<PyString> {
+        fsdecode(self.entry.path().as_os_str(), vm)
     }
 
     #[allow(clippy::match_bool)]
@@ -348,7 +355,7 @@ impl DirEntryRef {
         follow_symlinks: FollowSymlinks,
         vm: &VirtualMachine,
     ) -> PyResult<StatResult> {
-        os_stat(self.path(vm).try_into_ref(vm)?, dir_fd, follow_symlinks, vm)
+        os_stat(self.path(vm)?.into_ref(vm), dir_fd, follow_symlinks, vm)
     }
 }
 
@@ -384,7 +391,7 @@ impl ScandirIterator {
 }
 
 fn os_scandir(path: PyStringRef, vm: &VirtualMachine) -> PyResult {
-    match fs::read_dir(&path.value) {
+    match fs::read_dir(fsencode(&path, vm)?) {
         Ok(iter) => Ok(ScandirIterator {
             entries: RefCell::new(iter),
         }
@@ -480,7 +487,7 @@ fn to_seconds_from_nanos(secs: i64, nanos: i64) -> f64 {
 macro_rules! os_unix_stat_inner {
     ( $path:expr, $follow_symlinks:expr, $vm:expr ) => {{
         #[allow(clippy::match_bool)]
-        fn get_stats(path: &str, follow_symlinks: bool) -> io::Result<StatResult> {
+        fn get_stats(path: &OsStr, follow_symlinks: bool) -> io::Result<StatResult> {
             let meta = match follow_symlinks {
                 true => fs::metadata(path)?,
                 false => fs::symlink_metadata(path)?,
@@ -500,7 +507,7 @@ macro_rules! os_unix_stat_inner {
             })
         }
 
-        get_stats(&$path.value, $follow_symlinks.follow_symlinks)
+        get_stats(&fsencode(&$path, $vm)?, $follow_symlinks.follow_symlinks)
             .map_err(|err| convert_io_error($vm, err))
     }};
 }
@@ -583,7 +590,7 @@ fn os_stat(
 ) -> PyResult<StatResult> {
     use std::os::windows::fs::MetadataExt;
 
-    fn get_stats(path: &str, follow_symlinks: bool) -> io::Result<StatResult> {
+    fn get_stats(path: &OsStr, follow_symlinks: bool) -> io::Result<StatResult> {
         let meta = match follow_symlinks {
             true => fs::metadata(path)?,
             false => fs::symlink_metadata(path)?,
@@ -603,7 +610,7 @@ fn os_stat(
         })
     }
 
-    get_stats(&path.value, follow_symlinks.follow_symlinks)
+    get_stats(&fsencode(&path, vm)?, follow_symlinks.follow_symlinks)
         .map_err(|s| vm.new_os_error(s.to_string()))
 }
 
@@ -643,7 +650,8 @@ fn os_symlink(
 ) -> PyResult<()> {
     use std::os::unix::fs as unix_fs;
     let dst = make_path(vm, dst, &dir_fd);
-    unix_fs::symlink(&src.value, &dst.value).map_err(|err| convert_io_error(vm, err))
+    unix_fs::symlink(fsencode(&src, vm)?, fsencode(&dst, vm)?)
+        .map_err(|err| convert_io_error(vm, err))
 }
 
 #[cfg(windows)]
@@ -654,17 +662,19 @@ fn os_symlink(
     vm: &VirtualMachine,
 ) -> PyResult<()> {
     use std::os::windows::fs as win_fs;
-    let ret = match fs::metadata(&dst.value) {
+    let src = fsencode(&src, vm)?;
+    let dst = fsencode(&dst, vm)?;
+    let ret = match fs::metadata(&dst) {
         Ok(meta) => {
             if meta.is_file() {
-                win_fs::symlink_file(&src.value, &dst.value)
+                win_fs::symlink_file(&src, &dst)
             } else if meta.is_dir() {
-                win_fs::symlink_dir(&src.value, &dst.value)
+                win_fs::symlink_dir(&src, &dst)
             } else {
                 panic!("Uknown file type");
             }
         }
-        Err(_) => win_fs::symlink_file(&src.value, &dst.value),
+        Err(_) => win_fs::symlink_file(&src, &dst),
     };
     ret.map_err(|err| convert_io_error(vm, err))
 }
@@ -679,17 +689,13 @@ fn os_symlink(
     unimplemented!();
 }
 
-fn os_getcwd(vm: &VirtualMachine) -> PyResult<String> {
-    Ok(env::current_dir()
-        .map_err(|err| convert_io_error(vm, err))?
-        .as_path()
-        .to_str()
-        .unwrap()
-        .to_string())
+fn os_getcwd(vm: &VirtualMachine) -> PyResult<PyString> {
+    let cwd = env::current_dir().map_err(|err| convert_io_error(vm, err))?;
+    fsdecode(cwd.as_os_str(), vm)
 }
 
 fn os_chdir(path: PyStringRef, vm: &VirtualMachine) -> PyResult<()> {
-    env::set_current_dir(&path.value).map_err(|err| convert_io_error(vm, err))
+    env::set_current_dir(fsencode(&path, vm)?).map_err(|err| convert_io_error(vm, err))
 }
 
 fn os_fspath(path: PyObjectRef, vm: &VirtualMachine) -> PyResult {
@@ -706,7 +712,7 @@ fn os_fspath(path: PyObjectRef, vm: &VirtualMachine) -> PyResult {
 }
 
 fn os_rename(src: PyStringRef, dst: PyStringRef, vm: &VirtualMachine) -> PyResult<()> {
-    fs::rename(&src.value, &dst.value).map_err(|err| convert_io_error(vm, err))
+    fs::rename(fsencode(&src, vm)?, fsencode(&dst, vm)?).map_err(|err| convert_io_error(vm, err))
 }
 
 pub fn make_module(vm: &VirtualMachine) -> PyObjectRef {
@@ -718,7 +724,7 @@ pub fn make_module(vm: &VirtualMachine) -> PyObjectRef {
         "posix".to_string()
     };
 
-    let environ = _os_environ(vm);
+    let environ = _os_environ(vm).unwrap();
 
     let scandir_iter = ctx.new_class("ScandirIter", ctx.object());
     ScandirIterator::extend_class(ctx, &scandir_iter);
@@ -104,8 +104,8 @@ fn re_match(
     vm: &VirtualMachine,
 ) -> PyResult {
     let flags = extract_flags(flags);
-    let regex = make_regex(vm, &pattern.value, flags)?;
-    do_match(vm, &regex, &string.value)
+    let regex = make_regex(vm, pattern.as_str(), flags)?;
+    do_match(vm, &regex, string.as_str())
 }
 
 fn re_search(
@@ -115,8 +115,8 @@ fn re_search(
     vm: &VirtualMachine,
 ) -> PyResult {
     let flags = extract_flags(flags);
-    let regex = make_regex(vm, &pattern.value, flags)?;
-    do_search(vm, &regex, &string.value)
+    let regex = make_regex(vm, pattern.as_str(), flags)?;
+    do_search(vm, &regex, string.as_str())
 }
 
 fn do_match(vm: &VirtualMachine, regex: &PyPattern, search_text: &str) -> PyResult {
@@ -179,11 +179,11 @@ fn re_compile(
     vm: &VirtualMachine,
 ) -> PyResult<PyPattern> {
     let flags = extract_flags(flags);
-    make_regex(vm, &pattern.value, flags)
+    make_regex(vm, pattern.as_str(), flags)
 }
 
 fn re_escape(pattern: PyStringRef, _vm: &VirtualMachine) -> String {
-    regex::escape(&pattern.value)
+    regex::escape(pattern.as_str())
 }
 
 fn re_purge(_vm: &VirtualMachine) {}
@@ -192,19 +192,19 @@ fn re_purge(_vm: &VirtualMachine) {}
 impl PyPattern {
     #[pymethod(name = "match")]
     fn match_(&self, text: PyStringRef, vm: &VirtualMachine) -> PyResult {
-        do_match(vm, self, &text.value)
+        do_match(vm, self, text.as_str())
     }
 
     #[pymethod(name = "search")]
     fn search(&self, text: PyStringRef, vm: &VirtualMachine) -> PyResult {
-        do_search(vm, self, &text.value)
+        do_search(vm, self, text.as_str())
     }
 
     #[pymethod(name = "sub")]
     fn sub(&self, repl: PyStringRef, text: PyStringRef, vm: &VirtualMachine) -> PyResult {
         let replaced_text = self
             .regex
-            .replace_all(text.value.as_bytes(), repl.as_str().as_bytes())
+            .replace_all(text.as_str().as_bytes(), repl.as_str().as_bytes())
             .into_owned();
         // safe because both the search and replace arguments ^ are unicode strings temporarily
         // converted to bytes
@@ -4,6 +4,7 @@ use std::io::Read;
 use std::io::Write;
 use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket};
 
+use crate::gil;
 use crate::obj::objbytes::PyBytesRef;
 use crate::obj::objint::PyIntRef;
 use crate::obj::objstr::PyStringRef;
@@ -175,7 +176,7 @@ impl SocketRef {
         let address_string = address.get_address_string();
 
         match self.socket_kind {
-            SocketKind::Stream => match TcpStream::connect(address_string) {
+            SocketKind::Stream => match gil::allow_threads(|| TcpStream::connect(address_string)) {
                 Ok(stream) => {
                     self.con.borrow_mut().replace(Connection::TcpStream(stream));
                     Ok(())
@@ -222,7 +223,7 @@ impl SocketRef {
 
     fn accept(self, vm: &VirtualMachine) -> PyResult {
         let ret = match self.con.borrow_mut().as_mut() {
-            Some(v) => v.accept(),
+            Some(v) => gil::allow_threads(|| v.accept()),
             None => return Err(vm.new_type_error("".to_string())),
         };
 
@@ -246,7 +247,7 @@ impl SocketRef {
     fn recv(self, bufsize: PyIntRef, vm: &VirtualMachine) -> PyResult {
         let mut buffer = vec![0u8; bufsize.as_bigint().to_usize().unwrap()];
         match self.con.borrow_mut().as_mut() {
-            Some(v) => match v.read_exact(&mut buffer) {
+            Some(v) => match gil::allow_threads(|| v.read_exact(&mut buffer)) {
                 Ok(_) => (),
                 Err(s) => return Err(vm.new_os_error(s.to_string())),
             },
@@ -258,7 +259,7 @@ impl SocketRef {
     fn recvfrom(self, bufsize: PyIntRef, vm: &VirtualMachine) -> PyResult {
         let mut buffer = vec![0u8; bufsize.as_bigint().to_usize().unwrap()];
         let ret = match self.con.borrow().as_ref() {
-            Some(v) => v.recv_from(&mut buffer),
+            Some(v) => gil::allow_threads(|| v.recv_from(&mut buffer)),
             None => return Err(vm.new_type_error("".to_string())),
         };
 
@@ -354,7 +355,7 @@ impl TryFromObject for Address {
         } else {
             Ok(Address {
                 host: PyStringRef::try_from_object(vm, tuple.elements[0].clone())?
-                    .value
+                    .as_str()
                     .to_string(),
                 port: PyIntRef::try_from_object(vm, tuple.elements[1].clone())?
                     .as_bigint()
@@ -28,9 +28,9 @@ fn symtable_symtable(
     mode: PyStringRef,
     vm: &VirtualMachine,
 ) -> PyResult<PySymbolTableRef> {
-    let mode = get_compile_mode(vm, &mode.value)?;
+    let mode = get_compile_mode(vm, mode.as_str())?;
     let symtable =
-        source_to_symtable(&source.value, mode).map_err(|err| vm.new_syntax_error(&err))?;
+        source_to_symtable(source.as_str(), mode).map_err(|err| vm.new_syntax_error(&err))?;
 
     let py_symbol_table = to_py_symbol_table("top".to_string(), symtable);
     Ok(py_symbol_table.into_ref(vm))
@@ -88,7 +88,7 @@ impl PySymbolTable {
 
     #[pymethod(name = "lookup")]
     fn lookup(&self, name: PyStringRef, vm: &VirtualMachine) -> PyResult<PySymbolRef> {
-        let name = &name.value;
+        let name = name.as_str();
         if let Some(symbol) = self.symtable.symbols.get(name) {
             Ok(PySymbol {
                 symbol: symbol.clone(),
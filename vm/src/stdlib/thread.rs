@@ -1,58 +1,443 @@
-/// Implementation of the _thread module, currently noop implementation as RustPython doesn't yet
-/// support threading
-use super::super::pyobject::PyObjectRef;
-use crate::function::PyFuncArgs;
-use crate::pyobject::PyResult;
+//! Implementation of the `_thread` module.
+//!
+//! Every thread runs on an OS thread, with a `VirtualMachine` of its own
+//! which shares the modules and types of the one that started it. Threads
+//! take turns running Python code, see `gil`.
+
+use std::cell::Cell;
+use std::fmt;
+use std::mem;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::exceptions;
+use crate::function::{Args, OptionalArg, PyFuncArgs};
+use crate::gc;
+use crate::gil;
+use crate::obj::objdict::PyDictRef;
+use crate::obj::objfloat;
+use crate::obj::objstr::PyStringRef;
+use crate::obj::objtuple::PyTupleRef;
+use crate::obj::objtype::PyClassRef;
+use crate::pyobject::{
+    IdProtocol, PyClassImpl, PyObjectRef, PyRef, PyResult, PyValue, TryFromObject, TypeProtocol,
+};
 use crate::vm::VirtualMachine;
 
-fn rlock_acquire(vm: &VirtualMachine, _args: PyFuncArgs) -> PyResult {
-    Ok(vm.get_none())
+/// The longest timeout `acquire` accepts, in seconds.
+const TIMEOUT_MAX: f64 = (i64::max_value() / 1_000_000_000) as f64;
+
+/// The smallest stack size `stack_size` accepts, in bytes.
+const MIN_STACK_SIZE: usize = 32 * 1024;
+
+static NEXT_IDENT: AtomicU64 = AtomicU64::new(1);
+static THREAD_COUNT: AtomicUsize = AtomicUsize::new(0);
+static STACK_SIZE: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static IDENT: Cell<u64> = Cell::new(0);
+}
+
+/// The identifier of the current thread, given out on first use.
+fn current_ident() -> u64 {
+    IDENT.with(|ident| {
+        if ident.get() == 0 {
+            ident.set(NEXT_IDENT.fetch_add(1, Ordering::SeqCst));
+        }
+        ident.get()
+    })
+}
+
+/// A lock which may be released by a thread other than the one which took it.
+#[derive(Debug, Default)]
+struct RawLock {
+    locked: Mutex<bool>,
+    unlocked: Condvar,
 }
 
-fn rlock_release(_zelf: PyObjectRef, _vm: &VirtualMachine) {}
+impl RawLock {
+    fn try_acquire(&self) -> bool {
+        let mut locked = self.locked.lock().unwrap();
+        !mem::replace(&mut *locked, true)
+    }
+
+    /// Wait for the lock to be free and take it. Gives up after `timeout`,
+    /// if there is one.
+    fn acquire_timeout(&self, timeout: Option<Duration>) -> bool {
+        let deadline = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+        let mut locked = self.locked.lock().unwrap();
+        while *locked {
+            locked = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return false;
+                    }
+                    self.unlocked
+                        .wait_timeout(locked, deadline - now)
+                        .unwrap()
+                        .0
+                }
+                None => self.unlocked.wait(locked).unwrap(),
+            };
+        }
+        *locked = true;
+        true
+    }
+
+    /// Take the lock, releasing the GIL while waiting for it.
+    fn acquire(&self, timeout: Option<Duration>) -> bool {
+        self.try_acquire()
+            || (timeout != Some(Duration::from_secs(0))
+                && gil::allow_threads(|| self.acquire_timeout(timeout)))
+    }
 
-fn rlock_enter(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
-    arg_check!(vm, args, required = [(instance, None)]);
-    Ok(instance.clone())
+    /// Release the lock, or return false if it was not locked.
+    fn release(&self) -> bool {
+        let mut locked = self.locked.lock().unwrap();
+        if !*locked {
+            return false;
+        }
+        *locked = false;
+        drop(locked);
+        self.unlocked.notify_one();
+        true
+    }
+
+    fn is_locked(&self) -> bool {
+        *self.locked.lock().unwrap()
+    }
 }
 
-fn rlock_exit(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
-    arg_check!(
-        vm,
-        args,
-        // The context manager protocol requires these, but we don't use them
-        required = [
-            (_instance, None),
-            (_exception_type, None),
-            (_exception_value, None),
-            (_traceback, None)
-        ]
-    );
-    Ok(vm.get_none())
+#[derive(FromArgs)]
+struct AcquireArgs {
+    #[pyarg(positional_or_keyword, default = "true")]
+    blocking: bool,
+    #[pyarg(positional_or_keyword, default = "None")]
+    timeout: Option<PyObjectRef>,
 }
 
-fn get_ident(_vm: &VirtualMachine) -> u32 {
-    1
+impl AcquireArgs {
+    /// How long to wait for the lock: zero for a non-blocking call, and
+    /// `None` to wait for as long as it takes.
+    fn timeout(&self, vm: &VirtualMachine) -> PyResult<Option<Duration>> {
+        let timeout = match &self.timeout {
+            Some(timeout) => objfloat::try_float(timeout, vm)?.ok_or_else(|| {
+                vm.new_type_error(format!(
+                    "'{}' object cannot be interpreted as a float",
+                    timeout.class().name
+                ))
+            })?,
+            None => -1.0,
+        };
+        if !self.blocking {
+            if timeout != -1.0 {
+                return Err(vm.new_value_error(
+                    "can't specify a timeout for a non-blocking call".to_string(),
+                ));
+            }
+            return Ok(Some(Duration::from_secs(0)));
+        }
+        if timeout == -1.0 {
+            Ok(None)
+        } else if timeout < 0.0 {
+            Err(vm.new_value_error("timeout value must be positive".to_string()))
+        } else if timeout > TIMEOUT_MAX {
+            Err(vm.new_overflow_error("timeout value is too large".to_string()))
+        } else {
+            Ok(Some(Duration::from_secs_f64(timeout)))
+        }
+    }
 }
 
-fn allocate_lock(vm: &VirtualMachine) -> PyResult {
-    let lock_class = vm.class("_thread", "RLock");
-    vm.invoke(lock_class.into_object(), vec![])
+#[pyclass(name = "lock")]
+#[derive(Debug, Default)]
+struct PyLock {
+    lock: RawLock,
 }
 
-pub fn make_module(vm: &VirtualMachine) -> PyObjectRef {
-    let ctx = &vm.ctx;
+type PyLockRef = PyRef<PyLock>;
+
+impl PyValue for PyLock {
+    fn class(vm: &VirtualMachine) -> PyClassRef {
+        vm.class("_thread", "LockType")
+    }
+}
+
+#[pyimpl]
+impl PyLock {
+    #[pymethod]
+    fn acquire(&self, args: AcquireArgs, vm: &VirtualMachine) -> PyResult<bool> {
+        Ok(self.lock.acquire(args.timeout(vm)?))
+    }
+
+    #[pymethod]
+    fn acquire_lock(&self, args: AcquireArgs, vm: &VirtualMachine) -> PyResult<bool> {
+        self.acquire(args, vm)
+    }
+
+    #[pymethod(name = "__enter__")]
+    fn enter(&self, _vm: &VirtualMachine) -> bool {
+        self.lock.acquire(None)
+    }
+
+    #[pymethod]
+    fn release(&self, vm: &VirtualMachine) -> PyResult<()> {
+        if self.lock.release() {
+            Ok(())
+        } else {
+            Err(vm.new_exception(
+                vm.ctx.exceptions.runtime_error.clone(),
+                "release unlocked lock".to_string(),
+            ))
+        }
+    }
+
+    #[pymethod]
+    fn release_lock(&self, vm: &VirtualMachine) -> PyResult<()> {
+        self.release(vm)
+    }
+
+    #[pymethod(name = "__exit__")]
+    fn exit(&self, _args: Args, vm: &VirtualMachine) -> PyResult<()> {
+        self.release(vm)
+    }
+
+    #[pymethod]
+    fn locked(&self, _vm: &VirtualMachine) -> bool {
+        self.lock.is_locked()
+    }
+
+    #[pymethod]
+    fn locked_lock(&self, _vm: &VirtualMachine) -> bool {
+        self.lock.is_locked()
+    }
+
+    #[pymethod(name = "__repr__")]
+    fn repr(zelf: PyLockRef, _vm: &VirtualMachine) -> String {
+        let state = if zelf.lock.is_locked() {
+            "locked"
+        } else {
+            "unlocked"
+        };
+        format!("<{} _thread.lock object at {:#x}>", state, zelf.get_id())
+    }
+}
+
+/// A lock which the thread holding it may take again.
+#[pyclass(name = "RLock")]
+#[derive(Default)]
+struct PyRLock {
+    lock: RawLock,
+    owner: Cell<u64>,
+    count: Cell<usize>,
+}
+
+type PyRLockRef = PyRef<PyRLock>;
+
+impl fmt::Debug for PyRLock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "RLock()")
+    }
+}
 
-    let rlock_type = py_class!(ctx, "_thread.RLock", ctx.object(), {
-        "acquire" => ctx.new_rustfunc(rlock_acquire),
-        "release" => ctx.new_rustfunc(rlock_release),
-        "__enter__" => ctx.new_rustfunc(rlock_enter),
-        "__exit__" => ctx.new_rustfunc(rlock_exit),
+impl PyValue for PyRLock {
+    fn class(vm: &VirtualMachine) -> PyClassRef {
+        vm.class("_thread", "RLock")
+    }
+}
+
+#[pyimpl]
+impl PyRLock {
+    #[pymethod(name = "__new__")]
+    fn new(cls: PyClassRef, _args: Args, vm: &VirtualMachine) -> PyResult<PyRLockRef> {
+        PyRLock::default().into_ref_with_type(vm, cls)
+    }
+
+    #[pymethod]
+    fn acquire(&self, args: AcquireArgs, vm: &VirtualMachine) -> PyResult<bool> {
+        let timeout = args.timeout(vm)?;
+        Ok(self.acquire_timeout(timeout))
+    }
+
+    fn acquire_timeout(&self, timeout: Option<Duration>) -> bool {
+        let ident = current_ident();
+        if self.count.get() > 0 && self.owner.get() == ident {
+            self.count.set(self.count.get() + 1);
+            return true;
+        }
+        if !self.lock.acquire(timeout) {
+            return false;
+        }
+        self.owner.set(ident);
+        self.count.set(1);
+        true
+    }
+
+    #[pymethod(name = "__enter__")]
+    fn enter(&self, _vm: &VirtualMachine) -> bool {
+        self.acquire_timeout(None)
+    }
+
+    #[pymethod]
+    fn release(&self, vm: &VirtualMachine) -> PyResult<()> {
+        if self.count.get() == 0 || self.owner.get() != current_ident() {
+            return Err(vm.new_exception(
+                vm.ctx.exceptions.runtime_error.clone(),
+                "cannot release un-acquired lock".to_string(),
+            ));
+        }
+        self.count.set(self.count.get() - 1);
+        if self.count.get() == 0 {
+            self.owner.set(0);
+            self.lock.release();
+        }
+        Ok(())
+    }
+
+    #[pymethod(name = "__exit__")]
+    fn exit(&self, _args: Args, vm: &VirtualMachine) -> PyResult<()> {
+        self.release(vm)
+    }
+
+    #[pymethod(name = "_is_owned")]
+    fn is_owned(&self, _vm: &VirtualMachine) -> bool {
+        self.count.get() > 0 && self.owner.get() == current_ident()
+    }
+
+    #[pymethod(name = "__repr__")]
+    fn repr(zelf: PyRLockRef, _vm: &VirtualMachine) -> String {
+        let state = if zelf.count.get() > 0 {
+            "locked"
+        } else {
+            "unlocked"
+        };
+        format!(
+            "<{} _thread.RLock object owner={} count={} at {:#x}>",
+            state,
+            zelf.owner.get(),
+            zelf.count.get(),
+            zelf.get_id()
+        )
+    }
+}
+
+fn thread_allocate_lock(vm: &VirtualMachine) -> PyLockRef {
+    PyLock::default().into_ref(vm)
+}
+
+fn thread_get_ident(_vm: &VirtualMachine) -> u64 {
+    current_ident()
+}
+
+fn thread_count(_vm: &VirtualMachine) -> usize {
+    THREAD_COUNT.load(Ordering::SeqCst)
+}
+
+fn thread_stack_size(size: OptionalArg<usize>, vm: &VirtualMachine) -> PyResult<usize> {
+    let size = size.unwrap_or(0);
+    if size != 0 && size < MIN_STACK_SIZE {
+        return Err(vm.new_value_error(format!("size not valid: {} bytes", size)));
+    }
+    Ok(STACK_SIZE.swap(size, Ordering::SeqCst))
+}
+
+fn thread_start_new_thread(
+    func: PyObjectRef,
+    args: PyTupleRef,
+    kwargs: OptionalArg<PyDictRef>,
+    vm: &VirtualMachine,
+) -> PyResult<u64> {
+    if !vm.is_callable(&func) {
+        return Err(vm.new_type_error("first arg must be callable".to_string()));
+    }
+    let mut func_args = PyFuncArgs::from(args.elements.clone());
+    if let OptionalArg::Present(kwargs) = kwargs {
+        for (key, value) in kwargs {
+            let key = PyStringRef::try_from_object(vm, key)?;
+            func_args.kwargs.insert(key.as_str().to_string(), value);
+        }
+    }
+
+    // The thread starting the first other thread takes the GIL, and from
+    // then on every thread needs it to run.
+    gil::ensure_held();
+    let ident = NEXT_IDENT.fetch_add(1, Ordering::SeqCst);
+    let state = gil::GilSend((vm.new_thread(), gc::current_heap(), func, func_args));
+
+    let mut builder = thread::Builder::new();
+    let stack_size = STACK_SIZE.load(Ordering::SeqCst);
+    if stack_size != 0 {
+        builder = builder.stack_size(stack_size);
+    }
+    THREAD_COUNT.fetch_add(1, Ordering::SeqCst);
+    let spawned = builder.spawn(move || {
+        gil::acquire();
+        // Declared before the thread's objects, so it runs after they are
+        // dropped, whether the thread returns or panics.
+        let mut exit = ThreadExit { own_heap: None };
+        IDENT.with(|current| current.set(ident));
+        let gil::GilSend((vm, heap, func, func_args)) = state;
+        exit.own_heap = Some(gc::set_heap(heap));
+        run_thread(&vm, func, func_args);
     });
+    match spawned {
+        Ok(_) => Ok(ident),
+        Err(err) => {
+            THREAD_COUNT.fetch_sub(1, Ordering::SeqCst);
+            Err(vm.new_exception(
+                vm.ctx.exceptions.runtime_error.clone(),
+                format!("can't start new thread: {}", err),
+            ))
+        }
+    }
+}
+
+/// Ends a thread started by `start_new_thread`: gives back the heap it
+/// shared and then the GIL, so that a panicking thread doesn't leave the
+/// other threads waiting for it forever.
+struct ThreadExit {
+    own_heap: Option<Rc<gc::Heap>>,
+}
+
+impl Drop for ThreadExit {
+    fn drop(&mut self) {
+        if let Some(own_heap) = self.own_heap.take() {
+            drop(gc::set_heap(own_heap));
+        }
+        THREAD_COUNT.fetch_sub(1, Ordering::SeqCst);
+        gil::release();
+    }
+}
+
+fn run_thread(vm: &VirtualMachine, func: PyObjectRef, args: PyFuncArgs) {
+    if let Err(exc) = vm.invoke(func.clone(), args) {
+        let repr = vm.to_repr(&func).map_or_else(
+            |_| "<object repr() failed>".to_string(),
+            |repr| repr.as_str().to_string(),
+        );
+        println!("Unhandled exception in thread started by {}", repr);
+        exceptions::print_exception(vm, &exc);
+    }
+}
+
+pub fn make_module(vm: &VirtualMachine) -> PyObjectRef {
+    let ctx = &vm.ctx;
 
     py_module!(vm, "_thread", {
-        "RLock" => rlock_type,
-        "get_ident" => ctx.new_rustfunc(get_ident),
-        "allocate_lock" => ctx.new_rustfunc(allocate_lock),
+        "LockType" => PyLock::make_class(ctx),
+        "RLock" => PyRLock::make_class(ctx),
+        "allocate_lock" => ctx.new_rustfunc(thread_allocate_lock),
+        "allocate" => ctx.new_rustfunc(thread_allocate_lock),
+        "get_ident" => ctx.new_rustfunc(thread_get_ident),
+        "start_new_thread" => ctx.new_rustfunc(thread_start_new_thread),
+        "start_new" => ctx.new_rustfunc(thread_start_new_thread),
+        "stack_size" => ctx.new_rustfunc(thread_stack_size),
+        "_count" => ctx.new_rustfunc(thread_count),
+        "error" => ctx.exceptions.runtime_error.clone(),
+        "TIMEOUT_MAX" => ctx.new_float(TIMEOUT_MAX),
     })
 }
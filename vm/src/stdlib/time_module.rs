@@ -6,6 +6,7 @@ use std::thread;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use crate::function::{OptionalArg, PyFuncArgs};
+use crate::gil;
 use crate::obj::objint::PyIntRef;
 use crate::obj::objsequence::get_sequence_index;
 use crate::obj::objstr::PyStringRef;
@@ -25,7 +26,7 @@ fn time_sleep(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     let secs: u64 = seconds.trunc() as u64;
     let nanos: u32 = (seconds.fract() * 1e9) as u32;
     let duration = Duration::new(secs, nanos);
-    thread::sleep(duration);
+    gil::allow_threads(|| thread::sleep(duration));
     Ok(vm.get_none())
 }
 
@@ -142,7 +143,7 @@ fn time_strftime(
         OptionalArg::Present(t) => t.get_date_time(),
         OptionalArg::Missing => default,
     };
-    let formatted_time = instant.format(&format.value).to_string();
+    let formatted_time = instant.format(format.as_str()).to_string();
     Ok(vm.ctx.new_str(formatted_time))
 }
 
@@ -152,10 +153,10 @@ fn time_strptime(
     vm: &VirtualMachine,
 ) -> PyResult<PyStructTime> {
     let format: String = match format {
-        OptionalArg::Present(format) => format.value.clone(),
+        OptionalArg::Present(format) => format.as_str().to_string(),
         OptionalArg::Missing => "%a %b %H:%M:%S %Y".to_string(),
     };
-    let instant = NaiveDateTime::parse_from_str(&string.value, &format)
+    let instant = NaiveDateTime::parse_from_str(string.as_str(), &format)
         .map_err(|e| vm.new_value_error(format!("Parse error: {:?}", e)))?;
     let struct_time = PyStructTime::new(instant);
     Ok(struct_time)
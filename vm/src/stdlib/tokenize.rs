@@ -1,34 +1,215 @@
 /*
- * python tokenize module.
+ * Native helpers for the python tokenize module.
+ *
+ * The token stream comes from the lexer of the parser, so the tokenize
+ * module sees exactly the tokens the compiler does. Lib/tokenize.py wraps
+ * the tuples produced here into TokenInfo objects.
  */
 
-use std::iter::FromIterator;
+use rustpython_parser::error::LexicalError;
+use rustpython_parser::lexer::{self, Tok};
+use rustpython_parser::location::Location;
 
-use rustpython_parser::lexer;
-
-use crate::function::PyFuncArgs;
-use crate::obj::objstr;
+use crate::obj::objstr::PyStringRef;
 use crate::pyobject::{PyObjectRef, PyResult};
 use crate::vm::VirtualMachine;
 
-fn tokenize_tokenize(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
-    arg_check!(vm, args, required = [(readline, Some(vm.ctx.str_type()))]);
-    let source = objstr::get_value(readline);
+// Token numbers, these must match Lib/token.py
+const ENDMARKER: u8 = 0;
+const NAME: u8 = 1;
+const NUMBER: u8 = 2;
+const STRING: u8 = 3;
+const NEWLINE: u8 = 4;
+const INDENT: u8 = 5;
+const DEDENT: u8 = 6;
+const OP: u8 = 53;
+const COMMENT: u8 = 55;
+const NL: u8 = 56;
 
-    // TODO: implement generator when the time has come.
-    let lexer1 = lexer::make_tokenizer(&source);
+fn token_type(tok: &Tok) -> u8 {
+    match tok {
+        Tok::Name { .. } => NAME,
+        Tok::Int { .. } | Tok::Float { .. } | Tok::Complex { .. } => NUMBER,
+        Tok::String { .. } | Tok::Bytes { .. } => STRING,
+        Tok::Newline => NEWLINE,
+        Tok::NonLogicalNewline => NL,
+        Tok::Comment { .. } => COMMENT,
+        Tok::Indent => INDENT,
+        Tok::Dedent => DEDENT,
+        Tok::EndOfFile => ENDMARKER,
+        Tok::False
+        | Tok::None
+        | Tok::True
+        | Tok::And
+        | Tok::As
+        | Tok::Assert
+        | Tok::Async
+        | Tok::Await
+        | Tok::Break
+        | Tok::Class
+        | Tok::Continue
+        | Tok::Def
+        | Tok::Del
+        | Tok::Elif
+        | Tok::Else
+        | Tok::Except
+        | Tok::Finally
+        | Tok::For
+        | Tok::From
+        | Tok::Global
+        | Tok::If
+        | Tok::Import
+        | Tok::In
+        | Tok::Is
+        | Tok::Lambda
+        | Tok::Nonlocal
+        | Tok::Not
+        | Tok::Or
+        | Tok::Pass
+        | Tok::Raise
+        | Tok::Return
+        | Tok::Try
+        | Tok::While
+        | Tok::With
+        | Tok::Yield => NAME,
+        _ => OP,
+    }
+}
 
-    let tokens = lexer1.map(|st| vm.ctx.new_str(format!("{:?}", st.unwrap().1)));
-    let tokens = Vec::from_iter(tokens);
-    Ok(vm.ctx.new_list(tokens))
+/// The physical lines of a source text, newlines included.
+struct SourceLines<'a> {
+    lines: Vec<&'a str>,
+}
+
+impl<'a> SourceLines<'a> {
+    fn new(source: &'a str) -> Self {
+        let mut lines = vec![];
+        let mut start = 0;
+        for (index, c) in source.char_indices() {
+            if c == '\n' {
+                lines.push(&source[start..=index]);
+                start = index + 1;
+            }
+        }
+        if start < source.len() {
+            lines.push(&source[start..]);
+        }
+        SourceLines { lines }
+    }
+
+    fn line(&self, row: usize) -> &'a str {
+        self.lines.get(row - 1).cloned().unwrap_or("")
+    }
+
+    /// Byte offset of the 0-based character column `col` in line `row`.
+    fn offset(&self, row: usize, col: usize) -> usize {
+        let line = self.line(row);
+        line.char_indices()
+            .nth(col)
+            .map_or(line.len(), |(index, _)| index)
+    }
+
+    /// The source text between two (row, 0-based column) positions.
+    fn text(&self, start: (usize, usize), end: (usize, usize)) -> String {
+        if start.0 == end.0 {
+            let line = self.line(start.0);
+            return line[self.offset(start.0, start.1)..self.offset(end.0, end.1)].to_string();
+        }
+        let mut text = self.line(start.0)[self.offset(start.0, start.1)..].to_string();
+        for row in start.0 + 1..end.0 {
+            text.push_str(self.line(row));
+        }
+        text.push_str(&self.line(end.0)[..self.offset(end.0, end.1)]);
+        text
+    }
+
+    /// All physical lines a token spans.
+    fn span(&self, start_row: usize, end_row: usize) -> String {
+        (start_row..=end_row).map(|row| self.line(row)).collect()
+    }
 }
 
-// TODO: create main function when called with -m
+fn position(location: &Location) -> (usize, usize) {
+    (location.row(), location.column() - 1)
+}
+
+fn new_token_tuple(
+    vm: &VirtualMachine,
+    typ: u8,
+    string: String,
+    start: (usize, usize),
+    end: (usize, usize),
+    line: String,
+) -> PyObjectRef {
+    let new_position =
+        |(row, col): (usize, usize)| vm.ctx.new_tuple(vec![vm.new_int(row), vm.new_int(col)]);
+    vm.ctx.new_tuple(vec![
+        vm.new_int(typ),
+        vm.new_str(string),
+        new_position(start),
+        new_position(end),
+        vm.new_str(line),
+    ])
+}
+
+fn new_tokenize_error(vm: &VirtualMachine, error: LexicalError) -> PyObjectRef {
+    let syntax_error_type = vm.ctx.exceptions.syntax_error.clone();
+    let syntax_error = vm.new_exception(syntax_error_type, error.error.to_string());
+    let lineno = vm.new_int(error.location.row());
+    vm.set_attr(&syntax_error, "lineno", lineno).unwrap();
+    let offset = vm.new_int(error.location.column());
+    vm.set_attr(&syntax_error, "offset", offset).unwrap();
+    syntax_error
+}
+
+/// Tokenize a complete source text into a list of
+/// `(type, string, start, end, line)` tuples, ending with ENDMARKER.
+fn tokenize_tokenize(source: PyStringRef, vm: &VirtualMachine) -> PyResult {
+    // Use the same newline normalization as the lexer, so positions line up.
+    let source: String = lexer::NewlineHandler::new(source.as_str().chars()).collect();
+    let lines = SourceLines::new(&source);
+
+    let mut tokens = vec![];
+    for token in lexer::make_tokenizer_with_trivia(&source) {
+        let (start, tok, end) = token.map_err(|error| new_tokenize_error(vm, error))?;
+        let typ = token_type(&tok);
+        let (start, end, string) = match tok {
+            Tok::Newline | Tok::NonLogicalNewline => {
+                // The lexer ends these on the next line, tokenize does not.
+                let string = if start == end { "" } else { "\n" };
+                let start = position(&start);
+                (start, (start.0, start.1 + 1), string.to_string())
+            }
+            Tok::Indent => {
+                let end = position(&end);
+                let start = (end.0, 0);
+                (start, end, lines.text(start, end))
+            }
+            _ => {
+                let (start, end) = (position(&start), position(&end));
+                (start, end, lines.text(start, end))
+            }
+        };
+        let line = lines.span(start.0, end.0);
+        tokens.push(new_token_tuple(vm, typ, string, start, end, line));
+    }
+
+    let end = (lines.lines.len() + 1, 0);
+    tokens.push(new_token_tuple(
+        vm,
+        ENDMARKER,
+        String::new(),
+        end,
+        end,
+        String::new(),
+    ));
+    Ok(vm.ctx.new_list(tokens))
+}
 
 pub fn make_module(vm: &VirtualMachine) -> PyObjectRef {
     let ctx = &vm.ctx;
 
-    py_module!(vm, "tokenize", {
+    py_module!(vm, "_tokenize", {
         "tokenize" => ctx.new_rustfunc(tokenize_tokenize)
     })
 }
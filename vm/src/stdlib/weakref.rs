@@ -5,21 +5,34 @@
 //! - [rust weak struct](https://doc.rust-lang.org/std/rc/struct.Weak.html)
 //!
 
-use crate::pyobject::PyObjectRef;
+use crate::gc;
+use crate::obj::objdict::PyDictRef;
+use crate::obj::objweakref::PyWeak;
+use crate::pyobject::{ItemProtocol, PyObjectRef, PyResult};
 use crate::vm::VirtualMachine;
-use std::rc::Rc;
 
 fn weakref_getweakrefcount(obj: PyObjectRef, _vm: &VirtualMachine) -> usize {
-    Rc::weak_count(&obj)
+    gc::get_weakrefs(&obj).len()
 }
 
-fn weakref_getweakrefs(_obj: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
-    // TODO: implement this, may require a different gc
-    vm.ctx.new_list(vec![])
+fn weakref_getweakrefs(obj: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
+    vm.ctx.new_list(gc::get_weakrefs(&obj))
 }
 
-fn weakref_remove_dead_weakref(_obj: PyObjectRef, _key: PyObjectRef, _vm: &VirtualMachine) {
-    // TODO
+fn weakref_remove_dead_weakref(
+    dict: PyDictRef,
+    key: PyObjectRef,
+    vm: &VirtualMachine,
+) -> PyResult<()> {
+    if let Some(value) = dict.get_item_option(key.clone(), vm)? {
+        let dead = value
+            .payload::<PyWeak>()
+            .map_or(false, |weak| weak.upgrade().is_none());
+        if dead {
+            dict.del_item(key, vm)?;
+        }
+    }
+    Ok(())
 }
 
 pub fn make_module(vm: &VirtualMachine) -> PyObjectRef {
@@ -0,0 +1,638 @@
+//! Lone surrogates in `str`.
+//!
+//! A Python string can hold the surrogate code points U+D800 to U+DFFF on
+//! their own, which is how `surrogateescape` carries bytes that aren't UTF-8
+//! through file names and environment variables. A Rust `String` can't, so a
+//! `str` holding any keeps each of them as a code point of a block of 2048
+//! supplementary ones, its `StandIns`, and remembers which block that is. A
+//! `str` without lone surrogates has no stand-ins, and every code point in it
+//! is itself.
+//!
+//! The block is U+10F800 to U+10FFFF, at the end of the last private use
+//! plane, unless the string holds some of those characters itself, in which
+//! case it is the highest block the string doesn't use. That only depends on
+//! what the string holds, so equal strings have equal text. A string using
+//! every block of supplementary code points has no room left for lone
+//! surrogates, and making one is an error.
+//!
+//! Everything that turns a `str` into code points or bytes and back goes
+//! through this module, so that the stand-ins never leak out.
+
+use std::char;
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::str;
+
+use crate::obj::objstr::PyString;
+use crate::pyobject::{PyObjectRef, PyResult};
+use crate::vm::VirtualMachine;
+
+const FIRST_SURROGATE: u32 = 0xD800;
+const LAST_SURROGATE: u32 = 0xDFFF;
+
+/// The number of code points in a block of stand-ins, one for each
+/// surrogate.
+const BLOCK_LEN: u32 = LAST_SURROGATE - FIRST_SURROGATE + 1;
+/// Stand-ins are supplementary code points, so that they take four bytes of
+/// UTF-8 whichever block they are in.
+const FIRST_BLOCK: u32 = 0x1_0000 / BLOCK_LEN;
+const BLOCKS: u32 = 0x11_0000 / BLOCK_LEN;
+
+/// The lowest surrogate `surrogateescape` turns a byte into; the byte is
+/// added to it.
+const ESCAPE_BASE: u32 = 0xDC00;
+
+pub fn is_surrogate(code_point: u32) -> bool {
+    (FIRST_SURROGATE..=LAST_SURROGATE).contains(&code_point)
+}
+
+/// The block of code points standing for the lone surrogates of a `str`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StandIns {
+    first: u32,
+}
+
+impl StandIns {
+    /// U+10F800 to U+10FFFF, the stand-ins of any string not holding those
+    /// characters itself.
+    pub const DEFAULT: StandIns = StandIns {
+        first: (BLOCKS - 1) * BLOCK_LEN,
+    };
+
+    /// The block starting at `first`, as the parser records it for a string
+    /// constant.
+    pub fn starting_at(first: u32) -> StandIns {
+        debug_assert!(
+            first % BLOCK_LEN == 0 && (FIRST_BLOCK..BLOCKS).contains(&(first / BLOCK_LEN))
+        );
+        StandIns { first }
+    }
+
+    /// The highest block that none of `chars` is in, if there is one.
+    pub fn avoiding<I: IntoIterator<Item = char>>(chars: I) -> Option<StandIns> {
+        let mut taken = [0u64; (BLOCKS as usize + 63) / 64];
+        for c in chars {
+            let block = c as usize / BLOCK_LEN as usize;
+            taken[block / 64] |= 1 << (block % 64);
+        }
+        (FIRST_BLOCK..BLOCKS)
+            .rev()
+            .find(|&block| taken[block as usize / 64] & (1 << (block % 64)) == 0)
+            .map(|block| StandIns {
+                first: block * BLOCK_LEN,
+            })
+    }
+
+    pub fn contains(self, c: char) -> bool {
+        (c as u32).wrapping_sub(self.first) < BLOCK_LEN
+    }
+
+    /// The surrogate `c` stands for, if it is one of these stand-ins.
+    pub fn surrogate(self, c: char) -> Option<u32> {
+        if self.contains(c) {
+            Some(c as u32 - self.first + FIRST_SURROGATE)
+        } else {
+            None
+        }
+    }
+
+    pub fn stand_in(self, surrogate: u32) -> char {
+        debug_assert!(is_surrogate(surrogate));
+        char::from_u32(surrogate - FIRST_SURROGATE + self.first).unwrap()
+    }
+
+    /// `text` with these stand-ins swapped for `other`.
+    pub fn move_to(self, text: &str, other: StandIns) -> String {
+        text.chars()
+            .map(|c| match self.surrogate(c) {
+                Some(surrogate) => other.stand_in(surrogate),
+                None => c,
+            })
+            .collect()
+    }
+}
+
+/// The code point `c` stands for in a `str` with `stand_ins`.
+pub fn code_point(c: char, stand_ins: Option<StandIns>) -> u32 {
+    stand_ins
+        .and_then(|stand_ins| stand_ins.surrogate(c))
+        .unwrap_or(c as u32)
+}
+
+/// `text` holding lone surrogates as `stand_ins`, moved to the stand-ins it
+/// should have by the rule above, or none if it has no surrogates after all.
+pub fn normalize(text: String, stand_ins: StandIns) -> (String, Option<StandIns>) {
+    if !text.chars().any(|c| stand_ins.contains(c)) {
+        return (text, None);
+    }
+    if stand_ins == StandIns::DEFAULT {
+        return (text, Some(stand_ins));
+    }
+    // The other characters all avoid `stand_ins`, so some block is found.
+    let best = StandIns::avoiding(text.chars().filter(|&c| !stand_ins.contains(c))).unwrap();
+    if best == stand_ins {
+        (text, Some(stand_ins))
+    } else {
+        (stand_ins.move_to(&text, best), Some(best))
+    }
+}
+
+/// The error for a string that has no room for its lone surrogates.
+pub fn no_stand_ins_error(vm: &VirtualMachine) -> PyObjectRef {
+    vm.new_value_error(
+        "string uses every block of supplementary code points, leaving none for its lone surrogates"
+            .to_string(),
+    )
+}
+
+/// Builds a `str` out of characters and lone surrogates.
+#[derive(Default)]
+pub struct Builder {
+    text: String,
+    /// Where the stand-ins for surrogates start in `text`. They are the
+    /// default ones until `finish` looks for others.
+    surrogates: Vec<usize>,
+}
+
+impl Builder {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Builder {
+            text: String::with_capacity(capacity),
+            surrogates: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.text.push(c);
+    }
+
+    pub fn push_str(&mut self, s: &str) {
+        self.text.push_str(s);
+    }
+
+    pub fn push_surrogate(&mut self, surrogate: u32) {
+        self.surrogates.push(self.text.len());
+        self.text.push(StandIns::DEFAULT.stand_in(surrogate));
+    }
+
+    /// Push a character or lone surrogate, or return `false` if
+    /// `code_point` is neither.
+    pub fn push_code_point(&mut self, code_point: u32) -> bool {
+        if is_surrogate(code_point) {
+            self.push_surrogate(code_point);
+        } else if let Some(c) = char::from_u32(code_point) {
+            self.push(c);
+        } else {
+            return false;
+        }
+        true
+    }
+
+    /// The string built, or `None` if it has no room for its surrogates.
+    pub fn finish(self) -> Option<PyString> {
+        let default = StandIns::DEFAULT;
+        if self.surrogates.is_empty() {
+            return Some(PyString::from(self.text));
+        }
+        if self.text.chars().filter(|&c| default.contains(c)).count() == self.surrogates.len() {
+            return Some(PyString::with_stand_ins(self.text, Some(default)));
+        }
+        // Some of the default stand-ins are in the string as themselves.
+        let surrogates = &self.surrogates;
+        let is_surrogate_at = |offset| surrogates.binary_search(&offset).is_ok();
+        let stand_ins = StandIns::avoiding(
+            self.text
+                .char_indices()
+                .filter(|&(offset, _)| !is_surrogate_at(offset))
+                .map(|(_, c)| c),
+        )?;
+        let text = self
+            .text
+            .char_indices()
+            .map(|(offset, c)| match default.surrogate(c) {
+                Some(surrogate) if is_surrogate_at(offset) => stand_ins.stand_in(surrogate),
+                _ => c,
+            })
+            .collect();
+        Some(PyString::with_stand_ins(text, Some(stand_ins)))
+    }
+}
+/// What to do with what can't be encoded or decoded, named by the `errors`
+/// argument of `str.encode` and `bytes.decode`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ErrorHandler {
+    Strict,
+    Ignore,
+    Replace,
+    SurrogateEscape,
+    SurrogatePass,
+    Unknown,
+}
+
+impl ErrorHandler {
+    pub fn from_name(name: &str) -> ErrorHandler {
+        match name {
+            "strict" => ErrorHandler::Strict,
+            "ignore" => ErrorHandler::Ignore,
+            "replace" => ErrorHandler::Replace,
+            "surrogateescape" => ErrorHandler::SurrogateEscape,
+            "surrogatepass" => ErrorHandler::SurrogatePass,
+            _ => ErrorHandler::Unknown,
+        }
+    }
+}
+
+/// The codecs `str.encode` and `bytes.decode` know.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Encoding {
+    Utf8,
+    Latin1,
+    Ascii,
+}
+
+impl Encoding {
+    /// The codec called `name`, spelled as `normalize_encoding` leaves it.
+    pub fn from_normalized_name(name: &str) -> Option<Encoding> {
+        match name {
+            "utf_8" | "utf8" | "u8" | "utf" => Some(Encoding::Utf8),
+            "latin_1" | "latin1" | "latin" | "l1" | "iso_8859_1" | "iso8859_1" | "8859"
+            | "cp819" => Some(Encoding::Latin1),
+            "ascii" | "us_ascii" | "646" => Some(Encoding::Ascii),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "utf-8",
+            Encoding::Latin1 => "latin-1",
+            Encoding::Ascii => "ascii",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum CodecError {
+    /// The code points from `start` to `end` can't be encoded.
+    Encode {
+        encoding: &'static str,
+        start: usize,
+        end: usize,
+        first: u32,
+        reason: &'static str,
+    },
+    /// The bytes from `start` to `end` can't be decoded.
+    Decode {
+        encoding: &'static str,
+        start: usize,
+        end: usize,
+        first: u8,
+        reason: &'static str,
+    },
+    /// The error handler was needed but isn't one we know.
+    UnknownHandler,
+    /// The decoded string has no room for its lone surrogates.
+    NoStandIns,
+}
+
+impl CodecError {
+    pub fn into_exception(self, errors: &str, vm: &VirtualMachine) -> PyObjectRef {
+        let exc_type = match self {
+            CodecError::Encode { .. } => vm.ctx.exceptions.unicode_encode_error.clone(),
+            CodecError::Decode { .. } => vm.ctx.exceptions.unicode_decode_error.clone(),
+            CodecError::UnknownHandler => {
+                return vm.new_exception(
+                    vm.ctx.exceptions.lookup_error.clone(),
+                    format!("unknown error handler name '{}'", errors),
+                );
+            }
+            CodecError::NoStandIns => return no_stand_ins_error(vm),
+        };
+        vm.new_exception(exc_type, self.to_string())
+    }
+}
+
+impl fmt::Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            CodecError::Encode {
+                encoding,
+                start,
+                end,
+                first,
+                reason,
+            } => {
+                if end - start == 1 {
+                    write!(
+                        f,
+                        "'{}' codec can't encode character '{}' in position {}: {}",
+                        encoding,
+                        escape_code_point(first),
+                        start,
+                        reason
+                    )
+                } else {
+                    write!(
+                        f,
+                        "'{}' codec can't encode characters in position {}-{}: {}",
+                        encoding,
+                        start,
+                        end - 1,
+                        reason
+                    )
+                }
+            }
+            CodecError::Decode {
+                encoding,
+                start,
+                end,
+                first,
+                reason,
+            } => {
+                if end - start == 1 {
+                    write!(
+                        f,
+                        "'{}' codec can't decode byte 0x{:02x} in position {}: {}",
+                        encoding, first, start, reason
+                    )
+                } else {
+                    write!(
+                        f,
+                        "'{}' codec can't decode bytes in position {}-{}: {}",
+                        encoding,
+                        start,
+                        end - 1,
+                        reason
+                    )
+                }
+            }
+            CodecError::UnknownHandler => write!(f, "unknown error handler"),
+            CodecError::NoStandIns => write!(f, "no room for lone surrogates"),
+        }
+    }
+}
+
+/// The escape `repr` would use for `code_point`.
+fn escape_code_point(code_point: u32) -> String {
+    if code_point <= 0xFF {
+        format!("\\x{:02x}", code_point)
+    } else if code_point <= 0xFFFF {
+        format!("\\u{:04x}", code_point)
+    } else {
+        format!("\\U{:08x}", code_point)
+    }
+}
+
+/// Encode `s` with `encoding`, handling what it can't encode with `errors`.
+pub fn encode(
+    s: &PyString,
+    encoding: Encoding,
+    errors: ErrorHandler,
+) -> Result<Vec<u8>, CodecError> {
+    let (limit, reason) = match encoding {
+        Encoding::Utf8 => return encode_utf8(s, errors),
+        Encoding::Latin1 => (0x100, "ordinal not in range(256)"),
+        Encoding::Ascii => (0x80, "ordinal not in range(128)"),
+    };
+    let is_escaped = |code_point| {
+        errors == ErrorHandler::SurrogateEscape && (0xDC80..=0xDCFF).contains(&code_point)
+    };
+    let mut bytes = Vec::with_capacity(s.as_str().len());
+    let mut code_points = s.code_points().enumerate().peekable();
+    while let Some((position, code_point)) = code_points.next() {
+        if code_point < limit {
+            bytes.push(code_point as u8);
+            continue;
+        }
+        match errors {
+            ErrorHandler::Ignore => {}
+            ErrorHandler::Replace => bytes.push(b'?'),
+            _ if is_escaped(code_point) => bytes.push((code_point - ESCAPE_BASE) as u8),
+            ErrorHandler::Unknown => return Err(CodecError::UnknownHandler),
+            _ => {
+                let mut end = position + 1;
+                while let Some(&(_, code_point)) = code_points.peek() {
+                    if code_point < limit || is_escaped(code_point) {
+                        break;
+                    }
+                    code_points.next();
+                    end += 1;
+                }
+                return Err(CodecError::Encode {
+                    encoding: encoding.name(),
+                    start: position,
+                    end,
+                    first: code_point,
+                    reason,
+                });
+            }
+        }
+    }
+    Ok(bytes)
+}
+
+/// Decode `bytes` with `encoding`, handling what it can't decode with
+/// `errors`.
+pub fn decode(
+    bytes: &[u8],
+    encoding: Encoding,
+    errors: ErrorHandler,
+) -> Result<PyString, CodecError> {
+    match encoding {
+        Encoding::Utf8 => decode_utf8(bytes, errors),
+        Encoding::Latin1 => Ok(PyString::from(
+            bytes.iter().map(|&b| char::from(b)).collect::<String>(),
+        )),
+        Encoding::Ascii => {
+            let mut decoded = Builder::with_capacity(bytes.len());
+            for (position, &b) in bytes.iter().enumerate() {
+                if b < 0x80 {
+                    decoded.push(char::from(b));
+                    continue;
+                }
+                match errors {
+                    ErrorHandler::Ignore => {}
+                    ErrorHandler::Replace => decoded.push(char::REPLACEMENT_CHARACTER),
+                    ErrorHandler::SurrogateEscape => {
+                        decoded.push_surrogate(ESCAPE_BASE + u32::from(b))
+                    }
+                    ErrorHandler::Unknown => return Err(CodecError::UnknownHandler),
+                    ErrorHandler::Strict | ErrorHandler::SurrogatePass => {
+                        return Err(CodecError::Decode {
+                            encoding: encoding.name(),
+                            start: position,
+                            end: position + 1,
+                            first: b,
+                            reason: "ordinal not in range(128)",
+                        });
+                    }
+                }
+            }
+            decoded.finish().ok_or(CodecError::NoStandIns)
+        }
+    }
+}
+
+/// Encode `s` as UTF-8, handling the lone surrogates in it with `errors`.
+pub fn encode_utf8(s: &PyString, errors: ErrorHandler) -> Result<Vec<u8>, CodecError> {
+    let stand_ins = match s.stand_ins() {
+        Some(stand_ins) => stand_ins,
+        None => return Ok(s.as_str().as_bytes().to_vec()),
+    };
+    let mut bytes = Vec::with_capacity(s.as_str().len());
+    let mut chars = s.as_str().chars().enumerate().peekable();
+    while let Some((position, c)) = chars.next() {
+        let surrogate = match stand_ins.surrogate(c) {
+            Some(surrogate) => surrogate,
+            None => {
+                let mut buf = [0; 4];
+                bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                continue;
+            }
+        };
+        match errors {
+            ErrorHandler::Ignore => {}
+            ErrorHandler::Replace => bytes.push(b'?'),
+            ErrorHandler::SurrogateEscape if (0xDC80..=0xDCFF).contains(&surrogate) => {
+                bytes.push((surrogate - ESCAPE_BASE) as u8)
+            }
+            ErrorHandler::SurrogatePass => {
+                bytes.push(0xE0 | (surrogate >> 12) as u8);
+                bytes.push(0x80 | ((surrogate >> 6) & 0x3F) as u8);
+                bytes.push(0x80 | (surrogate & 0x3F) as u8);
+            }
+            ErrorHandler::Unknown => return Err(CodecError::UnknownHandler),
+            ErrorHandler::Strict | ErrorHandler::SurrogateEscape => {
+                let mut end = position + 1;
+                while let Some(&(_, c)) = chars.peek() {
+                    if !surrogate_is_unencodable(c, stand_ins, errors) {
+                        break;
+                    }
+                    chars.next();
+                    end += 1;
+                }
+                return Err(CodecError::Encode {
+                    encoding: Encoding::Utf8.name(),
+                    start: position,
+                    end,
+                    first: surrogate,
+                    reason: "surrogates not allowed",
+                });
+            }
+        }
+    }
+    Ok(bytes)
+}
+
+fn surrogate_is_unencodable(c: char, stand_ins: StandIns, errors: ErrorHandler) -> bool {
+    match stand_ins.surrogate(c) {
+        Some(surrogate) => {
+            errors == ErrorHandler::Strict || !(0xDC80..=0xDCFF).contains(&surrogate)
+        }
+        None => false,
+    }
+}
+
+/// Decode UTF-8 `bytes`, handling what isn't UTF-8 in them with `errors`.
+pub fn decode_utf8(bytes: &[u8], errors: ErrorHandler) -> Result<PyString, CodecError> {
+    let mut decoded = Builder::with_capacity(bytes.len());
+    let mut position = 0;
+    loop {
+        let rest = &bytes[position..];
+        let err = match str::from_utf8(rest) {
+            Ok(valid) => {
+                decoded.push_str(valid);
+                return decoded.finish().ok_or(CodecError::NoStandIns);
+            }
+            Err(err) => err,
+        };
+        let valid_up_to = err.valid_up_to();
+        decoded.push_str(str::from_utf8(&rest[..valid_up_to]).unwrap());
+        position += valid_up_to;
+        let invalid = &bytes[position..];
+        let invalid_len = err.error_len().unwrap_or(invalid.len());
+
+        match errors {
+            ErrorHandler::SurrogatePass if encoded_surrogate(invalid).is_some() => {
+                let surrogate = encoded_surrogate(invalid).unwrap();
+                decoded.push_surrogate(surrogate);
+                position += 3;
+                continue;
+            }
+            ErrorHandler::Ignore => {}
+            ErrorHandler::Replace => decoded.push(char::REPLACEMENT_CHARACTER),
+            ErrorHandler::SurrogateEscape => {
+                for &b in &invalid[..invalid_len] {
+                    decoded.push_surrogate(ESCAPE_BASE + u32::from(b));
+                }
+            }
+            ErrorHandler::Unknown => return Err(CodecError::UnknownHandler),
+            ErrorHandler::Strict | ErrorHandler::SurrogatePass => {
+                let reason = if err.error_len().is_none() {
+                    "unexpected end of data"
+                } else if is_start_byte(invalid[0]) {
+                    "invalid continuation byte"
+                } else {
+                    "invalid start byte"
+                };
+                return Err(CodecError::Decode {
+                    encoding: Encoding::Utf8.name(),
+                    start: position,
+                    end: position + invalid_len,
+                    first: invalid[0],
+                    reason,
+                });
+            }
+        }
+        position += invalid_len;
+    }
+}
+
+/// Whether `b` can start a sequence of more than one byte.
+fn is_start_byte(b: u8) -> bool {
+    (0xC2..=0xF4).contains(&b)
+}
+
+/// The surrogate `bytes` start with, encoded as if it were a character.
+fn encoded_surrogate(bytes: &[u8]) -> Option<u32> {
+    if bytes.len() >= 3
+        && bytes[0] == 0xED
+        && (0xA0..=0xBF).contains(&bytes[1])
+        && (0x80..=0xBF).contains(&bytes[2])
+    {
+        Some(0xD000 | (u32::from(bytes[1] & 0x3F) << 6) | u32::from(bytes[2] & 0x3F))
+    } else {
+        None
+    }
+}
+
+/// The name of a file or environment variable as the OS sees it, with the
+/// lone surrogates in `name` turned back into the bytes they escape, like
+/// `os.fsencode`.
+#[cfg(unix)]
+pub fn fsencode(name: &PyString, vm: &VirtualMachine) -> PyResult<OsString> {
+    use std::os::unix::ffi::OsStringExt;
+    let bytes = encode_utf8(name, ErrorHandler::SurrogateEscape)
+        .map_err(|err| err.into_exception("surrogateescape", vm))?;
+    Ok(OsString::from_vec(bytes))
+}
+
+#[cfg(not(unix))]
+pub fn fsencode(name: &PyString, vm: &VirtualMachine) -> PyResult<OsString> {
+    encode_utf8(name, ErrorHandler::Strict).map_err(|err| err.into_exception("strict", vm))?;
+    Ok(OsString::from(name.as_str()))
+}
+
+/// A name from the OS as a `str`, with the bytes that aren't UTF-8 escaped as
+/// lone surrogates, like `os.fsdecode`.
+#[cfg(unix)]
+pub fn fsdecode(name: &OsStr, vm: &VirtualMachine) -> PyResult<PyString> {
+    use std::os::unix::ffi::OsStrExt;
+    decode_utf8(name.as_bytes(), ErrorHandler::SurrogateEscape)
+        .map_err(|err| err.into_exception("surrogateescape", vm))
+}
+
+#[cfg(not(unix))]
+pub fn fsdecode(name: &OsStr, _vm: &VirtualMachine) -> PyResult<PyString> {
+    Ok(PyString::from(name.to_string_lossy().into_owned()))
+}
@@ -1,12 +1,12 @@
-use std::rc::Rc;
 use std::{env, mem};
 
 use crate::frame::FrameRef;
 use crate::function::{OptionalArg, PyFuncArgs};
 use crate::obj::objstr::PyStringRef;
 use crate::pyobject::{
-    IntoPyObject, ItemProtocol, PyClassImpl, PyContext, PyObjectRef, PyResult, TypeProtocol,
+    IntoPyObject, ItemProtocol, PyClassImpl, PyObjectRef, PyResult, TypeProtocol,
 };
+use crate::surrogates;
 use crate::version;
 use crate::vm::{PySettings, VirtualMachine};
 
@@ -14,17 +14,19 @@ use crate::vm::{PySettings, VirtualMachine};
  * The magic sys module.
  */
 
-fn argv(ctx: &PyContext) -> PyObjectRef {
-    let mut argv: Vec<PyObjectRef> = env::args().map(|x| ctx.new_str(x)).collect();
+fn argv(vm: &VirtualMachine) -> PyResult {
+    let mut argv = env::args_os()
+        .map(|arg| surrogates::fsdecode(&arg, vm)?.into_pyobject(vm))
+        .collect::<PyResult<Vec<_>>>()?;
     argv.remove(0);
-    ctx.new_list(argv)
+    Ok(vm.ctx.new_list(argv))
 }
 
-fn executable(ctx: &PyContext) -> PyObjectRef {
-    if let Some(arg) = env::args().next() {
-        ctx.new_str(arg)
+fn executable(vm: &VirtualMachine) -> PyResult {
+    if let Some(arg) = env::args_os().next() {
+        surrogates::fsdecode(&arg, vm)?.into_pyobject(vm)
     } else {
-        ctx.none()
+        Ok(vm.get_none())
     }
 }
 
@@ -95,7 +97,7 @@ impl SysFlags {
 
 fn sys_getrefcount(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     arg_check!(vm, args, required = [(object, None)]);
-    let size = Rc::strong_count(&object);
+    let size = PyObjectRef::strong_count(&object);
     Ok(vm.ctx.new_int(size))
 }
 
@@ -295,11 +297,11 @@ settrace() -- set the global debug tracing function
 
     extend_module!(vm, module, {
       "__name__" => ctx.new_str(String::from("sys")),
-      "argv" => argv(ctx),
+      "argv" => argv(vm).unwrap(),
       "builtin_module_names" => builtin_module_names,
       "byteorder" => ctx.new_str(bytorder),
       "copyright" => ctx.new_str(copyright.to_string()),
-      "executable" => executable(ctx),
+      "executable" => executable(vm).unwrap(),
       "flags" => flags,
       "getrefcount" => ctx.new_rustfunc(sys_getrefcount),
       "getsizeof" => ctx.new_rustfunc(sys_getsizeof),
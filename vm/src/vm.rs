@@ -38,7 +38,6 @@ use crate::pyobject::{
 use crate::scope::Scope;
 use crate::stdlib;
 use crate::sysmodule;
-use num_bigint::BigInt;
 #[cfg(feature = "rustpython-compiler")]
 use rustpython_compiler::{compile, error::CompileError};
 
@@ -51,12 +50,12 @@ use rustpython_compiler::{compile, error::CompileError};
 pub struct VirtualMachine {
     pub builtins: PyObjectRef,
     pub sys_module: PyObjectRef,
-    pub stdlib_inits: RefCell<HashMap<String, stdlib::StdlibInitFunc>>,
+    pub stdlib_inits: Rc<RefCell<HashMap<String, stdlib::StdlibInitFunc>>>,
     pub ctx: PyContext,
     pub frames: RefCell<Vec<FrameRef>>,
     pub wasm_id: Option<String>,
     pub exceptions: RefCell<Vec<PyObjectRef>>,
-    pub frozen: RefCell<HashMap<String, bytecode::FrozenModule>>,
+    pub frozen: Rc<RefCell<HashMap<String, bytecode::FrozenModule>>>,
     pub import_func: RefCell<PyObjectRef>,
     pub profile_func: RefCell<PyObjectRef>,
     pub trace_func: RefCell<PyObjectRef>,
@@ -65,6 +64,7 @@ pub struct VirtualMachine {
 }
 
 /// Struct containing all kind of settings for the python vm.
+#[derive(Clone)]
 pub struct PySettings {
     /// -d command line switch
     pub debug: bool,
@@ -141,8 +141,8 @@ impl VirtualMachine {
         let builtins = ctx.new_module("builtins", ctx.new_dict());
         let sysmod = ctx.new_module("sys", ctx.new_dict());
 
-        let stdlib_inits = RefCell::new(stdlib::get_module_inits());
-        let frozen = RefCell::new(frozen::get_module_inits());
+        let stdlib_inits = Rc::new(RefCell::new(stdlib::get_module_inits()));
+        let frozen = Rc::new(RefCell::new(frozen::get_module_inits()));
         let import_func = RefCell::new(ctx.none());
         let profile_func = RefCell::new(ctx.none());
         let trace_func = RefCell::new(ctx.none());
@@ -167,6 +167,26 @@ impl VirtualMachine {
         vm
     }
 
+    /// Create the `VirtualMachine` for a new thread. It shares the modules
+    /// and types of this one, but has its own frames and exception state.
+    pub fn new_thread(&self) -> VirtualMachine {
+        VirtualMachine {
+            builtins: self.builtins.clone(),
+            sys_module: self.sys_module.clone(),
+            stdlib_inits: self.stdlib_inits.clone(),
+            ctx: self.ctx.clone(),
+            frames: RefCell::new(vec![]),
+            wasm_id: self.wasm_id.clone(),
+            exceptions: RefCell::new(vec![]),
+            frozen: self.frozen.clone(),
+            import_func: RefCell::new(self.import_func.borrow().clone()),
+            profile_func: RefCell::new(self.ctx.none()),
+            trace_func: RefCell::new(self.ctx.none()),
+            use_tracing: RefCell::new(false),
+            settings: self.settings.clone(),
+        }
+    }
+
     pub fn run_code_obj(&self, code: PyCodeRef, scope: Scope) -> PyResult {
         let frame = Frame::new(code, scope).into_ref(self);
         self.run_frame_full(frame)
@@ -239,7 +259,7 @@ impl VirtualMachine {
     }
 
     /// Create a new python int object.
-    pub fn new_int<T: Into<BigInt>>(&self, i: T) -> PyObjectRef {
+    pub fn new_int<T: Into<PyInt>>(&self, i: T) -> PyObjectRef {
         self.ctx.new_int(i)
     }
 
@@ -338,11 +358,26 @@ impl VirtualMachine {
 
     #[cfg(feature = "rustpython-compiler")]
     pub fn new_syntax_error(&self, error: &CompileError) -> PyObjectRef {
-        let syntax_error_type = self.ctx.exceptions.syntax_error.clone();
-        let syntax_error = self.new_exception(syntax_error_type, error.to_string());
-        let lineno = self.new_int(error.location.row());
-        self.set_attr(&syntax_error, "lineno", lineno).unwrap();
-        syntax_error
+        let syntax_error_type = if error.is_tab_error() {
+            self.ctx.exceptions.tab_error.clone()
+        } else if error.is_indentation_error() {
+            self.ctx.exceptions.indentation_error.clone()
+        } else {
+            self.ctx.exceptions.syntax_error.clone()
+        };
+        let optional_str = |value: &Option<String>| match value {
+            Some(value) => self.new_str(value.clone()),
+            None => self.get_none(),
+        };
+        let details = self.ctx.new_tuple(vec![
+            optional_str(&error.source_path),
+            self.new_int(error.location.row()),
+            self.new_int(error.location.column()),
+            optional_str(&error.statement),
+        ]);
+        let msg = self.new_str(error.error.to_string());
+        self.new_exception_obj(syntax_error_type, vec![msg, details])
+            .unwrap()
     }
 
     pub fn new_import_error(&self, msg: String) -> PyObjectRef {
@@ -387,7 +422,7 @@ impl VirtualMachine {
 
     pub fn to_pystr<'a, T: Into<&'a PyObjectRef>>(&'a self, obj: T) -> Result<String, PyObjectRef> {
         let py_str_obj = self.to_str(obj.into())?;
-        Ok(py_str_obj.value.clone())
+        Ok(py_str_obj.as_str().to_string())
     }
 
     pub fn to_repr(&self, obj: &PyObjectRef) -> PyResult<PyStringRef> {
@@ -431,7 +466,7 @@ impl VirtualMachine {
     pub fn isinstance(&self, obj: &PyObjectRef, cls: &PyClassRef) -> PyResult<bool> {
         // cpython first does an exact check on the type, although documentation doesn't state that
         // https://github.com/python/cpython/blob/a24107b04c1277e3c1105f98aff5bfa3a98b33a0/Objects/abstract.c#L2408
-        if Rc::ptr_eq(&obj.class().into_object(), cls.as_object()) {
+        if obj.class().is(cls) {
             Ok(true)
         } else {
             let ret = self.call_method(cls.as_object(), "__instancecheck__", vec![obj.clone()])?;
@@ -491,10 +526,14 @@ impl VirtualMachine {
             ref scope,
             ref defaults,
             ref kw_only_defaults,
+            ..
         }) = func_ref.payload()
         {
+            // Clone the defaults out so the function body may reassign them.
+            let defaults = defaults.borrow().clone();
+            let kw_only_defaults = kw_only_defaults.borrow().clone();
             self.trace_event(TraceEvent::Call)?;
-            let res = self.invoke_python_function(code, scope, defaults, kw_only_defaults, args);
+            let res = self.invoke_python_function(code, scope, &defaults, &kw_only_defaults, args);
             self.trace_event(TraceEvent::Return)?;
             res
         } else if let Some(PyMethod {
@@ -507,7 +546,7 @@ impl VirtualMachine {
             value(self, args)
         } else {
             // TODO: is it safe to just invoke __call__ otherwise?
-            vm_trace!("invoke __call__ for: {:?}", &func_ref.payload);
+            vm_trace!("invoke __call__ for: {:?}", &*func_ref.payload);
             self.call_method(&func_ref, "__call__", args)
         }
     }
@@ -869,7 +908,19 @@ impl VirtualMachine {
         mode: &compile::Mode,
         source_path: String,
     ) -> Result<PyCodeRef, CompileError> {
-        compile::compile(source, mode, source_path, self.settings.optimize)
+        self.compile_with_optimize(source, mode, source_path, self.settings.optimize)
+    }
+
+    /// Compile with an optimization level other than the one of the interpreter.
+    #[cfg(feature = "rustpython-compiler")]
+    pub fn compile_with_optimize(
+        &self,
+        source: &str,
+        mode: &compile::Mode,
+        source_path: String,
+        optimize: u8,
+    ) -> Result<PyCodeRef, CompileError> {
+        compile::compile(source, mode, source_path, optimize)
             .map(|codeobj| PyCode::new(codeobj).into_ref(self))
     }
 
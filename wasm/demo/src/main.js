@@ -117,10 +117,9 @@ term.write(getPrompt());
 function resetInput() {
     continuedInput = [];
     input = '';
-    continuing = false;
 }
 
-let continuedInput, input, continuing;
+let continuedInput, input;
 resetInput();
 
 let ps2;
@@ -131,25 +130,17 @@ term.on('data', data => {
         // CR
         term.write('\r\n');
         continuedInput.push(input);
-        if (continuing) {
-            if (input === '') {
-                continuing = false;
-            } else {
-                input = '';
-                term.write(ps2);
-                return;
-            }
+        const source = continuedInput.join('\n');
+        if (rp.inputStatus(source) === 'incomplete') {
+            ps2 = getPrompt('ps2');
+            term.write(ps2);
+            input = '';
+            return;
         }
         try {
-            terminalVM.execSingle(continuedInput.join('\n'));
+            terminalVM.execSingle(source);
         } catch (err) {
-            if (err instanceof SyntaxError && err.message.includes('EOF')) {
-                ps2 = getPrompt('ps2');
-                term.write(ps2);
-                continuing = true;
-                input = '';
-                return;
-            } else if (err instanceof WebAssembly.RuntimeError) {
+            if (err instanceof WebAssembly.RuntimeError) {
                 err = window.__RUSTPYTHON_ERROR || err;
             }
             printToConsole(err);
@@ -95,6 +95,27 @@ pub fn eval_py(source: String, options: Option<Object>) -> Result<JsValue, JsVal
     vm.exec(source)
 }
 
+#[wasm_bindgen(js_name = inputStatus)]
+/// Check whether some interactive input is ready to be run
+///
+/// ```js
+/// inputStatus(code);
+/// ```
+///
+/// `code`: `string`: The lines entered so far
+///
+/// Returns `"complete"`, `"incomplete"` if more lines are needed, or
+/// `"invalid"` if running it would give a syntax error.
+pub fn input_status(source: &str) -> String {
+    use rustpython_parser::parser::{classify_input, InputStatus};
+    match classify_input(source) {
+        InputStatus::Complete => "complete",
+        InputStatus::Incomplete => "incomplete",
+        InputStatus::Invalid(_) => "invalid",
+    }
+    .to_string()
+}
+
 #[wasm_bindgen(typescript_custom_section)]
 const TYPESCRIPT_DEFS: &'static str = r#"
 */
@@ -104,4 +125,6 @@ export interface PyEvalOptions {
 }
 
 export function pyEval(code: string, options?: PyEvalOptions): any;
+
+export function inputStatus(code: string): 'complete' | 'incomplete' | 'invalid';
 "#;
@@ -8,7 +8,7 @@ use wasm_bindgen::prelude::*;
 use rustpython_compiler::compile;
 use rustpython_vm::function::PyFuncArgs;
 use rustpython_vm::import;
-use rustpython_vm::pyobject::{PyObject, PyObjectPayload, PyObjectRef, PyResult, PyValue};
+use rustpython_vm::pyobject::{PyObjectRef, PyObjectWeak, PyResult, PyValue};
 use rustpython_vm::scope::{NameProtocol, Scope};
 use rustpython_vm::VirtualMachine;
 
@@ -168,12 +168,9 @@ impl WASMVirtualMachine {
         STORED_VMS.with(|cell| cell.borrow().contains_key(&self.id))
     }
 
-    pub(crate) fn push_held_rc(
-        &self,
-        obj: PyObjectRef,
-    ) -> Result<Weak<PyObject<dyn PyObjectPayload>>, JsValue> {
+    pub(crate) fn push_held_rc(&self, obj: PyObjectRef) -> Result<PyObjectWeak, JsValue> {
         self.with(|stored_vm| {
-            let weak = Rc::downgrade(&obj);
+            let weak = PyObjectRef::downgrade(&obj);
             stored_vm.held_objects.borrow_mut().push(obj);
             weak
         })